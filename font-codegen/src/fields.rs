@@ -286,6 +286,9 @@ fn traversal_arm_for_field(
     let name_str = &fld.name.to_string();
     let name = &fld.name;
     let maybe_unwrap = fld.attrs.since_version.is_some().then(|| quote!(.unwrap()));
+    // the getter for a versioned nullable offset returns `None` for both a
+    // missing field and a null offset, and `FieldType` handles the latter
+    let maybe_unwrap_target = (!fld.is_nullable()).then(|| maybe_unwrap.clone()).flatten();
     if let Some(traverse_with) = &fld.attrs.traverse_with {
         let traverse_fn = &traverse_with.attr;
         if traverse_fn == "skip" {
@@ -307,7 +310,7 @@ fn traversal_arm_for_field(
                     #name_str,
                     traversal::FieldType::offset_to_array_of_records(
                         self.#name()#maybe_unwrap,
-                        self.#getter(#pass_data)#maybe_unwrap,
+                        self.#getter(#pass_data)#maybe_unwrap_target,
                         stringify!(#typ),
                         #offset_data,
                     )
@@ -319,7 +322,7 @@ fn traversal_arm_for_field(
                 OffsetTarget::Array(_) => quote!(offset_to_array_of_scalars),
             };
             let getter = fld.offset_getter_name();
-            quote!(Field::new(#name_str, FieldType::#constructor_name(self.#name()#maybe_unwrap, self.#getter(#pass_data)#maybe_unwrap)))
+            quote!(Field::new(#name_str, FieldType::#constructor_name(self.#name()#maybe_unwrap, self.#getter(#pass_data)#maybe_unwrap_target)))
         }
         FieldType::Scalar { .. } => quote!(Field::new(#name_str, self.#name()#maybe_unwrap)),
 
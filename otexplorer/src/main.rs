@@ -107,18 +107,29 @@ fn get_some_table<'a>(
         tables::gsub::Gsub::TAG => font.gsub().map(|x| Box::new(x) as _),
         tables::cmap::Cmap::TAG => font.cmap().map(|x| Box::new(x) as _),
         tables::fvar::Fvar::TAG => font.fvar().map(|x| Box::new(x) as _),
+        tables::gasp::Gasp::TAG => font.gasp().map(|x| Box::new(x) as _),
         tables::avar::Avar::TAG => font.avar().map(|x| Box::new(x) as _),
         tables::gdef::Gdef::TAG => font.gdef().map(|x| Box::new(x) as _),
         tables::glyf::Glyf::TAG => font.glyf().map(|x| Box::new(x) as _),
+        tables::hdmx::Hdmx::TAG => font.hdmx().map(|x| Box::new(x) as _),
         tables::head::Head::TAG => font.head().map(|x| Box::new(x) as _),
         tables::hhea::Hhea::TAG => font.hhea().map(|x| Box::new(x) as _),
         tables::hmtx::Hmtx::TAG => font.hmtx().map(|x| Box::new(x) as _),
         tables::loca::Loca::TAG => font.loca(None).map(|x| Box::new(x) as _),
+        tables::ltsh::Ltsh::TAG => font.ltsh().map(|x| Box::new(x) as _),
+        tables::ltag::Ltag::TAG => font.ltag().map(|x| Box::new(x) as _),
+        tables::meta::Meta::TAG => font.meta().map(|x| Box::new(x) as _),
         tables::maxp::Maxp::TAG => font.maxp().map(|x| Box::new(x) as _),
         tables::name::Name::TAG => font.name().map(|x| Box::new(x) as _),
         tables::post::Post::TAG => font.post().map(|x| Box::new(x) as _),
         tables::colr::Colr::TAG => font.colr().map(|x| Box::new(x) as _),
+        tables::cvar::Cvar::TAG => font.cvar().map(|x| Box::new(x) as _),
+        tables::cvt::Cvt::TAG => font.cvt().map(|x| Box::new(x) as _),
+        tables::fpgm::Fpgm::TAG => font.fpgm().map(|x| Box::new(x) as _),
+        tables::prep::Prep::TAG => font.prep().map(|x| Box::new(x) as _),
         tables::stat::Stat::TAG => font.stat().map(|x| Box::new(x) as _),
+        tables::varc::Varc::TAG => font.varc().map(|x| Box::new(x) as _),
+        tables::vdmx::Vdmx::TAG => font.vdmx().map(|x| Box::new(x) as _),
         tables::vhea::Vhea::TAG => font.vhea().map(|x| Box::new(x) as _),
         tables::vmtx::Vmtx::TAG => font.vmtx().map(|x| Box::new(x) as _),
         _ => Err(ReadError::TableIsMissing(tag)),
@@ -121,10 +121,7 @@ impl<'a> SomeTable<'a> for Base<'a> {
             )),
             3usize if version.compatible((1, 1)) => Some(Field::new(
                 "item_var_store_offset",
-                FieldType::offset(
-                    self.item_var_store_offset().unwrap(),
-                    self.item_var_store().unwrap(),
-                ),
+                FieldType::offset(self.item_var_store_offset().unwrap(), self.item_var_store()),
             )),
             _ => None,
         }
@@ -872,7 +869,7 @@ impl FeatMinMaxRecord {
     ///
     /// The `data` argument should be retrieved from the parent table
     /// By calling its `offset_data` method.
-    pub fn min_coord<'a>(&self, data: FontData<'a>) -> Option<Result<MinMax<'a>, ReadError>> {
+    pub fn min_coord<'a>(&self, data: FontData<'a>) -> Option<Result<BaseCoord<'a>, ReadError>> {
         self.min_coord_offset().resolve(data)
     }
 
@@ -887,7 +884,7 @@ impl FeatMinMaxRecord {
     ///
     /// The `data` argument should be retrieved from the parent table
     /// By calling its `offset_data` method.
-    pub fn max_coord<'a>(&self, data: FontData<'a>) -> Option<Result<MinMax<'a>, ReadError>> {
+    pub fn max_coord<'a>(&self, data: FontData<'a>) -> Option<Result<BaseCoord<'a>, ReadError>> {
         self.max_coord_offset().resolve(data)
     }
 }
@@ -254,32 +254,26 @@ impl<'a> SomeTable<'a> for Colr<'a> {
                 "base_glyph_list_offset",
                 FieldType::offset(
                     self.base_glyph_list_offset().unwrap(),
-                    self.base_glyph_list().unwrap(),
+                    self.base_glyph_list(),
                 ),
             )),
             6usize if version.compatible(1) => Some(Field::new(
                 "layer_list_offset",
-                FieldType::offset(
-                    self.layer_list_offset().unwrap(),
-                    self.layer_list().unwrap(),
-                ),
+                FieldType::offset(self.layer_list_offset().unwrap(), self.layer_list()),
             )),
             7usize if version.compatible(1) => Some(Field::new(
                 "clip_list_offset",
-                FieldType::offset(self.clip_list_offset().unwrap(), self.clip_list().unwrap()),
+                FieldType::offset(self.clip_list_offset().unwrap(), self.clip_list()),
             )),
             8usize if version.compatible(1) => Some(Field::new(
                 "var_index_map_offset",
-                FieldType::offset(
-                    self.var_index_map_offset().unwrap(),
-                    self.var_index_map().unwrap(),
-                ),
+                FieldType::offset(self.var_index_map_offset().unwrap(), self.var_index_map()),
             )),
             9usize if version.compatible(1) => Some(Field::new(
                 "item_variation_store_offset",
                 FieldType::offset(
                     self.item_variation_store_offset().unwrap(),
-                    self.item_variation_store().unwrap(),
+                    self.item_variation_store(),
                 ),
             )),
             _ => None,
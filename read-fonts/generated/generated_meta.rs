@@ -0,0 +1,174 @@
+// THIS FILE IS AUTOGENERATED.
+// Any changes to this file will be overwritten.
+// For more information about how codegen works, see font-codegen/README.md
+
+#[allow(unused_imports)]
+use crate::codegen_prelude::*;
+
+/// The [meta (Metadata)](https://learn.microsoft.com/en-us/typography/opentype/spec/meta) table
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct MetaMarker {
+    data_maps_byte_len: usize,
+}
+
+impl MetaMarker {
+    fn version_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u32::RAW_BYTE_LEN
+    }
+    fn flags_byte_range(&self) -> Range<usize> {
+        let start = self.version_byte_range().end;
+        start..start + u32::RAW_BYTE_LEN
+    }
+    fn reserved_byte_range(&self) -> Range<usize> {
+        let start = self.flags_byte_range().end;
+        start..start + u32::RAW_BYTE_LEN
+    }
+    fn data_maps_count_byte_range(&self) -> Range<usize> {
+        let start = self.reserved_byte_range().end;
+        start..start + u32::RAW_BYTE_LEN
+    }
+    fn data_maps_byte_range(&self) -> Range<usize> {
+        let start = self.data_maps_count_byte_range().end;
+        start..start + self.data_maps_byte_len
+    }
+}
+
+impl TopLevelTable for Meta<'_> {
+    /// `meta`
+    const TAG: Tag = Tag::new(b"meta");
+}
+
+impl<'a> FontRead<'a> for Meta<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u32>();
+        cursor.advance::<u32>();
+        cursor.advance::<u32>();
+        let data_maps_count: u32 = cursor.read()?;
+        let data_maps_byte_len = data_maps_count as usize * DataMapRecord::RAW_BYTE_LEN;
+        cursor.advance_by(data_maps_byte_len);
+        cursor.finish(MetaMarker { data_maps_byte_len })
+    }
+}
+
+/// The [meta (Metadata)](https://learn.microsoft.com/en-us/typography/opentype/spec/meta) table
+pub type Meta<'a> = TableRef<'a, MetaMarker>;
+
+impl<'a> Meta<'a> {
+    /// Version number of the metadata table — set to 1.
+    pub fn version(&self) -> u32 {
+        let range = self.shape.version_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Flags — currently unused; set to 0.
+    pub fn flags(&self) -> u32 {
+        let range = self.shape.flags_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// The number of data maps in the table.
+    pub fn data_maps_count(&self) -> u32 {
+        let range = self.shape.data_maps_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Array of data map records.
+    pub fn data_maps(&self) -> &'a [DataMapRecord] {
+        let range = self.shape.data_maps_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for Meta<'a> {
+    fn type_name(&self) -> &str {
+        "Meta"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("version", self.version())),
+            1usize => Some(Field::new("flags", self.flags())),
+            2usize => Some(Field::new("data_maps_count", self.data_maps_count())),
+            3usize => Some(Field::new(
+                "data_maps",
+                traversal::FieldType::array_of_records(
+                    stringify!(DataMapRecord),
+                    self.data_maps(),
+                    self.offset_data(),
+                ),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for Meta<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// A [DataMapRecord](https://learn.microsoft.com/en-us/typography/opentype/spec/meta#table-formats)
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(C)]
+#[repr(packed)]
+pub struct DataMapRecord {
+    /// A tag indicating the type of metadata.
+    pub tag: BigEndian<Tag>,
+    /// Offset in bytes from the beginning of the metadata table to the
+    /// data for this tag.
+    pub data_offset: BigEndian<u32>,
+    /// Length of the data, in bytes. The data is not required to be
+    /// padded to any byte boundary.
+    pub data_length: BigEndian<u32>,
+}
+
+impl DataMapRecord {
+    /// A tag indicating the type of metadata.
+    pub fn tag(&self) -> Tag {
+        self.tag.get()
+    }
+
+    /// Offset in bytes from the beginning of the metadata table to the
+    /// data for this tag.
+    pub fn data_offset(&self) -> u32 {
+        self.data_offset.get()
+    }
+
+    /// Length of the data, in bytes. The data is not required to be
+    /// padded to any byte boundary.
+    pub fn data_length(&self) -> u32 {
+        self.data_length.get()
+    }
+}
+
+impl FixedSize for DataMapRecord {
+    const RAW_BYTE_LEN: usize = Tag::RAW_BYTE_LEN + u32::RAW_BYTE_LEN + u32::RAW_BYTE_LEN;
+}
+
+impl sealed::Sealed for DataMapRecord {}
+
+/// SAFETY: see the [`FromBytes`] trait documentation.
+unsafe impl FromBytes for DataMapRecord {
+    fn this_trait_should_only_be_implemented_in_generated_code() {}
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeRecord<'a> for DataMapRecord {
+    fn traverse(self, data: FontData<'a>) -> RecordResolver<'a> {
+        RecordResolver {
+            name: "DataMapRecord",
+            get_field: Box::new(move |idx, _data| match idx {
+                0usize => Some(Field::new("tag", self.tag())),
+                1usize => Some(Field::new("data_offset", self.data_offset())),
+                2usize => Some(Field::new("data_length", self.data_length())),
+                _ => None,
+            }),
+            data,
+        }
+    }
+}
@@ -0,0 +1,1514 @@
+// THIS FILE IS AUTOGENERATED.
+// Any changes to this file will be overwritten.
+// For more information about how codegen works, see font-codegen/README.md
+
+#[allow(unused_imports)]
+use crate::codegen_prelude::*;
+
+/// The [VARC (Variable Composites / Components)](https://github.com/harfbuzz/boring-expansion-spec/blob/main/VARC.md)
+/// table.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct VarcMarker {}
+
+impl VarcMarker {
+    fn version_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + MajorMinor::RAW_BYTE_LEN
+    }
+    fn coverage_offset_byte_range(&self) -> Range<usize> {
+        let start = self.version_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+    fn multi_var_store_offset_byte_range(&self) -> Range<usize> {
+        let start = self.coverage_offset_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+    fn condition_list_offset_byte_range(&self) -> Range<usize> {
+        let start = self.multi_var_store_offset_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+    fn axis_indices_list_offset_byte_range(&self) -> Range<usize> {
+        let start = self.condition_list_offset_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+    fn var_composite_glyphs_offset_byte_range(&self) -> Range<usize> {
+        let start = self.axis_indices_list_offset_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+}
+
+impl TopLevelTable for Varc<'_> {
+    /// `VARC`
+    const TAG: Tag = Tag::new(b"VARC");
+}
+
+impl<'a> FontRead<'a> for Varc<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<MajorMinor>();
+        cursor.advance::<Offset32>();
+        cursor.advance::<Offset32>();
+        cursor.advance::<Offset32>();
+        cursor.advance::<Offset32>();
+        cursor.advance::<Offset32>();
+        cursor.finish(VarcMarker {})
+    }
+}
+
+/// The [VARC (Variable Composites / Components)](https://github.com/harfbuzz/boring-expansion-spec/blob/main/VARC.md)
+/// table.
+pub type Varc<'a> = TableRef<'a, VarcMarker>;
+
+impl<'a> Varc<'a> {
+    /// Major/minor version number. Set to 1.0.
+    pub fn version(&self) -> MajorMinor {
+        let range = self.shape.version_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offset to the coverage table of glyphs with variable composite
+    /// descriptions.
+    pub fn coverage_offset(&self) -> Offset32 {
+        let range = self.shape.coverage_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`coverage_offset`][Self::coverage_offset].
+    pub fn coverage(&self) -> Result<CoverageTable<'a>, ReadError> {
+        let data = self.data;
+        self.coverage_offset().resolve(data)
+    }
+
+    /// Offset to the multi-item variation store.
+    pub fn multi_var_store_offset(&self) -> Nullable<Offset32> {
+        let range = self.shape.multi_var_store_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`multi_var_store_offset`][Self::multi_var_store_offset].
+    pub fn multi_var_store(&self) -> Option<Result<MultiItemVariationStore<'a>, ReadError>> {
+        let data = self.data;
+        self.multi_var_store_offset().resolve(data)
+    }
+
+    /// Offset to the list of conditions referenced by components.
+    pub fn condition_list_offset(&self) -> Nullable<Offset32> {
+        let range = self.shape.condition_list_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`condition_list_offset`][Self::condition_list_offset].
+    pub fn condition_list(&self) -> Option<Result<ConditionList<'a>, ReadError>> {
+        let data = self.data;
+        self.condition_list_offset().resolve(data)
+    }
+
+    /// Offset to a CFF2 style INDEX of packed axis index lists.
+    pub fn axis_indices_list_offset(&self) -> Nullable<Offset32> {
+        let range = self.shape.axis_indices_list_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`axis_indices_list_offset`][Self::axis_indices_list_offset].
+    pub fn axis_indices_list(&self) -> Option<Result<Index2<'a>, ReadError>> {
+        let data = self.data;
+        self.axis_indices_list_offset().resolve(data)
+    }
+
+    /// Offset to a CFF2 style INDEX of variable composite glyph records,
+    /// ordered by coverage index.
+    pub fn var_composite_glyphs_offset(&self) -> Offset32 {
+        let range = self.shape.var_composite_glyphs_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`var_composite_glyphs_offset`][Self::var_composite_glyphs_offset].
+    pub fn var_composite_glyphs(&self) -> Result<Index2<'a>, ReadError> {
+        let data = self.data;
+        self.var_composite_glyphs_offset().resolve(data)
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for Varc<'a> {
+    fn type_name(&self) -> &str {
+        "Varc"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("version", self.version())),
+            1usize => Some(Field::new(
+                "coverage_offset",
+                FieldType::offset(self.coverage_offset(), self.coverage()),
+            )),
+            2usize => Some(Field::new(
+                "multi_var_store_offset",
+                FieldType::offset(self.multi_var_store_offset(), self.multi_var_store()),
+            )),
+            3usize => Some(Field::new(
+                "condition_list_offset",
+                FieldType::offset(self.condition_list_offset(), self.condition_list()),
+            )),
+            4usize => Some(Field::new(
+                "axis_indices_list_offset",
+                FieldType::offset(self.axis_indices_list_offset(), self.axis_indices_list()),
+            )),
+            5usize => Some(Field::new(
+                "var_composite_glyphs_offset",
+                FieldType::offset(
+                    self.var_composite_glyphs_offset(),
+                    self.var_composite_glyphs(),
+                ),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for Varc<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+impl Format<u16> for MultiItemVariationStoreMarker {
+    const FORMAT: u16 = 1;
+}
+
+/// A variation store where each item references a vector of deltas.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct MultiItemVariationStoreMarker {
+    variation_data_offsets_byte_len: usize,
+}
+
+impl MultiItemVariationStoreMarker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn region_list_offset_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + Offset32::RAW_BYTE_LEN
+    }
+    fn variation_data_count_byte_range(&self) -> Range<usize> {
+        let start = self.region_list_offset_byte_range().end;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn variation_data_offsets_byte_range(&self) -> Range<usize> {
+        let start = self.variation_data_count_byte_range().end;
+        start..start + self.variation_data_offsets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for MultiItemVariationStore<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u16>();
+        cursor.advance::<Offset32>();
+        let variation_data_count: u16 = cursor.read()?;
+        let variation_data_offsets_byte_len =
+            variation_data_count as usize * Offset32::RAW_BYTE_LEN;
+        cursor.advance_by(variation_data_offsets_byte_len);
+        cursor.finish(MultiItemVariationStoreMarker {
+            variation_data_offsets_byte_len,
+        })
+    }
+}
+
+/// A variation store where each item references a vector of deltas.
+pub type MultiItemVariationStore<'a> = TableRef<'a, MultiItemVariationStoreMarker>;
+
+impl<'a> MultiItemVariationStore<'a> {
+    /// Format. Set to 1.
+    pub fn format(&self) -> u16 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offset to the sparse variation region list.
+    pub fn region_list_offset(&self) -> Offset32 {
+        let range = self.shape.region_list_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`region_list_offset`][Self::region_list_offset].
+    pub fn region_list(&self) -> Result<SparseVariationRegionList<'a>, ReadError> {
+        let data = self.data;
+        self.region_list_offset().resolve(data)
+    }
+
+    /// Number of variation data subtables.
+    pub fn variation_data_count(&self) -> u16 {
+        let range = self.shape.variation_data_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offsets to variation data subtables.
+    pub fn variation_data_offsets(&self) -> &'a [BigEndian<Offset32>] {
+        let range = self.shape.variation_data_offsets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A dynamically resolving wrapper for [`variation_data_offsets`][Self::variation_data_offsets].
+    pub fn variation_data(&self) -> ArrayOfOffsets<'a, MultiItemVariationData<'a>, Offset32> {
+        let data = self.data;
+        let offsets = self.variation_data_offsets();
+        ArrayOfOffsets::new(offsets, data, ())
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for MultiItemVariationStore<'a> {
+    fn type_name(&self) -> &str {
+        "MultiItemVariationStore"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new(
+                "region_list_offset",
+                FieldType::offset(self.region_list_offset(), self.region_list()),
+            )),
+            2usize => Some(Field::new(
+                "variation_data_count",
+                self.variation_data_count(),
+            )),
+            3usize => Some({
+                let data = self.data;
+                Field::new(
+                    "variation_data_offsets",
+                    FieldType::array_of_offsets(
+                        better_type_name::<MultiItemVariationData>(),
+                        self.variation_data_offsets(),
+                        move |off| {
+                            let target = off.get().resolve::<MultiItemVariationData>(data);
+                            FieldType::offset(off.get(), target)
+                        },
+                    ),
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for MultiItemVariationStore<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// List of regions where each region only references the axes it uses.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct SparseVariationRegionListMarker {
+    region_offsets_byte_len: usize,
+}
+
+impl SparseVariationRegionListMarker {
+    fn region_count_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn region_offsets_byte_range(&self) -> Range<usize> {
+        let start = self.region_count_byte_range().end;
+        start..start + self.region_offsets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for SparseVariationRegionList<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        let region_count: u16 = cursor.read()?;
+        let region_offsets_byte_len = region_count as usize * Offset32::RAW_BYTE_LEN;
+        cursor.advance_by(region_offsets_byte_len);
+        cursor.finish(SparseVariationRegionListMarker {
+            region_offsets_byte_len,
+        })
+    }
+}
+
+/// List of regions where each region only references the axes it uses.
+pub type SparseVariationRegionList<'a> = TableRef<'a, SparseVariationRegionListMarker>;
+
+impl<'a> SparseVariationRegionList<'a> {
+    /// Number of regions.
+    pub fn region_count(&self) -> u16 {
+        let range = self.shape.region_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offsets to sparse variation regions.
+    pub fn region_offsets(&self) -> &'a [BigEndian<Offset32>] {
+        let range = self.shape.region_offsets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A dynamically resolving wrapper for [`region_offsets`][Self::region_offsets].
+    pub fn regions(&self) -> ArrayOfOffsets<'a, SparseVariationRegion<'a>, Offset32> {
+        let data = self.data;
+        let offsets = self.region_offsets();
+        ArrayOfOffsets::new(offsets, data, ())
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for SparseVariationRegionList<'a> {
+    fn type_name(&self) -> &str {
+        "SparseVariationRegionList"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("region_count", self.region_count())),
+            1usize => Some({
+                let data = self.data;
+                Field::new(
+                    "region_offsets",
+                    FieldType::array_of_offsets(
+                        better_type_name::<SparseVariationRegion>(),
+                        self.region_offsets(),
+                        move |off| {
+                            let target = off.get().resolve::<SparseVariationRegion>(data);
+                            FieldType::offset(off.get(), target)
+                        },
+                    ),
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for SparseVariationRegionList<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// A variation region defined over a subset of the font's axes.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct SparseVariationRegionMarker {
+    region_axes_byte_len: usize,
+}
+
+impl SparseVariationRegionMarker {
+    fn region_axis_count_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn region_axes_byte_range(&self) -> Range<usize> {
+        let start = self.region_axis_count_byte_range().end;
+        start..start + self.region_axes_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for SparseVariationRegion<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        let region_axis_count: u16 = cursor.read()?;
+        let region_axes_byte_len =
+            region_axis_count as usize * SparseRegionAxisCoordinates::RAW_BYTE_LEN;
+        cursor.advance_by(region_axes_byte_len);
+        cursor.finish(SparseVariationRegionMarker {
+            region_axes_byte_len,
+        })
+    }
+}
+
+/// A variation region defined over a subset of the font's axes.
+pub type SparseVariationRegion<'a> = TableRef<'a, SparseVariationRegionMarker>;
+
+impl<'a> SparseVariationRegion<'a> {
+    /// Number of axes referenced by this region.
+    pub fn region_axis_count(&self) -> u16 {
+        let range = self.shape.region_axis_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Coordinates for each referenced axis.
+    pub fn region_axes(&self) -> &'a [SparseRegionAxisCoordinates] {
+        let range = self.shape.region_axes_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for SparseVariationRegion<'a> {
+    fn type_name(&self) -> &str {
+        "SparseVariationRegion"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("region_axis_count", self.region_axis_count())),
+            1usize => Some(Field::new(
+                "region_axes",
+                traversal::FieldType::array_of_records(
+                    stringify!(SparseRegionAxisCoordinates),
+                    self.region_axes(),
+                    self.offset_data(),
+                ),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for SparseVariationRegion<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// The region coordinates for a single axis.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(C)]
+#[repr(packed)]
+pub struct SparseRegionAxisCoordinates {
+    /// Index of the axis in the `fvar` table.
+    pub axis_index: BigEndian<u16>,
+    /// The region start coordinate value for the axis.
+    pub start: BigEndian<F2Dot14>,
+    /// The region peak coordinate value for the axis.
+    pub peak: BigEndian<F2Dot14>,
+    /// The region end coordinate value for the axis.
+    pub end: BigEndian<F2Dot14>,
+}
+
+impl SparseRegionAxisCoordinates {
+    /// Index of the axis in the `fvar` table.
+    pub fn axis_index(&self) -> u16 {
+        self.axis_index.get()
+    }
+
+    /// The region start coordinate value for the axis.
+    pub fn start(&self) -> F2Dot14 {
+        self.start.get()
+    }
+
+    /// The region peak coordinate value for the axis.
+    pub fn peak(&self) -> F2Dot14 {
+        self.peak.get()
+    }
+
+    /// The region end coordinate value for the axis.
+    pub fn end(&self) -> F2Dot14 {
+        self.end.get()
+    }
+}
+
+impl FixedSize for SparseRegionAxisCoordinates {
+    const RAW_BYTE_LEN: usize =
+        u16::RAW_BYTE_LEN + F2Dot14::RAW_BYTE_LEN + F2Dot14::RAW_BYTE_LEN + F2Dot14::RAW_BYTE_LEN;
+}
+
+impl sealed::Sealed for SparseRegionAxisCoordinates {}
+
+/// SAFETY: see the [`FromBytes`] trait documentation.
+unsafe impl FromBytes for SparseRegionAxisCoordinates {
+    fn this_trait_should_only_be_implemented_in_generated_code() {}
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeRecord<'a> for SparseRegionAxisCoordinates {
+    fn traverse(self, data: FontData<'a>) -> RecordResolver<'a> {
+        RecordResolver {
+            name: "SparseRegionAxisCoordinates",
+            get_field: Box::new(move |idx, _data| match idx {
+                0usize => Some(Field::new("axis_index", self.axis_index())),
+                1usize => Some(Field::new("start", self.start())),
+                2usize => Some(Field::new("peak", self.peak())),
+                3usize => Some(Field::new("end", self.end())),
+                _ => None,
+            }),
+            data,
+        }
+    }
+}
+
+impl Format<u8> for MultiItemVariationDataMarker {
+    const FORMAT: u8 = 0;
+}
+
+/// A set of delta vectors sharing a list of regions.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct MultiItemVariationDataMarker {
+    region_indices_byte_len: usize,
+    raw_delta_sets_byte_len: usize,
+}
+
+impl MultiItemVariationDataMarker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u8::RAW_BYTE_LEN
+    }
+    fn region_index_count_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn region_indices_byte_range(&self) -> Range<usize> {
+        let start = self.region_index_count_byte_range().end;
+        start..start + self.region_indices_byte_len
+    }
+    fn raw_delta_sets_byte_range(&self) -> Range<usize> {
+        let start = self.region_indices_byte_range().end;
+        start..start + self.raw_delta_sets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for MultiItemVariationData<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u8>();
+        let region_index_count: u16 = cursor.read()?;
+        let region_indices_byte_len = region_index_count as usize * u16::RAW_BYTE_LEN;
+        cursor.advance_by(region_indices_byte_len);
+        let raw_delta_sets_byte_len = cursor.remaining_bytes();
+        cursor.advance_by(raw_delta_sets_byte_len);
+        cursor.finish(MultiItemVariationDataMarker {
+            region_indices_byte_len,
+            raw_delta_sets_byte_len,
+        })
+    }
+}
+
+/// A set of delta vectors sharing a list of regions.
+pub type MultiItemVariationData<'a> = TableRef<'a, MultiItemVariationDataMarker>;
+
+impl<'a> MultiItemVariationData<'a> {
+    /// Format. Set to 0.
+    pub fn format(&self) -> u8 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Number of regions referenced by this subtable.
+    pub fn region_index_count(&self) -> u16 {
+        let range = self.shape.region_index_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Indices into the region list.
+    pub fn region_indices(&self) -> &'a [BigEndian<u16>] {
+        let range = self.shape.region_indices_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A CFF2 style INDEX of packed delta vectors, one per item.
+    pub fn raw_delta_sets(&self) -> &'a [u8] {
+        let range = self.shape.raw_delta_sets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for MultiItemVariationData<'a> {
+    fn type_name(&self) -> &str {
+        "MultiItemVariationData"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new("region_index_count", self.region_index_count())),
+            2usize => Some(Field::new("region_indices", self.region_indices())),
+            3usize => Some(Field::new("raw_delta_sets", self.raw_delta_sets())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for MultiItemVariationData<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// A list of conditions referenced by index.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ConditionListMarker {
+    condition_offsets_byte_len: usize,
+}
+
+impl ConditionListMarker {
+    fn condition_count_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u32::RAW_BYTE_LEN
+    }
+    fn condition_offsets_byte_range(&self) -> Range<usize> {
+        let start = self.condition_count_byte_range().end;
+        start..start + self.condition_offsets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for ConditionList<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        let condition_count: u32 = cursor.read()?;
+        let condition_offsets_byte_len = condition_count as usize * Offset32::RAW_BYTE_LEN;
+        cursor.advance_by(condition_offsets_byte_len);
+        cursor.finish(ConditionListMarker {
+            condition_offsets_byte_len,
+        })
+    }
+}
+
+/// A list of conditions referenced by index.
+pub type ConditionList<'a> = TableRef<'a, ConditionListMarker>;
+
+impl<'a> ConditionList<'a> {
+    /// Number of conditions.
+    pub fn condition_count(&self) -> u32 {
+        let range = self.shape.condition_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offsets to condition tables, from the beginning of this table.
+    pub fn condition_offsets(&self) -> &'a [BigEndian<Offset32>] {
+        let range = self.shape.condition_offsets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A dynamically resolving wrapper for [`condition_offsets`][Self::condition_offsets].
+    pub fn conditions(&self) -> ArrayOfOffsets<'a, Condition<'a>, Offset32> {
+        let data = self.data;
+        let offsets = self.condition_offsets();
+        ArrayOfOffsets::new(offsets, data, ())
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for ConditionList<'a> {
+    fn type_name(&self) -> &str {
+        "ConditionList"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("condition_count", self.condition_count())),
+            1usize => Some({
+                let data = self.data;
+                Field::new(
+                    "condition_offsets",
+                    FieldType::array_of_offsets(
+                        better_type_name::<Condition>(),
+                        self.condition_offsets(),
+                        move |off| {
+                            let target = off.get().resolve::<Condition>(data);
+                            FieldType::offset(off.get(), target)
+                        },
+                    ),
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for ConditionList<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// A condition that is evaluated against the current variation
+/// coordinates.
+pub enum Condition<'a> {
+    Format1AxisRange(ConditionFormat1<'a>),
+    Format2VariableValue(ConditionFormat2<'a>),
+    Format3And(ConditionFormat3<'a>),
+    Format4Or(ConditionFormat4<'a>),
+    Format5Negate(ConditionFormat5<'a>),
+}
+
+impl<'a> FontRead<'a> for Condition<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let format: u16 = data.read_at(0usize)?;
+        match format {
+            ConditionFormat1Marker::FORMAT => Ok(Self::Format1AxisRange(FontRead::read(data)?)),
+            ConditionFormat2Marker::FORMAT => Ok(Self::Format2VariableValue(FontRead::read(data)?)),
+            ConditionFormat3Marker::FORMAT => Ok(Self::Format3And(FontRead::read(data)?)),
+            ConditionFormat4Marker::FORMAT => Ok(Self::Format4Or(FontRead::read(data)?)),
+            ConditionFormat5Marker::FORMAT => Ok(Self::Format5Negate(FontRead::read(data)?)),
+            other => Err(ReadError::InvalidFormat(other.into())),
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> Condition<'a> {
+    fn dyn_inner<'b>(&'b self) -> &'b dyn SomeTable<'a> {
+        match self {
+            Self::Format1AxisRange(table) => table,
+            Self::Format2VariableValue(table) => table,
+            Self::Format3And(table) => table,
+            Self::Format4Or(table) => table,
+            Self::Format5Negate(table) => table,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for Condition<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.dyn_inner().fmt(f)
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for Condition<'a> {
+    fn type_name(&self) -> &str {
+        self.dyn_inner().type_name()
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        self.dyn_inner().get_field(idx)
+    }
+}
+
+impl Format<u16> for ConditionFormat2Marker {
+    const FORMAT: u16 = 2;
+}
+
+/// Condition format 2: a variable value that is true when positive.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ConditionFormat2Marker {}
+
+impl ConditionFormat2Marker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn default_value_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + i16::RAW_BYTE_LEN
+    }
+    fn var_index_byte_range(&self) -> Range<usize> {
+        let start = self.default_value_byte_range().end;
+        start..start + u32::RAW_BYTE_LEN
+    }
+}
+
+impl<'a> FontRead<'a> for ConditionFormat2<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u16>();
+        cursor.advance::<i16>();
+        cursor.advance::<u32>();
+        cursor.finish(ConditionFormat2Marker {})
+    }
+}
+
+/// Condition format 2: a variable value that is true when positive.
+pub type ConditionFormat2<'a> = TableRef<'a, ConditionFormat2Marker>;
+
+impl<'a> ConditionFormat2<'a> {
+    /// Format, = 2
+    pub fn format(&self) -> u16 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Value at the default location.
+    pub fn default_value(&self) -> i16 {
+        let range = self.shape.default_value_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Variation index into the multi-item variation store.
+    pub fn var_index(&self) -> u32 {
+        let range = self.shape.var_index_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for ConditionFormat2<'a> {
+    fn type_name(&self) -> &str {
+        "ConditionFormat2"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new("default_value", self.default_value())),
+            2usize => Some(Field::new("var_index", self.var_index())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for ConditionFormat2<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+impl Format<u16> for ConditionFormat3Marker {
+    const FORMAT: u16 = 3;
+}
+
+/// Condition format 3: true when all subconditions are true.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ConditionFormat3Marker {
+    condition_offsets_byte_len: usize,
+}
+
+impl ConditionFormat3Marker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn condition_count_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + u8::RAW_BYTE_LEN
+    }
+    fn condition_offsets_byte_range(&self) -> Range<usize> {
+        let start = self.condition_count_byte_range().end;
+        start..start + self.condition_offsets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for ConditionFormat3<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u16>();
+        let condition_count: u8 = cursor.read()?;
+        let condition_offsets_byte_len = condition_count as usize * Offset24::RAW_BYTE_LEN;
+        cursor.advance_by(condition_offsets_byte_len);
+        cursor.finish(ConditionFormat3Marker {
+            condition_offsets_byte_len,
+        })
+    }
+}
+
+/// Condition format 3: true when all subconditions are true.
+pub type ConditionFormat3<'a> = TableRef<'a, ConditionFormat3Marker>;
+
+impl<'a> ConditionFormat3<'a> {
+    /// Format, = 3
+    pub fn format(&self) -> u16 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Number of subconditions.
+    pub fn condition_count(&self) -> u8 {
+        let range = self.shape.condition_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offsets to subconditions, from the beginning of this table.
+    pub fn condition_offsets(&self) -> &'a [BigEndian<Offset24>] {
+        let range = self.shape.condition_offsets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A dynamically resolving wrapper for [`condition_offsets`][Self::condition_offsets].
+    pub fn conditions(&self) -> ArrayOfOffsets<'a, Condition<'a>, Offset24> {
+        let data = self.data;
+        let offsets = self.condition_offsets();
+        ArrayOfOffsets::new(offsets, data, ())
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for ConditionFormat3<'a> {
+    fn type_name(&self) -> &str {
+        "ConditionFormat3"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new("condition_count", self.condition_count())),
+            2usize => Some({
+                let data = self.data;
+                Field::new(
+                    "condition_offsets",
+                    FieldType::array_of_offsets(
+                        better_type_name::<Condition>(),
+                        self.condition_offsets(),
+                        move |off| {
+                            let target = off.get().resolve::<Condition>(data);
+                            FieldType::offset(off.get(), target)
+                        },
+                    ),
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for ConditionFormat3<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+impl Format<u16> for ConditionFormat4Marker {
+    const FORMAT: u16 = 4;
+}
+
+/// Condition format 4: true when any subcondition is true.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ConditionFormat4Marker {
+    condition_offsets_byte_len: usize,
+}
+
+impl ConditionFormat4Marker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn condition_count_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + u8::RAW_BYTE_LEN
+    }
+    fn condition_offsets_byte_range(&self) -> Range<usize> {
+        let start = self.condition_count_byte_range().end;
+        start..start + self.condition_offsets_byte_len
+    }
+}
+
+impl<'a> FontRead<'a> for ConditionFormat4<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u16>();
+        let condition_count: u8 = cursor.read()?;
+        let condition_offsets_byte_len = condition_count as usize * Offset24::RAW_BYTE_LEN;
+        cursor.advance_by(condition_offsets_byte_len);
+        cursor.finish(ConditionFormat4Marker {
+            condition_offsets_byte_len,
+        })
+    }
+}
+
+/// Condition format 4: true when any subcondition is true.
+pub type ConditionFormat4<'a> = TableRef<'a, ConditionFormat4Marker>;
+
+impl<'a> ConditionFormat4<'a> {
+    /// Format, = 4
+    pub fn format(&self) -> u16 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Number of subconditions.
+    pub fn condition_count(&self) -> u8 {
+        let range = self.shape.condition_count_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offsets to subconditions, from the beginning of this table.
+    pub fn condition_offsets(&self) -> &'a [BigEndian<Offset24>] {
+        let range = self.shape.condition_offsets_byte_range();
+        self.data.read_array(range).unwrap()
+    }
+
+    /// A dynamically resolving wrapper for [`condition_offsets`][Self::condition_offsets].
+    pub fn conditions(&self) -> ArrayOfOffsets<'a, Condition<'a>, Offset24> {
+        let data = self.data;
+        let offsets = self.condition_offsets();
+        ArrayOfOffsets::new(offsets, data, ())
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for ConditionFormat4<'a> {
+    fn type_name(&self) -> &str {
+        "ConditionFormat4"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new("condition_count", self.condition_count())),
+            2usize => Some({
+                let data = self.data;
+                Field::new(
+                    "condition_offsets",
+                    FieldType::array_of_offsets(
+                        better_type_name::<Condition>(),
+                        self.condition_offsets(),
+                        move |off| {
+                            let target = off.get().resolve::<Condition>(data);
+                            FieldType::offset(off.get(), target)
+                        },
+                    ),
+                )
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for ConditionFormat4<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+impl Format<u16> for ConditionFormat5Marker {
+    const FORMAT: u16 = 5;
+}
+
+/// Condition format 5: true when the subcondition is false.
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ConditionFormat5Marker {}
+
+impl ConditionFormat5Marker {
+    fn format_byte_range(&self) -> Range<usize> {
+        let start = 0;
+        start..start + u16::RAW_BYTE_LEN
+    }
+    fn condition_offset_byte_range(&self) -> Range<usize> {
+        let start = self.format_byte_range().end;
+        start..start + Offset24::RAW_BYTE_LEN
+    }
+}
+
+impl<'a> FontRead<'a> for ConditionFormat5<'a> {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        cursor.advance::<u16>();
+        cursor.advance::<Offset24>();
+        cursor.finish(ConditionFormat5Marker {})
+    }
+}
+
+/// Condition format 5: true when the subcondition is false.
+pub type ConditionFormat5<'a> = TableRef<'a, ConditionFormat5Marker>;
+
+impl<'a> ConditionFormat5<'a> {
+    /// Format, = 5
+    pub fn format(&self) -> u16 {
+        let range = self.shape.format_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Offset to the subcondition, from the beginning of this table.
+    pub fn condition_offset(&self) -> Offset24 {
+        let range = self.shape.condition_offset_byte_range();
+        self.data.read_at(range.start).unwrap()
+    }
+
+    /// Attempt to resolve [`condition_offset`][Self::condition_offset].
+    pub fn condition(&self) -> Result<Condition<'a>, ReadError> {
+        let data = self.data;
+        self.condition_offset().resolve(data)
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeTable<'a> for ConditionFormat5<'a> {
+    fn type_name(&self) -> &str {
+        "ConditionFormat5"
+    }
+    fn get_field(&self, idx: usize) -> Option<Field<'a>> {
+        match idx {
+            0usize => Some(Field::new("format", self.format())),
+            1usize => Some(Field::new(
+                "condition_offset",
+                FieldType::offset(self.condition_offset(), self.condition()),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> std::fmt::Debug for ConditionFormat5<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (self as &dyn SomeTable<'a>).fmt(f)
+    }
+}
+
+/// Flags for a variable component record.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VarcFlags {
+    bits: u32,
+}
+
+impl VarcFlags {
+    /// Axes not specified by the component are reset to the default
+    /// location rather than inherited from the parent.
+    pub const RESET_UNSPECIFIED_AXES: Self = Self { bits: 0x0001 };
+
+    /// The component specifies axis values.
+    pub const HAVE_AXES: Self = Self { bits: 0x0002 };
+
+    /// The axis values have variations.
+    pub const AXIS_VALUES_HAVE_VARIATION: Self = Self { bits: 0x0004 };
+
+    /// The transform has variations.
+    pub const TRANSFORM_HAS_VARIATION: Self = Self { bits: 0x0008 };
+
+    /// The transform has a horizontal translation.
+    pub const HAVE_TRANSLATE_X: Self = Self { bits: 0x0010 };
+
+    /// The transform has a vertical translation.
+    pub const HAVE_TRANSLATE_Y: Self = Self { bits: 0x0020 };
+
+    /// The transform has a rotation.
+    pub const HAVE_ROTATION: Self = Self { bits: 0x0040 };
+
+    /// The component is only used when a condition is met.
+    pub const HAVE_CONDITION: Self = Self { bits: 0x0080 };
+
+    /// The transform has a horizontal scale.
+    pub const HAVE_SCALE_X: Self = Self { bits: 0x0100 };
+
+    /// The transform has a vertical scale.
+    pub const HAVE_SCALE_Y: Self = Self { bits: 0x0200 };
+
+    /// The transform has a horizontal center.
+    pub const HAVE_TCENTER_X: Self = Self { bits: 0x0400 };
+
+    /// The transform has a vertical center.
+    pub const HAVE_TCENTER_Y: Self = Self { bits: 0x0800 };
+
+    /// The glyph identifier is stored in 24 bits.
+    pub const GID_IS_24BIT: Self = Self { bits: 0x1000 };
+
+    /// The transform has a horizontal skew.
+    pub const HAVE_SKEW_X: Self = Self { bits: 0x2000 };
+
+    /// The transform has a vertical skew.
+    pub const HAVE_SKEW_Y: Self = Self { bits: 0x4000 };
+}
+
+impl VarcFlags {
+    ///  Returns an empty set of flags.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Returns the set containing all flags.
+    #[inline]
+    pub const fn all() -> Self {
+        Self {
+            bits: Self::RESET_UNSPECIFIED_AXES.bits
+                | Self::HAVE_AXES.bits
+                | Self::AXIS_VALUES_HAVE_VARIATION.bits
+                | Self::TRANSFORM_HAS_VARIATION.bits
+                | Self::HAVE_TRANSLATE_X.bits
+                | Self::HAVE_TRANSLATE_Y.bits
+                | Self::HAVE_ROTATION.bits
+                | Self::HAVE_CONDITION.bits
+                | Self::HAVE_SCALE_X.bits
+                | Self::HAVE_SCALE_Y.bits
+                | Self::HAVE_TCENTER_X.bits
+                | Self::HAVE_TCENTER_Y.bits
+                | Self::GID_IS_24BIT.bits
+                | Self::HAVE_SKEW_X.bits
+                | Self::HAVE_SKEW_Y.bits,
+        }
+    }
+
+    /// Returns the raw value of the flags currently stored.
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Convert from underlying bit representation, unless that
+    /// representation contains bits that do not correspond to a flag.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Option<Self> {
+        if (bits & !Self::all().bits()) == 0 {
+            Some(Self { bits })
+        } else {
+            None
+        }
+    }
+
+    /// Convert from underlying bit representation, dropping any bits
+    /// that do not correspond to flags.
+    #[inline]
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self {
+            bits: bits & Self::all().bits,
+        }
+    }
+
+    /// Returns `true` if no flags are currently stored.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.bits() == Self::empty().bits()
+    }
+
+    /// Returns `true` if there are flags common to both `self` and `other`.
+    #[inline]
+    pub const fn intersects(&self, other: Self) -> bool {
+        !(Self {
+            bits: self.bits & other.bits,
+        })
+        .is_empty()
+    }
+
+    /// Returns `true` if all of the flags in `other` are contained within `self`.
+    #[inline]
+    pub const fn contains(&self, other: Self) -> bool {
+        (self.bits & other.bits) == other.bits
+    }
+
+    /// Inserts the specified flags in-place.
+    #[inline]
+    pub fn insert(&mut self, other: Self) {
+        self.bits |= other.bits;
+    }
+
+    /// Removes the specified flags in-place.
+    #[inline]
+    pub fn remove(&mut self, other: Self) {
+        self.bits &= !other.bits;
+    }
+
+    /// Toggles the specified flags in-place.
+    #[inline]
+    pub fn toggle(&mut self, other: Self) {
+        self.bits ^= other.bits;
+    }
+
+    /// Returns the intersection between the flags in `self` and
+    /// `other`.
+    ///
+    /// Specifically, the returned set contains only the flags which are
+    /// present in *both* `self` *and* `other`.
+    ///
+    /// This is equivalent to using the `&` operator (e.g.
+    /// [`ops::BitAnd`]), as in `flags & other`.
+    ///
+    /// [`ops::BitAnd`]: https://doc.rust-lang.org/std/ops/trait.BitAnd.html
+    #[inline]
+    #[must_use]
+    pub const fn intersection(self, other: Self) -> Self {
+        Self {
+            bits: self.bits & other.bits,
+        }
+    }
+
+    /// Returns the union of between the flags in `self` and `other`.
+    ///
+    /// Specifically, the returned set contains all flags which are
+    /// present in *either* `self` *or* `other`, including any which are
+    /// present in both.
+    ///
+    /// This is equivalent to using the `|` operator (e.g.
+    /// [`ops::BitOr`]), as in `flags | other`.
+    ///
+    /// [`ops::BitOr`]: https://doc.rust-lang.org/std/ops/trait.BitOr.html
+    #[inline]
+    #[must_use]
+    pub const fn union(self, other: Self) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    /// Returns the difference between the flags in `self` and `other`.
+    ///
+    /// Specifically, the returned set contains all flags present in
+    /// `self`, except for the ones present in `other`.
+    ///
+    /// It is also conceptually equivalent to the "bit-clear" operation:
+    /// `flags & !other` (and this syntax is also supported).
+    ///
+    /// This is equivalent to using the `-` operator (e.g.
+    /// [`ops::Sub`]), as in `flags - other`.
+    ///
+    /// [`ops::Sub`]: https://doc.rust-lang.org/std/ops/trait.Sub.html
+    #[inline]
+    #[must_use]
+    pub const fn difference(self, other: Self) -> Self {
+        Self {
+            bits: self.bits & !other.bits,
+        }
+    }
+}
+
+impl std::ops::BitOr for VarcFlags {
+    type Output = Self;
+
+    /// Returns the union of the two sets of flags.
+    #[inline]
+    fn bitor(self, other: VarcFlags) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+impl std::ops::BitOrAssign for VarcFlags {
+    /// Adds the set of flags.
+    #[inline]
+    fn bitor_assign(&mut self, other: Self) {
+        self.bits |= other.bits;
+    }
+}
+
+impl std::ops::BitXor for VarcFlags {
+    type Output = Self;
+
+    /// Returns the left flags, but with all the right flags toggled.
+    #[inline]
+    fn bitxor(self, other: Self) -> Self {
+        Self {
+            bits: self.bits ^ other.bits,
+        }
+    }
+}
+
+impl std::ops::BitXorAssign for VarcFlags {
+    /// Toggles the set of flags.
+    #[inline]
+    fn bitxor_assign(&mut self, other: Self) {
+        self.bits ^= other.bits;
+    }
+}
+
+impl std::ops::BitAnd for VarcFlags {
+    type Output = Self;
+
+    /// Returns the intersection between the two sets of flags.
+    #[inline]
+    fn bitand(self, other: Self) -> Self {
+        Self {
+            bits: self.bits & other.bits,
+        }
+    }
+}
+
+impl std::ops::BitAndAssign for VarcFlags {
+    /// Disables all flags disabled in the set.
+    #[inline]
+    fn bitand_assign(&mut self, other: Self) {
+        self.bits &= other.bits;
+    }
+}
+
+impl std::ops::Sub for VarcFlags {
+    type Output = Self;
+
+    /// Returns the set difference of the two sets of flags.
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        Self {
+            bits: self.bits & !other.bits,
+        }
+    }
+}
+
+impl std::ops::SubAssign for VarcFlags {
+    /// Disables all flags enabled in the set.
+    #[inline]
+    fn sub_assign(&mut self, other: Self) {
+        self.bits &= !other.bits;
+    }
+}
+
+impl std::ops::Not for VarcFlags {
+    type Output = Self;
+
+    /// Returns the complement of this set of flags.
+    #[inline]
+    fn not(self) -> Self {
+        Self { bits: !self.bits } & Self::all()
+    }
+}
+
+impl std::fmt::Debug for VarcFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let members: &[(&str, Self)] = &[
+            ("RESET_UNSPECIFIED_AXES", Self::RESET_UNSPECIFIED_AXES),
+            ("HAVE_AXES", Self::HAVE_AXES),
+            (
+                "AXIS_VALUES_HAVE_VARIATION",
+                Self::AXIS_VALUES_HAVE_VARIATION,
+            ),
+            ("TRANSFORM_HAS_VARIATION", Self::TRANSFORM_HAS_VARIATION),
+            ("HAVE_TRANSLATE_X", Self::HAVE_TRANSLATE_X),
+            ("HAVE_TRANSLATE_Y", Self::HAVE_TRANSLATE_Y),
+            ("HAVE_ROTATION", Self::HAVE_ROTATION),
+            ("HAVE_CONDITION", Self::HAVE_CONDITION),
+            ("HAVE_SCALE_X", Self::HAVE_SCALE_X),
+            ("HAVE_SCALE_Y", Self::HAVE_SCALE_Y),
+            ("HAVE_TCENTER_X", Self::HAVE_TCENTER_X),
+            ("HAVE_TCENTER_Y", Self::HAVE_TCENTER_Y),
+            ("GID_IS_24BIT", Self::GID_IS_24BIT),
+            ("HAVE_SKEW_X", Self::HAVE_SKEW_X),
+            ("HAVE_SKEW_Y", Self::HAVE_SKEW_Y),
+        ];
+        let mut first = true;
+        for (name, value) in members {
+            if self.contains(*value) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                first = false;
+                f.write_str(name)?;
+            }
+        }
+        if first {
+            f.write_str("(empty)")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Binary for VarcFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Binary::fmt(&self.bits, f)
+    }
+}
+
+impl std::fmt::Octal for VarcFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Octal::fmt(&self.bits, f)
+    }
+}
+
+impl std::fmt::LowerHex for VarcFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::LowerHex::fmt(&self.bits, f)
+    }
+}
+
+impl std::fmt::UpperHex for VarcFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::UpperHex::fmt(&self.bits, f)
+    }
+}
+
+impl font_types::Scalar for VarcFlags {
+    type Raw = <u32 as font_types::Scalar>::Raw;
+    fn to_raw(self) -> Self::Raw {
+        self.bits().to_raw()
+    }
+    fn from_raw(raw: Self::Raw) -> Self {
+        let t = <u32>::from_raw(raw);
+        Self::from_bits_truncate(t)
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> From<VarcFlags> for FieldType<'a> {
+    fn from(src: VarcFlags) -> FieldType<'a> {
+        src.bits().into()
+    }
+}
@@ -24,7 +24,8 @@ pub struct ComputedArray<'a, T: ReadArgs> {
 impl<'a, T: ComputeSize> ComputedArray<'a, T> {
     pub fn new(data: FontData<'a>, args: T::Args) -> Self {
         let item_len = T::compute_size(&args);
-        let len = data.len() / item_len;
+        // zero-sized items (e.g. regions with no axes) make an empty array
+        let len = data.len().checked_div(item_len).unwrap_or_default();
         ComputedArray {
             item_len,
             len,
@@ -152,3 +153,17 @@ impl<'a, T: FromBytes> FontReadWithArgs<'a> for &'a [T] {
         data.read_array(0..len)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::variations::Tuple;
+
+    #[test]
+    fn zero_sized_items() {
+        // a tuple with no axes has no data
+        let array = ComputedArray::<Tuple>::new(FontData::new(&[0, 1, 2, 3]), 0);
+        assert!(array.is_empty());
+        assert_eq!(array.iter().count(), 0);
+    }
+}
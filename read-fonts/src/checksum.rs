@@ -0,0 +1,254 @@
+//! Verification of table checksums and the `head` checksum adjustment.
+//!
+//! See the [table directory] section of the OpenType spec for details on
+//! how checksums are computed.
+//!
+//! [table directory]: https://learn.microsoft.com/en-us/typography/opentype/spec/otff#calculating-checksums
+
+use font_types::Tag;
+
+use crate::{FontData, FontRef};
+
+/// The value that the checksum of a complete font should equal, once the
+/// `head` table's `checksumAdjustment` is set.
+pub const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+const HEAD: Tag = Tag::new(b"head");
+/// Offset of `checksumAdjustment` in the head table.
+const CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+/// Size of the table directory, excluding the table records.
+const TABLE_DIRECTORY_HEADER_LEN: usize = 12;
+const TABLE_RECORD_LEN: usize = 16;
+
+/// Computes the checksum of some data, padded with zeros to a multiple of
+/// four bytes.
+pub fn checksum(data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(4);
+    let mut sum = chunks.by_ref().fold(0u32, |sum, chunk| {
+        sum.wrapping_add(u32::from_be_bytes(chunk.try_into().unwrap()))
+    });
+    let rem = chunks.remainder();
+    if !rem.is_empty() {
+        let mut last = [0u8; 4];
+        last[..rem.len()].copy_from_slice(rem);
+        sum = sum.wrapping_add(u32::from_be_bytes(last));
+    }
+    sum
+}
+
+/// A checksum stored in a font that does not match the font's data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumMismatch {
+    /// The checksum in a table record does not match the table data.
+    Table {
+        tag: Tag,
+        stored: u32,
+        computed: u32,
+    },
+    /// The table record points outside of the font data.
+    OutOfBounds { tag: Tag },
+    /// The `checksumAdjustment` field in the `head` table is incorrect.
+    HeadAdjustment { stored: u32, computed: u32 },
+}
+
+impl ChecksumMismatch {
+    /// The tag of the table with the incorrect checksum.
+    pub fn tag(&self) -> Tag {
+        match self {
+            Self::Table { tag, .. } | Self::OutOfBounds { tag } => *tag,
+            Self::HeadAdjustment { .. } => HEAD,
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Table {
+                tag,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "checksum mismatch in '{tag}' table: stored 0x{stored:08X}, computed 0x{computed:08X}"
+            ),
+            Self::OutOfBounds { tag } => write!(f, "'{tag}' table is out of bounds"),
+            Self::HeadAdjustment { stored, computed } => write!(
+                f,
+                "incorrect head.checksumAdjustment: stored 0x{stored:08X}, computed 0x{computed:08X}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChecksumMismatch {}
+
+impl<'a> FontRef<'a> {
+    /// Returns an iterator over all checksums in the font that do not match
+    /// the font data.
+    ///
+    /// The checksum in each table record is compared with the checksum of
+    /// the table data, and, if there is a `head` table, its
+    /// `checksumAdjustment` is compared with the value computed from the
+    /// table directory and table checksums. The adjustment is only checked
+    /// when all tables are in bounds.
+    ///
+    /// For a font in a collection, the adjustment is computed for that font
+    /// alone, as if it were a standalone font file.
+    pub fn checksum_mismatches(&self) -> impl Iterator<Item = ChecksumMismatch> + 'a {
+        let data = self.data;
+        let records = self.table_directory.table_records();
+        let table_mismatches = records.iter().filter_map(move |record| {
+            let tag = record.tag();
+            let Some(computed) = table_checksum(data, tag, record.offset(), record.length()) else {
+                return Some(ChecksumMismatch::OutOfBounds { tag });
+            };
+            (computed != record.checksum()).then_some(ChecksumMismatch::Table {
+                tag,
+                stored: record.checksum(),
+                computed,
+            })
+        });
+        let directory = self.table_directory.offset_data();
+        let adjustment_mismatch = std::iter::once(()).filter_map(move |_| {
+            let head = records.iter().find(|record| record.tag() == HEAD)?;
+            let stored = data
+                .read_at::<u32>(head.offset() as usize + CHECKSUM_ADJUSTMENT_OFFSET)
+                .ok()?;
+            let directory_len = TABLE_DIRECTORY_HEADER_LEN + records.len() * TABLE_RECORD_LEN;
+            let directory_sum = checksum(directory.as_bytes().get(..directory_len)?);
+            let font_sum = records.iter().try_fold(directory_sum, |sum, record| {
+                table_checksum(data, record.tag(), record.offset(), record.length())
+                    .map(|table_sum| sum.wrapping_add(table_sum))
+            })?;
+            let computed = CHECKSUM_MAGIC.wrapping_sub(font_sum);
+            (computed != stored).then_some(ChecksumMismatch::HeadAdjustment { stored, computed })
+        });
+        table_mismatches.chain(adjustment_mismatch)
+    }
+}
+
+/// Computes the checksum of a table, treating the `head` table's
+/// `checksumAdjustment` as zero.
+fn table_checksum(data: FontData, tag: Tag, offset: u32, length: u32) -> Option<u32> {
+    let start = offset as usize;
+    let table = data.slice(start..start.checked_add(length as usize)?)?;
+    let sum = checksum(table.as_bytes());
+    if tag == HEAD {
+        let adjustment = table.read_at::<u32>(CHECKSUM_ADJUSTMENT_OFFSET).ok()?;
+        Some(sum.wrapping_sub(adjustment))
+    } else {
+        Some(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::BeBuffer;
+
+    /// Builds a font with a single `head`-like table and a second table,
+    /// with correct checksums and adjustment.
+    fn make_font(head: &[u8], other: &[u8]) -> Vec<u8> {
+        let head_offset = TABLE_DIRECTORY_HEADER_LEN + 2 * TABLE_RECORD_LEN;
+        let other_offset = head_offset + ((head.len() + 3) & !3);
+        let mut head = head.to_vec();
+        head[CHECKSUM_ADJUSTMENT_OFFSET..CHECKSUM_ADJUSTMENT_OFFSET + 4].fill(0);
+        let buf = BeBuffer::new()
+            .push(0x00010000u32)
+            .extend([2u16, 32, 1, 0])
+            .push(u32::from_be_bytes(*b"abcd"))
+            .push(checksum(other))
+            .push(other_offset as u32)
+            .push(other.len() as u32)
+            .extend(HEAD.to_be_bytes())
+            .push(checksum(&head))
+            .push(head_offset as u32)
+            .push(head.len() as u32);
+        let mut font = buf.to_vec();
+        font.extend_from_slice(&head);
+        font.resize(other_offset, 0);
+        font.extend_from_slice(other);
+        let adjustment = CHECKSUM_MAGIC.wrapping_sub(checksum(&font));
+        font[head_offset + CHECKSUM_ADJUSTMENT_OFFSET..][..4]
+            .copy_from_slice(&adjustment.to_be_bytes());
+        font
+    }
+
+    #[test]
+    fn checksum_with_padding() {
+        assert_eq!(checksum(&[]), 0);
+        assert_eq!(checksum(&[1, 2, 3, 4, 5]), 0x01020304 + 0x05000000);
+        assert_eq!(checksum(&[0xFF; 8]), 0xFFFFFFFE);
+    }
+
+    #[test]
+    fn valid_font_has_no_mismatches() {
+        let data = make_font(&[0xAA; 20], b"hello");
+        let font = FontRef::new(&data).unwrap();
+        assert_eq!(font.checksum_mismatches().count(), 0);
+        assert_eq!(checksum(&data), CHECKSUM_MAGIC);
+    }
+
+    #[test]
+    fn reports_bad_table_checksum() {
+        let mut data = make_font(&[0xAA; 20], b"hello");
+        let adjustment_pos = TABLE_DIRECTORY_HEADER_LEN + 2 * TABLE_RECORD_LEN + 8;
+        let stored = u32::from_be_bytes(data[adjustment_pos..][..4].try_into().unwrap());
+        // change the data of the 'abcd' table
+        let len = data.len();
+        data[len - 1] = b'!';
+        let font = FontRef::new(&data).unwrap();
+        let computed = checksum(b"hell!");
+        assert_eq!(
+            font.checksum_mismatches().collect::<Vec<_>>(),
+            [
+                ChecksumMismatch::Table {
+                    tag: Tag::new(b"abcd"),
+                    stored: checksum(b"hello"),
+                    computed,
+                },
+                ChecksumMismatch::HeadAdjustment {
+                    stored,
+                    computed: stored.wrapping_sub(computed.wrapping_sub(checksum(b"hello"))),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_bad_adjustment() {
+        let mut data = make_font(&[0xAA; 20], b"hello");
+        let adjustment_pos = TABLE_DIRECTORY_HEADER_LEN + 2 * TABLE_RECORD_LEN + 8;
+        let expected = u32::from_be_bytes(data[adjustment_pos..][..4].try_into().unwrap());
+        data[adjustment_pos..][..4].copy_from_slice(&[0, 0, 0, 1]);
+        let font = FontRef::new(&data).unwrap();
+        assert_eq!(
+            font.checksum_mismatches().collect::<Vec<_>>(),
+            [ChecksumMismatch::HeadAdjustment {
+                stored: 1,
+                computed: expected,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_out_of_bounds_table() {
+        let mut data = make_font(&[0xAA; 20], b"hello");
+        data.truncate(data.len() - 2);
+        let font = FontRef::new(&data).unwrap();
+        assert_eq!(
+            font.checksum_mismatches().collect::<Vec<_>>(),
+            [ChecksumMismatch::OutOfBounds {
+                tag: Tag::new(b"abcd")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fonts_are_consistent() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        assert_eq!(font.checksum_mismatches().collect::<Vec<_>>(), []);
+    }
+}
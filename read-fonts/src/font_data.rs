@@ -294,6 +294,11 @@ impl<'a> Cursor<'a> {
         self.data.len().saturating_sub(self.pos)
     }
 
+    /// Returns the data from the current position to the end.
+    pub(crate) fn remaining(&self) -> Option<FontData<'a>> {
+        self.data.split_off(self.pos)
+    }
+
     pub(crate) fn finish<T>(self, shape: T) -> Result<TableRef<'a, T>, ReadError> {
         let data = self.data;
         data.check_in_bounds(self.pos)?;
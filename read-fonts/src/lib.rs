@@ -68,15 +68,19 @@ extern crate std;
 extern crate core as std;
 
 pub mod array;
+pub mod checksum;
 mod font_data;
 mod offset;
 mod offset_array;
 mod read;
+#[cfg(feature = "traversal")]
+pub mod sanitize;
 mod table_provider;
 mod table_ref;
 pub mod tables;
 #[cfg(feature = "traversal")]
 pub mod traversal;
+pub mod woff;
 
 #[cfg(any(test, feature = "codegen_test"))]
 pub mod codegen_test;
@@ -161,6 +165,9 @@ pub(crate) mod codegen_prelude {
 
 include!("../generated/font.rs");
 
+const ENCODED_WEB_FONT: ReadError =
+    ReadError::MalformedData("web fonts must be decoded before use");
+
 #[derive(Clone)]
 /// Reference to the content of a font or font collection file.
 pub enum FileRef<'a> {
@@ -172,9 +179,14 @@ pub enum FileRef<'a> {
 
 impl<'a> FileRef<'a> {
     /// Creates a new reference to a file representing a font or font collection.
+    ///
+    /// WOFF and WOFF2 files are rejected; use [`WebFontRef`](woff::WebFontRef)
+    /// to decode them first.
     pub fn new(data: &'a [u8]) -> Result<Self, ReadError> {
         Ok(if let Ok(collection) = CollectionRef::new(data) {
             Self::Collection(collection)
+        } else if woff::WebFontRef::new(data).is_ok() {
+            return Err(ENCODED_WEB_FONT);
         } else {
             Self::Font(FontRef::new(data)?)
         })
@@ -0,0 +1,846 @@
+//! Eager structural validation of untrusted fonts.
+//!
+//! Parsing in this crate is lazy: most problems with font data are only
+//! discovered when the relevant accessor is called. This is the right
+//! tradeoff for trusted fonts, but when accepting fonts from an untrusted
+//! source it is often preferable to reject bad data up front.
+//!
+//! [`sanitize`] walks every table that this crate knows how to parse, using
+//! the [traversal] machinery to follow all offsets, records and arrays, and
+//! additionally checks constraints that span multiple tables, such as glyph
+//! counts that must agree with `maxp`. Any problems are collected into a
+//! [`SanitizeReport`]; the sanitizer never panics on malformed input.
+//!
+//! [traversal]: crate::traversal
+
+use std::fmt;
+
+use types::{GlyphId, Tag};
+
+use crate::{
+    tables::{
+        self,
+        cmap::CmapSubtable,
+        glyf::Glyph,
+        postscript::{self, dict, Index},
+    },
+    traversal::{FieldType, SomeArray, SomeTable},
+    FontData, FontRef, ReadError, TableProvider, TableRecord, TopLevelTable,
+};
+
+/// The maximum number of offsets, records and arrays visited in a single table.
+const MAX_NODES_PER_TABLE: usize = 1 << 20;
+/// The maximum nesting depth of subtables, records and arrays.
+const MAX_DEPTH: usize = 64;
+const HEAD_MAGIC: u32 = 0x5F0F3CF5;
+
+/// The result of sanitizing a font.
+#[derive(Clone, Debug, Default)]
+pub struct SanitizeReport {
+    issues: Vec<Issue>,
+}
+
+impl SanitizeReport {
+    /// Returns `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// All problems found in the font, in the order they were encountered.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Returns an iterator over the problems found in the table with the
+    /// given tag.
+    pub fn issues_for_table(&self, tag: Tag) -> impl Iterator<Item = &Issue> + '_ {
+        self.issues.iter().filter(move |issue| issue.table == tag)
+    }
+}
+
+impl fmt::Display for SanitizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "no issues found");
+        }
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single problem found while sanitizing a font.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    /// The table containing the problem.
+    pub table: Tag,
+    /// The location of the problem within the table, e.g.
+    /// `lookup_list_offset.lookup_offsets[2]`.
+    ///
+    /// This is empty if the problem concerns the table as a whole.
+    pub path: String,
+    /// The kind of problem.
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "'{}': {}", self.table, self.kind)
+        } else {
+            write!(f, "'{}' {}: {}", self.table, self.path, self.kind)
+        }
+    }
+}
+
+/// The kinds of problems reported by the sanitizer.
+#[derive(Clone, Debug)]
+pub enum IssueKind {
+    /// The record in the table directory points outside of the font data.
+    TableOutOfBounds,
+    /// The table records are not sorted by tag, so lookups may fail.
+    UnsortedTableRecords,
+    /// More than one table record has this tag.
+    DuplicateTable,
+    /// A table, subtable or offset could not be read.
+    Read(ReadError),
+    /// Parsing of CFF or CFF2 data failed.
+    Postscript(postscript::Error),
+    /// Only the first `readable` of `len` array elements could be read.
+    TruncatedArray { len: usize, readable: usize },
+    /// Subtables are nested too deeply, or there are too many of them.
+    LimitExceeded,
+    /// A field has a value that is not allowed.
+    InvalidValue { field: &'static str, value: i64 },
+    /// A glyph count disagrees with the number of glyphs in `maxp`.
+    GlyphCountMismatch { expected: u16, found: u32 },
+    /// A glyph identifier is not less than the number of glyphs in `maxp`.
+    GlyphOutOfRange(u32),
+    /// The `loca` offset for this glyph is less than that of the previous glyph.
+    LocaNotMonotonic(GlyphId),
+    /// The `loca` offset for this glyph is past the end of the `glyf` table.
+    LocaOutOfBounds(GlyphId),
+    /// The table is shorter than required by values in other tables.
+    TableTooShort { expected: usize, found: usize },
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TableOutOfBounds => write!(f, "table is out of bounds"),
+            Self::UnsortedTableRecords => write!(f, "table records are not sorted"),
+            Self::DuplicateTable => write!(f, "duplicate table record"),
+            Self::Read(err) => write!(f, "{err}"),
+            Self::Postscript(err) => write!(f, "{err}"),
+            Self::TruncatedArray { len, readable } => {
+                write!(f, "only {readable} of {len} array elements are readable")
+            }
+            Self::LimitExceeded => write!(f, "nesting or size limit exceeded"),
+            Self::InvalidValue { field, value } => write!(f, "invalid {field} {value}"),
+            Self::GlyphCountMismatch { expected, found } => {
+                write!(f, "glyph count {found} does not match maxp ({expected})")
+            }
+            Self::GlyphOutOfRange(gid) => write!(f, "glyph {gid} is out of range"),
+            Self::LocaNotMonotonic(gid) => write!(f, "loca offset for {gid} decreases"),
+            Self::LocaOutOfBounds(gid) => write!(f, "loca offset for {gid} is out of bounds"),
+            Self::TableTooShort { expected, found } => {
+                write!(f, "expected at least {expected} bytes, found {found}")
+            }
+        }
+    }
+}
+
+/// Checks the structure of all tables in the font.
+///
+/// See the [module documentation](self) for details.
+pub fn sanitize(font: &FontRef) -> SanitizeReport {
+    let mut sanitizer = Sanitizer::default();
+    sanitizer.check_table_directory(font);
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        // out of bounds tables have already been reported
+        if record_data(font, record).is_none() {
+            continue;
+        }
+        match some_table(font, tag) {
+            Some(Ok(table)) => sanitizer.walk_table(tag, &table),
+            Some(Err(err)) => sanitizer.report(tag, IssueKind::Read(err)),
+            None => (),
+        }
+    }
+    sanitizer.check_cross_table(font);
+    SanitizeReport {
+        issues: sanitizer.issues,
+    }
+}
+
+/// Returns the table with the given tag, if we know how to parse it.
+fn some_table<'a>(
+    font: &FontRef<'a>,
+    tag: Tag,
+) -> Option<Result<Box<dyn SomeTable<'a> + 'a>, ReadError>> {
+    use tables::*;
+    fn boxed<'a, T: SomeTable<'a> + 'a>(
+        table: Result<T, ReadError>,
+    ) -> Option<Result<Box<dyn SomeTable<'a> + 'a>, ReadError>> {
+        Some(table.map(|table| Box::new(table) as _))
+    }
+    match tag {
+        avar::Avar::TAG => boxed(font.avar()),
+        cbdt::Cbdt::TAG => boxed(font.cbdt()),
+        cblc::Cblc::TAG => boxed(font.cblc()),
+        cmap::Cmap::TAG => boxed(font.cmap()),
+        colr::Colr::TAG => boxed(font.colr()),
+        cpal::Cpal::TAG => boxed(font.cpal()),
+        cvar::Cvar::TAG => boxed(font.cvar()),
+        cvt::Cvt::TAG => boxed(font.cvt()),
+        ebdt::Ebdt::TAG => boxed(font.ebdt()),
+        eblc::Eblc::TAG => boxed(font.eblc()),
+        fpgm::Fpgm::TAG => boxed(font.fpgm()),
+        fvar::Fvar::TAG => boxed(font.fvar()),
+        gasp::Gasp::TAG => boxed(font.gasp()),
+        gdef::Gdef::TAG => boxed(font.gdef()),
+        glyf::Glyf::TAG => boxed(font.glyf()),
+        gpos::Gpos::TAG => boxed(font.gpos()),
+        gsub::Gsub::TAG => boxed(font.gsub()),
+        gvar::Gvar::TAG => boxed(font.gvar()),
+        hdmx::Hdmx::TAG => boxed(font.hdmx()),
+        head::Head::TAG => boxed(font.head()),
+        hhea::Hhea::TAG => boxed(font.hhea()),
+        hmtx::Hmtx::TAG => boxed(font.hmtx()),
+        hvar::Hvar::TAG => boxed(font.hvar()),
+        loca::Loca::TAG => boxed(font.loca(None)),
+        ltag::Ltag::TAG => boxed(font.ltag()),
+        ltsh::Ltsh::TAG => boxed(font.ltsh()),
+        maxp::Maxp::TAG => boxed(font.maxp()),
+        meta::Meta::TAG => boxed(font.meta()),
+        mvar::Mvar::TAG => boxed(font.mvar()),
+        name::Name::TAG => boxed(font.name()),
+        os2::Os2::TAG => boxed(font.os2()),
+        post::Post::TAG => boxed(font.post()),
+        prep::Prep::TAG => boxed(font.prep()),
+        sbix::Sbix::TAG => boxed(font.sbix()),
+        stat::Stat::TAG => boxed(font.stat()),
+        varc::Varc::TAG => boxed(font.varc()),
+        vdmx::Vdmx::TAG => boxed(font.vdmx()),
+        vhea::Vhea::TAG => boxed(font.vhea()),
+        vmtx::Vmtx::TAG => boxed(font.vmtx()),
+        vvar::Vvar::TAG => boxed(font.vvar()),
+        _ => None,
+    }
+}
+
+/// One component of the path to the current location in a table.
+enum PathElement {
+    Field(&'static str),
+    Index(usize),
+}
+
+#[derive(Default)]
+struct Sanitizer {
+    issues: Vec<Issue>,
+    path: Vec<PathElement>,
+    nodes: usize,
+}
+
+impl Sanitizer {
+    fn report(&mut self, table: Tag, kind: IssueKind) {
+        self.issues.push(Issue {
+            table,
+            path: String::new(),
+            kind,
+        });
+    }
+
+    fn report_at(&mut self, table: Tag, path: impl Into<String>, kind: IssueKind) {
+        self.issues.push(Issue {
+            table,
+            path: path.into(),
+            kind,
+        });
+    }
+
+    /// Reports an issue at the current traversal path.
+    fn report_here(&mut self, table: Tag, kind: IssueKind) {
+        let mut path = String::new();
+        for element in &self.path {
+            match element {
+                PathElement::Field(name) if path.is_empty() => path.push_str(name),
+                PathElement::Field(name) => {
+                    path.push('.');
+                    path.push_str(name);
+                }
+                PathElement::Index(idx) => path.push_str(&format!("[{idx}]")),
+            }
+        }
+        self.report_at(table, path, kind);
+    }
+
+    fn check_table_directory(&mut self, font: &FontRef) {
+        let records = font.table_directory.table_records();
+        for (i, record) in records.iter().enumerate() {
+            let tag = record.tag();
+            if record_data(font, record).is_none() {
+                self.report(tag, IssueKind::TableOutOfBounds);
+            }
+            if let Some(prev) = i.checked_sub(1).and_then(|i| records.get(i)) {
+                if prev.tag() == tag {
+                    self.report(tag, IssueKind::DuplicateTable);
+                } else if prev.tag() > tag {
+                    self.report(tag, IssueKind::UnsortedTableRecords);
+                }
+            }
+        }
+    }
+
+    /// Walks a table and all of its subtables, reporting any errors.
+    fn walk_table<'a>(&mut self, tag: Tag, table: &(dyn SomeTable<'a> + 'a)) {
+        self.nodes = 0;
+        self.path.clear();
+        self.walk_fields(tag, table);
+    }
+
+    /// Returns `false` if traversal should stop because a limit was reached.
+    fn enter(&mut self, tag: Tag) -> bool {
+        self.nodes += 1;
+        if self.nodes > MAX_NODES_PER_TABLE || self.path.len() > MAX_DEPTH {
+            self.report_here(tag, IssueKind::LimitExceeded);
+            return false;
+        }
+        true
+    }
+
+    fn walk_fields<'a>(&mut self, tag: Tag, table: &(dyn SomeTable<'a> + 'a)) {
+        if !self.enter(tag) {
+            return;
+        }
+        for field in table.iter() {
+            self.path.push(PathElement::Field(field.name));
+            let keep_going = self.walk_value(tag, field.value);
+            self.path.pop();
+            if !keep_going {
+                break;
+            }
+        }
+    }
+
+    /// Returns `false` if a limit was reached.
+    fn walk_value(&mut self, tag: Tag, value: FieldType) -> bool {
+        match value {
+            FieldType::ResolvedOffset(offset) => match offset.target {
+                Ok(table) => self.walk_fields(tag, &table),
+                Err(err) => self.report_here(tag, IssueKind::Read(err)),
+            },
+            FieldType::StringOffset(offset) => {
+                if let Err(err) = offset.target {
+                    self.report_here(tag, IssueKind::Read(err));
+                }
+            }
+            FieldType::ArrayOffset(offset) => match offset.target {
+                Ok(array) => self.walk_array(tag, &array),
+                Err(err) => self.report_here(tag, IssueKind::Read(err)),
+            },
+            FieldType::Record(record) => self.walk_fields(tag, &record),
+            FieldType::Array(array) => self.walk_array(tag, &array),
+            _ => (),
+        }
+        self.nodes <= MAX_NODES_PER_TABLE
+    }
+
+    fn walk_array<'a>(&mut self, tag: Tag, array: &(dyn SomeArray<'a> + 'a)) {
+        if !self.enter(tag) {
+            return;
+        }
+        let len = array.len();
+        for idx in 0..len {
+            let Some(item) = array.get(idx) else {
+                self.report_here(tag, IssueKind::TruncatedArray { len, readable: idx });
+                return;
+            };
+            // scalars are already bounds checked, so there is nothing to do
+            if is_scalar(&item) {
+                return;
+            }
+            self.path.push(PathElement::Index(idx));
+            let keep_going = self.walk_value(tag, item);
+            self.path.pop();
+            if !keep_going {
+                return;
+            }
+        }
+    }
+
+    fn check_cross_table(&mut self, font: &FontRef) {
+        if let Ok(head) = font.head() {
+            self.check_head(&head);
+        }
+        let Ok(num_glyphs) = font.maxp().map(|maxp| maxp.num_glyphs()) else {
+            return;
+        };
+        self.check_metrics(font, num_glyphs);
+        self.check_glyf(font, num_glyphs);
+        self.check_cmap(font, num_glyphs);
+        self.check_cff(font, num_glyphs);
+        if let Ok(gvar) = font.gvar() {
+            self.check_glyph_count(tables::gvar::Gvar::TAG, num_glyphs, gvar.glyph_count());
+        }
+        if let Some(count) = font.post().ok().and_then(|post| post.num_glyphs()) {
+            self.check_glyph_count(tables::post::Post::TAG, num_glyphs, count);
+        }
+        if let Ok(ltsh) = font.ltsh() {
+            self.check_glyph_count(tables::ltsh::Ltsh::TAG, num_glyphs, ltsh.num_glyphs());
+        }
+    }
+
+    fn check_glyph_count(&mut self, tag: Tag, expected: u16, found: impl Into<u32>) {
+        let found = found.into();
+        if found != expected as u32 {
+            self.report(tag, IssueKind::GlyphCountMismatch { expected, found });
+        }
+    }
+
+    fn check_head(&mut self, head: &tables::head::Head) {
+        let tag = tables::head::Head::TAG;
+        if head.magic_number() != HEAD_MAGIC {
+            self.report(
+                tag,
+                IssueKind::InvalidValue {
+                    field: "magic_number",
+                    value: head.magic_number() as i64,
+                },
+            );
+        }
+        if !(16..=16384).contains(&head.units_per_em()) {
+            self.report(
+                tag,
+                IssueKind::InvalidValue {
+                    field: "units_per_em",
+                    value: head.units_per_em() as i64,
+                },
+            );
+        }
+        if !(0..=1).contains(&head.index_to_loc_format()) {
+            self.report(
+                tag,
+                IssueKind::InvalidValue {
+                    field: "index_to_loc_format",
+                    value: head.index_to_loc_format() as i64,
+                },
+            );
+        }
+    }
+
+    /// Checks that hmtx and vmtx are large enough for the number of glyphs
+    /// and long metrics.
+    fn check_metrics(&mut self, font: &FontRef, num_glyphs: u16) {
+        let horizontal = font
+            .hhea()
+            .map(|hhea| hhea.number_of_long_metrics())
+            .map(|count| (tables::hhea::Hhea::TAG, tables::hmtx::Hmtx::TAG, count));
+        let vertical = font
+            .vhea()
+            .map(|vhea| vhea.number_of_long_ver_metrics())
+            .map(|count| (tables::vhea::Vhea::TAG, tables::vmtx::Vmtx::TAG, count));
+        for (header_tag, metrics_tag, num_long_metrics) in
+            [horizontal, vertical].into_iter().flatten()
+        {
+            let Some(data) = font.table_data(metrics_tag) else {
+                continue;
+            };
+            if num_long_metrics == 0 || num_long_metrics > num_glyphs {
+                self.report(
+                    header_tag,
+                    IssueKind::InvalidValue {
+                        field: "number_of_long_metrics",
+                        value: num_long_metrics as i64,
+                    },
+                );
+                continue;
+            }
+            let expected =
+                num_long_metrics as usize * 4 + (num_glyphs - num_long_metrics) as usize * 2;
+            if data.len() < expected {
+                self.report(
+                    metrics_tag,
+                    IssueKind::TableTooShort {
+                        expected,
+                        found: data.len(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Checks loca offsets and the structure of every glyph.
+    fn check_glyf(&mut self, font: &FontRef, num_glyphs: u16) {
+        let (Ok(loca), Ok(glyf)) = (font.loca(None), font.glyf()) else {
+            return;
+        };
+        let loca_tag = tables::loca::Loca::TAG;
+        let glyf_tag = tables::glyf::Glyf::TAG;
+        if loca.len() < num_glyphs as usize {
+            self.check_glyph_count(loca_tag, num_glyphs, loca.len() as u32);
+            return;
+        }
+        let glyf_len = glyf.offset_data().len();
+        let mut prev = 0;
+        for gid in 0..=num_glyphs as usize {
+            let offset = loca.get_raw(gid).unwrap_or_default();
+            let glyph_id = GlyphId::new(gid as u16);
+            if offset < prev {
+                self.report(loca_tag, IssueKind::LocaNotMonotonic(glyph_id));
+                return;
+            }
+            if offset as usize > glyf_len {
+                self.report(loca_tag, IssueKind::LocaOutOfBounds(glyph_id));
+                return;
+            }
+            prev = offset;
+        }
+        for gid in 0..num_glyphs {
+            let glyph_id = GlyphId::new(gid);
+            let path = || format!("glyph {gid}");
+            match loca.get_glyf(glyph_id, &glyf) {
+                Ok(Some(Glyph::Simple(glyph))) => {
+                    let readable = glyph.points().count();
+                    let len = glyph.num_points();
+                    if readable != len {
+                        self.report_at(
+                            glyf_tag,
+                            path(),
+                            IssueKind::TruncatedArray { len, readable },
+                        );
+                    }
+                }
+                Ok(Some(Glyph::Composite(glyph))) => {
+                    for component in glyph.components() {
+                        if component.glyph.to_u16() >= num_glyphs {
+                            self.report_at(
+                                glyf_tag,
+                                path(),
+                                IssueKind::GlyphOutOfRange(component.glyph.to_u16() as u32),
+                            );
+                        }
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => self.report_at(glyf_tag, path(), IssueKind::Read(err)),
+            }
+        }
+    }
+
+    /// Checks that every glyph mapped by cmap exists.
+    fn check_cmap(&mut self, font: &FontRef, num_glyphs: u16) {
+        let Ok(cmap) = font.cmap() else {
+            return;
+        };
+        let tag = tables::cmap::Cmap::TAG;
+        let num_glyphs = num_glyphs as u32;
+        for (i, record) in cmap.encoding_records().iter().enumerate() {
+            let Ok(subtable) = record.subtable(cmap.offset_data()) else {
+                // already reported during traversal
+                continue;
+            };
+            // only the first bad mapping in each subtable is reported
+            let bad_glyph = match &subtable {
+                CmapSubtable::Format0(table) => table
+                    .glyph_id_array()
+                    .iter()
+                    .map(|gid| *gid as u32)
+                    .find(|gid| *gid >= num_glyphs),
+                CmapSubtable::Format4(table) => table
+                    .iter()
+                    .map(|(_, gid)| gid.to_u16() as u32)
+                    .find(|gid| *gid >= num_glyphs),
+                CmapSubtable::Format6(table) => table
+                    .glyph_id_array()
+                    .iter()
+                    .map(|gid| gid.get() as u32)
+                    .find(|gid| *gid >= num_glyphs),
+                CmapSubtable::Format10(table) => table
+                    .glyph_id_array()
+                    .iter()
+                    .map(|gid| gid.get() as u32)
+                    .find(|gid| *gid >= num_glyphs),
+                CmapSubtable::Format8(table) => table.groups().iter().find_map(|group| {
+                    let len = group
+                        .end_char_code()
+                        .saturating_sub(group.start_char_code());
+                    let last = group.start_glyph_id().saturating_add(len);
+                    (last >= num_glyphs).then_some(last)
+                }),
+                CmapSubtable::Format12(table) => table.groups().iter().find_map(|group| {
+                    let len = group
+                        .end_char_code()
+                        .saturating_sub(group.start_char_code());
+                    let last = group.start_glyph_id().saturating_add(len);
+                    (last >= num_glyphs).then_some(last)
+                }),
+                CmapSubtable::Format13(table) => table
+                    .groups()
+                    .iter()
+                    .map(|group| group.glyph_id())
+                    .find(|gid| *gid >= num_glyphs),
+                CmapSubtable::Format2(_) | CmapSubtable::Format14(_) => None,
+            };
+            if let Some(gid) = bad_glyph {
+                self.report_at(
+                    tag,
+                    format!("encoding_records[{i}]"),
+                    IssueKind::GlyphOutOfRange(gid),
+                );
+            }
+        }
+    }
+
+    /// Checks that the number of charstrings matches the number of glyphs.
+    fn check_cff(&mut self, font: &FontRef, num_glyphs: u16) {
+        if let Ok(cff) = font.cff() {
+            let tag = tables::cff::Cff::TAG;
+            match cff.top_dicts().get(0) {
+                Ok(top_dict) => {
+                    self.check_charstrings(tag, cff.offset_data().as_bytes(), top_dict, num_glyphs)
+                }
+                Err(err) => self.report(tag, IssueKind::Postscript(err)),
+            }
+        }
+        if let Ok(cff2) = font.cff2() {
+            self.check_charstrings(
+                tables::cff2::Cff2::TAG,
+                cff2.offset_data().as_bytes(),
+                cff2.top_dict_data(),
+                num_glyphs,
+            );
+        }
+    }
+
+    fn check_charstrings(&mut self, tag: Tag, table: &[u8], top_dict: &[u8], num_glyphs: u16) {
+        let is_cff2 = tag == tables::cff2::Cff2::TAG;
+        let offset = dict::entries(top_dict, None).find_map(|entry| match entry {
+            Ok(dict::Entry::CharstringsOffset(offset)) => Some(Ok(offset)),
+            Err(err) => Some(Err(err)),
+            _ => None,
+        });
+        let charstrings = match offset {
+            Some(Ok(offset)) => table
+                .get(offset..)
+                .ok_or(ReadError::OutOfBounds.into())
+                .and_then(|data| Index::new(data, is_cff2)),
+            Some(Err(err)) => Err(err),
+            None => Err(postscript::Error::MissingCharstrings),
+        };
+        match charstrings {
+            Ok(charstrings) => self.check_glyph_count(tag, num_glyphs, charstrings.count()),
+            Err(err) => self.report(tag, IssueKind::Postscript(err)),
+        }
+    }
+}
+
+/// Returns the data for a table record, without relying on the records
+/// being sorted.
+fn record_data<'a>(font: &FontRef<'a>, record: &TableRecord) -> Option<FontData<'a>> {
+    let start = record.offset() as usize;
+    font.data
+        .slice(start..start.checked_add(record.length() as usize)?)
+}
+
+fn is_scalar(field: &FieldType) -> bool {
+    !matches!(
+        field,
+        FieldType::ResolvedOffset(_)
+            | FieldType::StringOffset(_)
+            | FieldType::ArrayOffset(_)
+            | FieldType::Record(_)
+            | FieldType::Array(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::BeBuffer;
+
+    /// Builds a font from the given tables, which must be sorted by tag.
+    fn make_font(tables: &[(Tag, &[u8])]) -> Vec<u8> {
+        let mut offset = 12 + 16 * tables.len();
+        let mut buf = BeBuffer::new()
+            .push(0x00010000u32)
+            .extend([tables.len() as u16, 0, 0, 0]);
+        for (tag, data) in tables {
+            buf = buf
+                .push(*tag)
+                .push(0u32)
+                .push(offset as u32)
+                .push(data.len() as u32);
+            offset += (data.len() + 3) & !3;
+        }
+        let mut font = buf.to_vec();
+        for (_, data) in tables {
+            font.extend_from_slice(data);
+            font.resize((font.len() + 3) & !3, 0);
+        }
+        font
+    }
+
+    fn maxp(num_glyphs: u16) -> Vec<u8> {
+        BeBuffer::new()
+            .push(0x00005000u32)
+            .push(num_glyphs)
+            .to_vec()
+    }
+
+    #[test]
+    fn test_fonts_are_clean() {
+        for data in [
+            font_test_data::VAZIRMATN_VAR,
+            font_test_data::CANTARELL_VF_TRIMMED,
+            font_test_data::COLRV0V1,
+            font_test_data::NOTO_SERIF_DISPLAY_TRIMMED,
+        ] {
+            let font = FontRef::new(data).unwrap();
+            let report = sanitize(&font);
+            assert!(report.is_ok(), "{report}");
+        }
+    }
+
+    #[test]
+    fn reports_bad_directory() {
+        let maxp = maxp(1);
+        let mut data = make_font(&[(Tag::new(b"maxp"), &maxp), (Tag::new(b"abcd"), b"hi")]);
+        // make the second table extend past the end of the data
+        data[12 + 16 + 12..12 + 16 + 16].copy_from_slice(&100u32.to_be_bytes());
+        let font = FontRef::new(&data).unwrap();
+        let report = sanitize(&font);
+        let kinds = report
+            .issues()
+            .iter()
+            .map(|issue| (issue.table, issue.kind.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (Tag::new(b"abcd"), IssueKind::TableOutOfBounds.to_string()),
+                (
+                    Tag::new(b"abcd"),
+                    IssueKind::UnsortedTableRecords.to_string()
+                ),
+                // lookups fail because the records are unsorted
+                (
+                    Tag::new(b"maxp"),
+                    IssueKind::Read(ReadError::TableIsMissing(Tag::new(b"maxp"))).to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_bad_offsets() {
+        // a cmap with a single encoding record pointing past the end
+        let cmap = BeBuffer::new()
+            .extend([0u16, 1, 3, 1])
+            .push(0xFFFFu32)
+            .to_vec();
+        let maxp = maxp(1);
+        let data = make_font(&[(Tag::new(b"cmap"), &cmap), (Tag::new(b"maxp"), &maxp)]);
+        let font = FontRef::new(&data).unwrap();
+        let report = sanitize(&font);
+        let issue = report.issues_for_table(Tag::new(b"cmap")).next().unwrap();
+        assert!(matches!(
+            issue.kind,
+            IssueKind::Read(ReadError::OutOfBounds)
+        ));
+        assert_eq!(issue.path, "encoding_records[0].subtable_offset");
+    }
+
+    #[test]
+    fn reports_cmap_glyph_out_of_range() {
+        // format 6 subtable mapping 'A' and 'B' to glyphs 1 and 5
+        let subtable = BeBuffer::new().extend([6u16, 14, 0, 0x41, 2, 1, 5]);
+        let cmap = BeBuffer::new()
+            .extend([0u16, 1, 3, 1])
+            .push(12u32)
+            .extend(subtable.iter().copied())
+            .to_vec();
+        let maxp = maxp(2);
+        let data = make_font(&[(Tag::new(b"cmap"), &cmap), (Tag::new(b"maxp"), &maxp)]);
+        let font = FontRef::new(&data).unwrap();
+        let report = sanitize(&font);
+        assert_eq!(report.issues().len(), 1, "{report}");
+        let issue = &report.issues()[0];
+        assert_eq!(issue.table, Tag::new(b"cmap"));
+        assert!(matches!(issue.kind, IssueKind::GlyphOutOfRange(5)));
+    }
+
+    #[test]
+    fn reports_bad_loca() {
+        let maxp = maxp(3);
+        let head = {
+            let mut head = FontRef::new(font_test_data::SIMPLE_GLYF)
+                .unwrap()
+                .table_data(Tag::new(b"head"))
+                .unwrap()
+                .as_bytes()
+                .to_vec();
+            // long loca
+            head[50..52].copy_from_slice(&1u16.to_be_bytes());
+            head
+        };
+        let loca = BeBuffer::new().extend([0u32, 0, 8, 4]).to_vec();
+        let glyf = [0u8; 8];
+        let data = make_font(&[
+            (Tag::new(b"glyf"), &glyf),
+            (Tag::new(b"head"), &head),
+            (Tag::new(b"loca"), &loca),
+            (Tag::new(b"maxp"), &maxp),
+        ]);
+        let font = FontRef::new(&data).unwrap();
+        let report = sanitize(&font);
+        let loca_issues = report
+            .issues_for_table(Tag::new(b"loca"))
+            .collect::<Vec<_>>();
+        assert_eq!(loca_issues.len(), 1, "{report}");
+        assert!(matches!(
+            loca_issues[0].kind,
+            IssueKind::LocaNotMonotonic(gid) if gid == GlyphId::new(3)
+        ));
+    }
+
+    #[test]
+    fn reports_glyph_count_mismatch() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut tables = font
+            .table_directory
+            .table_records()
+            .iter()
+            .map(|record| {
+                let data = font.table_data(record.tag()).unwrap().as_bytes().to_vec();
+                (record.tag(), data)
+            })
+            .collect::<Vec<_>>();
+        let gvar = tables
+            .iter_mut()
+            .find(|(tag, _)| *tag == Tag::new(b"gvar"))
+            .unwrap();
+        // glyphCount is at offset 12
+        gvar.1[12..14].copy_from_slice(&7u16.to_be_bytes());
+        let tables = tables
+            .iter()
+            .map(|(tag, data)| (*tag, data.as_slice()))
+            .collect::<Vec<_>>();
+        let data = make_font(&tables);
+        let font = FontRef::new(&data).unwrap();
+        let report = sanitize(&font);
+        let issue = report
+            .issues_for_table(Tag::new(b"gvar"))
+            .find(|issue| matches!(issue.kind, IssueKind::GlyphCountMismatch { .. }))
+            .unwrap();
+        assert!(matches!(
+            issue.kind,
+            IssueKind::GlyphCountMismatch {
+                expected: 4,
+                found: 7
+            }
+        ));
+    }
+}
@@ -106,6 +106,26 @@ pub trait TableProvider<'a> {
         self.expect_table()
     }
 
+    fn cvt(&self) -> Result<tables::cvt::Cvt<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn fpgm(&self) -> Result<tables::fpgm::Fpgm<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn prep(&self) -> Result<tables::prep::Prep<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn cvar(&self) -> Result<tables::cvar::Cvar<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn gasp(&self) -> Result<tables::gasp::Gasp<'a>, ReadError> {
+        self.expect_table()
+    }
+
     fn cff(&self) -> Result<tables::cff::Cff<'a>, ReadError> {
         self.expect_table()
     }
@@ -164,6 +184,33 @@ pub trait TableProvider<'a> {
     fn stat(&self) -> Result<tables::stat::Stat<'a>, ReadError> {
         self.expect_table()
     }
+
+    fn hdmx(&self) -> Result<tables::hdmx::Hdmx<'a>, ReadError> {
+        // should we make the user pass this in?
+        let num_glyphs = self.maxp().map(|maxp| maxp.num_glyphs())?;
+        let data = self.expect_data_for_tag(tables::hdmx::Hdmx::TAG)?;
+        tables::hdmx::Hdmx::read(data, num_glyphs)
+    }
+
+    fn ltsh(&self) -> Result<tables::ltsh::Ltsh<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn vdmx(&self) -> Result<tables::vdmx::Vdmx<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn meta(&self) -> Result<tables::meta::Meta<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn ltag(&self) -> Result<tables::ltag::Ltag<'a>, ReadError> {
+        self.expect_table()
+    }
+
+    fn varc(&self) -> Result<tables::varc::Varc<'a>, ReadError> {
+        self.expect_table()
+    }
 }
 
 #[cfg(test)]
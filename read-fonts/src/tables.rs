@@ -10,29 +10,40 @@ pub mod cff2;
 pub mod cmap;
 pub mod colr;
 pub mod cpal;
+pub mod cvar;
+pub mod cvt;
 pub mod ebdt;
 pub mod eblc;
+pub mod fpgm;
 pub mod fvar;
+pub mod gasp;
 pub mod gdef;
 pub mod glyf;
 pub mod gpos;
 pub mod gsub;
 pub mod gvar;
+pub mod hdmx;
 pub mod head;
 pub mod hhea;
 pub mod hmtx;
 pub mod hvar;
 pub mod layout;
 pub mod loca;
+pub mod ltag;
+pub mod ltsh;
 pub mod maxp;
+pub mod meta;
 pub mod mvar;
 pub mod name;
 pub mod os2;
 pub mod post;
 pub mod postscript;
+pub mod prep;
 pub mod sbix;
 pub mod stat;
+pub mod varc;
 pub mod variations;
+pub mod vdmx;
 pub mod vhea;
 pub mod vmtx;
 pub mod vvar;
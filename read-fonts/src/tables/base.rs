@@ -51,4 +51,31 @@ mod tests {
             Tag::new(b"latn")
         );
     }
+
+    #[test]
+    fn feat_min_max_coords() {
+        let data = BeBuffer::new()
+            // MinMax table
+            .push(0u16) // minCoord
+            .push(0u16) // maxCoord
+            .push(1u16) // featMinMaxCount
+            .push(Tag::new(b"vkrn"))
+            .push(14u16) // minCoord offset
+            .push(0u16) // maxCoord offset
+            // BaseCoord format 2
+            .push(2u16)
+            .push(-120i16)
+            .push(7u16) // referenceGlyph
+            .push(3u16); // baseCoordPoint
+
+        let min_max = MinMax::read(data.font_data()).unwrap();
+        let record = &min_max.feat_min_max_records()[0];
+        assert_eq!(record.feature_table_tag(), Tag::new(b"vkrn"));
+        let Some(Ok(BaseCoord::Format2(coord))) = record.min_coord(min_max.offset_data()) else {
+            panic!("expected a format 2 BaseCoord");
+        };
+        assert_eq!(coord.coordinate(), -120);
+        assert_eq!(coord.reference_glyph(), 7);
+        assert!(record.max_coord(min_max.offset_data()).is_none());
+    }
 }
@@ -57,7 +57,10 @@ impl BitmapSize {
                 IndexSubtable::Format4(st) => {
                     location.format = st.image_format();
                     let array = st.glyph_array();
-                    let array_ix = match array.binary_search_by(|x| x.glyph_id().cmp(&glyph_id)) {
+                    // the last entry only marks the end of the data, and its
+                    // glyph id is not meaningful
+                    let glyphs = &array[..array.len().saturating_sub(1)];
+                    let array_ix = match glyphs.binary_search_by(|x| x.glyph_id().cmp(&glyph_id)) {
                         Ok(ix) => ix,
                         _ => {
                             return Err(ReadError::InvalidCollectionIndex(glyph_id.to_u16() as u32))
@@ -68,18 +71,21 @@ impl BitmapSize {
                         .get(array_ix + 1)
                         .ok_or(ReadError::OutOfBounds)?
                         .sbit_offset() as usize;
-                    location.data_offset = offset1;
+                    location.data_offset = st.image_data_offset() as usize + offset1;
                     location.data_size = Some(offset2 - offset1);
                 }
                 IndexSubtable::Format5(st) => {
                     location.format = st.image_format();
                     let array = st.glyph_array();
-                    if array.binary_search_by(|x| x.get().cmp(&glyph_id)).is_err() {
-                        return Err(ReadError::InvalidCollectionIndex(glyph_id.to_u16() as u32));
-                    }
+                    let array_ix = match array.binary_search_by(|x| x.get().cmp(&glyph_id)) {
+                        Ok(ix) => ix,
+                        _ => {
+                            return Err(ReadError::InvalidCollectionIndex(glyph_id.to_u16() as u32))
+                        }
+                    };
                     let data_size = st.image_size() as usize;
                     location.data_size = Some(data_size);
-                    location.data_offset = st.image_data_offset() as usize + glyph_ix * data_size;
+                    location.data_offset = st.image_data_offset() as usize + array_ix * data_size;
                     location.metrics = Some(st.big_metrics()[0].clone());
                 }
             }
@@ -319,3 +325,72 @@ impl SbitLineMetrics {
         FieldType::Record(self.traverse(data))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tables::eblc::Eblc, test_helpers::BeBuffer};
+
+    /// Builds an EBLC table with a single size, covering glyphs
+    /// `first..=last` with the given index subtable.
+    fn eblc_with_subtable(first: u16, last: u16, subtable: BeBuffer) -> Vec<u8> {
+        let mut data = BeBuffer::new()
+            .push(MajorMinor::VERSION_2_0)
+            .push(1u32) // num_sizes
+            // bitmap size
+            .push(56u32) // index_subtable_array_offset
+            .push(8u32 + subtable.len() as u32) // index_tables_size
+            .push(1u32) // number_of_index_subtables
+            .push(0u32) // color_ref
+            .extend([0u8; 24]) // hori and vert line metrics
+            .extend([first, last])
+            .extend([16u8, 16, 1, 1]) // ppem_x, ppem_y, bit_depth, flags
+            // index subtable array
+            .extend([first, last])
+            .push(8u32) // additional_offset_to_index_subtable
+            .to_vec();
+        data.extend_from_slice(&subtable);
+        data
+    }
+
+    fn location(eblc_data: &[u8], glyph_id: u16) -> Result<BitmapLocation, ReadError> {
+        let eblc = Eblc::read(FontData::new(eblc_data)).unwrap();
+        eblc.bitmap_sizes()[0].location(eblc.offset_data(), GlyphId::new(glyph_id))
+    }
+
+    #[test]
+    fn format4_location() {
+        let subtable = BeBuffer::new()
+            .extend([4u16, 1]) // index_format, image_format
+            .push(100u32) // image_data_offset
+            .push(2u32) // num_glyphs
+            // glyph id and offset pairs, with a final entry marking the end
+            .extend([5u16, 0, 9, 10, 0, 25]);
+        let eblc = eblc_with_subtable(0, 9, subtable);
+        let glyph5 = location(&eblc, 5).unwrap();
+        assert_eq!((glyph5.data_offset, glyph5.data_size), (100, Some(10)));
+        let glyph9 = location(&eblc, 9).unwrap();
+        assert_eq!((glyph9.data_offset, glyph9.data_size), (110, Some(15)));
+        assert!(location(&eblc, 7).is_err());
+        // the glyph id of the final entry is not a glyph
+        assert!(location(&eblc, 0).is_err());
+    }
+
+    #[test]
+    fn format5_location() {
+        let subtable = BeBuffer::new()
+            .extend([5u16, 5]) // index_format, image_format
+            .push(200u32) // image_data_offset
+            .push(20u32) // image_size
+            .extend([0u8; 8]) // big_metrics
+            .push(3u32) // num_glyphs
+            .extend([3u16, 8, 12]);
+        let eblc = eblc_with_subtable(3, 12, subtable);
+        // glyphs are located by their index in the glyph array
+        let glyph8 = location(&eblc, 8).unwrap();
+        assert_eq!((glyph8.data_offset, glyph8.data_size), (220, Some(20)));
+        let glyph12 = location(&eblc, 12).unwrap();
+        assert_eq!((glyph12.data_offset, glyph12.data_size), (240, Some(20)));
+        assert!(location(&eblc, 4).is_err());
+    }
+}
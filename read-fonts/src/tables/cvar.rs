@@ -0,0 +1,191 @@
+//! The [cvar (CVT Variations)](https://learn.microsoft.com/en-us/typography/opentype/spec/cvar)
+//! table
+
+include!("../../generated/generated_cvar.rs");
+
+use super::variations::{
+    TupleDelta, TupleVariationCount, TupleVariationData, TupleVariationHeader,
+};
+
+/// Variation data specialized for the CVT variations table.
+pub type CvtVariationData<'a> = TupleVariationData<'a, CvtDelta>;
+
+impl<'a> Cvar<'a> {
+    /// Returns the variation data containing the tuples and deltas for the
+    /// control value table.
+    ///
+    /// This table doesn't contain an axis count field so this must be provided
+    /// by the user and can be read from the `fvar` table.
+    pub fn variation_data(&self, axis_count: u16) -> Result<CvtVariationData<'a>, ReadError> {
+        let count = self.tuple_variation_count();
+        let data = self.data()?;
+        let header_data = self.raw_tuple_header_data();
+        // cvar tuples always embed their peaks, so there are no shared tuples
+        Ok(CvtVariationData::from_parts(
+            axis_count,
+            None,
+            count,
+            header_data,
+            data,
+        ))
+    }
+
+    /// Computes the accumulated deltas for the given set of normalized
+    /// coordinates and adds them to the provided `deltas` slice.
+    ///
+    /// The `deltas` slice is indexed by CVT entry, and should generally have
+    /// the same length as the `cvt ` table. Deltas for entries beyond the end
+    /// of the slice are ignored.
+    pub fn deltas(
+        &self,
+        axis_count: u16,
+        coords: &[F2Dot14],
+        deltas: &mut [Fixed],
+    ) -> Result<(), ReadError> {
+        let var_data = self.variation_data(axis_count)?;
+        for tuple in var_data.tuples() {
+            let Some(scalar) = tuple.compute_scalar(coords) else {
+                continue;
+            };
+            for delta in tuple.deltas() {
+                if let Some(value) = deltas.get_mut(delta.position as usize) {
+                    *value += delta.apply_scalar(scalar);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn raw_tuple_header_data(&self) -> FontData<'a> {
+        let range = self.shape.tuple_variation_headers_byte_range();
+        self.data.split_off(range.start).unwrap()
+    }
+}
+
+/// Delta for an entry in the control value table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CvtDelta {
+    /// The index in the CVT.
+    pub position: u16,
+    /// The delta to apply to the value in the CVT.
+    pub value: i16,
+}
+
+impl CvtDelta {
+    /// Applies a tuple scalar to this delta.
+    pub fn apply_scalar(self, scalar: Fixed) -> Fixed {
+        Fixed::from_i32(self.value as i32) * scalar
+    }
+}
+
+impl TupleDelta for CvtDelta {
+    fn is_point() -> bool {
+        false
+    }
+
+    fn new(position: u16, x: i16, _y: i16) -> Self {
+        Self { position, value: x }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A cvar table with two tuples for a single axis, adapted from the
+    // example in fontTools' tests:
+    // https://github.com/fonttools/fonttools/blob/main/Tests/ttLib/tables/_c_v_a_r_test.py
+    static CVAR_DATA: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version 1.0
+        0x00, 0x02, // tupleVariationCount: 2, no shared points
+        0x00, 0x14, // dataOffset: 20
+        // tuple header 0
+        0x00, 0x09, // variationDataSize: 9
+        0xA0, 0x00, // tupleIndex: EMBEDDED_PEAK_TUPLE | PRIVATE_POINT_NUMBERS
+        0x40, 0x00, // peak: 1.0
+        // tuple header 1
+        0x00, 0x05, // variationDataSize: 5
+        0xA0, 0x00, // tupleIndex: EMBEDDED_PEAK_TUPLE | PRIVATE_POINT_NUMBERS
+        0xC0, 0x00, // peak: -1.0
+        // tuple 0 data
+        0x03, // 3 points
+        0x02, 0x00, 0x01, 0x02, // points: 0, 1, 3
+        0x02, 0x03, 0x7F, 0xFE, // deltas: 3, 127, -2
+        // tuple 1 data
+        0x01, // 1 point
+        0x00, 0x02, // points: 2
+        0x00, 0xF6, // deltas: -10
+    ];
+
+    #[test]
+    fn tuples_and_deltas() {
+        let cvar = Cvar::read(FontData::new(CVAR_DATA)).unwrap();
+        let var_data = cvar.variation_data(1).unwrap();
+        let tuples = var_data.tuples().collect::<Vec<_>>();
+        assert_eq!(tuples.len(), 2);
+        assert_eq!(tuples[0].peak().values(), &[F2Dot14::from_f32(1.0)]);
+        assert_eq!(tuples[1].peak().values(), &[F2Dot14::from_f32(-1.0)]);
+        assert_eq!(
+            tuples[0]
+                .deltas()
+                .map(|d| (d.position, d.value))
+                .collect::<Vec<_>>(),
+            &[(0, 3), (1, 127), (3, -2)]
+        );
+        assert_eq!(
+            tuples[1]
+                .deltas()
+                .map(|d| (d.position, d.value))
+                .collect::<Vec<_>>(),
+            &[(2, -10)]
+        );
+    }
+
+    #[test]
+    fn apply_deltas() {
+        let cvar = Cvar::read(FontData::new(CVAR_DATA)).unwrap();
+        let mut deltas = [Fixed::ZERO; 4];
+        cvar.deltas(1, &[F2Dot14::from_f32(0.5)], &mut deltas)
+            .unwrap();
+        assert_eq!(deltas, [1.5, 63.5, 0.0, -1.0].map(Fixed::from_f64));
+        let mut deltas = [Fixed::ZERO; 4];
+        cvar.deltas(1, &[F2Dot14::from_f32(-1.0)], &mut deltas)
+            .unwrap();
+        assert_eq!(deltas, [0.0, 0.0, -10.0, 0.0].map(Fixed::from_f64));
+    }
+
+    #[test]
+    fn intermediate_region() {
+        static DATA: &[u8] = &[
+            0x00, 0x01, 0x00, 0x00, // version 1.0
+            0x00, 0x01, // tupleVariationCount: 1, no shared points
+            0x00, 0x12, // dataOffset: 18
+            // tuple header
+            0x00, 0x05, // variationDataSize: 5
+            0xE0,
+            0x00, // tupleIndex: EMBEDDED_PEAK_TUPLE | INTERMEDIATE_REGION | PRIVATE_POINT_NUMBERS
+            0x20, 0x00, // peak: 0.5
+            0x10, 0x00, // intermediate start: 0.25
+            0x40, 0x00, // intermediate end: 1.0
+            // tuple data
+            0x01, // 1 point
+            0x00, 0x00, // points: 0
+            0x00, 0x08, // deltas: 8
+        ];
+        let cvar = Cvar::read(FontData::new(DATA)).unwrap();
+        let tuple = cvar.variation_data(1).unwrap().tuples().next().unwrap();
+        let (start, end) = tuple.intermediate().unwrap();
+        assert_eq!(start.values(), &[F2Dot14::from_f32(0.25)]);
+        assert_eq!(end.values(), &[F2Dot14::from_f32(1.0)]);
+        let scalar = |coord| tuple.compute_scalar(&[F2Dot14::from_f32(coord)]);
+        assert_eq!(scalar(0.25), None);
+        assert_eq!(scalar(0.75), Some(Fixed::from_f64(0.5)));
+
+        // tuples without an intermediate region
+        let cvar = Cvar::read(FontData::new(CVAR_DATA)).unwrap();
+        let var_data = cvar.variation_data(1).unwrap();
+        assert!(var_data
+            .tuples()
+            .all(|tuple| tuple.intermediate().is_none()));
+    }
+}
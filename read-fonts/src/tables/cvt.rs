@@ -0,0 +1,16 @@
+//! The [cvt (Control Value Table)](https://learn.microsoft.com/en-us/typography/opentype/spec/cvt) table
+
+include!("../../generated/generated_cvt.rs");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_values() {
+        let data = [0x00, 0x10, 0xFF, 0xF6, 0x02, 0x00];
+        let cvt = Cvt::read(FontData::new(&data)).unwrap();
+        let values = cvt.values().iter().map(|v| v.get()).collect::<Vec<_>>();
+        assert_eq!(values, [FWord::new(16), FWord::new(-10), FWord::new(512)]);
+    }
+}
@@ -0,0 +1,16 @@
+//! The [fpgm (Font Program)](https://learn.microsoft.com/en-us/typography/opentype/spec/fpgm) table
+
+include!("../../generated/generated_fpgm.rs");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_instructions() {
+        // PUSHB[0] 0, FDEF, ENDF
+        let data = [0xB0, 0x00, 0x2C, 0x2D];
+        let fpgm = Fpgm::read(FontData::new(&data)).unwrap();
+        assert_eq!(fpgm.instructions(), &data);
+    }
+}
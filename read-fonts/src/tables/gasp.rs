@@ -0,0 +1,26 @@
+//! The [gasp (Grid-fitting and Scan-conversion Procedure)](https://learn.microsoft.com/en-us/typography/opentype/spec/gasp) table
+
+include!("../../generated/generated_gasp.rs");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FontRef, TableProvider};
+
+    #[test]
+    fn read_gasp_ranges() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let gasp = font.gasp().unwrap();
+        assert_eq!(gasp.version(), 1);
+        let ranges = gasp.gasp_ranges();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].range_max_ppem(), 0xFFFF);
+        assert_eq!(
+            ranges[0].range_gasp_behavior(),
+            GaspRangeBehavior::GASP_GRIDFIT
+                | GaspRangeBehavior::GASP_DOGRAY
+                | GaspRangeBehavior::GASP_SYMMETRIC_GRIDFIT
+                | GaspRangeBehavior::GASP_SYMMETRIC_SMOOTHING
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! The [Horizontal Device Metrics](https://learn.microsoft.com/en-us/typography/opentype/spec/hdmx) table
+
+include!("../../generated/generated_hdmx.rs");
+
+impl<'a> Hdmx<'a> {
+    /// Returns the device record for the given pixels per em size, if
+    /// present.
+    pub fn record_for_size(&self, ppem: u8) -> Option<DeviceRecord<'a>> {
+        let records = self.records();
+        // Records are required to be sorted by pixel size
+        let mut lo = 0;
+        let mut hi = records.len();
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let record = records.get(mid).ok()?;
+            match ppem.cmp(&record.pixel_size) {
+                std::cmp::Ordering::Less => hi = mid,
+                std::cmp::Ordering::Greater => lo = mid + 1,
+                std::cmp::Ordering::Equal => return Some(record),
+            }
+        }
+        None
+    }
+}
+
+/// A [device record](https://learn.microsoft.com/en-us/typography/opentype/spec/hdmx#device-record)
+/// containing the hinted advance widths for a single size.
+#[derive(Clone, Debug)]
+pub struct DeviceRecord<'a> {
+    /// Pixel size for following widths (as ppem).
+    pub pixel_size: u8,
+    /// Maximum width.
+    pub max_width: u8,
+    /// Array of widths, indexed by glyph id.
+    pub widths: &'a [u8],
+}
+
+impl<'a> DeviceRecord<'a> {
+    /// Returns the hinted advance width, in pixels, for the given glyph.
+    pub fn width(&self, glyph_id: GlyphId) -> Option<u8> {
+        self.widths.get(glyph_id.to_u16() as usize).copied()
+    }
+}
+
+impl ReadArgs for DeviceRecord<'_> {
+    type Args = (u16, u32);
+}
+
+impl<'a> DeviceRecord<'a> {
+    /// Parse a device record with a known glyph count and record size.
+    pub fn read(data: FontData<'a>, num_glyphs: u16, record_size: u32) -> Result<Self, ReadError> {
+        let args = (num_glyphs, record_size);
+        Self::read_with_args(data, &args)
+    }
+}
+
+impl<'a> FontReadWithArgs<'a> for DeviceRecord<'a> {
+    fn read_with_args(data: FontData<'a>, args: &Self::Args) -> Result<Self, ReadError> {
+        let mut cursor = data.cursor();
+        let num_glyphs = args.0 as usize;
+        Ok(Self {
+            pixel_size: cursor.read()?,
+            max_width: cursor.read()?,
+            widths: cursor.read_array(num_glyphs)?,
+        })
+    }
+}
+
+impl ComputeSize for DeviceRecord<'_> {
+    #[inline]
+    fn compute_size(args: &(u16, u32)) -> usize {
+        // Records are padded to 32-bit alignment, so the size is explicitly
+        // provided by the table header.
+        args.1 as usize
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> DeviceRecord<'a> {
+    pub(crate) fn get_field(&self, idx: usize, _data: FontData<'a>) -> Option<Field<'a>> {
+        match idx {
+            0 => Some(Field::new("pixel_size", self.pixel_size)),
+            1 => Some(Field::new("max_width", self.max_width)),
+            2 => Some(Field::new("widths", self.widths)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "traversal")]
+impl<'a> SomeRecord<'a> for DeviceRecord<'a> {
+    fn traverse(self, data: FontData<'a>) -> RecordResolver<'a> {
+        RecordResolver {
+            name: "DeviceRecord",
+            data,
+            get_field: Box::new(move |idx, data| self.get_field(idx, data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two device records for a font with 3 glyphs. Each record is padded to
+    // 8 bytes.
+    static HDMX_DATA: &[u8] = &[
+        0x00, 0x00, // version
+        0x00, 0x02, // num_records
+        0x00, 0x00, 0x00, 0x08, // size_device_record
+        12, 9, 5, 9, 7, 0, 0, 0, // ppem 12
+        16, 11, 6, 11, 10, 0, 0, 0, // ppem 16
+    ];
+
+    #[test]
+    fn read_records() {
+        let hdmx = Hdmx::read(FontData::new(HDMX_DATA), 3).unwrap();
+        assert_eq!(hdmx.num_records(), 2);
+        let records = hdmx
+            .records()
+            .iter()
+            .map(|rec| {
+                let rec = rec.unwrap();
+                (rec.pixel_size, rec.max_width, rec.widths.to_vec())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            &[(12, 9, vec![5, 9, 7]), (16, 11, vec![6, 11, 10])]
+        );
+    }
+
+    #[test]
+    fn find_record_for_size() {
+        let hdmx = Hdmx::read(FontData::new(HDMX_DATA), 3).unwrap();
+        let record = hdmx.record_for_size(16).unwrap();
+        assert_eq!(record.width(GlyphId::new(2)), Some(10));
+        assert_eq!(record.width(GlyphId::new(3)), None);
+        assert!(hdmx.record_for_size(12).is_some());
+        assert!(hdmx.record_for_size(13).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+//! The [ltag (Language Tag)](https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6ltag.html) table
+
+include!("../../generated/generated_ltag.rs");
+
+impl<'a> Ltag<'a> {
+    /// Returns the language tag at the given index.
+    ///
+    /// This index is used as the language identifier for Unicode platform
+    /// entries in the `name` table and by language references in `morx`.
+    pub fn tag(&self, index: u32) -> Option<&'a str> {
+        let range = self.tag_ranges().get(index as usize)?;
+        let start = range.offset() as usize;
+        let end = start + range.length() as usize;
+        let bytes = self.offset_data().read_array(start..end).ok()?;
+        std::str::from_utf8(bytes).ok()
+    }
+
+    /// Returns an iterator over all of the language tags in the table.
+    ///
+    /// Tags that are out of bounds or not valid UTF-8 are skipped.
+    pub fn tags(&self) -> impl Iterator<Item = &'a str> + 'a + Clone {
+        let this = self.clone();
+        (0..self.num_tags()).filter_map(move |index| this.tag(index))
+    }
+
+    /// Returns the index of the given language tag, if present.
+    pub fn index_for_tag(&self, tag: &str) -> Option<u32> {
+        (0..self.num_tags()).find(|index| self.tag(*index) == Some(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from the spec
+    static LTAG_DATA: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x03, // num_tags
+        0x00, 0x18, 0x00, 0x02, // "en"
+        0x00, 0x1A, 0x00, 0x02, // "sp"
+        0x00, 0x1C, 0x00, 0x02, // "sr"
+        b'e', b'n', b's', b'p', b's', b'r',
+    ];
+
+    #[test]
+    fn read_tags() {
+        let ltag = Ltag::read(FontData::new(LTAG_DATA)).unwrap();
+        assert_eq!(ltag.tags().collect::<Vec<_>>(), ["en", "sp", "sr"]);
+        assert_eq!(ltag.tag(1), Some("sp"));
+        assert_eq!(ltag.tag(3), None);
+        assert_eq!(ltag.index_for_tag("sr"), Some(2));
+        assert_eq!(ltag.index_for_tag("de"), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! The [Linear Threshold](https://learn.microsoft.com/en-us/typography/opentype/spec/ltsh) table
+
+include!("../../generated/generated_ltsh.rs");
+
+impl<'a> Ltsh<'a> {
+    /// Returns true if the given glyph can be assumed to scale linearly at
+    /// the given size in pixels per em.
+    ///
+    /// A glyph with a threshold of 1 is assumed to scale linearly at all
+    /// sizes. Glyphs without an entry in the table are assumed to never
+    /// scale linearly.
+    pub fn is_linear(&self, glyph_id: GlyphId, ppem: u16) -> bool {
+        self.y_pels()
+            .get(glyph_id.to_u16() as usize)
+            .map(|threshold| *threshold != 0 && ppem >= *threshold as u16)
+            .unwrap_or(false)
+    }
+}
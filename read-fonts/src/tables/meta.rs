@@ -0,0 +1,122 @@
+//! The [meta (Metadata)](https://learn.microsoft.com/en-us/typography/opentype/spec/meta) table
+
+include!("../../generated/generated_meta.rs");
+
+/// Tag for the list of languages that the font was primarily designed for.
+pub const DLNG: Tag = Tag::new(b"dlng");
+/// Tag for the list of languages that the font is capable of supporting.
+pub const SLNG: Tag = Tag::new(b"slng");
+
+impl DataMapRecord {
+    /// Returns the data for this record, given the data for the parent
+    /// `meta` table.
+    pub fn data<'a>(&self, data: FontData<'a>) -> Result<&'a [u8], ReadError> {
+        let start = self.data_offset() as usize;
+        let end = start.saturating_add(self.data_length() as usize);
+        data.read_array(start..end)
+    }
+}
+
+impl<'a> Meta<'a> {
+    /// Returns the data for the first data map with the given tag.
+    pub fn data_for_tag(&self, tag: Tag) -> Option<&'a [u8]> {
+        self.data_maps()
+            .iter()
+            .find(|record| record.tag() == tag)
+            .and_then(|record| record.data(self.offset_data()).ok())
+    }
+
+    /// Returns the list of languages that the font was primarily designed
+    /// for, from the `dlng` metadata.
+    pub fn design_languages(&self) -> ScriptLangTags<'a> {
+        ScriptLangTags::new(self.data_for_tag(DLNG).unwrap_or_default())
+    }
+
+    /// Returns the list of languages that the font is capable of supporting,
+    /// from the `slng` metadata.
+    pub fn supported_languages(&self) -> ScriptLangTags<'a> {
+        ScriptLangTags::new(self.data_for_tag(SLNG).unwrap_or_default())
+    }
+}
+
+/// An iterator over a comma separated list of
+/// [ScriptLangTag](https://learn.microsoft.com/en-us/typography/opentype/spec/meta#scriptlangtag-values)
+/// values.
+///
+/// Each item is a string in a form similar to a BCP 47 language tag (for
+/// example, "en-Latn" or "Hant") with surrounding whitespace removed.
+/// If the data is not valid UTF-8, the iterator is empty.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptLangTags<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> ScriptLangTags<'a> {
+    /// Creates a new iterator over the tags in the given data.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            remaining: std::str::from_utf8(data).unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a> Iterator for ScriptLangTags<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+            let (tag, rest) = self
+                .remaining
+                .split_once(',')
+                .unwrap_or((self.remaining, ""));
+            self.remaining = rest;
+            let tag = tag.trim();
+            if !tag.is_empty() {
+                return Some(tag);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static META_DATA: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x00, // reserved
+        0x00, 0x00, 0x00, 0x02, // data_maps_count
+        b'd', b'l', b'n', b'g', 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x04, // dlng
+        b's', b'l', b'n', b'g', 0x00, 0x00, 0x00, 0x2C, 0x00, 0x00, 0x00, 0x11, // slng
+        b'H', b'a', b'n', b's', // "Hans"
+        b'H', b'a', b'n', b's', b',', b' ', b'L', b'a', b't', b'n', b',', b'j', b'a', b'-', b'J',
+        b'p', b'a', // "Hans, Latn,ja-Jpa"
+    ];
+
+    #[test]
+    fn read_languages() {
+        let meta = Meta::read(FontData::new(META_DATA)).unwrap();
+        assert_eq!(meta.data_maps().len(), 2);
+        assert_eq!(meta.design_languages().collect::<Vec<_>>(), ["Hans"]);
+        assert_eq!(
+            meta.supported_languages().collect::<Vec<_>>(),
+            ["Hans", "Latn", "ja-Jpa"]
+        );
+        assert!(meta.data_for_tag(Tag::new(b"appl")).is_none());
+    }
+
+    #[test]
+    fn script_lang_tags() {
+        fn parse(s: &str) -> Vec<&str> {
+            ScriptLangTags::new(s.as_bytes()).collect()
+        }
+        assert!(parse("").is_empty());
+        assert_eq!(parse("en-Latn"), ["en-Latn"]);
+        assert_eq!(parse(" Cyrl ,, Grek,"), ["Cyrl", "Grek"]);
+        assert!(ScriptLangTags::new(&[0xFF, b'a']).next().is_none());
+    }
+}
@@ -130,3 +130,29 @@ impl<'a> Mvar<'a> {
         Err(ReadError::MetricIsMissing(tag))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FontRef, TableProvider};
+
+    #[test]
+    fn metric_deltas() {
+        let font = FontRef::new(font_test_data::CANTARELL_VF_TRIMMED).unwrap();
+        let mvar = font.mvar().unwrap();
+        assert_eq!(mvar.value_records().len(), 4);
+        let delta = |tag, coord| {
+            mvar.metric_delta(tag, &[F2Dot14::from_f32(coord)])
+                .unwrap()
+                .to_i32()
+        };
+        assert_eq!(delta(tags::XHGT, 1.0), 8);
+        assert_eq!(delta(tags::XHGT, -1.0), -2);
+        assert_eq!(delta(tags::STRO, 1.0), 5);
+        assert_eq!(delta(tags::XHGT, 0.0), 0);
+        assert!(matches!(
+            mvar.metric_delta(tags::HASC, &[F2Dot14::ONE]),
+            Err(ReadError::MetricIsMissing(_))
+        ));
+    }
+}
@@ -0,0 +1,19 @@
+//! The [prep (Control Value Program)](https://learn.microsoft.com/en-us/typography/opentype/spec/prep) table
+
+include!("../../generated/generated_prep.rs");
+
+#[cfg(test)]
+mod tests {
+    use crate::{FontRef, TableProvider};
+
+    #[test]
+    fn read_instructions() {
+        let font = FontRef::new(font_test_data::NOTO_SERIF_DISPLAY_TRIMMED).unwrap();
+        let prep = font.prep().unwrap();
+        // PUSHW[0] 511, SCANCTRL, PUSHB[0] 4, SCANTYPE
+        assert_eq!(
+            prep.instructions(),
+            &[0xB8, 0x01, 0xFF, 0x85, 0xB0, 0x04, 0x8D]
+        );
+    }
+}
@@ -0,0 +1,768 @@
+//! The [VARC (Variable Composites / Components)](https://github.com/harfbuzz/boring-expansion-spec/blob/main/VARC.md) table
+
+pub use super::{
+    layout::{ConditionFormat1, CoverageTable},
+    postscript::Index2,
+};
+
+use super::layout::ConditionFormat1Marker;
+
+include!("../../generated/generated_varc.rs");
+
+/// Variation index indicating that there are no variations.
+pub const NO_VARIATION_INDEX: u32 = 0xFFFFFFFF;
+
+/// Limit for nested conditions to avoid cycles in malicious fonts.
+const CONDITION_NESTING_DEPTH_LIMIT: usize = 32;
+
+impl<'a> Varc<'a> {
+    /// Returns the variable composite description for the given glyph, or
+    /// `None` if the glyph is not covered by this table.
+    pub fn glyph(&self, glyph_id: GlyphId) -> Result<Option<VarcGlyph<'a>>, ReadError> {
+        let Some(coverage_index) = self.coverage()?.get(glyph_id) else {
+            return Ok(None);
+        };
+        let data = self
+            .var_composite_glyphs()?
+            .get(coverage_index as usize)
+            .map_err(|_| ReadError::OutOfBounds)?;
+        Ok(Some(VarcGlyph {
+            data: FontData::new(data),
+            axis_indices_list: self.axis_indices_list().transpose()?,
+        }))
+    }
+
+    /// Returns the condition at the given index in the condition list.
+    pub fn condition(&self, index: u32) -> Result<Condition<'a>, ReadError> {
+        self.condition_list()
+            .ok_or(ReadError::NullOffset)??
+            .conditions()
+            .get(index as usize)
+    }
+}
+
+/// The sequence of components for a variable composite glyph.
+#[derive(Clone)]
+pub struct VarcGlyph<'a> {
+    data: FontData<'a>,
+    axis_indices_list: Option<Index2<'a>>,
+}
+
+impl<'a> VarcGlyph<'a> {
+    /// Returns an iterator over the components of the glyph.
+    pub fn components(&self) -> VarcComponentIter<'a> {
+        VarcComponentIter {
+            cursor: self.data.cursor(),
+            axis_indices_list: self.axis_indices_list.clone(),
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the components of a variable composite glyph.
+#[derive(Clone)]
+pub struct VarcComponentIter<'a> {
+    cursor: Cursor<'a>,
+    axis_indices_list: Option<Index2<'a>>,
+    done: bool,
+}
+
+impl<'a> Iterator for VarcComponentIter<'a> {
+    type Item = Result<VarcComponent<'a>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.cursor.remaining_bytes() == 0 {
+            return None;
+        }
+        let result = VarcComponent::read(&mut self.cursor, self.axis_indices_list.as_ref());
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+/// A single component of a variable composite glyph.
+#[derive(Clone, Debug)]
+pub struct VarcComponent<'a> {
+    flags: VarcFlags,
+    condition_index: Option<u32>,
+    glyph_id: GlyphId,
+    axis_indices: TupleValues<'a>,
+    axis_values: TupleValues<'a>,
+    axis_values_var_index: u32,
+    transform_var_index: u32,
+    transform: [i16; TRANSFORM_FIELD_COUNT],
+}
+
+impl<'a> VarcComponent<'a> {
+    fn read(
+        cursor: &mut Cursor<'a>,
+        axis_indices_list: Option<&Index2<'a>>,
+    ) -> Result<Self, ReadError> {
+        let flags = VarcFlags::from_bits_truncate(read_u32_var(cursor)?);
+        let condition_index = if flags.contains(VarcFlags::HAVE_CONDITION) {
+            Some(read_u32_var(cursor)?)
+        } else {
+            None
+        };
+        let raw_glyph_id = if flags.contains(VarcFlags::GID_IS_24BIT) {
+            cursor.read::<Uint24>()?.to_u32()
+        } else {
+            cursor.read::<u16>()? as u32
+        };
+        let glyph_id = u16::try_from(raw_glyph_id)
+            .map(GlyphId::new)
+            .map_err(|_| ReadError::MalformedData("component glyph id out of range"))?;
+        let (axis_indices, axis_values) = if flags.contains(VarcFlags::HAVE_AXES) {
+            let index = read_u32_var(cursor)?;
+            let axis_indices = axis_indices_list
+                .ok_or(ReadError::NullOffset)?
+                .get(index as usize)
+                .map_err(|_| ReadError::OutOfBounds)?;
+            let axis_indices = TupleValues::new(FontData::new(axis_indices));
+            let axis_values = TupleValues::read(cursor, axis_indices.len())?;
+            (axis_indices, axis_values)
+        } else {
+            Default::default()
+        };
+        let axis_values_var_index = if flags.contains(VarcFlags::AXIS_VALUES_HAVE_VARIATION) {
+            read_u32_var(cursor)?
+        } else {
+            NO_VARIATION_INDEX
+        };
+        let transform_var_index = if flags.contains(VarcFlags::TRANSFORM_HAS_VARIATION) {
+            read_u32_var(cursor)?
+        } else {
+            NO_VARIATION_INDEX
+        };
+        let mut transform = [0; TRANSFORM_FIELD_COUNT];
+        for (value, field) in transform.iter_mut().zip(TRANSFORM_FIELDS) {
+            if flags.contains(field.flag) {
+                *value = cursor.read()?;
+            }
+        }
+        // Skip the values for any reserved flags.
+        let reserved_count = (flags.bits() & !VarcFlags::all().bits()).count_ones();
+        for _ in 0..reserved_count {
+            read_u32_var(cursor)?;
+        }
+        Ok(Self {
+            flags,
+            condition_index,
+            glyph_id,
+            axis_indices,
+            axis_values,
+            axis_values_var_index,
+            transform_var_index,
+            transform,
+        })
+    }
+
+    /// Returns the flags for this component.
+    pub fn flags(&self) -> VarcFlags {
+        self.flags
+    }
+
+    /// Returns the index of the condition that determines whether this
+    /// component is present.
+    pub fn condition_index(&self) -> Option<u32> {
+        self.condition_index
+    }
+
+    /// Returns the glyph identifier of the component.
+    pub fn glyph_id(&self) -> GlyphId {
+        self.glyph_id
+    }
+
+    /// Returns the indices of the axes for which this component specifies
+    /// values.
+    pub fn axis_indices(&self) -> TupleValues<'a> {
+        self.axis_indices.clone()
+    }
+
+    /// Returns the axis values for this component, as raw `F2Dot14` values
+    /// parallel to [`axis_indices`](Self::axis_indices).
+    pub fn axis_values(&self) -> TupleValues<'a> {
+        self.axis_values.clone()
+    }
+
+    /// Returns the variation index for the axis values.
+    pub fn axis_values_var_index(&self) -> u32 {
+        self.axis_values_var_index
+    }
+
+    /// Returns the variation index for the transform.
+    pub fn transform_var_index(&self) -> u32 {
+        self.transform_var_index
+    }
+
+    /// Returns the number of transform values present in this component.
+    ///
+    /// This is the expected length of the delta vector referenced by
+    /// [`transform_var_index`](Self::transform_var_index).
+    pub fn transform_value_count(&self) -> usize {
+        TRANSFORM_FIELDS
+            .iter()
+            .filter(|field| self.flags.contains(field.flag))
+            .count()
+    }
+
+    /// Returns the transform at the default location.
+    pub fn transform(&self) -> DecomposedTransform {
+        self.transform_with_deltas(&[])
+    }
+
+    /// Returns the transform after adding the given deltas.
+    ///
+    /// The deltas are in the raw units of the stored values and apply, in
+    /// order, only to the values present in this component.
+    pub fn transform_with_deltas(&self, deltas: &[f32]) -> DecomposedTransform {
+        let mut values = [0f32; TRANSFORM_FIELD_COUNT];
+        let mut deltas = deltas.iter();
+        for ((value, raw), field) in values.iter_mut().zip(self.transform).zip(TRANSFORM_FIELDS) {
+            *value = if self.flags.contains(field.flag) {
+                let delta = deltas.next().copied().unwrap_or_default();
+                (raw as f32 + delta) * field.scale
+            } else {
+                field.default
+            };
+        }
+        let [translate_x, translate_y, rotation, scale_x, mut scale_y, skew_x, skew_y, center_x, center_y] =
+            values;
+        if !self.flags.contains(VarcFlags::HAVE_SCALE_Y) {
+            scale_y = scale_x;
+        }
+        DecomposedTransform {
+            translate_x,
+            translate_y,
+            rotation,
+            scale_x,
+            scale_y,
+            skew_x,
+            skew_y,
+            center_x,
+            center_y,
+        }
+    }
+}
+
+const TRANSFORM_FIELD_COUNT: usize = 9;
+
+struct TransformField {
+    flag: VarcFlags,
+    scale: f32,
+    default: f32,
+}
+
+impl TransformField {
+    const fn new(flag: VarcFlags, fraction_bits: u32, default: f32) -> Self {
+        Self {
+            flag,
+            scale: 1.0 / (1 << fraction_bits) as f32,
+            default,
+        }
+    }
+}
+
+/// Transform values in the order they are stored in a component record.
+const TRANSFORM_FIELDS: [TransformField; TRANSFORM_FIELD_COUNT] = [
+    TransformField::new(VarcFlags::HAVE_TRANSLATE_X, 0, 0.0),
+    TransformField::new(VarcFlags::HAVE_TRANSLATE_Y, 0, 0.0),
+    TransformField::new(VarcFlags::HAVE_ROTATION, 12, 0.0),
+    TransformField::new(VarcFlags::HAVE_SCALE_X, 10, 1.0),
+    TransformField::new(VarcFlags::HAVE_SCALE_Y, 10, 1.0),
+    TransformField::new(VarcFlags::HAVE_SKEW_X, 12, 0.0),
+    TransformField::new(VarcFlags::HAVE_SKEW_Y, 12, 0.0),
+    TransformField::new(VarcFlags::HAVE_TCENTER_X, 0, 0.0),
+    TransformField::new(VarcFlags::HAVE_TCENTER_Y, 0, 0.0),
+];
+
+/// The transform of a variable component, in decomposed form.
+///
+/// The full transform is computed as
+/// `translate(translate + center) * rotate * scale * skew * translate(-center)`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DecomposedTransform {
+    /// Horizontal translation in font units.
+    pub translate_x: f32,
+    /// Vertical translation in font units.
+    pub translate_y: f32,
+    /// Counter-clockwise rotation in multiples of pi radians.
+    pub rotation: f32,
+    /// Horizontal scale factor.
+    pub scale_x: f32,
+    /// Vertical scale factor.
+    pub scale_y: f32,
+    /// Counter-clockwise horizontal skew angle in multiples of pi radians.
+    ///
+    /// A positive value leans vertical lines to the left, so the skew
+    /// matrix uses `tan(-skew_x)`.
+    pub skew_x: f32,
+    /// Counter-clockwise vertical skew angle in multiples of pi radians.
+    pub skew_y: f32,
+    /// Horizontal center of rotation, scale and skew in font units.
+    pub center_x: f32,
+    /// Vertical center of rotation, scale and skew in font units.
+    pub center_y: f32,
+}
+
+impl Default for DecomposedTransform {
+    fn default() -> Self {
+        Self {
+            translate_x: 0.0,
+            translate_y: 0.0,
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            skew_x: 0.0,
+            skew_y: 0.0,
+            center_x: 0.0,
+            center_y: 0.0,
+        }
+    }
+}
+
+/// Reads a variable length encoded 32-bit unsigned integer.
+///
+/// The number of leading one bits in the first byte determines the number
+/// of additional bytes to read.
+fn read_u32_var(cursor: &mut Cursor) -> Result<u32, ReadError> {
+    let first = cursor.read::<u8>()? as u32;
+    let (extra_bytes, mut value) = match first {
+        0x00..=0x7F => return Ok(first),
+        0x80..=0xBF => (1, first & 0x3F),
+        0xC0..=0xDF => (2, first & 0x1F),
+        0xE0..=0xEF => (3, first & 0x0F),
+        _ => (4, 0),
+    };
+    for _ in 0..extra_bytes {
+        value = (value << 8) | cursor.read::<u8>()? as u32;
+    }
+    Ok(value)
+}
+
+/// A packed sequence of integer values.
+///
+/// This uses the [packed deltas](https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#packed-deltas)
+/// encoding, extended with support for runs of 32-bit values.
+#[derive(Clone, Debug, Default)]
+pub struct TupleValues<'a> {
+    data: FontData<'a>,
+    count: usize,
+}
+
+impl<'a> TupleValues<'a> {
+    /// Creates a new sequence where all of the data is assumed to be values.
+    pub fn new(data: FontData<'a>) -> Self {
+        let count = TupleValuesIter::new(data.cursor(), usize::MAX).count();
+        Self { data, count }
+    }
+
+    /// Reads a sequence with a known number of values and advances the
+    /// cursor past the end.
+    fn read(cursor: &mut Cursor<'a>, count: usize) -> Result<Self, ReadError> {
+        let data = cursor.remaining().ok_or(ReadError::OutOfBounds)?;
+        let mut iter = TupleValuesIter::new(*cursor, count);
+        for _ in 0..count {
+            iter.next().ok_or(ReadError::OutOfBounds)?;
+        }
+        *cursor = iter.cursor;
+        Ok(Self { data, count })
+    }
+
+    /// Returns the number of values in the sequence.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns an iterator over the values in the sequence.
+    pub fn iter(&self) -> TupleValuesIter<'a> {
+        TupleValuesIter::new(self.data.cursor(), self.count)
+    }
+}
+
+/// Iterator over the values in a [`TupleValues`] sequence.
+#[derive(Clone, Debug)]
+pub struct TupleValuesIter<'a> {
+    cursor: Cursor<'a>,
+    remaining: usize,
+    run_remaining: u8,
+    run_control: u8,
+}
+
+impl<'a> TupleValuesIter<'a> {
+    fn new(cursor: Cursor<'a>, count: usize) -> Self {
+        Self {
+            cursor,
+            remaining: count,
+            run_remaining: 0,
+            run_control: 0,
+        }
+    }
+}
+
+impl Iterator for TupleValuesIter<'_> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        /// Flag indicating that the values for this run are all zero.
+        const VALUES_ARE_ZERO: u8 = 0x80;
+        /// Flag indicating that the values for this run are 16-bit.
+        const VALUES_ARE_WORDS: u8 = 0x40;
+        /// Both flags indicate that the values for this run are 32-bit.
+        const VALUES_ARE_LONGS: u8 = VALUES_ARE_ZERO | VALUES_ARE_WORDS;
+        /// Mask for the number of values in the run, minus one.
+        const RUN_COUNT_MASK: u8 = 0x3F;
+
+        if self.remaining == 0 {
+            return None;
+        }
+        while self.run_remaining == 0 {
+            let control: u8 = self.cursor.read().ok()?;
+            self.run_control = control & VALUES_ARE_LONGS;
+            self.run_remaining = (control & RUN_COUNT_MASK) + 1;
+        }
+        self.run_remaining -= 1;
+        self.remaining -= 1;
+        match self.run_control {
+            VALUES_ARE_ZERO => Some(0),
+            VALUES_ARE_WORDS => self.cursor.read::<i16>().ok().map(|v| v as i32),
+            VALUES_ARE_LONGS => self.cursor.read::<i32>().ok(),
+            _ => self.cursor.read::<i8>().ok().map(|v| v as i32),
+        }
+    }
+}
+
+impl<'a> MultiItemVariationStore<'a> {
+    /// Computes the delta vector for the given variation index and set of
+    /// normalized variation coordinates and adds it to `deltas`.
+    ///
+    /// Values in the delta vector beyond the length of `deltas` are
+    /// ignored.
+    pub fn add_deltas(
+        &self,
+        var_index: u32,
+        coords: &[F2Dot14],
+        deltas: &mut [f32],
+    ) -> Result<(), ReadError> {
+        if var_index == NO_VARIATION_INDEX {
+            return Ok(());
+        }
+        let data = self.variation_data().get((var_index >> 16) as usize)?;
+        let region_indices = data.region_indices();
+        if region_indices.is_empty() {
+            return Ok(());
+        }
+        let values = data.delta_set(var_index & 0xFFFF)?;
+        let vector_len = values.len() / region_indices.len();
+        let regions = self.region_list()?.regions();
+        let mut values = values.iter();
+        for region_index in region_indices {
+            let scalar = regions
+                .get(region_index.get() as usize)?
+                .compute_scalar(coords);
+            let row = values.by_ref().take(vector_len);
+            if scalar == Fixed::ZERO {
+                row.for_each(drop);
+                continue;
+            }
+            let scalar = scalar.to_f64() as f32;
+            for (delta, value) in deltas.iter_mut().zip(row) {
+                *delta += value as f32 * scalar;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> MultiItemVariationData<'a> {
+    /// Returns the INDEX containing the packed delta vectors.
+    pub fn delta_sets(&self) -> Result<Index2<'a>, ReadError> {
+        Index2::read(FontData::new(self.raw_delta_sets()))
+    }
+
+    /// Returns the packed deltas for the item at the given index.
+    ///
+    /// The values are stored in region-major order: each region
+    /// contributes a contiguous row of deltas.
+    pub fn delta_set(&self, inner_index: u32) -> Result<TupleValues<'a>, ReadError> {
+        let data = self
+            .delta_sets()?
+            .get(inner_index as usize)
+            .map_err(|_| ReadError::OutOfBounds)?;
+        Ok(TupleValues::new(FontData::new(data)))
+    }
+}
+
+impl SparseVariationRegion<'_> {
+    /// Computes a scalar value for this region and the specified
+    /// normalized variation coordinates.
+    pub fn compute_scalar(&self, coords: &[F2Dot14]) -> Fixed {
+        const ZERO: Fixed = Fixed::ZERO;
+        let mut scalar = Fixed::ONE;
+        for axis in self.region_axes() {
+            let coord = coords
+                .get(axis.axis_index() as usize)
+                .map(|coord| coord.to_fixed())
+                .unwrap_or(ZERO);
+            let start = axis.start().to_fixed();
+            let end = axis.end().to_fixed();
+            let peak = axis.peak().to_fixed();
+            if start > peak || peak > end || peak == ZERO || start < ZERO && end > ZERO {
+                continue;
+            } else if coord < start || coord > end {
+                return ZERO;
+            } else if coord == peak {
+                continue;
+            } else if coord < peak {
+                scalar = scalar.mul_div(coord - start, peak - start);
+            } else {
+                scalar = scalar.mul_div(end - coord, end - peak);
+            }
+        }
+        scalar
+    }
+}
+
+impl Condition<'_> {
+    /// Evaluates the condition for the given normalized variation
+    /// coordinates.
+    ///
+    /// The variation store is used to compute the value of variable
+    /// (format 2) conditions.
+    pub fn evaluate(
+        &self,
+        coords: &[F2Dot14],
+        var_store: Option<&MultiItemVariationStore>,
+    ) -> Result<bool, ReadError> {
+        self.evaluate_impl(coords, var_store, 0)
+    }
+
+    fn evaluate_impl(
+        &self,
+        coords: &[F2Dot14],
+        var_store: Option<&MultiItemVariationStore>,
+        depth: usize,
+    ) -> Result<bool, ReadError> {
+        if depth > CONDITION_NESTING_DEPTH_LIMIT {
+            return Err(ReadError::MalformedData("condition nesting too deep"));
+        }
+        Ok(match self {
+            Self::Format1AxisRange(condition) => {
+                let coord = coords
+                    .get(condition.axis_index() as usize)
+                    .copied()
+                    .unwrap_or_default();
+                coord >= condition.filter_range_min_value()
+                    && coord <= condition.filter_range_max_value()
+            }
+            Self::Format2VariableValue(condition) => {
+                let mut delta = [0f32];
+                if let Some(var_store) = var_store {
+                    var_store.add_deltas(condition.var_index(), coords, &mut delta)?;
+                }
+                condition.default_value() as f32 + delta[0] > 0.0
+            }
+            Self::Format3And(condition) => {
+                for sub_condition in condition.conditions().iter() {
+                    if !sub_condition?.evaluate_impl(coords, var_store, depth + 1)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Self::Format4Or(condition) => {
+                for sub_condition in condition.conditions().iter() {
+                    if sub_condition?.evaluate_impl(coords, var_store, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Self::Format5Negate(condition) => {
+                !condition
+                    .condition()?
+                    .evaluate_impl(coords, var_store, depth + 1)?
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VARC_DATA: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version 1.0
+        0x00, 0x00, 0x00, 0x18, // coverage_offset: 24
+        0x00, 0x00, 0x00, 0x1E, // multi_var_store_offset: 30
+        0x00, 0x00, 0x00, 0x4F, // condition_list_offset: 79
+        0x00, 0x00, 0x00, 0x5F, // axis_indices_list_offset: 95
+        0x00, 0x00, 0x00, 0x68, // var_composite_glyphs_offset: 104
+        // coverage (24)
+        0x00, 0x01, // format 1
+        0x00, 0x01, // glyph_count
+        0x00, 0x05, // glyph 5
+        // multi item variation store (30)
+        0x00, 0x01, // format 1
+        0x00, 0x00, 0x00, 0x0C, // region_list_offset: 12
+        0x00, 0x01, // variation_data_count
+        0x00, 0x00, 0x00, 0x1C, // variation_data_offsets: [28]
+        // sparse region list (42)
+        0x00, 0x01, // region_count
+        0x00, 0x00, 0x00, 0x06, // region_offsets: [6]
+        // sparse region (48)
+        0x00, 0x01, // region_axis_count
+        0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x40, 0x00, // axis 0: 0, 1.0, 1.0
+        // multi item variation data (58)
+        0x00, // format 0
+        0x00, 0x01, // region_index_count
+        0x00, 0x00, // region_indices: [0]
+        0x00, 0x00, 0x00, 0x02, // delta sets INDEX count
+        0x01, // off_size
+        0x01, 0x06, 0x09, // offsets
+        0x41, 0x00, 0x64, 0x00, 0x00, // item 0: [100, 0]
+        0x40, 0x20, 0x00, // item 1: [8192]
+        // condition list (79)
+        0x00, 0x00, 0x00, 0x01, // condition_count
+        0x00, 0x00, 0x00, 0x08, // condition_offsets: [8]
+        0x00, 0x01, // format 1
+        0x00, 0x00, // axis_index
+        0x00, 0x00, // filter_range_min_value: 0.0
+        0x20, 0x00, // filter_range_max_value: 0.5
+        // axis indices list (95)
+        0x00, 0x00, 0x00, 0x01, // count
+        0x01, // off_size
+        0x01, 0x03, // offsets
+        0x00, 0x00, // item 0: [0]
+        // var composite glyphs (104)
+        0x00, 0x00, 0x00, 0x01, // count
+        0x01, // off_size
+        0x01, 0x19, // offsets
+        // component 0
+        0x80, 0xBE, // flags: condition, axes, variations, translate x/y
+        0x00, // condition_index
+        0x00, 0x03, // glyph_id
+        0x00, // axis_indices_index
+        0x40, 0x10, 0x00, // axis_values: [4096]
+        0x01, // axis_values_var_index
+        0x00, // transform_var_index
+        0x00, 0x0A, // translate_x: 10
+        0xFF, 0xF6, // translate_y: -10
+        // component 1
+        0x91, 0x40, // flags: rotation, scale x, 24-bit glyph id
+        0x00, 0x00, 0x04, // glyph_id
+        0x08, 0x00, // rotation: 0.5
+        0x08, 0x00, // scale_x: 2.0
+    ];
+
+    #[test]
+    fn read_components() {
+        let varc = Varc::read(FontData::new(VARC_DATA)).unwrap();
+        assert!(varc.glyph(GlyphId::new(1)).unwrap().is_none());
+        let glyph = varc.glyph(GlyphId::new(5)).unwrap().unwrap();
+        let components = glyph.components().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(components.len(), 2);
+        let first = &components[0];
+        assert_eq!(first.glyph_id(), GlyphId::new(3));
+        assert_eq!(first.condition_index(), Some(0));
+        assert_eq!(first.axis_indices().iter().collect::<Vec<_>>(), [0]);
+        assert_eq!(first.axis_values().iter().collect::<Vec<_>>(), [4096]);
+        assert_eq!(first.axis_values_var_index(), 1);
+        assert_eq!(first.transform_var_index(), 0);
+        assert_eq!(first.transform_value_count(), 2);
+        let transform = first.transform();
+        assert_eq!(
+            (transform.translate_x, transform.translate_y),
+            (10.0, -10.0)
+        );
+        let transform = first.transform_with_deltas(&[100.0, 0.0]);
+        assert_eq!(
+            (transform.translate_x, transform.translate_y),
+            (110.0, -10.0)
+        );
+        let second = &components[1];
+        assert_eq!(second.glyph_id(), GlyphId::new(4));
+        assert_eq!(second.condition_index(), None);
+        assert!(second.axis_values().is_empty());
+        assert_eq!(second.transform_var_index(), NO_VARIATION_INDEX);
+        assert_eq!(
+            second.transform(),
+            DecomposedTransform {
+                rotation: 0.5,
+                scale_x: 2.0,
+                scale_y: 2.0,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn multi_item_deltas() {
+        let varc = Varc::read(FontData::new(VARC_DATA)).unwrap();
+        let store = varc.multi_var_store().unwrap().unwrap();
+        let deltas_at = |var_index, coord: f32, len| {
+            let mut deltas = vec![0.0; len];
+            store
+                .add_deltas(var_index, &[F2Dot14::from_f32(coord)], &mut deltas)
+                .unwrap();
+            deltas
+        };
+        assert_eq!(deltas_at(0, 0.0, 2), [0.0, 0.0]);
+        assert_eq!(deltas_at(0, 0.5, 2), [50.0, 0.0]);
+        assert_eq!(deltas_at(0, 1.0, 2), [100.0, 0.0]);
+        assert_eq!(deltas_at(1, 0.5, 1), [4096.0]);
+        assert_eq!(deltas_at(NO_VARIATION_INDEX, 1.0, 1), [0.0]);
+    }
+
+    #[test]
+    fn conditions() {
+        let varc = Varc::read(FontData::new(VARC_DATA)).unwrap();
+        let condition = varc.condition(0).unwrap();
+        let store = varc.multi_var_store().unwrap().unwrap();
+        let eval = |coord| {
+            condition
+                .evaluate(&[F2Dot14::from_f32(coord)], Some(&store))
+                .unwrap()
+        };
+        assert!(eval(0.0));
+        assert!(eval(0.5));
+        assert!(!eval(0.75));
+        assert!(!eval(-0.25));
+        assert!(varc.condition(1).is_err());
+    }
+
+    #[test]
+    fn u32_var() {
+        let read = |bytes: &[u8]| read_u32_var(&mut FontData::new(bytes).cursor()).ok();
+        assert_eq!(read(&[0x7F]), Some(0x7F));
+        assert_eq!(read(&[0x80, 0xBE]), Some(0xBE));
+        assert_eq!(read(&[0xBF, 0xFF]), Some(0x3FFF));
+        assert_eq!(read(&[0xC1, 0x02, 0x03]), Some(0x010203));
+        assert_eq!(read(&[0xE1, 0x02, 0x03, 0x04]), Some(0x01020304));
+        assert_eq!(read(&[0xF0, 0xFF, 0xFF, 0xFF, 0xFF]), Some(u32::MAX));
+        assert_eq!(read(&[0xC1, 0x02]), None);
+    }
+
+    #[test]
+    fn tuple_values() {
+        let data = [
+            0x01, 0x05, 0xFB, // two bytes: [5, -5]
+            0x81, // two zeros
+            0x40, 0x01, 0x00, // one word: [256]
+            0xC0, 0x00, 0x01, 0x00, 0x00, // one long: [65536]
+        ];
+        let values = TupleValues::new(FontData::new(&data));
+        assert_eq!(values.len(), 6);
+        assert_eq!(values.iter().collect::<Vec<_>>(), [5, -5, 0, 0, 256, 65536]);
+        let mut cursor = FontData::new(&data).cursor();
+        let values = TupleValues::read(&mut cursor, 3).unwrap();
+        assert_eq!(values.iter().collect::<Vec<_>>(), [5, -5, 0]);
+        // the cursor is positioned after the run containing the last value
+        assert_eq!(cursor.position().unwrap(), 4);
+    }
+}
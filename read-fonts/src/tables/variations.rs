@@ -2,6 +2,8 @@
 
 include!("../../generated/generated_variations.rs");
 
+use core::iter::Skip;
+
 /// Outer and inner indices for reading from an [ItemVariationStore].
 #[derive(Copy, Clone, Debug)]
 pub struct DeltaSetIndex {
@@ -498,6 +500,334 @@ impl<'a> Iterator for TupleVariationHeaderIter<'a> {
     }
 }
 
+/// A trait for the type of deltas produced by a [`TupleVariation`].
+///
+/// Glyph variations (gvar) produce deltas for points with both x and y
+/// components, while CVT variations (cvar) produce a single value per
+/// entry in the control value table.
+pub trait TupleDelta: Sized + Copy {
+    /// Returns `true` if the delta is a point and requires reading both
+    /// x and y values from the packed delta data.
+    fn is_point() -> bool;
+
+    /// Creates a new delta for the given position and values.
+    ///
+    /// If [`is_point`](Self::is_point) is false, the `y` value is ignored.
+    fn new(position: u16, x: i16, y: i16) -> Self;
+}
+
+/// Variation data described by a set of [`TupleVariation`]s.
+///
+/// This is the common representation of the [tuple variation store][store]
+/// used by both the 'gvar' and 'cvar' tables, and is generic over the type
+/// of deltas it produces.
+///
+/// [store]: https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#tuple-variation-store
+#[derive(Clone)]
+pub struct TupleVariationData<'a, T> {
+    pub(crate) axis_count: u16,
+    pub(crate) shared_tuples: Option<ComputedArray<'a, Tuple<'a>>>,
+    pub(crate) shared_point_numbers: Option<PackedPointNumbers<'a>>,
+    pub(crate) tuple_count: TupleVariationCount,
+    // the data for all the tuple variation headers
+    pub(crate) header_data: FontData<'a>,
+    // the data for all the tuple bodies
+    pub(crate) serialized_data: FontData<'a>,
+    pub(crate) _marker: core::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TupleVariationData<'a, T>
+where
+    T: TupleDelta,
+{
+    /// Creates a new set of variation data.
+    ///
+    /// The `data` argument should point to the start of the serialized data,
+    /// which may begin with shared point numbers, depending on the flags in
+    /// `tuple_count`.
+    pub(crate) fn from_parts(
+        axis_count: u16,
+        shared_tuples: Option<ComputedArray<'a, Tuple<'a>>>,
+        tuple_count: TupleVariationCount,
+        header_data: FontData<'a>,
+        data: FontData<'a>,
+    ) -> Self {
+        // if there are shared point numbers, get them now
+        let (shared_point_numbers, serialized_data) = if tuple_count.shared_point_numbers() {
+            let (packed, data) = PackedPointNumbers::split_off_front(data);
+            (Some(packed), data)
+        } else {
+            (None, data)
+        };
+        Self {
+            axis_count,
+            shared_tuples,
+            shared_point_numbers,
+            tuple_count,
+            header_data,
+            serialized_data,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    /// Return an iterator over all of the variation tuples.
+    pub fn tuples(&self) -> TupleVariationIter<'a, T> {
+        TupleVariationIter {
+            current: 0,
+            parent: self.clone(),
+            header_iter: TupleVariationHeaderIter::new(
+                self.header_data,
+                self.tuple_count(),
+                self.axis_count,
+            ),
+            serialized_data: self.serialized_data,
+        }
+    }
+
+    /// Returns an iterator over all of the pairs of (variation tuple, scalar)
+    /// that are active for the given set of normalized coordinates.
+    pub fn active_tuples_at(
+        &self,
+        coords: &'a [F2Dot14],
+    ) -> impl Iterator<Item = (TupleVariation<'a, T>, Fixed)> + 'a
+    where
+        T: 'a,
+    {
+        self.tuples().filter_map(|tuple| {
+            let scaler = tuple.compute_scalar(coords)?;
+            Some((tuple, scaler))
+        })
+    }
+
+    pub(crate) fn tuple_count(&self) -> usize {
+        self.tuple_count.count() as usize
+    }
+}
+
+/// An iterator over the [`TupleVariation`]s in some [`TupleVariationData`].
+pub struct TupleVariationIter<'a, T> {
+    current: usize,
+    parent: TupleVariationData<'a, T>,
+    header_iter: TupleVariationHeaderIter<'a>,
+    serialized_data: FontData<'a>,
+}
+
+impl<'a, T> TupleVariationIter<'a, T>
+where
+    T: TupleDelta,
+{
+    fn next_tuple(&mut self) -> Option<TupleVariation<'a, T>> {
+        if self.parent.tuple_count() == self.current {
+            return None;
+        }
+        self.current += 1;
+
+        // FIXME: is it okay to discard an error here?
+        let header = self.header_iter.next()?.ok()?;
+        let data_len = header.variation_data_size() as usize;
+        let var_data = self.serialized_data.take_up_to(data_len)?;
+
+        let (point_numbers, packed_deltas) = if header.tuple_index().private_point_numbers() {
+            PackedPointNumbers::split_off_front(var_data)
+        } else {
+            (self.parent.shared_point_numbers.clone()?, var_data)
+        };
+        Some(TupleVariation {
+            axis_count: self.parent.axis_count,
+            header,
+            shared_tuples: self.parent.shared_tuples.clone(),
+            packed_deltas: PackedDeltas::new(packed_deltas),
+            point_numbers,
+            _marker: core::marker::PhantomData,
+        })
+    }
+}
+
+impl<'a, T> Iterator for TupleVariationIter<'a, T>
+where
+    T: TupleDelta,
+{
+    type Item = TupleVariation<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_tuple()
+    }
+}
+
+/// A single set of tuple variation data
+#[derive(Clone)]
+pub struct TupleVariation<'a, T> {
+    axis_count: u16,
+    header: TupleVariationHeader<'a>,
+    shared_tuples: Option<ComputedArray<'a, Tuple<'a>>>,
+    packed_deltas: PackedDeltas<'a>,
+    point_numbers: PackedPointNumbers<'a>,
+    _marker: core::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TupleVariation<'a, T>
+where
+    T: TupleDelta,
+{
+    /// Returns true if this tuple provides deltas for all points in a glyph
+    /// (or for all entries in the CVT).
+    pub fn has_deltas_for_all_points(&self) -> bool {
+        self.point_numbers.count() == 0
+    }
+
+    pub fn point_numbers(&self) -> PackedPointNumbersIter<'a> {
+        self.point_numbers.iter()
+    }
+
+    /// Returns the 'peak' tuple for this variation
+    pub fn peak(&self) -> Tuple<'a> {
+        self.header
+            .tuple_index()
+            .tuple_records_index()
+            .and_then(|idx| self.shared_tuples.as_ref()?.get(idx as usize).ok())
+            .or_else(|| self.header.peak_tuple())
+            .unwrap_or_default()
+    }
+
+    /// Returns the start and end tuples of the intermediate region for this
+    /// variation, if it has one.
+    ///
+    /// When absent, the region spans from zero to the peak on each axis.
+    pub fn intermediate(&self) -> Option<(Tuple<'a>, Tuple<'a>)> {
+        Some((
+            self.header.intermediate_start_tuple()?,
+            self.header.intermediate_end_tuple()?,
+        ))
+    }
+
+    // transcribed from pinot/moscato
+    /// Compute the scalar for a this tuple at a given point in design space.
+    ///
+    /// The `coords` slice must be of lesser or equal length to the number of axes.
+    /// If it is less, missing (trailing) axes will be assumed to have zero values.
+    ///
+    /// Returns `None` if this tuple is not applicable at the provided coordinates
+    /// (e.g. if the resulting scalar is zero).
+    pub fn compute_scalar(&self, coords: &[F2Dot14]) -> Option<Fixed> {
+        const ZERO: Fixed = Fixed::ZERO;
+        let mut scalar = Fixed::ONE;
+        let peak = self.peak();
+        let inter_start = self.header.intermediate_start_tuple();
+        let inter_end = self.header.intermediate_end_tuple();
+        if peak.len() != self.axis_count as usize {
+            return None;
+        }
+
+        for i in 0..self.axis_count {
+            let i = i as usize;
+            let coord = coords.get(i).copied().unwrap_or_default().to_fixed();
+            let peak = peak.get(i).unwrap_or_default().to_fixed();
+            if peak == ZERO || peak == coord {
+                continue;
+            }
+
+            if coord == ZERO {
+                return None;
+            }
+
+            if let (Some(inter_start), Some(inter_end)) = (&inter_start, &inter_end) {
+                let start = inter_start.get(i).unwrap_or_default().to_fixed();
+                let end = inter_end.get(i).unwrap_or_default().to_fixed();
+                if coord <= start || coord >= end {
+                    return None;
+                }
+                if coord < peak {
+                    scalar = scalar.mul_div(coord - start, peak - start);
+                } else {
+                    scalar = scalar.mul_div(end - coord, end - peak);
+                }
+            } else {
+                if coord < peak.min(ZERO) || coord > peak.max(ZERO) {
+                    return None;
+                }
+                scalar = scalar.mul_div(coord, peak);
+            }
+        }
+        Some(scalar)
+    }
+
+    /// Iterate over the deltas for this tuple.
+    ///
+    /// This does not account for scaling. Returns only explicitly encoded
+    /// deltas, e.g. an omission by IUP will not be present.
+    pub fn deltas(&self) -> TupleDeltaIter<'a, T> {
+        TupleDeltaIter::new(&self.point_numbers, &self.packed_deltas)
+    }
+}
+
+/// An iterator over the deltas for a [`TupleVariation`].
+#[derive(Clone, Debug)]
+pub struct TupleDeltaIter<'a, T> {
+    pub cur: usize,
+    // if None all points get deltas, if Some specifies subset of points that do
+    points: Option<PackedPointNumbersIter<'a>>,
+    next_point: usize,
+    x_iter: DeltaRunIter<'a>,
+    // only present for point deltas, where y values follow all x values
+    y_iter: Option<Skip<DeltaRunIter<'a>>>,
+    _marker: core::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TupleDeltaIter<'a, T>
+where
+    T: TupleDelta,
+{
+    fn new(points: &PackedPointNumbers<'a>, deltas: &PackedDeltas<'a>) -> TupleDeltaIter<'a, T> {
+        let mut points = points.iter();
+        let next_point = points.next();
+        let y_iter = T::is_point().then(|| {
+            let num_encoded_points = deltas.count() / 2; // x and y encoded independently
+            deltas.iter().skip(num_encoded_points)
+        });
+        TupleDeltaIter {
+            cur: 0,
+            points: next_point.map(|_| points),
+            next_point: next_point.unwrap_or_default() as usize,
+            x_iter: deltas.iter(),
+            y_iter,
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for TupleDeltaIter<'a, T>
+where
+    T: TupleDelta,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (position, dx, dy) = loop {
+            let position = if let Some(points) = &mut self.points {
+                // if we have points then result is sparse; only some points have deltas
+                if self.cur > self.next_point {
+                    self.next_point = points.next()? as usize;
+                }
+                self.next_point
+            } else {
+                // no points, every point has a delta. Just take the next one.
+                self.cur
+            };
+            if position == self.cur {
+                let dx = self.x_iter.next()?;
+                let dy = match &mut self.y_iter {
+                    Some(y_iter) => y_iter.next()?,
+                    None => 0,
+                };
+                break (position, dx, dy);
+            }
+            self.cur += 1;
+        };
+        self.cur += 1;
+        Some(T::new(position as u16, dx, dy))
+    }
+}
+
 impl EntryFormat {
     pub fn entry_size(self) -> u8 {
         ((self.bits() & Self::MAP_ENTRY_SIZE_MASK.bits()) >> 4) + 1
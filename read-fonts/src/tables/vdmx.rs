@@ -0,0 +1,105 @@
+//! The [Vertical Device Metrics](https://learn.microsoft.com/en-us/typography/opentype/spec/vdmx) table
+
+include!("../../generated/generated_vdmx.rs");
+
+impl<'a> Vdmx<'a> {
+    /// Returns the group for the first ratio range that matches the given
+    /// aspect ratio.
+    ///
+    /// The aspect ratio is described as the ratio of horizontal to vertical
+    /// device resolution. For devices with square pixels, both values
+    /// should be 1.
+    pub fn group_for_ratio(&self, x_ratio: u8, y_ratio: u8) -> Option<VdmxGroup<'a>> {
+        let idx = self
+            .rat_range()
+            .iter()
+            .position(|range| range.matches(x_ratio, y_ratio))?;
+        self.vdmx_group_offsets()
+            .get(idx)?
+            .get()
+            .resolve(self.offset_data())
+            .ok()
+    }
+
+    /// Returns the `(y_max, y_min)` extents, in pixels, for the given pixel
+    /// height and aspect ratio.
+    pub fn extents(&self, y_pel_height: u16, x_ratio: u8, y_ratio: u8) -> Option<(i16, i16)> {
+        let entry = self
+            .group_for_ratio(x_ratio, y_ratio)?
+            .entry_for_height(y_pel_height)?;
+        Some((entry.y_max(), entry.y_min()))
+    }
+}
+
+impl RatioRange {
+    /// Returns true if this range applies to the given aspect ratio.
+    ///
+    /// A record with all ratio values set to zero is a default grouping that
+    /// matches any aspect ratio.
+    pub fn matches(&self, x_ratio: u8, y_ratio: u8) -> bool {
+        if self.x_ratio == 0 && self.y_start_ratio == 0 && self.y_end_ratio == 0 {
+            return true;
+        }
+        // The ratio y/x is in the range [y_start/x_ratio, y_end/x_ratio]
+        let (x, y) = (x_ratio as u32, y_ratio as u32);
+        let range_x = self.x_ratio as u32;
+        let start = self.y_start_ratio as u32 * x;
+        let end = self.y_end_ratio as u32 * x;
+        (start..=end).contains(&(y * range_x))
+    }
+}
+
+impl<'a> VdmxGroup<'a> {
+    /// Returns the entry for the given pixel height, if present.
+    pub fn entry_for_height(&self, y_pel_height: u16) -> Option<&'a VTable> {
+        let entries = self.entries();
+        entries
+            .binary_search_by_key(&y_pel_height, |entry| entry.y_pel_height())
+            .ok()
+            .map(|idx| &entries[idx])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VDMX_DATA: &[u8] = &[
+        0x00, 0x01, // version
+        0x00, 0x01, // num_recs
+        0x00, 0x02, // num_ratios
+        0x01, 0x01, 0x01, 0x01, // ratio 0: 1:1
+        0x00, 0x00, 0x00, 0x00, // ratio 1: default
+        0x00, 0x12, 0x00, 0x12, // group offsets
+        0x00, 0x02, // recs
+        0x0A, // startsz
+        0x0B, // endsz
+        0x00, 0x0A, 0x00, 0x09, 0xFF, 0xFD, // 10: 9, -3
+        0x00, 0x0B, 0x00, 0x0A, 0xFF, 0xFC, // 11: 10, -4
+    ];
+
+    #[test]
+    fn read_extents() {
+        let vdmx = Vdmx::read(FontData::new(VDMX_DATA)).unwrap();
+        assert_eq!(vdmx.num_ratios(), 2);
+        assert_eq!(vdmx.extents(10, 1, 1), Some((9, -3)));
+        assert_eq!(vdmx.extents(11, 1, 1), Some((10, -4)));
+        assert_eq!(vdmx.extents(12, 1, 1), None);
+        // matches the default ratio record
+        assert_eq!(vdmx.extents(10, 2, 1), Some((9, -3)));
+    }
+
+    #[test]
+    fn ratio_matching() {
+        let range = RatioRange {
+            b_char_set: 1,
+            x_ratio: 2,
+            y_start_ratio: 1,
+            y_end_ratio: 2,
+        };
+        assert!(range.matches(1, 1));
+        assert!(range.matches(2, 1));
+        assert!(!range.matches(1, 2));
+        assert!(!range.matches(3, 1));
+    }
+}
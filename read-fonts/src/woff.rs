@@ -0,0 +1,352 @@
+//! Web Open Font Format containers.
+//!
+//! This module provides parsing of the [WOFF] and [WOFF2] file headers and,
+//! with the `woff` feature enabled, decoders that convert a web font back
+//! into a standard sfnt (or collection) buffer that can be loaded with
+//! [`FontRef`](crate::FontRef) or [`FileRef`](crate::FileRef).
+//!
+//! [WOFF]: https://www.w3.org/TR/WOFF/
+//! [WOFF2]: https://www.w3.org/TR/WOFF2/
+
+#[cfg(feature = "woff")]
+mod woff2;
+
+include!("../generated/generated_woff.rs");
+
+/// The signature at the start of a WOFF file.
+pub const WOFF_SIGNATURE: Tag = Tag::new(b"wOFF");
+
+/// The signature at the start of a WOFF2 file.
+pub const WOFF2_SIGNATURE: Tag = Tag::new(b"wOF2");
+
+/// The container format of a web font.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WebFontFormat {
+    /// WOFF 1.0, with zlib compressed tables.
+    Woff,
+    /// WOFF 2.0, with Brotli compressed and transformed tables.
+    Woff2,
+}
+
+/// Reference to the content of a WOFF or WOFF2 file.
+#[derive(Clone)]
+pub struct WebFontRef<'a> {
+    data: FontData<'a>,
+    format: WebFontFormat,
+}
+
+impl<'a> WebFontRef<'a> {
+    /// Creates a new reference to a web font.
+    ///
+    /// This only validates the signature and header; the table data is
+    /// not touched until the font is decoded.
+    pub fn new(data: &'a [u8]) -> Result<Self, ReadError> {
+        let data = FontData::new(data);
+        let format = match data.read_at::<Tag>(0)? {
+            WOFF_SIGNATURE => {
+                WoffHeader::read(data)?;
+                WebFontFormat::Woff
+            }
+            WOFF2_SIGNATURE => {
+                Woff2Header::read(data)?;
+                WebFontFormat::Woff2
+            }
+            _ => return Err(ReadError::MalformedData("not a WOFF or WOFF2 file")),
+        };
+        Ok(Self { data, format })
+    }
+
+    /// Returns the container format of the font.
+    pub fn format(&self) -> WebFontFormat {
+        self.format
+    }
+
+    /// Returns the raw data of the web font.
+    pub fn data(&self) -> FontData<'a> {
+        self.data
+    }
+
+    /// Returns the sfnt version of the wrapped font.
+    ///
+    /// This is `0x00010000` for TrueType outlines, 'OTTO' for CFF outlines
+    /// and 'ttcf' for a WOFF2 encoded font collection.
+    pub fn flavor(&self) -> u32 {
+        // both headers have the flavor immediately after the signature
+        self.data.read_at(4).unwrap_or_default()
+    }
+
+    /// Decodes the web font into an sfnt or font collection buffer.
+    #[cfg(feature = "woff")]
+    pub fn decode(&self) -> Result<Vec<u8>, ReadError> {
+        match self.format {
+            WebFontFormat::Woff => decode_woff(self.data.as_bytes()),
+            WebFontFormat::Woff2 => decode_woff2(self.data.as_bytes()),
+        }
+    }
+}
+
+/// Decodes a WOFF or WOFF2 file into an sfnt or font collection buffer.
+#[cfg(feature = "woff")]
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    WebFontRef::new(data)?.decode()
+}
+
+/// Decodes a WOFF file into an sfnt buffer.
+#[cfg(feature = "woff")]
+pub fn decode_woff(data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let data = FontData::new(data);
+    let header = WoffHeader::read(data)?;
+    if header.signature() != WOFF_SIGNATURE {
+        return Err(ReadError::MalformedData("not a WOFF file"));
+    }
+    let mut tables = Vec::with_capacity(header.num_tables() as usize);
+    for entry in header.table_directory() {
+        let start = entry.offset() as usize;
+        let comp_len = entry.comp_length() as usize;
+        let orig_len = entry.orig_length() as usize;
+        let compressed = data
+            .slice(start..start.checked_add(comp_len).ok_or(ReadError::OutOfBounds)?)
+            .ok_or(ReadError::OutOfBounds)?
+            .as_bytes();
+        let table_data = match comp_len.cmp(&orig_len) {
+            std::cmp::Ordering::Equal => compressed.to_vec(),
+            std::cmp::Ordering::Less => {
+                let decompressed =
+                    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, orig_len)
+                        .map_err(|_| ReadError::MalformedData("invalid zlib table data"))?;
+                if decompressed.len() != orig_len {
+                    return Err(ReadError::MalformedData("bad decompressed table length"));
+                }
+                decompressed
+            }
+            std::cmp::Ordering::Greater => {
+                return Err(ReadError::MalformedData(
+                    "compressed table larger than original",
+                ))
+            }
+        };
+        tables.push((entry.tag(), table_data));
+    }
+    let indices = (0..tables.len()).collect();
+    Ok(sfnt::assemble(&[(header.flavor(), indices)], tables, false))
+}
+
+/// Decodes a WOFF2 file into an sfnt or font collection buffer.
+///
+/// This reverses the `glyf`, `loca` and `hmtx` transforms. Fonts with the
+/// 'ttcf' flavor are decoded into a font collection.
+#[cfg(feature = "woff")]
+pub fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    woff2::decode(data)
+}
+
+/// Writing of the decoded tables as an sfnt or collection.
+#[cfg(feature = "woff")]
+mod sfnt {
+    use font_types::Tag;
+
+    use crate::checksum::{checksum, CHECKSUM_MAGIC};
+
+    const TTC_TAG: Tag = Tag::new(b"ttcf");
+    const HEAD: Tag = Tag::new(b"head");
+    /// Offset of checkSumAdjustment in the head table.
+    const CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+
+    /// Writes the tables as a single font, or as a collection if
+    /// `collection` is true.
+    ///
+    /// Each font is described by its sfnt version and the indices of the
+    /// tables it contains. Tables shared between fonts are only written once.
+    pub(super) fn assemble(
+        fonts: &[(u32, Vec<usize>)],
+        mut tables: Vec<(Tag, Vec<u8>)>,
+        collection: bool,
+    ) -> Vec<u8> {
+        // the head checksum is computed with a zero adjustment
+        for (tag, data) in tables.iter_mut() {
+            if *tag == HEAD {
+                if let Some(adjustment) =
+                    data.get_mut(CHECKSUM_ADJUSTMENT_OFFSET..CHECKSUM_ADJUSTMENT_OFFSET + 4)
+                {
+                    adjustment.fill(0);
+                }
+            }
+        }
+        let checksums = tables
+            .iter()
+            .map(|(_, data)| checksum(data))
+            .collect::<Vec<_>>();
+        let header_len = if collection { 12 + 4 * fonts.len() } else { 0 };
+        let mut directory_offsets = Vec::with_capacity(fonts.len());
+        let mut pos = header_len;
+        for (_, indices) in fonts {
+            directory_offsets.push(pos);
+            pos += 12 + 16 * indices.len();
+        }
+        let mut table_offsets = Vec::with_capacity(tables.len());
+        for (_, data) in &tables {
+            table_offsets.push(pos);
+            pos += (data.len() + 3) & !3;
+        }
+        let mut out = Vec::with_capacity(pos);
+        if collection {
+            out.extend_from_slice(&TTC_TAG.to_be_bytes());
+            out.extend_from_slice(&0x00010000u32.to_be_bytes());
+            out.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+            for offset in &directory_offsets {
+                out.extend_from_slice(&(*offset as u32).to_be_bytes());
+            }
+        }
+        let mut adjustments = Vec::with_capacity(fonts.len());
+        for (flavor, indices) in fonts {
+            let mut sorted = indices.clone();
+            sorted.sort_by_key(|ix| tables[*ix].0);
+            let start = out.len();
+            let num_tables = sorted.len() as u16;
+            // searchRange etc. are computed with 16 byte records
+            let entry_selector = (num_tables.max(1)).ilog2() as u16;
+            let search_range = (1u16 << entry_selector).wrapping_mul(16);
+            let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
+            out.extend_from_slice(&flavor.to_be_bytes());
+            for value in [num_tables, search_range, entry_selector, range_shift] {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            for ix in &sorted {
+                out.extend_from_slice(&tables[*ix].0.to_be_bytes());
+                out.extend_from_slice(&checksums[*ix].to_be_bytes());
+                out.extend_from_slice(&(table_offsets[*ix] as u32).to_be_bytes());
+                out.extend_from_slice(&(tables[*ix].1.len() as u32).to_be_bytes());
+            }
+            let font_sum = sorted.iter().fold(checksum(&out[start..]), |sum, ix| {
+                sum.wrapping_add(checksums[*ix])
+            });
+            let head = sorted.iter().copied().find(|ix| tables[*ix].0 == HEAD);
+            adjustments.push((head, CHECKSUM_MAGIC.wrapping_sub(font_sum)));
+        }
+        for (head, adjustment) in adjustments {
+            if let Some(data) = head.and_then(|ix| {
+                tables[ix]
+                    .1
+                    .get_mut(CHECKSUM_ADJUSTMENT_OFFSET..CHECKSUM_ADJUSTMENT_OFFSET + 4)
+            }) {
+                data.copy_from_slice(&adjustment.to_be_bytes());
+            }
+        }
+        for (_, data) in &tables {
+            out.extend_from_slice(data);
+            out.resize((out.len() + 3) & !3, 0);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "woff"))]
+mod tests {
+    use super::*;
+    use crate::test_helpers::BeBuffer;
+    use crate::{FileRef, FontRef, TableProvider};
+
+    /// Builds a WOFF file from an sfnt, compressing every table that
+    /// gets smaller.
+    fn make_woff(sfnt: &[u8]) -> Vec<u8> {
+        let font = FontRef::new(sfnt).unwrap();
+        let records = font.table_directory.table_records();
+        let mut directory = BeBuffer::new();
+        let mut table_data = Vec::new();
+        let header_len = 44 + 20 * records.len();
+        for record in records {
+            let data = font.table_data(record.tag()).unwrap().as_bytes();
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+            let stored = if compressed.len() < data.len() {
+                compressed.as_slice()
+            } else {
+                data
+            };
+            directory = directory
+                .push(record.tag())
+                .push((header_len + table_data.len()) as u32)
+                .push(stored.len() as u32)
+                .push(data.len() as u32)
+                .push(record.checksum());
+            table_data.extend_from_slice(stored);
+            table_data.resize((table_data.len() + 3) & !3, 0);
+        }
+        let header = BeBuffer::new()
+            .push(WOFF_SIGNATURE)
+            .push(font.table_directory.sfnt_version())
+            .push((header_len + table_data.len()) as u32)
+            .push(records.len() as u16)
+            .push(0u16)
+            .push(sfnt.len() as u32)
+            .extend([1u16, 0])
+            .extend([0u32; 5]);
+        let mut woff = header.to_vec();
+        woff.extend_from_slice(&directory);
+        woff.extend_from_slice(&table_data);
+        woff
+    }
+
+    /// Checks that two fonts contain the same tables, ignoring
+    /// checkSumAdjustment.
+    fn assert_same_tables(decoded: &FontRef, expected: &FontRef) {
+        let tags = |font: &FontRef| {
+            font.table_directory
+                .table_records()
+                .iter()
+                .map(|rec| rec.tag())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tags(decoded), tags(expected));
+        for tag in tags(expected) {
+            let decoded_data = decoded.table_data(tag).unwrap();
+            let expected_data = expected.table_data(tag).unwrap();
+            if tag == Tag::new(b"head") {
+                assert_eq!(decoded_data.as_bytes()[..8], expected_data.as_bytes()[..8]);
+                assert_eq!(
+                    decoded_data.as_bytes()[12..],
+                    expected_data.as_bytes()[12..]
+                );
+            } else {
+                assert_eq!(decoded_data.as_bytes(), expected_data.as_bytes(), "{tag}");
+            }
+        }
+    }
+
+    #[test]
+    fn decode_woff_round_trip() {
+        let sfnt = font_test_data::VAZIRMATN_VAR;
+        let woff = make_woff(sfnt);
+        let decoded = decode(&woff).unwrap();
+        let decoded_font = FontRef::new(&decoded).unwrap();
+        assert_same_tables(&decoded_font, &FontRef::new(sfnt).unwrap());
+        // table checksums and the checksum adjustment are consistent
+        assert_eq!(decoded_font.checksum_mismatches().count(), 0);
+        assert_eq!(
+            crate::checksum::checksum(&decoded),
+            crate::checksum::CHECKSUM_MAGIC
+        );
+    }
+
+    #[test]
+    fn woff_bad_compressed_length() {
+        let mut woff = make_woff(font_test_data::VAZIRMATN_VAR);
+        // set comp_length of the first table larger than orig_length
+        let orig_length = u32::from_be_bytes(woff[44 + 12..44 + 16].try_into().unwrap());
+        woff[44 + 8..44 + 12].copy_from_slice(&(orig_length + 1).to_be_bytes());
+        assert!(decode(&woff).is_err());
+    }
+
+    #[test]
+    fn web_font_ref() {
+        let woff = make_woff(font_test_data::VAZIRMATN_VAR);
+        let web_font = WebFontRef::new(&woff).unwrap();
+        assert_eq!(web_font.format(), WebFontFormat::Woff);
+        assert_eq!(web_font.flavor(), 0x00010000);
+        let decoded = web_font.decode().unwrap();
+        let font = FontRef::new(&decoded).unwrap();
+        assert_eq!(font.maxp().unwrap().num_glyphs(), 4);
+        // fonts must be decoded before use
+        assert!(FileRef::new(&woff).is_err());
+        assert!(FontRef::from_index(&woff, 0).is_err());
+    }
+}
@@ -0,0 +1,929 @@
+//! WOFF2 decoding.
+//!
+//! See <https://www.w3.org/TR/WOFF2/> for the format and
+//! <https://github.com/google/woff2> for the reference decoder.
+
+use std::io::Read;
+
+use font_types::Tag;
+
+use super::{sfnt, Woff2Header, WOFF2_SIGNATURE};
+use crate::{
+    font_data::Cursor,
+    tables::{
+        glyf::{CompositeGlyphFlags, SimpleGlyphFlags},
+        hhea::Hhea,
+        maxp::Maxp,
+    },
+    FontData, FontRead, ReadError,
+};
+
+const GLYF: Tag = Tag::new(b"glyf");
+const LOCA: Tag = Tag::new(b"loca");
+const HMTX: Tag = Tag::new(b"hmtx");
+const HHEA: Tag = Tag::new(b"hhea");
+const MAXP: Tag = Tag::new(b"maxp");
+const TTC_FLAVOR: u32 = u32::from_be_bytes(*b"ttcf");
+
+/// Tags for the table directory, indexed by the low six bits of the flags.
+///
+/// An index of 63 means that an explicit tag follows the flags.
+const KNOWN_TAGS: [Tag; 63] = [
+    Tag::new(b"cmap"),
+    Tag::new(b"head"),
+    Tag::new(b"hhea"),
+    Tag::new(b"hmtx"),
+    Tag::new(b"maxp"),
+    Tag::new(b"name"),
+    Tag::new(b"OS/2"),
+    Tag::new(b"post"),
+    Tag::new(b"cvt "),
+    Tag::new(b"fpgm"),
+    Tag::new(b"glyf"),
+    Tag::new(b"loca"),
+    Tag::new(b"prep"),
+    Tag::new(b"CFF "),
+    Tag::new(b"VORG"),
+    Tag::new(b"EBDT"),
+    Tag::new(b"EBLC"),
+    Tag::new(b"gasp"),
+    Tag::new(b"hdmx"),
+    Tag::new(b"kern"),
+    Tag::new(b"LTSH"),
+    Tag::new(b"PCLT"),
+    Tag::new(b"VDMX"),
+    Tag::new(b"vhea"),
+    Tag::new(b"vmtx"),
+    Tag::new(b"BASE"),
+    Tag::new(b"GDEF"),
+    Tag::new(b"GPOS"),
+    Tag::new(b"GSUB"),
+    Tag::new(b"EBSC"),
+    Tag::new(b"JSTF"),
+    Tag::new(b"MATH"),
+    Tag::new(b"CBDT"),
+    Tag::new(b"CBLC"),
+    Tag::new(b"COLR"),
+    Tag::new(b"CPAL"),
+    Tag::new(b"SVG "),
+    Tag::new(b"sbix"),
+    Tag::new(b"acnt"),
+    Tag::new(b"avar"),
+    Tag::new(b"bdat"),
+    Tag::new(b"bloc"),
+    Tag::new(b"bsln"),
+    Tag::new(b"cvar"),
+    Tag::new(b"fdsc"),
+    Tag::new(b"feat"),
+    Tag::new(b"fmtx"),
+    Tag::new(b"fvar"),
+    Tag::new(b"gvar"),
+    Tag::new(b"hsty"),
+    Tag::new(b"just"),
+    Tag::new(b"lcar"),
+    Tag::new(b"mort"),
+    Tag::new(b"morx"),
+    Tag::new(b"opbd"),
+    Tag::new(b"prop"),
+    Tag::new(b"trak"),
+    Tag::new(b"Zapf"),
+    Tag::new(b"Silf"),
+    Tag::new(b"Glat"),
+    Tag::new(b"Gloc"),
+    Tag::new(b"Feat"),
+    Tag::new(b"Sill"),
+];
+
+/// Size of the fixed WOFF2 header.
+const HEADER_LEN: usize = 48;
+
+/// An entry in the WOFF2 table directory.
+#[derive(Clone, Debug)]
+struct TableEntry {
+    tag: Tag,
+    transform_version: u8,
+    orig_length: u32,
+    /// Length of the table in the decompressed stream.
+    stream_length: u32,
+}
+
+impl TableEntry {
+    fn read(cursor: &mut Cursor) -> Result<Self, ReadError> {
+        let flags: u8 = cursor.read()?;
+        let tag = match flags & 0x3F {
+            63 => cursor.read()?,
+            ix => KNOWN_TAGS[ix as usize],
+        };
+        let transform_version = flags >> 6;
+        let orig_length = read_base128(cursor)?;
+        let stream_length = if is_transformed(tag, transform_version) {
+            let transform_length = read_base128(cursor)?;
+            let known = match tag {
+                GLYF => transform_version == 0,
+                LOCA => transform_version == 0 && transform_length == 0,
+                HMTX => transform_version == 1,
+                _ => false,
+            };
+            if !known {
+                return Err(ReadError::MalformedData("unknown WOFF2 table transform"));
+            }
+            transform_length
+        } else {
+            orig_length
+        };
+        Ok(Self {
+            tag,
+            transform_version,
+            orig_length,
+            stream_length,
+        })
+    }
+
+    fn is_transformed(&self) -> bool {
+        is_transformed(self.tag, self.transform_version)
+    }
+}
+
+/// The null transform is version 3 for glyf and loca and version 0 for all
+/// other tables.
+fn is_transformed(tag: Tag, transform_version: u8) -> bool {
+    match tag {
+        GLYF | LOCA => transform_version != 3,
+        _ => transform_version != 0,
+    }
+}
+
+/// Reads a UIntBase128 encoded value.
+fn read_base128(cursor: &mut Cursor) -> Result<u32, ReadError> {
+    let mut accum = 0u32;
+    for i in 0..5 {
+        let byte: u8 = cursor.read()?;
+        // leading zeros are not allowed
+        if i == 0 && byte == 0x80 {
+            return Err(ReadError::MalformedData("invalid UIntBase128 value"));
+        }
+        if accum & 0xFE00_0000 != 0 {
+            return Err(ReadError::MalformedData("UIntBase128 value overflows"));
+        }
+        accum = (accum << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(accum);
+        }
+    }
+    Err(ReadError::MalformedData(
+        "UIntBase128 value exceeds 5 bytes",
+    ))
+}
+
+/// Reads a 255UInt16 encoded value.
+fn read_255_u16(cursor: &mut Cursor) -> Result<u16, ReadError> {
+    const WORD_CODE: u8 = 253;
+    const ONE_MORE_BYTE_CODE2: u8 = 254;
+    const ONE_MORE_BYTE_CODE1: u8 = 255;
+    const LOWEST_U_CODE: u16 = 253;
+    let code: u8 = cursor.read()?;
+    Ok(match code {
+        WORD_CODE => cursor.read()?,
+        ONE_MORE_BYTE_CODE1 => cursor.read::<u8>()? as u16 + LOWEST_U_CODE,
+        ONE_MORE_BYTE_CODE2 => cursor.read::<u8>()? as u16 + LOWEST_U_CODE * 2,
+        _ => code as u16,
+    })
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let data = FontData::new(data);
+    let header = Woff2Header::read(data)?;
+    if header.signature() != WOFF2_SIGNATURE {
+        return Err(ReadError::MalformedData("not a WOFF2 file"));
+    }
+    let mut cursor = data
+        .split_off(HEADER_LEN)
+        .ok_or(ReadError::OutOfBounds)?
+        .cursor();
+    let entries = (0..header.num_tables())
+        .map(|_| TableEntry::read(&mut cursor))
+        .collect::<Result<Vec<_>, _>>()?;
+    let collection = header.flavor() == TTC_FLAVOR;
+    let fonts = if collection {
+        let _version: u32 = cursor.read()?;
+        let num_fonts = read_255_u16(&mut cursor)?;
+        let mut fonts = Vec::with_capacity(num_fonts as usize);
+        for _ in 0..num_fonts {
+            let num_tables = read_255_u16(&mut cursor)?;
+            let flavor: u32 = cursor.read()?;
+            let indices = (0..num_tables)
+                .map(|_| {
+                    let ix = read_255_u16(&mut cursor)? as usize;
+                    if ix < entries.len() {
+                        Ok(ix)
+                    } else {
+                        Err(ReadError::MalformedData("invalid WOFF2 table index"))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            fonts.push((flavor, indices));
+        }
+        fonts
+    } else {
+        vec![(header.flavor(), (0..entries.len()).collect())]
+    };
+    let compressed_start = cursor.position()? + HEADER_LEN;
+    let compressed_len = header.total_compressed_size() as usize;
+    let compressed = data
+        .slice(compressed_start..compressed_start.saturating_add(compressed_len))
+        .ok_or(ReadError::OutOfBounds)?;
+    let stream_len = entries
+        .iter()
+        .try_fold(0u64, |len, entry| {
+            len.checked_add(entry.stream_length as u64)
+        })
+        .ok_or(ReadError::OutOfBounds)?;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed.as_bytes(), 4096)
+        .take(stream_len + 1)
+        .read_to_end(&mut stream)
+        .map_err(|_| ReadError::MalformedData("invalid Brotli data"))?;
+    if stream.len() as u64 != stream_len {
+        return Err(ReadError::MalformedData("bad decompressed WOFF2 length"));
+    }
+    let mut table_data = Vec::with_capacity(entries.len());
+    let mut offset = 0;
+    for entry in &entries {
+        let end = offset + entry.stream_length as usize;
+        table_data.push(&stream[offset..end]);
+        offset = end;
+    }
+    let mut tables: Vec<Option<Vec<u8>>> = entries
+        .iter()
+        .zip(&table_data)
+        .map(|(entry, data)| (!entry.is_transformed()).then(|| data.to_vec()))
+        .collect();
+    // xMin of each glyph, keyed by the index of the transformed glyf table
+    let mut x_mins: Vec<Option<Vec<i16>>> = vec![None; entries.len()];
+    for (_, indices) in &fonts {
+        let find = |tag| indices.iter().copied().find(|ix| entries[*ix].tag == tag);
+        let glyf_ix = find(GLYF);
+        let loca_ix = find(LOCA);
+        if let Some(glyf_ix) = glyf_ix {
+            let loca_ix = loca_ix.ok_or(ReadError::TableIsMissing(LOCA))?;
+            if entries[glyf_ix].is_transformed() != entries[loca_ix].is_transformed() {
+                return Err(ReadError::MalformedData(
+                    "mismatched glyf and loca transforms",
+                ));
+            }
+            if entries[glyf_ix].is_transformed() {
+                // tables may be shared between fonts in a collection
+                if tables[glyf_ix].is_none() {
+                    let glyf = reconstruct_glyf(table_data[glyf_ix])?;
+                    if glyf.loca.len() != entries[loca_ix].orig_length as usize {
+                        return Err(ReadError::MalformedData("bad reconstructed loca length"));
+                    }
+                    tables[glyf_ix] = Some(glyf.glyf);
+                    tables[loca_ix] = Some(glyf.loca);
+                    x_mins[glyf_ix] = Some(glyf.x_mins);
+                }
+            }
+        } else if loca_ix.is_some_and(|ix| entries[ix].is_transformed()) {
+            return Err(ReadError::TableIsMissing(GLYF));
+        }
+        if let Some(hmtx_ix) = find(HMTX).filter(|ix| tables[*ix].is_none()) {
+            // the hmtx transform relies on the glyf transform for lsb values
+            let x_mins =
+                glyf_ix
+                    .and_then(|ix| x_mins[ix].as_deref())
+                    .ok_or(ReadError::MalformedData(
+                        "hmtx transform without glyf transform",
+                    ))?;
+            let table = |tag| {
+                find(tag)
+                    .and_then(|ix| tables[ix].as_deref())
+                    .map(FontData::new)
+                    .ok_or(ReadError::TableIsMissing(tag))
+            };
+            let num_glyphs = Maxp::read(table(MAXP)?)?.num_glyphs();
+            let num_h_metrics = Hhea::read(table(HHEA)?)?.number_of_long_metrics();
+            let hmtx = reconstruct_hmtx(table_data[hmtx_ix], num_glyphs, num_h_metrics, x_mins)?;
+            if hmtx.len() != entries[hmtx_ix].orig_length as usize {
+                return Err(ReadError::MalformedData("bad reconstructed hmtx length"));
+            }
+            tables[hmtx_ix] = Some(hmtx);
+        }
+    }
+    let tables = entries
+        .iter()
+        .zip(tables)
+        .map(|(entry, data)| {
+            data.map(|data| (entry.tag, data))
+                .ok_or(ReadError::MalformedData(
+                    "transformed table could not be reconstructed",
+                ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sfnt::assemble(&fonts, tables, collection))
+}
+
+/// The result of reversing the glyf transform.
+struct ReconstructedGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// The xMin of each glyph, used to reconstruct hmtx.
+    x_mins: Vec<i16>,
+}
+
+/// Reverses the glyf transform, producing the glyf and loca tables.
+fn reconstruct_glyf(data: &[u8]) -> Result<ReconstructedGlyf, ReadError> {
+    let data = FontData::new(data);
+    let mut header = data.cursor();
+    let _reserved: u16 = header.read()?;
+    let option_flags: u16 = header.read()?;
+    let num_glyphs: u16 = header.read()?;
+    let index_format: u16 = header.read()?;
+    let mut streams = [FontData::new(&[]); 7];
+    let mut offset = 36usize;
+    for stream in streams.iter_mut() {
+        let len: u32 = header.read()?;
+        let end = offset
+            .checked_add(len as usize)
+            .ok_or(ReadError::OutOfBounds)?;
+        *stream = data.slice(offset..end).ok_or(ReadError::OutOfBounds)?;
+        offset = end;
+    }
+    let [mut n_contour, mut n_points, mut flags, mut glyph, mut composite, mut bbox, mut instructions] =
+        streams.map(|stream| stream.cursor());
+    let bitmap_len = 4 * (num_glyphs as usize).div_ceil(32);
+    let bbox_bitmap = bbox.read_array::<u8>(bitmap_len)?;
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        let len = (num_glyphs as usize).div_ceil(8);
+        Some(
+            data.slice(offset..offset + len)
+                .ok_or(ReadError::OutOfBounds)?
+                .as_bytes(),
+        )
+    } else {
+        None
+    };
+    let bit_set = |bitmap: &[u8], ix: usize| bitmap[ix / 8] & (0x80 >> (ix % 8)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs as usize + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs as usize);
+    let mut points = Vec::new();
+    for gid in 0..num_glyphs as usize {
+        offsets.push(glyf.len());
+        let num_contours: i16 = n_contour.read()?;
+        let has_bbox = bit_set(bbox_bitmap, gid);
+        let explicit_bbox = if has_bbox {
+            Some(bbox.read_array::<font_types::BigEndian<i16>>(4)?)
+        } else {
+            None
+        };
+        let has_overlap = overlap_bitmap.is_some_and(|bitmap| bit_set(bitmap, gid));
+        match num_contours {
+            0 => {
+                if has_bbox {
+                    return Err(ReadError::MalformedData("empty glyph with bounding box"));
+                }
+                x_mins.push(0);
+            }
+            -1 => {
+                let bbox = explicit_bbox.ok_or(ReadError::MalformedData(
+                    "composite glyph without bounding box",
+                ))?;
+                glyf.extend_from_slice(&(-1i16).to_be_bytes());
+                for value in bbox {
+                    glyf.extend_from_slice(&value.get().to_be_bytes());
+                }
+                x_mins.push(bbox[0].get());
+                let mut have_instructions = false;
+                let mut first = true;
+                loop {
+                    let mut flags = CompositeGlyphFlags::from_bits_truncate(composite.read()?);
+                    if first && has_overlap {
+                        flags |= CompositeGlyphFlags::OVERLAP_COMPOUND;
+                    }
+                    first = false;
+                    let mut len = if flags.contains(CompositeGlyphFlags::ARG_1_AND_2_ARE_WORDS) {
+                        4
+                    } else {
+                        2
+                    };
+                    if flags.contains(CompositeGlyphFlags::WE_HAVE_A_SCALE) {
+                        len += 2;
+                    } else if flags.contains(CompositeGlyphFlags::WE_HAVE_AN_X_AND_Y_SCALE) {
+                        len += 4;
+                    } else if flags.contains(CompositeGlyphFlags::WE_HAVE_A_TWO_BY_TWO) {
+                        len += 8;
+                    }
+                    // glyph index, arguments and transform are copied as is
+                    glyf.extend_from_slice(&flags.bits().to_be_bytes());
+                    glyf.extend_from_slice(composite.read_array::<u8>(2 + len)?);
+                    have_instructions |= flags.contains(CompositeGlyphFlags::WE_HAVE_INSTRUCTIONS);
+                    if !flags.contains(CompositeGlyphFlags::MORE_COMPONENTS) {
+                        break;
+                    }
+                }
+                if have_instructions {
+                    let len = read_255_u16(&mut glyph)?;
+                    glyf.extend_from_slice(&len.to_be_bytes());
+                    glyf.extend_from_slice(instructions.read_array::<u8>(len as usize)?);
+                }
+            }
+            n if n > 0 => {
+                let mut end_pts = Vec::with_capacity(n as usize);
+                let mut num_points = 0u32;
+                for _ in 0..n {
+                    let count = read_255_u16(&mut n_points)?;
+                    num_points += count as u32;
+                    if num_points == 0 || num_points > u16::MAX as u32 + 1 {
+                        return Err(ReadError::MalformedData("invalid glyph point count"));
+                    }
+                    end_pts.push((num_points - 1) as u16);
+                }
+                let point_flags = flags.read_array::<u8>(num_points as usize)?;
+                points.clear();
+                let (mut x, mut y) = (0i32, 0i32);
+                for flag in point_flags {
+                    let (dx, dy) = read_triplet(*flag, &mut glyph)?;
+                    x += dx;
+                    y += dy;
+                    points.push((x, y, flag & 0x80 == 0));
+                }
+                let instruction_len = read_255_u16(&mut glyph)?;
+                let instruction_bytes = instructions.read_array::<u8>(instruction_len as usize)?;
+                let bbox = match explicit_bbox {
+                    Some(bbox) => [bbox[0].get(), bbox[1].get(), bbox[2].get(), bbox[3].get()],
+                    None => compute_bbox(&points)?,
+                };
+                x_mins.push(bbox[0]);
+                glyf.extend_from_slice(&n.to_be_bytes());
+                for value in bbox {
+                    glyf.extend_from_slice(&value.to_be_bytes());
+                }
+                for end_pt in end_pts {
+                    glyf.extend_from_slice(&end_pt.to_be_bytes());
+                }
+                glyf.extend_from_slice(&instruction_len.to_be_bytes());
+                glyf.extend_from_slice(instruction_bytes);
+                encode_points(&points, has_overlap, &mut glyf)?;
+            }
+            _ => return Err(ReadError::MalformedData("invalid glyph contour count")),
+        }
+        glyf.resize((glyf.len() + 3) & !3, 0);
+    }
+    offsets.push(glyf.len());
+    let loca = match index_format {
+        0 => {
+            if glyf.len() > 0x1FFFF {
+                return Err(ReadError::MalformedData("glyf too large for short loca"));
+            }
+            offsets
+                .iter()
+                .flat_map(|offset| ((offset / 2) as u16).to_be_bytes())
+                .collect()
+        }
+        1 => offsets
+            .iter()
+            .flat_map(|offset| (*offset as u32).to_be_bytes())
+            .collect(),
+        _ => return Err(ReadError::InvalidFormat(index_format as _)),
+    };
+    Ok(ReconstructedGlyf { glyf, loca, x_mins })
+}
+
+/// Decodes a point delta from the glyph stream, using the triplet
+/// encoding described in section 5.2 of the spec.
+fn read_triplet(flag: u8, stream: &mut Cursor) -> Result<(i32, i32), ReadError> {
+    fn with_sign(flag: i32, value: i32) -> i32 {
+        if flag & 1 != 0 {
+            value
+        } else {
+            -value
+        }
+    }
+    let flag = (flag & 0x7F) as i32;
+    Ok(if flag < 10 {
+        let b0: u8 = stream.read()?;
+        (0, with_sign(flag, ((flag & 14) << 7) + b0 as i32))
+    } else if flag < 20 {
+        let b0: u8 = stream.read()?;
+        (with_sign(flag, (((flag - 10) & 14) << 7) + b0 as i32), 0)
+    } else if flag < 84 {
+        let b0 = flag - 20;
+        let b1: u8 = stream.read()?;
+        let b1 = b1 as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = flag - 84;
+        let b1: u8 = stream.read()?;
+        let b2: u8 = stream.read()?;
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1 as i32),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2 as i32),
+        )
+    } else if flag < 124 {
+        let b1: u8 = stream.read()?;
+        let b2: u8 = stream.read()?;
+        let b3: u8 = stream.read()?;
+        let (b1, b2, b3) = (b1 as i32, b2 as i32, b3 as i32);
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3),
+        )
+    } else {
+        let x: u16 = stream.read()?;
+        let y: u16 = stream.read()?;
+        (with_sign(flag, x as i32), with_sign(flag >> 1, y as i32))
+    })
+}
+
+fn compute_bbox(points: &[(i32, i32, bool)]) -> Result<[i16; 4], ReadError> {
+    let mut bbox = [i32::MAX, i32::MAX, i32::MIN, i32::MIN];
+    for (x, y, _) in points {
+        bbox[0] = bbox[0].min(*x);
+        bbox[1] = bbox[1].min(*y);
+        bbox[2] = bbox[2].max(*x);
+        bbox[3] = bbox[3].max(*y);
+    }
+    let mut result = [0i16; 4];
+    for (value, coord) in result.iter_mut().zip(bbox) {
+        *value = coord
+            .try_into()
+            .map_err(|_| ReadError::MalformedData("glyph coordinate out of range"))?;
+    }
+    Ok(result)
+}
+
+/// Writes the flags and coordinates of a simple glyph in the standard
+/// glyf encoding.
+fn encode_points(
+    points: &[(i32, i32, bool)],
+    has_overlap: bool,
+    out: &mut Vec<u8>,
+) -> Result<(), ReadError> {
+    let mut flags = Vec::with_capacity(points.len());
+    let mut x_bytes = Vec::new();
+    let mut y_bytes = Vec::new();
+    let (mut last_x, mut last_y) = (0i32, 0i32);
+    for (i, (x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if *on_curve {
+            SimpleGlyphFlags::ON_CURVE_POINT
+        } else {
+            SimpleGlyphFlags::empty()
+        };
+        if i == 0 && has_overlap {
+            flag |= SimpleGlyphFlags::OVERLAP_SIMPLE;
+        }
+        let (dx, dy) = (x - last_x, y - last_y);
+        (last_x, last_y) = (*x, *y);
+        for (delta, bytes, short, same) in [
+            (
+                dx,
+                &mut x_bytes,
+                SimpleGlyphFlags::X_SHORT_VECTOR,
+                SimpleGlyphFlags::X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR,
+            ),
+            (
+                dy,
+                &mut y_bytes,
+                SimpleGlyphFlags::Y_SHORT_VECTOR,
+                SimpleGlyphFlags::Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR,
+            ),
+        ] {
+            if delta == 0 {
+                flag |= same;
+            } else if (-255..=255).contains(&delta) {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same;
+                }
+                bytes.push(delta.unsigned_abs() as u8);
+            } else {
+                let delta: i16 = delta
+                    .try_into()
+                    .map_err(|_| ReadError::MalformedData("glyph coordinate out of range"))?;
+                bytes.extend_from_slice(&delta.to_be_bytes());
+            }
+        }
+        flags.push(flag.bits());
+    }
+    let mut i = 0;
+    while i < flags.len() {
+        let flag = flags[i];
+        let repeat = flags[i + 1..]
+            .iter()
+            .take(255)
+            .take_while(|f| **f == flag)
+            .count();
+        if repeat > 0 {
+            out.push(flag | SimpleGlyphFlags::REPEAT_FLAG.bits());
+            out.push(repeat as u8);
+        } else {
+            out.push(flag);
+        }
+        i += repeat + 1;
+    }
+    out.extend_from_slice(&x_bytes);
+    out.extend_from_slice(&y_bytes);
+    Ok(())
+}
+
+/// Reverses the hmtx transform.
+fn reconstruct_hmtx(
+    data: &[u8],
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, ReadError> {
+    let mut cursor = FontData::new(data).cursor();
+    let flags: u8 = cursor.read()?;
+    let omit_proportional_lsbs = flags & 1 != 0;
+    let omit_monospaced_lsbs = flags & 2 != 0;
+    if flags & 0xFC != 0 || !(omit_proportional_lsbs || omit_monospaced_lsbs) {
+        return Err(ReadError::MalformedData("invalid hmtx transform flags"));
+    }
+    if num_h_metrics == 0 || num_h_metrics > num_glyphs || x_mins.len() < num_glyphs as usize {
+        return Err(ReadError::MalformedData("inconsistent hmtx glyph counts"));
+    }
+    let advances = cursor.read_array::<font_types::BigEndian<u16>>(num_h_metrics as usize)?;
+    let mut out = Vec::with_capacity(num_h_metrics as usize * 2 + num_glyphs as usize * 2);
+    for (gid, advance) in advances.iter().enumerate() {
+        let lsb = if omit_proportional_lsbs {
+            x_mins[gid]
+        } else {
+            cursor.read()?
+        };
+        out.extend_from_slice(&advance.get().to_be_bytes());
+        out.extend_from_slice(&lsb.to_be_bytes());
+    }
+    for x_min in &x_mins[num_h_metrics as usize..num_glyphs as usize] {
+        let lsb = if omit_monospaced_lsbs {
+            *x_min
+        } else {
+            cursor.read()?
+        };
+        out.extend_from_slice(&lsb.to_be_bytes());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{
+        tables::glyf::{CurvePoint, Glyph},
+        test_helpers::BeBuffer,
+        types::GlyphId,
+        FontRef,
+    };
+
+    fn push_base128(out: &mut Vec<u8>, value: u32) {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        let mut value = value >> 7;
+        while value != 0 {
+            bytes.push((value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        out.extend(bytes.iter().rev());
+    }
+
+    fn brotli_compress(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 9, 22);
+        writer.write_all(data).unwrap();
+        drop(writer);
+        out
+    }
+
+    /// Builds a WOFF2 file from an sfnt without transforming any tables.
+    fn make_woff2(sfnt: &[u8]) -> Vec<u8> {
+        let font = FontRef::new(sfnt).unwrap();
+        let records = font.table_directory.table_records();
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        for record in records {
+            let tag = record.tag();
+            let data = font.table_data(tag).unwrap().as_bytes();
+            let version = if tag == GLYF || tag == LOCA {
+                3 << 6
+            } else {
+                0
+            };
+            match KNOWN_TAGS.iter().position(|known| *known == tag) {
+                Some(ix) => directory.push(ix as u8 | version),
+                None => {
+                    directory.push(63 | version);
+                    directory.extend_from_slice(&tag.to_be_bytes());
+                }
+            }
+            push_base128(&mut directory, data.len() as u32);
+            stream.extend_from_slice(data);
+        }
+        let compressed = brotli_compress(&stream);
+        let len = HEADER_LEN + directory.len() + compressed.len();
+        let header = BeBuffer::new()
+            .push(WOFF2_SIGNATURE)
+            .push(font.table_directory.sfnt_version())
+            .push(len as u32)
+            .push(records.len() as u16)
+            .push(0u16)
+            .push(sfnt.len() as u32)
+            .push(compressed.len() as u32)
+            .extend([1u16, 0])
+            .extend([0u32; 5]);
+        let mut woff2 = header.to_vec();
+        woff2.extend_from_slice(&directory);
+        woff2.extend_from_slice(&compressed);
+        woff2
+    }
+
+    #[test]
+    fn decode_null_transform() {
+        let sfnt = font_test_data::VAZIRMATN_VAR;
+        let decoded = decode(&make_woff2(sfnt)).unwrap();
+        let decoded = FontRef::new(&decoded).unwrap();
+        let expected = FontRef::new(sfnt).unwrap();
+        for record in expected.table_directory.table_records() {
+            let tag = record.tag();
+            let decoded_data = decoded.table_data(tag).unwrap().as_bytes();
+            let expected_data = expected.table_data(tag).unwrap().as_bytes();
+            if tag == Tag::new(b"head") {
+                // only checkSumAdjustment may differ
+                assert_eq!(decoded_data[..8], expected_data[..8]);
+                assert_eq!(decoded_data[12..], expected_data[12..]);
+            } else {
+                assert_eq!(decoded_data, expected_data, "{tag}");
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_stream() {
+        let mut woff2 = make_woff2(font_test_data::VAZIRMATN_VAR);
+        woff2.truncate(woff2.len() - 10);
+        assert!(decode(&woff2).is_err());
+    }
+
+    #[test]
+    fn base128() {
+        for (bytes, expected) in [
+            (&[0x3F][..], Some(63)),
+            (&[0x81, 0x00], Some(128)),
+            (&[0x8F, 0xFF, 0xFF, 0xFF, 0x7F], Some(u32::MAX)),
+            // leading zeros
+            (&[0x80, 0x3F], None),
+            // overflow
+            (&[0x9F, 0xFF, 0xFF, 0xFF, 0x7F], None),
+            // more than five bytes
+            (&[0x81, 0x80, 0x80, 0x80, 0x80, 0x00], None),
+        ] {
+            let mut cursor = FontData::new(bytes).cursor();
+            assert_eq!(read_base128(&mut cursor).ok(), expected, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn uint_255() {
+        for (bytes, expected) in [
+            (&[252][..], 252),
+            (&[255, 0], 253),
+            (&[255, 252], 505),
+            (&[254, 0], 506),
+            (&[253, 0x01, 0x02], 258),
+        ] {
+            let mut cursor = FontData::new(bytes).cursor();
+            assert_eq!(read_255_u16(&mut cursor).unwrap(), expected, "{bytes:?}");
+        }
+    }
+
+    /// A transformed glyf table with an empty glyph, a simple triangle with
+    /// instructions, and a composite of the triangle, both flagged as overlapping.
+    fn transformed_glyf() -> Vec<u8> {
+        let n_contour = BeBuffer::new().extend([0i16, 1, -1]);
+        let n_points = [3u8];
+        // on curve (0, 0), on curve (+100, 0), off curve (-50, +700)
+        let flags = [1u8, 11, 126 | 0x80];
+        let glyph = BeBuffer::new()
+            .extend([0u8, 100])
+            .extend([50u16, 700])
+            // instruction length
+            .push(2u8);
+        let composite = BeBuffer::new()
+            // ARGS_ARE_XY_VALUES, glyph 1, offset (10, 20)
+            .extend([0x0002u16, 1])
+            .extend([10i8, 20]);
+        let bbox = BeBuffer::new()
+            // explicit bbox for glyph 2 only
+            .extend([0x20u8, 0, 0, 0])
+            .extend([10i16, 20, 110, 720]);
+        let instructions = [0xB0u8, 0x01];
+        let overlap = [0x60u8];
+        let mut glyf = BeBuffer::new()
+            .push(0u16)
+            // option flags: has overlap bitmap
+            .push(1u16)
+            .push(3u16)
+            // short loca
+            .push(0u16)
+            .extend([
+                n_contour.len() as u32,
+                n_points.len() as u32,
+                flags.len() as u32,
+                glyph.len() as u32,
+                composite.len() as u32,
+                bbox.len() as u32,
+                instructions.len() as u32,
+            ])
+            .to_vec();
+        for stream in [
+            &n_contour[..],
+            &n_points,
+            &flags,
+            &glyph,
+            &composite,
+            &bbox,
+            &instructions,
+            &overlap,
+        ] {
+            glyf.extend_from_slice(stream);
+        }
+        glyf
+    }
+
+    #[test]
+    fn reverse_glyf_transform() {
+        let result = reconstruct_glyf(&transformed_glyf()).unwrap();
+        assert_eq!(result.x_mins, [0, 0, 10]);
+        let loca = crate::tables::loca::Loca::read(FontData::new(&result.loca), false).unwrap();
+        assert_eq!(loca.len(), 3);
+        let glyf = crate::tables::glyf::Glyf::read(FontData::new(&result.glyf)).unwrap();
+        assert!(loca.get_glyf(GlyphId::new(0), &glyf).unwrap().is_none());
+        let Some(Glyph::Simple(simple)) = loca.get_glyf(GlyphId::new(1), &glyf).unwrap() else {
+            panic!("expected a simple glyph");
+        };
+        assert_eq!(
+            [
+                simple.x_min(),
+                simple.y_min(),
+                simple.x_max(),
+                simple.y_max()
+            ],
+            [0, 0, 100, 700]
+        );
+        assert_eq!(simple.instructions(), [0xB0, 0x01]);
+        assert!(simple.has_overlapping_contours());
+        assert_eq!(
+            simple.points().collect::<Vec<_>>(),
+            [
+                CurvePoint::on_curve(0, 0),
+                CurvePoint::on_curve(100, 0),
+                CurvePoint::off_curve(50, 700)
+            ]
+        );
+        let Some(Glyph::Composite(composite)) = loca.get_glyf(GlyphId::new(2), &glyf).unwrap()
+        else {
+            panic!("expected a composite glyph");
+        };
+        assert_eq!(composite.x_min(), 10);
+        assert_eq!(composite.y_max(), 720);
+        let component = composite.components().next().unwrap();
+        assert_eq!(component.glyph, GlyphId::new(1));
+        assert!(component
+            .flags
+            .contains(CompositeGlyphFlags::OVERLAP_COMPOUND));
+    }
+
+    #[test]
+    fn reverse_hmtx_transform() {
+        let x_mins = [5, -3, 7, 12];
+        // proportional lsbs omitted, monospaced lsbs present
+        let data = BeBuffer::new()
+            .push(1u8)
+            .extend([500u16, 600])
+            .extend([-1i16, 2]);
+        let hmtx = reconstruct_hmtx(&data, 4, 2, &x_mins).unwrap();
+        let expected = BeBuffer::new()
+            .extend([500u16])
+            .push(5i16)
+            .push(600u16)
+            .push(-3i16)
+            .extend([-1i16, 2]);
+        assert_eq!(hmtx, &expected[..]);
+        // both omitted
+        let data = BeBuffer::new().push(3u8).extend([500u16, 600]);
+        let hmtx = reconstruct_hmtx(&data, 4, 2, &x_mins).unwrap();
+        let expected = BeBuffer::new()
+            .push(500u16)
+            .push(5i16)
+            .push(600u16)
+            .extend([-3i16, 7, 12]);
+        assert_eq!(hmtx, &expected[..]);
+        // the transform requires at least one omitted array
+        let data = BeBuffer::new().push(0u8).extend([500u16, 600]);
+        assert!(reconstruct_hmtx(&data, 4, 2, &x_mins).is_err());
+    }
+}
@@ -158,6 +158,7 @@ table IndexSubtable4 {
     /// Offset to image data in EBDT table.
     image_data_offset: u32,
     /// Array length.
+    #[compile(self.compute_num_glyphs())]
     num_glyphs: u32,
     /// One per glyph.
     #[count(add($num_glyphs, 1))]
@@ -187,6 +188,7 @@ table IndexSubtable5 {
     #[count(1)]
     big_metrics: [BigGlyphMetrics],
     /// Array length.
+    #[compile(array_len($glyph_array))]
     num_glyphs: u32,
     /// One per glyph, sorted by glyhph ID.
     #[count($num_glyphs)]
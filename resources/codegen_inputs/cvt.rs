@@ -0,0 +1,10 @@
+#![parse_module(read_fonts::tables::cvt)]
+
+/// The [cvt (Control Value Table)](https://learn.microsoft.com/en-us/typography/opentype/spec/cvt) table.
+#[tag = "cvt "]
+table Cvt {
+    /// List of n values referenceable by instructions. n is the number of
+    /// FWORD items that fit in the size of the table.
+    #[count(..)]
+    values: [FWord],
+}
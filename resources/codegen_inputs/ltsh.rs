@@ -0,0 +1,14 @@
+#![parse_module(read_fonts::tables::ltsh)]
+
+/// The [Linear Threshold](https://learn.microsoft.com/en-us/typography/opentype/spec/ltsh) table.
+#[tag = "LTSH"]
+table Ltsh {
+    /// Version number (starts at 0).
+    version: u16,
+    /// Number of glyphs (from “numGlyphs” in 'maxp' table).
+    num_glyphs: u16,
+    /// The vertical pel height at which the glyph can be assumed to
+    /// scale linearly. On a per glyph basis.
+    #[count($num_glyphs)]
+    y_pels: [u8],
+}
@@ -0,0 +1,182 @@
+#![parse_module(read_fonts::tables::varc)]
+
+/// The [VARC (Variable Composites / Components)](https://github.com/harfbuzz/boring-expansion-spec/blob/main/VARC.md)
+/// table.
+#[tag = "VARC"]
+table Varc {
+    /// Major/minor version number. Set to 1.0.
+    #[compile(MajorMinor::VERSION_1_0)]
+    version: MajorMinor,
+    /// Offset to the coverage table of glyphs with variable composite
+    /// descriptions.
+    coverage_offset: Offset32<CoverageTable>,
+    /// Offset to the multi-item variation store.
+    #[nullable]
+    multi_var_store_offset: Offset32<MultiItemVariationStore>,
+    /// Offset to the list of conditions referenced by components.
+    #[nullable]
+    condition_list_offset: Offset32<ConditionList>,
+    /// Offset to a CFF2 style INDEX of packed axis index lists.
+    #[nullable]
+    axis_indices_list_offset: Offset32<Index2>,
+    /// Offset to a CFF2 style INDEX of variable composite glyph records,
+    /// ordered by coverage index.
+    var_composite_glyphs_offset: Offset32<Index2>,
+}
+
+/// A variation store where each item references a vector of deltas.
+table MultiItemVariationStore {
+    /// Format. Set to 1.
+    #[format = 1]
+    format: u16,
+    /// Offset to the sparse variation region list.
+    region_list_offset: Offset32<SparseVariationRegionList>,
+    /// Number of variation data subtables.
+    variation_data_count: u16,
+    /// Offsets to variation data subtables.
+    #[count($variation_data_count)]
+    variation_data_offsets: [Offset32<MultiItemVariationData>],
+}
+
+/// List of regions where each region only references the axes it uses.
+table SparseVariationRegionList {
+    /// Number of regions.
+    region_count: u16,
+    /// Offsets to sparse variation regions.
+    #[count($region_count)]
+    region_offsets: [Offset32<SparseVariationRegion>],
+}
+
+/// A variation region defined over a subset of the font's axes.
+table SparseVariationRegion {
+    /// Number of axes referenced by this region.
+    region_axis_count: u16,
+    /// Coordinates for each referenced axis.
+    #[count($region_axis_count)]
+    region_axes: [SparseRegionAxisCoordinates],
+}
+
+/// The region coordinates for a single axis.
+record SparseRegionAxisCoordinates {
+    /// Index of the axis in the `fvar` table.
+    axis_index: u16,
+    /// The region start coordinate value for the axis.
+    start: F2Dot14,
+    /// The region peak coordinate value for the axis.
+    peak: F2Dot14,
+    /// The region end coordinate value for the axis.
+    end: F2Dot14,
+}
+
+/// A set of delta vectors sharing a list of regions.
+table MultiItemVariationData {
+    /// Format. Set to 0.
+    #[format = 0]
+    format: u8,
+    /// Number of regions referenced by this subtable.
+    region_index_count: u16,
+    /// Indices into the region list.
+    #[count($region_index_count)]
+    region_indices: [u16],
+    /// A CFF2 style INDEX of packed delta vectors, one per item.
+    #[count(..)]
+    raw_delta_sets: [u8],
+}
+
+/// A list of conditions referenced by index.
+table ConditionList {
+    /// Number of conditions.
+    condition_count: u32,
+    /// Offsets to condition tables, from the beginning of this table.
+    #[count($condition_count)]
+    condition_offsets: [Offset32<Condition>],
+}
+
+/// A condition that is evaluated against the current variation
+/// coordinates.
+format u16 Condition {
+    Format1AxisRange(ConditionFormat1),
+    Format2VariableValue(ConditionFormat2),
+    Format3And(ConditionFormat3),
+    Format4Or(ConditionFormat4),
+    Format5Negate(ConditionFormat5),
+}
+
+/// Condition format 2: a variable value that is true when positive.
+table ConditionFormat2 {
+    /// Format, = 2
+    #[format = 2]
+    format: u16,
+    /// Value at the default location.
+    default_value: i16,
+    /// Variation index into the multi-item variation store.
+    var_index: u32,
+}
+
+/// Condition format 3: true when all subconditions are true.
+table ConditionFormat3 {
+    /// Format, = 3
+    #[format = 3]
+    format: u16,
+    /// Number of subconditions.
+    condition_count: u8,
+    /// Offsets to subconditions, from the beginning of this table.
+    #[count($condition_count)]
+    condition_offsets: [Offset24<Condition>],
+}
+
+/// Condition format 4: true when any subcondition is true.
+table ConditionFormat4 {
+    /// Format, = 4
+    #[format = 4]
+    format: u16,
+    /// Number of subconditions.
+    condition_count: u8,
+    /// Offsets to subconditions, from the beginning of this table.
+    #[count($condition_count)]
+    condition_offsets: [Offset24<Condition>],
+}
+
+/// Condition format 5: true when the subcondition is false.
+table ConditionFormat5 {
+    /// Format, = 5
+    #[format = 5]
+    format: u16,
+    /// Offset to the subcondition, from the beginning of this table.
+    condition_offset: Offset24<Condition>,
+}
+
+/// Flags for a variable component record.
+flags u32 VarcFlags {
+    /// Axes not specified by the component are reset to the default
+    /// location rather than inherited from the parent.
+    RESET_UNSPECIFIED_AXES = 0x0001,
+    /// The component specifies axis values.
+    HAVE_AXES = 0x0002,
+    /// The axis values have variations.
+    AXIS_VALUES_HAVE_VARIATION = 0x0004,
+    /// The transform has variations.
+    TRANSFORM_HAS_VARIATION = 0x0008,
+    /// The transform has a horizontal translation.
+    HAVE_TRANSLATE_X = 0x0010,
+    /// The transform has a vertical translation.
+    HAVE_TRANSLATE_Y = 0x0020,
+    /// The transform has a rotation.
+    HAVE_ROTATION = 0x0040,
+    /// The component is only used when a condition is met.
+    HAVE_CONDITION = 0x0080,
+    /// The transform has a horizontal scale.
+    HAVE_SCALE_X = 0x0100,
+    /// The transform has a vertical scale.
+    HAVE_SCALE_Y = 0x0200,
+    /// The transform has a horizontal center.
+    HAVE_TCENTER_X = 0x0400,
+    /// The transform has a vertical center.
+    HAVE_TCENTER_Y = 0x0800,
+    /// The glyph identifier is stored in 24 bits.
+    GID_IS_24BIT = 0x1000,
+    /// The transform has a horizontal skew.
+    HAVE_SKEW_X = 0x2000,
+    /// The transform has a vertical skew.
+    HAVE_SKEW_Y = 0x4000,
+}
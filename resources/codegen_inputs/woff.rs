@@ -0,0 +1,90 @@
+#![parse_module(read_fonts::woff)]
+
+/// The [WOFF header](https://www.w3.org/TR/WOFF/#WOFFHeader) and table
+/// directory.
+table WoffHeader {
+    /// 0x774F4646 'wOFF'
+    signature: Tag,
+    /// The "sfnt version" of the input font.
+    flavor: u32,
+    /// Total size of the WOFF file.
+    length: u32,
+    /// Number of entries in directory of font tables.
+    num_tables: u16,
+    /// Reserved; set to zero.
+    #[skip_getter]
+    #[compile(0)]
+    reserved: u16,
+    /// Total size needed for the uncompressed font data, including the
+    /// sfnt header, directory, and font tables (including padding).
+    total_sfnt_size: u32,
+    /// Major version of the WOFF file.
+    major_version: u16,
+    /// Minor version of the WOFF file.
+    minor_version: u16,
+    /// Offset to metadata block, from beginning of WOFF file.
+    meta_offset: u32,
+    /// Length of compressed metadata block.
+    meta_length: u32,
+    /// Uncompressed size of metadata block.
+    meta_orig_length: u32,
+    /// Offset to private data block, from beginning of WOFF file.
+    priv_offset: u32,
+    /// Length of private data block.
+    priv_length: u32,
+    /// The table directory.
+    #[count($num_tables)]
+    table_directory: [WoffTableDirectoryEntry],
+}
+
+/// An entry in the [WOFF table directory](https://www.w3.org/TR/WOFF/#TableDirectory).
+record WoffTableDirectoryEntry {
+    /// 4-byte sfnt table identifier.
+    tag: Tag,
+    /// Offset to the data, from beginning of WOFF file.
+    offset: u32,
+    /// Length of the compressed data, excluding padding.
+    comp_length: u32,
+    /// Length of the uncompressed table, excluding padding.
+    orig_length: u32,
+    /// Checksum of the uncompressed table.
+    orig_checksum: u32,
+}
+
+/// The [WOFF2 header](https://www.w3.org/TR/WOFF2/#woff20Header).
+///
+/// The table directory that follows has variable length entries and is
+/// not included here.
+table Woff2Header {
+    /// 0x774F4632 'wOF2'
+    signature: Tag,
+    /// The "sfnt version" of the input font.
+    flavor: u32,
+    /// Total size of the WOFF file.
+    length: u32,
+    /// Number of entries in directory of font tables.
+    num_tables: u16,
+    /// Reserved; set to 0.
+    #[skip_getter]
+    #[compile(0)]
+    reserved: u16,
+    /// Total size needed for the uncompressed font data, including the
+    /// sfnt header, directory, and font tables (including padding).
+    total_sfnt_size: u32,
+    /// Total length of the compressed data block.
+    total_compressed_size: u32,
+    /// Major version of the WOFF file.
+    major_version: u16,
+    /// Minor version of the WOFF file.
+    minor_version: u16,
+    /// Offset to metadata block, from beginning of WOFF file.
+    meta_offset: u32,
+    /// Length of compressed metadata block.
+    meta_length: u32,
+    /// Uncompressed size of metadata block.
+    meta_orig_length: u32,
+    /// Offset to private data block, from beginning of WOFF file.
+    priv_offset: u32,
+    /// Length of private data block.
+    priv_length: u32,
+}
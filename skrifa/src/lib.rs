@@ -26,6 +26,8 @@ pub mod string;
 
 mod provider;
 mod small_array;
+#[cfg(test)]
+mod test_helpers;
 mod variation;
 
 pub use variation::{Axis, AxisCollection, NamedInstance, NamedInstanceCollection};
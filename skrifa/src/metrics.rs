@@ -29,6 +29,7 @@ use read_fonts::{
         hmtx::LongMetric,
         hvar::Hvar,
         loca::Loca,
+        ltsh::Ltsh,
         os2::SelectionFlags,
     },
     types::{BigEndian, Fixed, GlyphId},
@@ -68,6 +69,10 @@ pub struct Decoration {
 ///
 /// For variable fonts, deltas are computed using the  [MVAR](https://learn.microsoft.com/en-us/typography/opentype/spec/MVAR)
 /// table.
+///
+/// When constructed with [`Metrics::with_device_metrics`], the `ascent` and
+/// `descent` may instead be taken from the pixel-exact values in the
+/// [VDMX](https://learn.microsoft.com/en-us/typography/opentype/spec/vdmx) table.
 #[derive(Copy, Clone, PartialEq, Default, Debug)]
 pub struct Metrics {
     /// Number of font design units per em unit.
@@ -217,6 +222,33 @@ impl Metrics {
         }
         metrics
     }
+
+    /// Creates new metrics for the given font, size, and location in
+    /// normalized variation space, preferring precomputed device metrics
+    /// where available.
+    ///
+    /// This matches the behavior of GDI for hinted text: if `size` is an
+    /// integral pixels per em value and the location is the default, the
+    /// ascent and descent are replaced with the rounded extents from the
+    /// `VDMX` table when it contains an entry for that size. Otherwise, this
+    /// is equivalent to [`Metrics::new`].
+    pub fn with_device_metrics<'a>(
+        font: &impl TableProvider<'a>,
+        size: Size,
+        location: impl Into<LocationRef<'a>>,
+    ) -> Self {
+        let location = location.into();
+        let mut metrics = Self::new(font, size, location);
+        let extents = device_ppem(size, location.coords()).and_then(|ppem| {
+            // We assume square pixels so use a 1:1 aspect ratio
+            font.vdmx().ok()?.extents(ppem as u16, 1, 1)
+        });
+        if let Some((y_max, y_min)) = extents {
+            metrics.ascent = y_max as f32;
+            metrics.descent = y_min as f32;
+        }
+        metrics
+    }
 }
 
 /// Glyph specific metrics.
@@ -231,6 +263,7 @@ pub struct GlyphMetrics<'a> {
     gvar: Option<Gvar<'a>>,
     loca_glyf: Option<(Loca<'a>, Glyf<'a>)>,
     coords: &'a [NormalizedCoord],
+    device: Option<DeviceMetrics<'a>>,
 }
 
 impl<'a> GlyphMetrics<'a> {
@@ -277,7 +310,39 @@ impl<'a> GlyphMetrics<'a> {
             gvar,
             loca_glyf,
             coords,
+            device: None,
+        }
+    }
+
+    /// Creates new glyph metrics from the given font, size, and location in
+    /// normalized variation space, preferring precomputed device metrics
+    /// where available.
+    ///
+    /// If `size` is an integral pixels per em value and the location is the
+    /// default, advance widths are taken from the `hdmx` table when it
+    /// contains a record for that size. Otherwise, if the `LTSH` table
+    /// indicates that a glyph scales linearly at that size, the scaled
+    /// advance is rounded to whole pixels. In all other cases, the result
+    /// is the same as [`GlyphMetrics::new`].
+    pub fn with_device_metrics(
+        font: &impl TableProvider<'a>,
+        size: Size,
+        location: impl Into<LocationRef<'a>>,
+    ) -> Self {
+        let location = location.into();
+        let mut metrics = Self::new(font, size, location);
+        if let Some(ppem) = device_ppem(size, location.coords()) {
+            let widths = font
+                .hdmx()
+                .ok()
+                .and_then(|hdmx| hdmx.record_for_size(ppem))
+                .map(|record| record.widths);
+            let ltsh = font.ltsh().ok();
+            if widths.is_some() || ltsh.is_some() {
+                metrics.device = Some(DeviceMetrics { ppem, widths, ltsh });
+            }
         }
+        metrics
     }
 
     /// Returns the number of available glyphs in the font.
@@ -308,7 +373,13 @@ impl<'a> GlyphMetrics<'a> {
         } else if self.gvar.is_some() {
             advance += self.metric_deltas_from_gvar(glyph_id)[1];
         }
-        Some(self.fixed_scale.apply(advance))
+        let scaled_advance = self.fixed_scale.apply(advance);
+        if let Some(device) = &self.device {
+            if let Some(width) = device.advance_width(glyph_id, scaled_advance) {
+                return Some(width);
+            }
+        }
+        Some(scaled_advance)
     }
 
     /// Returns the left side bearing for the specified glyph.
@@ -370,6 +441,45 @@ impl<'a> GlyphMetrics<'a> {
     }
 }
 
+/// Precomputed metrics for a specific pixels per em size.
+#[derive(Clone)]
+struct DeviceMetrics<'a> {
+    ppem: u8,
+    widths: Option<&'a [u8]>,
+    ltsh: Option<Ltsh<'a>>,
+}
+
+impl<'a> DeviceMetrics<'a> {
+    /// Returns the device advance width for the given glyph, if known.
+    fn advance_width(&self, glyph_id: GlyphId, scaled_advance: f32) -> Option<f32> {
+        if let Some(width) = self
+            .widths
+            .and_then(|widths| widths.get(glyph_id.to_u16() as usize))
+        {
+            return Some(*width as f32);
+        }
+        // Glyphs that scale linearly at this size have a hinted advance
+        // that is simply the rounded linear advance
+        self.ltsh
+            .as_ref()?
+            .is_linear(glyph_id, self.ppem as u16)
+            .then(|| scaled_advance.round())
+    }
+}
+
+/// Returns the pixels per em value that should be used to select device
+/// metrics for the given size and location.
+///
+/// Device metrics only apply to integral sizes that can be represented in
+/// the tables and to the default location in variation space.
+fn device_ppem(size: Size, coords: &[NormalizedCoord]) -> Option<u8> {
+    let ppem = size.ppem()?;
+    if ppem.fract() != 0.0 || coords.iter().any(|coord| *coord != NormalizedCoord::ZERO) {
+        return None;
+    }
+    u8::try_from(ppem as u32).ok()
+}
+
 #[derive(Copy, Clone)]
 struct FixedScaleFactor(Fixed);
 
@@ -474,9 +584,9 @@ impl<'a> GvarMetricDeltas<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::MetadataProvider as _;
+    use crate::{test_helpers::ExtraTablesFont, MetadataProvider as _};
     use font_test_data::{SIMPLE_GLYF, VAZIRMATN_VAR};
-    use read_fonts::FontRef;
+    use read_fonts::{types::Tag, FontRef};
 
     #[test]
     fn metrics() {
@@ -631,6 +741,113 @@ mod tests {
         }
     }
 
+    // hdmx with a single record at 16 ppem for 4 glyphs
+    static HDMX_16PPEM: &[u8] = &[
+        0x00, 0x00, // version
+        0x00, 0x01, // num_records
+        0x00, 0x00, 0x00, 0x08, // size_device_record
+        16, 11, 7, 11, 10, 5, 0, 0, // pixel_size, max_width, widths, padding
+    ];
+
+    // LTSH where only glyph 3 scales linearly from 8 ppem
+    static LTSH_DATA: &[u8] = &[
+        0x00, 0x00, // version
+        0x00, 0x04, // num_glyphs
+        0, 0, 0, 8, // y_pels
+    ];
+
+    // VDMX with a single 1:1 ratio group containing an entry for 16 ppem
+    static VDMX_DATA: &[u8] = &[
+        0x00, 0x01, // version
+        0x00, 0x01, // num_recs
+        0x00, 0x01, // num_ratios
+        0x01, 0x01, 0x01, 0x01, // ratio: 1:1
+        0x00, 0x0C, // group offset
+        0x00, 0x01, // recs
+        0x10, // startsz
+        0x10, // endsz
+        0x00, 0x10, 0x00, 0x11, 0xFF, 0xF8, // 16: 17, -8
+    ];
+
+    static DEVICE_METRICS_TABLES: &[(Tag, &[u8])] = &[
+        (Tag::new(b"hdmx"), HDMX_16PPEM),
+        (Tag::new(b"LTSH"), LTSH_DATA),
+        (Tag::new(b"VDMX"), VDMX_DATA),
+    ];
+
+    fn device_metrics_font() -> ExtraTablesFont<'static> {
+        ExtraTablesFont::new(VAZIRMATN_VAR, DEVICE_METRICS_TABLES)
+    }
+
+    fn advances(glyph_metrics: &GlyphMetrics) -> Vec<f32> {
+        (0..glyph_metrics.glyph_count())
+            .map(|gid| glyph_metrics.advance_width(GlyphId::new(gid)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn glyph_metrics_hdmx_advances() {
+        let font = device_metrics_font();
+        let glyph_metrics =
+            GlyphMetrics::with_device_metrics(&font, Size::new(16.0), LocationRef::default());
+        assert_eq!(advances(&glyph_metrics), [7.0, 11.0, 10.0, 5.0]);
+    }
+
+    #[test]
+    fn glyph_metrics_ltsh_advances() {
+        let font = device_metrics_font();
+        let size = Size::new(17.0);
+        let linear = advances(&GlyphMetrics::new(&font, size, LocationRef::default()));
+        let device = advances(&GlyphMetrics::with_device_metrics(
+            &font,
+            size,
+            LocationRef::default(),
+        ));
+        // No hdmx record for this size so only the linear glyph is rounded
+        assert_eq!(&device[..3], &linear[..3]);
+        assert_eq!(device[3], linear[3].round());
+    }
+
+    #[test]
+    fn glyph_metrics_ignore_device_metrics() {
+        let font = device_metrics_font();
+        // fractional sizes
+        let size = Size::new(16.5);
+        assert_eq!(
+            advances(&GlyphMetrics::with_device_metrics(
+                &font,
+                size,
+                LocationRef::default()
+            )),
+            advances(&GlyphMetrics::new(&font, size, LocationRef::default()))
+        );
+        // non-default locations
+        let size = Size::new(16.0);
+        let coords = &[NormalizedCoord::from_f32(0.5)];
+        assert_eq!(
+            advances(&GlyphMetrics::with_device_metrics(
+                &font,
+                size,
+                LocationRef::new(coords)
+            )),
+            advances(&GlyphMetrics::new(&font, size, LocationRef::new(coords)))
+        );
+    }
+
+    #[test]
+    fn metrics_vdmx_extents() {
+        let font = device_metrics_font();
+        let metrics = Metrics::with_device_metrics(&font, Size::new(16.0), LocationRef::default());
+        assert_eq!(metrics.ascent, 17.0);
+        assert_eq!(metrics.descent, -8.0);
+        // Missing entries use the regular metrics
+        let size = Size::new(15.0);
+        assert_eq!(
+            Metrics::with_device_metrics(&font, size, LocationRef::default()),
+            Metrics::new(&font, size, LocationRef::default())
+        );
+    }
+
     /// Ensure our fixed point scaling code matches FreeType for advances.
     ///
     /// <https://github.com/googlefonts/fontations/issues/590>
@@ -3,7 +3,7 @@ use super::{
     charmap::Charmap,
     instance::{LocationRef, Size},
     metrics::{GlyphMetrics, Metrics},
-    string::{LocalizedStrings, StringId},
+    string::{Languages, LocalizedStrings, StringId},
     variation::{AxisCollection, NamedInstanceCollection},
 };
 
@@ -31,6 +31,18 @@ pub trait MetadataProvider<'a>: raw::TableProvider<'a> + Sized {
         LocalizedStrings::new(self, id)
     }
 
+    /// Returns an iterator over the languages the font was primarily designed
+    /// for.
+    fn design_languages(&self) -> Languages<'a> {
+        Languages::design(self)
+    }
+
+    /// Returns an iterator over the languages the font declares support for,
+    /// falling back to the design languages if none are declared.
+    fn supported_languages(&self) -> Languages<'a> {
+        Languages::supported(self)
+    }
+
     /// Returns the global font metrics for the specified size and location in
     /// normalized variation space.
     fn metrics(&self, size: Size, location: impl Into<LocationRef<'a>>) -> Metrics {
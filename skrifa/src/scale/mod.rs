@@ -151,6 +151,7 @@ mod cff;
 mod error;
 mod glyf;
 mod scaler;
+mod varc;
 
 pub use read_fonts::types::Pen;
 
@@ -1,5 +1,5 @@
 use super::{
-    cff, glyf, Context, Error, NormalizedCoord, Pen, Result, Size, UniqueId, VariationSetting,
+    cff, glyf, varc, Context, Error, NormalizedCoord, Pen, Result, Size, UniqueId, VariationSetting,
 };
 
 #[cfg(feature = "hinting")]
@@ -156,6 +156,7 @@ impl<'a> ScalerBuilder<'a> {
             #[cfg(feature = "hinting")]
             hint: self.hint,
             outlines,
+            varc: varc::Scaler::new(font),
         }
     }
 
@@ -209,6 +210,7 @@ pub struct Scaler<'a> {
     #[cfg(feature = "hinting")]
     hint: Option<Hinting>,
     outlines: Option<Outlines<'a>>,
+    varc: Option<varc::Scaler<'a>>,
 }
 
 impl<'a> Scaler<'a> {
@@ -224,7 +226,26 @@ impl<'a> Scaler<'a> {
 
     /// Loads a simple outline for the specified glyph identifier and invokes the functions
     /// in the given pen for the sequence of path commands that define the outline.
+    ///
+    /// Glyphs with variable composite descriptions in the `VARC` table are
+    /// built by loading each component at its own location in variation
+    /// space. Hinting is not applied to these glyphs.
     pub fn outline(&mut self, glyph_id: GlyphId, pen: &mut impl Pen) -> Result<ScalerMetrics> {
+        if let (Some(varc), Some(outlines)) = (&self.varc, &mut self.outlines) {
+            if let Some(glyph) = varc.glyph(glyph_id)? {
+                varc.outline(
+                    glyph_id,
+                    &glyph,
+                    self.size,
+                    self.coords,
+                    &mut |component_id, coords, pen| {
+                        outlines.unscaled_outline(component_id, coords, pen)
+                    },
+                    pen,
+                )?;
+                return Ok(ScalerMetrics::default());
+            }
+        }
         if let Some(outlines) = &mut self.outlines {
             outlines.outline(
                 glyph_id,
@@ -259,20 +280,7 @@ impl<'a> Outlines<'a> {
     ) -> Result<ScalerMetrics> {
         match self {
             Self::TrueType(scaler, buf) => {
-                let glyph = scaler.glyph(glyph_id, false)?;
-                let buf_size = glyph.required_buffer_size();
-                if buf.len() < buf_size {
-                    buf.resize(buf_size, 0);
-                }
-                let memory = glyph
-                    .memory_from_buffer(&mut buf[..])
-                    .ok_or(Error::InsufficientMemory)?;
-                let outline = scaler.outline(memory, &glyph, size, coords)?;
-                outline.to_path(pen)?;
-                Ok(ScalerMetrics {
-                    has_overlaps: glyph.has_overlaps,
-                    ..Default::default()
-                })
+                Self::truetype_outline(scaler, buf, glyph_id, size, coords, pen)
             }
             Self::PostScript(scaler, subfont) => {
                 let subfont_index = scaler.subfont_index(glyph_id);
@@ -292,4 +300,52 @@ impl<'a> Outlines<'a> {
             }
         }
     }
+
+    /// Loads an unhinted outline in font units at the given location.
+    ///
+    /// This is used for the components of variable composite glyphs which
+    /// are each loaded at their own location.
+    fn unscaled_outline(
+        &mut self,
+        glyph_id: GlyphId,
+        coords: &[NormalizedCoord],
+        pen: &mut impl Pen,
+    ) -> Result<()> {
+        match self {
+            Self::TrueType(scaler, buf) => {
+                Self::truetype_outline(scaler, buf, glyph_id, 0.0, coords, pen)?;
+            }
+            Self::PostScript(scaler, _) => {
+                // The cached subfont is specific to the location of the
+                // scaler so we always create a new one here.
+                let subfont = scaler.subfont(scaler.subfont_index(glyph_id), 0.0, coords)?;
+                scaler.outline(&subfont, glyph_id, coords, false, pen)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn truetype_outline(
+        scaler: &glyf::Scaler,
+        buf: &mut Vec<u8>,
+        glyph_id: GlyphId,
+        size: f32,
+        coords: &[NormalizedCoord],
+        pen: &mut impl Pen,
+    ) -> Result<ScalerMetrics> {
+        let glyph = scaler.glyph(glyph_id, false)?;
+        let buf_size = glyph.required_buffer_size();
+        if buf.len() < buf_size {
+            buf.resize(buf_size, 0);
+        }
+        let memory = glyph
+            .memory_from_buffer(&mut buf[..])
+            .ok_or(Error::InsufficientMemory)?;
+        let outline = scaler.outline(memory, &glyph, size, coords)?;
+        outline.to_path(pen)?;
+        Ok(ScalerMetrics {
+            has_overlaps: glyph.has_overlaps,
+            ..Default::default()
+        })
+    }
 }
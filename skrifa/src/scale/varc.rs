@@ -0,0 +1,422 @@
+//! Scaling support for variable composite glyphs from the `VARC` table.
+//!
+//! Each component of a variable composite glyph is loaded at its own
+//! location in variation space and then transformed into the coordinate
+//! space of the parent. Components may themselves be variable composites.
+
+use read_fonts::{
+    tables::varc::{
+        DecomposedTransform, MultiItemVariationStore, Varc, VarcComponent, VarcFlags, VarcGlyph,
+        NO_VARIATION_INDEX,
+    },
+    types::{F2Dot14, GlyphId},
+    TableProvider,
+};
+
+use core::f32::consts::PI;
+
+use super::{Error, NormalizedCoord, Pen, Result, GLYF_COMPOSITE_RECURSION_LIMIT};
+
+/// Scaler state for variable composite glyphs.
+#[derive(Clone)]
+pub struct Scaler<'a> {
+    varc: Varc<'a>,
+    var_store: Option<MultiItemVariationStore<'a>>,
+    axis_count: usize,
+    units_per_em: u16,
+}
+
+impl<'a> Scaler<'a> {
+    pub fn new(font: &impl TableProvider<'a>) -> Option<Self> {
+        let varc = font.varc().ok()?;
+        let var_store = varc.multi_var_store().transpose().ok()?;
+        let axis_count = font
+            .fvar()
+            .map(|fvar| fvar.axis_count() as usize)
+            .unwrap_or_default();
+        Some(Self {
+            varc,
+            var_store,
+            axis_count,
+            units_per_em: font.head().ok()?.units_per_em(),
+        })
+    }
+
+    /// Returns the variable composite description for the given glyph, if
+    /// any.
+    pub fn glyph(&self, glyph_id: GlyphId) -> Result<Option<VarcGlyph<'a>>> {
+        Ok(self.varc.glyph(glyph_id)?)
+    }
+
+    /// Loads the outline for a variable composite glyph at the given size
+    /// and location.
+    ///
+    /// The `load_base` function is invoked to load unscaled outlines for
+    /// components that are not themselves variable composites.
+    pub fn outline<P: Pen>(
+        &self,
+        glyph_id: GlyphId,
+        glyph: &VarcGlyph<'a>,
+        size: f32,
+        coords: &[NormalizedCoord],
+        load_base: &mut impl FnMut(GlyphId, &[NormalizedCoord], &mut TransformPen<P>) -> Result<()>,
+        pen: &mut P,
+    ) -> Result<()> {
+        let scale = if size > 0.0 && self.units_per_em != 0 {
+            size / self.units_per_em as f32
+        } else {
+            1.0
+        };
+        let mut full_coords = coords.to_vec();
+        full_coords.resize(self.axis_count.max(coords.len()), F2Dot14::ZERO);
+        let transform = [scale, 0.0, 0.0, scale, 0.0, 0.0];
+        self.load(glyph_id, glyph, &full_coords, &transform, 0, load_base, pen)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn load<P: Pen>(
+        &self,
+        glyph_id: GlyphId,
+        glyph: &VarcGlyph<'a>,
+        coords: &[NormalizedCoord],
+        transform: &Transform,
+        depth: usize,
+        load_base: &mut impl FnMut(GlyphId, &[NormalizedCoord], &mut TransformPen<P>) -> Result<()>,
+        pen: &mut P,
+    ) -> Result<()> {
+        if depth > GLYF_COMPOSITE_RECURSION_LIMIT {
+            return Err(Error::RecursionLimitExceeded(glyph_id));
+        }
+        for component in glyph.components() {
+            let component = component?;
+            if !self.is_present(&component, coords)? {
+                continue;
+            }
+            let component_coords = self.component_coords(&component, coords)?;
+            let component_transform = mul_transforms(
+                transform,
+                &to_matrix(&self.component_transform(&component, coords)?),
+            );
+            let component_id = component.glyph_id();
+            // A component referencing its parent glyph loads the outline
+            // from the base glyph source.
+            let component_glyph = if component_id != glyph_id {
+                self.glyph(component_id)?
+            } else {
+                None
+            };
+            if let Some(component_glyph) = component_glyph {
+                self.load(
+                    component_id,
+                    &component_glyph,
+                    &component_coords,
+                    &component_transform,
+                    depth + 1,
+                    load_base,
+                    pen,
+                )?;
+            } else {
+                let mut transform_pen = TransformPen {
+                    inner: pen,
+                    transform: component_transform,
+                };
+                load_base(component_id, &component_coords, &mut transform_pen)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_present(&self, component: &VarcComponent, coords: &[NormalizedCoord]) -> Result<bool> {
+        let Some(condition_index) = component.condition_index() else {
+            return Ok(true);
+        };
+        Ok(self
+            .varc
+            .condition(condition_index)?
+            .evaluate(coords, self.var_store.as_ref())?)
+    }
+
+    /// Computes the location of a component from the location of its
+    /// parent.
+    fn component_coords(
+        &self,
+        component: &VarcComponent,
+        coords: &[NormalizedCoord],
+    ) -> Result<Vec<NormalizedCoord>> {
+        let mut component_coords = if component
+            .flags()
+            .contains(VarcFlags::RESET_UNSPECIFIED_AXES)
+        {
+            vec![F2Dot14::ZERO; coords.len()]
+        } else {
+            coords.to_vec()
+        };
+        if component.flags().contains(VarcFlags::HAVE_AXES) {
+            let mut values = component
+                .axis_values()
+                .iter()
+                .map(|value| value as f32)
+                .collect::<Vec<_>>();
+            self.add_deltas(component.axis_values_var_index(), coords, &mut values)?;
+            for (axis_index, value) in component.axis_indices().iter().zip(values) {
+                if let Some(coord) = usize::try_from(axis_index)
+                    .ok()
+                    .and_then(|index| component_coords.get_mut(index))
+                {
+                    *coord = F2Dot14::from_bits(value.round().clamp(-16384.0, 16384.0) as i16);
+                }
+            }
+        }
+        Ok(component_coords)
+    }
+
+    fn component_transform(
+        &self,
+        component: &VarcComponent,
+        coords: &[NormalizedCoord],
+    ) -> Result<DecomposedTransform> {
+        if component.transform_var_index() == NO_VARIATION_INDEX {
+            return Ok(component.transform());
+        }
+        let mut deltas = vec![0.0; component.transform_value_count()];
+        self.add_deltas(component.transform_var_index(), coords, &mut deltas)?;
+        Ok(component.transform_with_deltas(&deltas))
+    }
+
+    fn add_deltas(
+        &self,
+        var_index: u32,
+        coords: &[NormalizedCoord],
+        deltas: &mut [f32],
+    ) -> Result<()> {
+        if let Some(var_store) = self.var_store.as_ref() {
+            var_store.add_deltas(var_index, coords, deltas)?;
+        }
+        Ok(())
+    }
+}
+
+/// Affine transform in the form `[xx, xy, yx, yy, dx, dy]`.
+type Transform = [f32; 6];
+
+/// Returns a transform that applies `b` followed by `a`.
+fn mul_transforms(a: &Transform, b: &Transform) -> Transform {
+    [
+        a[0] * b[0] + a[2] * b[1],
+        a[1] * b[0] + a[3] * b[1],
+        a[0] * b[2] + a[2] * b[3],
+        a[1] * b[2] + a[3] * b[3],
+        a[0] * b[4] + a[2] * b[5] + a[4],
+        a[1] * b[4] + a[3] * b[5] + a[5],
+    ]
+}
+
+/// Converts a decomposed transform into a matrix.
+///
+/// As in HarfBuzz, the skew is `skew(-skew_x, skew_y)`: a positive `skew_x`
+/// leans vertical lines counter-clockwise.
+fn to_matrix(transform: &DecomposedTransform) -> Transform {
+    let DecomposedTransform {
+        translate_x,
+        translate_y,
+        rotation,
+        scale_x,
+        scale_y,
+        skew_x,
+        skew_y,
+        center_x,
+        center_y,
+    } = *transform;
+    let mut matrix = [
+        1.0,
+        0.0,
+        0.0,
+        1.0,
+        translate_x + center_x,
+        translate_y + center_y,
+    ];
+    if rotation != 0.0 {
+        let (sin, cos) = (rotation * PI).sin_cos();
+        matrix = mul_transforms(&matrix, &[cos, sin, -sin, cos, 0.0, 0.0]);
+    }
+    if scale_x != 1.0 || scale_y != 1.0 {
+        matrix = mul_transforms(&matrix, &[scale_x, 0.0, 0.0, scale_y, 0.0, 0.0]);
+    }
+    if skew_x != 0.0 || skew_y != 0.0 {
+        let skew = [
+            1.0,
+            (skew_y * PI).tan(),
+            (-skew_x * PI).tan(),
+            1.0,
+            0.0,
+            0.0,
+        ];
+        matrix = mul_transforms(&matrix, &skew);
+    }
+    if center_x != 0.0 || center_y != 0.0 {
+        matrix = mul_transforms(&matrix, &[1.0, 0.0, 0.0, 1.0, -center_x, -center_y]);
+    }
+    matrix
+}
+
+/// Pen that applies an affine transform to all points before forwarding
+/// them to an inner pen.
+pub struct TransformPen<'a, P> {
+    inner: &'a mut P,
+    transform: Transform,
+}
+
+impl<P> TransformPen<'_, P> {
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let t = &self.transform;
+        (t[0] * x + t[2] * y + t[4], t[1] * x + t[3] * y + t[5])
+    }
+}
+
+impl<P: Pen> Pen for TransformPen<'_, P> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.inner.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.inner.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        let (cx0, cy0) = self.map(cx0, cy0);
+        let (x, y) = self.map(x, y);
+        self.inner.quad_to(cx0, cy0, x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        let (cx0, cy0) = self.map(cx0, cy0);
+        let (cx1, cy1) = self.map(cx1, cy1);
+        let (x, y) = self.map(x, y);
+        self.inner.curve_to(cx0, cy0, cx1, cy1, x, y);
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scale::Context, test_helpers::ExtraTablesFont};
+    use read_fonts::{scaler_test, types::Tag, FontRef};
+
+    // Glyph 1 is glyph 3 at wght=1.0, translated by (100, 50). Glyph 2 is
+    // glyph 1 translated by (10, 0).
+    static VARC_DATA: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version 1.0
+        0x00, 0x00, 0x00, 0x18, // coverage_offset: 24
+        0x00, 0x00, 0x00, 0x00, // multi_var_store_offset: NULL
+        0x00, 0x00, 0x00, 0x00, // condition_list_offset: NULL
+        0x00, 0x00, 0x00, 0x20, // axis_indices_list_offset: 32
+        0x00, 0x00, 0x00, 0x29, // var_composite_glyphs_offset: 41
+        // coverage (24)
+        0x00, 0x01, // format 1
+        0x00, 0x02, // glyph_count
+        0x00, 0x01, 0x00, 0x02, // glyphs 1 and 2
+        // axis indices list (32)
+        0x00, 0x00, 0x00, 0x01, // count
+        0x01, // off_size
+        0x01, 0x03, // offsets
+        0x00, 0x00, // item 0: [0]
+        // var composite glyphs (41)
+        0x00, 0x00, 0x00, 0x02, // count
+        0x01, // off_size
+        0x01, 0x0C, 0x11, // offsets
+        // glyph 1
+        0x33, // flags: reset axes, axes, translate x/y
+        0x00, 0x03, // glyph_id
+        0x00, // axis_indices_index
+        0x40, 0x40, 0x00, // axis_values: [1.0]
+        0x00, 0x64, // translate_x: 100
+        0x00, 0x32, // translate_y: 50
+        // glyph 2
+        0x10, // flags: translate x
+        0x00, 0x01, // glyph_id
+        0x00, 0x0A, // translate_x: 10
+    ];
+
+    fn check_varc_outlines(font_data: &[u8]) {
+        let tables = [(Tag::new(b"VARC"), VARC_DATA)];
+        let font = ExtraTablesFont::new(font_data, &tables);
+        let mut cx = Context::new();
+        // the component location overrides the location of the scaler
+        let mut scaler = cx
+            .new_scaler()
+            .normalized_coords([F2Dot14::from_f32(-1.0)])
+            .build(&font);
+        let mut outline = |gid| {
+            let mut path = scaler_test::Path::default();
+            scaler.outline(GlyphId::new(gid), &mut path).unwrap();
+            path.elements
+        };
+        let glyph1 = outline(1);
+        let glyph2 = outline(2);
+        let mut expected = |dx, dy| {
+            let mut path = scaler_test::Path::default();
+            let mut scaler = cx
+                .new_scaler()
+                .normalized_coords([F2Dot14::from_f32(1.0)])
+                .build(&FontRef::new(font_data).unwrap());
+            scaler
+                .outline(
+                    GlyphId::new(3),
+                    &mut TransformPen {
+                        inner: &mut path,
+                        transform: [1.0, 0.0, 0.0, 1.0, dx, dy],
+                    },
+                )
+                .unwrap();
+            path.elements
+        };
+        assert!(!glyph1.is_empty());
+        assert_eq!(glyph1, expected(100.0, 50.0));
+        assert_eq!(glyph2, expected(110.0, 50.0));
+    }
+
+    #[test]
+    fn glyf_components() {
+        check_varc_outlines(font_test_data::VAZIRMATN_VAR);
+    }
+
+    #[test]
+    fn cff2_components() {
+        check_varc_outlines(font_test_data::CANTARELL_VF_TRIMMED);
+    }
+
+    fn assert_close(a: Transform, b: Transform) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-5, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn decomposed_to_matrix() {
+        assert_close(
+            to_matrix(&DecomposedTransform::default()),
+            [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        );
+        // half turn around (10, 0), then translate
+        let transform = DecomposedTransform {
+            translate_x: 5.0,
+            rotation: 1.0,
+            center_x: 10.0,
+            ..Default::default()
+        };
+        assert_close(to_matrix(&transform), [-1.0, 0.0, 0.0, -1.0, 25.0, 0.0]);
+        let transform = DecomposedTransform {
+            scale_x: 2.0,
+            scale_y: 3.0,
+            skew_x: 0.25,
+            ..Default::default()
+        };
+        assert_close(to_matrix(&transform), [2.0, 0.0, -2.0, 3.0, 0.0, 0.0]);
+    }
+}
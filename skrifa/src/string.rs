@@ -24,7 +24,11 @@
 //! ```
 
 use read_fonts::{
-    tables::name::{CharIter, Name, NameRecord, NameString},
+    tables::{
+        ltag::Ltag,
+        meta::ScriptLangTags,
+        name::{CharIter, Name, NameRecord, NameString},
+    },
     TableProvider,
 };
 
@@ -51,6 +55,7 @@ impl<'a> Iterator for Chars<'a> {
 #[derive(Clone)]
 pub struct LocalizedStrings<'a> {
     name: Option<Name<'a>>,
+    ltag: Option<Ltag<'a>>,
     records: core::slice::Iter<'a, NameRecord>,
     id: StringId,
 }
@@ -63,7 +68,13 @@ impl<'a> LocalizedStrings<'a> {
             .as_ref()
             .map(|name| name.name_record().iter())
             .unwrap_or([].iter());
-        Self { name, records, id }
+        let ltag = font.ltag().ok();
+        Self {
+            name,
+            ltag,
+            records,
+            id,
+        }
     }
 
     /// Returns the informational string identifier for this iterator.
@@ -107,7 +118,7 @@ impl<'a> Iterator for LocalizedStrings<'a> {
         loop {
             let record = self.records.next()?;
             if record.name_id() == self.id {
-                return Some(LocalizedString::new(name, record));
+                return Some(LocalizedString::new(name, self.ltag.as_ref(), record));
             }
         }
     }
@@ -117,6 +128,7 @@ impl Default for LocalizedStrings<'_> {
     fn default() -> Self {
         Self {
             name: None,
+            ltag: None,
             records: [].iter(),
             id: StringId::default(),
         }
@@ -131,8 +143,8 @@ pub struct LocalizedString<'a> {
 }
 
 impl<'a> LocalizedString<'a> {
-    fn new(name: &Name<'a>, record: &NameRecord) -> Self {
-        let language = Language::new(name, record);
+    fn new(name: &Name<'a>, ltag: Option<&Ltag>, record: &NameRecord) -> Self {
+        let language = Language::new(name, ltag, record);
         let value = record.string(name.string_data()).ok();
         Self { language, value }
     }
@@ -159,6 +171,57 @@ impl fmt::Display for LocalizedString<'_> {
     }
 }
 
+/// Iterator over a list of languages declared in the font metadata.
+///
+/// Each item is a
+/// [ScriptLangTag](https://learn.microsoft.com/en-us/typography/opentype/spec/meta#scriptlangtag-values)
+/// string, which is in a form similar to a BCP-47 language tag (for
+/// example, "en-Latn" or "Hant").
+#[derive(Clone, Debug, Default)]
+pub struct Languages<'a> {
+    tags: ScriptLangTags<'a>,
+}
+
+impl<'a> Languages<'a> {
+    /// Creates a new iterator over the languages the font was primarily
+    /// designed for, as declared by the `dlng` entry of the
+    /// [meta](https://learn.microsoft.com/en-us/typography/opentype/spec/meta)
+    /// table.
+    pub fn design(font: &impl TableProvider<'a>) -> Self {
+        let tags = font
+            .meta()
+            .map(|meta| meta.design_languages())
+            .unwrap_or_default();
+        Self { tags }
+    }
+
+    /// Creates a new iterator over the languages the font is capable of
+    /// supporting, as declared by the `slng` entry of the
+    /// [meta](https://learn.microsoft.com/en-us/typography/opentype/spec/meta)
+    /// table.
+    ///
+    /// If the font does not declare any supported languages, this falls back
+    /// to the design languages.
+    pub fn supported(font: &impl TableProvider<'a>) -> Self {
+        let Ok(meta) = font.meta() else {
+            return Self::default();
+        };
+        let mut tags = meta.supported_languages();
+        if tags.clone().next().is_none() {
+            tags = meta.design_languages();
+        }
+        Self { tags }
+    }
+}
+
+impl<'a> Iterator for Languages<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tags.next()
+    }
+}
+
 /// This value is chosen arbitrarily to accommodate common language tags that
 /// are almost always <= 11 bytes (LLL-SSSS-RR where L is primary language, S
 /// is script and R is region) and to keep the Language enum at a reasonable
@@ -176,7 +239,7 @@ enum Language {
 }
 
 impl Language {
-    fn new(name: &Name, record: &NameRecord) -> Option<Self> {
+    fn new(name: &Name, ltag: Option<&Ltag>, record: &NameRecord) -> Option<Self> {
         let language_id = record.language_id();
         // For version 1 name tables, prefer language tags:
         // https://learn.microsoft.com/en-us/typography/opentype/spec/name#naming-table-version-1
@@ -188,9 +251,15 @@ impl Language {
                 .get(index)?
                 .lang_tag(name.string_data())
                 .ok()?;
-            Self::from_name_string(&language_string)
+            Self::from_chars(language_string.chars())
         } else {
             match record.platform_id() {
+                // Unicode platform language ids are indices into the `ltag`
+                // table, with 0xFFFF meaning no language:
+                // <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6name.html>
+                0 if language_id != 0xFFFF => {
+                    Self::from_chars(ltag?.tag(language_id as u32)?.chars())
+                }
                 // We only match Macintosh and Windows language ids.
                 1 | 3 => Self::from_language_id(language_id),
                 _ => None,
@@ -199,10 +268,10 @@ impl Language {
     }
 
     /// Decodes a language tag string into an inline ASCII byte sequence.
-    fn from_name_string(s: &NameString) -> Option<Self> {
+    fn from_chars(chars: impl Iterator<Item = char>) -> Option<Self> {
         let mut buf = [0u8; MAX_INLINE_LANGUAGE_LEN];
         let mut len = 0;
-        for ch in s.chars() {
+        for ch in chars {
             // From "Tags for Identifying Languages" <https://www.rfc-editor.org/rfc/rfc5646.html#page-6>:
             // "Although [RFC5234] refers to octets, the language tags described in
             // this document are sequences of characters from the US-ASCII [ISO646]
@@ -599,10 +668,61 @@ const LANGUAGE_ID_TO_BCP47: &[(u16, &str)] = &[
 
 #[cfg(test)]
 mod tests {
-    use crate::MetadataProvider;
+    use crate::{test_helpers::ExtraTablesFont, MetadataProvider};
 
     use super::*;
-    use read_fonts::FontRef;
+    use read_fonts::{types::Tag, FontRef};
+
+    // name table with a single Unicode platform record referencing ltag
+    // index 1
+    static UNICODE_NAME: &[u8] = &[
+        0x00, 0x00, // version
+        0x00, 0x01, // count
+        0x00, 0x12, // storage_offset
+        0x00, 0x00, // platform_id: Unicode
+        0x00, 0x03, // encoding_id
+        0x00, 0x01, // language_id: ltag index 1
+        0x00, 0x02, // name_id: subfamily
+        0x00, 0x08, // length
+        0x00, 0x00, // string_offset
+        0x00, b'F', 0x00, b'e', 0x00, b't', 0x00, b't', // "Fett"
+    ];
+
+    static LTAG_DATA: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x02, // num_tags
+        0x00, 0x14, 0x00, 0x02, // "en"
+        0x00, 0x16, 0x00, 0x05, // "de-DE"
+        b'e', b'n', b'd', b'e', b'-', b'D', b'E',
+    ];
+
+    // meta table with only a dlng entry
+    static META_DLNG_ONLY: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x00, // reserved
+        0x00, 0x00, 0x00, 0x01, // data_maps_count
+        b'd', b'l', b'n', b'g', 0x00, 0x00, 0x00, 0x1C, 0x00, 0x00, 0x00, 0x0E, // dlng
+        b'L', b'a', b't', b'n', b',', b' ', b'd', b'e', b'-', b'L', b'a', b't', b'n',
+        b',', // "Latn, de-Latn,"
+    ];
+
+    // meta table with both dlng and slng entries
+    static META_DATA: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x00, // reserved
+        0x00, 0x00, 0x00, 0x02, // data_maps_count
+        b'd', b'l', b'n', b'g', 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x04, // dlng
+        b's', b'l', b'n', b'g', 0x00, 0x00, 0x00, 0x2C, 0x00, 0x00, 0x00, 0x09, // slng
+        b'L', b'a', b't', b'n', // "Latn"
+        b'L', b'a', b't', b'n', b',', b'C', b'y', b'r', b'l', // "Latn,Cyrl"
+    ];
+
+    fn font_with_tables<'a>(extra_tables: &'a [(Tag, &'a [u8])]) -> ExtraTablesFont<'a> {
+        ExtraTablesFont::new(font_test_data::NAMES_ONLY, extra_tables)
+    }
 
     #[test]
     fn localized() {
@@ -646,4 +766,54 @@ mod tests {
             "Regular"
         );
     }
+
+    #[test]
+    fn unicode_platform_language_from_ltag() {
+        let tables = [
+            (Tag::new(b"name"), UNICODE_NAME),
+            (Tag::new(b"ltag"), LTAG_DATA),
+        ];
+        let font = font_with_tables(&tables);
+        let string = font
+            .localized_strings(StringId::SUBFAMILY_NAME)
+            .next()
+            .unwrap();
+        assert_eq!(string.language(), Some("de-DE"));
+        assert_eq!(string.to_string(), "Fett");
+        // without ltag, there is no language
+        let font = font_with_tables(&tables[..1]);
+        let string = font
+            .localized_strings(StringId::SUBFAMILY_NAME)
+            .next()
+            .unwrap();
+        assert_eq!(string.language(), None);
+    }
+
+    #[test]
+    fn supported_languages() {
+        let tables = [(Tag::new(b"meta"), META_DATA)];
+        let font = font_with_tables(&tables);
+        assert_eq!(font.design_languages().collect::<Vec<_>>(), ["Latn"]);
+        assert_eq!(
+            font.supported_languages().collect::<Vec<_>>(),
+            ["Latn", "Cyrl"]
+        );
+    }
+
+    #[test]
+    fn supported_languages_falls_back_to_design() {
+        let tables = [(Tag::new(b"meta"), META_DLNG_ONLY)];
+        let font = font_with_tables(&tables);
+        assert_eq!(
+            font.supported_languages().collect::<Vec<_>>(),
+            ["Latn", "de-Latn"]
+        );
+    }
+
+    #[test]
+    fn no_meta_no_languages() {
+        let font = FontRef::new(font_test_data::NAMES_ONLY).unwrap();
+        assert!(font.supported_languages().next().is_none());
+        assert!(font.design_languages().next().is_none());
+    }
 }
@@ -0,0 +1,29 @@
+//! small utilities used in tests
+
+use read_fonts::{types::Tag, FontData, FontRef, TableProvider};
+
+/// A font that serves the tables of an existing font, replacing or adding
+/// tables from raw data.
+pub struct ExtraTablesFont<'a> {
+    font: FontRef<'a>,
+    extra_tables: &'a [(Tag, &'a [u8])],
+}
+
+impl<'a> ExtraTablesFont<'a> {
+    pub fn new(font_data: &'a [u8], extra_tables: &'a [(Tag, &'a [u8])]) -> Self {
+        Self {
+            font: FontRef::new(font_data).unwrap(),
+            extra_tables,
+        }
+    }
+}
+
+impl<'a> TableProvider<'a> for ExtraTablesFont<'a> {
+    fn data_for_tag(&self, tag: Tag) -> Option<FontData<'a>> {
+        self.extra_tables
+            .iter()
+            .find(|(extra_tag, _)| *extra_tag == tag)
+            .map(|(_, data)| FontData::new(data))
+            .or_else(|| self.font.data_for_tag(tag))
+    }
+}
@@ -0,0 +1,1030 @@
+// THIS FILE IS AUTOGENERATED.
+// Any changes to this file will be overwritten.
+// For more information about how codegen works, see font-codegen/README.md
+
+#[allow(unused_imports)]
+use crate::codegen_prelude::*;
+
+pub use read_fonts::tables::bitmap::BitmapFlags;
+
+/// [BitmapSize](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#bitmapsize-record) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitmapSize {
+    /// Offset to index subtable from beginning of EBLC/CBLC.
+    pub index_subtable_array_offset: u32,
+    /// Number of bytes in corresponding index subtables and array.
+    pub index_tables_size: u32,
+    /// There is an index subtable for each range or format change.
+    pub number_of_index_subtables: u32,
+    /// Not used; set to 0.
+    pub color_ref: u32,
+    /// Line metrics for text rendered horizontally.
+    pub hori: SbitLineMetrics,
+    /// Line metrics for text rendered vertically.
+    pub vert: SbitLineMetrics,
+    /// Lowest glyph index for this size.
+    pub start_glyph_index: GlyphId,
+    /// Highest glyph index for this size.
+    pub end_glyph_index: GlyphId,
+    /// Horizontal pixels per em.
+    pub ppem_x: u8,
+    /// Vertical pixels per em.
+    pub ppem_y: u8,
+    /// The Microsoft rasterizer v.1.7 or greater supports the following
+    /// bitDepth values, as described below: 1, 2, 4, and 8 (and 32 for CBLC).
+    pub bit_depth: u8,
+    /// Vertical or horizontal.
+    pub flags: BitmapFlags,
+}
+
+impl BitmapSize {
+    /// Construct a new `BitmapSize`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        index_subtable_array_offset: u32,
+        index_tables_size: u32,
+        number_of_index_subtables: u32,
+        color_ref: u32,
+        hori: SbitLineMetrics,
+        vert: SbitLineMetrics,
+        start_glyph_index: GlyphId,
+        end_glyph_index: GlyphId,
+        ppem_x: u8,
+        ppem_y: u8,
+        bit_depth: u8,
+        flags: BitmapFlags,
+    ) -> Self {
+        Self {
+            index_subtable_array_offset,
+            index_tables_size,
+            number_of_index_subtables,
+            color_ref,
+            hori,
+            vert,
+            start_glyph_index,
+            end_glyph_index,
+            ppem_x,
+            ppem_y,
+            bit_depth,
+            flags,
+        }
+    }
+}
+
+impl FontWrite for BitmapSize {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.index_subtable_array_offset.write_into(writer);
+        self.index_tables_size.write_into(writer);
+        self.number_of_index_subtables.write_into(writer);
+        self.color_ref.write_into(writer);
+        self.hori.write_into(writer);
+        self.vert.write_into(writer);
+        self.start_glyph_index.write_into(writer);
+        self.end_glyph_index.write_into(writer);
+        self.ppem_x.write_into(writer);
+        self.ppem_y.write_into(writer);
+        self.bit_depth.write_into(writer);
+        self.flags.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("BitmapSize")
+    }
+}
+
+impl Validate for BitmapSize {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::BitmapSize> for BitmapSize {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::BitmapSize, offset_data: FontData) -> Self {
+        BitmapSize {
+            index_subtable_array_offset: obj.index_subtable_array_offset(),
+            index_tables_size: obj.index_tables_size(),
+            number_of_index_subtables: obj.number_of_index_subtables(),
+            color_ref: obj.color_ref(),
+            hori: obj.hori().to_owned_obj(offset_data),
+            vert: obj.vert().to_owned_obj(offset_data),
+            start_glyph_index: obj.start_glyph_index(),
+            end_glyph_index: obj.end_glyph_index(),
+            ppem_x: obj.ppem_x(),
+            ppem_y: obj.ppem_y(),
+            bit_depth: obj.bit_depth(),
+            flags: obj.flags(),
+        }
+    }
+}
+
+/// [SbitLineMetrics](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#sbitlinemetrics-record) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SbitLineMetrics {
+    pub ascender: i8,
+    pub descender: i8,
+    pub width_max: u8,
+    pub caret_slope_numerator: i8,
+    pub caret_slope_denominator: u8,
+    pub caret_offset: i8,
+    pub min_origin_sb: i8,
+    pub min_advance_sb: i8,
+    pub max_before_bl: i8,
+    pub min_after_bl: i8,
+    pub pad1: i8,
+    pub pad2: i8,
+}
+
+impl SbitLineMetrics {
+    /// Construct a new `SbitLineMetrics`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ascender: i8,
+        descender: i8,
+        width_max: u8,
+        caret_slope_numerator: i8,
+        caret_slope_denominator: u8,
+        caret_offset: i8,
+        min_origin_sb: i8,
+        min_advance_sb: i8,
+        max_before_bl: i8,
+        min_after_bl: i8,
+        pad1: i8,
+        pad2: i8,
+    ) -> Self {
+        Self {
+            ascender,
+            descender,
+            width_max,
+            caret_slope_numerator,
+            caret_slope_denominator,
+            caret_offset,
+            min_origin_sb,
+            min_advance_sb,
+            max_before_bl,
+            min_after_bl,
+            pad1,
+            pad2,
+        }
+    }
+}
+
+impl FontWrite for SbitLineMetrics {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.ascender.write_into(writer);
+        self.descender.write_into(writer);
+        self.width_max.write_into(writer);
+        self.caret_slope_numerator.write_into(writer);
+        self.caret_slope_denominator.write_into(writer);
+        self.caret_offset.write_into(writer);
+        self.min_origin_sb.write_into(writer);
+        self.min_advance_sb.write_into(writer);
+        self.max_before_bl.write_into(writer);
+        self.min_after_bl.write_into(writer);
+        self.pad1.write_into(writer);
+        self.pad2.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("SbitLineMetrics")
+    }
+}
+
+impl Validate for SbitLineMetrics {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::SbitLineMetrics> for SbitLineMetrics {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::SbitLineMetrics, _: FontData) -> Self {
+        SbitLineMetrics {
+            ascender: obj.ascender(),
+            descender: obj.descender(),
+            width_max: obj.width_max(),
+            caret_slope_numerator: obj.caret_slope_numerator(),
+            caret_slope_denominator: obj.caret_slope_denominator(),
+            caret_offset: obj.caret_offset(),
+            min_origin_sb: obj.min_origin_sb(),
+            min_advance_sb: obj.min_advance_sb(),
+            max_before_bl: obj.max_before_bl(),
+            min_after_bl: obj.min_after_bl(),
+            pad1: obj.pad1(),
+            pad2: obj.pad2(),
+        }
+    }
+}
+
+impl FontWrite for BitmapFlags {
+    fn write_into(&self, writer: &mut TableWriter) {
+        writer.write_slice(&self.bits().to_be_bytes())
+    }
+}
+
+/// [BigGlyphMetrics](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#bigglyphmetrics) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BigGlyphMetrics {
+    /// Number of rows of data.
+    pub height: u8,
+    /// Number of columns of data.
+    pub width: u8,
+    /// Distance in pixels from the horizontal origin to the left edge of the bitmap.
+    pub hori_bearing_x: i8,
+    /// Distance in pixels from the horizontal origin to the top edge of the bitmap.
+    pub hori_bearing_y: i8,
+    /// Horizontal advance width in pixels.
+    pub hori_advance: u8,
+    /// Distance in pixels from the vertical origin to the left edge of the bitmap.
+    pub vert_bearing_x: i8,
+    /// Distance in pixels from the vertical origin to the top edge of the bitmap.
+    pub vert_bearing_y: i8,
+    /// Vertical advance width in pixels.
+    pub vert_advance: u8,
+}
+
+impl BigGlyphMetrics {
+    /// Construct a new `BigGlyphMetrics`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        height: u8,
+        width: u8,
+        hori_bearing_x: i8,
+        hori_bearing_y: i8,
+        hori_advance: u8,
+        vert_bearing_x: i8,
+        vert_bearing_y: i8,
+        vert_advance: u8,
+    ) -> Self {
+        Self {
+            height,
+            width,
+            hori_bearing_x,
+            hori_bearing_y,
+            hori_advance,
+            vert_bearing_x,
+            vert_bearing_y,
+            vert_advance,
+        }
+    }
+}
+
+impl FontWrite for BigGlyphMetrics {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.height.write_into(writer);
+        self.width.write_into(writer);
+        self.hori_bearing_x.write_into(writer);
+        self.hori_bearing_y.write_into(writer);
+        self.hori_advance.write_into(writer);
+        self.vert_bearing_x.write_into(writer);
+        self.vert_bearing_y.write_into(writer);
+        self.vert_advance.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("BigGlyphMetrics")
+    }
+}
+
+impl Validate for BigGlyphMetrics {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::BigGlyphMetrics> for BigGlyphMetrics {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::BigGlyphMetrics, _: FontData) -> Self {
+        BigGlyphMetrics {
+            height: obj.height(),
+            width: obj.width(),
+            hori_bearing_x: obj.hori_bearing_x(),
+            hori_bearing_y: obj.hori_bearing_y(),
+            hori_advance: obj.hori_advance(),
+            vert_bearing_x: obj.vert_bearing_x(),
+            vert_bearing_y: obj.vert_bearing_y(),
+            vert_advance: obj.vert_advance(),
+        }
+    }
+}
+
+/// [SmallGlyphMetrics](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#smallglyphmetrics) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SmallGlyphMetrics {
+    /// Number of rows of data.
+    pub height: u8,
+    /// Number of columns of data.
+    pub width: u8,
+    /// Distance in pixels from the horizontal origin to the left edge of the bitmap (for horizontal text); or distance in pixels from the vertical origin to the top edge of the bitmap (for vertical text).
+    pub bearing_x: i8,
+    /// Distance in pixels from the horizontal origin to the top edge of the bitmap (for horizontal text); or distance in pixels from the vertical origin to the left edge of the bitmap (for vertical text).
+    pub bearing_y: i8,
+    /// Horizontal or vertical advance width in pixels.
+    pub advance: u8,
+}
+
+impl SmallGlyphMetrics {
+    /// Construct a new `SmallGlyphMetrics`
+    pub fn new(height: u8, width: u8, bearing_x: i8, bearing_y: i8, advance: u8) -> Self {
+        Self {
+            height,
+            width,
+            bearing_x,
+            bearing_y,
+            advance,
+        }
+    }
+}
+
+impl FontWrite for SmallGlyphMetrics {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.height.write_into(writer);
+        self.width.write_into(writer);
+        self.bearing_x.write_into(writer);
+        self.bearing_y.write_into(writer);
+        self.advance.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("SmallGlyphMetrics")
+    }
+}
+
+impl Validate for SmallGlyphMetrics {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::SmallGlyphMetrics> for SmallGlyphMetrics {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::SmallGlyphMetrics, _: FontData) -> Self {
+        SmallGlyphMetrics {
+            height: obj.height(),
+            width: obj.width(),
+            bearing_x: obj.bearing_x(),
+            bearing_y: obj.bearing_y(),
+            advance: obj.advance(),
+        }
+    }
+}
+
+/// [IndexSubtableArray](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtablearray) table.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtableArray {
+    /// First glyph ID of this range.
+    pub first_glyph_index: GlyphId,
+    /// Last glyph ID of this range (inclusive).
+    pub last_glyph_index: GlyphId,
+    /// Add to indexSubTableArrayOffset to get offset from beginning of EBLC.
+    pub additional_offset_to_index_subtable: u32,
+}
+
+impl IndexSubtableArray {
+    /// Construct a new `IndexSubtableArray`
+    pub fn new(
+        first_glyph_index: GlyphId,
+        last_glyph_index: GlyphId,
+        additional_offset_to_index_subtable: u32,
+    ) -> Self {
+        Self {
+            first_glyph_index,
+            last_glyph_index,
+            additional_offset_to_index_subtable,
+        }
+    }
+}
+
+impl FontWrite for IndexSubtableArray {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.first_glyph_index.write_into(writer);
+        self.last_glyph_index.write_into(writer);
+        self.additional_offset_to_index_subtable.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtableArray")
+    }
+}
+
+impl Validate for IndexSubtableArray {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtableArray<'a>> for IndexSubtableArray {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtableArray<'a>, _: FontData) -> Self {
+        IndexSubtableArray {
+            first_glyph_index: obj.first_glyph_index(),
+            last_glyph_index: obj.last_glyph_index(),
+            additional_offset_to_index_subtable: obj.additional_offset_to_index_subtable(),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtableArray<'a>> for IndexSubtableArray {}
+
+impl<'a> FontRead<'a> for IndexSubtableArray {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtableArray as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [IndexSubtables](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtables) format type.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndexSubtable {
+    Format1(IndexSubtable1),
+    Format2(IndexSubtable2),
+    Format3(IndexSubtable3),
+    Format4(IndexSubtable4),
+    Format5(IndexSubtable5),
+}
+
+impl IndexSubtable {
+    /// Construct a new `IndexSubtable1` subtable
+    pub fn format_1(image_format: u16, image_data_offset: u32, sbit_offsets: Vec<u32>) -> Self {
+        Self::Format1(IndexSubtable1::new(
+            image_format,
+            image_data_offset,
+            sbit_offsets,
+        ))
+    }
+
+    /// Construct a new `IndexSubtable2` subtable
+    pub fn format_2(
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        big_metrics: Vec<BigGlyphMetrics>,
+    ) -> Self {
+        Self::Format2(IndexSubtable2::new(
+            image_format,
+            image_data_offset,
+            image_size,
+            big_metrics,
+        ))
+    }
+
+    /// Construct a new `IndexSubtable3` subtable
+    pub fn format_3(image_format: u16, image_data_offset: u32, sbit_offsets: Vec<u16>) -> Self {
+        Self::Format3(IndexSubtable3::new(
+            image_format,
+            image_data_offset,
+            sbit_offsets,
+        ))
+    }
+
+    /// Construct a new `IndexSubtable4` subtable
+    pub fn format_4(
+        image_format: u16,
+        image_data_offset: u32,
+        glyph_array: Vec<GlyphIdOffsetPair>,
+    ) -> Self {
+        Self::Format4(IndexSubtable4::new(
+            image_format,
+            image_data_offset,
+            glyph_array,
+        ))
+    }
+
+    /// Construct a new `IndexSubtable5` subtable
+    pub fn format_5(
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        big_metrics: Vec<BigGlyphMetrics>,
+        glyph_array: Vec<GlyphId>,
+    ) -> Self {
+        Self::Format5(IndexSubtable5::new(
+            image_format,
+            image_data_offset,
+            image_size,
+            big_metrics,
+            glyph_array,
+        ))
+    }
+}
+
+impl Default for IndexSubtable {
+    fn default() -> Self {
+        Self::Format1(Default::default())
+    }
+}
+
+impl FontWrite for IndexSubtable {
+    fn write_into(&self, writer: &mut TableWriter) {
+        match self {
+            Self::Format1(item) => item.write_into(writer),
+            Self::Format2(item) => item.write_into(writer),
+            Self::Format3(item) => item.write_into(writer),
+            Self::Format4(item) => item.write_into(writer),
+            Self::Format5(item) => item.write_into(writer),
+        }
+    }
+    fn table_type(&self) -> TableType {
+        match self {
+            Self::Format1(item) => item.table_type(),
+            Self::Format2(item) => item.table_type(),
+            Self::Format3(item) => item.table_type(),
+            Self::Format4(item) => item.table_type(),
+            Self::Format5(item) => item.table_type(),
+        }
+    }
+}
+
+impl Validate for IndexSubtable {
+    fn validate_impl(&self, ctx: &mut ValidationCtx) {
+        match self {
+            Self::Format1(item) => item.validate_impl(ctx),
+            Self::Format2(item) => item.validate_impl(ctx),
+            Self::Format3(item) => item.validate_impl(ctx),
+            Self::Format4(item) => item.validate_impl(ctx),
+            Self::Format5(item) => item.validate_impl(ctx),
+        }
+    }
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::IndexSubtable<'_>> for IndexSubtable {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable, _: FontData) -> Self {
+        use read_fonts::tables::bitmap::IndexSubtable as ObjRefType;
+        match obj {
+            ObjRefType::Format1(item) => IndexSubtable::Format1(item.to_owned_table()),
+            ObjRefType::Format2(item) => IndexSubtable::Format2(item.to_owned_table()),
+            ObjRefType::Format3(item) => IndexSubtable::Format3(item.to_owned_table()),
+            ObjRefType::Format4(item) => IndexSubtable::Format4(item.to_owned_table()),
+            ObjRefType::Format5(item) => IndexSubtable::Format5(item.to_owned_table()),
+        }
+    }
+}
+
+impl FromTableRef<read_fonts::tables::bitmap::IndexSubtable<'_>> for IndexSubtable {}
+
+impl<'a> FontRead<'a> for IndexSubtable {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+impl From<IndexSubtable1> for IndexSubtable {
+    fn from(src: IndexSubtable1) -> IndexSubtable {
+        IndexSubtable::Format1(src)
+    }
+}
+
+impl From<IndexSubtable2> for IndexSubtable {
+    fn from(src: IndexSubtable2) -> IndexSubtable {
+        IndexSubtable::Format2(src)
+    }
+}
+
+impl From<IndexSubtable3> for IndexSubtable {
+    fn from(src: IndexSubtable3) -> IndexSubtable {
+        IndexSubtable::Format3(src)
+    }
+}
+
+impl From<IndexSubtable4> for IndexSubtable {
+    fn from(src: IndexSubtable4) -> IndexSubtable {
+        IndexSubtable::Format4(src)
+    }
+}
+
+impl From<IndexSubtable5> for IndexSubtable {
+    fn from(src: IndexSubtable5) -> IndexSubtable {
+        IndexSubtable::Format5(src)
+    }
+}
+
+/// [IndexSubTable1](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtable1-variable-metrics-glyphs-with-4-byte-offsets): variable-metrics glyphs with 4-byte offsets.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtable1 {
+    /// Format of EBDT image data.
+    pub image_format: u16,
+    /// Offset to image data in EBDT table.
+    pub image_data_offset: u32,
+    pub sbit_offsets: Vec<u32>,
+}
+
+impl IndexSubtable1 {
+    /// Construct a new `IndexSubtable1`
+    pub fn new(image_format: u16, image_data_offset: u32, sbit_offsets: Vec<u32>) -> Self {
+        Self {
+            image_format,
+            image_data_offset,
+            sbit_offsets: sbit_offsets.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FontWrite for IndexSubtable1 {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        (1 as u16).write_into(writer);
+        self.image_format.write_into(writer);
+        self.image_data_offset.write_into(writer);
+        self.sbit_offsets.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtable1")
+    }
+}
+
+impl Validate for IndexSubtable1 {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtable1<'a>> for IndexSubtable1 {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable1<'a>, _: FontData) -> Self {
+        let offset_data = obj.offset_data();
+        IndexSubtable1 {
+            image_format: obj.image_format(),
+            image_data_offset: obj.image_data_offset(),
+            sbit_offsets: obj.sbit_offsets().to_owned_obj(offset_data),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtable1<'a>> for IndexSubtable1 {}
+
+impl<'a> FontRead<'a> for IndexSubtable1 {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable1 as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [IndexSubTable2](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtable2-all-glyphs-have-identical-metrics): all glyphs have identical metrics.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtable2 {
+    /// Format of EBDT image data.
+    pub image_format: u16,
+    /// Offset to image data in EBDT table.
+    pub image_data_offset: u32,
+    /// All the glyphs are of the same size.
+    pub image_size: u32,
+    /// All glyphs have the same metrics; glyph data may be compressed, byte-aligned, or bit-aligned.
+    pub big_metrics: Vec<BigGlyphMetrics>,
+}
+
+impl IndexSubtable2 {
+    /// Construct a new `IndexSubtable2`
+    pub fn new(
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        big_metrics: Vec<BigGlyphMetrics>,
+    ) -> Self {
+        Self {
+            image_format,
+            image_data_offset,
+            image_size,
+            big_metrics: big_metrics.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FontWrite for IndexSubtable2 {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        (2 as u16).write_into(writer);
+        self.image_format.write_into(writer);
+        self.image_data_offset.write_into(writer);
+        self.image_size.write_into(writer);
+        self.big_metrics.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtable2")
+    }
+}
+
+impl Validate for IndexSubtable2 {
+    fn validate_impl(&self, ctx: &mut ValidationCtx) {
+        ctx.in_table("IndexSubtable2", |ctx| {
+            ctx.in_field("big_metrics", |ctx| {
+                self.big_metrics.validate_impl(ctx);
+            });
+        })
+    }
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtable2<'a>> for IndexSubtable2 {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable2<'a>, _: FontData) -> Self {
+        let offset_data = obj.offset_data();
+        IndexSubtable2 {
+            image_format: obj.image_format(),
+            image_data_offset: obj.image_data_offset(),
+            image_size: obj.image_size(),
+            big_metrics: obj.big_metrics().to_owned_obj(offset_data),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtable2<'a>> for IndexSubtable2 {}
+
+impl<'a> FontRead<'a> for IndexSubtable2 {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable2 as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [IndexSubTable3](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtable3-variable-metrics-glyphs-with-2-byte-offsets): variable-metrics glyphs with 2-byte offsets.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtable3 {
+    /// Format of EBDT image data.
+    pub image_format: u16,
+    /// Offset to image data in EBDT table.
+    pub image_data_offset: u32,
+    pub sbit_offsets: Vec<u16>,
+}
+
+impl IndexSubtable3 {
+    /// Construct a new `IndexSubtable3`
+    pub fn new(image_format: u16, image_data_offset: u32, sbit_offsets: Vec<u16>) -> Self {
+        Self {
+            image_format,
+            image_data_offset,
+            sbit_offsets: sbit_offsets.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FontWrite for IndexSubtable3 {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        (3 as u16).write_into(writer);
+        self.image_format.write_into(writer);
+        self.image_data_offset.write_into(writer);
+        self.sbit_offsets.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtable3")
+    }
+}
+
+impl Validate for IndexSubtable3 {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtable3<'a>> for IndexSubtable3 {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable3<'a>, _: FontData) -> Self {
+        let offset_data = obj.offset_data();
+        IndexSubtable3 {
+            image_format: obj.image_format(),
+            image_data_offset: obj.image_data_offset(),
+            sbit_offsets: obj.sbit_offsets().to_owned_obj(offset_data),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtable3<'a>> for IndexSubtable3 {}
+
+impl<'a> FontRead<'a> for IndexSubtable3 {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable3 as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [IndexSubTable4](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtable3-variable-metrics-glyphs-with-2-byte-offsets): variable-metrics glyphs with sparse glyph codes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtable4 {
+    /// Format of EBDT image data.
+    pub image_format: u16,
+    /// Offset to image data in EBDT table.
+    pub image_data_offset: u32,
+    /// One per glyph.
+    pub glyph_array: Vec<GlyphIdOffsetPair>,
+}
+
+impl IndexSubtable4 {
+    /// Construct a new `IndexSubtable4`
+    pub fn new(
+        image_format: u16,
+        image_data_offset: u32,
+        glyph_array: Vec<GlyphIdOffsetPair>,
+    ) -> Self {
+        Self {
+            image_format,
+            image_data_offset,
+            glyph_array: glyph_array.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FontWrite for IndexSubtable4 {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        (4 as u16).write_into(writer);
+        self.image_format.write_into(writer);
+        self.image_data_offset.write_into(writer);
+        (self.compute_num_glyphs() as u32).write_into(writer);
+        self.glyph_array.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtable4")
+    }
+}
+
+impl Validate for IndexSubtable4 {
+    fn validate_impl(&self, ctx: &mut ValidationCtx) {
+        ctx.in_table("IndexSubtable4", |ctx| {
+            ctx.in_field("glyph_array", |ctx| {
+                self.glyph_array.validate_impl(ctx);
+            });
+        })
+    }
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtable4<'a>> for IndexSubtable4 {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable4<'a>, _: FontData) -> Self {
+        let offset_data = obj.offset_data();
+        IndexSubtable4 {
+            image_format: obj.image_format(),
+            image_data_offset: obj.image_data_offset(),
+            glyph_array: obj.glyph_array().to_owned_obj(offset_data),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtable4<'a>> for IndexSubtable4 {}
+
+impl<'a> FontRead<'a> for IndexSubtable4 {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable4 as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [GlyphIdOffsetPair](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#glyphidoffsetpair-record) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlyphIdOffsetPair {
+    /// Glyph ID of glyph present.
+    pub glyph_id: GlyphId,
+    /// Location in EBDT.
+    pub sbit_offset: u16,
+}
+
+impl GlyphIdOffsetPair {
+    /// Construct a new `GlyphIdOffsetPair`
+    pub fn new(glyph_id: GlyphId, sbit_offset: u16) -> Self {
+        Self {
+            glyph_id,
+            sbit_offset,
+        }
+    }
+}
+
+impl FontWrite for GlyphIdOffsetPair {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.glyph_id.write_into(writer);
+        self.sbit_offset.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("GlyphIdOffsetPair")
+    }
+}
+
+impl Validate for GlyphIdOffsetPair {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::GlyphIdOffsetPair> for GlyphIdOffsetPair {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::GlyphIdOffsetPair, _: FontData) -> Self {
+        GlyphIdOffsetPair {
+            glyph_id: obj.glyph_id(),
+            sbit_offset: obj.sbit_offset(),
+        }
+    }
+}
+
+/// [IndexSubTable5](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc#indexsubtable5-constant-metrics-glyphs-with-sparse-glyph-codes): constant-metrics glyphs with sparse glyph codes
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexSubtable5 {
+    /// Format of EBDT image data.
+    pub image_format: u16,
+    /// Offset to image data in EBDT table.
+    pub image_data_offset: u32,
+    /// All glyphs have the same data size.
+    pub image_size: u32,
+    /// All glyphs have the same metrics.
+    pub big_metrics: Vec<BigGlyphMetrics>,
+    /// One per glyph, sorted by glyhph ID.
+    pub glyph_array: Vec<GlyphId>,
+}
+
+impl IndexSubtable5 {
+    /// Construct a new `IndexSubtable5`
+    pub fn new(
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        big_metrics: Vec<BigGlyphMetrics>,
+        glyph_array: Vec<GlyphId>,
+    ) -> Self {
+        Self {
+            image_format,
+            image_data_offset,
+            image_size,
+            big_metrics: big_metrics.into_iter().map(Into::into).collect(),
+            glyph_array: glyph_array.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FontWrite for IndexSubtable5 {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        (5 as u16).write_into(writer);
+        self.image_format.write_into(writer);
+        self.image_data_offset.write_into(writer);
+        self.image_size.write_into(writer);
+        self.big_metrics.write_into(writer);
+        (array_len(&self.glyph_array).unwrap() as u32).write_into(writer);
+        self.glyph_array.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("IndexSubtable5")
+    }
+}
+
+impl Validate for IndexSubtable5 {
+    fn validate_impl(&self, ctx: &mut ValidationCtx) {
+        ctx.in_table("IndexSubtable5", |ctx| {
+            ctx.in_field("big_metrics", |ctx| {
+                self.big_metrics.validate_impl(ctx);
+            });
+            ctx.in_field("glyph_array", |ctx| {
+                if self.glyph_array.len() > (u32::MAX as usize) {
+                    ctx.report("array exceeds max length");
+                }
+            });
+        })
+    }
+}
+
+impl<'a> FromObjRef<read_fonts::tables::bitmap::IndexSubtable5<'a>> for IndexSubtable5 {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::IndexSubtable5<'a>, _: FontData) -> Self {
+        let offset_data = obj.offset_data();
+        IndexSubtable5 {
+            image_format: obj.image_format(),
+            image_data_offset: obj.image_data_offset(),
+            image_size: obj.image_size(),
+            big_metrics: obj.big_metrics().to_owned_obj(offset_data),
+            glyph_array: obj.glyph_array().to_owned_obj(offset_data),
+        }
+    }
+}
+
+impl<'a> FromTableRef<read_fonts::tables::bitmap::IndexSubtable5<'a>> for IndexSubtable5 {}
+
+impl<'a> FontRead<'a> for IndexSubtable5 {
+    fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+        <read_fonts::tables::bitmap::IndexSubtable5 as FontRead>::read(data)
+            .map(|x| x.to_owned_table())
+    }
+}
+
+/// [EbdtComponent](https://learn.microsoft.com/en-us/typography/opentype/spec/ebdt#ebdtcomponent-record) record.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BdtComponent {
+    /// Component glyph ID.
+    pub glyph_id: GlyphId,
+    /// Position of component left.
+    pub x_offset: i8,
+    /// Position of component top.
+    pub y_offset: i8,
+}
+
+impl BdtComponent {
+    /// Construct a new `BdtComponent`
+    pub fn new(glyph_id: GlyphId, x_offset: i8, y_offset: i8) -> Self {
+        Self {
+            glyph_id,
+            x_offset,
+            y_offset,
+        }
+    }
+}
+
+impl FontWrite for BdtComponent {
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.glyph_id.write_into(writer);
+        self.x_offset.write_into(writer);
+        self.y_offset.write_into(writer);
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("BdtComponent")
+    }
+}
+
+impl Validate for BdtComponent {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FromObjRef<read_fonts::tables::bitmap::BdtComponent> for BdtComponent {
+    fn from_obj_ref(obj: &read_fonts::tables::bitmap::BdtComponent, _: FontData) -> Self {
+        BdtComponent {
+            glyph_id: obj.glyph_id(),
+            x_offset: obj.x_offset(),
+            y_offset: obj.y_offset(),
+        }
+    }
+}
@@ -125,6 +125,38 @@ pub struct TTCHeader {
     pub dsig_offset: Option<u32>,
 }
 
+impl FontWrite for TTCHeader {
+    #[allow(clippy::unnecessary_cast)]
+    fn write_into(&self, writer: &mut TableWriter) {
+        self.ttc_tag.write_into(writer);
+        let version = self.compute_version() as MajorMinor;
+        version.write_into(writer);
+        self.num_fonts.write_into(writer);
+        self.table_directory_offsets.write_into(writer);
+        version.compatible((2, 0)).then(|| {
+            self.dsig_tag
+                .as_ref()
+                .expect("missing versioned field should have failed validation")
+                .write_into(writer)
+        });
+        version.compatible((2, 0)).then(|| {
+            self.dsig_length
+                .as_ref()
+                .expect("missing versioned field should have failed validation")
+                .write_into(writer)
+        });
+        version.compatible((2, 0)).then(|| {
+            self.dsig_offset
+                .as_ref()
+                .expect("missing versioned field should have failed validation")
+                .write_into(writer)
+        });
+    }
+    fn table_type(&self) -> TableType {
+        TableType::Named("TTCHeader")
+    }
+}
+
 impl Validate for TTCHeader {
     fn validate_impl(&self, ctx: &mut ValidationCtx) {
         ctx.in_table("TTCHeader", |ctx| {
@@ -1,14 +1,21 @@
 //!  A builder for top-level font objects
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::{borrow::Cow, fmt::Display};
 
-use read_fonts::{FontRef, TableProvider};
-use types::{Tag, TT_SFNT_VERSION};
+use read_fonts::{checksum::CHECKSUM_MAGIC, FontRef, TableProvider};
+use types::{Tag, CFF_SFTN_VERSION, TTC_HEADER_TAG, TT_SFNT_VERSION};
 
 include!("../generated/generated_font.rs");
 
 const TABLE_RECORD_LEN: usize = 16;
+const TABLE_DIRECTORY_HEADER_LEN: usize = 12;
+const DSIG: Tag = Tag::new(b"DSIG");
+const HEAD: Tag = Tag::new(b"head");
+const CFF: Tag = Tag::new(b"CFF ");
+const CFF2: Tag = Tag::new(b"CFF2");
+/// The location of `checksumAdjustment` in the head table.
+const CHECKSUM_ADJUSTMENT_RANGE: std::ops::Range<usize> = 8..12;
 
 /// Build a font from some set of tables.
 #[derive(Debug, Clone, Default)]
@@ -16,6 +23,16 @@ pub struct FontBuilder<'a> {
     tables: BTreeMap<Tag, Cow<'a, [u8]>>,
 }
 
+/// Build a font collection (TTC) from several fonts.
+///
+/// Tables that are byte-for-byte identical in more than one font are only
+/// written once, and are shared between the table directories of those fonts.
+#[derive(Debug, Clone, Default)]
+pub struct CollectionBuilder<'a> {
+    fonts: Vec<FontBuilder<'a>>,
+    dsig: Option<Cow<'a, [u8]>>,
+}
+
 /// An error returned when attempting to add a table to the builder.
 ///
 /// This wraps a compilation error, adding the tag of the table where it was
@@ -96,6 +113,15 @@ impl<'a> FontBuilder<'a> {
         self.tables.contains_key(&tag)
     }
 
+    /// The sfnt version for the table directory, based on the outline format.
+    fn sfnt_version(&self) -> u32 {
+        if self.contains(CFF) || self.contains(CFF2) {
+            CFF_SFTN_VERSION
+        } else {
+            TT_SFNT_VERSION
+        }
+    }
+
     /// Assemble all the tables into a binary font file with a [Table Directory].
     ///
     /// [Table Directory]: https://learn.microsoft.com/en-us/typography/opentype/spec/otff#table-directory
@@ -105,6 +131,7 @@ impl<'a> FontBuilder<'a> {
             + self.tables.len() * TABLE_RECORD_LEN;
 
         let mut position = header_len as u32;
+        let mut adjustment_position = None;
         let table_records: Vec<_> = self
             .tables
             .iter_mut()
@@ -112,13 +139,23 @@ impl<'a> FontBuilder<'a> {
                 let offset = position;
                 let length = data.len() as u32;
                 position += length;
-                let (checksum, padding) = checksum_and_padding(data);
+                let (mut checksum, padding) = checksum_and_padding(data);
                 position += padding;
+                // the head checksum is computed with a zero checksumAdjustment
+                if *tag == HEAD {
+                    if let Some(adjustment) = data.get(CHECKSUM_ADJUSTMENT_RANGE) {
+                        let adjustment = u32::from_be_bytes(adjustment.try_into().unwrap());
+                        checksum = checksum.wrapping_sub(adjustment);
+                        adjustment_position =
+                            Some(offset as usize + CHECKSUM_ADJUSTMENT_RANGE.start);
+                    }
+                }
                 TableRecord::new(*tag, checksum, offset, length)
             })
             .collect();
 
-        let directory = TableDirectory::from_table_records(table_records);
+        let mut directory = TableDirectory::from_table_records(table_records);
+        directory.sfnt_version = self.sfnt_version();
 
         let mut writer = TableWriter::default();
         directory.write_into(&mut writer);
@@ -129,6 +166,187 @@ impl<'a> FontBuilder<'a> {
             let padding = [0u8; 4];
             data.extend_from_slice(&padding[..rem]);
         }
+        if let Some(pos) = adjustment_position {
+            let adjustment_range = pos..pos + 4;
+            data[adjustment_range.clone()].fill(0);
+            let (checksum, _) = checksum_and_padding(&data);
+            let adjustment = CHECKSUM_MAGIC.wrapping_sub(checksum);
+            data[adjustment_range].copy_from_slice(&adjustment.to_be_bytes());
+        }
+        data
+    }
+
+    /// Assemble all the tables into a [WOFF] file.
+    ///
+    /// [WOFF]: https://www.w3.org/TR/WOFF/
+    #[cfg(feature = "woff")]
+    pub fn build_woff(&mut self) -> Vec<u8> {
+        let sfnt = self.build();
+        // we just wrote the table directory, so this can't fail
+        crate::woff::encode_woff(&FontRef::new(&sfnt).unwrap())
+    }
+
+    /// Assemble all the tables into a [WOFF2] file.
+    ///
+    /// [WOFF2]: https://www.w3.org/TR/WOFF2/
+    #[cfg(feature = "woff")]
+    pub fn build_woff2(&mut self) -> Vec<u8> {
+        let sfnt = self.build();
+        crate::woff::encode_woff2(&FontRef::new(&sfnt).unwrap())
+    }
+}
+
+impl<'a> CollectionBuilder<'a> {
+    /// Create a new builder to compile a font collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a font to the collection.
+    ///
+    /// Fonts are stored in the order in which they are added.
+    pub fn add_font(&mut self, font: FontBuilder<'a>) -> &mut Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// Set the data for the collection's digital signature.
+    ///
+    /// If this is set, a version 2.0 [TTC Header] is written, with the
+    /// signature at the end of the file.
+    ///
+    /// [TTC Header]: https://learn.microsoft.com/en-us/typography/opentype/spec/otff#ttc-header
+    pub fn set_dsig(&mut self, data: impl Into<Cow<'a, [u8]>>) -> &mut Self {
+        self.dsig = Some(data.into());
+        self
+    }
+
+    /// Returns the number of fonts in the collection.
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Returns `true` if no fonts have been added.
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// Assemble all the fonts into a binary font collection file.
+    pub fn build(&self) -> Vec<u8> {
+        let header_len = std::mem::size_of::<u32>() * 3 // tag, version, num_fonts
+            + self.fonts.len() * std::mem::size_of::<u32>()
+            + if self.dsig.is_some() {
+                std::mem::size_of::<u32>() * 3
+            } else {
+                0
+            };
+
+        let mut position = header_len;
+        let table_directory_offsets = self
+            .fonts
+            .iter()
+            .map(|font| {
+                let offset = position as u32;
+                position += TABLE_DIRECTORY_HEADER_LEN + font.tables.len() * TABLE_RECORD_LEN;
+                offset
+            })
+            .collect();
+
+        // identical tables share a single offset. Each head table gets its
+        // own checksumAdjustment, which depends on the font's whole table
+        // directory, so head tables are only shared by identical fonts.
+        let mut offsets: HashMap<&[u8], u32> = HashMap::new();
+        let mut head_offsets: HashMap<&BTreeMap<Tag, Cow<[u8]>>, u32> = HashMap::new();
+        let mut table_data = Vec::new();
+        let mut adjustment_positions = Vec::new();
+        let directories: Vec<_> = self
+            .fonts
+            .iter()
+            .map(|font| {
+                let table_records = font
+                    .tables
+                    .iter()
+                    .map(|(tag, data)| {
+                        let alloc = || {
+                            let offset = position as u32;
+                            position += round4(data.len());
+                            table_data.push(data.as_ref());
+                            offset
+                        };
+                        let offset = if *tag == HEAD {
+                            *head_offsets.entry(&font.tables).or_insert_with(alloc)
+                        } else {
+                            *offsets.entry(data.as_ref()).or_insert_with(alloc)
+                        };
+                        let (mut checksum, _) = checksum_and_padding(data);
+                        // the head checksum is computed with a zero checksumAdjustment
+                        if *tag == HEAD {
+                            if let Some(adjustment) = data.get(CHECKSUM_ADJUSTMENT_RANGE) {
+                                let adjustment = u32::from_be_bytes(adjustment.try_into().unwrap());
+                                checksum = checksum.wrapping_sub(adjustment);
+                                adjustment_positions
+                                    .push(offset as usize + CHECKSUM_ADJUSTMENT_RANGE.start);
+                            }
+                        }
+                        TableRecord::new(*tag, checksum, offset, data.len() as u32)
+                    })
+                    .collect();
+                let mut directory = TableDirectory::from_table_records(table_records);
+                directory.sfnt_version = font.sfnt_version();
+                directory
+            })
+            .collect();
+
+        let header = TTCHeader {
+            ttc_tag: TTC_HEADER_TAG,
+            num_fonts: self.fonts.len() as u32,
+            table_directory_offsets,
+            dsig_tag: self
+                .dsig
+                .as_ref()
+                .map(|_| u32::from_be_bytes(DSIG.to_be_bytes())),
+            dsig_length: self.dsig.as_ref().map(|dsig| dsig.len() as u32),
+            dsig_offset: self.dsig.as_ref().map(|_| position as u32),
+        };
+
+        let mut writer = TableWriter::default();
+        header.write_into(&mut writer);
+        for directory in &directories {
+            directory.write_into(&mut writer);
+        }
+        let mut data = writer.into_data().bytes;
+        for table in table_data.into_iter().chain(self.dsig.as_deref()) {
+            data.extend_from_slice(table);
+            let rem = round4(table.len()) - table.len();
+            let padding = [0u8; 4];
+            data.extend_from_slice(&padding[..rem]);
+        }
+
+        // each font's checksumAdjustment is computed as if it were a
+        // standalone font, from its table directory and table checksums.
+        let mut adjustment_positions = adjustment_positions.into_iter();
+        for (directory, offset) in directories.iter().zip(&header.table_directory_offsets) {
+            let has_head = directory
+                .table_records
+                .iter()
+                .any(|record| record.tag == HEAD && record.length >= 12);
+            if !has_head {
+                continue;
+            }
+            let start = *offset as usize;
+            let directory_len =
+                TABLE_DIRECTORY_HEADER_LEN + directory.table_records.len() * TABLE_RECORD_LEN;
+            let (directory_sum, _) = checksum_and_padding(&data[start..start + directory_len]);
+            let font_sum = directory
+                .table_records
+                .iter()
+                .fold(directory_sum, |sum, record| {
+                    sum.wrapping_add(record.checksum)
+                });
+            let adjustment = CHECKSUM_MAGIC.wrapping_sub(font_sum);
+            let pos = adjustment_positions.next().unwrap();
+            data[pos..pos + 4].copy_from_slice(&adjustment.to_be_bytes());
+        }
         data
     }
 }
@@ -160,7 +378,11 @@ fn checksum_and_padding(table: &[u8]) -> (u32, u32) {
 
 impl TTCHeader {
     fn compute_version(&self) -> MajorMinor {
-        panic!("TTCHeader writing not supported (yet)")
+        if self.dsig_tag.is_some() {
+            MajorMinor::VERSION_2_0
+        } else {
+            MajorMinor::VERSION_1_0
+        }
     }
 }
 
@@ -178,10 +400,13 @@ impl std::error::Error for BuilderError {
 
 #[cfg(test)]
 mod tests {
-    use font_types::Tag;
-    use read_fonts::FontRef;
+    use font_types::{MajorMinor, Tag};
+    use read_fonts::{
+        checksum::{checksum, CHECKSUM_MAGIC},
+        CollectionRef, FontData, FontRead, FontRef, TTCHeader,
+    };
 
-    use crate::{font_builder::checksum_and_padding, FontBuilder};
+    use crate::{font_builder::checksum_and_padding, CollectionBuilder, FontBuilder};
 
     #[test]
     fn sets_binary_search_assists() {
@@ -205,6 +430,148 @@ mod tests {
         FontBuilder::default().build();
     }
 
+    #[test]
+    fn sets_checksum_adjustment() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut head = font
+            .table_data(Tag::new(b"head"))
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        head[8..12].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let mut builder = FontBuilder::new();
+        builder
+            .add_raw(Tag::new(b"head"), head)
+            .add_raw(Tag::new(b"abcd"), b"odd length".to_vec())
+            .copy_missing_tables(font);
+        let data = builder.build();
+        assert_eq!(checksum(&data), CHECKSUM_MAGIC);
+        let built = FontRef::new(&data).unwrap();
+        assert_eq!(built.checksum_mismatches().collect::<Vec<_>>(), []);
+    }
+
+    #[test]
+    fn collection_shares_tables() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut regular = FontBuilder::new();
+        regular.copy_missing_tables(font.clone());
+        let mut bold = FontBuilder::new();
+        bold.add_raw(Tag::new(b"name"), b"not really a name table".to_vec())
+            .copy_missing_tables(font.clone());
+        let mut builder = CollectionBuilder::new();
+        builder.add_font(regular.clone()).add_font(bold);
+        let data = builder.build();
+
+        let collection = CollectionRef::new(&data).unwrap();
+        assert_eq!(collection.len(), 2);
+        let (first, second) = (collection.get(0).unwrap(), collection.get(1).unwrap());
+        let num_tables = font.table_directory.num_tables() as usize;
+        for record in font.table_directory.table_records() {
+            let tag = record.tag();
+            let expected = font.table_data(tag).unwrap().as_bytes();
+            if tag == Tag::new(b"head") {
+                // each font has its own checksumAdjustment
+                continue;
+            }
+            assert_eq!(first.table_data(tag).unwrap().as_bytes(), expected);
+            if tag == Tag::new(b"name") {
+                assert_eq!(
+                    second.table_data(tag).unwrap().as_bytes(),
+                    b"not really a name table"
+                );
+            } else {
+                assert_eq!(second.table_data(tag).unwrap().as_bytes(), expected);
+            }
+        }
+        // only the name and head tables are stored twice
+        let offsets = |font: &FontRef| {
+            font.table_directory
+                .table_records()
+                .iter()
+                .map(|record| (record.tag(), record.offset()))
+                .collect::<Vec<_>>()
+        };
+        let shared = offsets(&first)
+            .into_iter()
+            .zip(offsets(&second))
+            .filter(|(a, b)| a == b)
+            .count();
+        assert_eq!(shared, num_tables - 2);
+        assert!(data.len() < regular.build().len() * 2);
+        let header = TTCHeader::read(FontData::new(&data)).unwrap();
+        assert_eq!(header.version(), MajorMinor::VERSION_1_0);
+        assert_eq!(header.dsig_tag(), None);
+    }
+
+    #[test]
+    fn collection_sets_checksum_adjustments() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut head = font
+            .table_data(Tag::new(b"head"))
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        head[8..12].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let mut regular = FontBuilder::new();
+        regular
+            .add_raw(Tag::new(b"head"), head)
+            .copy_missing_tables(font.clone());
+        let mut bold = regular.clone();
+        bold.add_raw(Tag::new(b"name"), b"not really a name table".to_vec());
+        let mut builder = CollectionBuilder::new();
+        builder
+            .add_font(regular.clone())
+            .add_font(bold)
+            .add_font(regular);
+        let data = builder.build();
+
+        let collection = CollectionRef::new(&data).unwrap();
+        for face in collection.iter() {
+            let face = face.unwrap();
+            assert_eq!(face.checksum_mismatches().collect::<Vec<_>>(), []);
+        }
+        // the head table can only be shared by identical fonts
+        let head_offset = |index| {
+            let face = collection.get(index).unwrap();
+            let record = face
+                .table_directory
+                .table_records()
+                .iter()
+                .find(|record| record.tag() == Tag::new(b"head"))
+                .unwrap();
+            record.offset()
+        };
+        assert_ne!(head_offset(0), head_offset(1));
+        assert_eq!(head_offset(0), head_offset(2));
+    }
+
+    #[test]
+    fn collection_with_dsig() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut face = FontBuilder::new();
+        face.copy_missing_tables(font);
+        let dsig = [0u8, 0, 0, 1, 0, 0, 0, 0];
+        let mut builder = CollectionBuilder::new();
+        builder
+            .add_font(face.clone())
+            .add_font(face)
+            .set_dsig(&dsig[..]);
+        let data = builder.build();
+        let collection = CollectionRef::new(&data).unwrap();
+        let header = TTCHeader::read(FontData::new(&data)).unwrap();
+        assert_eq!(header.version(), MajorMinor::VERSION_2_0);
+        assert_eq!(header.dsig_tag(), Some(u32::from_be_bytes(*b"DSIG")));
+        assert_eq!(header.dsig_length(), Some(dsig.len() as u32));
+        let offset = header.dsig_offset().unwrap() as usize;
+        assert_eq!(&data[offset..offset + dsig.len()], dsig);
+        // identical faces point at the same table data
+        let (first, second) = (collection.get(0).unwrap(), collection.get(1).unwrap());
+        assert_eq!(
+            first.table_directory.table_records(),
+            second.table_directory.table_records()
+        );
+    }
+
     #[test]
     fn pad4() {
         for i in 0..10 {
@@ -0,0 +1,788 @@
+//! Instancing variable fonts.
+//!
+//! The instancer pins variation axes to fixed locations, or restricts them
+//! to a smaller range, and rewrites the font so that it only covers the
+//! requested part of the design space. Pinning every axis produces a static
+//! font; otherwise the result is a variable font with fewer or smaller axes.
+//!
+//! Every variation in the font (a `gvar` or `cvar` tuple, a region of an item
+//! variation store, or of a CFF2 blend) is rebased onto the new axis limits.
+//! Deltas that apply everywhere in the new design space are added to the
+//! default values, and the others are expressed in the normalized
+//! coordinates of the new limits.
+//!
+//! The following tables are instanced: `glyf`/`gvar` (along with the glyph
+//! metrics and bounding boxes), `cvt `/`cvar`, `CFF2`, `HVAR`, `VVAR`,
+//! `MVAR`, `GDEF`, `GPOS` and `GSUB` (including feature variations), `COLR`,
+//! `fvar` (with its named instances), `avar` and `STAT`. When an axis is
+//! pinned, `OS/2` and `post` are updated with the weight, width and slant of
+//! the instance. The `hdmx`, `LTSH` and `VDMX` tables depend on the outlines,
+//! and are dropped.
+//!
+//! A restricted range must include the default value of its axis. Fonts with
+//! a version 2 `avar` table are not supported.
+
+mod axes;
+mod cff2;
+mod colr;
+mod glyf;
+mod layout;
+mod solver;
+mod variations;
+
+use std::collections::BTreeMap;
+
+use read_fonts::{
+    tables::{avar::Avar as ReadAvar, fvar::Fvar as ReadFvar},
+    FontRef, ReadError, TableProvider, TopLevelTable,
+};
+use types::{Fixed, Tag};
+
+use crate::{
+    from_obj::ToOwnedTable,
+    tables::{
+        head::Head,
+        hhea::Hhea,
+        hmtx::{expand_metrics, pack_metrics, Hmtx, LongMetric},
+        os2::Os2,
+        post::Post,
+        vhea::Vhea,
+        vmtx::Vmtx,
+    },
+    BuilderError, FontBuilder,
+};
+
+use solver::{NormalizedLimit, Region, Tent};
+
+/// Tables that depend on the outlines, and so are dropped.
+const DROPPED_TABLES: &[Tag] = &[
+    Tag::new(b"DSIG"),
+    Tag::new(b"LTSH"),
+    Tag::new(b"VDMX"),
+    Tag::new(b"hdmx"),
+];
+
+/// Tables that are rewritten (or dropped) by the instancer.
+const INSTANCED_TABLES: &[Tag] = &[
+    Tag::new(b"CFF2"),
+    Tag::new(b"COLR"),
+    Tag::new(b"GDEF"),
+    Tag::new(b"GPOS"),
+    Tag::new(b"GSUB"),
+    Tag::new(b"HVAR"),
+    Tag::new(b"MVAR"),
+    Tag::new(b"OS/2"),
+    Tag::new(b"STAT"),
+    Tag::new(b"VVAR"),
+    Tag::new(b"avar"),
+    Tag::new(b"cvar"),
+    Tag::new(b"cvt "),
+    Tag::new(b"fvar"),
+    Tag::new(b"glyf"),
+    Tag::new(b"gvar"),
+    Tag::new(b"head"),
+    Tag::new(b"hhea"),
+    Tag::new(b"hmtx"),
+    Tag::new(b"loca"),
+    Tag::new(b"post"),
+    Tag::new(b"vhea"),
+    Tag::new(b"vmtx"),
+];
+
+/// The locations to pin, and the ranges to restrict, when instancing.
+///
+/// Values are given in user (design) coordinates, as in the `fvar` table.
+/// Axes without a limit are left unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct AxisLimits {
+    limits: BTreeMap<Tag, AxisLimit>,
+}
+
+/// The new limit of a single axis, in user coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisLimit {
+    /// Pin the axis to a single value, removing it from the font.
+    Pin(f64),
+    /// Restrict the axis to a range, which must include its default value.
+    Range { min: f64, max: f64 },
+}
+
+/// An error that occurs while instancing a font.
+#[derive(Clone, Debug)]
+pub enum InstancerError {
+    /// A table in the source font could not be read.
+    Read { tag: Tag, inner: ReadError },
+    /// An instanced table could not be compiled.
+    Write(BuilderError),
+    /// The font has no `fvar` table.
+    NotVariable,
+    /// A limit was given for an axis that is not in the font.
+    UnknownAxis(Tag),
+    /// A restricted range is empty, or does not include the default value
+    /// of its axis.
+    InvalidRange(Tag),
+    /// The font uses a feature that the instancer does not support.
+    Unsupported { tag: Tag, reason: &'static str },
+}
+
+impl AxisLimits {
+    /// Create a new, empty set of limits.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Pin the axis with the given tag to `value`.
+    pub fn pin(&mut self, tag: Tag, value: f64) -> &mut Self {
+        self.limits.insert(tag, AxisLimit::Pin(value));
+        self
+    }
+
+    /// Restrict the axis with the given tag to the range from `min` to `max`.
+    pub fn restrict(&mut self, tag: Tag, min: f64, max: f64) -> &mut Self {
+        self.limits.insert(tag, AxisLimit::Range { min, max });
+        self
+    }
+
+    /// Returns the limit of the axis with the given tag, if any.
+    pub fn get(&self, tag: Tag) -> Option<AxisLimit> {
+        self.limits.get(&tag).copied()
+    }
+}
+
+/// The axis limits, resolved against the font.
+struct Plan<'a> {
+    font: FontRef<'a>,
+    /// The number of axes in the source font.
+    axis_count: u16,
+    /// The limit of each axis in user coordinates, clamped to the axis range.
+    user_limits: Vec<Option<AxisLimit>>,
+    /// The limit of each axis in normalized coordinates.
+    limits: Vec<Option<NormalizedLimit>>,
+}
+
+impl<'a> Plan<'a> {
+    fn new(font: &FontRef<'a>, limits: &AxisLimits) -> Result<Self, InstancerError> {
+        let fvar = optional(ReadFvar::TAG, font.fvar())?.ok_or(InstancerError::NotVariable)?;
+        let axes = fvar.axes().map_err(read_error(ReadFvar::TAG))?;
+        if let Some(tag) = limits
+            .limits
+            .keys()
+            .find(|tag| !axes.iter().any(|axis| axis.axis_tag() == **tag))
+        {
+            return Err(InstancerError::UnknownAxis(*tag));
+        }
+        let avar = optional(ReadAvar::TAG, font.avar())?;
+        if avar.as_ref().is_some_and(|avar| avar.version().major > 1) {
+            return Err(InstancerError::Unsupported {
+                tag: ReadAvar::TAG,
+                reason: "version 2 axis variations",
+            });
+        }
+        let segment_maps = avar.as_ref().map(|avar| avar.axis_segment_maps());
+
+        let mut user_limits = Vec::new();
+        let mut normalized_limits = Vec::new();
+        for (i, axis) in axes.iter().enumerate() {
+            let normalize = |value: f64| {
+                let mut coord = axis.normalize(Fixed::from_f64(value));
+                if let Some(map) = segment_maps.as_ref().and_then(|maps| maps.get(i)) {
+                    coord = map
+                        .map_err(read_error(ReadAvar::TAG))?
+                        .apply(coord.to_f2dot14().to_fixed());
+                }
+                Ok::<_, InstancerError>(coord.to_f2dot14().to_f32() as f64)
+            };
+            let min_value = axis.min_value().to_f64();
+            let default = axis.default_value().to_f64();
+            let max_value = axis.max_value().to_f64();
+            let clamp = |value: f64| value.clamp(min_value, max_value);
+            let (user, normalized) = match limits.get(axis.axis_tag()) {
+                None => (None, None),
+                Some(AxisLimit::Pin(value)) => {
+                    let value = clamp(value);
+                    (
+                        Some(AxisLimit::Pin(value)),
+                        Some(NormalizedLimit::Pin(normalize(value)?)),
+                    )
+                }
+                Some(AxisLimit::Range { min, max }) => {
+                    if min > max || min > default || max < default {
+                        return Err(InstancerError::InvalidRange(axis.axis_tag()));
+                    }
+                    let (min, max) = (clamp(min), clamp(max));
+                    let normalized = if min == max {
+                        NormalizedLimit::Pin(0.0)
+                    } else {
+                        NormalizedLimit::Range {
+                            min: normalize(min)?,
+                            max: normalize(max)?,
+                        }
+                    };
+                    let user = match normalized {
+                        NormalizedLimit::Pin(_) => AxisLimit::Pin(default),
+                        _ => AxisLimit::Range { min, max },
+                    };
+                    (Some(user), Some(normalized))
+                }
+            };
+            user_limits.push(user);
+            normalized_limits.push(normalized);
+        }
+        Ok(Self {
+            font: font.clone(),
+            axis_count: axes.len() as u16,
+            user_limits,
+            limits: normalized_limits,
+        })
+    }
+
+    /// `true` if every axis is pinned.
+    fn is_static(&self) -> bool {
+        self.limits
+            .iter()
+            .all(|limit| matches!(limit, Some(NormalizedLimit::Pin(_))))
+    }
+
+    /// The user limit of the axis with the given tag.
+    fn user_limit(&self, tag: Tag) -> Option<AxisLimit> {
+        let axes = self.font.fvar().ok()?.axes().ok()?;
+        let idx = axes.iter().position(|axis| axis.axis_tag() == tag)?;
+        self.user_limits[idx]
+    }
+
+    /// The user value of the axis with the given tag, if it is pinned.
+    fn pinned_value(&self, tag: Tag) -> Option<f64> {
+        match self.user_limit(tag)? {
+            AxisLimit::Pin(value) => Some(value),
+            AxisLimit::Range { .. } => None,
+        }
+    }
+
+    /// Rebases a region of the source font onto the new axis limits.
+    ///
+    /// See [`solver::rebase_region`].
+    fn rebase(&self, region: &[Tent]) -> Vec<(f64, Region)> {
+        solver::rebase_region(region, &self.limits)
+    }
+}
+
+/// Tables that are updated by more than one part of the instancer.
+#[derive(Default)]
+struct Tables {
+    head: Option<Head>,
+    hhea: Option<Hhea>,
+    vhea: Option<Vhea>,
+    os2: Option<Os2>,
+    post: Option<Post>,
+    /// The horizontal metrics of each glyph.
+    hmtx: Option<Vec<LongMetric>>,
+    /// The vertical metrics of each glyph.
+    vmtx: Option<Vec<LongMetric>>,
+}
+
+impl Tables {
+    fn new(font: &FontRef) -> Result<Self, InstancerError> {
+        let num_glyphs = font
+            .maxp()
+            .map_err(read_error(Tag::new(b"maxp")))?
+            .num_glyphs() as usize;
+        let hmtx = optional(Hmtx::TAG, font.hmtx())?.map(|hmtx| {
+            expand_metrics(
+                hmtx.h_metrics()
+                    .iter()
+                    .map(|metric| (metric.advance(), metric.side_bearing())),
+                hmtx.left_side_bearings().iter().map(|lsb| lsb.get()),
+                num_glyphs,
+            )
+        });
+        let vmtx = optional(Vmtx::TAG, font.vmtx())?.map(|vmtx| {
+            expand_metrics(
+                vmtx.v_metrics()
+                    .iter()
+                    .map(|metric| (metric.advance(), metric.side_bearing())),
+                vmtx.top_side_bearings().iter().map(|tsb| tsb.get()),
+                num_glyphs,
+            )
+        });
+        Ok(Self {
+            head: optional(Head::TAG, font.head())?.map(|head| head.to_owned_table()),
+            hhea: optional(Hhea::TAG, font.hhea())?.map(|hhea| hhea.to_owned_table()),
+            vhea: optional(Vhea::TAG, font.vhea())?.map(|vhea| vhea.to_owned_table()),
+            os2: optional(Os2::TAG, font.os2())?.map(|os2| os2.to_owned_table()),
+            post: optional(Post::TAG, font.post())?.map(|post| post.to_owned_table()),
+            hmtx,
+            vmtx,
+        })
+    }
+
+    fn write(self, builder: &mut FontBuilder) -> Result<(), InstancerError> {
+        if let Some(mut hhea) = self.hhea {
+            if let Some(hmtx) = self.hmtx {
+                let (long, bearings) = pack_metrics(hmtx);
+                hhea.number_of_long_metrics = long.len() as u16;
+                builder.add_table(&Hmtx::new(long, bearings))?;
+            }
+            builder.add_table(&hhea)?;
+        }
+        if let Some(mut vhea) = self.vhea {
+            if let Some(vmtx) = self.vmtx {
+                let (long, bearings) = pack_metrics(vmtx);
+                vhea.number_of_long_ver_metrics = long.len() as u16;
+                builder.add_table(&Vmtx::new(long, bearings))?;
+            }
+            builder.add_table(&vhea)?;
+        }
+        if let Some(head) = self.head {
+            builder.add_table(&head)?;
+        }
+        if let Some(os2) = self.os2 {
+            builder.add_table(&os2)?;
+        }
+        if let Some(post) = self.post {
+            builder.add_table(&post)?;
+        }
+        Ok(())
+    }
+}
+
+/// Instance a variable font, returning the binary data of the new font.
+///
+/// Each axis with a limit in `limits` is either pinned, which removes it
+/// from the font, or restricted to a smaller range. If every axis is pinned,
+/// the result is a static font.
+pub fn instantiate_font(font: &FontRef, limits: &AxisLimits) -> Result<Vec<u8>, InstancerError> {
+    let plan = Plan::new(font, limits)?;
+    let mut tables = Tables::new(font)?;
+    let mut builder = FontBuilder::new();
+
+    glyf::instantiate(&plan, &mut tables, &mut builder)?;
+    cff2::instantiate(&plan, &mut tables, &mut builder)?;
+    variations::instantiate(&plan, &mut tables, &mut builder)?;
+    layout::instantiate(&plan, &mut builder)?;
+    colr::instantiate(&plan, &mut builder)?;
+    axes::instantiate(&plan, &mut tables, &mut builder)?;
+    tables.write(&mut builder)?;
+
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        if builder.contains(tag) || INSTANCED_TABLES.contains(&tag) || DROPPED_TABLES.contains(&tag)
+        {
+            continue;
+        }
+        if let Some(data) = font.data_for_tag(tag) {
+            builder.add_raw(tag, data.as_bytes());
+        }
+    }
+    Ok(builder.build())
+}
+
+fn read_error(tag: Tag) -> impl FnOnce(ReadError) -> InstancerError {
+    move |inner| InstancerError::Read { tag, inner }
+}
+
+/// Converts a missing table into `None`, and other errors into an
+/// [`InstancerError`].
+fn optional<T>(tag: Tag, table: Result<T, ReadError>) -> Result<Option<T>, InstancerError> {
+    match table {
+        Ok(table) => Ok(Some(table)),
+        Err(ReadError::TableIsMissing(_)) => Ok(None),
+        Err(inner) => Err(InstancerError::Read { tag, inner }),
+    }
+}
+
+impl From<BuilderError> for InstancerError {
+    fn from(value: BuilderError) -> Self {
+        InstancerError::Write(value)
+    }
+}
+
+impl std::fmt::Display for InstancerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstancerError::Read { tag, inner } => write!(f, "failed to read '{tag}': {inner}"),
+            InstancerError::Write(error) => error.fmt(f),
+            InstancerError::NotVariable => write!(f, "the font is not a variable font"),
+            InstancerError::UnknownAxis(tag) => write!(f, "the font has no '{tag}' axis"),
+            InstancerError::InvalidRange(tag) => write!(
+                f,
+                "the range of the '{tag}' axis must include its default value"
+            ),
+            InstancerError::Unsupported { tag, reason } => {
+                write!(f, "instancing '{tag}' with {reason} is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstancerError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use read_fonts::{
+        sanitize::sanitize,
+        tables::{
+            glyf::Glyph as ReadGlyph,
+            postscript::{
+                charstring::{self, CommandSink},
+                BlendState, Index,
+            },
+            variations::ItemVariationStore,
+        },
+        FontData, FontRead,
+    };
+    use types::{F2Dot14, GlyphId};
+
+    use super::*;
+    use crate::tables::postscript::font::{CffFont, VARIATION_STORE};
+
+    const VARIABLE_FONTS: &[&[u8]] = &[
+        font_test_data::VAZIRMATN_VAR,
+        font_test_data::CANTARELL_VF_TRIMMED,
+        font_test_data::COLRV0V1_VARIABLE,
+        font_test_data::HVAR_WITH_TRUNCATED_ADVANCE_INDEX_MAP,
+    ];
+
+    /// Limits that pin every axis to a value between its default and maximum.
+    fn pin_all(font: &FontRef) -> AxisLimits {
+        let mut limits = AxisLimits::new();
+        for axis in font.fvar().unwrap().axes().unwrap() {
+            let (default, max) = (axis.default_value().to_f64(), axis.max_value().to_f64());
+            limits.pin(axis.axis_tag(), (default + max) / 2.0);
+        }
+        limits
+    }
+
+    /// Limits that restrict the first axis to the lower part of its range.
+    fn restrict_first(font: &FontRef) -> AxisLimits {
+        let mut limits = AxisLimits::new();
+        let axes = font.fvar().unwrap().axes().unwrap();
+        let axis = &axes[0];
+        let (default, max) = (axis.default_value().to_f64(), axis.max_value().to_f64());
+        limits.restrict(
+            axis.axis_tag(),
+            axis.min_value().to_f64(),
+            (default + max) / 2.0,
+        );
+        limits
+    }
+
+    #[test]
+    fn instances_are_well_formed() {
+        for data in VARIABLE_FONTS {
+            let font = FontRef::new(data).unwrap();
+            // problems in tables that are copied unchanged are ignored
+            let source_issues: BTreeSet<_> = sanitize(&font)
+                .issues()
+                .iter()
+                .map(|issue| (issue.table, issue.path.clone()))
+                .collect();
+            for limits in [pin_all(&font), restrict_first(&font)] {
+                let instance = instantiate_font(&font, &limits).unwrap();
+                let instance = FontRef::new(&instance).unwrap();
+                let issues: Vec<_> = sanitize(&instance)
+                    .issues()
+                    .iter()
+                    .filter(|issue| !source_issues.contains(&(issue.table, issue.path.clone())))
+                    .cloned()
+                    .collect();
+                assert!(issues.is_empty(), "{limits:?}: {issues:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn static_instances_have_no_variations() {
+        for data in VARIABLE_FONTS {
+            let font = FontRef::new(data).unwrap();
+            let instance = instantiate_font(&font, &pin_all(&font)).unwrap();
+            let instance = FontRef::new(&instance).unwrap();
+            for tag in [b"fvar", b"gvar", b"avar", b"HVAR", b"MVAR", b"cvar"] {
+                assert!(instance.data_for_tag(Tag::new(tag)).is_none(), "{tag:?}");
+            }
+        }
+    }
+
+    /// Returns the points of each simple glyph at the given location.
+    fn simple_glyph_points(font: &FontRef, coords: &[F2Dot14]) -> Vec<Vec<(f64, f64)>> {
+        let glyf = font.glyf().unwrap();
+        let loca = font.loca(None).unwrap();
+        let gvar = font.gvar().ok();
+        let num_glyphs = font.maxp().unwrap().num_glyphs();
+        (0..num_glyphs)
+            .map(GlyphId::new)
+            .map(|gid| {
+                let Some(ReadGlyph::Simple(glyph)) = loca.get_glyf(gid, &glyf).unwrap() else {
+                    return Vec::new();
+                };
+                let mut points: Vec<_> = glyph
+                    .points()
+                    .map(|point| (point.x as f64, point.y as f64))
+                    .collect();
+                let Some(data) = gvar.as_ref().and_then(|g| g.glyph_variation_data(gid).ok())
+                else {
+                    return points;
+                };
+                let contour_ends: Vec<_> = glyph
+                    .end_pts_of_contours()
+                    .iter()
+                    .map(|end| end.get() as usize)
+                    .collect();
+                let coords_with_phantoms: Vec<_> = points
+                    .iter()
+                    .map(|(x, y)| kurbo::Point::new(*x, *y))
+                    .chain([kurbo::Point::ZERO; 4])
+                    .collect();
+                let mut total = vec![(0.0, 0.0); points.len()];
+                for tuple in data.tuples() {
+                    let Some(scalar) = tuple.compute_scalar(coords) else {
+                        continue;
+                    };
+                    let mut deltas = vec![None; coords_with_phantoms.len()];
+                    for delta in tuple.deltas() {
+                        deltas[delta.position as usize] =
+                            Some(kurbo::Vec2::new(delta.x_delta as f64, delta.y_delta as f64));
+                    }
+                    let deltas = crate::tables::gvar::iup::iup_delta(
+                        &deltas,
+                        &coords_with_phantoms,
+                        &contour_ends,
+                    )
+                    .unwrap();
+                    for (total, delta) in total.iter_mut().zip(deltas) {
+                        total.0 += delta.x * scalar.to_f64();
+                        total.1 += delta.y * scalar.to_f64();
+                    }
+                }
+                for (point, delta) in points.iter_mut().zip(total) {
+                    point.0 += delta.0;
+                    point.1 += delta.1;
+                }
+                points
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pinned_outlines_match_source() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let axis = font.fvar().unwrap().axes().unwrap()[0].clone();
+        let value = (axis.default_value().to_f64() + axis.max_value().to_f64()) / 2.0;
+        let coord = axis.normalize(Fixed::from_f64(value));
+        let avar = font.avar().unwrap();
+        let coord = avar
+            .axis_segment_maps()
+            .get(0)
+            .unwrap()
+            .unwrap()
+            .apply(coord);
+        let expected = simple_glyph_points(&font, &[coord.to_f2dot14()]);
+
+        let mut limits = AxisLimits::new();
+        limits.pin(axis.axis_tag(), value);
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        let actual = simple_glyph_points(&instance, &[]);
+        assert_eq!(expected.len(), actual.len());
+        for (gid, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+            assert_eq!(expected.len(), actual.len(), "glyph {gid}");
+            for (expected, actual) in expected.iter().zip(actual) {
+                assert!(
+                    (expected.0 - actual.0).abs() <= 1.0 && (expected.1 - actual.1).abs() <= 1.0,
+                    "glyph {gid}: {expected:?} != {actual:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn restricted_outlines_match_source() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let axis = font.fvar().unwrap().axes().unwrap()[0].clone();
+        let (default, max) = (axis.default_value().to_f64(), axis.max_value().to_f64());
+        let mut limits = AxisLimits::new();
+        limits.restrict(axis.axis_tag(), default, (default + max) / 2.0);
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        let instance_axis = instance.fvar().unwrap().axes().unwrap()[0].clone();
+        assert_eq!(instance_axis.max_value().to_f64(), (default + max) / 2.0);
+
+        // compare at a location within the new range
+        let value = default + (max - default) / 4.0;
+        let location = |font: &FontRef, axis: &read_fonts::tables::fvar::VariationAxisRecord| {
+            let coord = axis.normalize(Fixed::from_f64(value));
+            let avar = font.avar().unwrap();
+            let coord = avar
+                .axis_segment_maps()
+                .get(0)
+                .unwrap()
+                .unwrap()
+                .apply(coord);
+            [coord.to_f2dot14()]
+        };
+        let expected = simple_glyph_points(&font, &location(&font, &axis));
+        let actual = simple_glyph_points(&instance, &location(&instance, &instance_axis));
+        for (gid, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+            for (expected, actual) in expected.iter().zip(actual) {
+                assert!(
+                    (expected.0 - actual.0).abs() <= 2.0 && (expected.1 - actual.1).abs() <= 2.0,
+                    "glyph {gid}: {expected:?} != {actual:?}"
+                );
+            }
+        }
+    }
+
+    /// Records the points of a charstring.
+    #[derive(Default)]
+    struct PointSink(Vec<(f64, f64)>);
+
+    impl CommandSink for PointSink {
+        fn move_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push((x.to_f64(), y.to_f64()));
+        }
+
+        fn line_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push((x.to_f64(), y.to_f64()));
+        }
+
+        fn curve_to(&mut self, cx0: Fixed, cy0: Fixed, cx1: Fixed, cy1: Fixed, x: Fixed, y: Fixed) {
+            self.0.extend(
+                [cx0, cy0, cx1, cy1, x, y]
+                    .chunks(2)
+                    .map(|xy| (xy[0].to_f64(), xy[1].to_f64())),
+            );
+        }
+
+        fn close(&mut self) {}
+    }
+
+    /// Returns the points of each CFF2 charstring at the given location.
+    fn cff2_points(font: &FontRef, coords: &[F2Dot14]) -> Vec<Vec<(f64, f64)>> {
+        let data = font.data_for_tag(Tag::new(b"CFF2")).unwrap();
+        let data = data.as_bytes();
+        let cff = CffFont::new_cff2(data).unwrap();
+        let store = cff
+            .top_dict
+            .iter()
+            .find(|entry| entry.op == VARIATION_STORE)
+            .map(|entry| {
+                let data = FontData::new(&data[entry.offset().unwrap() + 2..]);
+                ItemVariationStore::read(data).unwrap()
+            });
+        (0..cff.num_glyphs())
+            .map(|gid| {
+                let fd = cff
+                    .fd_select
+                    .as_ref()
+                    .and_then(|fd_select| fd_select.font_index(GlyphId::new(gid as u16)))
+                    .unwrap_or_default();
+                let subrs = cff.font_dicts[fd as usize]
+                    .private
+                    .as_ref()
+                    .and_then(|private| private.subrs)
+                    .map(|subrs| Index::new(subrs, true).unwrap());
+                let blend_state = store
+                    .clone()
+                    .map(|store| BlendState::new(store, coords, 0).unwrap());
+                let mut sink = PointSink::default();
+                charstring::evaluate(
+                    cff.charstrings.get(gid).unwrap(),
+                    cff.global_subrs.clone(),
+                    subrs,
+                    blend_state,
+                    &mut sink,
+                )
+                .unwrap();
+                sink.0
+            })
+            .collect()
+    }
+
+    /// The normalized location of a value of the first axis.
+    fn first_axis_location(font: &FontRef, value: f64) -> [F2Dot14; 1] {
+        let axis = font.fvar().unwrap().axes().unwrap()[0].clone();
+        let coord = axis.normalize(Fixed::from_f64(value));
+        let coord = match font.avar() {
+            Ok(avar) => avar
+                .axis_segment_maps()
+                .get(0)
+                .unwrap()
+                .unwrap()
+                .apply(coord),
+            Err(_) => coord,
+        };
+        [coord.to_f2dot14()]
+    }
+
+    fn assert_points_match(
+        expected: &[Vec<(f64, f64)>],
+        actual: &[Vec<(f64, f64)>],
+        tolerance: f64,
+    ) {
+        assert_eq!(expected.len(), actual.len());
+        for (gid, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+            assert_eq!(expected.len(), actual.len(), "glyph {gid}");
+            for (expected, actual) in expected.iter().zip(actual) {
+                assert!(
+                    (expected.0 - actual.0).abs() <= tolerance
+                        && (expected.1 - actual.1).abs() <= tolerance,
+                    "glyph {gid}: {expected:?} != {actual:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cff2_outlines_match_source() {
+        let font = FontRef::new(font_test_data::CANTARELL_VF_TRIMMED).unwrap();
+        let axis = font.fvar().unwrap().axes().unwrap()[0].clone();
+        let (default, max) = (axis.default_value().to_f64(), axis.max_value().to_f64());
+
+        // a static instance
+        let value = (default + max) / 2.0;
+        let mut limits = AxisLimits::new();
+        limits.pin(axis.axis_tag(), value);
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        let expected = cff2_points(&font, &first_axis_location(&font, value));
+        assert_points_match(&expected, &cff2_points(&instance, &[]), 0.01);
+
+        // a restricted instance, compared within the new range
+        let mut limits = AxisLimits::new();
+        limits.restrict(axis.axis_tag(), axis.min_value().to_f64(), value);
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        for value in [default, (default + value) / 2.0, value] {
+            let expected = cff2_points(&font, &first_axis_location(&font, value));
+            let actual = cff2_points(&instance, &first_axis_location(&instance, value));
+            assert_points_match(&expected, &actual, 0.5);
+        }
+    }
+
+    #[test]
+    fn invalid_limits() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut limits = AxisLimits::new();
+        limits.pin(Tag::new(b"nope"), 1.0);
+        assert!(matches!(
+            instantiate_font(&font, &limits),
+            Err(InstancerError::UnknownAxis(_))
+        ));
+
+        let axis = font.fvar().unwrap().axes().unwrap()[0].clone();
+        let default = axis.default_value().to_f64();
+        let mut limits = AxisLimits::new();
+        limits.restrict(axis.axis_tag(), default + 1.0, default + 2.0);
+        assert!(matches!(
+            instantiate_font(&font, &limits),
+            Err(InstancerError::InvalidRange(_))
+        ));
+
+        let font = FontRef::new(font_test_data::SIMPLE_GLYF).unwrap();
+        assert!(matches!(
+            instantiate_font(&font, &AxisLimits::new()),
+            Err(InstancerError::NotVariable)
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+//! Instancing the axis definitions and the style of the font.
+
+use read_fonts::{tables::fvar::Fvar as ReadFvar, TableProvider, TopLevelTable};
+use types::{F2Dot14, Fixed, Tag};
+
+use super::{
+    optional, read_error, solver::NormalizedLimit, AxisLimit, InstancerError, Plan, Tables,
+};
+use crate::{
+    from_obj::ToOwnedTable,
+    tables::{
+        avar::{Avar, AxisValueMap},
+        fvar::Fvar,
+        stat::{AxisValue, Stat},
+    },
+    FontBuilder,
+};
+
+const WGHT: Tag = Tag::new(b"wght");
+const WDTH: Tag = Tag::new(b"wdth");
+const SLNT: Tag = Tag::new(b"slnt");
+
+/// The `wdth` value of each `usWidthClass`, from ultra-condensed to
+/// ultra-expanded.
+const WIDTH_CLASSES: [f64; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+
+/// Instances `fvar`, `avar` and `STAT`, and updates the weight, width and
+/// slant in `OS/2` and `post` for pinned axes.
+pub(super) fn instantiate(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    let fvar = plan.font.fvar().map_err(read_error(ReadFvar::TAG))?;
+    if !plan.is_static() {
+        builder.add_table(&instantiate_fvar(plan, &fvar))?;
+        if let Some(avar) = instantiate_avar(plan, &fvar)? {
+            builder.add_table(&avar)?;
+        }
+    }
+    if let Some(stat) = optional(Stat::TAG, plan.font.stat())? {
+        let mut stat: Stat = stat.to_owned_table();
+        instantiate_stat(plan, &mut stat);
+        builder.add_table(&stat)?;
+    }
+    update_style(plan, tables);
+    Ok(())
+}
+
+/// Removes the pinned axes and the named instances that are no longer in
+/// the design space, and updates the ranges of restricted axes.
+fn instantiate_fvar(plan: &Plan, fvar: &ReadFvar) -> Fvar {
+    let mut fvar: Fvar = fvar.to_owned_table();
+    let arrays = &mut fvar.axis_instance_arrays;
+    let limits = &plan.user_limits;
+    arrays.instances.retain(|instance| {
+        instance
+            .coordinates
+            .iter()
+            .zip(limits)
+            .all(|(coord, limit)| match limit {
+                Some(AxisLimit::Pin(value)) => coord.to_f64() == *value,
+                Some(AxisLimit::Range { min, max }) => (*min..=*max).contains(&coord.to_f64()),
+                None => true,
+            })
+    });
+    for instance in arrays.instances.iter_mut() {
+        instance.coordinates = retained(&instance.coordinates, limits);
+    }
+    for (axis, limit) in arrays.axes.iter_mut().zip(limits) {
+        if let Some(AxisLimit::Range { min, max }) = limit {
+            axis.min_value = Fixed::from_f64(*min);
+            axis.max_value = Fixed::from_f64(*max);
+        }
+    }
+    arrays.axes = retained(&arrays.axes, limits);
+    fvar
+}
+
+/// Returns the items for the axes that are not pinned.
+fn retained<T: Clone>(items: &[T], limits: &[Option<AxisLimit>]) -> Vec<T> {
+    items
+        .iter()
+        .zip(limits)
+        .filter(|(_, limit)| !matches!(limit, Some(AxisLimit::Pin(_))))
+        .map(|(item, _)| item.clone())
+        .collect()
+}
+
+/// Removes the maps of pinned axes, and renormalizes the maps of
+/// restricted axes.
+///
+/// Both sides of each map are expressed in the normalized coordinates of the
+/// new range: the input side relative to the range before `avar` is
+/// applied, and the output side relative to the range after.
+fn instantiate_avar(plan: &Plan, fvar: &ReadFvar) -> Result<Option<Avar>, InstancerError> {
+    let Some(avar) = optional(Avar::TAG, plan.font.avar())? else {
+        return Ok(None);
+    };
+    let mut avar: Avar = avar.to_owned_table();
+    let axes = fvar.axes().map_err(read_error(ReadFvar::TAG))?;
+    for (((map, limit), normalized), axis) in avar
+        .axis_segment_maps
+        .iter_mut()
+        .zip(&plan.user_limits)
+        .zip(&plan.limits)
+        .zip(axes)
+    {
+        let (
+            Some(AxisLimit::Range { min, max }),
+            Some(NormalizedLimit::Range {
+                min: to_min,
+                max: to_max,
+            }),
+        ) = (limit, normalized)
+        else {
+            continue;
+        };
+        if map.axis_value_maps.is_empty() {
+            continue;
+        }
+        let normalize = |value: f64| axis.normalize(Fixed::from_f64(value)).to_f64();
+        let (from_min, from_max) = (normalize(*min), normalize(*max));
+        let (to_min, to_max) = (*to_min, *to_max);
+        let mut values: Vec<_> = map
+            .axis_value_maps
+            .iter()
+            .map(|value| {
+                (
+                    value.from_coordinate.to_f32() as f64,
+                    value.to_coordinate.to_f32() as f64,
+                )
+            })
+            .filter(|(from, _)| (from_min..=from_max).contains(from))
+            .map(|(from, to)| {
+                (
+                    renormalize(from, from_min, from_max),
+                    renormalize(to, to_min, to_max),
+                )
+            })
+            .chain([(-1.0, -1.0), (0.0, 0.0), (1.0, 1.0)])
+            .map(|(from, to)| {
+                let to_f2dot14 = |value: f64| F2Dot14::from_f32(value as f32);
+                AxisValueMap {
+                    from_coordinate: to_f2dot14(from),
+                    to_coordinate: to_f2dot14(to),
+                }
+            })
+            .collect();
+        values.sort_by_key(|value| value.from_coordinate);
+        values.dedup_by_key(|value| value.from_coordinate);
+        map.axis_value_maps = values;
+    }
+    avar.axis_segment_maps = retained(&avar.axis_segment_maps, &plan.user_limits);
+    Ok(Some(avar))
+}
+
+/// Expresses a normalized coordinate relative to a new range.
+fn renormalize(coord: f64, min: f64, max: f64) -> f64 {
+    if coord < 0.0 {
+        if min < 0.0 {
+            coord / -min
+        } else {
+            0.0
+        }
+    } else if max > 0.0 {
+        coord / max
+    } else {
+        0.0
+    }
+}
+
+/// Removes the axis values that are outside the new limits.
+fn instantiate_stat(plan: &Plan, stat: &mut Stat) {
+    let axis_tags: Vec<_> = stat.design_axes.iter().map(|axis| axis.axis_tag).collect();
+    let in_range = |axis_index: u16, value: Fixed| {
+        let Some(tag) = axis_tags.get(axis_index as usize) else {
+            return true;
+        };
+        let value = value.to_f64();
+        match plan.user_limit(*tag) {
+            Some(AxisLimit::Pin(pin)) => value == pin,
+            Some(AxisLimit::Range { min, max }) => (min..=max).contains(&value),
+            None => true,
+        }
+    };
+    stat.offset_to_axis_values.retain(|value| match &**value {
+        AxisValue::Format1(value) => in_range(value.axis_index, value.value),
+        AxisValue::Format2(value) => in_range(value.axis_index, value.nominal_value),
+        AxisValue::Format3(value) => in_range(value.axis_index, value.value),
+        AxisValue::Format4(value) => value
+            .axis_values
+            .iter()
+            .all(|record| in_range(record.axis_index, record.value)),
+    });
+}
+
+/// Updates the weight and width classes, and the italic angle, of a pinned
+/// instance.
+fn update_style(plan: &Plan, tables: &mut Tables) {
+    if let Some(os2) = tables.os2.as_mut() {
+        if let Some(weight) = plan.pinned_value(WGHT) {
+            os2.us_weight_class = weight.round().clamp(1.0, 1000.0) as u16;
+        }
+        if let Some(width) = plan.pinned_value(WDTH) {
+            os2.us_width_class = width_class(width);
+        }
+    }
+    if let Some(post) = tables.post.as_mut() {
+        if let Some(slant) = plan.pinned_value(SLNT) {
+            post.italic_angle = Fixed::from_f64(slant.clamp(-90.0, 90.0));
+        }
+    }
+}
+
+/// Returns the `usWidthClass` closest to a `wdth` value.
+fn width_class(width: f64) -> u16 {
+    let mut class = 1;
+    for (i, pair) in WIDTH_CLASSES.windows(2).enumerate() {
+        if width >= (pair[0] + pair[1]) / 2.0 {
+            class = i as u16 + 2;
+        }
+    }
+    class
+}
@@ -0,0 +1,684 @@
+//! Instancing the CFF2 table.
+//!
+//! Charstrings are desubroutinized while they are instanced: the operands of
+//! each `blend` are rebased onto the new axis limits, and rewritten as new
+//! `blend` operands (or plain numbers, if they no longer vary). The same is
+//! done for the operands of the Private DICTs.
+//!
+//! The new variation store keeps one item variation data for each source
+//! item variation data, so that the `vsindex` operands remain valid.
+
+use std::collections::HashMap;
+
+use read_fonts::{
+    tables::{
+        cff2::Cff2,
+        postscript::{
+            charstring::{self, CommandSink, NESTING_DEPTH_LIMIT},
+            BlendState, Index,
+        },
+        variations::ItemVariationStore,
+    },
+    FontData, FontRead, ReadError, TableProvider,
+};
+use types::{F2Dot14, Fixed, GlyphId, Tag};
+
+use super::{
+    read_error,
+    solver::{is_default, NormalizedLimit, Region, Tent},
+    variations::variation_region,
+    InstancerError, Plan, Tables,
+};
+use crate::{
+    dump_table,
+    tables::postscript::{
+        font::{
+            malformed, ps_error, top_dict_offsets, write_dict, CffFont, DictEntry, FontDict,
+            PrivateDict, CHARSTRINGS, FD_ARRAY, FD_SELECT, SUBRS, VARIATION_STORE,
+        },
+        write_index, NumberEncoding,
+    },
+    tables::variations::{
+        ItemVariationData, ItemVariationStore as WriteItemVariationStore, VariationRegionList,
+    },
+    FontBuilder,
+};
+
+const CFF2: Tag = Tag::new(b"CFF2");
+
+/// The `vsindex` operator of a Private DICT.
+const DICT_VSINDEX: u16 = 22;
+/// The `blend` operator of a Private DICT.
+const DICT_BLEND: u16 = 23;
+
+/// Instances the `CFF2` table, and updates the left side bearings and the
+/// font bounding box for the new default outlines.
+pub(super) fn instantiate(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    let Some(data) = plan.font.data_for_tag(CFF2) else {
+        return Ok(());
+    };
+    let cff2 = instantiate_cff2(plan, tables, data.as_bytes()).map_err(read_error(CFF2))?;
+    builder.add_raw(CFF2, cff2);
+    Ok(())
+}
+
+/// An operand, with its deltas for each region of the active item variation
+/// data.
+#[derive(Clone, Debug, Default)]
+struct Operand {
+    default: f64,
+    deltas: Vec<f64>,
+}
+
+impl Operand {
+    fn constant(default: f64) -> Self {
+        Self {
+            default,
+            deltas: Vec::new(),
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.deltas.iter().all(|delta| *delta == 0.0)
+    }
+}
+
+/// The rebased regions of one item variation data.
+#[derive(Default)]
+struct BlendData {
+    /// For each source region: the rebased parts, as a scale factor and the
+    /// position of the new region in `region_indexes`, or `None` for the
+    /// part that applies at the new default location.
+    sources: Vec<Vec<(f64, Option<usize>)>>,
+    /// The indices of the new regions in the new region list.
+    region_indexes: Vec<u16>,
+}
+
+/// Rebases `blend` operands onto the new axis limits.
+struct Blender {
+    data: Vec<BlendData>,
+    regions: Vec<Region>,
+    is_static: bool,
+}
+
+impl Blender {
+    fn new(plan: &Plan, store: Option<&ItemVariationStore>) -> Result<Self, ReadError> {
+        let mut blender = Self {
+            data: Vec::new(),
+            regions: Vec::new(),
+            is_static: plan.is_static(),
+        };
+        let Some(store) = store else {
+            return Ok(blender);
+        };
+        let source_regions = store
+            .variation_region_list()?
+            .variation_regions()
+            .iter()
+            .map(|region| {
+                let region: Region = region?
+                    .region_axes()
+                    .iter()
+                    .map(|axis| Tent::new(axis.start_coord(), axis.peak_coord(), axis.end_coord()))
+                    .collect();
+                Ok(plan.rebase(&region))
+            })
+            .collect::<Result<Vec<_>, ReadError>>()?;
+        let mut region_map: HashMap<Region, u16> = HashMap::new();
+        for data in store.item_variation_data().iter() {
+            let mut blend_data = BlendData::default();
+            let Some(data) = data.transpose()? else {
+                blender.data.push(blend_data);
+                continue;
+            };
+            for region_idx in data.region_indexes() {
+                let rebased = source_regions
+                    .get(region_idx.get() as usize)
+                    .ok_or(ReadError::MalformedData("invalid region index"))?;
+                let mut parts = Vec::new();
+                for (scale, region) in rebased {
+                    if is_default(region) {
+                        parts.push((*scale, None));
+                        continue;
+                    }
+                    let next_idx = blender.regions.len() as u16;
+                    let idx = *region_map.entry(region.clone()).or_insert_with(|| {
+                        blender.regions.push(region.clone());
+                        next_idx
+                    });
+                    let pos = match blend_data.region_indexes.iter().position(|i| *i == idx) {
+                        Some(pos) => pos,
+                        None => {
+                            blend_data.region_indexes.push(idx);
+                            blend_data.region_indexes.len() - 1
+                        }
+                    };
+                    parts.push((*scale, Some(pos)));
+                }
+                blend_data.sources.push(parts);
+            }
+            blender.data.push(blend_data);
+        }
+        Ok(blender)
+    }
+
+    /// The number of source regions of an item variation data.
+    fn region_count(&self, vsindex: u16) -> Result<usize, ReadError> {
+        self.data
+            .get(vsindex as usize)
+            .map(|data| data.sources.len())
+            .ok_or(ReadError::MalformedData("invalid vsindex"))
+    }
+
+    /// Rebases an operand: the result has a delta for each new region of
+    /// the item variation data.
+    fn instance(&self, vsindex: u16, operand: &Operand) -> Result<Operand, ReadError> {
+        if operand.deltas.is_empty() {
+            return Ok(operand.clone());
+        }
+        let data = self
+            .data
+            .get(vsindex as usize)
+            .ok_or(ReadError::MalformedData("invalid vsindex"))?;
+        let mut result = Operand {
+            default: operand.default,
+            deltas: vec![0.0; data.region_indexes.len()],
+        };
+        for (delta, parts) in operand.deltas.iter().zip(&data.sources) {
+            for (scale, pos) in parts {
+                match pos {
+                    Some(pos) => result.deltas[*pos] += delta * scale,
+                    None => result.default += delta * scale,
+                }
+            }
+        }
+        if self.is_static {
+            result.deltas.clear();
+        }
+        Ok(result)
+    }
+
+    /// Builds the new variation store.
+    fn store(&self) -> WriteItemVariationStore {
+        let regions = self
+            .regions
+            .iter()
+            .map(|region| variation_region(region))
+            .collect();
+        let data = self
+            .data
+            .iter()
+            .map(|data| {
+                Some(ItemVariationData::new(
+                    0,
+                    0,
+                    data.region_indexes.clone(),
+                    Vec::new(),
+                ))
+            })
+            .collect();
+        WriteItemVariationStore::new(VariationRegionList::new(regions), data)
+    }
+
+    /// Replaces the `n * (k + 1) + 1` operands of a `blend` at the top of
+    /// the stack with `n` blended operands.
+    fn blend(&self, vsindex: u16, stack: &mut Vec<Operand>) -> Result<(), ReadError> {
+        let region_count = self.region_count(vsindex)?;
+        let count = stack.pop().ok_or_else(malformed)?.default;
+        if count < 0.0 {
+            return Err(malformed());
+        }
+        let count = count as usize;
+        let len = count * (region_count + 1);
+        let start = stack.len().checked_sub(len).ok_or_else(malformed)?;
+        let values: Vec<_> = stack
+            .drain(start..)
+            .map(|operand| operand.default)
+            .collect();
+        let (defaults, deltas) = values.split_at(count);
+        stack.extend(defaults.iter().enumerate().map(|(i, default)| Operand {
+            default: *default,
+            deltas: deltas[i * region_count..(i + 1) * region_count].to_vec(),
+        }));
+        Ok(())
+    }
+}
+
+impl NumberEncoding {
+    /// Writes operands, with a `blend` for each run of operands that still
+    /// vary.
+    fn write_operands(
+        self,
+        out: &mut Vec<u8>,
+        blender: &Blender,
+        vsindex: u16,
+        operands: &[Operand],
+    ) -> Result<(), ReadError> {
+        let operands = operands
+            .iter()
+            .map(|operand| blender.instance(vsindex, operand))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rest = operands.as_slice();
+        while let Some(first) = rest.first() {
+            if first.is_constant() {
+                self.write_number(out, first.default);
+                rest = &rest[1..];
+                continue;
+            }
+            let len = rest
+                .iter()
+                .position(Operand::is_constant)
+                .unwrap_or(rest.len());
+            let (run, tail) = rest.split_at(len);
+            for operand in run {
+                self.write_number(out, operand.default);
+            }
+            for operand in run {
+                for delta in &operand.deltas {
+                    self.write_number(out, *delta);
+                }
+            }
+            self.write_number(out, run.len() as f64);
+            out.push(self.blend_op());
+            rest = tail;
+        }
+        Ok(())
+    }
+}
+
+/// The default `vsindex` of a Private DICT.
+fn default_vsindex(private: Option<&PrivateDict>) -> Result<u16, ReadError> {
+    let Some(entry) = private.and_then(|private| {
+        private
+            .entries
+            .iter()
+            .find(|entry| entry.op == DICT_VSINDEX)
+    }) else {
+        return Ok(0);
+    };
+    match entry.numbers()?.as_slice() {
+        [vsindex] if (0.0..=u16::MAX as f64).contains(vsindex) => Ok(*vsindex as u16),
+        _ => Err(malformed()),
+    }
+}
+
+/// Instances the entries of a Private DICT, dropping the local subroutines
+/// (and the `vsindex`, if the font is static).
+fn instantiate_private(
+    blender: &Blender,
+    private: &PrivateDict,
+) -> Result<Vec<(u16, Vec<u8>)>, ReadError> {
+    let vsindex = default_vsindex(Some(private))?;
+    let mut stack = Vec::new();
+    let mut entries = Vec::new();
+    for entry in &private.entries {
+        stack.extend(entry.numbers()?.into_iter().map(Operand::constant));
+        match entry.op {
+            DICT_BLEND => blender.blend(vsindex, &mut stack)?,
+            SUBRS => stack.clear(),
+            DICT_VSINDEX if blender.is_static => stack.clear(),
+            op => {
+                let mut operands = Vec::new();
+                NumberEncoding::Dict.write_operands(&mut operands, blender, vsindex, &stack)?;
+                stack.clear();
+                entries.push((op, operands));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Desubroutinizes and instances a charstring.
+struct CharstringInstancer<'a> {
+    blender: &'a Blender,
+    global_subrs: &'a Index<'a>,
+    local_subrs: Option<&'a Index<'a>>,
+    vsindex: u16,
+    stack: Vec<Operand>,
+    stem_count: usize,
+    out: Vec<u8>,
+}
+
+impl CharstringInstancer<'_> {
+    fn flush(&mut self, op: &[u8]) -> Result<(), ReadError> {
+        let stack = std::mem::take(&mut self.stack);
+        NumberEncoding::Charstring.write_operands(
+            &mut self.out,
+            self.blender,
+            self.vsindex,
+            &stack,
+        )?;
+        self.out.extend_from_slice(op);
+        Ok(())
+    }
+
+    /// Instances a charstring or subroutine, until its end or a `return`.
+    fn instantiate(&mut self, data: &[u8], depth: u32) -> Result<(), ReadError> {
+        const HSTEM: u8 = 1;
+        const VSTEM: u8 = 3;
+        const CALLSUBR: u8 = 10;
+        const RETURN: u8 = 11;
+        const ESCAPE: u8 = 12;
+        const ENDCHAR: u8 = 14;
+        const VSINDEX: u8 = 15;
+        const BLEND: u8 = 16;
+        const HSTEMHM: u8 = 18;
+        const HINTMASK: u8 = 19;
+        const CNTRMASK: u8 = 20;
+        const VSTEMHM: u8 = 23;
+        const CALLGSUBR: u8 = 29;
+
+        if depth > NESTING_DEPTH_LIMIT {
+            return Err(ReadError::MalformedData(
+                "charstring subroutines are nested too deeply",
+            ));
+        }
+        let mut pos = 0;
+        while let Some(&b0) = data.get(pos) {
+            pos += 1;
+            let bytes = &data[pos..];
+            let value = match b0 {
+                28 => {
+                    let value = bytes.get(..2).ok_or(ReadError::OutOfBounds)?;
+                    pos += 2;
+                    i16::from_be_bytes([value[0], value[1]]) as f64
+                }
+                32..=246 => b0 as f64 - 139.0,
+                247..=250 => {
+                    pos += 1;
+                    let b1 = *bytes.first().ok_or(ReadError::OutOfBounds)? as f64;
+                    (b0 as f64 - 247.0) * 256.0 + b1 + 108.0
+                }
+                251..=254 => {
+                    pos += 1;
+                    let b1 = *bytes.first().ok_or(ReadError::OutOfBounds)? as f64;
+                    -(b0 as f64 - 251.0) * 256.0 - b1 - 108.0
+                }
+                // a 16.16 fixed point value
+                255 => {
+                    let value = bytes.get(..4).ok_or(ReadError::OutOfBounds)?;
+                    pos += 4;
+                    Fixed::from_bits(i32::from_be_bytes(value.try_into().unwrap())).to_f64()
+                }
+                HSTEM | VSTEM | HSTEMHM | VSTEMHM => {
+                    self.stem_count += self.stack.len() / 2;
+                    self.flush(&[b0])?;
+                    continue;
+                }
+                HINTMASK | CNTRMASK => {
+                    // an implied vstem
+                    self.stem_count += self.stack.len() / 2;
+                    let len = self.stem_count.div_ceil(8);
+                    let mask = bytes.get(..len).ok_or(ReadError::OutOfBounds)?;
+                    pos += len;
+                    self.flush(&[b0])?;
+                    self.out.extend_from_slice(mask);
+                    continue;
+                }
+                CALLSUBR | CALLGSUBR => {
+                    let subrs = if b0 == CALLSUBR {
+                        self.local_subrs.ok_or_else(malformed)?
+                    } else {
+                        self.global_subrs
+                    };
+                    let idx =
+                        self.stack.pop().ok_or_else(malformed)?.default as i32 + subrs.subr_bias();
+                    let subr = subrs
+                        .get(usize::try_from(idx).map_err(|_| malformed())?)
+                        .map_err(ps_error)?;
+                    self.instantiate(subr, depth + 1)?;
+                    continue;
+                }
+                RETURN => return Ok(()),
+                ENDCHAR => return self.flush(&[b0]),
+                VSINDEX => {
+                    let vsindex = self.stack.pop().ok_or_else(malformed)?.default;
+                    self.vsindex = vsindex as u16;
+                    if !self.blender.is_static {
+                        NumberEncoding::Charstring.write_number(&mut self.out, vsindex);
+                        self.out.push(VSINDEX);
+                    }
+                    continue;
+                }
+                BLEND => {
+                    self.blender.blend(self.vsindex, &mut self.stack)?;
+                    continue;
+                }
+                ESCAPE => {
+                    let b1 = *bytes.first().ok_or(ReadError::OutOfBounds)?;
+                    pos += 1;
+                    self.flush(&[b0, b1])?;
+                    continue;
+                }
+                _ => {
+                    self.flush(&[b0])?;
+                    continue;
+                }
+            };
+            self.stack.push(Operand::constant(value));
+        }
+        Ok(())
+    }
+}
+
+/// Tracks the bounds of the points of an outline.
+#[derive(Default)]
+struct ControlBounds(Option<[f64; 4]>);
+
+impl ControlBounds {
+    fn add(&mut self, x: Fixed, y: Fixed) {
+        let (x, y) = (x.to_f64(), y.to_f64());
+        let bounds = self.0.get_or_insert([x, y, x, y]);
+        *bounds = [
+            bounds[0].min(x),
+            bounds[1].min(y),
+            bounds[2].max(x),
+            bounds[3].max(y),
+        ];
+    }
+}
+
+impl CommandSink for ControlBounds {
+    fn move_to(&mut self, x: Fixed, y: Fixed) {
+        self.add(x, y);
+    }
+
+    fn line_to(&mut self, x: Fixed, y: Fixed) {
+        self.add(x, y);
+    }
+
+    fn curve_to(&mut self, cx0: Fixed, cy0: Fixed, cx1: Fixed, cy1: Fixed, x: Fixed, y: Fixed) {
+        self.add(cx0, cy0);
+        self.add(cx1, cy1);
+        self.add(x, y);
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Rewrites a [CFF2] table for the new axis limits.
+///
+/// [CFF2]: https://learn.microsoft.com/en-us/typography/opentype/spec/cff2
+fn instantiate_cff2(plan: &Plan, tables: &mut Tables, data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let cff2 = Cff2::read(FontData::new(data))?;
+    let mut font = CffFont::new_cff2(data)?;
+    let header_len = cff2.header().header_size() as usize;
+    let store = match font
+        .top_dict
+        .iter()
+        .find(|entry| entry.op == VARIATION_STORE)
+    {
+        // the variation store is prefixed by its length
+        Some(entry) => {
+            let data = data
+                .get(entry.offset()? + 2..)
+                .ok_or(ReadError::OutOfBounds)?;
+            Some(ItemVariationStore::read(FontData::new(data))?)
+        }
+        None => None,
+    };
+    let blender = Blender::new(plan, store.as_ref())?;
+
+    // the location of the new default, in the source design space
+    let coords: Vec<_> = plan
+        .limits
+        .iter()
+        .map(|limit| match limit {
+            Some(NormalizedLimit::Pin(value)) => F2Dot14::from_f32(*value as f32),
+            _ => F2Dot14::ZERO,
+        })
+        .collect();
+    let local_subrs = font
+        .font_dicts
+        .iter()
+        .map(|font_dict| {
+            let subrs = font_dict.private.as_ref().and_then(|private| private.subrs);
+            subrs
+                .map(|subrs| Index::new(subrs, true).map_err(ps_error))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let vsindexes = font
+        .font_dicts
+        .iter()
+        .map(|font_dict| default_vsindex(font_dict.private.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut charstrings = Vec::new();
+    let mut bounds = ControlBounds::default();
+    for gid in 0..font.num_glyphs() {
+        let glyph_id = GlyphId::new(gid as u16);
+        let fd = font
+            .fd_select
+            .as_ref()
+            .and_then(|fd_select| fd_select.font_index(glyph_id))
+            .unwrap_or_default() as usize;
+        let local_subrs = local_subrs.get(fd).and_then(Option::as_ref);
+        let vsindex = vsindexes.get(fd).copied().unwrap_or_default();
+        let charstring = font.charstrings.get(gid).map_err(ps_error)?;
+        let mut instancer = CharstringInstancer {
+            blender: &blender,
+            global_subrs: &font.global_subrs,
+            local_subrs,
+            vsindex,
+            stack: Vec::new(),
+            stem_count: 0,
+            out: Vec::new(),
+        };
+        instancer.instantiate(charstring, 0)?;
+        charstrings.push(instancer.out);
+
+        // the outline at the new default location
+        let blend_state = store
+            .clone()
+            .map(|store| BlendState::new(store, &coords, vsindex))
+            .transpose()
+            .map_err(ps_error)?;
+        let mut glyph_bounds = ControlBounds::default();
+        charstring::evaluate(
+            charstring,
+            font.global_subrs.clone(),
+            local_subrs.cloned(),
+            blend_state,
+            &mut glyph_bounds,
+        )
+        .map_err(ps_error)?;
+        if let Some([x_min, y_min, x_max, y_max]) = glyph_bounds.0 {
+            if let Some(metric) = tables.hmtx.as_mut().and_then(|hmtx| hmtx.get_mut(gid)) {
+                metric.side_bearing = x_min.floor() as i16;
+            }
+            bounds.add(Fixed::from_f64(x_min), Fixed::from_f64(y_min));
+            bounds.add(Fixed::from_f64(x_max), Fixed::from_f64(y_max));
+        }
+    }
+    if let (Some(head), Some([x_min, y_min, x_max, y_max])) = (tables.head.as_mut(), bounds.0) {
+        head.x_min = x_min.floor() as i16;
+        head.y_min = y_min.floor() as i16;
+        head.x_max = x_max.ceil() as i16;
+        head.y_max = y_max.ceil() as i16;
+    }
+
+    let privates = font
+        .font_dicts
+        .iter()
+        .map(|font_dict| {
+            font_dict
+                .private
+                .as_ref()
+                .map(|private| instantiate_private(&blender, private))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let font_dicts = font
+        .font_dicts
+        .iter()
+        .zip(&privates)
+        .map(|(font_dict, private)| FontDict {
+            entries: font_dict.entries.clone(),
+            private: private.as_ref().map(|entries| PrivateDict {
+                entries: entries
+                    .iter()
+                    .map(|(op, operands)| DictEntry { operands, op: *op })
+                    .collect(),
+                subrs: None,
+            }),
+        })
+        .collect();
+    let variation_store = (!blender.is_static && store.is_some()).then(|| {
+        let store = dump_table(&blender.store()).expect("variation store is always valid");
+        let mut data = (store.len() as u16).to_be_bytes().to_vec();
+        data.extend(store);
+        data
+    });
+    if variation_store.is_none() {
+        font.top_dict.retain(|entry| entry.op != VARIATION_STORE);
+    }
+    font.font_dicts = font_dicts;
+
+    let fd_select =
+        font.write_fd_select((0..font.num_glyphs()).map(|gid| GlyphId::new(gid as u16)));
+    let charstrings = write_index(charstrings.iter().map(Vec::as_slice), true);
+    let global_subrs = write_index([], true);
+
+    let replaced = [VARIATION_STORE, CHARSTRINGS, FD_ARRAY, FD_SELECT];
+    let write_top_dict =
+        |offsets: &[(u16, Vec<usize>)]| write_dict(&font.top_dict, &replaced, offsets);
+    let placeholder_offsets = top_dict_offsets(&font, [0; 5], None);
+    let top_dict_len = write_top_dict(&placeholder_offsets).len();
+
+    let variation_store_offset = header_len + top_dict_len + global_subrs.len();
+    let fd_select_offset =
+        variation_store_offset + variation_store.as_ref().map(Vec::len).unwrap_or_default();
+    let charstrings_offset =
+        fd_select_offset + fd_select.as_ref().map(Vec::len).unwrap_or_default();
+    let fd_array_offset = charstrings_offset + charstrings.len();
+    let (fd_array, privates, _) = font.write_dicts(fd_array_offset);
+
+    let offsets = top_dict_offsets(
+        &font,
+        [
+            0,
+            charstrings_offset,
+            fd_select_offset,
+            fd_array_offset,
+            variation_store_offset,
+        ],
+        None,
+    );
+    let top_dict = write_top_dict(&offsets);
+
+    let mut cff2 = data[..header_len].to_vec();
+    cff2[3..5].copy_from_slice(&(top_dict.len() as u16).to_be_bytes());
+    cff2.extend(top_dict);
+    cff2.extend(global_subrs);
+    cff2.extend(variation_store.unwrap_or_default());
+    cff2.extend(fd_select.unwrap_or_default());
+    cff2.extend(charstrings);
+    cff2.extend(fd_array.unwrap_or_default());
+    cff2.extend(privates);
+    Ok(cff2)
+}
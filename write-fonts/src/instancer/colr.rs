@@ -0,0 +1,427 @@
+//! Instancing variable color glyphs.
+//!
+//! The table is copied and patched in place: the default deltas are added
+//! to the fields of each variable paint, color stop and clip box, and their
+//! variation index bases are rewritten to refer to a new variation store and
+//! delta-set index map, which are appended to the table.
+
+use std::collections::{BTreeMap, HashSet};
+
+use read_fonts::{tables::variations::DeltaSetIndex, ReadError, TableProvider};
+use types::{GlyphId, Tag};
+
+use super::{
+    read_error,
+    variations::{variation_index, StoreInstancer, NO_VARIATION_INDEX},
+    InstancerError, Plan,
+};
+use crate::{
+    dump_table,
+    raw::{read_u16, read_u24, read_u32, read_u8, write_u16, write_u32},
+    round::OtRound,
+    tables::variations::DeltaSetIndexMap,
+    FontBuilder,
+};
+
+const COLR: Tag = Tag::new(b"COLR");
+
+/// The position of the offset to the delta-set index map in the header.
+const VAR_INDEX_MAP_OFFSET: usize = 26;
+/// The position of the offset to the item variation store in the header.
+const VARIATION_STORE_OFFSET: usize = 30;
+const BASE_GLYPH_PAINT_RECORD_LEN: usize = 6;
+const CLIP_RECORD_LEN: usize = 7;
+const VAR_COLOR_STOP_LEN: usize = 10;
+
+/// Instances the `COLR` table.
+pub(super) fn instantiate(plan: &Plan, builder: &mut FontBuilder) -> Result<(), InstancerError> {
+    let Some(data) = plan.font.data_for_tag(COLR) else {
+        return Ok(());
+    };
+    let colr = instantiate_colr(plan, data.as_bytes()).map_err(read_error(COLR))?;
+    builder.add_raw(COLR, colr);
+    Ok(())
+}
+
+/// The version 1 lists of a `COLR` table.
+struct RawColr {
+    /// The position of the root paint of each base glyph.
+    base_paints: BTreeMap<GlyphId, usize>,
+    layer_list: Option<usize>,
+    clip_list: Option<usize>,
+}
+
+impl RawColr {
+    fn new(data: &[u8]) -> Result<Self, ReadError> {
+        let offset = |pos| -> Result<Option<usize>, ReadError> {
+            let offset = read_u32(data, pos)? as usize;
+            Ok((offset != 0).then_some(offset))
+        };
+        let mut base_paints = BTreeMap::new();
+        if let Some(list) = offset(14)? {
+            for i in 0..read_u32(data, list)? as usize {
+                let pos = list + 4 + i * BASE_GLYPH_PAINT_RECORD_LEN;
+                let paint = list + read_u32(data, pos + 2)? as usize;
+                base_paints.insert(GlyphId::new(read_u16(data, pos)?), paint);
+            }
+        }
+        Ok(Self {
+            base_paints,
+            layer_list: offset(18)?,
+            clip_list: offset(22)?,
+        })
+    }
+
+    /// Returns the position of a paint in the layer list.
+    fn layer_paint(&self, data: &[u8], idx: usize) -> Result<usize, ReadError> {
+        let list = self.layer_list.ok_or(ReadError::NullOffset)?;
+        Ok(list + read_u32(data, list + 4 + idx * 4)? as usize)
+    }
+}
+
+/// The type of a variable field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    /// An `FWORD` or `F2DOT14`.
+    Int16,
+    /// A `UFWORD`.
+    Uint16,
+    /// A `Fixed`.
+    Fixed,
+}
+
+impl Field {
+    fn len(self) -> usize {
+        match self {
+            Field::Int16 | Field::Uint16 => 2,
+            Field::Fixed => 4,
+        }
+    }
+
+    fn read(self, data: &[u8], pos: usize) -> Result<f64, ReadError> {
+        Ok(match self {
+            Field::Int16 => read_u16(data, pos)? as i16 as f64,
+            Field::Uint16 => read_u16(data, pos)? as f64,
+            Field::Fixed => read_u32(data, pos)? as i32 as f64,
+        })
+    }
+
+    /// The range of raw values of the field.
+    fn range(self) -> (i32, i32) {
+        match self {
+            Field::Int16 => (i16::MIN as i32, i16::MAX as i32),
+            Field::Uint16 => (0, u16::MAX as i32),
+            Field::Fixed => (i32::MIN, i32::MAX),
+        }
+    }
+
+    fn write(self, data: &mut [u8], pos: usize, value: f64) {
+        let (min, max) = self.range();
+        let value: i32 = value.ot_round();
+        let value = value.clamp(min, max);
+        match self {
+            Field::Int16 | Field::Uint16 => write_u16(data, pos, value as u16),
+            Field::Fixed => write_u32(data, pos, value as u32),
+        }
+    }
+}
+
+/// The variable fields of a table, and the position of its
+/// `varIndexBase`.
+///
+/// Field `i` uses the variation index `varIndexBase + i`.
+#[derive(Debug)]
+struct VarItem {
+    fields: Vec<(usize, Field)>,
+    var_index_base: usize,
+}
+
+impl VarItem {
+    /// An item with contiguous fields, followed by the `varIndexBase`.
+    fn contiguous(mut pos: usize, fields: &[Field]) -> Self {
+        let fields = fields
+            .iter()
+            .map(|field| {
+                let item = (pos, *field);
+                pos += field.len();
+                item
+            })
+            .collect();
+        Self {
+            fields,
+            var_index_base: pos,
+        }
+    }
+}
+
+/// The start and the types of the variable fields of a paint format.
+fn paint_fields(format: u8) -> Option<(usize, &'static [Field])> {
+    use Field::*;
+    Some(match format {
+        // PaintVarSolid
+        3 => (3, &[Int16]),
+        // PaintVarLinearGradient
+        5 => (4, &[Int16; 6]),
+        // PaintVarRadialGradient
+        7 => (4, &[Int16, Int16, Uint16, Int16, Int16, Uint16]),
+        // PaintVarSweepGradient, PaintVarScaleAroundCenter,
+        // PaintVarSkewAroundCenter
+        9 | 19 | 31 => (4, &[Int16; 4]),
+        // PaintVarTranslate, PaintVarScale, PaintVarSkew
+        15 | 17 | 29 => (4, &[Int16; 2]),
+        // PaintVarScaleUniform, PaintVarRotate
+        21 | 25 => (4, &[Int16]),
+        // PaintVarScaleUniformAroundCenter, PaintVarRotateAroundCenter
+        23 | 27 => (4, &[Int16; 3]),
+        _ => return None,
+    })
+}
+
+/// Finds the variable tables of a version 1 `COLR` table.
+///
+/// Returns the items, and the position of the last table that was visited.
+fn var_items(colr: &RawColr, data: &[u8]) -> Result<(Vec<VarItem>, usize), ReadError> {
+    let mut items = Vec::new();
+    let mut last = [4, 8, 14, 18, 22]
+        .into_iter()
+        .map(|pos| read_u32(data, pos).map(|offset| offset as usize))
+        .try_fold(0, |last, offset| offset.map(|offset| last.max(offset)))?;
+    let num_layers = match colr.layer_list {
+        Some(list) => read_u32(data, list)? as usize,
+        None => 0,
+    };
+    let mut stack = colr.base_paints.values().copied().collect::<Vec<_>>();
+    for idx in 0..num_layers {
+        stack.push(colr.layer_paint(data, idx)?);
+    }
+    let mut visited = HashSet::new();
+    while let Some(pos) = stack.pop() {
+        if !visited.insert(pos) {
+            continue;
+        }
+        last = last.max(pos);
+        let child = |offset_pos: usize| Ok::<_, ReadError>(pos + read_u24(data, pos + offset_pos)?);
+        let format = read_u8(data, pos)?;
+        match format {
+            // gradients, with a ColorLine or VarColorLine
+            4..=9 => {
+                let color_line = child(1)?;
+                last = last.max(color_line);
+                if format % 2 == 1 && visited.insert(color_line) {
+                    for i in 0..read_u16(data, color_line + 1)? as usize {
+                        let stop = color_line + 3 + i * VAR_COLOR_STOP_LEN;
+                        items.push(VarItem {
+                            fields: vec![(stop, Field::Int16), (stop + 4, Field::Int16)],
+                            var_index_base: stop + 6,
+                        });
+                    }
+                }
+            }
+            // PaintGlyph, and the other transforms
+            10 | 14..=31 => stack.push(child(1)?),
+            // PaintTransform, PaintVarTransform
+            12 | 13 => {
+                stack.push(child(1)?);
+                let transform = child(4)?;
+                last = last.max(transform);
+                if format == 13 && visited.insert(transform) {
+                    items.push(VarItem::contiguous(transform, &[Field::Fixed; 6]));
+                }
+            }
+            // PaintComposite
+            32 => {
+                stack.push(child(1)?);
+                stack.push(child(5)?);
+            }
+            1..=3 | 11 => (),
+            _ => return Err(ReadError::MalformedData("invalid paint format")),
+        }
+        if let Some((start, fields)) = paint_fields(format) {
+            items.push(VarItem::contiguous(pos + start, fields));
+        }
+    }
+    if let Some(list) = colr.clip_list {
+        let mut clip_boxes = HashSet::new();
+        for i in 0..read_u32(data, list + 1)? as usize {
+            let clip_box = list + read_u24(data, list + 5 + i * CLIP_RECORD_LEN + 4)?;
+            last = last.max(clip_box);
+            if read_u8(data, clip_box)? == 2 && clip_boxes.insert(clip_box) {
+                items.push(VarItem::contiguous(clip_box + 1, &[Field::Int16; 4]));
+            }
+        }
+    }
+    Ok((items, last))
+}
+
+/// Rewrites a [COLR] table for the new axis limits.
+///
+/// [COLR]: https://learn.microsoft.com/en-us/typography/opentype/spec/colr
+fn instantiate_colr(plan: &Plan, data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let font_colr = plan.font.colr()?;
+    let (Some(store), 1) = (font_colr.item_variation_store(), font_colr.version()) else {
+        return Ok(data.to_vec());
+    };
+    let mut instancer = StoreInstancer::new(plan, store?)?;
+    let var_index_map = font_colr.var_index_map().transpose()?;
+    let delta_set_index = |var_index: u32| match &var_index_map {
+        Some(map) => map.get(var_index),
+        None => Ok(DeltaSetIndex {
+            outer: (var_index >> 16) as u16,
+            inner: var_index as u16,
+        }),
+    };
+
+    let colr = RawColr::new(data)?;
+    let (items, last) = var_items(&colr, data)?;
+    let mut out = data.to_vec();
+    let mut item_ids = Vec::new();
+    for item in &items {
+        let var_index_base = read_u32(data, item.var_index_base)?;
+        if var_index_base == NO_VARIATION_INDEX {
+            continue;
+        }
+        let mut ids = Vec::new();
+        for (i, (pos, field)) in item.fields.iter().enumerate() {
+            let index = delta_set_index(var_index_base.saturating_add(i as u32))?;
+            let (default, id) = instancer.instance(index)?;
+            field.write(&mut out, *pos, field.read(data, *pos)? + default);
+            ids.push(id);
+        }
+        item_ids.push((item.var_index_base, ids));
+    }
+
+    // the source variation data is dropped if nothing follows it
+    let var_data_start = [VAR_INDEX_MAP_OFFSET, VARIATION_STORE_OFFSET]
+        .into_iter()
+        .map(|pos| read_u32(data, pos).map(|offset| offset as usize))
+        .filter(|offset| !matches!(offset, Ok(0)))
+        .try_fold(usize::MAX, |start, offset| {
+            offset.map(|offset| start.min(offset))
+        })?;
+    if var_data_start > last && var_data_start < out.len() {
+        out.truncate(var_data_start);
+    }
+    write_u32(&mut out, VAR_INDEX_MAP_OFFSET, 0);
+    write_u32(&mut out, VARIATION_STORE_OFFSET, 0);
+
+    let remapping = instancer.build();
+    let mut entries = Vec::new();
+    for (pos, ids) in item_ids {
+        let var_index_base = match &remapping {
+            Some((_, remapping)) if ids.iter().any(Option::is_some) => {
+                let base = entries.len() as u32;
+                entries.extend(ids.into_iter().map(|id| variation_index(remapping, id)));
+                base
+            }
+            _ => NO_VARIATION_INDEX,
+        };
+        write_u32(&mut out, pos, var_index_base);
+    }
+    let Some((store, _)) = remapping.filter(|_| !entries.is_empty()) else {
+        return Ok(out);
+    };
+    let invalid = |_| ReadError::MalformedData("invalid instanced variation data");
+    let map: DeltaSetIndexMap = entries.into_iter().collect();
+    for (pos, table) in [
+        (VAR_INDEX_MAP_OFFSET, dump_table(&map).map_err(invalid)?),
+        (VARIATION_STORE_OFFSET, dump_table(&store).map_err(invalid)?),
+    ] {
+        out.resize(out.len().next_multiple_of(4), 0);
+        let offset = out.len() as u32;
+        write_u32(&mut out, pos, offset);
+        out.extend(table);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{tables::variations::DeltaSetIndexMap as ReadDeltaSetIndexMap, FontRef};
+    use types::{F2Dot14, Fixed};
+
+    use super::*;
+    use crate::instancer::{instantiate_font, AxisLimits};
+
+    /// The raw values of the variable fields of a `COLR` table, at the given
+    /// location.
+    fn var_values(font: &FontRef, coords: &[F2Dot14]) -> Vec<f64> {
+        let data = font.data_for_tag(COLR).unwrap();
+        let data = data.as_bytes();
+        let colr = font.colr().unwrap();
+        let store = colr.item_variation_store().transpose().unwrap();
+        let map: Option<ReadDeltaSetIndexMap> = colr.var_index_map().transpose().unwrap();
+        let (items, _) = var_items(&RawColr::new(data).unwrap(), data).unwrap();
+        let mut values = Vec::new();
+        for item in items {
+            let base = read_u32(data, item.var_index_base).unwrap();
+            for (i, (pos, field)) in item.fields.iter().enumerate() {
+                let mut value = field.read(data, *pos).unwrap();
+                if let (Some(store), false) = (&store, base == NO_VARIATION_INDEX) {
+                    let index = map.as_ref().unwrap().get(base + i as u32).unwrap();
+                    if (index.outer, index.inner) != (0xFFFF, 0xFFFF) {
+                        value += store.compute_delta(index, coords).unwrap() as f64;
+                    }
+                }
+                let (min, max) = field.range();
+                values.push(value.clamp(min as f64, max as f64));
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn pinned_paints_match_source() {
+        let font = FontRef::new(font_test_data::COLRV0V1_VARIABLE).unwrap();
+        let axes = font.fvar().unwrap().axes().unwrap();
+        let mut limits = AxisLimits::new();
+        let mut coords = Vec::new();
+        for axis in axes {
+            let value = (axis.default_value().to_f64() + axis.max_value().to_f64()) / 2.0;
+            limits.pin(axis.axis_tag(), value);
+            coords.push(axis.normalize(Fixed::from_f64(value)).to_f2dot14());
+        }
+        let expected = var_values(&font, &coords);
+        assert!(!expected.is_empty());
+
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        let colr = instance.colr().unwrap();
+        assert!(colr.item_variation_store().is_none());
+        assert!(colr.var_index_map().is_none());
+        let actual = var_values(&instance, &[]);
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(&actual) {
+            assert!((expected - actual).abs() <= 1.0, "{expected} != {actual}");
+        }
+    }
+
+    #[test]
+    fn restricted_paints_match_source() {
+        let font = FontRef::new(font_test_data::COLRV0V1_VARIABLE).unwrap();
+        let axes = font.fvar().unwrap().axes().unwrap();
+        let axis = &axes[0];
+        let (default, max) = (axis.default_value().to_f64(), axis.max_value().to_f64());
+        let mut limits = AxisLimits::new();
+        limits.restrict(
+            axis.axis_tag(),
+            axis.min_value().to_f64(),
+            (default + max) / 2.0,
+        );
+        let instance = instantiate_font(&font, &limits).unwrap();
+        let instance = FontRef::new(&instance).unwrap();
+        let instance_axes = instance.fvar().unwrap().axes().unwrap();
+
+        let value = (default + max) / 4.0 + default / 2.0;
+        let location = |axes: &[read_fonts::tables::fvar::VariationAxisRecord]| {
+            let mut coords = vec![F2Dot14::ZERO; axes.len()];
+            coords[0] = axes[0].normalize(Fixed::from_f64(value)).to_f2dot14();
+            coords
+        };
+        let expected = var_values(&font, &location(axes));
+        let actual = var_values(&instance, &location(instance_axes));
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(&actual) {
+            assert!((expected - actual).abs() <= 2.0, "{expected} != {actual}");
+        }
+    }
+}
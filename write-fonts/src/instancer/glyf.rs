@@ -0,0 +1,610 @@
+//! Instancing TrueType outlines, metrics and control values.
+
+use std::collections::HashMap;
+
+use kurbo::{BezPath, Point, Vec2};
+use read_fonts::{
+    tables::{gvar::GlyphVariationData, variations::Tuple as ReadTuple},
+    TableProvider,
+};
+use types::{GlyphId, Tag};
+
+use super::{
+    optional, read_error,
+    solver::{is_default, Region, Tent},
+    InstancerError, Plan, Tables,
+};
+use crate::{
+    round::OtRound,
+    tables::{
+        glyf::{Anchor, Bbox, GlyfLocaBuilder, Glyph, SimpleGlyph},
+        gvar::{
+            iup::{iup_delta, iup_delta_optimize},
+            GlyphDelta, GlyphDeltas, GlyphVariations, Gvar,
+        },
+        variations::{PackedDeltas, Tuple, TupleIndex},
+    },
+    BuilderError, FontBuilder,
+};
+
+const GLYF: Tag = Tag::new(b"glyf");
+const GVAR: Tag = Tag::new(b"gvar");
+const CVT: Tag = Tag::new(b"cvt ");
+const CVAR: Tag = Tag::new(b"cvar");
+
+/// The number of phantom points that follow the points of each glyph.
+const NUM_PHANTOM_POINTS: usize = 4;
+/// The tolerance when removing deltas that can be interpolated, in font
+/// units.
+const IUP_TOLERANCE: f64 = 0.5;
+/// The maximum depth of nested composite glyphs.
+const MAX_COMPONENT_DEPTH: usize = 64;
+
+/// Instances the `glyf`, `gvar`, `cvt ` and `cvar` tables, and updates the
+/// glyph metrics and the font bounding box.
+pub(super) fn instantiate(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    instantiate_glyphs(plan, tables, builder)?;
+    instantiate_cvt(plan, builder)
+}
+
+/// A glyph, with the coordinates that its variations apply to.
+struct VarGlyph {
+    glyph: Option<Glyph>,
+    /// The points of a simple glyph or the component offsets of a composite
+    /// glyph, followed by the phantom points.
+    coords: Vec<Point>,
+    /// The index of the last point of each contour of a simple glyph.
+    contour_ends: Vec<usize>,
+    /// The remaining variations, with a delta for each coordinate.
+    variations: Vec<(Region, Vec<Vec2>)>,
+}
+
+impl VarGlyph {
+    fn new(glyph: Option<Glyph>, phantom_points: [Point; NUM_PHANTOM_POINTS]) -> Self {
+        let mut coords = Vec::new();
+        let mut contour_ends = Vec::new();
+        match &glyph {
+            Some(Glyph::Simple(simple)) => {
+                for contour in simple.contours() {
+                    coords.extend(
+                        contour
+                            .iter()
+                            .map(|point| Point::new(point.x as f64, point.y as f64)),
+                    );
+                    contour_ends.push(coords.len() - 1);
+                }
+            }
+            Some(Glyph::Composite(composite)) => coords.extend(composite.components().iter().map(
+                |component| match component.anchor {
+                    Anchor::Offset { x, y } => Point::new(x as f64, y as f64),
+                    Anchor::Point { .. } => Point::ZERO,
+                },
+            )),
+            None => (),
+        }
+        coords.extend(phantom_points);
+        Self {
+            glyph,
+            coords,
+            contour_ends,
+            variations: Vec::new(),
+        }
+    }
+
+    /// Returns a delta for each coordinate, interpolating the deltas of
+    /// points without an explicit delta.
+    fn dense_deltas(&self, tuple: &read_fonts::tables::gvar::TupleVariation) -> Vec<Vec2> {
+        let mut deltas = vec![None; self.coords.len()];
+        for delta in tuple.deltas() {
+            if let Some(slot) = deltas.get_mut(delta.position as usize) {
+                *slot = Some(Vec2::new(delta.x_delta as f64, delta.y_delta as f64));
+            }
+        }
+        if tuple.has_deltas_for_all_points() || !matches!(self.glyph, Some(Glyph::Simple(_))) {
+            return deltas.into_iter().map(Option::unwrap_or_default).collect();
+        }
+        iup_delta(&deltas, &self.coords, &self.contour_ends)
+            .expect("contours are within the glyph points")
+    }
+
+    /// Rebases the variations of the glyph, applying those that no longer
+    /// vary to its coordinates.
+    fn instantiate(&mut self, plan: &Plan, data: GlyphVariationData) {
+        let mut default = vec![Vec2::ZERO; self.coords.len()];
+        let mut regions: HashMap<Region, usize> = HashMap::new();
+        for tuple in data.tuples() {
+            let region = tuple_region(tuple.peak(), tuple.intermediate(), plan.axis_count);
+            let deltas = self.dense_deltas(&tuple);
+            for (scale, region) in plan.rebase(&region) {
+                let target = if is_default(&region) {
+                    &mut default
+                } else {
+                    let idx = *regions.entry(region.clone()).or_insert_with(|| {
+                        self.variations
+                            .push((region, vec![Vec2::ZERO; self.coords.len()]));
+                        self.variations.len() - 1
+                    });
+                    &mut self.variations[idx].1
+                };
+                for (target, delta) in target.iter_mut().zip(&deltas) {
+                    *target += *delta * scale;
+                }
+            }
+        }
+        for (coord, delta) in self.coords.iter_mut().zip(default) {
+            *coord = (coord.to_vec2() + delta).ot_round().to_point();
+        }
+        for (_, deltas) in self.variations.iter_mut() {
+            for delta in deltas.iter_mut() {
+                *delta = delta.ot_round();
+            }
+        }
+        self.variations
+            .retain(|(_, deltas)| deltas.iter().any(|delta| *delta != Vec2::ZERO));
+    }
+
+    /// Writes the instanced coordinates back to the glyph.
+    fn update_glyph(&mut self) {
+        let mut coords = self.coords.iter();
+        match &mut self.glyph {
+            Some(Glyph::Simple(simple)) => {
+                for point in simple
+                    .contours_mut()
+                    .iter_mut()
+                    .flat_map(|contour| contour.iter_mut())
+                {
+                    let coord = coords.next().unwrap();
+                    point.x = coord.x as i16;
+                    point.y = coord.y as i16;
+                }
+            }
+            Some(Glyph::Composite(composite)) => {
+                for component in composite.components_mut() {
+                    let coord = coords.next().unwrap();
+                    if let Anchor::Offset { x, y } = &mut component.anchor {
+                        *x = coord.x as i16;
+                        *y = coord.y as i16;
+                    }
+                }
+            }
+            None => (),
+        }
+    }
+
+    fn phantom_points(&self) -> &[Point] {
+        &self.coords[self.coords.len() - NUM_PHANTOM_POINTS..]
+    }
+
+    /// The variations of the glyph, for the `gvar` table.
+    fn glyph_deltas(&self) -> Vec<GlyphDeltas> {
+        self.variations
+            .iter()
+            .map(|(region, deltas)| {
+                let deltas = if matches!(self.glyph, Some(Glyph::Simple(_)) | None) {
+                    iup_delta_optimize(
+                        deltas.clone(),
+                        self.coords.clone(),
+                        IUP_TOLERANCE,
+                        &self.contour_ends,
+                    )
+                    .expect("deltas match the glyph points")
+                } else {
+                    deltas
+                        .iter()
+                        .map(|delta| GlyphDelta::required(delta.x as i16, delta.y as i16))
+                        .collect()
+                };
+                let (peak, intermediate) = region_tuples(region);
+                GlyphDeltas::new(peak, deltas, intermediate)
+            })
+            .collect()
+    }
+}
+
+/// Returns the region of a `gvar` or `cvar` tuple.
+fn tuple_region(
+    peak: ReadTuple,
+    intermediate: Option<(ReadTuple, ReadTuple)>,
+    axis_count: u16,
+) -> Region {
+    (0..axis_count as usize)
+        .map(|i| {
+            let peak = peak.get(i).unwrap_or_default();
+            match &intermediate {
+                Some((start, end)) => Tent::new(
+                    start.get(i).unwrap_or_default(),
+                    peak,
+                    end.get(i).unwrap_or_default(),
+                ),
+                None => Tent::from_peak(peak),
+            }
+        })
+        .collect()
+}
+
+/// Returns the peak tuple of a region, and its intermediate region if the
+/// region does not span from the default location to the peak.
+fn region_tuples(region: &[Tent]) -> (Tuple, Option<(Tuple, Tuple)>) {
+    let peak = Tuple::new(region.iter().map(|tent| tent.peak).collect());
+    let intermediate = region
+        .iter()
+        .any(|tent| *tent != Tent::from_peak(tent.peak))
+        .then(|| {
+            (
+                Tuple::new(region.iter().map(|tent| tent.start).collect()),
+                Tuple::new(region.iter().map(|tent| tent.end).collect()),
+            )
+        });
+    (peak, intermediate)
+}
+
+fn instantiate_glyphs(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    let font = &plan.font;
+    let Some(glyf) = optional(GLYF, font.glyf())? else {
+        return Ok(());
+    };
+    let loca = font.loca(None).map_err(read_error(Tag::new(b"loca")))?;
+    let gvar = optional(GVAR, font.gvar())?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(read_error(Tag::new(b"maxp")))?
+        .num_glyphs();
+    let (ascender, descender) = tables
+        .hhea
+        .as_ref()
+        .map(|hhea| (hhea.ascender.to_i16(), hhea.descender.to_i16()))
+        .unwrap_or_default();
+
+    let mut glyphs = Vec::with_capacity(num_glyphs as usize);
+    for gid in (0..num_glyphs).map(GlyphId::new) {
+        let glyph = loca
+            .get_glyf(gid, &glyf)
+            .map_err(read_error(GLYF))?
+            .map(|glyph| crate::from_obj::ToOwnedTable::to_owned_table(&glyph));
+        let bbox = glyph.as_ref().map(Glyph::bbox).unwrap_or_default();
+        let idx = gid.to_u16() as usize;
+        let (advance, lsb) = metric(tables.hmtx.as_deref(), idx).unwrap_or((0, bbox.x_min));
+        let (vadvance, tsb) = metric(tables.vmtx.as_deref(), idx)
+            .unwrap_or(((ascender - descender) as u16, ascender - bbox.y_max));
+        let left = (bbox.x_min - lsb) as f64;
+        let top = (bbox.y_max + tsb) as f64;
+        let mut glyph = VarGlyph::new(
+            glyph,
+            [
+                Point::new(left, 0.0),
+                Point::new(left + advance as f64, 0.0),
+                Point::new(0.0, top),
+                Point::new(0.0, top - vadvance as f64),
+            ],
+        );
+        // glyphs without variations have no variation data
+        if let Some(data) = gvar
+            .as_ref()
+            .and_then(|gvar| gvar.glyph_variation_data(gid).ok())
+        {
+            glyph.instantiate(plan, data);
+        }
+        glyph.update_glyph();
+        glyphs.push(glyph);
+    }
+
+    // bounding boxes of composites depend on their (instanced) components
+    let mut points = HashMap::new();
+    let bboxes: Vec<_> = (0..glyphs.len())
+        .map(|idx| {
+            let glyph_points = flattened_points(&glyphs, idx, &mut points, 0);
+            points_bbox(&glyph_points)
+        })
+        .collect();
+    for (glyph, bbox) in glyphs.iter_mut().zip(&bboxes) {
+        match &mut glyph.glyph {
+            Some(Glyph::Simple(simple)) => simple.bbox = *bbox,
+            Some(Glyph::Composite(composite)) => composite.bbox = *bbox,
+            None => (),
+        }
+    }
+    update_metrics(&glyphs, &bboxes, tables);
+
+    let empty = SimpleGlyph::from_bezpath(&BezPath::new()).unwrap();
+    let mut glyf_builder = GlyfLocaBuilder::new();
+    for glyph in &glyphs {
+        match &glyph.glyph {
+            Some(glyph) => glyf_builder.add_glyph(glyph),
+            None => glyf_builder.add_glyph(&empty),
+        }
+        .map_err(|inner| BuilderError { tag: GLYF, inner })?;
+    }
+    let (glyf, loca, loca_format) = glyf_builder.build();
+    builder.add_table(&glyf)?;
+    builder.add_table(&loca)?;
+    if let Some(head) = tables.head.as_mut() {
+        head.index_to_loc_format = loca_format as i16;
+    }
+
+    if gvar.is_some() && !plan.is_static() {
+        let variations: Vec<_> = glyphs
+            .iter()
+            .enumerate()
+            .map(|(gid, glyph)| {
+                GlyphVariations::new(GlyphId::new(gid as u16), glyph.glyph_deltas())
+            })
+            .collect();
+        // a table without any variations would have no axes
+        if variations.iter().any(|glyph| glyph.axis_count().is_some()) {
+            let gvar = Gvar::new(variations).expect("all regions have the retained axes");
+            builder.add_table(&gvar)?;
+        }
+    }
+    Ok(())
+}
+
+fn metric(metrics: Option<&[crate::tables::hmtx::LongMetric]>, idx: usize) -> Option<(u16, i16)> {
+    metrics
+        .and_then(|metrics| metrics.get(idx))
+        .map(|metric| (metric.advance, metric.side_bearing))
+}
+
+/// Returns the points of a glyph, with composite glyphs flattened into the
+/// points of their components.
+fn flattened_points(
+    glyphs: &[VarGlyph],
+    idx: usize,
+    cache: &mut HashMap<usize, Vec<Point>>,
+    depth: usize,
+) -> Vec<Point> {
+    if let Some(points) = cache.get(&idx) {
+        return points.clone();
+    }
+    let Some(glyph) = glyphs.get(idx) else {
+        return Vec::new();
+    };
+    let points = match &glyph.glyph {
+        Some(Glyph::Simple(_)) => glyph.coords[..glyph.coords.len() - NUM_PHANTOM_POINTS].to_vec(),
+        Some(Glyph::Composite(composite)) if depth < MAX_COMPONENT_DEPTH => {
+            let mut points: Vec<Point> = Vec::new();
+            for component in composite.components() {
+                let transform = component.transform;
+                let component_points: Vec<_> =
+                    flattened_points(glyphs, component.glyph.to_u16() as usize, cache, depth + 1)
+                        .into_iter()
+                        .map(|point| {
+                            let [xx, yx, xy, yy] =
+                                [transform.xx, transform.yx, transform.xy, transform.yy]
+                                    .map(|value| value.to_f32() as f64);
+                            Point::new(xx * point.x + xy * point.y, yx * point.x + yy * point.y)
+                        })
+                        .collect();
+                let offset = match component.anchor {
+                    Anchor::Offset { x, y } => Vec2::new(x as f64, y as f64),
+                    Anchor::Point { base, component } => {
+                        let base = points.get(base as usize).copied().unwrap_or_default();
+                        let component = component_points
+                            .get(component as usize)
+                            .copied()
+                            .unwrap_or_default();
+                        base - component
+                    }
+                };
+                points.extend(component_points.into_iter().map(|point| point + offset));
+            }
+            points
+        }
+        _ => Vec::new(),
+    };
+    cache.insert(idx, points.clone());
+    points
+}
+
+fn points_bbox(points: &[Point]) -> Bbox {
+    let Some(first) = points.first() else {
+        return Bbox::default();
+    };
+    let (mut min, mut max) = (*first, *first);
+    for point in points {
+        min = Point::new(min.x.min(point.x), min.y.min(point.y));
+        max = Point::new(max.x.max(point.x), max.y.max(point.y));
+    }
+    Bbox {
+        x_min: min.x.ot_round(),
+        y_min: min.y.ot_round(),
+        x_max: max.x.ot_round(),
+        y_max: max.y.ot_round(),
+    }
+}
+
+/// Updates the glyph metrics from the phantom points, and the summary values
+/// in the `head`, `hhea` and `vhea` tables.
+fn update_metrics(glyphs: &[VarGlyph], bboxes: &[Bbox], tables: &mut Tables) {
+    let has_outline = |glyph: &VarGlyph| glyph.glyph.as_ref().is_some_and(|g| !g.is_empty());
+    if let Some(hmtx) = tables.hmtx.as_mut() {
+        for ((metric, glyph), bbox) in hmtx.iter_mut().zip(glyphs).zip(bboxes) {
+            let [left, right, ..] = glyph.phantom_points() else {
+                continue;
+            };
+            metric.advance = (right.x - left.x).max(0.0).min(u16::MAX as f64) as u16;
+            metric.side_bearing = (bbox.x_min as f64 - left.x) as i16;
+        }
+        if let Some(hhea) = tables.hhea.as_mut() {
+            let mut advance_max = 0;
+            let mut min_lsb = i16::MAX;
+            let mut min_rsb = i16::MAX;
+            let mut max_extent = i16::MIN;
+            for ((metric, glyph), bbox) in hmtx.iter().zip(glyphs).zip(bboxes) {
+                advance_max = advance_max.max(metric.advance);
+                if !has_outline(glyph) {
+                    continue;
+                }
+                let extent = metric.side_bearing + (bbox.x_max - bbox.x_min);
+                min_lsb = min_lsb.min(metric.side_bearing);
+                min_rsb = min_rsb.min(metric.advance as i16 - extent);
+                max_extent = max_extent.max(extent);
+            }
+            hhea.advance_width_max = advance_max.into();
+            if max_extent != i16::MIN {
+                hhea.min_left_side_bearing = min_lsb.into();
+                hhea.min_right_side_bearing = min_rsb.into();
+                hhea.x_max_extent = max_extent.into();
+            }
+        }
+    }
+    if let Some(vmtx) = tables.vmtx.as_mut() {
+        for ((metric, glyph), bbox) in vmtx.iter_mut().zip(glyphs).zip(bboxes) {
+            let [.., top, bottom] = glyph.phantom_points() else {
+                continue;
+            };
+            metric.advance = (top.y - bottom.y).max(0.0).min(u16::MAX as f64) as u16;
+            metric.side_bearing = (top.y - bbox.y_max as f64) as i16;
+        }
+        if let Some(vhea) = tables.vhea.as_mut() {
+            let mut advance_max = 0;
+            let mut min_tsb = i16::MAX;
+            let mut min_bsb = i16::MAX;
+            let mut max_extent = i16::MIN;
+            for ((metric, glyph), bbox) in vmtx.iter().zip(glyphs).zip(bboxes) {
+                advance_max = advance_max.max(metric.advance);
+                if !has_outline(glyph) {
+                    continue;
+                }
+                let extent = metric.side_bearing + (bbox.y_max - bbox.y_min);
+                min_tsb = min_tsb.min(metric.side_bearing);
+                min_bsb = min_bsb.min(metric.advance as i16 - extent);
+                max_extent = max_extent.max(extent);
+            }
+            vhea.advance_height_max = advance_max.into();
+            if max_extent != i16::MIN {
+                vhea.min_top_side_bearing = min_tsb.into();
+                vhea.min_bottom_side_bearing = min_bsb.into();
+                vhea.y_max_extent = max_extent.into();
+            }
+        }
+    }
+    if let Some(head) = tables.head.as_mut() {
+        let bbox = glyphs
+            .iter()
+            .zip(bboxes)
+            .filter(|(glyph, _)| has_outline(glyph))
+            .map(|(_, bbox)| *bbox)
+            .reduce(Bbox::union)
+            .unwrap_or_default();
+        head.x_min = bbox.x_min;
+        head.y_min = bbox.y_min;
+        head.x_max = bbox.x_max;
+        head.y_max = bbox.y_max;
+    }
+}
+
+/// Instances the control values, rewriting the `cvar` table if any
+/// variations remain.
+fn instantiate_cvt(plan: &Plan, builder: &mut FontBuilder) -> Result<(), InstancerError> {
+    let Some(cvt) = plan.font.data_for_tag(CVT) else {
+        return Ok(());
+    };
+    let mut values: Vec<f64> = cvt
+        .as_bytes()
+        .chunks_exact(2)
+        .map(|value| i16::from_be_bytes([value[0], value[1]]) as f64)
+        .collect();
+    let Some(cvar) = optional(CVAR, plan.font.cvar())? else {
+        builder.add_raw(CVT, cvt.as_bytes().to_vec());
+        return Ok(());
+    };
+    let data = cvar
+        .variation_data(plan.axis_count)
+        .map_err(read_error(CVAR))?;
+    let mut variations: Vec<(Region, Vec<f64>)> = Vec::new();
+    let mut regions: HashMap<Region, usize> = HashMap::new();
+    for tuple in data.tuples() {
+        let region = tuple_region(tuple.peak(), tuple.intermediate(), plan.axis_count);
+        let mut deltas = vec![0.0; values.len()];
+        for delta in tuple.deltas() {
+            if let Some(slot) = deltas.get_mut(delta.position as usize) {
+                *slot = delta.value as f64;
+            }
+        }
+        for (scale, region) in plan.rebase(&region) {
+            let target = if is_default(&region) {
+                &mut values
+            } else {
+                let idx = *regions.entry(region.clone()).or_insert_with(|| {
+                    variations.push((region, vec![0.0; deltas.len()]));
+                    variations.len() - 1
+                });
+                &mut variations[idx].1
+            };
+            for (target, delta) in target.iter_mut().zip(&deltas) {
+                *target += delta * scale;
+            }
+        }
+    }
+
+    let cvt: Vec<u8> = values
+        .iter()
+        .flat_map(|value| {
+            let value: i32 = value.ot_round();
+            (value.clamp(i16::MIN as i32, i16::MAX as i32) as i16).to_be_bytes()
+        })
+        .collect();
+    builder.add_raw(CVT, cvt);
+    let variations: Vec<_> = variations
+        .into_iter()
+        .map(|(region, deltas)| {
+            let deltas: Vec<i16> = deltas.iter().map(|delta| delta.ot_round()).collect();
+            (region, deltas)
+        })
+        .filter(|(_, deltas)| deltas.iter().any(|delta| *delta != 0))
+        .collect();
+    if !plan.is_static() && !variations.is_empty() {
+        builder.add_raw(CVAR, write_cvar(&variations));
+    }
+    Ok(())
+}
+
+/// Writes a [cvar] table, where each tuple has a delta for every value.
+///
+/// [cvar]: https://learn.microsoft.com/en-us/typography/opentype/spec/cvar
+fn write_cvar(variations: &[(Region, Vec<i16>)]) -> Vec<u8> {
+    /// The size of the table header.
+    const HEADER_LEN: usize = 8;
+    /// A point count of zero: the tuple has deltas for all values.
+    const ALL_POINTS: u8 = 0;
+
+    let mut headers = Vec::new();
+    let mut data = Vec::new();
+    for (region, deltas) in variations {
+        let packed = crate::dump_table(&PackedDeltas::new(deltas.clone()))
+            .expect("packed deltas are always valid");
+        let size = 1 + packed.len();
+        data.push(ALL_POINTS);
+        data.extend(packed);
+
+        let (peak, intermediate) = region_tuples(region);
+        let mut tuple_index = TupleIndex::EMBEDDED_PEAK_TUPLE | TupleIndex::PRIVATE_POINT_NUMBERS;
+        if intermediate.is_some() {
+            tuple_index |= TupleIndex::INTERMEDIATE_REGION;
+        }
+        headers.extend_from_slice(&(size as u16).to_be_bytes());
+        headers.extend_from_slice(&tuple_index.to_be_bytes());
+        let tuples =
+            std::iter::once(peak).chain(intermediate.into_iter().flat_map(|(s, e)| [s, e]));
+        for tuple in tuples {
+            for value in tuple.values {
+                headers.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+    let mut cvar = Vec::with_capacity(HEADER_LEN + headers.len() + data.len());
+    cvar.extend_from_slice(&[0, 1, 0, 0]);
+    cvar.extend_from_slice(&(variations.len() as u16).to_be_bytes());
+    cvar.extend_from_slice(&((HEADER_LEN + headers.len()) as u16).to_be_bytes());
+    cvar.extend(headers);
+    cvar.extend(data);
+    cvar
+}
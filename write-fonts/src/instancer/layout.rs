@@ -0,0 +1,563 @@
+//! Instancing the OpenType layout tables.
+//!
+//! The variation indices in `GDEF` and `GPOS` refer to the item variation
+//! store in `GDEF`, so both tables are instanced together: the default
+//! deltas are added to each value, and the indices are remapped to the new
+//! store. Feature variations in `GSUB` and `GPOS` have their conditions
+//! updated for the new axis limits; substitutions that apply everywhere are
+//! applied to the feature list.
+
+use read_fonts::{tables::variations::DeltaSetIndex, ReadError, TableProvider, TopLevelTable};
+use types::F2Dot14;
+
+use super::{
+    optional, read_error, solver::NormalizedLimit, variations::StoreInstancer, InstancerError, Plan,
+};
+use crate::{
+    from_obj::ToOwnedTable,
+    offsets::{NullableOffsetMarker, OffsetMarker},
+    round::OtRound,
+    tables::{
+        gdef::{CaretValue, Gdef},
+        gpos::{
+            AnchorTable, CursivePosFormat1, ExtensionSubtable, Gpos, MarkBasePosFormat1,
+            MarkLigPosFormat1, MarkMarkPosFormat1, MarkRecord, PairPos, PositionLookup, SinglePos,
+            ValueRecord,
+        },
+        gsub::Gsub,
+        layout::{
+            ConditionFormat1, DeviceOrVariationIndex, FeatureList, FeatureVariations, Lookup,
+            PendingVariationIndex, VariationIndex,
+        },
+        variations::ivs_builder::VariationIndexRemapping,
+    },
+    FontBuilder,
+};
+
+/// A function applied to each value with a device or variation index table.
+type DeviceVisitor<'a> = dyn FnMut(&mut i16, &mut NullableOffsetMarker<DeviceOrVariationIndex>) -> Result<(), ReadError>
+    + 'a;
+
+/// Instances `GDEF`, `GPOS` and `GSUB`.
+pub(super) fn instantiate(plan: &Plan, builder: &mut FontBuilder) -> Result<(), InstancerError> {
+    let font = &plan.font;
+    let mut gdef: Option<Gdef> = optional(Gdef::TAG, font.gdef())?.map(|t| t.to_owned_table());
+    let mut gpos: Option<Gpos> = optional(Gpos::TAG, font.gpos())?.map(|t| t.to_owned_table());
+    let gsub: Option<Gsub> = optional(Gsub::TAG, font.gsub())?.map(|t| t.to_owned_table());
+
+    let store = font
+        .gdef()
+        .ok()
+        .and_then(|gdef| gdef.item_var_store())
+        .transpose()
+        .map_err(read_error(Gdef::TAG))?;
+    if let Some(store) = store {
+        let mut store = StoreInstancer::new(plan, store).map_err(read_error(Gdef::TAG))?;
+        let mut instance = |value: &mut i16, device: &mut NullableOffsetMarker<_>| {
+            instance_device(&mut store, value, device)
+        };
+        if let Some(gdef) = gdef.as_mut() {
+            visit_carets(gdef, &mut instance).map_err(read_error(Gdef::TAG))?;
+        }
+        if let Some(gpos) = gpos.as_mut() {
+            gpos.visit_devices(&mut instance)
+                .map_err(read_error(Gpos::TAG))?;
+        }
+        let new_store = store.build();
+        if let Some(gdef) = gdef.as_mut() {
+            gdef.item_var_store = new_store.as_ref().map(|(store, _)| store.clone()).into();
+        }
+        let remapping = new_store.map(|(_, remapping)| remapping);
+        let mut remap = |_: &mut i16, device: &mut NullableOffsetMarker<_>| {
+            remap_device(remapping.as_ref(), device);
+            Ok(())
+        };
+        if let Some(gdef) = gdef.as_mut() {
+            visit_carets(gdef, &mut remap).map_err(read_error(Gdef::TAG))?;
+            if plan.is_static() {
+                remove_caret_devices(gdef);
+            }
+        }
+        if let Some(gpos) = gpos.as_mut() {
+            gpos.visit_devices(&mut remap)
+                .map_err(read_error(Gpos::TAG))?;
+            for lookup in gpos.lookup_list.lookups.iter_mut() {
+                lookup.unify_value_formats();
+            }
+        }
+    }
+
+    if let Some(mut gpos) = gpos {
+        if let Some(variations) = gpos.feature_variations.as_mut() {
+            let keep = instantiate_feature_variations(plan, variations, &mut gpos.feature_list);
+            if !keep {
+                gpos.feature_variations = NullableOffsetMarker::new(None);
+            }
+        }
+        builder.add_table(&gpos)?;
+    }
+    if let Some(mut gsub) = gsub {
+        if let Some(variations) = gsub.feature_variations.as_mut() {
+            let keep = instantiate_feature_variations(plan, variations, &mut gsub.feature_list);
+            if !keep {
+                gsub.feature_variations = NullableOffsetMarker::new(None);
+            }
+        }
+        builder.add_table(&gsub)?;
+    }
+    if let Some(gdef) = gdef {
+        builder.add_table(&gdef)?;
+    }
+    Ok(())
+}
+
+/// Adds the default delta of a variation index table to `value`, and
+/// replaces the table with a pending index into the new store.
+fn instance_device(
+    store: &mut StoreInstancer,
+    value: &mut i16,
+    device: &mut NullableOffsetMarker<DeviceOrVariationIndex>,
+) -> Result<(), ReadError> {
+    let Some(DeviceOrVariationIndex::VariationIndex(index)) = device.as_ref() else {
+        return Ok(());
+    };
+    let (default, id) = store.instance(DeltaSetIndex {
+        outer: index.delta_set_outer_index,
+        inner: index.delta_set_inner_index,
+    })?;
+    *value = (*value as f64 + default).ot_round();
+    *device = id
+        .map(|id| DeviceOrVariationIndex::PendingVariationIndex(PendingVariationIndex::new(id)))
+        .into();
+    Ok(())
+}
+
+/// Replaces a pending index with its final index in the new store.
+fn remap_device(
+    remapping: Option<&VariationIndexRemapping>,
+    device: &mut NullableOffsetMarker<DeviceOrVariationIndex>,
+) {
+    let Some(DeviceOrVariationIndex::PendingVariationIndex(pending)) = device.as_ref() else {
+        return;
+    };
+    *device = remapping
+        .and_then(|remapping| remapping.get(pending.delta_set_id))
+        .map(|index| {
+            DeviceOrVariationIndex::VariationIndex(VariationIndex::new(
+                index.delta_set_outer_index,
+                index.delta_set_inner_index,
+            ))
+        })
+        .into();
+}
+
+/// Visits the ligature carets with a device or variation index table.
+fn visit_carets(gdef: &mut Gdef, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+    let Some(carets) = gdef.lig_caret_list.as_mut() else {
+        return Ok(());
+    };
+    for glyph in carets.lig_glyphs.iter_mut() {
+        for caret in glyph.caret_values.iter_mut() {
+            if let CaretValue::Format3(caret) = &mut **caret {
+                let mut device = NullableOffsetMarker::new(Some((*caret.device).clone()));
+                f(&mut caret.coordinate, &mut device)?;
+                if let Some(device) = device.as_ref() {
+                    caret.device = OffsetMarker::new(device.clone());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts carets with variation index tables to format 1, once the font
+/// is static.
+fn remove_caret_devices(gdef: &mut Gdef) {
+    let Some(carets) = gdef.lig_caret_list.as_mut() else {
+        return;
+    };
+    for glyph in carets.lig_glyphs.iter_mut() {
+        for caret in glyph.caret_values.iter_mut() {
+            if let CaretValue::Format3(format3) = &**caret {
+                if !matches!(*format3.device, DeviceOrVariationIndex::Device(_)) {
+                    *caret = OffsetMarker::new(CaretValue::format_1(format3.coordinate));
+                }
+            }
+        }
+    }
+}
+
+/// Updates the conditions of each feature variation record for the new axis
+/// limits.
+///
+/// Records that can no longer apply are removed. The substitutions of the
+/// first record that applies everywhere are applied to the feature list, and
+/// later records are removed, since they would never be selected. Returns
+/// `false` if no records remain.
+fn instantiate_feature_variations(
+    plan: &Plan,
+    variations: &mut FeatureVariations,
+    feature_list: &mut FeatureList,
+) -> bool {
+    let new_axis_indices: Vec<_> = plan
+        .limits
+        .iter()
+        .scan(0u16, |next, limit| {
+            Some(match limit {
+                Some(NormalizedLimit::Pin(_)) => None,
+                _ => {
+                    *next += 1;
+                    Some(*next - 1)
+                }
+            })
+        })
+        .collect();
+    let mut records = Vec::new();
+    for mut record in std::mem::take(&mut variations.feature_variation_records) {
+        let mut applies = true;
+        if let Some(conditions) = record.condition_set.as_mut() {
+            let mut retained = Vec::new();
+            for condition in conditions.conditions.drain(..) {
+                match instantiate_condition(plan, &new_axis_indices, &condition) {
+                    ConditionState::Always => (),
+                    ConditionState::Never => applies = false,
+                    ConditionState::Updated(condition) => retained.push(condition.into()),
+                }
+            }
+            conditions.conditions = retained;
+        }
+        if !applies {
+            continue;
+        }
+        let always = record
+            .condition_set
+            .as_ref()
+            .is_none_or(|conditions| conditions.conditions.is_empty());
+        if always {
+            if let Some(substitution) = record.feature_table_substitution.as_ref() {
+                for substitution in &substitution.substitutions {
+                    if let Some(feature) = feature_list
+                        .feature_records
+                        .get_mut(substitution.feature_index as usize)
+                    {
+                        feature.feature =
+                            OffsetMarker::new((*substitution.alternate_feature).clone());
+                    }
+                }
+            }
+            break;
+        }
+        records.push(record);
+    }
+    variations.feature_variation_records = records;
+    !variations.feature_variation_records.is_empty()
+}
+
+enum ConditionState {
+    /// The condition is met everywhere in the new design space.
+    Always,
+    /// The condition is not met anywhere in the new design space.
+    Never,
+    /// The condition, in the coordinates of the new design space.
+    Updated(ConditionFormat1),
+}
+
+fn instantiate_condition(
+    plan: &Plan,
+    new_axis_indices: &[Option<u16>],
+    condition: &ConditionFormat1,
+) -> ConditionState {
+    let idx = condition.axis_index as usize;
+    let min = condition.filter_range_min_value.to_f32() as f64;
+    let max = condition.filter_range_max_value.to_f32() as f64;
+    let Some(new_idx) = new_axis_indices.get(idx).copied() else {
+        // conditions on axes that are not in the font are never met
+        return ConditionState::Never;
+    };
+    let (lo, hi) = match plan.limits[idx] {
+        Some(NormalizedLimit::Pin(value)) => {
+            return if (min..=max).contains(&value) {
+                ConditionState::Always
+            } else {
+                ConditionState::Never
+            };
+        }
+        Some(NormalizedLimit::Range { min, max }) => (min, max),
+        None => (-1.0, 1.0),
+    };
+    let (min, max) = (min.max(lo), max.min(hi));
+    if min > max {
+        return ConditionState::Never;
+    }
+    let renormalize = |value: f64| {
+        let value = if value < 0.0 { -value / lo } else { value / hi };
+        F2Dot14::from_f32(if value.is_finite() { value as f32 } else { 0.0 })
+    };
+    ConditionState::Updated(ConditionFormat1::new(
+        new_idx.expect("pinned axes are handled above"),
+        renormalize(min),
+        renormalize(max),
+    ))
+}
+
+/// Visits every value in a table with a device or variation index table.
+trait VisitDevices {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError>;
+}
+
+impl VisitDevices for Gpos {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        for lookup in self.lookup_list.lookups.iter_mut() {
+            lookup.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for PositionLookup {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        match self {
+            PositionLookup::Single(lookup) => lookup.visit_devices(f),
+            PositionLookup::Pair(lookup) => lookup.visit_devices(f),
+            PositionLookup::Cursive(lookup) => lookup.visit_devices(f),
+            PositionLookup::MarkToBase(lookup) => lookup.visit_devices(f),
+            PositionLookup::MarkToLig(lookup) => lookup.visit_devices(f),
+            PositionLookup::MarkToMark(lookup) => lookup.visit_devices(f),
+            PositionLookup::Contextual(_) | PositionLookup::ChainContextual(_) => Ok(()),
+            PositionLookup::Extension(lookup) => lookup.visit_devices(f),
+        }
+    }
+}
+
+impl<T: VisitDevices> VisitDevices for Lookup<T> {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        for subtable in self.subtables.iter_mut() {
+            subtable.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for ExtensionSubtable {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        match self {
+            Self::Single(table) => table.extension.visit_devices(f),
+            Self::Pair(table) => table.extension.visit_devices(f),
+            Self::Cursive(table) => table.extension.visit_devices(f),
+            Self::MarkToBase(table) => table.extension.visit_devices(f),
+            Self::MarkToLig(table) => table.extension.visit_devices(f),
+            Self::MarkToMark(table) => table.extension.visit_devices(f),
+            Self::Contextual(_) | Self::ChainContextual(_) => Ok(()),
+        }
+    }
+}
+
+impl VisitDevices for ValueRecord {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        for (value, device) in [
+            (&mut self.x_placement, &mut self.x_placement_device),
+            (&mut self.y_placement, &mut self.y_placement_device),
+            (&mut self.x_advance, &mut self.x_advance_device),
+            (&mut self.y_advance, &mut self.y_advance_device),
+        ] {
+            if device.is_some() {
+                let mut new_value = value.unwrap_or_default();
+                f(&mut new_value, device)?;
+                *value = Some(new_value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for AnchorTable {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        let AnchorTable::Format3(anchor) = self else {
+            return Ok(());
+        };
+        f(&mut anchor.x_coordinate, &mut anchor.x_device)?;
+        f(&mut anchor.y_coordinate, &mut anchor.y_device)?;
+        if anchor.x_device.is_none() && anchor.y_device.is_none() {
+            *self = AnchorTable::format_1(anchor.x_coordinate, anchor.y_coordinate);
+        }
+        Ok(())
+    }
+}
+
+impl<T: VisitDevices> VisitDevices for OffsetMarker<T> {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        (**self).visit_devices(f)
+    }
+}
+
+impl<T: VisitDevices> VisitDevices for NullableOffsetMarker<T> {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        match self.as_mut() {
+            Some(table) => table.visit_devices(f),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: VisitDevices> VisitDevices for [T] {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        for item in self.iter_mut() {
+            item.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for MarkRecord {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        self.mark_anchor.visit_devices(f)
+    }
+}
+
+impl VisitDevices for SinglePos {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        match self {
+            SinglePos::Format1(table) => table.value_record.visit_devices(f),
+            SinglePos::Format2(table) => table.value_records.visit_devices(f),
+        }
+    }
+}
+
+impl VisitDevices for PairPos {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        match self {
+            PairPos::Format1(table) => {
+                for record in table
+                    .pair_sets
+                    .iter_mut()
+                    .flat_map(|set| set.pair_value_records.iter_mut())
+                {
+                    record.value_record1.visit_devices(f)?;
+                    record.value_record2.visit_devices(f)?;
+                }
+            }
+            PairPos::Format2(table) => {
+                for record in table
+                    .class1_records
+                    .iter_mut()
+                    .flat_map(|record| record.class2_records.iter_mut())
+                {
+                    record.value_record1.visit_devices(f)?;
+                    record.value_record2.visit_devices(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for CursivePosFormat1 {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        for record in self.entry_exit_record.iter_mut() {
+            record.entry_anchor.visit_devices(f)?;
+            record.exit_anchor.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for MarkBasePosFormat1 {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        self.mark_array.mark_records.visit_devices(f)?;
+        for record in self.base_array.base_records.iter_mut() {
+            record.base_anchors.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for MarkLigPosFormat1 {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        self.mark_array.mark_records.visit_devices(f)?;
+        for component in self
+            .ligature_array
+            .ligature_attaches
+            .iter_mut()
+            .flat_map(|ligature| ligature.component_records.iter_mut())
+        {
+            component.ligature_anchors.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl VisitDevices for MarkMarkPosFormat1 {
+    fn visit_devices(&mut self, f: &mut DeviceVisitor) -> Result<(), ReadError> {
+        self.mark1_array.mark_records.visit_devices(f)?;
+        for record in self.mark2_array.mark2_records.iter_mut() {
+            record.mark2_anchors.visit_devices(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl PositionLookup {
+    /// Gives every value record in each subtable the same format.
+    ///
+    /// Instancing can add or remove fields from individual records, but
+    /// the records of a subtable share a single format.
+    fn unify_value_formats(&mut self) {
+        match self {
+            PositionLookup::Single(lookup) => {
+                for subtable in lookup.subtables.iter_mut() {
+                    unify_single_pos(subtable);
+                }
+            }
+            PositionLookup::Pair(lookup) => {
+                for subtable in lookup.subtables.iter_mut() {
+                    unify_pair_pos(subtable);
+                }
+            }
+            PositionLookup::Extension(lookup) => {
+                for subtable in lookup.subtables.iter_mut() {
+                    match &mut **subtable {
+                        ExtensionSubtable::Single(table) => unify_single_pos(&mut table.extension),
+                        ExtensionSubtable::Pair(table) => unify_pair_pos(&mut table.extension),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn unify_single_pos(table: &mut SinglePos) {
+    if let SinglePos::Format2(table) = table {
+        unify_formats(table.value_records.iter_mut());
+    }
+}
+
+fn unify_pair_pos(table: &mut PairPos) {
+    let mut records: Vec<_> = match table {
+        PairPos::Format1(table) => table
+            .pair_sets
+            .iter_mut()
+            .flat_map(|set| set.pair_value_records.iter_mut())
+            .map(|record| (&mut record.value_record1, &mut record.value_record2))
+            .collect(),
+        PairPos::Format2(table) => table
+            .class1_records
+            .iter_mut()
+            .flat_map(|record| record.class2_records.iter_mut())
+            .map(|record| (&mut record.value_record1, &mut record.value_record2))
+            .collect(),
+    };
+    unify_formats(records.iter_mut().map(|(first, _)| &mut **first));
+    unify_formats(records.iter_mut().map(|(_, second)| &mut **second));
+}
+
+fn unify_formats<'a>(records: impl Iterator<Item = &'a mut ValueRecord>) {
+    let records: Vec<_> = records.collect();
+    let format = records.iter().fold(Default::default(), |format, record| {
+        format | record.format()
+    });
+    for record in records {
+        record.set_explicit_value_format(format);
+    }
+}
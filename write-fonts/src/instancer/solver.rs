@@ -0,0 +1,291 @@
+//! Rebasing variation regions onto new axis limits.
+//!
+//! Every variation in a font applies over a region of the design space,
+//! which is the product of a tent function on each axis. When an axis is
+//! pinned, its tent reduces to a scale factor. When an axis is restricted,
+//! the part of the tent that lies within the new range is re-expressed as
+//! a sum of tents in the normalized coordinates of that range; a tent that
+//! is non-zero at the new default contributes to the default values.
+
+use types::F2Dot14;
+
+/// The new limit of an axis, in normalized coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum NormalizedLimit {
+    /// The axis is removed, and the font is instanced at this location.
+    Pin(f64),
+    /// The axis is kept, and restricted to this range.
+    ///
+    /// The range always includes the default location, so that `min <= 0`
+    /// and `max >= 0`.
+    Range { min: f64, max: f64 },
+}
+
+/// The support of a variation along one axis, in normalized coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(super) struct Tent {
+    pub start: F2Dot14,
+    pub peak: F2Dot14,
+    pub end: F2Dot14,
+}
+
+/// The support of a variation: one tent for each axis.
+pub(super) type Region = Vec<Tent>;
+
+impl Tent {
+    /// A tent that does not depend on the axis.
+    pub(super) const NONE: Tent = Tent {
+        start: F2Dot14::ZERO,
+        peak: F2Dot14::ZERO,
+        end: F2Dot14::ZERO,
+    };
+
+    pub(super) fn new(start: F2Dot14, peak: F2Dot14, end: F2Dot14) -> Self {
+        Self { start, peak, end }
+    }
+
+    /// The tent of a peak without an explicit intermediate region, which
+    /// spans from the default location to the peak.
+    pub(super) fn from_peak(peak: F2Dot14) -> Self {
+        Self::new(peak.min(F2Dot14::ZERO), peak, peak.max(F2Dot14::ZERO))
+    }
+
+    fn from_f64(start: f64, peak: f64, end: f64) -> Self {
+        let to_f2dot14 = |value: f64| F2Dot14::from_f32(value as f32);
+        Self::new(to_f2dot14(start), to_f2dot14(peak), to_f2dot14(end))
+    }
+
+    fn to_f64(self) -> (f64, f64, f64) {
+        (
+            self.start.to_f32() as f64,
+            self.peak.to_f32() as f64,
+            self.end.to_f32() as f64,
+        )
+    }
+
+    /// `true` if this tent is one everywhere.
+    ///
+    /// This matches the rules for regions in the item variation store: a
+    /// tent with a zero peak, or that is malformed, does not depend on the
+    /// axis.
+    pub(super) fn is_none(&self) -> bool {
+        let (start, peak, end) = self.to_f64();
+        peak == 0.0 || start > peak || peak > end || (start < 0.0 && end > 0.0)
+    }
+
+    /// The value of the tent at `coord`.
+    pub(super) fn scalar(&self, coord: f64) -> f64 {
+        if self.is_none() {
+            return 1.0;
+        }
+        let (start, peak, end) = self.to_f64();
+        if coord == peak {
+            1.0
+        } else if coord <= start || coord >= end {
+            0.0
+        } else if coord < peak {
+            (coord - start) / (peak - start)
+        } else {
+            (end - coord) / (end - peak)
+        }
+    }
+
+    fn mirror(self) -> Self {
+        let neg = |value: F2Dot14| F2Dot14::from_bits(value.to_bits().saturating_neg());
+        Self::new(neg(self.end), neg(self.peak), neg(self.start))
+    }
+}
+
+/// `true` if a region applies everywhere, so that its deltas belong to the
+/// default values.
+pub(super) fn is_default(region: &[Tent]) -> bool {
+    region.iter().all(Tent::is_none)
+}
+
+/// Rebases a tent onto a new axis limit.
+///
+/// Returns the tents, with their scale factors, whose sum equals the input
+/// tent within the limit. For a pinned axis, the result is the value of the
+/// tent at the pinned location.
+pub(super) fn rebase_tent(tent: Tent, limit: NormalizedLimit) -> Vec<(f64, Tent)> {
+    let (min, max) = match limit {
+        NormalizedLimit::Pin(value) => {
+            let scalar = tent.scalar(value);
+            return if scalar == 0.0 {
+                Vec::new()
+            } else {
+                vec![(scalar, Tent::NONE)]
+            };
+        }
+        NormalizedLimit::Range { min, max } => (min, max),
+    };
+    if tent.is_none() {
+        return vec![(1.0, Tent::NONE)];
+    }
+    // work on the positive side of the axis
+    let negative = tent.peak < F2Dot14::ZERO;
+    let (tent, bound) = if negative {
+        (tent.mirror(), -min)
+    } else {
+        (tent, max)
+    };
+    if bound <= 0.0 {
+        return Vec::new();
+    }
+
+    let (start, peak, end) = tent.to_f64();
+    let mut result = Vec::new();
+    if start >= bound {
+        // the tent is outside the new range
+    } else if peak > bound {
+        // the tent rises towards the new limit
+        result.push((tent.scalar(bound), Tent::from_f64(start / bound, 1.0, 1.0)));
+    } else if end > bound && peak < bound {
+        // the tent falls towards its value at the new limit
+        result.push((1.0, Tent::from_f64(start / bound, peak / bound, 1.0)));
+        let scalar = tent.scalar(bound);
+        if scalar != 0.0 {
+            result.push((scalar, Tent::from_f64(peak / bound, 1.0, 1.0)));
+        }
+    } else {
+        result.push((
+            1.0,
+            Tent::from_f64(start / bound, peak / bound, (end / bound).min(1.0)),
+        ));
+    }
+    if negative {
+        for (_, tent) in result.iter_mut() {
+            *tent = tent.mirror();
+        }
+    }
+    result
+}
+
+/// Rebases a region onto the new limit of each axis.
+///
+/// Returns the new regions, with their scale factors. The new regions only
+/// have tents for the axes that are not pinned; regions for which
+/// [`is_default`] is true apply to the default values.
+pub(super) fn rebase_region(
+    region: &[Tent],
+    limits: &[Option<NormalizedLimit>],
+) -> Vec<(f64, Region)> {
+    let mut result = vec![(1.0, Region::new())];
+    for (i, limit) in limits.iter().enumerate() {
+        let tent = region.get(i).copied().unwrap_or(Tent::NONE);
+        let rebased = match limit {
+            Some(limit) => rebase_tent(tent, *limit),
+            None => vec![(1.0, tent)],
+        };
+        let pinned = matches!(limit, Some(NormalizedLimit::Pin(_)));
+        result = result
+            .into_iter()
+            .flat_map(|(scale, region)| {
+                rebased.iter().map(move |(tent_scale, tent)| {
+                    let mut region = region.clone();
+                    if !pinned {
+                        region.push(*tent);
+                    }
+                    (scale * tent_scale, region)
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tent(start: f32, peak: f32, end: f32) -> Tent {
+        Tent::new(
+            F2Dot14::from_f32(start),
+            F2Dot14::from_f32(peak),
+            F2Dot14::from_f32(end),
+        )
+    }
+
+    fn range(min: f64, max: f64) -> NormalizedLimit {
+        NormalizedLimit::Range { min, max }
+    }
+
+    #[test]
+    fn pin() {
+        let rebased = rebase_tent(tent(0.0, 1.0, 1.0), NormalizedLimit::Pin(0.5));
+        assert_eq!(rebased, [(0.5, Tent::NONE)]);
+        let rebased = rebase_tent(tent(0.0, 1.0, 1.0), NormalizedLimit::Pin(-0.5));
+        assert!(rebased.is_empty());
+        let rebased = rebase_tent(Tent::NONE, NormalizedLimit::Pin(-0.5));
+        assert_eq!(rebased, [(1.0, Tent::NONE)]);
+    }
+
+    #[test]
+    fn tent_within_range() {
+        let rebased = rebase_tent(tent(0.0, 0.25, 0.5), range(-1.0, 0.5));
+        assert_eq!(rebased, [(1.0, tent(0.0, 0.5, 1.0))]);
+        let rebased = rebase_tent(tent(-0.5, -0.25, 0.0), range(-0.5, 1.0));
+        assert_eq!(rebased, [(1.0, tent(-1.0, -0.5, 0.0))]);
+    }
+
+    #[test]
+    fn tent_beyond_range() {
+        // the peak is cut off, so the tent ends at its value at the limit
+        let rebased = rebase_tent(tent(0.0, 1.0, 1.0), range(0.0, 0.5));
+        assert_eq!(rebased, [(0.5, tent(0.0, 1.0, 1.0))]);
+        // the tent is outside the range
+        let rebased = rebase_tent(tent(-1.0, -1.0, 0.0), range(0.0, 0.5));
+        assert!(rebased.is_empty());
+        // an intermediate tent is split at the limit
+        let rebased = rebase_tent(tent(0.25, 0.5, 1.0), range(0.0, 0.75));
+        assert_eq!(
+            rebased,
+            [
+                (1.0, tent(1.0 / 3.0, 2.0 / 3.0, 1.0)),
+                (0.5, tent(2.0 / 3.0, 1.0, 1.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn rebased_tents_sum_to_original() {
+        let tents = [
+            tent(0.0, 1.0, 1.0),
+            tent(0.2, 0.6, 0.8),
+            tent(-1.0, -0.4, 0.0),
+            tent(0.5, 0.5, 1.0),
+        ];
+        let (min, max) = (-0.6, 0.7);
+        for original in tents {
+            let rebased = rebase_tent(original, range(min, max));
+            for i in 0..=20 {
+                let coord = min + (max - min) * i as f64 / 20.0;
+                let new_coord = if coord < 0.0 {
+                    coord / -min
+                } else {
+                    coord / max
+                };
+                let sum: f64 = rebased
+                    .iter()
+                    .map(|(scale, tent)| scale * tent.scalar(new_coord))
+                    .sum();
+                let expected = original.scalar(coord);
+                assert!((sum - expected).abs() < 1e-3, "{original:?} at {coord}");
+            }
+        }
+    }
+
+    #[test]
+    fn regions() {
+        let region = [tent(0.0, 1.0, 1.0), tent(-1.0, -1.0, 0.0)];
+        let limits = [Some(NormalizedLimit::Pin(0.5)), Some(range(-0.5, 0.0))];
+        let rebased = rebase_region(&region, &limits);
+        assert_eq!(rebased, [(0.25, vec![tent(-1.0, -1.0, 0.0)])]);
+
+        // a region that no longer varies applies to the default
+        let limits = [Some(NormalizedLimit::Pin(1.0)), None];
+        let rebased = rebase_region(&[tent(0.0, 1.0, 1.0), Tent::NONE], &limits);
+        assert_eq!(rebased, [(1.0, vec![Tent::NONE])]);
+        assert!(is_default(&rebased[0].1));
+    }
+}
@@ -0,0 +1,423 @@
+//! Instancing item variation stores, and the metrics variations tables.
+
+use std::collections::HashMap;
+
+use read_fonts::{
+    tables::variations::{
+        DeltaSetIndex, DeltaSetIndexMap as ReadDeltaSetIndexMap, ItemVariationStore,
+    },
+    ReadError, TableProvider,
+};
+use types::{MajorMinor, Tag};
+
+use super::{
+    optional, read_error,
+    solver::{is_default, Region, Tent},
+    InstancerError, Plan, Tables,
+};
+use crate::{
+    dump_table,
+    round::OtRound,
+    tables::{
+        hmtx::LongMetric,
+        variations::{
+            ivs_builder::{VariationIndexRemapping, VariationStoreBuilder},
+            DeltaSetIndexMap, ItemVariationStore as WriteItemVariationStore, RegionAxisCoordinates,
+            VariationRegion,
+        },
+    },
+    FontBuilder,
+};
+
+const HVAR: Tag = Tag::new(b"HVAR");
+const VVAR: Tag = Tag::new(b"VVAR");
+const MVAR: Tag = Tag::new(b"MVAR");
+
+/// The size of the MVAR header, up to the value records.
+const MVAR_HEADER_LEN: usize = 12;
+/// The size of an MVAR value record.
+const MVAR_RECORD_LEN: usize = 8;
+/// The delta-set index of entries without variations.
+pub(super) const NO_VARIATION_INDEX: u32 = 0xFFFF_FFFF;
+
+/// Instances the deltas of an item variation store.
+///
+/// Each delta set of the source store is split into a delta at the new
+/// default location, and (if the font remains variable) a delta set in a new
+/// store, built with a [`VariationStoreBuilder`].
+pub(super) struct StoreInstancer<'a> {
+    store: ItemVariationStore<'a>,
+    /// The rebased regions of each region in the source store.
+    regions: Vec<Vec<(f64, Region)>>,
+    builder: Option<VariationStoreBuilder>,
+    cache: HashMap<(u16, u16), (f64, Option<u32>)>,
+}
+
+impl<'a> StoreInstancer<'a> {
+    pub(super) fn new(plan: &Plan, store: ItemVariationStore<'a>) -> Result<Self, ReadError> {
+        let regions = store
+            .variation_region_list()?
+            .variation_regions()
+            .iter()
+            .map(|region| {
+                let region: Region = region?
+                    .region_axes()
+                    .iter()
+                    .map(|axis| Tent::new(axis.start_coord(), axis.peak_coord(), axis.end_coord()))
+                    .collect();
+                Ok(plan.rebase(&region))
+            })
+            .collect::<Result<_, ReadError>>()?;
+        Ok(Self {
+            store,
+            regions,
+            builder: (!plan.is_static()).then(VariationStoreBuilder::new),
+            cache: HashMap::new(),
+        })
+    }
+
+    /// Instances the delta set at the given index.
+    ///
+    /// Returns the delta at the new default location, and the temporary
+    /// identifier of the remaining delta set, if the font is still variable.
+    pub(super) fn instance(
+        &mut self,
+        index: DeltaSetIndex,
+    ) -> Result<(f64, Option<u32>), ReadError> {
+        if let Some(result) = self.cache.get(&(index.outer, index.inner)) {
+            return Ok(*result);
+        }
+        if (index.outer as u32) << 16 | index.inner as u32 == NO_VARIATION_INDEX {
+            return Ok((0.0, None));
+        }
+        let mut default = 0.0;
+        let mut deltas: Vec<(Region, f64)> = Vec::new();
+        if let Some(data) = self.store.item_variation_data().get(index.outer as usize) {
+            let data = data?;
+            let region_indices = data.region_indexes();
+            for (i, delta) in data.delta_set(index.inner).enumerate() {
+                let region_idx = region_indices
+                    .get(i)
+                    .ok_or(ReadError::MalformedData(
+                        "invalid delta sets in ItemVariationStore",
+                    ))?
+                    .get() as usize;
+                let rebased = self
+                    .regions
+                    .get(region_idx)
+                    .ok_or(ReadError::MalformedData("invalid region index"))?;
+                for (scale, region) in rebased {
+                    let delta = delta as f64 * scale;
+                    if is_default(region) {
+                        default += delta;
+                    } else if let Some((_, sum)) = deltas.iter_mut().find(|(r, _)| r == region) {
+                        *sum += delta;
+                    } else {
+                        deltas.push((region.clone(), delta));
+                    }
+                }
+            }
+        }
+        let id = self.builder.as_mut().map(|builder| {
+            builder.add_deltas(
+                deltas
+                    .into_iter()
+                    .map(|(region, delta)| (variation_region(&region), delta.ot_round()))
+                    .collect::<Vec<(_, i32)>>(),
+            )
+        });
+        self.cache.insert((index.outer, index.inner), (default, id));
+        Ok((default, id))
+    }
+
+    /// Builds the new store, or returns `None` if the font is static.
+    pub(super) fn build(self) -> Option<(WriteItemVariationStore, VariationIndexRemapping)> {
+        self.builder.map(VariationStoreBuilder::build)
+    }
+}
+
+pub(super) fn variation_region(region: &[Tent]) -> VariationRegion {
+    VariationRegion::new(
+        region
+            .iter()
+            .map(|tent| RegionAxisCoordinates::new(tent.start, tent.peak, tent.end))
+            .collect(),
+    )
+}
+
+/// Instances `HVAR`, `VVAR` and `MVAR`.
+///
+/// Glyph metrics are instanced along with the outlines for `glyf` fonts, so
+/// the default deltas of `HVAR` and `VVAR` are only applied to fonts with
+/// other outlines.
+pub(super) fn instantiate(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    let font = &plan.font;
+    let apply_metrics = font.data_for_tag(Tag::new(b"glyf")).is_none();
+    if let Some(hvar) = optional(HVAR, font.hvar())? {
+        let store = hvar.item_variation_store().map_err(read_error(HVAR))?;
+        let table = instantiate_metrics_variations(
+            plan,
+            hvar.version(),
+            store,
+            [
+                hvar.advance_width_mapping().transpose(),
+                hvar.lsb_mapping().transpose(),
+                hvar.rsb_mapping().transpose(),
+            ],
+            apply_metrics.then_some(()).and(tables.hmtx.as_mut()),
+        )
+        .map_err(read_error(HVAR))?;
+        if let Some(table) = table {
+            builder.add_raw(HVAR, table);
+        }
+        if let (true, Some(hmtx), Some(hhea)) = (apply_metrics, &tables.hmtx, tables.hhea.as_mut())
+        {
+            let max = hmtx.iter().map(|metric| metric.advance).max();
+            hhea.advance_width_max = max.unwrap_or_default().into();
+        }
+    }
+    if let Some(vvar) = optional(VVAR, font.vvar())? {
+        let store = vvar.item_variation_store().map_err(read_error(VVAR))?;
+        let table = instantiate_metrics_variations(
+            plan,
+            vvar.version(),
+            store,
+            [
+                vvar.advance_height_mapping().transpose(),
+                vvar.tsb_mapping().transpose(),
+                vvar.bsb_mapping().transpose(),
+                vvar.v_org_mapping().transpose(),
+            ],
+            apply_metrics.then_some(()).and(tables.vmtx.as_mut()),
+        )
+        .map_err(read_error(VVAR))?;
+        if let Some(table) = table {
+            builder.add_raw(VVAR, table);
+        }
+        if let (true, Some(vmtx), Some(vhea)) = (apply_metrics, &tables.vmtx, tables.vhea.as_mut())
+        {
+            let max = vmtx.iter().map(|metric| metric.advance).max();
+            vhea.advance_height_max = max.unwrap_or_default().into();
+        }
+    }
+    instantiate_mvar(plan, tables, builder)
+}
+
+/// Instances an [HVAR] or [VVAR] table.
+///
+/// If `metrics` is provided, the default advance deltas are applied to it.
+/// Returns the new table, or `None` if the font is static. The advance
+/// mapping of the new table is always explicit.
+///
+/// [HVAR]: https://learn.microsoft.com/en-us/typography/opentype/spec/hvar
+/// [VVAR]: https://learn.microsoft.com/en-us/typography/opentype/spec/vvar
+fn instantiate_metrics_variations<const N: usize>(
+    plan: &Plan,
+    version: MajorMinor,
+    store: ItemVariationStore,
+    mappings: [Result<Option<ReadDeltaSetIndexMap>, ReadError>; N],
+    mut metrics: Option<&mut Vec<LongMetric>>,
+) -> Result<Option<Vec<u8>>, ReadError> {
+    let num_glyphs = plan.font.maxp()?.num_glyphs() as u32;
+    let mut instancer = StoreInstancer::new(plan, store)?;
+    let mut temp_mappings = Vec::new();
+    for (i, mapping) in mappings.into_iter().enumerate() {
+        let mapping = mapping?;
+        // only the advance mapping is implicit
+        if mapping.is_none() && i > 0 {
+            temp_mappings.push(None);
+            continue;
+        }
+        let mut ids = Vec::with_capacity(num_glyphs as usize);
+        for gid in 0..num_glyphs {
+            let index = match &mapping {
+                Some(mapping) => mapping.get(gid)?,
+                None => DeltaSetIndex {
+                    outer: 0,
+                    inner: gid as u16,
+                },
+            };
+            let (default, id) = instancer.instance(index)?;
+            if i == 0 {
+                if let Some(metric) = metrics
+                    .as_deref_mut()
+                    .and_then(|metrics| metrics.get_mut(gid as usize))
+                {
+                    let advance: i32 = (metric.advance as f64 + default).ot_round();
+                    metric.advance = advance.clamp(0, u16::MAX as i32) as u16;
+                }
+            }
+            ids.push(id);
+        }
+        temp_mappings.push(Some(ids));
+    }
+    let Some((store, remapping)) = instancer.build() else {
+        return Ok(None);
+    };
+    let invalid = |_| ReadError::MalformedData("invalid instanced variation data");
+    let mut tables = vec![Some(dump_table(&store).map_err(invalid)?)];
+    for mapping in temp_mappings {
+        let Some(ids) = mapping else {
+            tables.push(None);
+            continue;
+        };
+        let mapping: DeltaSetIndexMap = ids
+            .into_iter()
+            .map(|id| variation_index(&remapping, id))
+            .collect();
+        tables.push(Some(dump_table(&mapping).map_err(invalid)?));
+    }
+    Ok(Some(write_offsets_table(
+        version.to_be_bytes().to_vec(),
+        tables,
+    )))
+}
+
+/// Returns the final delta-set index of a temporary identifier, as an
+/// `outer << 16 | inner` entry.
+pub(super) fn variation_index(remapping: &VariationIndexRemapping, id: Option<u32>) -> u32 {
+    id.and_then(|id| remapping.get(id))
+        .map(|idx| (idx.delta_set_outer_index as u32) << 16 | idx.delta_set_inner_index as u32)
+        .unwrap_or(NO_VARIATION_INDEX)
+}
+
+/// Appends 32-bit offsets to `header`, followed by the (4-byte aligned)
+/// subtables they point to.
+fn write_offsets_table(mut header: Vec<u8>, tables: Vec<Option<Vec<u8>>>) -> Vec<u8> {
+    let mut body = Vec::new();
+    let header_len = header.len() + tables.len() * 4;
+    for table in tables {
+        match table {
+            Some(table) => {
+                header.extend_from_slice(&((header_len + body.len()) as u32).to_be_bytes());
+                body.extend_from_slice(&table);
+                body.resize(body.len().next_multiple_of(4), 0);
+            }
+            None => header.extend_from_slice(&0u32.to_be_bytes()),
+        }
+    }
+    header.extend_from_slice(&body);
+    header
+}
+
+/// Instances the [MVAR] table, applying the default deltas to the font-wide
+/// metrics.
+///
+/// [MVAR]: https://learn.microsoft.com/en-us/typography/opentype/spec/mvar
+fn instantiate_mvar(
+    plan: &Plan,
+    tables: &mut Tables,
+    builder: &mut FontBuilder,
+) -> Result<(), InstancerError> {
+    let Some(mvar) = optional(MVAR, plan.font.mvar())? else {
+        return Ok(());
+    };
+    let Some(store) = mvar.item_variation_store() else {
+        return Ok(());
+    };
+    let store = store.map_err(read_error(MVAR))?;
+    let mut instancer = StoreInstancer::new(plan, store).map_err(read_error(MVAR))?;
+    let mut records = Vec::new();
+    for record in mvar.value_records() {
+        let index = DeltaSetIndex {
+            outer: record.delta_set_outer_index(),
+            inner: record.delta_set_inner_index(),
+        };
+        let (default, id) = instancer.instance(index).map_err(read_error(MVAR))?;
+        apply_metric_delta(tables, record.value_tag(), default);
+        records.push((record.value_tag(), id));
+    }
+    let Some((store, remapping)) = instancer.build() else {
+        return Ok(());
+    };
+    records.sort_by_key(|(tag, _)| *tag);
+    let store = dump_table(&store).map_err(|_| InstancerError::Read {
+        tag: MVAR,
+        inner: ReadError::MalformedData("invalid instanced variation data"),
+    })?;
+    let store_offset = MVAR_HEADER_LEN + records.len() * MVAR_RECORD_LEN;
+    let mut data = Vec::with_capacity(store_offset + store.len());
+    data.extend_from_slice(&MajorMinor::VERSION_1_0.to_be_bytes());
+    data.extend_from_slice(&0u16.to_be_bytes());
+    data.extend_from_slice(&(MVAR_RECORD_LEN as u16).to_be_bytes());
+    data.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    data.extend_from_slice(&(store_offset as u16).to_be_bytes());
+    for (tag, id) in records {
+        let index = variation_index(&remapping, id);
+        data.extend_from_slice(&tag.to_be_bytes());
+        data.extend_from_slice(&index.to_be_bytes()[..]);
+    }
+    data.extend_from_slice(&store);
+    builder.add_raw(MVAR, data);
+    Ok(())
+}
+
+/// Adds a delta to the font-wide metric identified by an MVAR value tag.
+fn apply_metric_delta(tables: &mut Tables, tag: Tag, delta: f64) {
+    fn add<T: Into<f64> + Copy>(value: &mut T, delta: f64)
+    where
+        f64: OtRound<T>,
+    {
+        *value = ((*value).into() + delta).ot_round();
+    }
+    if let Some(os2) = tables.os2.as_mut() {
+        match &tag.to_be_bytes() {
+            b"hasc" => add(&mut os2.s_typo_ascender, delta),
+            b"hdsc" => add(&mut os2.s_typo_descender, delta),
+            b"hlgp" => add(&mut os2.s_typo_line_gap, delta),
+            b"hcla" => add(&mut os2.us_win_ascent, delta),
+            b"hcld" => add(&mut os2.us_win_descent, delta),
+            b"xhgt" => os2.sx_height.iter_mut().for_each(|v| add(v, delta)),
+            b"cpht" => os2.s_cap_height.iter_mut().for_each(|v| add(v, delta)),
+            b"sbxs" => add(&mut os2.y_subscript_x_size, delta),
+            b"sbys" => add(&mut os2.y_subscript_y_size, delta),
+            b"sbxo" => add(&mut os2.y_subscript_x_offset, delta),
+            b"sbyo" => add(&mut os2.y_subscript_y_offset, delta),
+            b"spxs" => add(&mut os2.y_superscript_x_size, delta),
+            b"spys" => add(&mut os2.y_superscript_y_size, delta),
+            b"spxo" => add(&mut os2.y_superscript_x_offset, delta),
+            b"spyo" => add(&mut os2.y_superscript_y_offset, delta),
+            b"strs" => add(&mut os2.y_strikeout_size, delta),
+            b"stro" => add(&mut os2.y_strikeout_position, delta),
+            _ => (),
+        }
+    }
+    if let Some(hhea) = tables.hhea.as_mut() {
+        match &tag.to_be_bytes() {
+            b"hcrs" => add(&mut hhea.caret_slope_rise, delta),
+            b"hcrn" => add(&mut hhea.caret_slope_run, delta),
+            b"hcof" => add(&mut hhea.caret_offset, delta),
+            _ => (),
+        }
+    }
+    if let Some(vhea) = tables.vhea.as_mut() {
+        let add_fword = |value: &mut types::FWord| {
+            let rounded: i16 = (value.to_i16() as f64 + delta).ot_round();
+            *value = rounded.into();
+        };
+        match &tag.to_be_bytes() {
+            b"vasc" => add_fword(&mut vhea.ascender),
+            b"vdsc" => add_fword(&mut vhea.descender),
+            b"vlgp" => add_fword(&mut vhea.line_gap),
+            b"vcrs" => add(&mut vhea.caret_slope_rise, delta),
+            b"vcrn" => add(&mut vhea.caret_slope_run, delta),
+            b"vcof" => add(&mut vhea.caret_offset, delta),
+            _ => (),
+        }
+    }
+    if let Some(post) = tables.post.as_mut() {
+        let add_fword = |value: &mut types::FWord| {
+            let rounded: i16 = (value.to_i16() as f64 + delta).ot_round();
+            *value = rounded.into();
+        };
+        match &tag.to_be_bytes() {
+            b"undo" => add_fword(&mut post.underline_position),
+            b"unds" => add_fword(&mut post.underline_thickness),
+            _ => (),
+        }
+    }
+}
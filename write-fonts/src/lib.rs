@@ -125,13 +125,19 @@ pub mod error;
 mod font_builder;
 pub mod from_obj;
 mod graph;
+pub mod instancer;
+pub mod merger;
 mod offsets;
 pub mod pens;
+mod raw;
 mod round;
+pub mod subset;
 mod table_type;
 pub mod tables;
 mod util;
 pub mod validate;
+#[cfg(feature = "woff")]
+pub mod woff;
 mod write;
 
 #[cfg(test)]
@@ -139,7 +145,7 @@ mod codegen_test;
 #[cfg(test)]
 mod hex_diff;
 
-pub use font_builder::{BuilderError, FontBuilder};
+pub use font_builder::{BuilderError, CollectionBuilder, FontBuilder};
 pub use offsets::{NullableOffsetMarker, OffsetMarker};
 pub use round::OtRound;
 pub use write::{dump_table, FontWrite, TableWriter};
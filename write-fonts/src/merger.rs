@@ -0,0 +1,808 @@
+//! Merging fonts.
+//!
+//! The merger combines several fonts, such as separately built fonts for
+//! different scripts, into a single font. The glyphs of the first font keep
+//! their identifiers, and the glyphs of each following font (other than
+//! `.notdef`) are appended in order. Glyph names that are already in use are
+//! made unique by adding a numeric suffix, as in `a#1`.
+//!
+//! The following tables are merged: `glyf`/`loca`, `head`, `maxp`, `hhea`,
+//! `hmtx`, `vhea`/`vmtx` (if every font has them), `cmap` (including
+//! variation sequences), `OS/2`, `post`, `name`, `GDEF`, `GSUB` and `GPOS`.
+//! Fonts that disagree about how a codepoint is mapped, or about the name
+//! and vertical metrics of the font, are reconciled according to the
+//! [`MergeOptions`]. Fonts with different units per em cannot be merged.
+//!
+//! Layout lookups and features are concatenated, and scripts and language
+//! systems with the same tag are combined. The `fpgm`, `prep` and `cvt `
+//! tables are retained if all fonts that have them agree; otherwise, the
+//! instructions are removed from every glyph.
+//!
+//! The `hdmx`, `LTSH`, `VDMX`, `sbix` and `DSIG` tables depend on the whole
+//! glyph set, and are dropped. All other tables are copied from the first
+//! font, where they still refer to the same glyphs; fonts after the first
+//! must not have tables such as `COLR`, `kern` or `MATH` that refer to
+//! glyphs but are not merged. Variable fonts and fonts with CFF outlines are
+//! not supported.
+
+mod cmap;
+mod glyf;
+mod layout;
+mod metrics;
+
+use std::collections::{BTreeSet, HashSet};
+
+use read_fonts::{
+    tables::post::Post as ReadPost, FontRef, ReadError, TableProvider, TopLevelTable,
+};
+use types::{GlyphId, Tag, Version16Dot16};
+
+use crate::{
+    from_obj::ToOwnedTable,
+    subset::GlyphMap,
+    tables::{head::Head, maxp::Maxp, name::Name, os2::Os2, post::Post},
+    BuilderError, FontBuilder,
+};
+
+/// Tables that depend on the whole glyph set, and so are dropped.
+const DROPPED_TABLES: &[Tag] = &[
+    Tag::new(b"DSIG"),
+    Tag::new(b"LTSH"),
+    Tag::new(b"VDMX"),
+    Tag::new(b"hdmx"),
+    Tag::new(b"sbix"),
+];
+
+/// Tables that are merged (or dropped) by the merger.
+const MERGED_TABLES: &[Tag] = &[
+    Tag::new(b"GDEF"),
+    Tag::new(b"GPOS"),
+    Tag::new(b"GSUB"),
+    Tag::new(b"OS/2"),
+    Tag::new(b"cmap"),
+    Tag::new(b"cvt "),
+    Tag::new(b"fpgm"),
+    Tag::new(b"glyf"),
+    Tag::new(b"head"),
+    Tag::new(b"hhea"),
+    Tag::new(b"hmtx"),
+    Tag::new(b"loca"),
+    Tag::new(b"maxp"),
+    Tag::new(b"name"),
+    Tag::new(b"post"),
+    Tag::new(b"prep"),
+    Tag::new(b"vhea"),
+    Tag::new(b"vmtx"),
+];
+
+/// Tables that indicate that a font is not supported.
+const UNSUPPORTED_TABLES: &[(Tag, &str)] = &[
+    (Tag::new(b"CFF "), "CFF outlines"),
+    (Tag::new(b"CFF2"), "CFF outlines"),
+    (Tag::new(b"fvar"), "font variations"),
+];
+
+/// Tables that refer to glyphs but are not merged; they are only supported
+/// in the first font, whose glyphs keep their identifiers.
+const FIRST_FONT_TABLES: &[(Tag, &str)] = &[
+    (Tag::new(b"CBDT"), "embedded bitmaps after the first font"),
+    (Tag::new(b"CBLC"), "embedded bitmaps after the first font"),
+    (Tag::new(b"COLR"), "color glyphs after the first font"),
+    (Tag::new(b"CPAL"), "color glyphs after the first font"),
+    (Tag::new(b"EBDT"), "embedded bitmaps after the first font"),
+    (Tag::new(b"EBLC"), "embedded bitmaps after the first font"),
+    (Tag::new(b"MATH"), "math layout after the first font"),
+    (Tag::new(b"SVG "), "SVG glyphs after the first font"),
+    (Tag::new(b"VORG"), "vertical origins after the first font"),
+    (Tag::new(b"kern"), "kerning tables after the first font"),
+];
+
+/// How the merger reconciles differences between the fonts.
+#[derive(Clone, Debug, Default)]
+pub struct MergeOptions {
+    names: NamePolicy,
+    metrics: MetricsPolicy,
+    codepoints: CodepointPolicy,
+}
+
+/// How the `name` tables are merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NamePolicy {
+    /// Use the names of the first font.
+    #[default]
+    First,
+    /// Use the names of the first font, adding any records (by platform,
+    /// encoding, language and name identifier) that only later fonts have.
+    Union,
+}
+
+/// How the ascender, descender and line gap in `hhea`, `vhea` and `OS/2`
+/// are merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetricsPolicy {
+    /// Use the largest extents of all fonts, so that no glyph is clipped.
+    #[default]
+    Max,
+    /// Use the metrics of the first font.
+    First,
+}
+
+/// How a codepoint that is mapped by more than one font is merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodepointPolicy {
+    /// Map the codepoint to the glyph of the first font that maps it.
+    #[default]
+    First,
+    /// Map the codepoint to the glyph of the last font that maps it.
+    Last,
+    /// Fail with [`MergeError::CodepointConflict`].
+    Error,
+}
+
+/// An error that occurs while merging fonts.
+#[derive(Clone, Debug)]
+pub enum MergeError {
+    /// A table in one of the fonts could not be read.
+    Read {
+        font: usize,
+        tag: Tag,
+        inner: ReadError,
+    },
+    /// A merged table could not be compiled.
+    Write(BuilderError),
+    /// No fonts were given.
+    NoFonts,
+    /// A value that must be the same in every font differs from that of the
+    /// first font.
+    Mismatch {
+        font: usize,
+        tag: Tag,
+        field: &'static str,
+    },
+    /// A codepoint is mapped by more than one font, and the policy is
+    /// [`CodepointPolicy::Error`].
+    CodepointConflict(u32),
+    /// The merged font would have more of some item than can be encoded.
+    Overflow(&'static str),
+    /// A font uses a feature that the merger does not support.
+    Unsupported { font: usize, reason: &'static str },
+}
+
+impl MergeOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how the `name` tables are merged.
+    pub fn set_name_policy(&mut self, policy: NamePolicy) -> &mut Self {
+        self.names = policy;
+        self
+    }
+
+    /// Set how the ascender, descender and line gap are merged.
+    pub fn set_metrics_policy(&mut self, policy: MetricsPolicy) -> &mut Self {
+        self.metrics = policy;
+        self
+    }
+
+    /// Set how codepoints that are mapped by more than one font are merged.
+    pub fn set_codepoint_policy(&mut self, policy: CodepointPolicy) -> &mut Self {
+        self.codepoints = policy;
+        self
+    }
+}
+
+/// The fonts to merge, and where their glyphs end up.
+struct Plan<'a> {
+    fonts: &'a [FontRef<'a>],
+    options: &'a MergeOptions,
+    /// The mapping from the glyphs of each font to the merged glyphs.
+    glyph_maps: Vec<GlyphMap>,
+    /// The number of glyphs in each font.
+    glyph_counts: Vec<u16>,
+}
+
+impl<'a> Plan<'a> {
+    fn new(fonts: &'a [FontRef<'a>], options: &'a MergeOptions) -> Result<Self, MergeError> {
+        let first = fonts.first().ok_or(MergeError::NoFonts)?;
+        let units_per_em = first
+            .head()
+            .map_err(read_error(0, Head::TAG))?
+            .units_per_em();
+        let mut glyph_maps = Vec::new();
+        let mut glyph_counts = Vec::new();
+        let mut next_gid = 0u32;
+        for (i, font) in fonts.iter().enumerate() {
+            let unsupported = match i {
+                0 => UNSUPPORTED_TABLES,
+                _ => &[UNSUPPORTED_TABLES, FIRST_FONT_TABLES].concat(),
+            };
+            if let Some((_, reason)) = unsupported
+                .iter()
+                .find(|(tag, _)| font.data_for_tag(*tag).is_some())
+            {
+                return Err(MergeError::Unsupported { font: i, reason });
+            }
+            if font.data_for_tag(Tag::new(b"glyf")).is_none() {
+                return Err(MergeError::Unsupported {
+                    font: i,
+                    reason: "fonts without TrueType outlines",
+                });
+            }
+            if font
+                .head()
+                .map_err(read_error(i, Head::TAG))?
+                .units_per_em()
+                != units_per_em
+            {
+                return Err(MergeError::Mismatch {
+                    font: i,
+                    tag: Head::TAG,
+                    field: "unitsPerEm",
+                });
+            }
+            let num_glyphs = font.maxp().map_err(read_error(i, Maxp::TAG))?.num_glyphs();
+            // the .notdef glyph of every font is merged into the first
+            let first_gid = if i == 0 { 0 } else { 1 };
+            let start = next_gid;
+            next_gid += num_glyphs.saturating_sub(first_gid) as u32;
+            if next_gid > u16::MAX as u32 {
+                return Err(MergeError::Overflow("glyphs"));
+            }
+            glyph_maps.push(GlyphMap::from_pairs(
+                std::iter::once((GlyphId::NOTDEF, GlyphId::NOTDEF)).chain(
+                    (first_gid..num_glyphs).map(|gid| {
+                        let new = start + (gid - first_gid) as u32;
+                        (GlyphId::new(gid), GlyphId::new(new as u16))
+                    }),
+                ),
+            ));
+            glyph_counts.push(num_glyphs);
+        }
+        Ok(Self {
+            fonts,
+            options,
+            glyph_maps,
+            glyph_counts,
+        })
+    }
+
+    /// The number of glyphs in the merged font.
+    fn num_glyphs(&self) -> u16 {
+        self.glyph_counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                if i == 0 {
+                    *count
+                } else {
+                    count.saturating_sub(1)
+                }
+            })
+            .sum()
+    }
+
+    /// Iterates over the glyphs of the merged font, as the index of the
+    /// source font and the glyph in that font.
+    fn source_glyphs(&self) -> impl Iterator<Item = (usize, GlyphId)> + '_ {
+        self.glyph_counts.iter().enumerate().flat_map(|(i, count)| {
+            let first_gid = if i == 0 { 0 } else { 1 };
+            (first_gid..*count).map(move |gid| (i, GlyphId::new(gid)))
+        })
+    }
+
+    fn first(&self) -> &FontRef<'a> {
+        &self.fonts[0]
+    }
+}
+
+/// Merge several fonts into one, returning the binary data of the new font.
+///
+/// The fonts must all have the same units per em. The first font provides
+/// the values that cannot be merged, such as the font revision and the
+/// style flags.
+pub fn merge_fonts(fonts: &[FontRef], options: &MergeOptions) -> Result<Vec<u8>, MergeError> {
+    let plan = Plan::new(fonts, options)?;
+    let mut builder = FontBuilder::new();
+
+    builder.add_table(&merge_maxp(&plan)?)?;
+    let loca_format = glyf::merge(&plan, &mut builder)?;
+    let mut head = merge_head(&plan)?;
+    head.index_to_loc_format = loca_format as i16;
+    builder.add_table(&head)?;
+    let average_advance = metrics::merge(&plan, &mut builder)?;
+    let codepoints = cmap::merge(&plan, &mut builder)?;
+    if let Some(mut os2) = merge_os2(&plan)? {
+        let first = codepoints.first();
+        let last = codepoints.last();
+        if let (Some(first), Some(last)) = (first, last) {
+            os2.us_first_char_index = (*first).min(0xFFFF) as u16;
+            os2.us_last_char_index = (*last).min(0xFFFF) as u16;
+        }
+        if let Some(average_advance) = average_advance {
+            os2.x_avg_char_width = average_advance;
+        }
+        builder.add_table(&os2)?;
+    }
+    if let Some(post) = merge_post(&plan)? {
+        builder.add_table(&post)?;
+    }
+    if let Some(name) = merge_name(&plan)? {
+        builder.add_table(&name)?;
+    }
+    layout::merge(&plan, &mut builder)?;
+
+    for record in plan.first().table_directory.table_records() {
+        let tag = record.tag();
+        if builder.contains(tag) || MERGED_TABLES.contains(&tag) || DROPPED_TABLES.contains(&tag) {
+            continue;
+        }
+        if let Some(data) = plan.first().data_for_tag(tag) {
+            builder.add_raw(tag, data.as_bytes());
+        }
+    }
+    Ok(builder.build())
+}
+
+fn read_error(font: usize, tag: Tag) -> impl FnOnce(ReadError) -> MergeError {
+    move |inner| MergeError::Read { font, tag, inner }
+}
+
+/// Converts a missing table into `None`, and other errors into a
+/// [`MergeError`].
+fn optional<T>(
+    font: usize,
+    tag: Tag,
+    table: Result<T, ReadError>,
+) -> Result<Option<T>, MergeError> {
+    match table {
+        Ok(table) => Ok(Some(table)),
+        Err(ReadError::TableIsMissing(_)) => Ok(None),
+        Err(inner) => Err(MergeError::Read { font, tag, inner }),
+    }
+}
+
+/// The glyph count is the total of all fonts, and every other limit is the
+/// largest of all fonts.
+fn merge_maxp(plan: &Plan) -> Result<Maxp, MergeError> {
+    let mut merged: Option<Maxp> = None;
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let maxp: Maxp = font
+            .maxp()
+            .map_err(read_error(i, Maxp::TAG))?
+            .to_owned_table();
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(maxp);
+            continue;
+        };
+        let max = |a: &mut Option<u16>, b: Option<u16>| {
+            if let (Some(a), Some(b)) = (a.as_mut(), b) {
+                *a = (*a).max(b);
+            }
+        };
+        max(&mut merged.max_points, maxp.max_points);
+        max(&mut merged.max_contours, maxp.max_contours);
+        max(&mut merged.max_composite_points, maxp.max_composite_points);
+        max(
+            &mut merged.max_composite_contours,
+            maxp.max_composite_contours,
+        );
+        max(&mut merged.max_zones, maxp.max_zones);
+        max(&mut merged.max_twilight_points, maxp.max_twilight_points);
+        max(&mut merged.max_storage, maxp.max_storage);
+        max(&mut merged.max_function_defs, maxp.max_function_defs);
+        max(&mut merged.max_instruction_defs, maxp.max_instruction_defs);
+        max(&mut merged.max_stack_elements, maxp.max_stack_elements);
+        max(
+            &mut merged.max_size_of_instructions,
+            maxp.max_size_of_instructions,
+        );
+        max(
+            &mut merged.max_component_elements,
+            maxp.max_component_elements,
+        );
+        max(&mut merged.max_component_depth, maxp.max_component_depth);
+    }
+    let mut merged = merged.ok_or(MergeError::NoFonts)?;
+    merged.num_glyphs = plan.num_glyphs();
+    Ok(merged)
+}
+
+/// The bounding box is the union of all fonts; everything else is taken
+/// from the first font.
+fn merge_head(plan: &Plan) -> Result<Head, MergeError> {
+    let mut merged: Head = plan
+        .first()
+        .head()
+        .map_err(read_error(0, Head::TAG))?
+        .to_owned_table();
+    for (i, font) in plan.fonts.iter().enumerate().skip(1) {
+        let head = font.head().map_err(read_error(i, Head::TAG))?;
+        merged.x_min = merged.x_min.min(head.x_min());
+        merged.y_min = merged.y_min.min(head.y_min());
+        merged.x_max = merged.x_max.max(head.x_max());
+        merged.y_max = merged.y_max.max(head.y_max());
+    }
+    Ok(merged)
+}
+
+/// Merges the `OS/2` tables of the fonts that have one.
+///
+/// The Unicode and code page ranges are combined, and the most restrictive
+/// embedding permissions are used. The typographic and Windows metrics
+/// follow the [`MetricsPolicy`]; everything else is taken from the first
+/// font with an `OS/2` table.
+fn merge_os2(plan: &Plan) -> Result<Option<Os2>, MergeError> {
+    let mut merged: Option<Os2> = None;
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let Some(os2) = optional(i, Os2::TAG, font.os2())? else {
+            continue;
+        };
+        let os2: Os2 = os2.to_owned_table();
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(os2);
+            continue;
+        };
+        merged.ul_unicode_range_1 |= os2.ul_unicode_range_1;
+        merged.ul_unicode_range_2 |= os2.ul_unicode_range_2;
+        merged.ul_unicode_range_3 |= os2.ul_unicode_range_3;
+        merged.ul_unicode_range_4 |= os2.ul_unicode_range_4;
+        if let (Some(merged), Some(range)) = (
+            merged.ul_code_page_range_1.as_mut(),
+            os2.ul_code_page_range_1,
+        ) {
+            *merged |= range;
+        }
+        if let (Some(merged), Some(range)) = (
+            merged.ul_code_page_range_2.as_mut(),
+            os2.ul_code_page_range_2,
+        ) {
+            *merged |= range;
+        }
+        merged.fs_type = merge_fs_type(merged.fs_type, os2.fs_type);
+        if let (Some(merged), Some(context)) = (merged.us_max_context.as_mut(), os2.us_max_context)
+        {
+            *merged = (*merged).max(context);
+        }
+        if plan.options.metrics == MetricsPolicy::Max {
+            merged.s_typo_ascender = merged.s_typo_ascender.max(os2.s_typo_ascender);
+            merged.s_typo_descender = merged.s_typo_descender.min(os2.s_typo_descender);
+            merged.s_typo_line_gap = merged.s_typo_line_gap.max(os2.s_typo_line_gap);
+            merged.us_win_ascent = merged.us_win_ascent.max(os2.us_win_ascent);
+            merged.us_win_descent = merged.us_win_descent.max(os2.us_win_descent);
+        }
+    }
+    Ok(merged)
+}
+
+/// Returns the more restrictive of two sets of embedding permissions.
+///
+/// The usage permissions, from least to most restrictive, are installable,
+/// editable, preview and print, and restricted; the subsetting and bitmap
+/// flags are combined.
+fn merge_fs_type(a: u16, b: u16) -> u16 {
+    const RESTRICTED: u16 = 0x0002;
+    const PREVIEW_AND_PRINT: u16 = 0x0004;
+    const EDITABLE: u16 = 0x0008;
+    const FLAGS: u16 = 0x0300;
+    let usage = [RESTRICTED, PREVIEW_AND_PRINT, EDITABLE]
+        .into_iter()
+        .find(|usage| (a | b) & usage != 0)
+        .unwrap_or_default();
+    usage | ((a | b) & FLAGS)
+}
+
+/// Merges the glyph names of all fonts into a version 2.0 table.
+///
+/// Names that are already used by an earlier glyph get a numeric suffix.
+/// If no font has glyph names, the table of the first font is used as is.
+fn merge_post(plan: &Plan) -> Result<Option<Post>, MergeError> {
+    let mut posts = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        posts.push(optional(i, Post::TAG, font.post())?);
+    }
+    let Some(first) = posts.first().cloned().flatten() else {
+        return Ok(None);
+    };
+    let owned: Post = first.to_owned_table();
+    let has_names = |post: &Option<ReadPost>| {
+        post.as_ref().is_some_and(|post| {
+            [Version16Dot16::VERSION_1_0, Version16Dot16::VERSION_2_0].contains(&post.version())
+        })
+    };
+    if !posts.iter().any(has_names) {
+        return Ok(Some(owned));
+    }
+    let mut used = HashSet::new();
+    let names: Vec<_> = plan
+        .source_glyphs()
+        .enumerate()
+        .map(|(new, (i, gid))| {
+            let name = posts[i]
+                .as_ref()
+                .and_then(|post| post.glyph_name(gid))
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("glyph{new}"));
+            let mut unique = name.clone();
+            let mut suffix = 0;
+            while !used.insert(unique.clone()) {
+                suffix += 1;
+                unique = format!("{name}#{suffix}");
+            }
+            unique
+        })
+        .collect();
+    Ok(Some(Post {
+        italic_angle: owned.italic_angle,
+        underline_position: owned.underline_position,
+        underline_thickness: owned.underline_thickness,
+        is_fixed_pitch: owned.is_fixed_pitch,
+        min_mem_type42: owned.min_mem_type42,
+        max_mem_type42: owned.max_mem_type42,
+        min_mem_type1: owned.min_mem_type1,
+        max_mem_type1: owned.max_mem_type1,
+        ..Post::new_v2(names.iter().map(String::as_str))
+    }))
+}
+
+/// Merges the `name` tables according to the [`NamePolicy`].
+fn merge_name(plan: &Plan) -> Result<Option<Name>, MergeError> {
+    let mut merged: Option<Name> = None;
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let Some(name) = optional(i, Name::TAG, font.name())? else {
+            continue;
+        };
+        let name: Name = name.to_owned_table();
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(name);
+            continue;
+        };
+        if plan.options.names == NamePolicy::First {
+            break;
+        }
+        let key = |record: &crate::tables::name::NameRecord| {
+            (
+                record.platform_id,
+                record.encoding_id,
+                record.language_id,
+                record.name_id,
+            )
+        };
+        let existing: BTreeSet<_> = merged.name_record.iter().map(key).collect();
+        merged.name_record.extend(
+            name.name_record
+                .into_iter()
+                .filter(|record| !existing.contains(&key(record))),
+        );
+    }
+    Ok(merged)
+}
+
+impl From<BuilderError> for MergeError {
+    fn from(value: BuilderError) -> Self {
+        MergeError::Write(value)
+    }
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Read { font, tag, inner } => {
+                write!(f, "failed to read '{tag}' in font {font}: {inner}")
+            }
+            MergeError::Write(error) => error.fmt(f),
+            MergeError::NoFonts => write!(f, "no fonts to merge"),
+            MergeError::Mismatch { font, tag, field } => {
+                write!(
+                    f,
+                    "'{tag}' {field} of font {font} differs from the first font"
+                )
+            }
+            MergeError::CodepointConflict(codepoint) => {
+                write!(
+                    f,
+                    "codepoint U+{codepoint:04X} is mapped by more than one font"
+                )
+            }
+            MergeError::Overflow(what) => write!(f, "too many {what} in the merged font"),
+            MergeError::Unsupported { font, reason } => {
+                write!(f, "font {font} is not supported: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{sanitize::sanitize, tables::cmap::Cmap as ReadCmap};
+
+    use super::*;
+    use crate::instancer::{instantiate_font, AxisLimits};
+
+    fn merge(fonts: &[&[u8]], options: &MergeOptions) -> Result<Vec<u8>, MergeError> {
+        let fonts: Vec<_> = fonts
+            .iter()
+            .map(|data| FontRef::new(data).unwrap())
+            .collect();
+        merge_fonts(&fonts, options)
+    }
+
+    /// A static font with the same units per em as `COLRV0V1`.
+    fn static_font() -> Vec<u8> {
+        let font = FontRef::new(font_test_data::HVAR_WITH_TRUNCATED_ADVANCE_INDEX_MAP).unwrap();
+        let mut limits = AxisLimits::new();
+        for axis in font.fvar().unwrap().axes().unwrap() {
+            limits.pin(axis.axis_tag(), axis.default_value().to_f64());
+        }
+        instantiate_font(&font, &limits).unwrap()
+    }
+
+    /// `COLRV0V1` without its color tables, so that it can follow another
+    /// font.
+    fn without_color() -> Vec<u8> {
+        let font = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let mut builder = FontBuilder::new();
+        for record in font.table_directory.table_records() {
+            let tag = record.tag();
+            if ![Tag::new(b"COLR"), Tag::new(b"CPAL")].contains(&tag) {
+                builder.add_raw(tag, font.data_for_tag(tag).unwrap().as_bytes());
+            }
+        }
+        builder.build()
+    }
+
+    fn advances(font: &FontRef) -> Vec<u16> {
+        let hmtx = font.hmtx().unwrap();
+        crate::tables::hmtx::expand_metrics(
+            hmtx.h_metrics()
+                .iter()
+                .map(|metric| (metric.advance(), metric.side_bearing())),
+            hmtx.left_side_bearings().iter().map(|lsb| lsb.get()),
+            font.maxp().unwrap().num_glyphs() as usize,
+        )
+        .into_iter()
+        .map(|metric| metric.advance)
+        .collect()
+    }
+
+    /// Returns the mappings of a character map, other than those to
+    /// `.notdef`.
+    fn mappings(cmap: &ReadCmap) -> BTreeSet<(u32, GlyphId)> {
+        crate::subset::cmap::unicode_mappings(cmap)
+            .unwrap()
+            .filter(|(_, gid)| *gid != GlyphId::NOTDEF)
+            .collect()
+    }
+
+    #[test]
+    fn merged_fonts_are_well_formed() {
+        let second = static_font();
+        let data = merge(&[font_test_data::COLRV0V1, &second], &MergeOptions::new()).unwrap();
+        let merged = FontRef::new(&data).unwrap();
+        let issues = sanitize(&merged);
+        assert!(issues.issues().is_empty(), "{:?}", issues.issues());
+
+        let first = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let second = FontRef::new(&second).unwrap();
+        let first_count = first.maxp().unwrap().num_glyphs();
+        let second_count = second.maxp().unwrap().num_glyphs();
+        assert_eq!(
+            merged.maxp().unwrap().num_glyphs(),
+            first_count + second_count - 1
+        );
+
+        let merged_cmap = merged.cmap().unwrap();
+        let first_mappings = mappings(&first.cmap().unwrap());
+        for (cp, gid) in &first_mappings {
+            assert_eq!(merged_cmap.map_codepoint(*cp), Some(*gid));
+        }
+        let merged_advances = advances(&merged);
+        let second_advances = advances(&second);
+        for (cp, gid) in mappings(&second.cmap().unwrap()) {
+            if first_mappings.iter().any(|(first_cp, _)| *first_cp == cp) {
+                continue;
+            }
+            let new_gid = GlyphId::new(gid.to_u16() + first_count - 1);
+            assert_eq!(merged_cmap.map_codepoint(cp), Some(new_gid));
+            assert_eq!(
+                merged_advances[new_gid.to_u16() as usize],
+                second_advances[gid.to_u16() as usize],
+                "{cp:X}"
+            );
+        }
+    }
+
+    #[test]
+    fn glyph_names_are_made_unique() {
+        let second = without_color();
+        let data = merge(&[font_test_data::COLRV0V1, &second], &MergeOptions::new()).unwrap();
+        let merged = FontRef::new(&data).unwrap();
+        let source = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let num_glyphs = source.maxp().unwrap().num_glyphs();
+        let post = merged.post().unwrap();
+        let source_post = source.post().unwrap();
+        for gid in 1..num_glyphs {
+            let name = source_post.glyph_name(GlyphId::new(gid)).unwrap();
+            assert_eq!(post.glyph_name(GlyphId::new(gid)), Some(name));
+            let copy = GlyphId::new(gid + num_glyphs - 1);
+            assert_eq!(post.glyph_name(copy), Some(format!("{name}#1").as_str()));
+        }
+    }
+
+    #[test]
+    fn codepoint_policies() {
+        let second = without_color();
+        let fonts = [font_test_data::COLRV0V1, &second];
+        let source = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let num_glyphs = source.maxp().unwrap().num_glyphs();
+        let (cp, gid) = mappings(&source.cmap().unwrap()).pop_first().unwrap();
+        let mut options = MergeOptions::new();
+        for (policy, expected) in [
+            (CodepointPolicy::First, gid),
+            (
+                CodepointPolicy::Last,
+                GlyphId::new(gid.to_u16() + num_glyphs - 1),
+            ),
+        ] {
+            let data = merge(&fonts, options.set_codepoint_policy(policy)).unwrap();
+            let merged = FontRef::new(&data).unwrap();
+            assert_eq!(merged.cmap().unwrap().map_codepoint(cp), Some(expected));
+        }
+        let result = merge(&fonts, options.set_codepoint_policy(CodepointPolicy::Error));
+        assert!(matches!(result, Err(MergeError::CodepointConflict(_))));
+    }
+
+    #[test]
+    fn irreconcilable_fonts() {
+        let result = merge(
+            &[font_test_data::COLRV0V1, font_test_data::SIMPLE_GLYF],
+            &MergeOptions::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(MergeError::Mismatch {
+                font: 1,
+                field: "unitsPerEm",
+                ..
+            })
+        ));
+        let result = merge(
+            &[font_test_data::COLRV0V1, font_test_data::COLRV0V1_VARIABLE],
+            &MergeOptions::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(MergeError::Unsupported { font: 1, .. })
+        ));
+        assert!(matches!(
+            merge(&[], &MergeOptions::new()),
+            Err(MergeError::NoFonts)
+        ));
+    }
+
+    #[test]
+    fn unmerged_glyph_tables_only_in_first_font() {
+        let first = static_font();
+        let result = merge(&[&first, font_test_data::COLRV0V1], &MergeOptions::new());
+        assert!(matches!(
+            result,
+            Err(MergeError::Unsupported {
+                font: 1,
+                reason: "color glyphs after the first font"
+            })
+        ));
+        let second = without_color();
+        assert!(merge(&[&first, &second], &MergeOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn embedding_permissions() {
+        assert_eq!(merge_fs_type(0, 0x0008), 0x0008);
+        assert_eq!(merge_fs_type(0x0004, 0x0008), 0x0004);
+        assert_eq!(merge_fs_type(0x0102, 0x0208), 0x0302);
+        assert_eq!(merge_fs_type(0, 0), 0);
+    }
+}
@@ -0,0 +1,98 @@
+//! Merging character maps.
+
+use std::collections::BTreeMap;
+
+use read_fonts::{tables::cmap::MapVariant, TableProvider};
+use types::{GlyphId, Tag, Uint24};
+
+use super::{optional, read_error, CodepointPolicy, MergeError, Plan};
+use crate::{
+    subset::cmap::{add_variation_subtable, build_cmap14, unicode_mappings, variation_subtable},
+    tables::cmap::{Cmap, UvsMapping},
+    FontBuilder,
+};
+
+const CMAP: Tag = Tag::new(b"cmap");
+
+/// Writes the union of the Unicode mappings of all fonts, returning the
+/// mapped codepoints in order.
+///
+/// Variation sequences are taken from the font that the base codepoint is
+/// mapped from.
+pub(super) fn merge(plan: &Plan, builder: &mut FontBuilder) -> Result<Vec<u32>, MergeError> {
+    let mut cmaps = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        cmaps.push(optional(i, CMAP, font.cmap())?);
+    }
+    if cmaps.iter().all(Option::is_none) {
+        return Ok(Vec::new());
+    }
+
+    // the merged glyph, and the font it came from
+    let mut mappings: BTreeMap<u32, (GlyphId, usize)> = BTreeMap::new();
+    for (i, cmap) in cmaps.iter().enumerate() {
+        let Some(cmap) = cmap else {
+            continue;
+        };
+        for (cp, gid) in unicode_mappings(cmap).map_err(read_error(i, CMAP))? {
+            // mappings to .notdef would hide those of later fonts
+            let Some(new_gid) = plan.glyph_maps[i]
+                .get(gid)
+                .filter(|gid| *gid != GlyphId::NOTDEF)
+            else {
+                continue;
+            };
+            match (mappings.contains_key(&cp), plan.options.codepoints) {
+                (false, _) | (true, CodepointPolicy::Last) => {
+                    mappings.insert(cp, (new_gid, i));
+                }
+                (true, CodepointPolicy::First) => (),
+                (true, CodepointPolicy::Error) => return Err(MergeError::CodepointConflict(cp)),
+            }
+        }
+    }
+
+    let mut sequences: BTreeMap<u32, (Vec<u32>, Vec<UvsMapping>)> = BTreeMap::new();
+    for (i, cmap) in cmaps.iter().enumerate() {
+        let Some(cmap14) = cmap
+            .as_ref()
+            .map(variation_subtable)
+            .transpose()
+            .map_err(read_error(i, CMAP))?
+            .flatten()
+        else {
+            continue;
+        };
+        for (cp, selector, variant) in cmap14.iter() {
+            if mappings.get(&cp).map(|(_, font)| *font) != Some(i) {
+                continue;
+            }
+            let (defaults, variants) = sequences.entry(selector).or_default();
+            match variant {
+                MapVariant::UseDefault => defaults.push(cp),
+                MapVariant::Variant(gid) => {
+                    if let Some(new_gid) = plan.glyph_maps[i].get(gid) {
+                        variants.push(UvsMapping::new(Uint24::new(cp), new_gid.to_u16()));
+                    }
+                }
+            }
+        }
+    }
+    for (defaults, variants) in sequences.values_mut() {
+        defaults.sort_unstable();
+        defaults.dedup();
+        variants.sort_by_key(|mapping| u32::from(mapping.unicode_value));
+        variants.dedup_by_key(|mapping| u32::from(mapping.unicode_value));
+    }
+
+    let mut cmap = Cmap::from_mappings(
+        mappings
+            .iter()
+            .filter_map(|(cp, (gid, _))| Some((char::from_u32(*cp)?, *gid))),
+    );
+    if let Some(cmap14) = build_cmap14(sequences) {
+        add_variation_subtable(&mut cmap, cmap14);
+    }
+    builder.add_table(&cmap)?;
+    Ok(mappings.into_keys().collect())
+}
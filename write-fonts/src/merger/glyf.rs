@@ -0,0 +1,84 @@
+//! Merging TrueType outlines and hinting.
+
+use std::collections::BTreeSet;
+
+use read_fonts::TableProvider;
+use types::{GlyphId, Tag};
+
+use super::{read_error, MergeError, Plan};
+use crate::{
+    subset::glyf::{component_positions, strip_instructions, Outlines},
+    tables::loca::{Loca, LocaFormat},
+    FontBuilder,
+};
+
+const GLYF: Tag = Tag::new(b"glyf");
+const LOCA: Tag = Tag::new(b"loca");
+/// The tables used by glyph instructions.
+const HINTING_TABLES: [Tag; 3] = [Tag::new(b"fpgm"), Tag::new(b"prep"), Tag::new(b"cvt ")];
+
+/// Writes the `glyf` and `loca` tables, and the hinting tables if they can
+/// be shared by all fonts, returning the new loca format.
+///
+/// Glyph data is copied unchanged, apart from the glyph identifiers of
+/// components and, if the hinting tables are dropped, the instructions.
+pub(super) fn merge<'a>(
+    plan: &Plan<'a>,
+    builder: &mut FontBuilder<'a>,
+) -> Result<LocaFormat, MergeError> {
+    let mut outlines = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let glyphs =
+            Outlines::new(font)
+                .map_err(read_error(i, LOCA))?
+                .ok_or(MergeError::Unsupported {
+                    font: i,
+                    reason: "fonts without TrueType outlines",
+                })?;
+        outlines.push(glyphs);
+    }
+
+    // fonts without hinting tables can still use instructions that do not
+    // depend on them, so only fonts that have them need to agree
+    let hinting: BTreeSet<_> = plan
+        .fonts
+        .iter()
+        .map(|font| HINTING_TABLES.map(|tag| font.data_for_tag(tag).map(|data| data.as_bytes())))
+        .filter(|tables| tables.iter().any(Option::is_some))
+        .collect();
+    let keep_hinting = hinting.len() <= 1;
+    if let Some(tables) = hinting.first().filter(|_| keep_hinting) {
+        for (tag, data) in HINTING_TABLES.iter().zip(tables) {
+            if let Some(data) = data {
+                builder.add_raw(*tag, *data);
+            }
+        }
+    }
+
+    let mut glyf = Vec::new();
+    let mut offsets = vec![0u32];
+    for (i, gid) in plan.source_glyphs() {
+        let start = glyf.len();
+        let data = outlines[i].glyph_data(gid);
+        if keep_hinting {
+            glyf.extend_from_slice(data);
+        } else {
+            glyf.extend(strip_instructions(data));
+        }
+        for pos in component_positions(&glyf[start..]) {
+            let pos = start + pos;
+            let component = GlyphId::new(u16::from_be_bytes([glyf[pos], glyf[pos + 1]]));
+            let new_gid = plan.glyph_maps[i].get(component).unwrap_or(GlyphId::NOTDEF);
+            glyf[pos..pos + 2].copy_from_slice(&new_gid.to_be_bytes());
+        }
+        // keep offsets even, so that they can be stored in a short loca
+        if glyf.len() % 2 != 0 {
+            glyf.push(0);
+        }
+        offsets.push(glyf.len() as u32);
+    }
+    let loca = Loca::new(offsets);
+    let format = loca.format();
+    builder.add_raw(GLYF, glyf).add_table(&loca)?;
+    Ok(format)
+}
@@ -0,0 +1,569 @@
+//! Merging the OpenType layout tables.
+//!
+//! Each table is first remapped to the merged glyph order with the layout
+//! subsetter. Lookups and features are then concatenated, with the lookup
+//! and feature indices of later fonts offset accordingly.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use read_fonts::TableProvider;
+use types::{GlyphId, Tag};
+
+use super::{optional, MergeError, Plan};
+use crate::{
+    from_obj::ToOwnedTable,
+    subset::layout::{
+        remap_feature, remap_lang_sys, subset_gdef, subset_gpos, subset_gsub, LayoutLookup,
+    },
+    tables::{
+        gdef::{AttachList, Gdef, LigCaretList, MarkGlyphSets},
+        gpos::Gpos,
+        gsub::Gsub,
+        layout::{
+            ClassDef, CoverageTable, FeatureList, FeatureRecord, LangSys, LangSysRecord,
+            LookupList, Script, ScriptList, ScriptRecord,
+        },
+    },
+    FontBuilder, OffsetMarker,
+};
+
+const GDEF: Tag = Tag::new(b"GDEF");
+const GPOS: Tag = Tag::new(b"GPOS");
+const GSUB: Tag = Tag::new(b"GSUB");
+
+/// The offsets added to the mark attachment classes and mark glyph sets of
+/// a font when its GDEF table is merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct MarkOffsets {
+    classes: u16,
+    sets: u16,
+}
+
+/// The parts of a GSUB or GPOS table that are merged.
+struct LayoutParts<L> {
+    script_list: ScriptList,
+    feature_list: FeatureList,
+    lookups: Vec<L>,
+}
+
+/// A script, with its language systems by tag.
+struct MergedScript {
+    default: Option<LangSys>,
+    languages: BTreeMap<Tag, LangSys>,
+}
+
+/// Writes the merged `GDEF`, `GSUB` and `GPOS` tables.
+pub(super) fn merge(plan: &Plan, builder: &mut FontBuilder) -> Result<(), MergeError> {
+    let mut gdefs = Vec::new();
+    let mut gsubs = Vec::new();
+    let mut gposes = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let glyphs = &plan.glyph_maps[i];
+        gdefs.push(
+            optional(i, GDEF, font.gdef())?.map(|gdef| subset_gdef(gdef.to_owned_table(), glyphs)),
+        );
+        gsubs.push(
+            optional(i, GSUB, font.gsub())?
+                .and_then(|gsub| subset_gsub(gsub.to_owned_table(), glyphs, None))
+                .map(|gsub| LayoutParts {
+                    script_list: gsub.script_list.into_inner(),
+                    feature_list: gsub.feature_list.into_inner(),
+                    lookups: lookups(gsub.lookup_list.into_inner()),
+                }),
+        );
+        gposes.push(
+            optional(i, GPOS, font.gpos())?
+                .and_then(|gpos| subset_gpos(gpos.to_owned_table(), glyphs, None))
+                .map(|gpos| LayoutParts {
+                    script_list: gpos.script_list.into_inner(),
+                    feature_list: gpos.feature_list.into_inner(),
+                    lookups: lookups(gpos.lookup_list.into_inner()),
+                }),
+        );
+    }
+
+    let (gdef, offsets) = merge_gdef(gdefs)?;
+    if let Some(gdef) = gdef {
+        builder.add_table(&gdef)?;
+    }
+    if let Some(gsub) = merge_layout(gsubs, &offsets)? {
+        builder.add_table(&Gsub::new(
+            gsub.script_list,
+            gsub.feature_list,
+            LookupList::new(gsub.lookups),
+        ))?;
+    }
+    if let Some(gpos) = merge_layout(gposes, &offsets)? {
+        builder.add_table(&Gpos::new(
+            gpos.script_list,
+            gpos.feature_list,
+            LookupList::new(gpos.lookups),
+        ))?;
+    }
+    Ok(())
+}
+
+fn lookups<L>(lookup_list: LookupList<L>) -> Vec<L> {
+    lookup_list
+        .lookups
+        .into_iter()
+        .map(OffsetMarker::into_inner)
+        .collect()
+}
+
+/// Merges the (remapped) GDEF tables, returning the offsets to apply to the
+/// lookup flags of each font.
+///
+/// Glyph classes, attachment points and ligature carets are combined, with
+/// the first font taking precedence. Mark attachment classes and mark glyph
+/// sets are appended, so that those of different fonts remain distinct.
+fn merge_gdef(gdefs: Vec<Option<Gdef>>) -> Result<(Option<Gdef>, Vec<MarkOffsets>), MergeError> {
+    let mut offsets = Vec::new();
+    if gdefs.iter().all(Option::is_none) {
+        offsets.resize(gdefs.len(), MarkOffsets::default());
+        return Ok((None, offsets));
+    }
+    let mut glyph_classes = BTreeMap::new();
+    let mut mark_classes = BTreeMap::new();
+    let mut attach_points = BTreeMap::new();
+    let mut lig_carets = BTreeMap::new();
+    let mut mark_sets: Vec<CoverageTable> = Vec::new();
+    let mut class_offset = 0u16;
+    for gdef in gdefs {
+        offsets.push(MarkOffsets {
+            classes: class_offset,
+            sets: u16::try_from(mark_sets.len())
+                .map_err(|_| MergeError::Overflow("mark glyph sets"))?,
+        });
+        let Some(gdef) = gdef else {
+            continue;
+        };
+        if let Some(class_def) = gdef.glyph_class_def.as_ref() {
+            for (gid, class) in class_def.iter() {
+                glyph_classes.entry(gid).or_insert(class);
+            }
+        }
+        if let Some(class_def) = gdef.mark_attach_class_def.as_ref() {
+            let mut max_class = 0;
+            for (gid, class) in class_def.iter().filter(|(_, class)| *class != 0) {
+                mark_classes.entry(gid).or_insert(class + class_offset);
+                max_class = max_class.max(class);
+            }
+            class_offset += max_class;
+            // mark attachment types are stored in the high byte of the
+            // lookup flags
+            if class_offset > u8::MAX as u16 {
+                return Err(MergeError::Overflow("mark attachment classes"));
+            }
+        }
+        if let Some(attach_list) = gdef.attach_list.into_inner() {
+            let glyphs = attach_list.coverage.iter().collect::<Vec<_>>();
+            for (gid, points) in glyphs.into_iter().zip(attach_list.attach_points) {
+                attach_points.entry(gid).or_insert(points.into_inner());
+            }
+        }
+        if let Some(lig_caret_list) = gdef.lig_caret_list.into_inner() {
+            let glyphs = lig_caret_list.coverage.iter().collect::<Vec<_>>();
+            for (gid, carets) in glyphs.into_iter().zip(lig_caret_list.lig_glyphs) {
+                lig_carets.entry(gid).or_insert(carets.into_inner());
+            }
+        }
+        if let Some(sets) = gdef.mark_glyph_sets_def.into_inner() {
+            mark_sets.extend(sets.coverages.into_iter().map(OffsetMarker::into_inner));
+        }
+    }
+
+    let class_def = |classes: BTreeMap<GlyphId, u16>| {
+        (!classes.is_empty()).then(|| classes.into_iter().collect::<ClassDef>())
+    };
+    let attach_list = (!attach_points.is_empty()).then(|| {
+        AttachList::new(
+            attach_points.keys().copied().collect(),
+            attach_points.into_values().collect(),
+        )
+    });
+    let lig_caret_list = (!lig_carets.is_empty()).then(|| {
+        LigCaretList::new(
+            lig_carets.keys().copied().collect(),
+            lig_carets.into_values().collect(),
+        )
+    });
+    let mut gdef = Gdef::new(
+        class_def(glyph_classes),
+        attach_list,
+        lig_caret_list,
+        class_def(mark_classes),
+    );
+    if !mark_sets.is_empty() {
+        gdef.mark_glyph_sets_def = MarkGlyphSets::new(mark_sets).into();
+    }
+    Ok((Some(gdef), offsets))
+}
+
+/// Concatenates the lookups and features of each font, and combines their
+/// scripts, returning `None` if no font has any lookups.
+///
+/// Features are sorted by tag, as required by the specification.
+fn merge_layout<L: LayoutLookup>(
+    tables: Vec<Option<LayoutParts<L>>>,
+    offsets: &[MarkOffsets],
+) -> Result<Option<LayoutParts<L>>, MergeError> {
+    let mut lookups = Vec::new();
+    let mut features = Vec::new();
+    let mut scripts: BTreeMap<Tag, MergedScript> = BTreeMap::new();
+    for (i, table) in tables.into_iter().enumerate() {
+        let Some(table) = table else {
+            continue;
+        };
+        let lookup_map =
+            index_map(lookups.len(), table.lookups.len()).ok_or(MergeError::Overflow("lookups"))?;
+        for mut lookup in table.lookups {
+            lookup.remap_lookups(&lookup_map);
+            lookup.offset_mark_references(offsets[i].classes, offsets[i].sets);
+            lookups.push(lookup);
+        }
+        let feature_records = table.feature_list.feature_records;
+        let feature_map = index_map(features.len(), feature_records.len())
+            .ok_or(MergeError::Overflow("features"))?;
+        for mut record in feature_records {
+            remap_feature(&mut record.feature, &lookup_map);
+            features.push(record);
+        }
+        for record in table.script_list.script_records {
+            let script = record.script.into_inner();
+            let mut default = script.default_lang_sys.into_inner();
+            if let Some(lang_sys) = default.as_mut() {
+                remap_lang_sys(lang_sys, &feature_map);
+            }
+            let languages = script
+                .lang_sys_records
+                .into_iter()
+                .map(|record| {
+                    let mut lang_sys = record.lang_sys.into_inner();
+                    remap_lang_sys(&mut lang_sys, &feature_map);
+                    (record.lang_sys_tag, lang_sys)
+                })
+                .collect();
+            let script = MergedScript { default, languages };
+            match scripts.get_mut(&record.script_tag) {
+                Some(merged) => merged.merge(script),
+                None => {
+                    scripts.insert(record.script_tag, script);
+                }
+            }
+        }
+    }
+    if lookups.is_empty() {
+        return Ok(None);
+    }
+
+    let mut order: Vec<_> = (0..features.len()).collect();
+    order.sort_by_key(|idx| features[*idx].feature_tag);
+    let feature_map: BTreeMap<_, _> = order
+        .iter()
+        .enumerate()
+        .map(|(new, old)| (*old as u16, new as u16))
+        .collect();
+    let mut features: Vec<Option<FeatureRecord>> = features.into_iter().map(Some).collect();
+    let features = order
+        .iter()
+        .filter_map(|idx| features[*idx].take())
+        .collect();
+
+    let script_records = scripts
+        .into_iter()
+        .map(|(tag, mut script)| {
+            let lang_systems = script
+                .default
+                .iter_mut()
+                .chain(script.languages.values_mut());
+            for lang_sys in lang_systems {
+                remap_lang_sys(lang_sys, &feature_map);
+                lang_sys.feature_indices.sort_unstable();
+                lang_sys.feature_indices.dedup();
+            }
+            let lang_sys_records = script
+                .languages
+                .into_iter()
+                .map(|(tag, lang_sys)| LangSysRecord::new(tag, lang_sys))
+                .collect();
+            ScriptRecord::new(tag, Script::new(script.default, lang_sys_records))
+        })
+        .collect();
+    Ok(Some(LayoutParts {
+        script_list: ScriptList::new(script_records),
+        feature_list: FeatureList::new(features),
+        lookups,
+    }))
+}
+
+/// Returns the map from the indices of `len` items to their indices after
+/// `start` existing items, or `None` if they would not fit in 16 bits.
+fn index_map(start: usize, len: usize) -> Option<BTreeMap<u16, u16>> {
+    if start + len > u16::MAX as usize {
+        return None;
+    }
+    Some(
+        (0..len)
+            .map(|idx| (idx as u16, (start + idx) as u16))
+            .collect(),
+    )
+}
+
+impl MergedScript {
+    /// Combines the language systems of another font with those of this
+    /// script.
+    ///
+    /// A language system that only one of the fonts has is combined with the
+    /// default language system of the other.
+    fn merge(&mut self, other: MergedScript) {
+        let tags: BTreeSet<_> = self
+            .languages
+            .keys()
+            .chain(other.languages.keys())
+            .copied()
+            .collect();
+        let languages = tags
+            .into_iter()
+            .filter_map(|tag| {
+                let ours = self.languages.get(&tag).or(self.default.as_ref());
+                let theirs = other.languages.get(&tag).or(other.default.as_ref());
+                Some((tag, merge_lang_sys(ours, theirs)?))
+            })
+            .collect();
+        self.default = merge_lang_sys(self.default.as_ref(), other.default.as_ref());
+        self.languages = languages;
+    }
+}
+
+/// Combines the features of two language systems.
+///
+/// Only one feature can be required, so that of the second language system
+/// becomes optional if the first also has one.
+fn merge_lang_sys(first: Option<&LangSys>, second: Option<&LangSys>) -> Option<LangSys> {
+    let mut merged = first.or(second)?.clone();
+    if let (Some(_), Some(second)) = (first, second) {
+        merged
+            .feature_indices
+            .extend_from_slice(&second.feature_indices);
+        match (merged.required_feature_index, second.required_feature_index) {
+            (_, 0xFFFF) => (),
+            (0xFFFF, required) => merged.required_feature_index = required,
+            (_, required) => merged.feature_indices.push(required),
+        }
+    }
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::tables::layout::LookupFlag;
+
+    use super::*;
+    use crate::tables::{
+        gsub::{SingleSubst, SingleSubstFormat1, SubstitutionLookup},
+        layout::{Feature, Lookup},
+    };
+
+    fn lookup(gid: u16, lookup_flag: LookupFlag, mark_filtering_set: u16) -> SubstitutionLookup {
+        let coverage = [GlyphId::new(gid)].into_iter().collect();
+        SubstitutionLookup::Single(Lookup::new(
+            lookup_flag,
+            vec![SingleSubst::Format1(SingleSubstFormat1::new(coverage, 1))],
+            mark_filtering_set,
+        ))
+    }
+
+    /// A feature with its lookup indices, or a language system with its
+    /// feature indices.
+    type Indices<'a> = (&'a [u8; 4], &'a [u16]);
+
+    fn parts(
+        lookups: Vec<SubstitutionLookup>,
+        features: &[Indices],
+        scripts: &[(&[u8; 4], &[u16], &[Indices])],
+    ) -> LayoutParts<SubstitutionLookup> {
+        let features = features
+            .iter()
+            .map(|(tag, lookups)| {
+                FeatureRecord::new(Tag::new(tag), Feature::new(None, lookups.to_vec()))
+            })
+            .collect();
+        let scripts = scripts
+            .iter()
+            .map(|(tag, default, languages)| {
+                let languages = languages
+                    .iter()
+                    .map(|(tag, features)| {
+                        LangSysRecord::new(Tag::new(tag), LangSys::new(features.to_vec()))
+                    })
+                    .collect();
+                let default = LangSys::new(default.to_vec());
+                ScriptRecord::new(Tag::new(tag), Script::new(Some(default), languages))
+            })
+            .collect();
+        LayoutParts {
+            script_list: ScriptList::new(scripts),
+            feature_list: FeatureList::new(features),
+            lookups,
+        }
+    }
+
+    fn class_def(classes: &[(u16, u16)]) -> ClassDef {
+        classes
+            .iter()
+            .map(|(gid, class)| (GlyphId::new(*gid), *class))
+            .collect()
+    }
+
+    fn mark_gdef(classes: &[(u16, u16)], sets: &[u16]) -> Gdef {
+        let mut gdef = Gdef::new(None, None, None, Some(class_def(classes)));
+        let coverages = sets
+            .iter()
+            .map(|gid| [GlyphId::new(*gid)].into_iter().collect())
+            .collect();
+        gdef.mark_glyph_sets_def = MarkGlyphSets::new(coverages).into();
+        gdef
+    }
+
+    #[test]
+    fn mark_classes_and_sets_are_appended() {
+        let gdefs = vec![
+            Some(mark_gdef(&[(5, 1)], &[5])),
+            None,
+            Some(mark_gdef(&[(20, 1), (21, 2)], &[20, 21])),
+        ];
+        let (gdef, offsets) = merge_gdef(gdefs).unwrap();
+        let gdef = gdef.unwrap();
+        assert_eq!(
+            offsets,
+            [
+                MarkOffsets::default(),
+                MarkOffsets {
+                    classes: 1,
+                    sets: 1
+                },
+                MarkOffsets {
+                    classes: 1,
+                    sets: 1
+                },
+            ]
+        );
+        assert_eq!(
+            gdef.mark_attach_class_def
+                .as_ref()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            class_def(&[(5, 1), (20, 2), (21, 3)])
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            gdef.mark_glyph_sets_def.as_ref().unwrap().coverages.len(),
+            3
+        );
+    }
+
+    #[test]
+    fn lookups_features_and_scripts_are_combined() {
+        let first = parts(
+            vec![lookup(1, LookupFlag::empty(), 0)],
+            &[(b"liga", &[0])],
+            &[(b"latn", &[0], &[])],
+        );
+        let mut flag = LookupFlag::empty();
+        flag.set_use_mark_filtering_set(true);
+        flag.set_mark_attachment_type(2);
+        let second = parts(
+            vec![lookup(10, LookupFlag::empty(), 0), lookup(11, flag, 1)],
+            &[(b"calt", &[0]), (b"liga", &[1])],
+            &[(b"hani", &[0], &[]), (b"latn", &[0, 1], &[(b"TRK ", &[1])])],
+        );
+        let offsets = [
+            MarkOffsets::default(),
+            MarkOffsets {
+                classes: 1,
+                sets: 2,
+            },
+        ];
+        let merged = merge_layout(vec![Some(first), Some(second)], &offsets)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(merged.lookups.len(), 3);
+        let SubstitutionLookup::Single(lookup) = &merged.lookups[2] else {
+            panic!("wrong lookup type");
+        };
+        assert_eq!(lookup.lookup_flag.mark_attachment_type_mask(), Some(3));
+        assert_eq!(lookup.mark_filtering_set, 3);
+
+        // features are sorted by tag: calt, liga (first font), liga (second)
+        let features: Vec<_> = merged
+            .feature_list
+            .feature_records
+            .iter()
+            .map(|record| {
+                (
+                    record.feature_tag,
+                    record.feature.lookup_list_indices.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            features,
+            [
+                (Tag::new(b"calt"), vec![1]),
+                (Tag::new(b"liga"), vec![0]),
+                (Tag::new(b"liga"), vec![2]),
+            ]
+        );
+
+        let scripts: Vec<_> = merged
+            .script_list
+            .script_records
+            .iter()
+            .map(|record| {
+                let languages: Vec<_> = record
+                    .script
+                    .lang_sys_records
+                    .iter()
+                    .map(|lang| (lang.lang_sys_tag, lang.lang_sys.feature_indices.clone()))
+                    .collect();
+                let default = record.script.default_lang_sys.as_ref().unwrap();
+                (
+                    record.script_tag,
+                    default.feature_indices.clone(),
+                    languages,
+                )
+            })
+            .collect();
+        assert_eq!(
+            scripts,
+            [
+                (Tag::new(b"hani"), vec![0], vec![]),
+                (
+                    Tag::new(b"latn"),
+                    vec![0, 1, 2],
+                    // the first font has no TRK language system, so its
+                    // default is used
+                    vec![(Tag::new(b"TRK "), vec![1, 2])]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn required_features_are_kept() {
+        let mut first = LangSys::new(vec![0]);
+        first.required_feature_index = 1;
+        let mut second = LangSys::new(vec![2]);
+        second.required_feature_index = 3;
+        let merged = merge_lang_sys(Some(&first), Some(&second)).unwrap();
+        assert_eq!(merged.required_feature_index, 1);
+        assert_eq!(merged.feature_indices, [0, 2, 3]);
+        let merged = merge_lang_sys(None, Some(&second)).unwrap();
+        assert_eq!(merged, second);
+        assert!(merge_lang_sys(None, None).is_none());
+    }
+}
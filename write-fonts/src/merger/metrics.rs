@@ -0,0 +1,170 @@
+//! Merging glyph metrics.
+
+use read_fonts::{FontRef, TableProvider, TopLevelTable};
+
+use super::{optional, MergeError, MetricsPolicy, Plan};
+use crate::{
+    from_obj::ToOwnedTable,
+    tables::{
+        hhea::Hhea,
+        hmtx::{expand_metrics, pack_metrics, Hmtx, LongMetric},
+        vhea::Vhea,
+        vmtx::Vmtx,
+    },
+    FontBuilder, OtRound,
+};
+
+/// Merges `hhea`/`hmtx` and `vhea`/`vmtx`, returning the average of the
+/// non-zero advance widths.
+///
+/// Horizontal metrics are only written if the first font has them, in which
+/// case every font must have them. Vertical metrics are only written if
+/// every font has them.
+pub(super) fn merge(plan: &Plan, builder: &mut FontBuilder) -> Result<Option<i16>, MergeError> {
+    let average_advance = merge_horizontal(plan, builder)?;
+    merge_vertical(plan, builder)?;
+    Ok(average_advance)
+}
+
+fn merge_horizontal(plan: &Plan, builder: &mut FontBuilder) -> Result<Option<i16>, MergeError> {
+    let mut headers = Vec::new();
+    let mut metrics = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        match horizontal_metrics(i, font, plan.glyph_counts[i])? {
+            Some((hhea, hmtx)) => {
+                headers.push(hhea);
+                metrics.push(hmtx);
+            }
+            None if i == 0 => return Ok(None),
+            None => {
+                return Err(MergeError::Unsupported {
+                    font: i,
+                    reason: "fonts without horizontal metrics",
+                })
+            }
+        }
+    }
+    let metrics = concat_metrics(plan, &metrics);
+    let mut headers = headers.into_iter();
+    let mut hhea = headers.next().ok_or(MergeError::NoFonts)?;
+    for header in headers {
+        hhea.min_left_side_bearing = hhea.min_left_side_bearing.min(header.min_left_side_bearing);
+        hhea.min_right_side_bearing = hhea
+            .min_right_side_bearing
+            .min(header.min_right_side_bearing);
+        hhea.x_max_extent = hhea.x_max_extent.max(header.x_max_extent);
+        if plan.options.metrics == MetricsPolicy::Max {
+            hhea.ascender = hhea.ascender.max(header.ascender);
+            hhea.descender = hhea.descender.min(header.descender);
+            hhea.line_gap = hhea.line_gap.max(header.line_gap);
+        }
+    }
+    hhea.advance_width_max = metrics
+        .iter()
+        .map(|metric| metric.advance)
+        .max()
+        .unwrap_or_default()
+        .into();
+    let advances: Vec<_> = metrics
+        .iter()
+        .map(|metric| metric.advance as f64)
+        .filter(|advance| *advance != 0.0)
+        .collect();
+    let average_advance = (!advances.is_empty())
+        .then(|| (advances.iter().sum::<f64>() / advances.len() as f64).ot_round());
+
+    let (long, bearings) = pack_metrics(metrics);
+    hhea.number_of_long_metrics = long.len() as u16;
+    builder
+        .add_table(&Hmtx::new(long, bearings))?
+        .add_table(&hhea)?;
+    Ok(average_advance)
+}
+
+fn merge_vertical(plan: &Plan, builder: &mut FontBuilder) -> Result<(), MergeError> {
+    let mut headers = Vec::new();
+    let mut metrics = Vec::new();
+    for (i, font) in plan.fonts.iter().enumerate() {
+        let Some((vhea, vmtx)) = vertical_metrics(i, font, plan.glyph_counts[i])? else {
+            return Ok(());
+        };
+        headers.push(vhea);
+        metrics.push(vmtx);
+    }
+    let metrics = concat_metrics(plan, &metrics);
+    let mut headers = headers.into_iter();
+    let mut vhea = headers.next().ok_or(MergeError::NoFonts)?;
+    for header in headers {
+        vhea.min_top_side_bearing = vhea.min_top_side_bearing.min(header.min_top_side_bearing);
+        vhea.min_bottom_side_bearing = vhea
+            .min_bottom_side_bearing
+            .min(header.min_bottom_side_bearing);
+        vhea.y_max_extent = vhea.y_max_extent.max(header.y_max_extent);
+        if plan.options.metrics == MetricsPolicy::Max {
+            vhea.ascender = vhea.ascender.max(header.ascender);
+            vhea.descender = vhea.descender.min(header.descender);
+            vhea.line_gap = vhea.line_gap.max(header.line_gap);
+        }
+    }
+    vhea.advance_height_max = metrics
+        .iter()
+        .map(|metric| metric.advance)
+        .max()
+        .unwrap_or_default()
+        .into();
+    let (long, bearings) = pack_metrics(metrics);
+    vhea.number_of_long_ver_metrics = long.len() as u16;
+    builder
+        .add_table(&Vmtx::new(long, bearings))?
+        .add_table(&vhea)?;
+    Ok(())
+}
+
+fn horizontal_metrics(
+    i: usize,
+    font: &FontRef,
+    num_glyphs: u16,
+) -> Result<Option<(Hhea, Vec<LongMetric>)>, MergeError> {
+    let Some(hhea) = optional(i, Hhea::TAG, font.hhea())? else {
+        return Ok(None);
+    };
+    let Some(hmtx) = optional(i, Hmtx::TAG, font.hmtx())? else {
+        return Ok(None);
+    };
+    let metrics = expand_metrics(
+        hmtx.h_metrics()
+            .iter()
+            .map(|metric| (metric.advance(), metric.side_bearing())),
+        hmtx.left_side_bearings().iter().map(|lsb| lsb.get()),
+        num_glyphs as usize,
+    );
+    Ok(Some((hhea.to_owned_table(), metrics)))
+}
+
+fn vertical_metrics(
+    i: usize,
+    font: &FontRef,
+    num_glyphs: u16,
+) -> Result<Option<(Vhea, Vec<LongMetric>)>, MergeError> {
+    let Some(vhea) = optional(i, Vhea::TAG, font.vhea())? else {
+        return Ok(None);
+    };
+    let Some(vmtx) = optional(i, Vmtx::TAG, font.vmtx())? else {
+        return Ok(None);
+    };
+    let metrics = expand_metrics(
+        vmtx.v_metrics()
+            .iter()
+            .map(|metric| (metric.advance(), metric.side_bearing())),
+        vmtx.top_side_bearings().iter().map(|tsb| tsb.get()),
+        num_glyphs as usize,
+    );
+    Ok(Some((vhea.to_owned_table(), metrics)))
+}
+
+/// Arranges the metrics of each font in merged glyph order.
+fn concat_metrics(plan: &Plan, metrics: &[Vec<LongMetric>]) -> Vec<LongMetric> {
+    plan.source_glyphs()
+        .map(|(i, gid)| metrics[i][gid.to_u16() as usize].clone())
+        .collect()
+}
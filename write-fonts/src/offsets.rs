@@ -15,10 +15,13 @@ pub const WIDTH_32: usize = 4;
 /// An offset subtable.
 ///
 /// The generic const `N` is the width of the offset, in bytes.
+///
+/// The subtable is boxed, so that tables can contain offsets to tables of
+/// their own type (as with the paint graph in `COLR`).
 #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OffsetMarker<T, const N: usize = 2> {
-    obj: T,
+    obj: Box<T>,
 }
 
 /// An offset subtable which may be null.
@@ -74,17 +77,17 @@ impl<T, const N: usize> AsMut<T> for OffsetMarker<T, N> {
 impl<const N: usize, T> OffsetMarker<T, N> {
     /// Create a new marker.
     pub fn new(obj: T) -> Self {
-        OffsetMarker { obj }
+        OffsetMarker { obj: Box::new(obj) }
     }
 
     /// Set the contents of the marker, replacing any existing contents.
     pub fn set(&mut self, obj: impl Into<T>) {
-        self.obj = obj.into();
+        *self.obj = obj.into();
     }
 
     /// Convert into the inner type
     pub fn into_inner(self) -> T {
-        self.obj
+        *self.obj
     }
 }
 
@@ -117,7 +120,7 @@ impl<const N: usize, T> NullableOffsetMarker<T, N> {
 
 impl<const N: usize, T: FontWrite> FontWrite for OffsetMarker<T, N> {
     fn write_into(&self, writer: &mut TableWriter) {
-        writer.write_offset(&self.obj, N);
+        writer.write_offset(self.obj.as_ref(), N);
     }
 
     fn table_type(&self) -> crate::table_type::TableType {
@@ -0,0 +1,36 @@
+//! Reading and patching big-endian integers in raw table data.
+//!
+//! These are used by the subsetter and the instancer for the tables that
+//! they rewrite at the level of their binary structures.
+
+use read_fonts::ReadError;
+
+pub(crate) fn read_u8(data: &[u8], pos: usize) -> Result<u8, ReadError> {
+    data.get(pos).copied().ok_or(ReadError::OutOfBounds)
+}
+
+pub(crate) fn read_u16(data: &[u8], pos: usize) -> Result<u16, ReadError> {
+    data.get(pos..pos + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or(ReadError::OutOfBounds)
+}
+
+pub(crate) fn read_u24(data: &[u8], pos: usize) -> Result<usize, ReadError> {
+    data.get(pos..pos + 3)
+        .map(|bytes| u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize)
+        .ok_or(ReadError::OutOfBounds)
+}
+
+pub(crate) fn read_u32(data: &[u8], pos: usize) -> Result<u32, ReadError> {
+    data.get(pos..pos + 4)
+        .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or(ReadError::OutOfBounds)
+}
+
+pub(crate) fn write_u16(data: &mut [u8], pos: usize, value: u16) {
+    data[pos..pos + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+pub(crate) fn write_u32(data: &mut [u8], pos: usize, value: u32) {
+    data[pos..pos + 4].copy_from_slice(&value.to_be_bytes());
+}
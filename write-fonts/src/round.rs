@@ -28,6 +28,13 @@ impl OtRound<i16> for f32 {
     }
 }
 
+impl OtRound<i32> for f64 {
+    #[inline]
+    fn ot_round(self) -> i32 {
+        (self + 0.5).floor() as i32
+    }
+}
+
 impl OtRound<u16> for f64 {
     #[inline]
     fn ot_round(self) -> u16 {
@@ -0,0 +1,671 @@
+//! Subsetting fonts.
+//!
+//! Given a font and a set of codepoints and glyph identifiers, this computes
+//! the set of glyphs needed to render them (the glyph closure), renumbers the
+//! retained glyphs and rewrites every table that refers to glyphs.
+//!
+//! The closure follows the character map (including Unicode variation
+//! sequences), GSUB substitutions, COLR layers and paints, composite glyphs,
+//! CFF `seac` accents and composite bitmaps. Glyph 0 (`.notdef`) is always
+//! retained, and retained glyphs keep their relative order.
+//!
+//! Layout lookups that are not reachable from a retained feature, or that
+//! become empty, are removed, as are features without lookups and name
+//! records that are no longer referenced.
+//!
+//! Tables that refer to glyphs but that are not supported by the subsetter
+//! (such as `kern`, `hdmx`, `MATH` or `SVG `) are dropped. All other tables are
+//! copied unchanged.
+
+mod bitmap;
+pub(crate) mod cff;
+pub(crate) mod cmap;
+pub(crate) mod colr;
+pub(crate) mod glyf;
+pub(crate) mod layout;
+mod metrics;
+mod variations;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use read_fonts::{
+    tables::{base::Base as ReadBase, cpal::Cpal, post::Post as ReadPost},
+    FontData, FontRead, FontRef, ReadError, TableProvider, TopLevelTable,
+};
+use types::{GlyphId, NameId, Tag, Version16Dot16};
+
+use crate::{
+    from_obj::ToOwnedTable,
+    tables::{
+        base::{Base, BaseCoord, BaseCoordFormat1, MinMax},
+        head::Head,
+        maxp::Maxp,
+        name::Name,
+        os2::Os2,
+        post::Post,
+    },
+    BuilderError, FontBuilder,
+};
+
+/// Name identifiers that are always retained.
+const DEFAULT_NAME_IDS: std::ops::RangeInclusive<u16> = 0..=6;
+
+/// Tables that refer to glyphs but are not supported, and so are dropped.
+const DROPPED_TABLES: &[Tag] = &[
+    Tag::new(b"DSIG"),
+    Tag::new(b"JSTF"),
+    Tag::new(b"LTSH"),
+    Tag::new(b"MATH"),
+    Tag::new(b"SVG "),
+    Tag::new(b"VARC"),
+    Tag::new(b"hdmx"),
+    Tag::new(b"kern"),
+    Tag::new(b"kerx"),
+    Tag::new(b"morx"),
+];
+
+/// Tables that are rewritten (or dropped) by the subsetter.
+const SUBSET_TABLES: &[Tag] = &[
+    Tag::new(b"BASE"),
+    Tag::new(b"CBDT"),
+    Tag::new(b"CBLC"),
+    Tag::new(b"CFF "),
+    Tag::new(b"CFF2"),
+    Tag::new(b"COLR"),
+    Tag::new(b"CPAL"),
+    Tag::new(b"EBDT"),
+    Tag::new(b"EBLC"),
+    Tag::new(b"GDEF"),
+    Tag::new(b"GPOS"),
+    Tag::new(b"GSUB"),
+    Tag::new(b"HVAR"),
+    Tag::new(b"OS/2"),
+    Tag::new(b"VORG"),
+    Tag::new(b"VVAR"),
+    Tag::new(b"cmap"),
+    Tag::new(b"glyf"),
+    Tag::new(b"gvar"),
+    Tag::new(b"head"),
+    Tag::new(b"hhea"),
+    Tag::new(b"hmtx"),
+    Tag::new(b"loca"),
+    Tag::new(b"maxp"),
+    Tag::new(b"name"),
+    Tag::new(b"post"),
+    Tag::new(b"sbix"),
+    Tag::new(b"vhea"),
+    Tag::new(b"vmtx"),
+];
+
+/// The codepoints, glyphs and other items to retain when subsetting.
+#[derive(Clone, Debug, Default)]
+pub struct SubsetInput {
+    unicodes: BTreeSet<u32>,
+    glyph_ids: BTreeSet<GlyphId>,
+    name_ids: BTreeSet<NameId>,
+    layout_features: Option<BTreeSet<Tag>>,
+}
+
+/// An error that occurs while subsetting a font.
+#[derive(Clone, Debug)]
+pub enum SubsetError {
+    /// A table in the source font could not be read.
+    Read { tag: Tag, inner: ReadError },
+    /// A subset table could not be compiled.
+    Write(BuilderError),
+}
+
+impl SubsetInput {
+    /// Create a new, empty input.
+    ///
+    /// Subsetting with an empty input retains only the `.notdef` glyph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retain the glyphs for these codepoints, as well as the codepoints
+    /// themselves in the character map.
+    pub fn add_unicodes(&mut self, unicodes: impl IntoIterator<Item = u32>) -> &mut Self {
+        self.unicodes.extend(unicodes);
+        self
+    }
+
+    /// Retain these glyphs, as well as any codepoints that map to them.
+    pub fn add_glyph_ids(&mut self, glyph_ids: impl IntoIterator<Item = GlyphId>) -> &mut Self {
+        self.glyph_ids.extend(glyph_ids);
+        self
+    }
+
+    /// Retain these name records, in addition to those referenced by the
+    /// font and the default name identifiers 0 to 6.
+    pub fn add_name_ids(&mut self, name_ids: impl IntoIterator<Item = NameId>) -> &mut Self {
+        self.name_ids.extend(name_ids);
+        self
+    }
+
+    /// Retain only these GSUB and GPOS features.
+    ///
+    /// By default, all features are retained.
+    pub fn set_layout_features(&mut self, features: impl IntoIterator<Item = Tag>) -> &mut Self {
+        self.layout_features = Some(features.into_iter().collect());
+        self
+    }
+}
+
+/// The mapping from glyph identifiers in the source font to those in the
+/// subset font.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GlyphMap {
+    old_to_new: BTreeMap<GlyphId, GlyphId>,
+    new_to_old: Vec<GlyphId>,
+}
+
+impl GlyphMap {
+    fn new(glyphs: &BTreeSet<GlyphId>) -> Self {
+        let new_to_old: Vec<_> = glyphs.iter().copied().collect();
+        let old_to_new = new_to_old
+            .iter()
+            .enumerate()
+            .map(|(new, old)| (*old, GlyphId::new(new as u16)))
+            .collect();
+        Self {
+            old_to_new,
+            new_to_old,
+        }
+    }
+
+    /// Creates a map from `(old, new)` pairs.
+    ///
+    /// Unlike a subset, the new identifiers need not be contiguous, as when
+    /// the glyphs of a font are moved into a larger font.
+    pub(crate) fn from_pairs(pairs: impl IntoIterator<Item = (GlyphId, GlyphId)>) -> Self {
+        let old_to_new: BTreeMap<_, _> = pairs.into_iter().collect();
+        let mut by_new: Vec<_> = old_to_new.iter().map(|(old, new)| (*new, *old)).collect();
+        by_new.sort();
+        Self {
+            old_to_new,
+            new_to_old: by_new.into_iter().map(|(_, old)| old).collect(),
+        }
+    }
+
+    /// Returns the new identifier of a glyph, or `None` if it is not retained.
+    pub(crate) fn get(&self, old: GlyphId) -> Option<GlyphId> {
+        self.old_to_new.get(&old).copied()
+    }
+
+    /// The number of glyphs in the subset font.
+    fn len(&self) -> usize {
+        self.new_to_old.len()
+    }
+
+    /// The source identifiers of the retained glyphs, in new glyph order.
+    fn old_glyphs(&self) -> impl Iterator<Item = GlyphId> + '_ {
+        self.new_to_old.iter().copied()
+    }
+
+    /// Iterates over `(old, new)` pairs of retained glyphs.
+    #[cfg(test)]
+    fn iter(&self) -> impl Iterator<Item = (GlyphId, GlyphId)> + '_ {
+        self.old_to_new.iter().map(|(old, new)| (*old, *new))
+    }
+}
+
+/// The state shared by the individual table subsetters.
+struct Plan<'a> {
+    font: FontRef<'a>,
+    glyph_map: GlyphMap,
+    /// Retained codepoints, with their glyphs in the source font.
+    unicodes: BTreeMap<u32, GlyphId>,
+}
+
+/// Subset a font, returning the binary data of the new font.
+pub fn subset_font(font: &FontRef, input: &SubsetInput) -> Result<Vec<u8>, SubsetError> {
+    let maxp = font.maxp().map_err(read_error(Maxp::TAG))?;
+    let num_glyphs = maxp.num_glyphs();
+
+    let mut glyphs: BTreeSet<_> = input.glyph_ids.iter().copied().collect();
+    glyphs.insert(GlyphId::NOTDEF);
+    let unicodes = cmap::closure(font, input, &mut glyphs).map_err(read_error(CMAP))?;
+
+    let gsub = optional(GSUB, font.gsub())?.map(|gsub| gsub.to_owned_table());
+    if let Some(gsub) = &gsub {
+        layout::gsub_closure(gsub, input.layout_features.as_ref(), &mut glyphs);
+    }
+    let colr = optional(COLR, font.colr())?.map(|colr| colr.to_owned_table());
+    if let Some(colr) = &colr {
+        colr::closure(colr, &mut glyphs);
+    }
+    glyf::closure(font, &mut glyphs).map_err(read_error(GLYF))?;
+    cff::closure(font, &mut glyphs).map_err(read_error(CFF))?;
+    bitmap::closure(font, &mut glyphs)?;
+    glyphs.retain(|gid| gid.to_u16() < num_glyphs);
+
+    let plan = Plan {
+        font: font.clone(),
+        glyph_map: GlyphMap::new(&glyphs),
+        unicodes: unicodes
+            .into_iter()
+            .filter(|(_, gid)| glyphs.contains(gid))
+            .collect(),
+    };
+
+    let mut builder = FontBuilder::new();
+    let mut maxp: Maxp = maxp.to_owned_table();
+    maxp.num_glyphs = plan.glyph_map.len() as u16;
+    builder.add_table(&maxp)?;
+
+    let loca_format = glyf::subset(&plan, &mut builder)?;
+    if let Some(head) = optional(Head::TAG, font.head())? {
+        let mut head: Head = head.to_owned_table();
+        if let Some(format) = loca_format {
+            head.index_to_loc_format = format as i16;
+        }
+        builder.add_table(&head)?;
+    }
+    cff::subset(&plan, &mut builder)?;
+    metrics::subset(&plan, &mut builder)?;
+    variations::subset(&plan, &mut builder)?;
+    bitmap::subset(&plan, &mut builder)?;
+    if let Some(cmap) = cmap::subset(&plan).map_err(read_error(CMAP))? {
+        builder.add_table(&cmap)?;
+    }
+    if let Some(os2) = optional(Os2::TAG, font.os2())? {
+        builder.add_table(&subset_os2(os2.to_owned_table(), &plan))?;
+    }
+    if let Some(post) = optional(Post::TAG, font.post())? {
+        builder.add_table(&subset_post(&post, &plan))?;
+    }
+    if let Some(base) = optional(Base::TAG, font.expect_table::<ReadBase>())? {
+        builder.add_table(&subset_base(base.to_owned_table(), &plan))?;
+    }
+
+    let mut name_ids: BTreeSet<_> = DEFAULT_NAME_IDS.map(NameId::new).collect();
+    name_ids.extend(input.name_ids.iter().copied());
+    if let Some(gsub) = gsub
+        .and_then(|gsub| layout::subset_gsub(gsub, &plan.glyph_map, input.layout_features.as_ref()))
+    {
+        name_ids.extend(layout::feature_name_ids(&gsub.feature_list));
+        builder.add_table(&gsub)?;
+    }
+    if let Some(gpos) = optional(GPOS, font.gpos())?.and_then(|gpos| {
+        layout::subset_gpos(
+            gpos.to_owned_table(),
+            &plan.glyph_map,
+            input.layout_features.as_ref(),
+        )
+    }) {
+        name_ids.extend(layout::feature_name_ids(&gpos.feature_list));
+        builder.add_table(&gpos)?;
+    }
+    if let Some(gdef) = optional(GDEF, font.gdef())? {
+        builder.add_table(&layout::subset_gdef(gdef.to_owned_table(), &plan.glyph_map))?;
+    }
+    let color_tables = match &colr {
+        Some(colr) => colr::subset(colr, &plan).map_err(read_error(CPAL))?,
+        None => None,
+    };
+    if let Some((colr, cpal)) = color_tables {
+        builder.add_table(&colr)?;
+        if let Some(cpal) = cpal {
+            name_ids.extend(cpal_name_ids(&cpal));
+            builder.add_raw(CPAL, cpal);
+        }
+    } else if font.colr().is_err() {
+        if let Some(cpal) = font.data_for_tag(CPAL) {
+            name_ids.extend(cpal_name_ids(cpal.as_bytes()));
+        }
+    }
+    name_ids.extend(referenced_name_ids(font));
+    if let Some(name) = optional(Name::TAG, font.name())? {
+        let mut name: Name = name.to_owned_table();
+        name.name_record
+            .retain(|record| name_ids.contains(&record.name_id));
+        builder.add_table(&name)?;
+    }
+
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        if builder.contains(tag) || SUBSET_TABLES.contains(&tag) || DROPPED_TABLES.contains(&tag) {
+            continue;
+        }
+        // CPAL is only rewritten along with COLR
+        if tag == CPAL && font.colr().is_ok() {
+            continue;
+        }
+        if let Some(data) = font.data_for_tag(tag) {
+            builder.add_raw(tag, data.as_bytes());
+        }
+    }
+    Ok(builder.build())
+}
+
+const CFF: Tag = Tag::new(b"CFF ");
+const CMAP: Tag = Tag::new(b"cmap");
+const COLR: Tag = Tag::new(b"COLR");
+const CPAL: Tag = Tag::new(b"CPAL");
+const GDEF: Tag = Tag::new(b"GDEF");
+const GLYF: Tag = Tag::new(b"glyf");
+const GPOS: Tag = Tag::new(b"GPOS");
+const GSUB: Tag = Tag::new(b"GSUB");
+
+fn read_error(tag: Tag) -> impl FnOnce(ReadError) -> SubsetError {
+    move |inner| SubsetError::Read { tag, inner }
+}
+
+/// Converts a missing table into `None`, and other errors into a
+/// [`SubsetError`].
+fn optional<T>(tag: Tag, table: Result<T, ReadError>) -> Result<Option<T>, SubsetError> {
+    match table {
+        Ok(table) => Ok(Some(table)),
+        Err(ReadError::TableIsMissing(_)) => Ok(None),
+        Err(inner) => Err(SubsetError::Read { tag, inner }),
+    }
+}
+
+fn subset_os2(mut os2: Os2, plan: &Plan) -> Os2 {
+    let first = plan.unicodes.keys().next();
+    let last = plan.unicodes.keys().next_back();
+    if let (Some(first), Some(last)) = (first, last) {
+        os2.us_first_char_index = (*first).min(0xFFFF) as u16;
+        os2.us_last_char_index = (*last).min(0xFFFF) as u16;
+    }
+    os2
+}
+
+/// Format 2 coordinates refer to a glyph; they are renumbered, or replaced
+/// by a plain coordinate if the glyph was removed.
+fn subset_base(mut base: Base, plan: &Plan) -> Base {
+    let remap = |coord: &mut BaseCoord| {
+        if let BaseCoord::Format2(format2) = coord {
+            match plan.glyph_map.get(GlyphId::new(format2.reference_glyph)) {
+                Some(new) => format2.reference_glyph = new.to_u16(),
+                None => *coord = BaseCoord::Format1(BaseCoordFormat1::new(format2.coordinate)),
+            }
+        }
+    };
+    let remap_min_max = |min_max: &mut MinMax| {
+        let records = min_max.feat_min_max_records.iter_mut();
+        let feature_coords =
+            records.flat_map(|record| [&mut record.min_coord, &mut record.max_coord]);
+        [&mut min_max.min_coord, &mut min_max.max_coord]
+            .into_iter()
+            .chain(feature_coords)
+            .filter_map(|coord| coord.as_mut())
+            .for_each(remap);
+    };
+    let axes = [base.horiz_axis.as_mut(), base.vert_axis.as_mut()];
+    for axis in axes.into_iter().flatten() {
+        for record in axis.base_script_list.base_script_records.iter_mut() {
+            let script = record.base_script.as_mut();
+            if let Some(values) = script.base_values.as_mut() {
+                values
+                    .base_coords
+                    .iter_mut()
+                    .map(AsMut::as_mut)
+                    .for_each(remap);
+            }
+            if let Some(min_max) = script.default_min_max.as_mut() {
+                remap_min_max(min_max);
+            }
+            for lang_sys in script.base_lang_sys_records.iter_mut() {
+                remap_min_max(lang_sys.min_max.as_mut());
+            }
+        }
+    }
+    base
+}
+
+/// Glyph names are only stored in version 1.0 and 2.0 tables; any other
+/// version is copied unchanged.
+fn subset_post(post: &ReadPost, plan: &Plan) -> Post {
+    let owned: Post = post.to_owned_table();
+    if ![Version16Dot16::VERSION_1_0, Version16Dot16::VERSION_2_0].contains(&post.version()) {
+        return owned;
+    }
+    let names: Vec<_> = plan
+        .glyph_map
+        .old_glyphs()
+        .map(|gid| match post.glyph_name(gid) {
+            Some(name) => name.to_owned(),
+            None => format!("glyph{}", gid.to_u16()),
+        })
+        .collect();
+    Post {
+        italic_angle: owned.italic_angle,
+        underline_position: owned.underline_position,
+        underline_thickness: owned.underline_thickness,
+        is_fixed_pitch: owned.is_fixed_pitch,
+        min_mem_type42: owned.min_mem_type42,
+        max_mem_type42: owned.max_mem_type42,
+        min_mem_type1: owned.min_mem_type1,
+        max_mem_type1: owned.max_mem_type1,
+        ..Post::new_v2(names.iter().map(String::as_str))
+    }
+}
+
+/// Name identifiers referenced by tables that are copied unchanged.
+fn referenced_name_ids(font: &FontRef) -> Vec<NameId> {
+    let mut ids = Vec::new();
+    if let Ok(fvar) = font.fvar() {
+        if let Ok(axes) = fvar.axes() {
+            ids.extend(axes.iter().map(|axis| axis.axis_name_id()));
+        }
+        if let Ok(instances) = fvar.instances() {
+            for instance in instances.iter().flatten() {
+                ids.push(instance.subfamily_name_id);
+                ids.extend(instance.post_script_name_id);
+            }
+        }
+    }
+    if let Ok(stat) = font.stat() {
+        ids.extend(stat.elided_fallback_name_id());
+        if let Ok(axes) = stat.design_axes() {
+            ids.extend(axes.iter().map(|axis| axis.axis_name_id()));
+        }
+        if let Ok(values) = stat.offset_to_axis_values() {
+            use read_fonts::tables::stat::AxisValue;
+            ids.extend(
+                values
+                    .axis_values()
+                    .iter()
+                    .flatten()
+                    .map(|value| match value {
+                        AxisValue::Format1(value) => value.value_name_id(),
+                        AxisValue::Format2(value) => value.value_name_id(),
+                        AxisValue::Format3(value) => value.value_name_id(),
+                        AxisValue::Format4(value) => value.value_name_id(),
+                    }),
+            );
+        }
+    }
+    ids
+}
+
+fn cpal_name_ids(cpal: &[u8]) -> Vec<NameId> {
+    let Ok(cpal) = Cpal::read(FontData::new(cpal)) else {
+        return Vec::new();
+    };
+    let labels = cpal
+        .palette_labels_array()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+        .iter()
+        .map(|id| NameId::new(id.get()));
+    let entry_labels = cpal
+        .palette_entry_labels_array()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+        .iter()
+        .map(|id| id.get());
+    labels
+        .chain(entry_labels)
+        .filter(|id| id.to_u16() != 0xFFFF)
+        .collect()
+}
+
+impl From<BuilderError> for SubsetError {
+    fn from(value: BuilderError) -> Self {
+        SubsetError::Write(value)
+    }
+}
+
+impl std::fmt::Display for SubsetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubsetError::Read { tag, inner } => write!(f, "failed to read '{tag}': {inner}"),
+            SubsetError::Write(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SubsetError {}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::sanitize::sanitize;
+
+    use super::*;
+
+    fn subset_glyphs(font: &FontRef, gids: &[u16]) -> Vec<u8> {
+        let mut input = SubsetInput::new();
+        input.add_glyph_ids(gids.iter().copied().map(GlyphId::new));
+        subset_font(font, &input).unwrap()
+    }
+
+    #[test]
+    fn subset_fonts_are_well_formed() {
+        for data in [
+            font_test_data::VAZIRMATN_VAR,
+            font_test_data::CANTARELL_VF_TRIMMED,
+            font_test_data::NOTO_SERIF_DISPLAY_TRIMMED,
+            font_test_data::COLRV0V1,
+            font_test_data::COLRV0V1_VARIABLE,
+            font_test_data::EMBEDDED_BITMAPS,
+            font_test_data::CMAP12_FONT1,
+        ] {
+            let font = FontRef::new(data).unwrap();
+            let num_glyphs = font.maxp().unwrap().num_glyphs();
+            // problems in tables that are copied unchanged are ignored
+            let source_issues: BTreeSet<_> = sanitize(&font)
+                .issues()
+                .iter()
+                .map(|issue| (issue.table, issue.path.clone()))
+                .collect();
+            for gids in [&[][..], &[1], &[1, num_glyphs - 1]] {
+                let subset = subset_glyphs(&font, gids);
+                let subset = FontRef::new(&subset).unwrap();
+                let issues: Vec<_> = sanitize(&subset)
+                    .issues()
+                    .iter()
+                    .filter(|issue| !source_issues.contains(&(issue.table, issue.path.clone())))
+                    .cloned()
+                    .collect();
+                assert!(issues.is_empty(), "{gids:?}: {issues:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn color_glyphs_are_retained() {
+        let font = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let colr = font.colr().unwrap();
+        let base_glyphs: Vec<_> = colr
+            .base_glyph_list()
+            .unwrap()
+            .unwrap()
+            .base_glyph_paint_records()
+            .iter()
+            .map(|record| record.glyph_id())
+            .collect();
+        for gid in base_glyphs {
+            let subset = subset_glyphs(&font, &[gid.to_u16()]);
+            let subset = FontRef::new(&subset).unwrap();
+            // components are also retained, so look for any color glyph
+            let num_glyphs = subset.maxp().unwrap().num_glyphs();
+            let paint = (1..num_glyphs)
+                .filter_map(|new| {
+                    subset
+                        .colr()
+                        .unwrap()
+                        .v1_base_glyph(GlyphId::new(new))
+                        .unwrap()
+                })
+                .next();
+            assert!(paint.is_some(), "{gid}");
+        }
+    }
+
+    #[test]
+    fn unicodes_are_mapped() {
+        let font = FontRef::new(font_test_data::CMAP12_FONT1).unwrap();
+        let source_cmap = font.cmap().unwrap();
+        let (codepoint, _) = (0..=0x10FFFF)
+            .filter_map(|cp| Some((cp, source_cmap.map_codepoint(cp)?)))
+            .find(|(_, gid)| gid.to_u16() > 1)
+            .unwrap();
+        let mut input = SubsetInput::new();
+        input.add_unicodes([codepoint]);
+        let subset = subset_font(&font, &input).unwrap();
+        let subset = FontRef::new(&subset).unwrap();
+        assert_eq!(subset.maxp().unwrap().num_glyphs(), 2);
+        let cmap = subset.cmap().unwrap();
+        assert_eq!(cmap.map_codepoint(codepoint), Some(GlyphId::new(1)));
+        assert_eq!(cmap.map_codepoint(codepoint + 1), None);
+    }
+
+    #[test]
+    fn svg_is_dropped_and_base_is_remapped() {
+        use crate::tables::base::{
+            Axis, BaseCoordFormat2, BaseScript, BaseScriptList, BaseScriptRecord, BaseValues,
+            FeatMinMaxRecord,
+        };
+
+        let format2 = |gid| BaseCoord::Format2(BaseCoordFormat2::new(-120, gid, 3));
+        let mut min_max = MinMax::new(None, None, Vec::new());
+        min_max.feat_min_max_records.push(FeatMinMaxRecord::new(
+            Tag::new(b"vkrn"),
+            Some(format2(1)),
+            None,
+        ));
+        let script = BaseScript::new(
+            Some(BaseValues::new(0, vec![format2(3), format2(2)])),
+            Some(min_max),
+            Vec::new(),
+        );
+        let script_list =
+            BaseScriptList::new(vec![BaseScriptRecord::new(Tag::new(b"latn"), script)]);
+        let base = Base::new(Some(Axis::new(None, script_list)), None);
+        // a minimal SVG table with an empty document list
+        let svg = [0u8, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0];
+
+        let source = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let font = FontBuilder::new()
+            .add_table(&base)
+            .unwrap()
+            .add_raw(Tag::new(b"SVG "), &svg[..])
+            .copy_missing_tables(source)
+            .build();
+        let font = FontRef::new(&font).unwrap();
+
+        let subset = subset_glyphs(&font, &[1, 3]);
+        let subset = FontRef::new(&subset).unwrap();
+        assert!(subset.data_for_tag(Tag::new(b"SVG ")).is_none());
+        let base: Base = subset.expect_table::<ReadBase>().unwrap().to_owned_table();
+        let script = &base
+            .horiz_axis
+            .as_ref()
+            .unwrap()
+            .base_script_list
+            .base_script_records[0]
+            .base_script;
+        let coords = &script.base_values.as_ref().unwrap().base_coords;
+        assert_eq!(*coords[0], format2(2));
+        assert_eq!(*coords[1], BaseCoord::Format1(BaseCoordFormat1::new(-120)));
+        let feature = &script
+            .default_min_max
+            .as_ref()
+            .unwrap()
+            .feat_min_max_records[0];
+        assert_eq!(feature.min_coord.as_ref(), Some(&format2(1)));
+    }
+}
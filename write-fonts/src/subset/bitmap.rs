@@ -0,0 +1,515 @@
+//! Subsetting embedded bitmaps.
+//!
+//! The location tables (`EBLC` and `CBLC`) are rebuilt, keeping the format
+//! of each index subtable, and the image data of retained glyphs is copied
+//! into new data tables (`EBDT` and `CBDT`). `sbix` strikes are rebuilt
+//! with the data of retained glyphs.
+
+use std::{collections::BTreeSet, ops::Range};
+
+use read_fonts::{FontRef, ReadError, TableProvider};
+use types::{GlyphId, Tag};
+
+use super::{optional, read_error, GlyphMap, Plan, SubsetError};
+use crate::{
+    raw::{read_u16, read_u32, write_u16, write_u32},
+    FontBuilder,
+};
+
+const CBDT: Tag = Tag::new(b"CBDT");
+const CBLC: Tag = Tag::new(b"CBLC");
+const EBDT: Tag = Tag::new(b"EBDT");
+const EBLC: Tag = Tag::new(b"EBLC");
+const SBIX: Tag = Tag::new(b"sbix");
+/// The graphic type of an `sbix` glyph that reuses the data of another glyph.
+const DUPE: Tag = Tag::new(b"dupe");
+
+/// The size of the header of a location or data table.
+const HEADER_LEN: usize = 8;
+const DATA_HEADER_LEN: usize = 4;
+const BITMAP_SIZE_LEN: usize = 48;
+const INDEX_SUBTABLE_RECORD_LEN: usize = 8;
+const INDEX_SUBTABLE_HEADER_LEN: usize = 8;
+/// The size of the image size and big glyph metrics of index formats 2 and 5.
+const FIXED_METRICS_LEN: usize = 12;
+const SBIX_HEADER_LEN: usize = 8;
+const SBIX_STRIKE_HEADER_LEN: usize = 4;
+/// The size of the origin offsets and graphic type of an `sbix` glyph.
+const SBIX_GLYPH_HEADER_LEN: usize = 8;
+
+/// Adds the components of compound `EBDT` bitmaps, and the targets of `sbix`
+/// `dupe` glyphs, to `glyphs`.
+pub(super) fn closure(font: &FontRef, glyphs: &mut BTreeSet<GlyphId>) -> Result<(), SubsetError> {
+    if let (Some(location), Some(data)) = (font.data_for_tag(EBLC), font.data_for_tag(EBDT)) {
+        let strikes = strikes(location.as_bytes()).map_err(read_error(EBLC))?;
+        let data = data.as_bytes();
+        loop {
+            let mut components = Vec::new();
+            for subtable in strikes.iter().flat_map(|strike| &strike.subtables) {
+                for (gid, range) in &subtable.glyphs {
+                    if glyphs.contains(gid) {
+                        let image = data.get(range.clone()).ok_or(ReadError::OutOfBounds);
+                        let image = image.map_err(read_error(EBDT))?;
+                        components.extend(compound_components(subtable.image_format, image));
+                    }
+                }
+            }
+            let len = glyphs.len();
+            glyphs.extend(components);
+            if glyphs.len() == len {
+                break;
+            }
+        }
+    }
+    if let Some(sbix) = optional(SBIX, font.sbix())? {
+        let num_glyphs = font.maxp().map_err(read_error(SBIX))?.num_glyphs();
+        loop {
+            let mut targets = Vec::new();
+            for strike in sbix.strikes().iter() {
+                let strike = strike.map_err(read_error(SBIX))?;
+                for gid in glyphs.iter().filter(|gid| gid.to_u16() < num_glyphs) {
+                    let glyph = strike.glyph_data(*gid).map_err(read_error(SBIX))?;
+                    if let Some(glyph) = glyph.filter(|glyph| glyph.graphic_type() == DUPE) {
+                        let target = read_u16(glyph.data(), 0).map_err(read_error(SBIX))?;
+                        targets.push(GlyphId::new(target));
+                    }
+                }
+            }
+            let len = glyphs.len();
+            glyphs.extend(targets);
+            if glyphs.len() == len {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites `EBLC`/`EBDT`, `CBLC`/`CBDT` and `sbix`.
+///
+/// A pair of location and data tables is dropped if no strike contains a
+/// retained glyph.
+pub(super) fn subset(plan: &Plan, builder: &mut FontBuilder) -> Result<(), SubsetError> {
+    let font = &plan.font;
+    for (location_tag, data_tag) in [(EBLC, EBDT), (CBLC, CBDT)] {
+        let (Some(location), Some(data)) =
+            (font.data_for_tag(location_tag), font.data_for_tag(data_tag))
+        else {
+            continue;
+        };
+        let tables = subset_strikes(location.as_bytes(), data.as_bytes(), &plan.glyph_map)
+            .map_err(read_error(location_tag))?;
+        if let Some((location, data)) = tables {
+            builder
+                .add_raw(location_tag, location)
+                .add_raw(data_tag, data);
+        }
+    }
+    if let Some(sbix) = font.data_for_tag(SBIX) {
+        let num_glyphs = font.maxp().map_err(read_error(SBIX))?.num_glyphs();
+        let sbix = subset_sbix(sbix.as_bytes(), num_glyphs as usize, &plan.glyph_map)
+            .map_err(read_error(SBIX))?;
+        builder.add_raw(SBIX, sbix);
+    }
+    Ok(())
+}
+
+/// A location table and its data table.
+type BitmapTables = (Vec<u8>, Vec<u8>);
+
+/// A strike, or bitmap size, in a location table.
+struct Strike<'a> {
+    record: &'a [u8],
+    subtables: Vec<IndexSubtable<'a>>,
+}
+
+/// An index subtable, with the location of each glyph's image.
+struct IndexSubtable<'a> {
+    index_format: u16,
+    image_format: u16,
+    /// The image size and metrics shared by all glyphs, in formats 2 and 5.
+    fixed_metrics: &'a [u8],
+    /// The glyphs and the ranges of their images in the data table.
+    glyphs: Vec<(GlyphId, Range<usize>)>,
+}
+
+/// Parses the strikes of an [EBLC] or [CBLC] table.
+///
+/// [EBLC]: https://learn.microsoft.com/en-us/typography/opentype/spec/eblc
+/// [CBLC]: https://learn.microsoft.com/en-us/typography/opentype/spec/cblc
+fn strikes(data: &[u8]) -> Result<Vec<Strike<'_>>, ReadError> {
+    let num_sizes = read_u32(data, 4)? as usize;
+    (0..num_sizes)
+        .map(|i| {
+            let pos = HEADER_LEN + i * BITMAP_SIZE_LEN;
+            let record = data
+                .get(pos..pos + BITMAP_SIZE_LEN)
+                .ok_or(ReadError::OutOfBounds)?;
+            let list = read_u32(record, 0)? as usize;
+            let subtables = (0..read_u32(record, 8)? as usize)
+                .map(|j| {
+                    let pos = list + j * INDEX_SUBTABLE_RECORD_LEN;
+                    let first = read_u16(data, pos)?;
+                    let last = read_u16(data, pos + 2)?;
+                    let offset = read_u32(data, pos + 4)? as usize;
+                    IndexSubtable::read(data, list + offset, first, last)
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Strike { record, subtables })
+        })
+        .collect()
+}
+
+impl<'a> IndexSubtable<'a> {
+    fn read(data: &'a [u8], pos: usize, first: u16, last: u16) -> Result<Self, ReadError> {
+        let index_format = read_u16(data, pos)?;
+        let image_format = read_u16(data, pos + 2)?;
+        let image_data_offset = read_u32(data, pos + 4)? as usize;
+        let num_glyphs = last.checked_sub(first).ok_or(ReadError::MalformedData(
+            "invalid index subtable glyph range",
+        ))? as usize
+            + 1;
+        let pos = pos + INDEX_SUBTABLE_HEADER_LEN;
+        let fixed_metrics = || {
+            data.get(pos..pos + FIXED_METRICS_LEN)
+                .ok_or(ReadError::OutOfBounds)
+        };
+        let gid = |idx: usize| GlyphId::new(first + idx as u16);
+        let (fixed_metrics, glyphs) = match index_format {
+            1 => (
+                Default::default(),
+                (0..num_glyphs)
+                    .map(|i| {
+                        let start = read_u32(data, pos + i * 4)? as usize;
+                        let end = read_u32(data, pos + i * 4 + 4)? as usize;
+                        Ok((gid(i), start..end))
+                    })
+                    .collect::<Result<Vec<_>, ReadError>>()?,
+            ),
+            3 => (
+                Default::default(),
+                (0..num_glyphs)
+                    .map(|i| {
+                        let start = read_u16(data, pos + i * 2)? as usize;
+                        let end = read_u16(data, pos + i * 2 + 2)? as usize;
+                        Ok((gid(i), start..end))
+                    })
+                    .collect::<Result<Vec<_>, ReadError>>()?,
+            ),
+            2 => {
+                let size = read_u32(data, pos)? as usize;
+                let glyphs = (0..num_glyphs)
+                    .map(|i| (gid(i), i * size..(i + 1) * size))
+                    .collect();
+                (fixed_metrics()?, glyphs)
+            }
+            4 => {
+                let num_glyphs = read_u32(data, pos)? as usize;
+                let glyphs = (0..num_glyphs)
+                    .map(|i| {
+                        let pos = pos + 4 + i * 4;
+                        let gid = GlyphId::new(read_u16(data, pos)?);
+                        let start = read_u16(data, pos + 2)? as usize;
+                        let end = read_u16(data, pos + 6)? as usize;
+                        Ok((gid, start..end))
+                    })
+                    .collect::<Result<Vec<_>, ReadError>>()?;
+                (Default::default(), glyphs)
+            }
+            5 => {
+                let size = read_u32(data, pos)? as usize;
+                let num_glyphs = read_u32(data, pos + FIXED_METRICS_LEN)? as usize;
+                let glyphs = (0..num_glyphs)
+                    .map(|i| {
+                        let gid = read_u16(data, pos + FIXED_METRICS_LEN + 4 + i * 2)?;
+                        Ok((GlyphId::new(gid), i * size..(i + 1) * size))
+                    })
+                    .collect::<Result<Vec<_>, ReadError>>()?;
+                (fixed_metrics()?, glyphs)
+            }
+            _ => return Err(ReadError::MalformedData("invalid index subtable format")),
+        };
+        let glyphs = glyphs
+            .into_iter()
+            .map(|(gid, range)| {
+                (
+                    gid,
+                    image_data_offset + range.start..image_data_offset + range.end,
+                )
+            })
+            .collect();
+        Ok(Self {
+            index_format,
+            image_format,
+            fixed_metrics,
+            glyphs,
+        })
+    }
+
+    /// Writes a subtable with the given glyphs, appending their images to
+    /// `image_data`.
+    ///
+    /// For formats 1, 2 and 3 the glyphs must cover a contiguous range, which
+    /// is the case for the retained glyphs of a source subtable.
+    fn write(&self, glyphs: &[(GlyphId, &[u8])], image_data: &mut Vec<u8>) -> Vec<u8> {
+        let image_data_offset = image_data.len();
+        let mut offsets = vec![0];
+        for (_, image) in glyphs {
+            image_data.extend_from_slice(image);
+            offsets.push(image_data.len() - image_data_offset);
+        }
+        let mut subtable = self.index_format.to_be_bytes().to_vec();
+        subtable.extend_from_slice(&self.image_format.to_be_bytes());
+        subtable.extend_from_slice(&(image_data_offset as u32).to_be_bytes());
+        match self.index_format {
+            1 => {
+                for offset in offsets {
+                    subtable.extend_from_slice(&(offset as u32).to_be_bytes());
+                }
+            }
+            3 => {
+                for offset in offsets {
+                    subtable.extend_from_slice(&(offset as u16).to_be_bytes());
+                }
+            }
+            4 => {
+                subtable.extend_from_slice(&(glyphs.len() as u32).to_be_bytes());
+                let gids = glyphs.iter().map(|(gid, _)| *gid);
+                for (gid, offset) in gids.chain([GlyphId::NOTDEF]).zip(offsets) {
+                    subtable.extend_from_slice(&gid.to_be_bytes());
+                    subtable.extend_from_slice(&(offset as u16).to_be_bytes());
+                }
+            }
+            2 => subtable.extend_from_slice(self.fixed_metrics),
+            _ => {
+                subtable.extend_from_slice(self.fixed_metrics);
+                subtable.extend_from_slice(&(glyphs.len() as u32).to_be_bytes());
+                for (gid, _) in glyphs {
+                    subtable.extend_from_slice(&gid.to_be_bytes());
+                }
+            }
+        }
+        subtable.resize(subtable.len().next_multiple_of(4), 0);
+        subtable
+    }
+}
+
+/// Returns the components of a compound bitmap in [EBDT] format 8 or 9.
+///
+/// [EBDT]: https://learn.microsoft.com/en-us/typography/opentype/spec/ebdt#glyph-bitmap-data-formats
+fn compound_components(image_format: u16, image: &[u8]) -> Vec<GlyphId> {
+    // the size of the metrics, and padding, before the component count
+    let metrics_len = match image_format {
+        8 => 6,
+        9 => 8,
+        _ => return Vec::new(),
+    };
+    let num_components = read_u16(image, metrics_len).unwrap_or_default() as usize;
+    (0..num_components)
+        .map_while(|i| read_u16(image, metrics_len + 2 + i * 4).ok())
+        .map(GlyphId::new)
+        .collect()
+}
+
+/// Rewrites a pair of location and data tables.
+///
+/// Returns `None` if no strike contains a retained glyph.
+fn subset_strikes(
+    location: &[u8],
+    data: &[u8],
+    glyph_map: &GlyphMap,
+) -> Result<Option<BitmapTables>, ReadError> {
+    let mut new_data = data
+        .get(..DATA_HEADER_LEN)
+        .ok_or(ReadError::OutOfBounds)?
+        .to_vec();
+    let mut records = Vec::new();
+    let mut lists = Vec::new();
+    for strike in strikes(location)? {
+        let mut subtable_records = Vec::new();
+        let mut subtables = Vec::new();
+        for subtable in &strike.subtables {
+            let glyphs = subtable
+                .glyphs
+                .iter()
+                .filter_map(|(gid, range)| Some((glyph_map.get(*gid)?, range)))
+                .map(|(gid, range)| {
+                    let image = data.get(range.clone()).ok_or(ReadError::OutOfBounds)?;
+                    Ok((gid, image))
+                })
+                .collect::<Result<Vec<_>, ReadError>>()?;
+            let (Some((first, _)), Some((last, _))) = (glyphs.first(), glyphs.last()) else {
+                continue;
+            };
+            subtable_records.push((*first, *last, subtables.len()));
+            subtables.extend(subtable.write(&glyphs, &mut new_data));
+        }
+        let (Some((start, _, _)), Some((_, end, _))) =
+            (subtable_records.first(), subtable_records.last())
+        else {
+            continue;
+        };
+        let mut record = strike.record.to_vec();
+        write_u16(&mut record, 40, start.to_u16());
+        write_u16(&mut record, 42, end.to_u16());
+        write_u32(&mut record, 8, subtable_records.len() as u32);
+        let records_len = subtable_records.len() * INDEX_SUBTABLE_RECORD_LEN;
+        let mut list = Vec::new();
+        for (first, last, offset) in subtable_records {
+            list.extend_from_slice(&first.to_be_bytes());
+            list.extend_from_slice(&last.to_be_bytes());
+            list.extend_from_slice(&((records_len + offset) as u32).to_be_bytes());
+        }
+        list.extend(subtables);
+        write_u32(&mut record, 4, list.len() as u32);
+        records.push(record);
+        lists.push(list);
+    }
+    if records.is_empty() {
+        return Ok(None);
+    }
+
+    let mut new_location = location[..4].to_vec();
+    new_location.extend_from_slice(&(records.len() as u32).to_be_bytes());
+    let mut list_offset = HEADER_LEN + records.len() * BITMAP_SIZE_LEN;
+    for mut record in records {
+        write_u32(&mut record, 0, list_offset as u32);
+        list_offset += read_u32(&record, 4)? as usize;
+        new_location.extend(record);
+    }
+    new_location.extend(lists.into_iter().flatten());
+    Ok(Some((new_location, new_data)))
+}
+
+/// Rewrites the [sbix] table, keeping the data of retained glyphs in each
+/// strike.
+///
+/// [sbix]: https://learn.microsoft.com/en-us/typography/opentype/spec/sbix
+fn subset_sbix(data: &[u8], num_glyphs: usize, glyph_map: &GlyphMap) -> Result<Vec<u8>, ReadError> {
+    let num_strikes = read_u32(data, 4)? as usize;
+    let offsets_len = (glyph_map.len() + 1) * 4;
+    let mut strikes = Vec::new();
+    for i in 0..num_strikes {
+        let pos = read_u32(data, SBIX_HEADER_LEN + i * 4)? as usize;
+        let header = data
+            .get(pos..pos + SBIX_STRIKE_HEADER_LEN)
+            .ok_or(ReadError::OutOfBounds)?;
+        let mut offsets = Vec::new();
+        let mut glyph_data = Vec::new();
+        for gid in glyph_map.old_glyphs() {
+            offsets.push(SBIX_STRIKE_HEADER_LEN + offsets_len + glyph_data.len());
+            let idx = gid.to_u16() as usize;
+            if idx >= num_glyphs {
+                continue;
+            }
+            let offset_pos = pos + SBIX_STRIKE_HEADER_LEN + idx * 4;
+            let start = pos + read_u32(data, offset_pos)? as usize;
+            let end = pos + read_u32(data, offset_pos + 4)? as usize;
+            let glyph = data.get(start..end).ok_or(ReadError::OutOfBounds)?;
+            let glyph_pos = glyph_data.len();
+            glyph_data.extend_from_slice(glyph);
+            if glyph.get(4..SBIX_GLYPH_HEADER_LEN) == Some(DUPE.to_be_bytes().as_slice()) {
+                let target = GlyphId::new(read_u16(glyph, SBIX_GLYPH_HEADER_LEN)?);
+                let target = glyph_map.get(target).unwrap_or(GlyphId::NOTDEF);
+                write_u16(
+                    &mut glyph_data,
+                    glyph_pos + SBIX_GLYPH_HEADER_LEN,
+                    target.to_u16(),
+                );
+            }
+        }
+        offsets.push(SBIX_STRIKE_HEADER_LEN + offsets_len + glyph_data.len());
+        let mut strike = header.to_vec();
+        for offset in offsets {
+            strike.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+        strike.extend(glyph_data);
+        strike.resize(strike.len().next_multiple_of(4), 0);
+        strikes.push(strike);
+    }
+
+    let mut sbix = data
+        .get(..SBIX_HEADER_LEN)
+        .ok_or(ReadError::OutOfBounds)?
+        .to_vec();
+    let mut offset = SBIX_HEADER_LEN + num_strikes * 4;
+    for strike in &strikes {
+        sbix.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += strike.len();
+    }
+    sbix.extend(strikes.into_iter().flatten());
+    Ok(sbix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph_map(gids: &[u16]) -> GlyphMap {
+        GlyphMap::new(&gids.iter().copied().map(GlyphId::new).collect())
+    }
+
+    fn image<'a>(strike: &Strike, data: &'a [u8], gid: GlyphId) -> Option<&'a [u8]> {
+        strike
+            .subtables
+            .iter()
+            .flat_map(|subtable| &subtable.glyphs)
+            .find(|(glyph, _)| *glyph == gid)
+            .and_then(|(_, range)| data.get(range.clone()))
+    }
+
+    #[test]
+    fn strike_images() {
+        let font = FontRef::new(font_test_data::EMBEDDED_BITMAPS).unwrap();
+        for (location_tag, data_tag) in [(EBLC, EBDT), (CBLC, CBDT)] {
+            let location = font.data_for_tag(location_tag).unwrap().as_bytes();
+            let data = font.data_for_tag(data_tag).unwrap().as_bytes();
+            let source = strikes(location).unwrap();
+            let glyph_map = glyph_map(&[0, 2, 3, 4]);
+            let (new_location, new_data) =
+                subset_strikes(location, data, &glyph_map).unwrap().unwrap();
+            let subset = strikes(&new_location).unwrap();
+            assert_eq!(subset.len(), source.len());
+            for (source, subset) in source.iter().zip(&subset) {
+                for (old, new) in glyph_map.iter() {
+                    assert_eq!(
+                        image(subset, &new_data, new),
+                        image(source, data, old),
+                        "{location_tag} {old}"
+                    );
+                }
+            }
+        }
+        // the only color bitmap is removed
+        let location = font.data_for_tag(CBLC).unwrap().as_bytes();
+        let data = font.data_for_tag(CBDT).unwrap().as_bytes();
+        assert!(subset_strikes(location, data, &glyph_map(&[0, 1]))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn sbix_dupe_is_remapped() {
+        // one strike with glyphs 0 and 1 empty, glyph 2 png data and glyph 3
+        // a duplicate of glyph 2
+        let mut data = vec![0, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0, 12];
+        let mut strike = vec![0, 20, 0, 72];
+        let png = [0, 0, 0, 0, b'p', b'n', b'g', b' ', 1, 2, 3];
+        let dupe = [0, 0, 0, 0, b'd', b'u', b'p', b'e', 0, 2];
+        let glyphs_start = 4 + 5 * 4;
+        for offset in [0, 0, 0, png.len(), png.len() + dupe.len()] {
+            strike.extend(((glyphs_start + offset) as u32).to_be_bytes());
+        }
+        strike.extend(png);
+        strike.extend(dupe);
+        data.extend(strike);
+
+        let sbix = subset_sbix(&data, 4, &glyph_map(&[0, 2, 3])).unwrap();
+        let strike = &sbix[12..];
+        let offset = |idx: usize| read_u32(strike, 4 + idx * 4).unwrap() as usize;
+        assert_eq!(&strike[offset(1)..offset(2)], png);
+        let dupe = &strike[offset(2)..offset(3)];
+        assert_eq!(&dupe[4..8], b"dupe");
+        assert_eq!(read_u16(dupe, 8).unwrap(), 1);
+    }
+}
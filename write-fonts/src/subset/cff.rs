@@ -0,0 +1,562 @@
+//! Subsetting PostScript outlines.
+//!
+//! The CFF and CFF2 tables are rewritten at the level of their binary
+//! structures: the charstrings, charset and FDSelect are rebuilt for the
+//! retained glyphs, while strings, subroutines and the variation store are
+//! copied unchanged.
+
+use std::collections::{BTreeSet, HashMap};
+
+use read_fonts::{
+    tables::postscript::{charstring::NESTING_DEPTH_LIMIT, Index},
+    tables::{cff::Cff, cff2::Cff2},
+    FontData, FontRead, FontRef, ReadError, TableProvider,
+};
+use types::{GlyphId, Tag};
+
+use super::{read_error, GlyphMap, Plan, SubsetError};
+use crate::{
+    tables::postscript::{
+        font::{
+            ps_error, top_dict_offsets, write_dict, CffFont, CHARSET, CHARSTRINGS, ENCODING,
+            FD_ARRAY, FD_SELECT, PRIVATE, VARIATION_STORE,
+        },
+        write_index, ESCAPE,
+    },
+    FontBuilder,
+};
+
+const CFF: Tag = Tag::new(b"CFF ");
+const CFF2: Tag = Tag::new(b"CFF2");
+
+/// Adds the base and accent glyphs of `seac`-like `endchar` operators to
+/// `glyphs`.
+///
+/// Only name-keyed CFF fonts can contain accented characters.
+pub(super) fn closure(font: &FontRef, glyphs: &mut BTreeSet<GlyphId>) -> Result<(), ReadError> {
+    let Some(data) = font.data_for_tag(CFF) else {
+        return Ok(());
+    };
+    let cff = CffFont::new_cff(data.as_bytes())?;
+    if cff.is_cid() {
+        return Ok(());
+    }
+    let charset = cff.charset()?;
+    let sid_to_gid: HashMap<_, _> = charset
+        .iter()
+        .enumerate()
+        .map(|(gid, sid)| (*sid, GlyphId::new(gid as u16)))
+        .collect();
+    let local_subrs = cff
+        .private
+        .as_ref()
+        .and_then(|private| private.subrs)
+        .map(|subrs| Index::new(subrs, false))
+        .transpose()
+        .map_err(ps_error)?;
+    let mut accents = Vec::new();
+    for gid in glyphs.iter() {
+        let Ok(charstring) = cff.charstrings.get(gid.to_u16() as usize) else {
+            continue;
+        };
+        let Some((base, accent)) = seac_components(charstring, &cff.global_subrs, &local_subrs)
+        else {
+            continue;
+        };
+        accents.extend(
+            [base, accent]
+                .into_iter()
+                .filter_map(standard_encoding_sid)
+                .filter_map(|sid| sid_to_gid.get(&sid).copied()),
+        );
+    }
+    glyphs.extend(accents);
+    Ok(())
+}
+
+/// Rewrites the `CFF ` and `CFF2` tables.
+pub(super) fn subset(plan: &Plan, builder: &mut FontBuilder) -> Result<(), SubsetError> {
+    if let Some(data) = plan.font.data_for_tag(CFF) {
+        let cff = subset_cff(data.as_bytes(), &plan.glyph_map).map_err(read_error(CFF))?;
+        builder.add_raw(CFF, cff);
+    }
+    if let Some(data) = plan.font.data_for_tag(CFF2) {
+        let cff2 = subset_cff2(data.as_bytes(), &plan.glyph_map).map_err(read_error(CFF2))?;
+        builder.add_raw(CFF2, cff2);
+    }
+    Ok(())
+}
+
+/// Returns the encoded size of a CFF or CFF2 INDEX.
+fn index_len(items: &[&[u8]], is_cff2: bool) -> usize {
+    write_index(items.iter().copied(), is_cff2).len()
+}
+
+/// The retained charstrings, in new glyph order.
+fn subset_charstrings<'a>(
+    font: &CffFont<'a>,
+    glyph_map: &GlyphMap,
+) -> Result<Vec<&'a [u8]>, ReadError> {
+    glyph_map
+        .old_glyphs()
+        .map(|gid| {
+            font.charstrings
+                .get(gid.to_u16() as usize)
+                .map_err(ps_error)
+        })
+        .collect()
+}
+
+/// Writes a charset with format 0.
+fn write_charset(sids: &[u16]) -> Vec<u8> {
+    let mut data = vec![0];
+    for sid in sids.iter().skip(1) {
+        data.extend_from_slice(&sid.to_be_bytes());
+    }
+    data
+}
+
+/// Rewrites a [CFF] table.
+///
+/// The Encoding is dropped, so that the standard encoding is used; all
+/// subroutines and Font DICTs are retained.
+///
+/// [CFF]: https://learn.microsoft.com/en-us/typography/opentype/spec/cff
+fn subset_cff(data: &[u8], glyph_map: &GlyphMap) -> Result<Vec<u8>, ReadError> {
+    let cff = Cff::read(FontData::new(data))?;
+    let font = CffFont::new_cff(data)?;
+    let names_end = cff.header().hdr_size() as usize + cff.names().size_in_bytes()?;
+    let strings_start = names_end + cff.top_dicts().size_in_bytes()?;
+    let strings_end =
+        strings_start + cff.strings().size_in_bytes()? + cff.global_subrs().size_in_bytes()?;
+    let strings_and_subrs = data
+        .get(strings_start..strings_end)
+        .ok_or(ReadError::OutOfBounds)?;
+
+    let charset = font.charset()?;
+    let charset: Vec<_> = glyph_map
+        .old_glyphs()
+        .map(|gid| {
+            charset
+                .get(gid.to_u16() as usize)
+                .copied()
+                .unwrap_or_default()
+        })
+        .collect();
+    let charset = write_charset(&charset);
+    let fd_select = font.write_fd_select(glyph_map.old_glyphs());
+    let charstrings = subset_charstrings(&font, glyph_map)?;
+    let charstrings = write_index(charstrings, false);
+
+    let replaced = [CHARSET, ENCODING, CHARSTRINGS, PRIVATE, FD_ARRAY, FD_SELECT];
+    let write_top_dict =
+        |offsets: &[(u16, Vec<usize>)]| write_dict(&font.top_dict, &replaced, offsets);
+    let placeholder_offsets = top_dict_offsets(&font, [0; 5], Some([0, 0]));
+    let top_dict_len = index_len(&[&write_top_dict(&placeholder_offsets)], false);
+
+    let charset_offset = names_end + top_dict_len + strings_and_subrs.len();
+    let fd_select_offset = charset_offset + charset.len();
+    let charstrings_offset =
+        fd_select_offset + fd_select.as_ref().map(Vec::len).unwrap_or_default();
+    let fd_array_offset = charstrings_offset + charstrings.len();
+    let (fd_array, privates, private) = font.write_dicts(fd_array_offset);
+
+    let offsets = top_dict_offsets(
+        &font,
+        [
+            charset_offset,
+            charstrings_offset,
+            fd_select_offset,
+            fd_array_offset,
+            0,
+        ],
+        private,
+    );
+    let top_dict = write_top_dict(&offsets);
+
+    let mut cff = data[..names_end].to_vec();
+    cff.extend(write_index([top_dict.as_slice()], false));
+    cff.extend_from_slice(strings_and_subrs);
+    cff.extend(charset);
+    cff.extend(fd_select.unwrap_or_default());
+    cff.extend(charstrings);
+    cff.extend(fd_array.unwrap_or_default());
+    cff.extend(privates);
+    Ok(cff)
+}
+
+/// Rewrites a [CFF2] table.
+///
+/// All subroutines, Font DICTs and the variation store are retained.
+///
+/// [CFF2]: https://learn.microsoft.com/en-us/typography/opentype/spec/cff2
+fn subset_cff2(data: &[u8], glyph_map: &GlyphMap) -> Result<Vec<u8>, ReadError> {
+    let cff2 = Cff2::read(FontData::new(data))?;
+    let font = CffFont::new_cff2(data)?;
+    let header_len = cff2.header().header_size() as usize;
+    let global_subrs_start = header_len + cff2.header().top_dict_length() as usize;
+    let global_subrs = data
+        .get(global_subrs_start..global_subrs_start + cff2.global_subrs().size_in_bytes()?)
+        .ok_or(ReadError::OutOfBounds)?;
+    // the variation store is prefixed by its length
+    let variation_store = match font
+        .top_dict
+        .iter()
+        .find(|entry| entry.op == VARIATION_STORE)
+    {
+        Some(entry) => {
+            let offset = entry.offset()?;
+            let len = data
+                .get(offset..offset + 2)
+                .map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+                .ok_or(ReadError::OutOfBounds)?;
+            Some(
+                data.get(offset..offset + 2 + len)
+                    .ok_or(ReadError::OutOfBounds)?,
+            )
+        }
+        None => None,
+    };
+
+    let fd_select = font.write_fd_select(glyph_map.old_glyphs());
+    let charstrings = subset_charstrings(&font, glyph_map)?;
+    let charstrings = write_index(charstrings, true);
+
+    let replaced = [VARIATION_STORE, CHARSTRINGS, FD_ARRAY, FD_SELECT];
+    let write_top_dict =
+        |offsets: &[(u16, Vec<usize>)]| write_dict(&font.top_dict, &replaced, offsets);
+    let placeholder_offsets = top_dict_offsets(&font, [0; 5], None);
+    let top_dict_len = write_top_dict(&placeholder_offsets).len();
+
+    let variation_store_offset = header_len + top_dict_len + global_subrs.len();
+    let fd_select_offset =
+        variation_store_offset + variation_store.map(<[u8]>::len).unwrap_or_default();
+    let charstrings_offset =
+        fd_select_offset + fd_select.as_ref().map(Vec::len).unwrap_or_default();
+    let fd_array_offset = charstrings_offset + charstrings.len();
+    let (fd_array, privates, _) = font.write_dicts(fd_array_offset);
+
+    let offsets = top_dict_offsets(
+        &font,
+        [
+            0,
+            charstrings_offset,
+            fd_select_offset,
+            fd_array_offset,
+            variation_store_offset,
+        ],
+        None,
+    );
+    let top_dict = write_top_dict(&offsets);
+
+    let mut cff2 = data[..header_len].to_vec();
+    cff2[3..5].copy_from_slice(&(top_dict.len() as u16).to_be_bytes());
+    cff2.extend(top_dict);
+    cff2.extend_from_slice(global_subrs);
+    cff2.extend_from_slice(variation_store.unwrap_or_default());
+    cff2.extend(fd_select.unwrap_or_default());
+    cff2.extend(charstrings);
+    cff2.extend(fd_array.unwrap_or_default());
+    cff2.extend(privates);
+    Ok(cff2)
+}
+
+/// Returns the standard encoding codes of the base and accent characters of
+/// a charstring that ends with a `seac`-like `endchar` operator.
+fn seac_components(
+    charstring: &[u8],
+    global_subrs: &Index,
+    local_subrs: &Option<Index>,
+) -> Option<(u8, u8)> {
+    let mut state = SeacState::default();
+    state
+        .evaluate(charstring, global_subrs, local_subrs, 0)
+        .ok()?
+}
+
+/// The state needed to find `seac` operands in a Type 2 charstring.
+#[derive(Default)]
+struct SeacState {
+    stack: Vec<i32>,
+    stem_count: usize,
+}
+
+impl SeacState {
+    /// Evaluates a charstring or subroutine.
+    ///
+    /// Returns `Ok(Some(..))` if the charstring ends with a `seac`, `Ok(None)`
+    /// if it ends without one, and `Err(())` if the end was not reached
+    /// (a `return` from a subroutine).
+    fn evaluate(
+        &mut self,
+        data: &[u8],
+        global_subrs: &Index,
+        local_subrs: &Option<Index>,
+        depth: u32,
+    ) -> Result<Option<(u8, u8)>, ()> {
+        const HSTEM: u8 = 1;
+        const VSTEM: u8 = 3;
+        const CALLSUBR: u8 = 10;
+        const RETURN: u8 = 11;
+        const ENDCHAR: u8 = 14;
+        const HSTEMHM: u8 = 18;
+        const HINTMASK: u8 = 19;
+        const CNTRMASK: u8 = 20;
+        const VSTEMHM: u8 = 23;
+        const CALLGSUBR: u8 = 29;
+
+        if depth > NESTING_DEPTH_LIMIT {
+            return Ok(None);
+        }
+        let mut pos = 0;
+        while let Some(&b0) = data.get(pos) {
+            pos += 1;
+            let bytes = &data[pos..];
+            match b0 {
+                28 => {
+                    let value = bytes.get(..2).ok_or(())?;
+                    self.stack
+                        .push(i16::from_be_bytes([value[0], value[1]]) as i32);
+                    pos += 2;
+                }
+                32..=246 => self.stack.push(b0 as i32 - 139),
+                247..=250 => {
+                    let b1 = *bytes.first().ok_or(())? as i32;
+                    self.stack.push((b0 as i32 - 247) * 256 + b1 + 108);
+                    pos += 1;
+                }
+                251..=254 => {
+                    let b1 = *bytes.first().ok_or(())? as i32;
+                    self.stack.push(-(b0 as i32 - 251) * 256 - b1 - 108);
+                    pos += 1;
+                }
+                // a 16.16 fixed point value
+                255 => {
+                    let value = bytes.get(..4).ok_or(())?;
+                    self.stack
+                        .push(i32::from_be_bytes(value.try_into().unwrap()) >> 16);
+                    pos += 4;
+                }
+                HSTEM | VSTEM | HSTEMHM | VSTEMHM => {
+                    self.stem_count += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                HINTMASK | CNTRMASK => {
+                    // an implied vstem
+                    self.stem_count += self.stack.len() / 2;
+                    self.stack.clear();
+                    pos += self.stem_count.div_ceil(8);
+                }
+                CALLSUBR | CALLGSUBR => {
+                    let subrs = if b0 == CALLSUBR {
+                        local_subrs.as_ref().ok_or(())?
+                    } else {
+                        global_subrs
+                    };
+                    let idx = self.stack.pop().ok_or(())? + subrs.subr_bias();
+                    let subr = subrs
+                        .get(usize::try_from(idx).map_err(|_| ())?)
+                        .map_err(|_| ())?;
+                    // a subroutine that returns continues the caller
+                    if let Ok(result) = self.evaluate(subr, global_subrs, local_subrs, depth + 1) {
+                        return Ok(result);
+                    }
+                }
+                RETURN => return Err(()),
+                ENDCHAR => {
+                    let seac = match self.stack.as_slice() {
+                        [.., base, accent] if self.stack.len() >= 4 => {
+                            u8::try_from(*base).ok().zip(u8::try_from(*accent).ok())
+                        }
+                        _ => None,
+                    };
+                    return Ok(seac);
+                }
+                ESCAPE => {
+                    pos += 1;
+                    self.stack.clear();
+                }
+                _ => self.stack.clear(),
+            }
+        }
+        Err(())
+    }
+}
+
+/// Returns the string identifier for a code in the [standard encoding].
+///
+/// [standard encoding]: https://adobe-type-tools.github.io/font-tech-notes/pdfs/5176.CFF.pdf#page=37
+fn standard_encoding_sid(code: u8) -> Option<u16> {
+    /// The codes above 126, with their string identifiers.
+    const UPPER_CODES: [(u8, u16); 54] = [
+        (161, 96),
+        (162, 97),
+        (163, 98),
+        (164, 99),
+        (165, 100),
+        (166, 101),
+        (167, 102),
+        (168, 103),
+        (169, 104),
+        (170, 105),
+        (171, 106),
+        (172, 107),
+        (173, 108),
+        (174, 109),
+        (175, 110),
+        (177, 111),
+        (178, 112),
+        (179, 113),
+        (180, 114),
+        (182, 115),
+        (183, 116),
+        (184, 117),
+        (185, 118),
+        (186, 119),
+        (187, 120),
+        (188, 121),
+        (189, 122),
+        (191, 123),
+        (193, 124),
+        (194, 125),
+        (195, 126),
+        (196, 127),
+        (197, 128),
+        (198, 129),
+        (199, 130),
+        (200, 131),
+        (202, 132),
+        (203, 133),
+        (205, 134),
+        (206, 135),
+        (207, 136),
+        (208, 137),
+        (225, 138),
+        (227, 139),
+        (232, 140),
+        (233, 141),
+        (234, 142),
+        (235, 143),
+        (241, 144),
+        (245, 145),
+        (248, 146),
+        (249, 147),
+        (250, 148),
+        (251, 149),
+    ];
+    match code {
+        32..=126 => Some(code as u16 - 31),
+        _ => UPPER_CODES
+            .binary_search_by_key(&code, |(code, _)| *code)
+            .ok()
+            .map(|idx| UPPER_CODES[idx].1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{tables::postscript::StringId, TableProvider};
+
+    use super::*;
+
+    fn glyph_map(gids: &[u16]) -> GlyphMap {
+        GlyphMap::new(&gids.iter().copied().map(GlyphId::new).collect())
+    }
+
+    #[test]
+    fn standard_encoding() {
+        let name = |code| {
+            let sid = standard_encoding_sid(code).unwrap();
+            StringId::new(sid).standard_string().unwrap().to_string()
+        };
+        assert_eq!(name(b' '), "space");
+        assert_eq!(name(b'A'), "A");
+        assert_eq!(name(b'~'), "asciitilde");
+        assert_eq!(name(0xA1), "exclamdown");
+        assert_eq!(name(0xC1), "grave");
+        assert_eq!(name(0xE1), "AE");
+        assert_eq!(name(0xFB), "germandbls");
+        assert_eq!(standard_encoding_sid(0xB0), None);
+        assert_eq!(standard_encoding_sid(10), None);
+    }
+
+    #[test]
+    fn seac_operands() {
+        // width, adx, ady, bchar ('A'), achar (acute), endchar
+        let charstring = [139 + 50, 139, 139, 139 + 65, 247, 86, 14];
+        let subrs = write_index([[11u8].as_slice()], false);
+        let global_subrs = Index::new(&subrs, false).unwrap();
+        assert_eq!(
+            seac_components(&charstring, &global_subrs, &None),
+            Some((65, 194))
+        );
+        // rmoveto with two operands, then endchar
+        let charstring = [139, 139, 21, 14];
+        assert_eq!(seac_components(&charstring, &global_subrs, &None), None);
+    }
+
+    fn check_cff(data: &[u8], glyph_map: &GlyphMap, is_cff2: bool) {
+        let source = if is_cff2 {
+            CffFont::new_cff2(data).unwrap()
+        } else {
+            CffFont::new_cff(data).unwrap()
+        };
+        let subset = if is_cff2 {
+            subset_cff2(data, glyph_map).unwrap()
+        } else {
+            subset_cff(data, glyph_map).unwrap()
+        };
+        let font = if is_cff2 {
+            CffFont::new_cff2(&subset).unwrap()
+        } else {
+            CffFont::new_cff(&subset).unwrap()
+        };
+        assert_eq!(font.num_glyphs(), glyph_map.len());
+        for (old, new) in glyph_map.iter() {
+            assert_eq!(
+                font.charstrings.get(new.to_u16() as usize).unwrap(),
+                source.charstrings.get(old.to_u16() as usize).unwrap()
+            );
+        }
+        assert_eq!(font.font_dicts.len(), source.font_dicts.len());
+        for (font_dict, source_dict) in font.font_dicts.iter().zip(&source.font_dicts) {
+            let subrs = font_dict.private.as_ref().and_then(|private| private.subrs);
+            let source_subrs = source_dict
+                .private
+                .as_ref()
+                .and_then(|private| private.subrs);
+            assert_eq!(subrs, source_subrs);
+        }
+        let subrs = font.private.as_ref().and_then(|private| private.subrs);
+        let source_subrs = source.private.as_ref().and_then(|private| private.subrs);
+        assert_eq!(subrs, source_subrs);
+        if !is_cff2 {
+            let charset = font.charset().unwrap();
+            let source_charset = source.charset().unwrap();
+            for (old, new) in glyph_map.iter() {
+                assert_eq!(
+                    charset[new.to_u16() as usize],
+                    source_charset[old.to_u16() as usize]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn subset_cff_charstrings() {
+        let font = FontRef::new(font_test_data::NOTO_SERIF_DISPLAY_TRIMMED).unwrap();
+        let data = font.data_for_tag(CFF).unwrap();
+        check_cff(data.as_bytes(), &glyph_map(&[0, 2, 3]), false);
+        // the subset table is readable with read-fonts
+        let subset = subset_cff(data.as_bytes(), &glyph_map(&[0, 2])).unwrap();
+        Cff::read(FontData::new(&subset)).unwrap();
+    }
+
+    #[test]
+    fn subset_cff2_charstrings() {
+        let font = FontRef::new(font_test_data::CANTARELL_VF_TRIMMED).unwrap();
+        let data = font.data_for_tag(CFF2).unwrap();
+        check_cff(data.as_bytes(), &glyph_map(&[0, 1, 3]), true);
+        assert!(font.cff2().is_ok());
+    }
+}
@@ -0,0 +1,240 @@
+//! Subsetting the character map.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use read_fonts::{
+    tables::cmap::{self as read, CmapSubtable as ReadSubtable, MapVariant},
+    FontRef, ReadError, TableProvider,
+};
+use types::{GlyphId, Uint24};
+
+use super::{Plan, SubsetInput};
+use crate::tables::cmap::{
+    Cmap, Cmap14, CmapSubtable, DefaultUvs, EncodingRecord, NonDefaultUvs, PlatformId,
+    UnicodeRange, UvsMapping, VariationSelector,
+};
+
+const UNICODE_VARIATION_ENCODING: u16 = 5;
+
+/// The size of the format 14 header, and of each of its records.
+const CMAP14_HEADER_LEN: u32 = 10;
+const VARIATION_SELECTOR_LEN: u32 = 11;
+const UVS_HEADER_LEN: u32 = 4;
+const UNICODE_RANGE_LEN: u32 = 4;
+const UVS_MAPPING_LEN: u32 = 5;
+
+/// Collects the codepoints to retain, and adds their glyphs (including any
+/// variation sequence glyphs) to `glyphs`.
+///
+/// A codepoint is retained if it was requested, or if it maps to a requested
+/// glyph.
+pub(super) fn closure(
+    font: &FontRef,
+    input: &SubsetInput,
+    glyphs: &mut BTreeSet<GlyphId>,
+) -> Result<BTreeMap<u32, GlyphId>, ReadError> {
+    let cmap = match font.cmap() {
+        Ok(cmap) => cmap,
+        Err(ReadError::TableIsMissing(_)) => return Ok(Default::default()),
+        Err(e) => return Err(e),
+    };
+    let unicodes: BTreeMap<_, _> = unicode_mappings(&cmap)?
+        .filter(|(cp, gid)| input.unicodes.contains(cp) || input.glyph_ids.contains(gid))
+        .collect();
+    glyphs.extend(unicodes.values().copied());
+    if let Some(cmap14) = variation_subtable(&cmap)? {
+        glyphs.extend(cmap14.iter().filter_map(|(cp, _, variant)| match variant {
+            MapVariant::Variant(gid) if unicodes.contains_key(&cp) => Some(gid),
+            _ => None,
+        }));
+    }
+    Ok(unicodes)
+}
+
+/// Builds a new character map for the retained codepoints.
+pub(super) fn subset(plan: &Plan) -> Result<Option<Cmap>, ReadError> {
+    let cmap = match plan.font.cmap() {
+        Ok(cmap) => cmap,
+        Err(ReadError::TableIsMissing(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mappings = plan
+        .unicodes
+        .iter()
+        .filter_map(|(cp, gid)| Some((char::from_u32(*cp)?, plan.glyph_map.get(*gid)?)));
+    let mut new_cmap = Cmap::from_mappings(mappings);
+    if let Some(cmap14) = variation_subtable(&cmap)?.and_then(|cmap14| subset_cmap14(&cmap14, plan))
+    {
+        add_variation_subtable(&mut new_cmap, cmap14);
+    }
+    Ok(Some(new_cmap))
+}
+
+/// Adds a format 14 subtable to a character map.
+pub(crate) fn add_variation_subtable(cmap: &mut Cmap, cmap14: Cmap14) {
+    // keep records sorted by platform and encoding
+    let pos = cmap
+        .encoding_records
+        .iter()
+        .take_while(|record| record.platform_id == PlatformId::Unicode)
+        .count();
+    cmap.encoding_records.insert(
+        pos,
+        EncodingRecord::new(
+            PlatformId::Unicode,
+            UNICODE_VARIATION_ENCODING,
+            CmapSubtable::Format14(cmap14),
+        ),
+    );
+}
+
+/// Returns the mappings from the best available Unicode subtable.
+///
+/// A format 12 subtable is preferred, as it covers the full Unicode range.
+pub(crate) fn unicode_mappings<'a>(
+    cmap: &read::Cmap<'a>,
+) -> Result<impl Iterator<Item = (u32, GlyphId)> + 'a, ReadError> {
+    let mut format4 = None;
+    let mut format12 = None;
+    for record in cmap.encoding_records() {
+        let is_unicode = match record.platform_id() {
+            read::PlatformId::Unicode => true,
+            read::PlatformId::Windows => [1, 10].contains(&record.encoding_id()),
+            _ => false,
+        };
+        if !is_unicode {
+            continue;
+        }
+        match record.subtable(cmap.offset_data())? {
+            ReadSubtable::Format4(subtable) => format4 = format4.or(Some(subtable)),
+            ReadSubtable::Format12(subtable) => format12 = format12.or(Some(subtable)),
+            _ => (),
+        }
+    }
+    let format4 = format4.filter(|_| format12.is_none());
+    Ok(format12
+        .into_iter()
+        .flat_map(|subtable| subtable.iter())
+        .chain(format4.into_iter().flat_map(|subtable| subtable.iter())))
+}
+
+pub(crate) fn variation_subtable<'a>(
+    cmap: &read::Cmap<'a>,
+) -> Result<Option<read::Cmap14<'a>>, ReadError> {
+    for record in cmap.encoding_records() {
+        if let ReadSubtable::Format14(subtable) = record.subtable(cmap.offset_data())? {
+            return Ok(Some(subtable));
+        }
+    }
+    Ok(None)
+}
+
+/// Retains the variation sequences of retained codepoints.
+fn subset_cmap14(cmap14: &read::Cmap14, plan: &Plan) -> Option<Cmap14> {
+    let mut sequences: BTreeMap<u32, (Vec<u32>, Vec<UvsMapping>)> = BTreeMap::new();
+    for (cp, selector, variant) in cmap14.iter() {
+        if !plan.unicodes.contains_key(&cp) {
+            continue;
+        }
+        let (defaults, mappings) = sequences.entry(selector).or_default();
+        match variant {
+            MapVariant::UseDefault => defaults.push(cp),
+            MapVariant::Variant(gid) => {
+                if let Some(new_gid) = plan.glyph_map.get(gid) {
+                    mappings.push(UvsMapping::new(Uint24::new(cp), new_gid.to_u16()));
+                }
+            }
+        }
+    }
+    build_cmap14(sequences)
+}
+
+/// Builds a format 14 subtable from the default codepoints and the glyph
+/// mappings of each variation selector, returning `None` if there are no
+/// sequences.
+///
+/// Codepoints must be sorted.
+pub(crate) fn build_cmap14(
+    sequences: BTreeMap<u32, (Vec<u32>, Vec<UvsMapping>)>,
+) -> Option<Cmap14> {
+    if sequences.is_empty() {
+        return None;
+    }
+
+    // identical subtables are shared when written, so only count them once
+    let mut seen_defaults = HashSet::new();
+    let mut seen_mappings = HashSet::new();
+    let mut length = CMAP14_HEADER_LEN + VARIATION_SELECTOR_LEN * sequences.len() as u32;
+    let var_selector: Vec<_> = sequences
+        .into_iter()
+        .map(|(selector, (defaults, mappings))| {
+            let ranges = unicode_ranges(&defaults);
+            let default_uvs = (!ranges.is_empty()).then(|| {
+                if seen_defaults.insert(ranges.clone()) {
+                    length += UVS_HEADER_LEN + UNICODE_RANGE_LEN * ranges.len() as u32;
+                }
+                DefaultUvs::new(ranges.len() as u32, ranges)
+            });
+            let non_default_uvs = (!mappings.is_empty()).then(|| {
+                if seen_mappings.insert(mappings.clone()) {
+                    length += UVS_HEADER_LEN + UVS_MAPPING_LEN * mappings.len() as u32;
+                }
+                NonDefaultUvs::new(mappings.len() as u32, mappings)
+            });
+            VariationSelector::new(Uint24::new(selector), default_uvs, non_default_uvs)
+        })
+        .collect();
+    Some(Cmap14::new(length, var_selector.len() as u32, var_selector))
+}
+
+/// Groups sorted codepoints into ranges of at most 256 codepoints.
+fn unicode_ranges(codepoints: &[u32]) -> Vec<UnicodeRange> {
+    let mut ranges: Vec<UnicodeRange> = Vec::new();
+    for cp in codepoints {
+        match ranges.last_mut() {
+            Some(range)
+                if u32::from(range.start_unicode_value) + range.additional_count as u32 + 1
+                    == *cp
+                    && range.additional_count < u8::MAX =>
+            {
+                range.additional_count += 1
+            }
+            _ => ranges.push(UnicodeRange::new(Uint24::new(*cp), 0)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::FontRef;
+
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let ranges = unicode_ranges(&[1, 2, 3, 5, 6, 300]);
+        let ranges: Vec<_> = ranges
+            .iter()
+            .map(|range| (u32::from(range.start_unicode_value), range.additional_count))
+            .collect();
+        assert_eq!(ranges, [(1, 2), (5, 1), (300, 0)]);
+
+        let long: Vec<_> = (0..300).collect();
+        assert_eq!(unicode_ranges(&long).len(), 2);
+    }
+
+    #[test]
+    fn codepoints_for_requested_glyphs() {
+        let font = FontRef::new(font_test_data::CMAP12_FONT1).unwrap();
+        let cmap = font.cmap().unwrap();
+        let (cp, gid) = unicode_mappings(&cmap).unwrap().nth(2).unwrap();
+        let mut input = SubsetInput::new();
+        input.add_glyph_ids([gid]);
+        let mut glyphs = BTreeSet::new();
+        let unicodes = closure(&font, &input, &mut glyphs).unwrap();
+        assert_eq!(unicodes.get(&cp), Some(&gid));
+        assert!(unicodes.values().all(|mapped| *mapped == gid));
+        assert!(glyphs.contains(&gid));
+    }
+}
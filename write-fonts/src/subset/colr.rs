@@ -0,0 +1,452 @@
+//! Subsetting color glyphs.
+//!
+//! The table is rebuilt from the retained color glyphs: only the layers and
+//! clip boxes that they use are kept, glyph identifiers and palette indices
+//! are remapped, and the layer list is packed again by [`ColrBuilder`].
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use read_fonts::{ReadError, TableProvider};
+use types::{GlyphId, Tag};
+
+use super::{GlyphMap, Plan};
+use crate::{
+    offsets::{OffsetMarker, WIDTH_32},
+    raw::{read_u16, read_u32},
+    tables::colr::{Colr, ColrBuilder, Paint},
+};
+
+const CPAL: Tag = Tag::new(b"CPAL");
+
+const CPAL_V0_HEADER_LEN: usize = 12;
+const COLOR_RECORD_LEN: usize = 4;
+/// The palette index that refers to the text foreground color.
+const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
+/// Adds the layer glyphs, and the glyphs referenced by the paint graphs, of
+/// retained color glyphs to `glyphs`.
+pub(super) fn closure(colr: &Colr, glyphs: &mut BTreeSet<GlyphId>) {
+    let layers = colr.layer_records.as_deref().unwrap_or_default();
+    let mut layer_glyphs = Vec::new();
+    for record in colr.base_glyph_records.iter().flatten() {
+        if glyphs.contains(&record.glyph_id) {
+            let first = record.first_layer_index as usize;
+            let records = layers
+                .get(first..first + record.num_layers as usize)
+                .unwrap_or_default();
+            layer_glyphs.extend(records.iter().map(|layer| layer.glyph_id));
+        }
+    }
+    glyphs.extend(layer_glyphs);
+
+    let base_paints = base_paints(colr);
+    let layer_paints = layer_paints(colr);
+    let mut stack: Vec<_> = base_paints
+        .iter()
+        .filter(|(gid, _)| glyphs.contains(gid))
+        .map(|(_, paint)| *paint)
+        .collect();
+    let mut visited_glyphs: HashSet<_> = glyphs.clone().into_iter().collect();
+    let mut visited_layers = HashSet::new();
+    while let Some(paint) = stack.pop() {
+        match paint {
+            Paint::ColrLayers(paint) => {
+                let first = paint.first_layer_index as usize;
+                if visited_layers.insert((first, paint.num_layers)) {
+                    let layers = layer_paints
+                        .get(first..first + paint.num_layers as usize)
+                        .unwrap_or_default();
+                    stack.extend(layers.iter().map(AsRef::as_ref));
+                }
+            }
+            Paint::Glyph(paint) => {
+                glyphs.insert(paint.glyph_id);
+            }
+            Paint::ColrGlyph(paint) => {
+                glyphs.insert(paint.glyph_id);
+                if visited_glyphs.insert(paint.glyph_id) {
+                    stack.extend(base_paints.get(&paint.glyph_id).copied());
+                }
+            }
+            _ => (),
+        }
+        stack.extend(children(paint));
+    }
+}
+
+/// Rewrites the `COLR` and `CPAL` tables.
+///
+/// Returns `None` if no color glyphs are retained, in which case both
+/// tables should be dropped. Only the palette entries that are used by the
+/// retained glyphs are kept.
+pub(super) fn subset(colr: &Colr, plan: &Plan) -> Result<Option<ColorTables>, ReadError> {
+    let glyph_map = &plan.glyph_map;
+    let map_glyph = |gid: GlyphId| glyph_map.get(gid).unwrap_or(GlyphId::NOTDEF);
+    let mut builder = ColrBuilder::default();
+    let layers = colr.layer_records.as_deref().unwrap_or_default();
+    for record in colr.base_glyph_records.iter().flatten() {
+        let Some(gid) = glyph_map.get(record.glyph_id) else {
+            continue;
+        };
+        let first = record.first_layer_index as usize;
+        let records = layers
+            .get(first..first + record.num_layers as usize)
+            .unwrap_or_default();
+        builder.add_v0_glyph(
+            gid,
+            records
+                .iter()
+                .map(|layer| (map_glyph(layer.glyph_id), layer.palette_index)),
+        );
+    }
+    let layer_paints = layer_paints(colr);
+    let base_paints = base_paints(colr);
+    for (gid, paint) in &base_paints {
+        if let Some(gid) = glyph_map.get(*gid) {
+            let paint = rebuild_layers(paint, layer_paints, &mut builder);
+            builder.add_v1_glyph(gid, paint);
+        }
+    }
+    for clip in colr.clip_list.iter().flat_map(|list| &list.clips) {
+        let start = clip.start_glyph_id.to_u16();
+        let end = clip.end_glyph_id.to_u16();
+        for gid in (start..=end).map(GlyphId::new) {
+            if !base_paints.contains_key(&gid) {
+                continue;
+            }
+            if let Some(gid) = glyph_map.get(gid) {
+                builder.set_clip_box(gid, clip.clip_box.as_ref().clone());
+            }
+        }
+    }
+    if let Some(store) = colr.item_variation_store.as_ref() {
+        builder.set_variations(colr.var_index_map.as_ref().cloned(), store.clone());
+    }
+    let mut colr = builder.build();
+    if colr.base_glyph_records.is_none() && colr.base_glyph_list.is_none() {
+        return Ok(None);
+    }
+    remap_glyphs(&mut colr, glyph_map);
+
+    // the palette entries used by retained glyphs
+    let mut palette_indices = BTreeSet::new();
+    for_each_palette_index(&mut colr, |idx| {
+        palette_indices.insert(*idx);
+    });
+    palette_indices.remove(&FOREGROUND_PALETTE_INDEX);
+    let palette_indices: Vec<_> = palette_indices.into_iter().collect();
+    let cpal = plan
+        .font
+        .data_for_tag(CPAL)
+        .map(|cpal| subset_cpal(cpal.as_bytes(), &palette_indices))
+        .transpose()?;
+    if cpal.is_some() {
+        let palette_map: BTreeMap<_, _> = palette_indices
+            .iter()
+            .enumerate()
+            .map(|(new, old)| (*old, new as u16))
+            .collect();
+        for_each_palette_index(&mut colr, |idx| {
+            if *idx != FOREGROUND_PALETTE_INDEX {
+                *idx = palette_map.get(idx).copied().unwrap_or_default();
+            }
+        });
+    }
+    Ok(Some((colr, cpal)))
+}
+
+/// A `COLR` table, and the `CPAL` table if the font has one.
+type ColorTables = (Colr, Option<Vec<u8>>);
+
+fn base_paints(colr: &Colr) -> BTreeMap<GlyphId, &Paint> {
+    colr.base_glyph_list
+        .iter()
+        .flat_map(|list| &list.base_glyph_paint_records)
+        .map(|record| (record.glyph_id, record.paint.as_ref()))
+        .collect()
+}
+
+fn layer_paints(colr: &Colr) -> &[OffsetMarker<Paint, WIDTH_32>] {
+    colr.layer_list
+        .as_ref()
+        .map(|list| list.paints.as_slice())
+        .unwrap_or_default()
+}
+
+/// Copies a paint graph, adding the layers that it uses to `builder`.
+fn rebuild_layers(
+    paint: &Paint,
+    layers: &[OffsetMarker<Paint, WIDTH_32>],
+    builder: &mut ColrBuilder,
+) -> Paint {
+    if let Paint::ColrLayers(paint) = paint {
+        let first = paint.first_layer_index as usize;
+        let layers = layers
+            .get(first..first + paint.num_layers as usize)
+            .unwrap_or_default()
+            .iter()
+            .map(|layer| rebuild_layers(layer, layers, builder))
+            .collect();
+        return builder.add_layers(layers);
+    }
+    let mut paint = paint.clone();
+    for child in children_mut(&mut paint) {
+        *child = rebuild_layers(child, layers, builder);
+    }
+    paint
+}
+
+/// Calls `f` with every paint in the base glyph and layer lists, including
+/// the paints that they draw.
+fn for_each_paint(colr: &mut Colr, mut f: impl FnMut(&mut Paint)) {
+    let base_paints = colr
+        .base_glyph_list
+        .iter_mut()
+        .flat_map(|list| &mut list.base_glyph_paint_records)
+        .map(|record| record.paint.as_mut());
+    let layer_paints = colr
+        .layer_list
+        .iter_mut()
+        .flat_map(|list| &mut list.paints)
+        .map(AsMut::as_mut);
+    let mut stack: Vec<_> = base_paints.chain(layer_paints).collect();
+    while let Some(paint) = stack.pop() {
+        f(paint);
+        stack.extend(children_mut(paint));
+    }
+}
+
+fn remap_glyphs(colr: &mut Colr, glyph_map: &GlyphMap) {
+    let map_glyph = |gid: &mut GlyphId| *gid = glyph_map.get(*gid).unwrap_or(GlyphId::NOTDEF);
+    for_each_paint(colr, |paint| match paint {
+        Paint::Glyph(paint) => map_glyph(&mut paint.glyph_id),
+        Paint::ColrGlyph(paint) => map_glyph(&mut paint.glyph_id),
+        _ => (),
+    });
+}
+
+fn for_each_palette_index(colr: &mut Colr, mut f: impl FnMut(&mut u16)) {
+    for layer in colr.layer_records.iter_mut().flatten() {
+        f(&mut layer.palette_index);
+    }
+    for_each_paint(colr, |paint| match paint {
+        Paint::Solid(paint) => f(&mut paint.palette_index),
+        Paint::VarSolid(paint) => f(&mut paint.palette_index),
+        Paint::LinearGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        Paint::RadialGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        Paint::SweepGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        Paint::VarLinearGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        Paint::VarRadialGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        Paint::VarSweepGradient(paint) => paint
+            .color_line
+            .color_stops
+            .iter_mut()
+            .for_each(|stop| f(&mut stop.palette_index)),
+        _ => (),
+    });
+}
+
+/// Expands to the paints drawn by a paint, other than through the layer
+/// list, using `$as` (`as_ref` or `as_mut`) to access each child.
+macro_rules! child_paints {
+    ($paint:expr, $as:ident) => {
+        match $paint {
+            Paint::Glyph(paint) => vec![paint.paint.$as()],
+            Paint::Transform(paint) => vec![paint.paint.$as()],
+            Paint::VarTransform(paint) => vec![paint.paint.$as()],
+            Paint::Translate(paint) => vec![paint.paint.$as()],
+            Paint::VarTranslate(paint) => vec![paint.paint.$as()],
+            Paint::Scale(paint) => vec![paint.paint.$as()],
+            Paint::VarScale(paint) => vec![paint.paint.$as()],
+            Paint::ScaleAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::VarScaleAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::ScaleUniform(paint) => vec![paint.paint.$as()],
+            Paint::VarScaleUniform(paint) => vec![paint.paint.$as()],
+            Paint::ScaleUniformAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::VarScaleUniformAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::Rotate(paint) => vec![paint.paint.$as()],
+            Paint::VarRotate(paint) => vec![paint.paint.$as()],
+            Paint::RotateAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::VarRotateAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::Skew(paint) => vec![paint.paint.$as()],
+            Paint::VarSkew(paint) => vec![paint.paint.$as()],
+            Paint::SkewAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::VarSkewAroundCenter(paint) => vec![paint.paint.$as()],
+            Paint::Composite(paint) => {
+                vec![paint.source_paint.$as(), paint.backdrop_paint.$as()]
+            }
+            _ => Vec::new(),
+        }
+    };
+}
+
+fn children(paint: &Paint) -> Vec<&Paint> {
+    child_paints!(paint, as_ref)
+}
+
+fn children_mut(paint: &mut Paint) -> Vec<&mut Paint> {
+    child_paints!(paint, as_mut)
+}
+
+/// Rewrites a [CPAL] table, keeping only the given palette entries in each
+/// palette.
+///
+/// [CPAL]: https://learn.microsoft.com/en-us/typography/opentype/spec/cpal
+fn subset_cpal(data: &[u8], palette_indices: &[u16]) -> Result<Vec<u8>, ReadError> {
+    let version = read_u16(data, 0)?;
+    let num_palettes = read_u16(data, 4)? as usize;
+    let color_records_offset = read_u32(data, 8)? as usize;
+    let num_entries = palette_indices.len();
+
+    let mut colors = Vec::new();
+    for palette in 0..num_palettes {
+        let first = read_u16(data, CPAL_V0_HEADER_LEN + palette * 2)? as usize;
+        for idx in palette_indices {
+            let pos = color_records_offset + (first + *idx as usize) * COLOR_RECORD_LEN;
+            let record = data
+                .get(pos..pos + COLOR_RECORD_LEN)
+                .ok_or(ReadError::OutOfBounds)?;
+            colors.extend_from_slice(record);
+        }
+    }
+
+    let mut header_len = CPAL_V0_HEADER_LEN + num_palettes * 2;
+    // the palette types, palette labels and palette entry labels
+    let mut arrays = Vec::new();
+    if version > 0 {
+        let v1_header_pos = header_len;
+        header_len += 12;
+        let array = |idx: usize, len: usize| -> Result<Option<&[u8]>, ReadError> {
+            let offset = read_u32(data, v1_header_pos + idx * 4)? as usize;
+            if offset == 0 {
+                return Ok(None);
+            }
+            data.get(offset..offset + len)
+                .map(Some)
+                .ok_or(ReadError::OutOfBounds)
+        };
+        arrays.push(array(0, num_palettes * 4)?.map(<[u8]>::to_vec));
+        arrays.push(array(1, num_palettes * 2)?.map(<[u8]>::to_vec));
+        let num_source_entries = read_u16(data, 2)? as usize;
+        arrays.push(array(2, num_source_entries * 2)?.map(|labels| {
+            palette_indices
+                .iter()
+                .flat_map(|idx| {
+                    let pos = *idx as usize * 2;
+                    labels.get(pos..pos + 2).unwrap_or(&[0xFF, 0xFF]).to_owned()
+                })
+                .collect()
+        }));
+    }
+
+    let mut cpal = version.to_be_bytes().to_vec();
+    cpal.extend_from_slice(&(num_entries as u16).to_be_bytes());
+    cpal.extend_from_slice(&(num_palettes as u16).to_be_bytes());
+    cpal.extend_from_slice(&((num_palettes * num_entries) as u16).to_be_bytes());
+    cpal.extend_from_slice(&(header_len as u32).to_be_bytes());
+    for palette in 0..num_palettes {
+        cpal.extend_from_slice(&((palette * num_entries) as u16).to_be_bytes());
+    }
+    let mut offset = header_len + colors.len();
+    for array in &arrays {
+        match array {
+            Some(array) => {
+                cpal.extend_from_slice(&(offset as u32).to_be_bytes());
+                offset += array.len();
+            }
+            None => cpal.extend_from_slice(&0u32.to_be_bytes()),
+        }
+    }
+    cpal.extend(colors);
+    cpal.extend(arrays.into_iter().flatten().flatten());
+    Ok(cpal)
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{tables::cpal::Cpal, FontData, FontRead, FontRef};
+
+    use super::*;
+    use crate::{
+        from_obj::ToOwnedTable,
+        subset::{subset_font, SubsetInput},
+    };
+
+    #[test]
+    fn unused_paints_are_dropped() {
+        let font = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let source_data = font.data_for_tag(Tag::new(b"COLR")).unwrap();
+        let source: Colr = font.colr().unwrap().to_owned_table();
+        let gid = source
+            .base_glyph_list
+            .as_ref()
+            .unwrap()
+            .base_glyph_paint_records[0]
+            .glyph_id;
+
+        let mut input = SubsetInput::new();
+        input.add_glyph_ids([gid]);
+        let subset = subset_font(&font, &input).unwrap();
+        let subset = FontRef::new(&subset).unwrap();
+        let num_glyphs = subset.maxp().unwrap().num_glyphs();
+        let data = subset.data_for_tag(Tag::new(b"COLR")).unwrap();
+        assert!(data.len() < source_data.len());
+        let mut colr: Colr = subset.colr().unwrap().to_owned_table();
+        assert!(layer_paints(&colr).len() < layer_paints(&source).len());
+
+        let mut glyphs = Vec::new();
+        glyphs.extend(base_paints(&colr).into_keys());
+        for clip in colr.clip_list.iter().flat_map(|list| &list.clips) {
+            glyphs.extend([clip.start_glyph_id, clip.end_glyph_id]);
+        }
+        for_each_paint(&mut colr, |paint| match paint {
+            Paint::Glyph(paint) => glyphs.push(paint.glyph_id),
+            Paint::ColrGlyph(paint) => glyphs.push(paint.glyph_id),
+            _ => (),
+        });
+        assert!(!glyphs.is_empty());
+        for gid in glyphs {
+            assert!(gid != GlyphId::NOTDEF && gid.to_u16() < num_glyphs, "{gid}");
+        }
+    }
+
+    #[test]
+    fn cpal_entries() {
+        let font = FontRef::new(font_test_data::COLRV0V1).unwrap();
+        let data = font.data_for_tag(CPAL).unwrap();
+        let source = Cpal::read(data).unwrap();
+        let subset = subset_cpal(data.as_bytes(), &[1, 3]).unwrap();
+        let subset = Cpal::read(FontData::new(&subset)).unwrap();
+        assert_eq!(subset.num_palettes(), source.num_palettes());
+        assert_eq!(subset.num_palette_entries(), 2);
+        let colors = |cpal: &Cpal, palette: usize, entry: usize| {
+            let first = cpal.color_record_indices()[palette].get() as usize;
+            let record = &cpal.color_records_array().unwrap().unwrap()[first + entry];
+            (record.red(), record.green(), record.blue(), record.alpha())
+        };
+        for palette in 0..source.num_palettes() as usize {
+            assert_eq!(colors(&subset, palette, 0), colors(&source, palette, 1));
+            assert_eq!(colors(&subset, palette, 1), colors(&source, palette, 3));
+        }
+    }
+}
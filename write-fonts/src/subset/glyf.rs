@@ -0,0 +1,225 @@
+//! Subsetting TrueType outlines.
+
+use std::collections::BTreeSet;
+
+use read_fonts::{
+    tables::{glyf::CompositeGlyphFlags, loca::Loca as ReadLoca},
+    FontRef, ReadError, TableProvider,
+};
+use types::{GlyphId, Tag};
+
+use super::{read_error, Plan, SubsetError};
+use crate::{
+    tables::loca::{Loca, LocaFormat},
+    FontBuilder,
+};
+
+const GLYF: Tag = Tag::new(b"glyf");
+const LOCA: Tag = Tag::new(b"loca");
+/// The size of the header shared by simple and composite glyphs.
+const GLYPH_HEADER_LEN: usize = 10;
+
+/// The `glyf` and `loca` tables of a font.
+pub(crate) struct Outlines<'a> {
+    glyf: &'a [u8],
+    loca: ReadLoca<'a>,
+}
+
+impl<'a> Outlines<'a> {
+    pub(crate) fn new(font: &FontRef<'a>) -> Result<Option<Self>, ReadError> {
+        let Some(glyf) = font.data_for_tag(GLYF) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            glyf: glyf.as_bytes(),
+            loca: font.loca(None)?,
+        }))
+    }
+
+    /// Returns the data for a glyph, which is empty if the glyph has no
+    /// outline or is out of bounds.
+    pub(crate) fn glyph_data(&self, gid: GlyphId) -> &'a [u8] {
+        let idx = gid.to_u16() as usize;
+        self.loca
+            .get_raw(idx)
+            .zip(self.loca.get_raw(idx + 1))
+            .and_then(|(start, end)| self.glyf.get(start as usize..end as usize))
+            .unwrap_or_default()
+    }
+}
+
+/// Adds the components of retained composite glyphs to `glyphs`.
+pub(super) fn closure(font: &FontRef, glyphs: &mut BTreeSet<GlyphId>) -> Result<(), ReadError> {
+    let Some(outlines) = Outlines::new(font)? else {
+        return Ok(());
+    };
+    let mut stack: Vec<_> = glyphs.iter().copied().collect();
+    while let Some(gid) = stack.pop() {
+        let data = outlines.glyph_data(gid);
+        for pos in component_positions(data) {
+            let component = GlyphId::new(u16::from_be_bytes([data[pos], data[pos + 1]]));
+            if glyphs.insert(component) {
+                stack.push(component);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the `glyf` and `loca` tables, returning the new loca format.
+///
+/// Glyph data is copied unchanged, apart from the glyph identifiers of
+/// components.
+pub(super) fn subset(
+    plan: &Plan,
+    builder: &mut FontBuilder,
+) -> Result<Option<LocaFormat>, SubsetError> {
+    let Some(outlines) = Outlines::new(&plan.font).map_err(read_error(LOCA))? else {
+        return Ok(None);
+    };
+    let mut glyf = Vec::new();
+    let mut offsets = vec![0u32];
+    for gid in plan.glyph_map.old_glyphs() {
+        let start = glyf.len();
+        glyf.extend_from_slice(outlines.glyph_data(gid));
+        for pos in component_positions(&glyf[start..]) {
+            let pos = start + pos;
+            let component = GlyphId::new(u16::from_be_bytes([glyf[pos], glyf[pos + 1]]));
+            let new_gid = plan.glyph_map.get(component).unwrap_or(GlyphId::NOTDEF);
+            glyf[pos..pos + 2].copy_from_slice(&new_gid.to_be_bytes());
+        }
+        // keep offsets even, so that they can be stored in a short loca
+        if glyf.len() % 2 != 0 {
+            glyf.push(0);
+        }
+        offsets.push(glyf.len() as u32);
+    }
+    let loca = Loca::new(offsets);
+    let format = loca.format();
+    builder.add_raw(GLYF, glyf).add_table(&loca)?;
+    Ok(Some(format))
+}
+
+/// Returns the positions of the component glyph identifiers in the data
+/// of a composite glyph.
+///
+/// This is empty for simple glyphs and for malformed data.
+pub(crate) fn component_positions(data: &[u8]) -> Vec<usize> {
+    component_records(data)
+        .0
+        .into_iter()
+        .map(|pos| pos + 2)
+        .collect()
+}
+
+/// Returns the glyph data without its instructions.
+///
+/// Malformed data is returned unchanged.
+pub(crate) fn strip_instructions(data: &[u8]) -> Vec<u8> {
+    let Some(num_contours) = data
+        .get(..2)
+        .map(|num_contours| i16::from_be_bytes([num_contours[0], num_contours[1]]))
+    else {
+        return data.to_vec();
+    };
+    if num_contours >= 0 {
+        let pos = GLYPH_HEADER_LEN + 2 * num_contours as usize;
+        let Some(len) = data.get(pos..pos + 2) else {
+            return data.to_vec();
+        };
+        let end = pos + 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+        if end > data.len() {
+            return data.to_vec();
+        }
+        let mut stripped = data[..pos].to_vec();
+        stripped.extend([0, 0]);
+        stripped.extend_from_slice(&data[end..]);
+        return stripped;
+    }
+    let (flag_positions, end) = component_records(data);
+    let mut stripped = data[..end.min(data.len())].to_vec();
+    for pos in flag_positions {
+        let flags = u16::from_be_bytes([stripped[pos], stripped[pos + 1]])
+            & !CompositeGlyphFlags::WE_HAVE_INSTRUCTIONS.bits();
+        stripped[pos..pos + 2].copy_from_slice(&flags.to_be_bytes());
+    }
+    stripped
+}
+
+/// Returns the positions of the flags of each component of a composite
+/// glyph, and the end of the last component.
+fn component_records(data: &[u8]) -> (Vec<usize>, usize) {
+    let mut positions = Vec::new();
+    let is_composite = data
+        .get(..2)
+        .map(|num_contours| i16::from_be_bytes([num_contours[0], num_contours[1]]) < 0)
+        .unwrap_or_default();
+    if !is_composite {
+        return (positions, data.len());
+    }
+    let mut pos = GLYPH_HEADER_LEN;
+    while let Some(flags) = data.get(pos..pos + 4) {
+        let flags =
+            CompositeGlyphFlags::from_bits_truncate(u16::from_be_bytes([flags[0], flags[1]]));
+        positions.push(pos);
+        pos += 4;
+        pos += if flags.contains(CompositeGlyphFlags::ARG_1_AND_2_ARE_WORDS) {
+            4
+        } else {
+            2
+        };
+        if flags.contains(CompositeGlyphFlags::WE_HAVE_A_SCALE) {
+            pos += 2;
+        } else if flags.contains(CompositeGlyphFlags::WE_HAVE_AN_X_AND_Y_SCALE) {
+            pos += 4;
+        } else if flags.contains(CompositeGlyphFlags::WE_HAVE_A_TWO_BY_TWO) {
+            pos += 8;
+        }
+        if !flags.contains(CompositeGlyphFlags::MORE_COMPONENTS) {
+            break;
+        }
+    }
+    (positions, pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composite_components() {
+        let mut data = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0];
+        // word args and a scale, with more components
+        data.extend([0x00, 0x29, 0x00, 0x05, 0, 0, 0, 0, 0x40, 0x00]);
+        // byte args and a 2x2 matrix
+        data.extend([0x00, 0x80, 0x00, 0x07, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let positions = component_positions(&data);
+        assert_eq!(positions, [12, 22]);
+        assert_eq!(data[13], 5);
+        assert_eq!(data[23], 7);
+    }
+
+    #[test]
+    fn instructions_are_stripped() {
+        // one contour, with two bytes of instructions
+        let mut simple = vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0xB0, 0x00, 0x01];
+        let stripped = strip_instructions(&simple);
+        assert_eq!(stripped[12..], [0, 0, 0x01]);
+        simple.truncate(3);
+        assert_eq!(strip_instructions(&simple), simple);
+
+        let mut composite = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0];
+        composite.extend([0x01, 0x00, 0x00, 0x05, 0, 0]);
+        composite.extend([0, 1, 0xB0]);
+        let stripped = strip_instructions(&composite);
+        assert_eq!(stripped.len(), 16);
+        assert_eq!(stripped[10..12], [0, 0]);
+        assert_eq!(component_positions(&stripped), [12]);
+    }
+
+    #[test]
+    fn simple_glyphs_have_no_components() {
+        assert!(component_positions(&[0, 1, 0, 0]).is_empty());
+        assert!(component_positions(&[]).is_empty());
+    }
+}
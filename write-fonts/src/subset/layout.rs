@@ -0,0 +1,1234 @@
+//! Subsetting the OpenType layout tables.
+//!
+//! The tables are converted to their owned representations and subset in
+//! place; glyph identifiers, lookup indices and feature indices are all
+//! remapped.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use types::{GlyphId, NameId, Tag};
+
+use super::GlyphMap;
+use crate::tables::{
+    gdef::Gdef,
+    gpos::{
+        self, CursivePosFormat1, Gpos, MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1,
+        MarkRecord, PairPos, PositionChainContext, PositionLookup, PositionSequenceContext,
+        SinglePos,
+    },
+    gsub::{
+        self, AlternateSubstFormat1, Gsub, LigatureSubstFormat1, MultipleSubstFormat1,
+        ReverseChainSingleSubstFormat1, SingleSubst, SingleSubstFormat1, SingleSubstFormat2,
+        SubstitutionChainContext, SubstitutionLookup, SubstitutionSequenceContext,
+    },
+    layout::{
+        ChainedSequenceContext, ClassDef, CoverageTable, Feature, FeatureList, FeatureParams,
+        FeatureVariations, LangSys, Lookup, ScriptList, SequenceContext, SequenceLookupRecord,
+    },
+};
+
+/// Applies the substitutions of the selected lookups to `glyphs`, until no
+/// new glyphs are reachable.
+pub(super) fn gsub_closure(
+    gsub: &Gsub,
+    features: Option<&BTreeSet<Tag>>,
+    glyphs: &mut BTreeSet<GlyphId>,
+) {
+    let lookups = &gsub.lookup_list.lookups;
+    let selected = select_features(&gsub.feature_list, features);
+    let selected = reachable_lookups(
+        &gsub.feature_list,
+        gsub.feature_variations.as_ref(),
+        &selected,
+        |idx| nested_lookups(lookups.get(idx as usize).map(|lookup| &**lookup)),
+    );
+    loop {
+        let mut added = BTreeSet::new();
+        for lookup in selected.iter().filter_map(|idx| lookups.get(*idx as usize)) {
+            lookup.closure(glyphs, &mut added);
+        }
+        let num_glyphs = glyphs.len();
+        glyphs.extend(added);
+        if glyphs.len() == num_glyphs {
+            break;
+        }
+    }
+}
+
+/// Subsets a GSUB table, returning `None` if no lookups remain.
+pub(crate) fn subset_gsub(
+    mut gsub: Gsub,
+    glyphs: &GlyphMap,
+    features: Option<&BTreeSet<Tag>>,
+) -> Option<Gsub> {
+    let features = select_features(&gsub.feature_list, features);
+    let lookups = &gsub.lookup_list.lookups;
+    let reachable = reachable_lookups(
+        &gsub.feature_list,
+        gsub.feature_variations.as_ref(),
+        &features,
+        |idx| nested_lookups(lookups.get(idx as usize).map(|lookup| &**lookup)),
+    );
+    let lookup_map = subset_lookups(&mut gsub.lookup_list.lookups, &reachable, glyphs)?;
+    subset_features(
+        &mut gsub.script_list,
+        &mut gsub.feature_list,
+        gsub.feature_variations.as_mut(),
+        &features,
+        &lookup_map,
+    );
+    Some(gsub)
+}
+
+/// Subsets a GPOS table, returning `None` if no lookups remain.
+pub(crate) fn subset_gpos(
+    mut gpos: Gpos,
+    glyphs: &GlyphMap,
+    features: Option<&BTreeSet<Tag>>,
+) -> Option<Gpos> {
+    let features = select_features(&gpos.feature_list, features);
+    let lookups = &gpos.lookup_list.lookups;
+    let reachable = reachable_lookups(
+        &gpos.feature_list,
+        gpos.feature_variations.as_ref(),
+        &features,
+        |idx| nested_lookups(lookups.get(idx as usize).map(|lookup| &**lookup)),
+    );
+    let lookup_map = subset_lookups(&mut gpos.lookup_list.lookups, &reachable, glyphs)?;
+    subset_features(
+        &mut gpos.script_list,
+        &mut gpos.feature_list,
+        gpos.feature_variations.as_mut(),
+        &features,
+        &lookup_map,
+    );
+    Some(gpos)
+}
+
+/// Remaps the glyphs of a GDEF table.
+///
+/// Mark glyph sets are retained even if they become empty, since they are
+/// referenced by index from lookups.
+pub(crate) fn subset_gdef(mut gdef: Gdef, glyphs: &GlyphMap) -> Gdef {
+    if let Some(class_def) = gdef.glyph_class_def.as_mut() {
+        subset_class_def(class_def, glyphs);
+    }
+    if let Some(class_def) = gdef.mark_attach_class_def.as_mut() {
+        subset_class_def(class_def, glyphs);
+    }
+    let has_attachments = gdef.attach_list.as_mut().map(|attach_list| {
+        retain_covered(
+            &mut attach_list.coverage,
+            &mut attach_list.attach_points,
+            glyphs,
+            |_| true,
+        )
+    });
+    if has_attachments == Some(false) {
+        gdef.attach_list.clear();
+    }
+    let has_carets = gdef.lig_caret_list.as_mut().map(|lig_caret_list| {
+        retain_covered(
+            &mut lig_caret_list.coverage,
+            &mut lig_caret_list.lig_glyphs,
+            glyphs,
+            |_| true,
+        )
+    });
+    if has_carets == Some(false) {
+        gdef.lig_caret_list.clear();
+    }
+    if let Some(mark_glyph_sets) = gdef.mark_glyph_sets_def.as_mut() {
+        for coverage in mark_glyph_sets.coverages.iter_mut() {
+            subset_coverage(coverage, glyphs);
+        }
+    }
+    gdef
+}
+
+/// Returns the name identifiers referenced by feature parameters.
+pub(super) fn feature_name_ids(feature_list: &FeatureList) -> Vec<NameId> {
+    let mut ids = Vec::new();
+    for record in &feature_list.feature_records {
+        match record.feature.feature_params.as_ref() {
+            Some(FeatureParams::Size(params)) if params.name_entry != 0 => {
+                ids.push(NameId::new(params.name_entry))
+            }
+            Some(FeatureParams::StylisticSet(params)) => ids.push(params.ui_name_id),
+            Some(FeatureParams::CharacterVariant(params)) => {
+                ids.extend([
+                    params.feat_ui_label_name_id,
+                    params.feat_ui_tooltip_text_name_id,
+                    params.sample_text_name_id,
+                ]);
+                let first = params.first_param_ui_label_name_id.to_u16();
+                ids.extend(
+                    (0..params.num_named_parameters).map(|i| NameId::new(first.saturating_add(i))),
+                );
+            }
+            _ => (),
+        }
+    }
+    ids
+}
+
+/// Returns the indices of features matching the requested tags.
+fn select_features(feature_list: &FeatureList, tags: Option<&BTreeSet<Tag>>) -> BTreeSet<u16> {
+    feature_list
+        .feature_records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| tags.is_none_or(|tags| tags.contains(&record.feature_tag)))
+        .map(|(idx, _)| idx as u16)
+        .collect()
+}
+
+/// Returns the indices of lookups referenced by the selected features
+/// (including their feature variation substitutions), and of any lookups
+/// that those reference in turn.
+fn reachable_lookups(
+    feature_list: &FeatureList,
+    feature_variations: Option<&FeatureVariations>,
+    features: &BTreeSet<u16>,
+    nested: impl Fn(u16) -> Vec<u16>,
+) -> BTreeSet<u16> {
+    let mut lookups: BTreeSet<u16> = features
+        .iter()
+        .filter_map(|idx| feature_list.feature_records.get(*idx as usize))
+        .flat_map(|record| record.feature.lookup_list_indices.iter().copied())
+        .collect();
+    let substitutions = feature_variations
+        .into_iter()
+        .flat_map(|variations| variations.feature_variation_records.iter())
+        .filter_map(|record| record.feature_table_substitution.as_ref())
+        .flat_map(|substitution| substitution.substitutions.iter());
+    for substitution in substitutions {
+        if features.contains(&substitution.feature_index) {
+            lookups.extend(substitution.alternate_feature.lookup_list_indices.iter());
+        }
+    }
+    let mut stack: Vec<_> = lookups.iter().copied().collect();
+    while let Some(idx) = stack.pop() {
+        for nested_idx in nested(idx) {
+            if lookups.insert(nested_idx) {
+                stack.push(nested_idx);
+            }
+        }
+    }
+    lookups
+}
+
+fn nested_lookups<L: LayoutLookup>(lookup: Option<&L>) -> Vec<u16> {
+    let mut lookups = Vec::new();
+    if let Some(lookup) = lookup {
+        lookup.nested_lookups(&mut lookups);
+    }
+    lookups
+}
+
+/// Subsets the reachable lookups and drops all others, returning the
+/// mapping from old to new lookup indices.
+///
+/// Returns `None` if no lookups remain.
+fn subset_lookups<L: LayoutLookup>(
+    lookups: &mut Vec<crate::OffsetMarker<L>>,
+    reachable: &BTreeSet<u16>,
+    glyphs: &GlyphMap,
+) -> Option<BTreeMap<u16, u16>> {
+    let mut lookup_map = BTreeMap::new();
+    for (idx, mut lookup) in std::mem::take(lookups).into_iter().enumerate() {
+        let idx = idx as u16;
+        if reachable.contains(&idx) && lookup.subset(glyphs) {
+            lookup_map.insert(idx, lookups.len() as u16);
+            lookups.push(lookup);
+        }
+    }
+    if lookups.is_empty() {
+        return None;
+    }
+    for lookup in lookups.iter_mut() {
+        lookup.remap_lookups(&lookup_map);
+    }
+    Some(lookup_map)
+}
+
+/// Drops features that were not selected or that no longer have any lookups,
+/// and remaps the feature indices of scripts and feature variations.
+fn subset_features(
+    script_list: &mut ScriptList,
+    feature_list: &mut FeatureList,
+    mut feature_variations: Option<&mut FeatureVariations>,
+    features: &BTreeSet<u16>,
+    lookup_map: &BTreeMap<u16, u16>,
+) {
+    // features may be empty by default, and only have lookups under some
+    // variation conditions
+    let mut substituted = BTreeSet::new();
+    if let Some(variations) = feature_variations.as_deref_mut() {
+        let substitutions = variations
+            .feature_variation_records
+            .iter_mut()
+            .filter_map(|record| record.feature_table_substitution.as_mut());
+        for substitution in substitutions {
+            substitution.substitutions.retain_mut(|record| {
+                remap_feature(&mut record.alternate_feature, lookup_map);
+                if !record.alternate_feature.lookup_list_indices.is_empty() {
+                    substituted.insert(record.feature_index);
+                }
+                features.contains(&record.feature_index)
+            });
+        }
+    }
+
+    let mut feature_map = BTreeMap::new();
+    for (idx, mut record) in std::mem::take(&mut feature_list.feature_records)
+        .into_iter()
+        .enumerate()
+    {
+        let idx = idx as u16;
+        if !features.contains(&idx) {
+            continue;
+        }
+        remap_feature(&mut record.feature, lookup_map);
+        if record.feature.lookup_list_indices.is_empty() && !substituted.contains(&idx) {
+            continue;
+        }
+        feature_map.insert(idx, feature_list.feature_records.len() as u16);
+        feature_list.feature_records.push(record);
+    }
+
+    if let Some(variations) = feature_variations {
+        let substitutions = variations
+            .feature_variation_records
+            .iter_mut()
+            .filter_map(|record| record.feature_table_substitution.as_mut());
+        for substitution in substitutions {
+            substitution.substitutions.retain_mut(|record| {
+                match feature_map.get(&record.feature_index) {
+                    Some(new_idx) => {
+                        record.feature_index = *new_idx;
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+    }
+    for record in script_list.script_records.iter_mut() {
+        let script = &mut *record.script;
+        if let Some(lang_sys) = script.default_lang_sys.as_mut() {
+            remap_lang_sys(lang_sys, &feature_map);
+        }
+        for record in script.lang_sys_records.iter_mut() {
+            remap_lang_sys(&mut record.lang_sys, &feature_map);
+        }
+    }
+}
+
+pub(crate) fn remap_feature(feature: &mut Feature, lookup_map: &BTreeMap<u16, u16>) {
+    feature.lookup_list_indices = feature
+        .lookup_list_indices
+        .iter()
+        .filter_map(|idx| lookup_map.get(idx).copied())
+        .collect();
+}
+
+pub(crate) fn remap_lang_sys(lang_sys: &mut LangSys, feature_map: &BTreeMap<u16, u16>) {
+    lang_sys.required_feature_index = feature_map
+        .get(&lang_sys.required_feature_index)
+        .copied()
+        .unwrap_or(0xFFFF);
+    lang_sys.feature_indices = lang_sys
+        .feature_indices
+        .iter()
+        .filter_map(|idx| feature_map.get(idx).copied())
+        .collect();
+}
+
+/// Remaps a coverage table, returning `false` if it is now empty.
+fn subset_coverage(coverage: &mut CoverageTable, glyphs: &GlyphMap) -> bool {
+    *coverage = coverage.iter().filter_map(|gid| glyphs.get(gid)).collect();
+    !coverage.is_empty()
+}
+
+/// Subsets a coverage table along with an array of items in coverage order.
+///
+/// An item is retained if its glyph is retained and `f` returns `true`.
+/// Returns `false` if no items remain.
+fn retain_covered<T>(
+    coverage: &mut CoverageTable,
+    items: &mut Vec<T>,
+    glyphs: &GlyphMap,
+    mut f: impl FnMut(&mut T) -> bool,
+) -> bool {
+    let mut new_glyphs = Vec::new();
+    for (gid, mut item) in coverage.iter().zip(std::mem::take(items)) {
+        if let Some(new_gid) = glyphs.get(gid) {
+            if f(&mut item) {
+                new_glyphs.push(new_gid);
+                items.push(item);
+            }
+        }
+    }
+    *coverage = new_glyphs.into_iter().collect();
+    !items.is_empty()
+}
+
+fn subset_class_def(class_def: &mut ClassDef, glyphs: &GlyphMap) {
+    *class_def = class_def
+        .iter()
+        .filter_map(|(gid, class)| Some((glyphs.get(gid)?, class)))
+        .collect();
+}
+
+/// Remaps a class definition, and renumbers its classes so that they remain
+/// contiguous.
+///
+/// Returns the old classes that are still used, in order; class 0 is always
+/// included.
+fn subset_classes(class_def: &mut ClassDef, glyphs: &GlyphMap) -> Vec<u16> {
+    let mapped: Vec<_> = class_def
+        .iter()
+        .filter_map(|(gid, class)| Some((glyphs.get(gid)?, class)))
+        .collect();
+    let mut classes: BTreeSet<_> = mapped.iter().map(|(_, class)| *class).collect();
+    classes.insert(0);
+    let classes: Vec<_> = classes.into_iter().collect();
+    *class_def = mapped
+        .into_iter()
+        .map(|(gid, class)| (gid, classes.binary_search(&class).unwrap() as u16))
+        .collect();
+    classes
+}
+
+/// Renumbers the classes of mark records so that they remain contiguous,
+/// returning the old classes in order.
+fn subset_mark_classes(marks: &mut [MarkRecord]) -> Vec<u16> {
+    let classes: BTreeSet<_> = marks.iter().map(|mark| mark.mark_class).collect();
+    let classes: Vec<_> = classes.into_iter().collect();
+    for mark in marks.iter_mut() {
+        mark.mark_class = classes.binary_search(&mark.mark_class).unwrap() as u16;
+    }
+    classes
+}
+
+/// Retains the items at the given (sorted) indices.
+fn retain_indices<T>(items: &mut Vec<T>, indices: &[u16]) {
+    *items = std::mem::take(items)
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| indices.binary_search(&(*idx as u16)).is_ok())
+        .map(|(_, item)| item)
+        .collect();
+}
+
+/// Remaps a sequence of glyphs, returning `false` if any are not retained.
+fn subset_sequence(sequence: &mut [GlyphId], glyphs: &GlyphMap) -> bool {
+    for gid in sequence.iter_mut() {
+        match glyphs.get(*gid) {
+            Some(new_gid) => *gid = new_gid,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// The operations needed to subset a GSUB or GPOS lookup.
+pub(crate) trait LayoutLookup {
+    /// Appends the indices of lookups referenced by contextual subtables.
+    fn nested_lookups(&self, lookups: &mut Vec<u16>);
+
+    /// Subsets the subtables, returning `false` if none remain.
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool;
+
+    /// Remaps the indices of lookups referenced by contextual subtables.
+    fn remap_lookups(&mut self, lookup_map: &BTreeMap<u16, u16>);
+
+    /// Adds offsets to the mark attachment class and mark filtering set
+    /// referenced by the lookup flags, if any.
+    ///
+    /// This is used when the lookup is moved into a font whose GDEF table
+    /// has more classes or mark glyph sets.
+    fn offset_mark_references(&mut self, class_offset: u16, set_offset: u16);
+}
+
+/// The operations needed to subset a lookup subtable.
+trait SubsetSubtable {
+    /// Subsets the subtable in place, returning `false` if it no longer has
+    /// any effect.
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool;
+
+    /// Calls `f` with each array of lookup records in the subtable.
+    fn lookup_records(&self, _f: &mut dyn FnMut(&[SequenceLookupRecord])) {}
+
+    /// Calls `f` with each mutable array of lookup records in the subtable.
+    fn lookup_records_mut(&mut self, _f: &mut dyn FnMut(&mut Vec<SequenceLookupRecord>)) {}
+}
+
+/// Glyph closure for GSUB subtables.
+trait SubstitutionClosure {
+    /// Adds the glyphs that may be substituted for `glyphs` to `added`.
+    fn closure(&self, _glyphs: &BTreeSet<GlyphId>, _added: &mut BTreeSet<GlyphId>) {}
+}
+
+fn subset_lookup<T: SubsetSubtable>(lookup: &mut Lookup<T>, glyphs: &GlyphMap) -> bool {
+    lookup
+        .subtables
+        .retain_mut(|subtable| subtable.subset(glyphs));
+    !lookup.subtables.is_empty()
+}
+
+fn lookup_nested_lookups<T: SubsetSubtable>(lookup: &Lookup<T>, lookups: &mut Vec<u16>) {
+    for subtable in &lookup.subtables {
+        subtable.lookup_records(&mut |records| {
+            lookups.extend(records.iter().map(|record| record.lookup_list_index))
+        });
+    }
+}
+
+fn remap_lookup_records<T: SubsetSubtable>(
+    lookup: &mut Lookup<T>,
+    lookup_map: &BTreeMap<u16, u16>,
+) {
+    for subtable in lookup.subtables.iter_mut() {
+        subtable.lookup_records_mut(&mut |records| {
+            records.retain_mut(|record| match lookup_map.get(&record.lookup_list_index) {
+                Some(new_idx) => {
+                    record.lookup_list_index = *new_idx;
+                    true
+                }
+                None => false,
+            })
+        });
+    }
+}
+
+fn offset_mark_references<T>(lookup: &mut Lookup<T>, class_offset: u16, set_offset: u16) {
+    if let Some(class) = lookup.lookup_flag.mark_attachment_type_mask() {
+        lookup
+            .lookup_flag
+            .set_mark_attachment_type(class + class_offset);
+    }
+    if lookup.lookup_flag.use_mark_filtering_set() {
+        lookup.mark_filtering_set += set_offset;
+    }
+}
+
+fn lookup_closure<T: SubstitutionClosure>(
+    lookup: &Lookup<T>,
+    glyphs: &BTreeSet<GlyphId>,
+    added: &mut BTreeSet<GlyphId>,
+) {
+    for subtable in &lookup.subtables {
+        subtable.closure(glyphs, added);
+    }
+}
+
+/// Calls a generic function with the inner lookup of each lookup variant.
+macro_rules! dispatch_lookup {
+    ($ty:ident, $lookup:expr, |$inner:ident| $body:expr, [$($variant:ident),*]) => {
+        match $lookup {
+            $($ty::$variant($inner) => $body,)*
+        }
+    };
+}
+
+macro_rules! dispatch_gsub {
+    ($lookup:expr, |$inner:ident| $body:expr) => {
+        dispatch_lookup!(
+            SubstitutionLookup,
+            $lookup,
+            |$inner| $body,
+            [
+                Single,
+                Multiple,
+                Alternate,
+                Ligature,
+                Contextual,
+                ChainContextual,
+                Extension,
+                Reverse
+            ]
+        )
+    };
+}
+
+macro_rules! dispatch_gpos {
+    ($lookup:expr, |$inner:ident| $body:expr) => {
+        dispatch_lookup!(
+            PositionLookup,
+            $lookup,
+            |$inner| $body,
+            [
+                Single,
+                Pair,
+                Cursive,
+                MarkToBase,
+                MarkToLig,
+                MarkToMark,
+                Contextual,
+                ChainContextual,
+                Extension
+            ]
+        )
+    };
+}
+
+impl LayoutLookup for SubstitutionLookup {
+    fn nested_lookups(&self, lookups: &mut Vec<u16>) {
+        dispatch_gsub!(self, |lookup| lookup_nested_lookups(lookup, lookups))
+    }
+
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        dispatch_gsub!(self, |lookup| subset_lookup(lookup, glyphs))
+    }
+
+    fn remap_lookups(&mut self, lookup_map: &BTreeMap<u16, u16>) {
+        dispatch_gsub!(self, |lookup| remap_lookup_records(lookup, lookup_map))
+    }
+
+    fn offset_mark_references(&mut self, class_offset: u16, set_offset: u16) {
+        dispatch_gsub!(self, |lookup| offset_mark_references(
+            lookup,
+            class_offset,
+            set_offset
+        ))
+    }
+}
+
+impl LayoutLookup for PositionLookup {
+    fn nested_lookups(&self, lookups: &mut Vec<u16>) {
+        dispatch_gpos!(self, |lookup| lookup_nested_lookups(lookup, lookups))
+    }
+
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        dispatch_gpos!(self, |lookup| subset_lookup(lookup, glyphs))
+    }
+
+    fn remap_lookups(&mut self, lookup_map: &BTreeMap<u16, u16>) {
+        dispatch_gpos!(self, |lookup| remap_lookup_records(lookup, lookup_map))
+    }
+
+    fn offset_mark_references(&mut self, class_offset: u16, set_offset: u16) {
+        dispatch_gpos!(self, |lookup| offset_mark_references(
+            lookup,
+            class_offset,
+            set_offset
+        ))
+    }
+}
+
+impl SubstitutionLookup {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        dispatch_gsub!(self, |lookup| lookup_closure(lookup, glyphs, added))
+    }
+}
+
+impl SingleSubst {
+    fn mappings(&self) -> Vec<(GlyphId, GlyphId)> {
+        match self {
+            SingleSubst::Format1(table) => table
+                .coverage
+                .iter()
+                .map(|gid| {
+                    let delta = table.delta_glyph_id as u16;
+                    (gid, GlyphId::new(gid.to_u16().wrapping_add(delta)))
+                })
+                .collect(),
+            SingleSubst::Format2(table) => table
+                .coverage
+                .iter()
+                .zip(table.substitute_glyph_ids.iter().copied())
+                .collect(),
+        }
+    }
+}
+
+impl SubsetSubtable for SingleSubst {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        let mappings: Vec<_> = self
+            .mappings()
+            .into_iter()
+            .filter_map(|(gid, sub)| Some((glyphs.get(gid)?, glyphs.get(sub)?)))
+            .collect();
+        let delta = |(gid, sub): &(GlyphId, GlyphId)| sub.to_u16().wrapping_sub(gid.to_u16());
+        let Some(first_delta) = mappings.first().map(delta) else {
+            return false;
+        };
+        let coverage = mappings.iter().map(|(gid, _)| *gid).collect();
+        *self = if mappings.iter().all(|mapping| delta(mapping) == first_delta) {
+            SingleSubst::Format1(SingleSubstFormat1::new(coverage, first_delta as i16))
+        } else {
+            let substitutes = mappings.iter().map(|(_, sub)| *sub).collect();
+            SingleSubst::Format2(SingleSubstFormat2::new(coverage, substitutes))
+        };
+        true
+    }
+}
+
+impl SubstitutionClosure for SingleSubst {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        added.extend(
+            self.mappings()
+                .into_iter()
+                .filter(|(gid, _)| glyphs.contains(gid))
+                .map(|(_, sub)| sub),
+        );
+    }
+}
+
+impl SubsetSubtable for MultipleSubstFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        retain_covered(
+            &mut self.coverage,
+            &mut self.sequences,
+            glyphs,
+            |sequence| subset_sequence(&mut sequence.substitute_glyph_ids, glyphs),
+        )
+    }
+}
+
+impl SubstitutionClosure for MultipleSubstFormat1 {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        for (gid, sequence) in self.coverage.iter().zip(&self.sequences) {
+            if glyphs.contains(&gid) {
+                added.extend(sequence.substitute_glyph_ids.iter().copied());
+            }
+        }
+    }
+}
+
+impl SubsetSubtable for AlternateSubstFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        retain_covered(
+            &mut self.coverage,
+            &mut self.alternate_sets,
+            glyphs,
+            |set| {
+                set.alternate_glyph_ids = set
+                    .alternate_glyph_ids
+                    .iter()
+                    .filter_map(|gid| glyphs.get(*gid))
+                    .collect();
+                !set.alternate_glyph_ids.is_empty()
+            },
+        )
+    }
+}
+
+impl SubstitutionClosure for AlternateSubstFormat1 {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        for (gid, set) in self.coverage.iter().zip(&self.alternate_sets) {
+            if glyphs.contains(&gid) {
+                added.extend(set.alternate_glyph_ids.iter().copied());
+            }
+        }
+    }
+}
+
+impl SubsetSubtable for LigatureSubstFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        retain_covered(&mut self.coverage, &mut self.ligature_sets, glyphs, |set| {
+            set.ligatures.retain_mut(|ligature| {
+                let Some(new_gid) = glyphs.get(ligature.ligature_glyph) else {
+                    return false;
+                };
+                ligature.ligature_glyph = new_gid;
+                subset_sequence(&mut ligature.component_glyph_ids, glyphs)
+            });
+            !set.ligatures.is_empty()
+        })
+    }
+}
+
+impl SubstitutionClosure for LigatureSubstFormat1 {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        for (gid, set) in self.coverage.iter().zip(&self.ligature_sets) {
+            if !glyphs.contains(&gid) {
+                continue;
+            }
+            for ligature in &set.ligatures {
+                if ligature
+                    .component_glyph_ids
+                    .iter()
+                    .all(|component| glyphs.contains(component))
+                {
+                    added.insert(ligature.ligature_glyph);
+                }
+            }
+        }
+    }
+}
+
+impl SubsetSubtable for ReverseChainSingleSubstFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        let has_substitutes = retain_covered(
+            &mut self.coverage,
+            &mut self.substitute_glyph_ids,
+            glyphs,
+            |sub| match glyphs.get(*sub) {
+                Some(new_gid) => {
+                    *sub = new_gid;
+                    true
+                }
+                None => false,
+            },
+        );
+        has_substitutes
+            && self
+                .backtrack_coverages
+                .iter_mut()
+                .chain(self.lookahead_coverages.iter_mut())
+                .all(|coverage| subset_coverage(coverage, glyphs))
+    }
+}
+
+impl SubstitutionClosure for ReverseChainSingleSubstFormat1 {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        let context_is_reachable = self
+            .backtrack_coverages
+            .iter()
+            .chain(self.lookahead_coverages.iter())
+            .all(|coverage| coverage.iter().any(|gid| glyphs.contains(&gid)));
+        if !context_is_reachable {
+            return;
+        }
+        for (gid, sub) in self.coverage.iter().zip(&self.substitute_glyph_ids) {
+            if glyphs.contains(&gid) {
+                added.insert(*sub);
+            }
+        }
+    }
+}
+
+impl SubsetSubtable for SequenceContext {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        match self {
+            SequenceContext::Format1(table) => retain_covered(
+                &mut table.coverage,
+                &mut table.seq_rule_sets,
+                glyphs,
+                |rule_set| {
+                    let Some(rule_set) = rule_set.as_mut() else {
+                        return false;
+                    };
+                    rule_set
+                        .seq_rules
+                        .retain_mut(|rule| subset_sequence(&mut rule.input_sequence, glyphs));
+                    !rule_set.seq_rules.is_empty()
+                },
+            ),
+            SequenceContext::Format2(table) => {
+                subset_class_def(&mut table.class_def, glyphs);
+                subset_coverage(&mut table.coverage, glyphs)
+            }
+            SequenceContext::Format3(table) => table
+                .coverages
+                .iter_mut()
+                .all(|coverage| subset_coverage(coverage, glyphs)),
+        }
+    }
+
+    fn lookup_records(&self, f: &mut dyn FnMut(&[SequenceLookupRecord])) {
+        match self {
+            SequenceContext::Format1(table) => table
+                .seq_rule_sets
+                .iter()
+                .filter_map(|rule_set| rule_set.as_ref())
+                .flat_map(|rule_set| rule_set.seq_rules.iter())
+                .for_each(|rule| f(&rule.seq_lookup_records)),
+            SequenceContext::Format2(table) => table
+                .class_seq_rule_sets
+                .iter()
+                .filter_map(|rule_set| rule_set.as_ref())
+                .flat_map(|rule_set| rule_set.class_seq_rules.iter())
+                .for_each(|rule| f(&rule.seq_lookup_records)),
+            SequenceContext::Format3(table) => f(&table.seq_lookup_records),
+        }
+    }
+
+    fn lookup_records_mut(&mut self, f: &mut dyn FnMut(&mut Vec<SequenceLookupRecord>)) {
+        match self {
+            SequenceContext::Format1(table) => table
+                .seq_rule_sets
+                .iter_mut()
+                .filter_map(|rule_set| rule_set.as_mut())
+                .flat_map(|rule_set| rule_set.seq_rules.iter_mut())
+                .for_each(|rule| f(&mut rule.seq_lookup_records)),
+            SequenceContext::Format2(table) => table
+                .class_seq_rule_sets
+                .iter_mut()
+                .filter_map(|rule_set| rule_set.as_mut())
+                .flat_map(|rule_set| rule_set.class_seq_rules.iter_mut())
+                .for_each(|rule| f(&mut rule.seq_lookup_records)),
+            SequenceContext::Format3(table) => f(&mut table.seq_lookup_records),
+        }
+    }
+}
+
+impl SubsetSubtable for ChainedSequenceContext {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        match self {
+            ChainedSequenceContext::Format1(table) => retain_covered(
+                &mut table.coverage,
+                &mut table.chained_seq_rule_sets,
+                glyphs,
+                |rule_set| {
+                    let Some(rule_set) = rule_set.as_mut() else {
+                        return false;
+                    };
+                    rule_set.chained_seq_rules.retain_mut(|rule| {
+                        subset_sequence(&mut rule.backtrack_sequence, glyphs)
+                            && subset_sequence(&mut rule.input_sequence, glyphs)
+                            && subset_sequence(&mut rule.lookahead_sequence, glyphs)
+                    });
+                    !rule_set.chained_seq_rules.is_empty()
+                },
+            ),
+            ChainedSequenceContext::Format2(table) => {
+                subset_class_def(&mut table.backtrack_class_def, glyphs);
+                subset_class_def(&mut table.input_class_def, glyphs);
+                subset_class_def(&mut table.lookahead_class_def, glyphs);
+                subset_coverage(&mut table.coverage, glyphs)
+            }
+            ChainedSequenceContext::Format3(table) => table
+                .backtrack_coverages
+                .iter_mut()
+                .chain(table.input_coverages.iter_mut())
+                .chain(table.lookahead_coverages.iter_mut())
+                .all(|coverage| subset_coverage(coverage, glyphs)),
+        }
+    }
+
+    fn lookup_records(&self, f: &mut dyn FnMut(&[SequenceLookupRecord])) {
+        match self {
+            ChainedSequenceContext::Format1(table) => table
+                .chained_seq_rule_sets
+                .iter()
+                .filter_map(|rule_set| rule_set.as_ref())
+                .flat_map(|rule_set| rule_set.chained_seq_rules.iter())
+                .for_each(|rule| f(&rule.seq_lookup_records)),
+            ChainedSequenceContext::Format2(table) => table
+                .chained_class_seq_rule_sets
+                .iter()
+                .filter_map(|rule_set| rule_set.as_ref())
+                .flat_map(|rule_set| rule_set.chained_class_seq_rules.iter())
+                .for_each(|rule| f(&rule.seq_lookup_records)),
+            ChainedSequenceContext::Format3(table) => f(&table.seq_lookup_records),
+        }
+    }
+
+    fn lookup_records_mut(&mut self, f: &mut dyn FnMut(&mut Vec<SequenceLookupRecord>)) {
+        match self {
+            ChainedSequenceContext::Format1(table) => table
+                .chained_seq_rule_sets
+                .iter_mut()
+                .filter_map(|rule_set| rule_set.as_mut())
+                .flat_map(|rule_set| rule_set.chained_seq_rules.iter_mut())
+                .for_each(|rule| f(&mut rule.seq_lookup_records)),
+            ChainedSequenceContext::Format2(table) => table
+                .chained_class_seq_rule_sets
+                .iter_mut()
+                .filter_map(|rule_set| rule_set.as_mut())
+                .flat_map(|rule_set| rule_set.chained_class_seq_rules.iter_mut())
+                .for_each(|rule| f(&mut rule.seq_lookup_records)),
+            ChainedSequenceContext::Format3(table) => f(&mut table.seq_lookup_records),
+        }
+    }
+}
+
+/// Implements [`SubsetSubtable`] for the GSUB and GPOS wrappers around the
+/// shared contextual subtables.
+macro_rules! context_newtype {
+    ($ty:ty) => {
+        impl SubsetSubtable for $ty {
+            fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+                (**self).subset(glyphs)
+            }
+
+            fn lookup_records(&self, f: &mut dyn FnMut(&[SequenceLookupRecord])) {
+                (**self).lookup_records(f)
+            }
+
+            fn lookup_records_mut(&mut self, f: &mut dyn FnMut(&mut Vec<SequenceLookupRecord>)) {
+                (**self).lookup_records_mut(f)
+            }
+        }
+    };
+}
+
+context_newtype!(SubstitutionSequenceContext);
+context_newtype!(SubstitutionChainContext);
+context_newtype!(PositionSequenceContext);
+context_newtype!(PositionChainContext);
+
+// contextual substitutions only apply other lookups, which are reachable
+// from the selected features in their own right
+impl SubstitutionClosure for SubstitutionSequenceContext {}
+impl SubstitutionClosure for SubstitutionChainContext {}
+
+/// Implements [`SubsetSubtable`] for an extension subtable enum, by
+/// delegating to the wrapped subtable.
+macro_rules! extension_subtable {
+    ($ty:ty, [$($variant:ident),*]) => {
+        impl SubsetSubtable for $ty {
+            fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+                match self {
+                    $(Self::$variant(table) => table.extension.subset(glyphs),)*
+                }
+            }
+
+            fn lookup_records(&self, f: &mut dyn FnMut(&[SequenceLookupRecord])) {
+                match self {
+                    $(Self::$variant(table) => table.extension.lookup_records(f),)*
+                }
+            }
+
+            fn lookup_records_mut(&mut self, f: &mut dyn FnMut(&mut Vec<SequenceLookupRecord>)) {
+                match self {
+                    $(Self::$variant(table) => table.extension.lookup_records_mut(f),)*
+                }
+            }
+        }
+    };
+}
+
+extension_subtable!(
+    gsub::ExtensionSubtable,
+    [
+        Single,
+        Multiple,
+        Alternate,
+        Ligature,
+        Contextual,
+        ChainContextual,
+        Reverse
+    ]
+);
+extension_subtable!(
+    gpos::ExtensionSubtable,
+    [
+        Single,
+        Pair,
+        Cursive,
+        MarkToBase,
+        MarkToLig,
+        MarkToMark,
+        Contextual,
+        ChainContextual
+    ]
+);
+
+impl SubstitutionClosure for gsub::ExtensionSubtable {
+    fn closure(&self, glyphs: &BTreeSet<GlyphId>, added: &mut BTreeSet<GlyphId>) {
+        match self {
+            Self::Single(table) => table.extension.closure(glyphs, added),
+            Self::Multiple(table) => table.extension.closure(glyphs, added),
+            Self::Alternate(table) => table.extension.closure(glyphs, added),
+            Self::Ligature(table) => table.extension.closure(glyphs, added),
+            Self::Reverse(table) => table.extension.closure(glyphs, added),
+            Self::Contextual(_) | Self::ChainContextual(_) => (),
+        }
+    }
+}
+
+impl SubsetSubtable for SinglePos {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        match self {
+            SinglePos::Format1(table) => subset_coverage(&mut table.coverage, glyphs),
+            SinglePos::Format2(table) => retain_covered(
+                &mut table.coverage,
+                &mut table.value_records,
+                glyphs,
+                |_| true,
+            ),
+        }
+    }
+}
+
+impl SubsetSubtable for PairPos {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        match self {
+            PairPos::Format1(table) => {
+                retain_covered(&mut table.coverage, &mut table.pair_sets, glyphs, |set| {
+                    set.pair_value_records.retain_mut(|record| {
+                        match glyphs.get(record.second_glyph) {
+                            Some(new_gid) => {
+                                record.second_glyph = new_gid;
+                                true
+                            }
+                            None => false,
+                        }
+                    });
+                    !set.pair_value_records.is_empty()
+                })
+            }
+            PairPos::Format2(table) => {
+                if !subset_coverage(&mut table.coverage, glyphs) {
+                    return false;
+                }
+                let classes1 = subset_classes(&mut table.class_def1, glyphs);
+                let classes2 = subset_classes(&mut table.class_def2, glyphs);
+                retain_indices(&mut table.class1_records, &classes1);
+                for record in table.class1_records.iter_mut() {
+                    retain_indices(&mut record.class2_records, &classes2);
+                }
+                true
+            }
+        }
+    }
+}
+
+impl SubsetSubtable for CursivePosFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        retain_covered(
+            &mut self.coverage,
+            &mut self.entry_exit_record,
+            glyphs,
+            |_| true,
+        )
+    }
+}
+
+impl SubsetSubtable for MarkBasePosFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        let marks = &mut self.mark_array.mark_records;
+        let bases = &mut self.base_array.base_records;
+        if !(retain_covered(&mut self.mark_coverage, marks, glyphs, |_| true)
+            && retain_covered(&mut self.base_coverage, bases, glyphs, |_| true))
+        {
+            return false;
+        }
+        let classes = subset_mark_classes(marks);
+        for base in bases.iter_mut() {
+            retain_indices(&mut base.base_anchors, &classes);
+        }
+        true
+    }
+}
+
+impl SubsetSubtable for MarkLigPosFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        let marks = &mut self.mark_array.mark_records;
+        let ligatures = &mut self.ligature_array.ligature_attaches;
+        if !(retain_covered(&mut self.mark_coverage, marks, glyphs, |_| true)
+            && retain_covered(&mut self.ligature_coverage, ligatures, glyphs, |_| true))
+        {
+            return false;
+        }
+        let classes = subset_mark_classes(marks);
+        for ligature in ligatures.iter_mut() {
+            for component in ligature.component_records.iter_mut() {
+                retain_indices(&mut component.ligature_anchors, &classes);
+            }
+        }
+        true
+    }
+}
+
+impl SubsetSubtable for MarkMarkPosFormat1 {
+    fn subset(&mut self, glyphs: &GlyphMap) -> bool {
+        let marks = &mut self.mark1_array.mark_records;
+        let bases = &mut self.mark2_array.mark2_records;
+        if !(retain_covered(&mut self.mark1_coverage, marks, glyphs, |_| true)
+            && retain_covered(&mut self.mark2_coverage, bases, glyphs, |_| true))
+        {
+            return false;
+        }
+        let classes = subset_mark_classes(marks);
+        for base in bases.iter_mut() {
+            retain_indices(&mut base.mark2_anchors, &classes);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tables::{
+        gpos::{Class1Record, Class2Record, PairPosFormat2, ValueRecord},
+        layout::CoverageTableBuilder,
+    };
+
+    use super::*;
+
+    fn glyph_map(gids: &[u16]) -> GlyphMap {
+        GlyphMap::new(&gids.iter().copied().map(GlyphId::new).collect())
+    }
+
+    fn gids(gids: &[u16]) -> Vec<GlyphId> {
+        gids.iter().copied().map(GlyphId::new).collect()
+    }
+
+    #[test]
+    fn single_subst_format_is_recomputed() {
+        // 1 -> 11, 2 -> 12, 3 -> 20
+        let coverage = CoverageTableBuilder::from_glyphs(gids(&[1, 2, 3])).build();
+        let mut subst =
+            SingleSubst::Format2(SingleSubstFormat2::new(coverage, gids(&[11, 12, 20])));
+        assert!(subst.subset(&glyph_map(&[0, 1, 2, 11, 12, 20])));
+        // 1 -> 3, 2 -> 4
+        assert!(matches!(&subst, SingleSubst::Format1(table) if table.delta_glyph_id == 2));
+        assert_eq!(
+            subst.mappings(),
+            [
+                (GlyphId::new(1), GlyphId::new(3)),
+                (GlyphId::new(2), GlyphId::new(4))
+            ]
+        );
+        assert!(!subst.subset(&glyph_map(&[0, 1, 2])));
+    }
+
+    #[test]
+    fn pair_pos_classes_are_compacted() {
+        let class_def1: ClassDef = [(1, 1), (2, 2), (3, 3)]
+            .into_iter()
+            .map(|(gid, class)| (GlyphId::new(gid), class))
+            .collect();
+        let class_def2: ClassDef = [(4, 1), (5, 2)]
+            .into_iter()
+            .map(|(gid, class)| (GlyphId::new(gid), class))
+            .collect();
+        let value = |x| ValueRecord::new().with_x_advance(x);
+        let class1_records = (0..4)
+            .map(|c1| {
+                Class1Record::new(
+                    (0..3)
+                        .map(|c2| Class2Record::new(value(c1 * 10 + c2), ValueRecord::new()))
+                        .collect(),
+                )
+            })
+            .collect();
+        let coverage = CoverageTableBuilder::from_glyphs(gids(&[1, 2, 3])).build();
+        let mut pair_pos = PairPos::Format2(PairPosFormat2::new(
+            coverage,
+            class_def1,
+            class_def2,
+            class1_records,
+        ));
+        assert!(pair_pos.subset(&glyph_map(&[0, 3, 5])));
+        let PairPos::Format2(table) = pair_pos else {
+            panic!("wrong format");
+        };
+        assert_eq!(
+            table.class_def1.iter().collect::<Vec<_>>(),
+            [(GlyphId::new(1), 1)]
+        );
+        assert_eq!(
+            table.class_def2.iter().collect::<Vec<_>>(),
+            [(GlyphId::new(2), 1)]
+        );
+        let advances: Vec<Vec<_>> = table
+            .class1_records
+            .iter()
+            .map(|record| {
+                record
+                    .class2_records
+                    .iter()
+                    .map(|record| record.value_record1.x_advance.unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(advances, [[0, 2], [30, 32]]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Subsetting glyph metrics.
+
+use read_fonts::{tables::hmtx::LongMetric as ReadLongMetric, TableProvider};
+use types::{BigEndian, GlyphId, Tag};
+
+use super::{optional, GlyphMap, Plan, SubsetError};
+use crate::{
+    from_obj::ToOwnedTable,
+    tables::{
+        hhea::Hhea,
+        hmtx::{Hmtx, LongMetric},
+        vhea::Vhea,
+        vmtx::Vmtx,
+    },
+    FontBuilder,
+};
+
+const HHEA: Tag = Tag::new(b"hhea");
+const HMTX: Tag = Tag::new(b"hmtx");
+const VHEA: Tag = Tag::new(b"vhea");
+const VMTX: Tag = Tag::new(b"vmtx");
+const VORG: Tag = Tag::new(b"VORG");
+/// The size of the VORG header, and of each vertical origin record.
+const VORG_HEADER_LEN: usize = 8;
+const VORG_RECORD_LEN: usize = 4;
+
+/// Rewrites `hmtx`, `vmtx` and `VORG`, updating the number of long metrics
+/// in `hhea` and `vhea`.
+///
+/// A metrics table is dropped if the corresponding header table is missing.
+pub(super) fn subset(plan: &Plan, builder: &mut FontBuilder) -> Result<(), SubsetError> {
+    let font = &plan.font;
+    if let Some(hhea) = optional(HHEA, font.hhea())? {
+        let mut hhea: Hhea = hhea.to_owned_table();
+        if let Some(hmtx) = optional(HMTX, font.hmtx())? {
+            let (long, bearings) =
+                subset_metrics(hmtx.h_metrics(), hmtx.left_side_bearings(), &plan.glyph_map);
+            hhea.number_of_long_metrics = long.len() as u16;
+            builder.add_table(&Hmtx::new(long, bearings))?;
+        }
+        builder.add_table(&hhea)?;
+    }
+    if let Some(vhea) = optional(VHEA, font.vhea())? {
+        let mut vhea: Vhea = vhea.to_owned_table();
+        if let Some(vmtx) = optional(VMTX, font.vmtx())? {
+            let (long, bearings) =
+                subset_metrics(vmtx.v_metrics(), vmtx.top_side_bearings(), &plan.glyph_map);
+            vhea.number_of_long_ver_metrics = long.len() as u16;
+            builder.add_table(&Vmtx::new(long, bearings))?;
+        }
+        builder.add_table(&vhea)?;
+    }
+    if let Some(vorg) = font.data_for_tag(VORG) {
+        if let Some(vorg) = subset_vorg(vorg.as_bytes(), &plan.glyph_map) {
+            builder.add_raw(VORG, vorg);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the long metrics and trailing side bearings for the retained
+/// glyphs.
+///
+/// Trailing glyphs with the same advance as the last long metric only store
+/// their side bearing.
+fn subset_metrics(
+    long: &[ReadLongMetric],
+    bearings: &[BigEndian<i16>],
+    glyph_map: &GlyphMap,
+) -> (Vec<LongMetric>, Vec<i16>) {
+    let last_advance = long
+        .last()
+        .map(|metric| metric.advance())
+        .unwrap_or_default();
+    let mut metrics: Vec<_> = glyph_map
+        .old_glyphs()
+        .map(|gid| {
+            let idx = gid.to_u16() as usize;
+            match long.get(idx) {
+                Some(metric) => LongMetric::new(metric.advance(), metric.side_bearing()),
+                None => {
+                    let bearing = bearings.get(idx - long.len()).map(BigEndian::get);
+                    LongMetric::new(last_advance, bearing.unwrap_or_default())
+                }
+            }
+        })
+        .collect();
+    let mut num_long = metrics.len();
+    while num_long > 1 && metrics[num_long - 1].advance == metrics[num_long - 2].advance {
+        num_long -= 1;
+    }
+    let bearings = metrics
+        .drain(num_long..)
+        .map(|metric| metric.side_bearing)
+        .collect();
+    (metrics, bearings)
+}
+
+/// Rewrites the [VORG] table, keeping the records of retained glyphs.
+///
+/// [VORG]: https://learn.microsoft.com/en-us/typography/opentype/spec/vorg
+fn subset_vorg(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let header = data.get(..VORG_HEADER_LEN)?;
+    let num_records = u16::from_be_bytes([header[6], header[7]]) as usize;
+    let records = data.get(VORG_HEADER_LEN..VORG_HEADER_LEN + num_records * VORG_RECORD_LEN)?;
+    let mut new_records = Vec::new();
+    for record in records.chunks_exact(VORG_RECORD_LEN) {
+        let gid = u16::from_be_bytes([record[0], record[1]]);
+        if let Some(new_gid) = glyph_map.get(GlyphId::new(gid)) {
+            new_records.extend_from_slice(&new_gid.to_be_bytes());
+            new_records.extend_from_slice(&record[2..]);
+        }
+    }
+    let mut vorg = header[..6].to_vec();
+    vorg.extend_from_slice(&((new_records.len() / VORG_RECORD_LEN) as u16).to_be_bytes());
+    vorg.extend_from_slice(&new_records);
+    Some(vorg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn glyph_map(gids: &[u16]) -> GlyphMap {
+        GlyphMap::new(
+            &gids
+                .iter()
+                .copied()
+                .map(GlyphId::new)
+                .collect::<BTreeSet<_>>(),
+        )
+    }
+
+    #[test]
+    fn trailing_advances_are_packed() {
+        let data: Vec<u8> = [(500u16, 1i16), (600, 2), (600, 3)]
+            .iter()
+            .flat_map(|(advance, bearing)| {
+                advance
+                    .to_be_bytes()
+                    .into_iter()
+                    .chain(bearing.to_be_bytes())
+            })
+            .collect();
+        let long: &[ReadLongMetric] = read_fonts::FontData::new(&data)
+            .read_array(0..data.len())
+            .unwrap();
+        let bearings = [BigEndian::from(4i16), BigEndian::from(5i16)];
+
+        let (long_metrics, trailing) = subset_metrics(long, &bearings, &glyph_map(&[0, 2, 3, 4]));
+        assert_eq!(
+            long_metrics,
+            [LongMetric::new(500, 1), LongMetric::new(600, 3)]
+        );
+        assert_eq!(trailing, [4, 5]);
+
+        let (long_metrics, trailing) = subset_metrics(long, &bearings, &glyph_map(&[1, 4]));
+        assert_eq!(long_metrics, [LongMetric::new(600, 2)]);
+        assert_eq!(trailing, [5]);
+    }
+
+    #[test]
+    fn vorg_records() {
+        let mut data = vec![0, 1, 0, 0, 0x03, 0x70, 0, 3];
+        data.extend([0, 2, 0x03, 0x20, 0, 5, 0x03, 0x10, 0, 9, 0x03, 0x00]);
+        let vorg = subset_vorg(&data, &glyph_map(&[0, 5, 9])).unwrap();
+        assert_eq!(
+            vorg,
+            [0, 1, 0, 0, 0x03, 0x70, 0, 2, 0, 1, 0x03, 0x10, 0, 2, 0x03, 0x00]
+        );
+    }
+}
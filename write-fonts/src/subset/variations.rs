@@ -0,0 +1,271 @@
+//! Subsetting glyph variations and metrics variations.
+
+use read_fonts::{
+    tables::variations::{DeltaSetIndexMap as ReadDeltaSetIndexMap, ItemVariationStore},
+    ReadError, TableProvider,
+};
+use types::{GlyphId, MajorMinor, Tag};
+
+use super::{optional, read_error, GlyphMap, Plan, SubsetError};
+use crate::{
+    dump_table, from_obj::ToOwnedTable, tables::variations::DeltaSetIndexMap, FontBuilder,
+};
+
+const GVAR: Tag = Tag::new(b"gvar");
+const HVAR: Tag = Tag::new(b"HVAR");
+const VVAR: Tag = Tag::new(b"VVAR");
+/// The size of the gvar header, up to the glyph variation data offsets.
+const GVAR_HEADER_LEN: usize = 20;
+/// The flag indicating that gvar offsets are 32-bit.
+const GVAR_LONG_OFFSETS: u16 = 1;
+/// The largest glyph variation data size that can use 16-bit offsets.
+const GVAR_MAX_SHORT_OFFSET: usize = u16::MAX as usize * 2;
+
+/// Rewrites `gvar`, `HVAR` and `VVAR`.
+pub(super) fn subset(plan: &Plan, builder: &mut FontBuilder) -> Result<(), SubsetError> {
+    let font = &plan.font;
+    if let Some(gvar) = font.data_for_tag(GVAR) {
+        let gvar = subset_gvar(gvar.as_bytes(), &plan.glyph_map).ok_or(SubsetError::Read {
+            tag: GVAR,
+            inner: ReadError::OutOfBounds,
+        })?;
+        builder.add_raw(GVAR, gvar);
+    }
+    if let Some(hvar) = optional(HVAR, font.hvar())? {
+        let hvar = subset_metrics_variations(
+            hvar.version(),
+            hvar.item_variation_store(),
+            [
+                hvar.advance_width_mapping().transpose(),
+                hvar.lsb_mapping().transpose(),
+                hvar.rsb_mapping().transpose(),
+            ],
+            &plan.glyph_map,
+        )
+        .map_err(read_error(HVAR))?;
+        builder.add_raw(HVAR, hvar);
+    }
+    if let Some(vvar) = optional(VVAR, font.vvar())? {
+        let vvar = subset_metrics_variations(
+            vvar.version(),
+            vvar.item_variation_store(),
+            [
+                vvar.advance_height_mapping().transpose(),
+                vvar.tsb_mapping().transpose(),
+                vvar.bsb_mapping().transpose(),
+                vvar.v_org_mapping().transpose(),
+            ],
+            &plan.glyph_map,
+        )
+        .map_err(read_error(VVAR))?;
+        builder.add_raw(VVAR, vvar);
+    }
+    Ok(())
+}
+
+/// The raw contents of a `gvar` table.
+struct RawGvar<'a> {
+    data: &'a [u8],
+    glyph_count: usize,
+    long_offsets: bool,
+    data_array_offset: usize,
+}
+
+impl<'a> RawGvar<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let header = data.get(..GVAR_HEADER_LEN)?;
+        let flags = u16::from_be_bytes([header[14], header[15]]);
+        Some(Self {
+            data,
+            glyph_count: u16::from_be_bytes([header[12], header[13]]) as usize,
+            long_offsets: flags & GVAR_LONG_OFFSETS != 0,
+            data_array_offset: u32::from_be_bytes(header[16..20].try_into().unwrap()) as usize,
+        })
+    }
+
+    fn shared_tuples(&self) -> Option<&'a [u8]> {
+        let axis_count = u16::from_be_bytes([self.data[4], self.data[5]]) as usize;
+        let tuple_count = u16::from_be_bytes([self.data[6], self.data[7]]) as usize;
+        let offset = u32::from_be_bytes(self.data[8..12].try_into().unwrap()) as usize;
+        self.data.get(offset..offset + axis_count * tuple_count * 2)
+    }
+
+    fn offset(&self, idx: usize) -> Option<usize> {
+        if self.long_offsets {
+            let pos = GVAR_HEADER_LEN + idx * 4;
+            let bytes = self.data.get(pos..pos + 4)?;
+            Some(u32::from_be_bytes(bytes.try_into().unwrap()) as usize)
+        } else {
+            let pos = GVAR_HEADER_LEN + idx * 2;
+            let bytes = self.data.get(pos..pos + 2)?;
+            Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize * 2)
+        }
+    }
+
+    /// Returns the variation data for a glyph, which is empty if the glyph
+    /// has no variations.
+    fn glyph_data(&self, gid: GlyphId) -> Option<&'a [u8]> {
+        let idx = gid.to_u16() as usize;
+        if idx >= self.glyph_count {
+            return Some(&[]);
+        }
+        let start = self.data_array_offset + self.offset(idx)?;
+        let end = self.data_array_offset + self.offset(idx + 1)?;
+        self.data.get(start..end)
+    }
+}
+
+/// Rewrites the [gvar] table, keeping the variation data of retained glyphs.
+///
+/// [gvar]: https://learn.microsoft.com/en-us/typography/opentype/spec/gvar
+fn subset_gvar(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let gvar = RawGvar::new(data)?;
+    let shared_tuples = gvar.shared_tuples()?;
+    let mut glyph_data = Vec::new();
+    let mut offsets = vec![0];
+    for gid in glyph_map.old_glyphs() {
+        glyph_data.extend_from_slice(gvar.glyph_data(gid)?);
+        if glyph_data.len() % 2 != 0 {
+            glyph_data.push(0);
+        }
+        offsets.push(glyph_data.len());
+    }
+
+    let long_offsets = glyph_data.len() > GVAR_MAX_SHORT_OFFSET;
+    let offsets_len = offsets.len() * if long_offsets { 4 } else { 2 };
+    let shared_tuples_offset = GVAR_HEADER_LEN + offsets_len;
+    let data_array_offset = shared_tuples_offset + shared_tuples.len();
+    let flags = u16::from_be_bytes([data[14], data[15]]) & !GVAR_LONG_OFFSETS
+        | if long_offsets { GVAR_LONG_OFFSETS } else { 0 };
+
+    let mut gvar = data[..8].to_vec();
+    gvar.extend_from_slice(&(shared_tuples_offset as u32).to_be_bytes());
+    gvar.extend_from_slice(&(glyph_map.len() as u16).to_be_bytes());
+    gvar.extend_from_slice(&flags.to_be_bytes());
+    gvar.extend_from_slice(&(data_array_offset as u32).to_be_bytes());
+    for offset in offsets {
+        if long_offsets {
+            gvar.extend_from_slice(&(offset as u32).to_be_bytes());
+        } else {
+            gvar.extend_from_slice(&((offset / 2) as u16).to_be_bytes());
+        }
+    }
+    gvar.extend_from_slice(shared_tuples);
+    gvar.extend_from_slice(&glyph_data);
+    Some(gvar)
+}
+
+/// Rewrites an [HVAR] or [VVAR] table, which consist of a version, an item
+/// variation store and a number of delta-set index maps.
+///
+/// The item variation store is retained unchanged, and each map is rebuilt
+/// for the retained glyphs. If there is no advance mapping, the glyph
+/// identifiers were used as implicit indices, so an explicit mapping is
+/// added.
+///
+/// [HVAR]: https://learn.microsoft.com/en-us/typography/opentype/spec/hvar
+/// [VVAR]: https://learn.microsoft.com/en-us/typography/opentype/spec/vvar
+fn subset_metrics_variations<const N: usize>(
+    version: MajorMinor,
+    item_variation_store: Result<ItemVariationStore, ReadError>,
+    mappings: [Result<Option<ReadDeltaSetIndexMap>, ReadError>; N],
+    glyph_map: &GlyphMap,
+) -> Result<Vec<u8>, ReadError> {
+    let store: crate::tables::variations::ItemVariationStore =
+        item_variation_store?.to_owned_table();
+    let store =
+        dump_table(&store).map_err(|_| ReadError::MalformedData("invalid item variation store"))?;
+    let mut tables = vec![Some(store)];
+    for (i, mapping) in mappings.into_iter().enumerate() {
+        let mapping = mapping?;
+        // only the advance mapping is implicit
+        if mapping.is_none() && i > 0 {
+            tables.push(None);
+            continue;
+        }
+        let entries = glyph_map
+            .old_glyphs()
+            .map(|gid| match &mapping {
+                Some(mapping) => mapping
+                    .get(gid.to_u16() as u32)
+                    .map(|idx| (idx.outer as u32) << 16 | idx.inner as u32),
+                None => Ok(gid.to_u16() as u32),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mapping: DeltaSetIndexMap = entries.into_iter().collect();
+        let mapping = dump_table(&mapping)
+            .map_err(|_| ReadError::MalformedData("invalid delta-set index map"))?;
+        tables.push(Some(mapping));
+    }
+
+    let mut header = version.to_be_bytes().to_vec();
+    let mut body = Vec::new();
+    let header_len = 4 + tables.len() * 4;
+    for table in tables {
+        match table {
+            Some(table) => {
+                header.extend_from_slice(&((header_len + body.len()) as u32).to_be_bytes());
+                body.extend_from_slice(&table);
+                body.resize(body.len().next_multiple_of(4), 0);
+            }
+            None => header.extend_from_slice(&0u32.to_be_bytes()),
+        }
+    }
+    header.extend_from_slice(&body);
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{FontRead, FontRef};
+
+    use super::*;
+
+    fn glyph_map(gids: &[u16]) -> GlyphMap {
+        GlyphMap::new(&gids.iter().copied().map(GlyphId::new).collect())
+    }
+
+    #[test]
+    fn gvar_glyph_data() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let data = font.data_for_tag(GVAR).unwrap().as_bytes();
+        let source = RawGvar::new(data).unwrap();
+        let subset = subset_gvar(data, &glyph_map(&[0, 2, 3])).unwrap();
+        let subset = RawGvar::new(&subset).unwrap();
+        assert_eq!(subset.glyph_count, 3);
+        assert_eq!(subset.shared_tuples(), source.shared_tuples());
+        for (new, old) in [(0, 0), (1, 2), (2, 3)] {
+            let new_data = subset.glyph_data(GlyphId::new(new)).unwrap();
+            let old_data = source.glyph_data(GlyphId::new(old)).unwrap();
+            assert_eq!(&new_data[..old_data.len()], old_data);
+        }
+    }
+
+    #[test]
+    fn implicit_advance_mapping() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let hvar = font.hvar().unwrap();
+        let glyph_map = glyph_map(&[0, 3]);
+        let data = subset_metrics_variations(
+            hvar.version(),
+            hvar.item_variation_store(),
+            [
+                hvar.advance_width_mapping().transpose(),
+                hvar.lsb_mapping().transpose(),
+                hvar.rsb_mapping().transpose(),
+            ],
+            &glyph_map,
+        )
+        .unwrap();
+        let subset =
+            read_fonts::tables::hvar::Hvar::read(read_fonts::FontData::new(&data)).unwrap();
+        let mapping = subset.advance_width_mapping().unwrap().unwrap();
+        for (old, new) in glyph_map.iter() {
+            let expected = match hvar.advance_width_mapping() {
+                Some(mapping) => mapping.unwrap().get(old.to_u16() as u32).unwrap().inner,
+                None => old.to_u16(),
+            };
+            assert_eq!(mapping.get(new.to_u16() as u32).unwrap().inner, expected);
+        }
+    }
+}
@@ -5,7 +5,16 @@
 
 pub mod avar;
 pub mod base;
+pub mod bitmap;
+pub mod cbdt;
+pub mod cblc;
+pub mod cff;
+pub mod cff2;
 pub mod cmap;
+pub mod colr;
+pub mod cpal;
+pub mod ebdt;
+pub mod eblc;
 pub mod fvar;
 pub mod gdef;
 pub mod glyf;
@@ -22,6 +31,7 @@ pub mod maxp;
 pub mod name;
 pub mod os2;
 pub mod post;
+pub mod postscript;
 pub mod sbix;
 pub mod stat;
 pub mod variations;
@@ -36,7 +46,15 @@ fn do_we_even_serde() {
     struct AllTables {
         avar: avar::Avar,
         base: base::Base,
+        cbdt: cbdt::Cbdt,
+        cblc: cblc::Cblc,
+        cff: cff::Cff,
+        cff2: cff2::Cff2,
         cmap: cmap::Cmap,
+        colr: colr::Colr,
+        cpal: cpal::Cpal,
+        ebdt: ebdt::Ebdt,
+        eblc: eblc::Eblc,
         fvar: fvar::Fvar,
         gdef: gdef::Gdef,
         glyf: glyf::Glyf,
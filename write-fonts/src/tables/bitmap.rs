@@ -0,0 +1,868 @@
+//! Common bitmap (EBLC/EBDT/CBLC/CBDT) types, and a builder for the bitmap
+//! location and data tables.
+
+use std::collections::BTreeMap;
+
+use super::{cbdt::Cbdt, cblc::Cblc, ebdt::Ebdt, eblc::Eblc};
+
+include!("../../generated/generated_bitmap.rs");
+
+const LOCATION_HEADER_LEN: usize = 8;
+const BITMAP_SIZE_LEN: usize = 48;
+const INDEX_SUBTABLE_RECORD_LEN: usize = 8;
+
+/// The fewest glyphs with identical metrics and image sizes that are given
+/// their own index subtable, with the metrics stored once.
+///
+/// A separate subtable costs about as much as the metrics of three glyphs.
+const MIN_CONSTANT_RUN: usize = 4;
+
+/// A macro to define one of the bitmap tables, which are built together by
+/// [`build_cbdt`] or [`build_ebdt`] and written as opaque data.
+macro_rules! bitmap_table {
+    ($(#[$attr:meta])* $name:ident, $tag:literal) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name(pub(crate) Vec<u8>);
+
+        impl read_fonts::TopLevelTable for $name {
+            const TAG: font_types::Tag = font_types::Tag::new($tag);
+        }
+
+        impl $crate::validate::Validate for $name {
+            fn validate_impl(&self, _ctx: &mut $crate::validate::ValidationCtx) {}
+        }
+
+        impl $crate::FontWrite for $name {
+            fn write_into(&self, writer: &mut $crate::TableWriter) {
+                writer.write_slice(&self.0)
+            }
+        }
+    };
+}
+
+pub(crate) use bitmap_table;
+
+impl IndexSubtable4 {
+    fn compute_num_glyphs(&self) -> u32 {
+        // the last entry only marks the end of the image data
+        self.glyph_array.len().saturating_sub(1) as u32
+    }
+}
+
+/// An error that occurred while building bitmap tables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BitmapError {
+    /// A strike has a bit depth that the table does not support.
+    InvalidBitDepth(u8),
+    /// A glyph has a PNG image, which is only supported in color tables.
+    UnexpectedPng(GlyphId),
+    /// The length of an uncompressed image does not match its metrics.
+    ImageSize {
+        glyph: GlyphId,
+        expected: usize,
+        found: usize,
+    },
+    /// The image data is too large to be addressed by the location table.
+    TooLarge,
+}
+
+impl std::fmt::Display for BitmapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitmapError::InvalidBitDepth(depth) => write!(f, "Unsupported bit depth {depth}"),
+            BitmapError::UnexpectedPng(glyph) => {
+                write!(f, "Glyph {glyph} has a PNG image in a monochrome table")
+            }
+            BitmapError::ImageSize {
+                glyph,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Image of glyph {glyph} has {found} bytes, but its metrics require {expected}"
+            ),
+            BitmapError::TooLarge => write!(f, "Bitmap data is too large"),
+        }
+    }
+}
+
+impl std::error::Error for BitmapError {}
+
+/// The metrics of a glyph in a bitmap strike.
+///
+/// Small metrics describe only the direction given by the strike's flags.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitmapMetrics {
+    Small(SmallGlyphMetrics),
+    Big(BigGlyphMetrics),
+}
+
+/// The image of a glyph in a bitmap strike.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitmapImage {
+    /// Pixels in rows from the top, with each row padded to a byte boundary.
+    ByteAligned(Vec<u8>),
+    /// Pixels in rows from the top, with no padding between rows.
+    BitAligned(Vec<u8>),
+    /// A PNG image; only supported in color tables.
+    Png(Vec<u8>),
+    /// Other glyphs of the same strike, drawn at the given positions.
+    Composite(Vec<BdtComponent>),
+}
+
+/// A glyph in a bitmap strike.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitmapGlyph {
+    pub metrics: BitmapMetrics,
+    pub image: BitmapImage,
+}
+
+/// The bitmaps of a font at one size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitmapStrike {
+    pub ppem_x: u8,
+    pub ppem_y: u8,
+    /// The number of bits per pixel: 1, 2, 4 or 8, or 32 for color bitmaps.
+    pub bit_depth: u8,
+    /// Whether the strike is for horizontal or vertical text, or both.
+    pub flags: BitmapFlags,
+    pub glyphs: BTreeMap<GlyphId, BitmapGlyph>,
+}
+
+impl BitmapStrike {
+    /// Creates an empty strike for horizontal text.
+    pub fn new(ppem: u8, bit_depth: u8) -> Self {
+        Self {
+            ppem_x: ppem,
+            ppem_y: ppem,
+            bit_depth,
+            flags: BitmapFlags::HORIZONTAL_METRICS,
+            glyphs: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a glyph to the strike, replacing any existing image.
+    pub fn add_glyph(&mut self, glyph: GlyphId, metrics: BitmapMetrics, image: BitmapImage) {
+        self.glyphs.insert(glyph, BitmapGlyph { metrics, image });
+    }
+
+    /// Returns `true` if the small metrics of this strike are vertical.
+    fn is_vertical(&self) -> bool {
+        self.flags == BitmapFlags::VERTICAL_METRICS
+    }
+
+    /// Computes the line metrics for each direction from the glyph metrics.
+    ///
+    /// If no glyph has metrics for one direction, it uses the line metrics
+    /// of the other.
+    fn line_metrics(&self) -> (SbitLineMetrics, SbitLineMetrics) {
+        let mut hori = Vec::new();
+        let mut vert = Vec::new();
+        for glyph in self.glyphs.values() {
+            match &glyph.metrics {
+                BitmapMetrics::Small(m) if self.is_vertical() => vert.push(Extent::vertical(
+                    m.width,
+                    m.height,
+                    m.bearing_x,
+                    m.bearing_y,
+                    m.advance,
+                )),
+                BitmapMetrics::Small(m) => hori.push(Extent::horizontal(
+                    m.width,
+                    m.height,
+                    m.bearing_x,
+                    m.bearing_y,
+                    m.advance,
+                )),
+                BitmapMetrics::Big(m) => {
+                    hori.push(Extent::horizontal(
+                        m.width,
+                        m.height,
+                        m.hori_bearing_x,
+                        m.hori_bearing_y,
+                        m.hori_advance,
+                    ));
+                    vert.push(Extent::vertical(
+                        m.width,
+                        m.height,
+                        m.vert_bearing_x,
+                        m.vert_bearing_y,
+                        m.vert_advance,
+                    ));
+                }
+            }
+        }
+        let hori = Extent::line_metrics(&hori, true);
+        let vert = Extent::line_metrics(&vert, false);
+        match (hori, vert) {
+            (Some(hori), Some(vert)) => (hori, vert),
+            (Some(hori), None) => (hori.clone(), hori),
+            (None, Some(vert)) => (vert.clone(), vert),
+            (None, None) => Default::default(),
+        }
+    }
+}
+
+/// The extent of a glyph image relative to its origin and baseline, in one
+/// direction of text.
+struct Extent {
+    /// The distance from the origin to the image, along the advance.
+    origin_sb: i32,
+    /// The size of the image along the advance.
+    size: i32,
+    advance: i32,
+    /// The distance from the baseline to the far side of the image; above
+    /// the baseline for horizontal text, and to the right of the center line
+    /// for vertical text.
+    before_bl: i32,
+    /// The distance from the baseline to the near side of the image.
+    after_bl: i32,
+}
+
+impl Extent {
+    fn horizontal(width: u8, height: u8, bearing_x: i8, bearing_y: i8, advance: u8) -> Self {
+        Extent {
+            origin_sb: bearing_x as i32,
+            size: width as i32,
+            advance: advance as i32,
+            before_bl: bearing_y as i32,
+            after_bl: bearing_y as i32 - height as i32,
+        }
+    }
+
+    fn vertical(width: u8, height: u8, bearing_x: i8, bearing_y: i8, advance: u8) -> Self {
+        Extent {
+            origin_sb: bearing_y as i32,
+            size: height as i32,
+            advance: advance as i32,
+            before_bl: bearing_x as i32 + width as i32,
+            after_bl: bearing_x as i32,
+        }
+    }
+
+    fn line_metrics(extents: &[Extent], horizontal: bool) -> Option<SbitLineMetrics> {
+        let max = |f: fn(&Extent) -> i32| extents.iter().map(f).max();
+        let min = |f: fn(&Extent) -> i32| extents.iter().map(f).min();
+        let clamp = |value: i32| value.clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+        let max_before_bl = clamp(max(|e| e.before_bl)?);
+        let min_after_bl = clamp(min(|e| e.after_bl)?);
+        // the caret is perpendicular to the direction of text
+        let (caret_slope_numerator, caret_slope_denominator) =
+            if horizontal { (1, 0) } else { (0, 1) };
+        Some(SbitLineMetrics {
+            ascender: max_before_bl,
+            descender: min_after_bl,
+            width_max: max(|e| e.size)?.clamp(0, u8::MAX as i32) as u8,
+            caret_slope_numerator,
+            caret_slope_denominator,
+            caret_offset: 0,
+            min_origin_sb: clamp(min(|e| e.origin_sb)?),
+            min_advance_sb: clamp(min(|e| e.advance - e.origin_sb - e.size)?),
+            max_before_bl,
+            min_after_bl,
+            pad1: 0,
+            pad2: 0,
+        })
+    }
+}
+
+impl BitmapGlyph {
+    /// The image format when the metrics are stored with the image.
+    fn image_format(&self) -> u16 {
+        let small = matches!(self.metrics, BitmapMetrics::Small(_));
+        match (&self.image, small) {
+            (BitmapImage::ByteAligned(_), true) => 1,
+            (BitmapImage::BitAligned(_), true) => 2,
+            (BitmapImage::ByteAligned(_), false) => 6,
+            (BitmapImage::BitAligned(_), false) => 7,
+            (BitmapImage::Composite(_), true) => 8,
+            (BitmapImage::Composite(_), false) => 9,
+            (BitmapImage::Png(_), true) => 17,
+            (BitmapImage::Png(_), false) => 18,
+        }
+    }
+
+    /// The image format, metrics and image length when the metrics are
+    /// stored in the location table, if the image supports this.
+    fn constant_format(&self, bit_depth: u8) -> Option<(u16, &BigGlyphMetrics, usize)> {
+        let BitmapMetrics::Big(metrics) = &self.metrics else {
+            return None;
+        };
+        match &self.image {
+            BitmapImage::BitAligned(data) => Some((5, metrics, data.len())),
+            // without padding, byte-aligned rows are also bit-aligned
+            BitmapImage::ByteAligned(data)
+                if (metrics.width as usize * bit_depth as usize).is_multiple_of(8) =>
+            {
+                Some((5, metrics, data.len()))
+            }
+            BitmapImage::Png(data) => Some((19, metrics, data.len() + 4)),
+            _ => None,
+        }
+    }
+
+    fn check(&self, glyph: GlyphId, bit_depth: u8, is_color: bool) -> Result<(), BitmapError> {
+        let (width, height) = match &self.metrics {
+            BitmapMetrics::Small(m) => (m.width as usize, m.height as usize),
+            BitmapMetrics::Big(m) => (m.width as usize, m.height as usize),
+        };
+        let bit_depth = bit_depth as usize;
+        let (expected, found) = match &self.image {
+            BitmapImage::ByteAligned(data) => {
+                ((width * bit_depth).div_ceil(8) * height, data.len())
+            }
+            BitmapImage::BitAligned(data) => ((width * height * bit_depth).div_ceil(8), data.len()),
+            BitmapImage::Png(_) if !is_color => return Err(BitmapError::UnexpectedPng(glyph)),
+            BitmapImage::Png(_) | BitmapImage::Composite(_) => return Ok(()),
+        };
+        if expected != found {
+            return Err(BitmapError::ImageSize {
+                glyph,
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes the image, preceded by the metrics unless they are stored in
+    /// the location table.
+    fn write_into(&self, writer: &mut TableWriter, with_metrics: bool) {
+        if with_metrics {
+            match &self.metrics {
+                BitmapMetrics::Small(metrics) => metrics.write_into(writer),
+                BitmapMetrics::Big(metrics) => metrics.write_into(writer),
+            }
+        }
+        match &self.image {
+            BitmapImage::ByteAligned(data) | BitmapImage::BitAligned(data) => {
+                writer.write_slice(data)
+            }
+            BitmapImage::Png(data) => {
+                (data.len() as u32).write_into(writer);
+                writer.write_slice(data);
+            }
+            BitmapImage::Composite(components) => {
+                if matches!(self.metrics, BitmapMetrics::Small(_)) {
+                    // format 8 pads the small metrics to an even length
+                    0u8.write_into(writer);
+                }
+                (components.len() as u16).write_into(writer);
+                components.write_into(writer);
+            }
+        }
+    }
+}
+
+/// Builds the [CBLC] and [CBDT] tables from a list of strikes.
+///
+/// The index subtable format for each group of glyphs is chosen to minimize
+/// the size of the location table, and the line metrics of each strike are
+/// computed from the metrics of its glyphs.
+///
+/// [CBLC]: https://learn.microsoft.com/en-us/typography/opentype/spec/cblc
+/// [CBDT]: https://learn.microsoft.com/en-us/typography/opentype/spec/cbdt
+pub fn build_cbdt(strikes: &[BitmapStrike]) -> Result<(Cblc, Cbdt), BitmapError> {
+    let (location, data) = build_tables(strikes, 3, true)?;
+    Ok((Cblc(location), Cbdt(data)))
+}
+
+/// Builds the [EBLC] and [EBDT] tables from a list of strikes.
+///
+/// Strikes must have a bit depth of 1, 2, 4 or 8, and glyphs must not have
+/// PNG images. See [`build_cbdt`] for details.
+///
+/// [EBLC]: https://learn.microsoft.com/en-us/typography/opentype/spec/eblc
+/// [EBDT]: https://learn.microsoft.com/en-us/typography/opentype/spec/ebdt
+pub fn build_ebdt(strikes: &[BitmapStrike]) -> Result<(Eblc, Ebdt), BitmapError> {
+    let (location, data) = build_tables(strikes, 2, false)?;
+    Ok((Eblc(location), Ebdt(data)))
+}
+
+fn build_tables(
+    strikes: &[BitmapStrike],
+    major_version: u16,
+    is_color: bool,
+) -> Result<(Vec<u8>, Vec<u8>), BitmapError> {
+    let mut data = TableWriter::default();
+    major_version.write_into(&mut data);
+    0u16.write_into(&mut data);
+
+    let mut sizes = Vec::with_capacity(strikes.len());
+    let mut index_tables = Vec::new();
+    let mut offset = LOCATION_HEADER_LEN + strikes.len() * BITMAP_SIZE_LEN;
+    for strike in strikes {
+        let valid_depths: &[u8] = if is_color {
+            &[1, 2, 4, 8, 32]
+        } else {
+            &[1, 2, 4, 8]
+        };
+        if !valid_depths.contains(&strike.bit_depth) {
+            return Err(BitmapError::InvalidBitDepth(strike.bit_depth));
+        }
+        for (gid, glyph) in &strike.glyphs {
+            glyph.check(*gid, strike.bit_depth, is_color)?;
+        }
+        let subtables = write_strike(strike, &mut data)?;
+        let tables = write_index_tables(&subtables);
+        let (hori, vert) = strike.line_metrics();
+        sizes.push(BitmapSize {
+            index_subtable_array_offset: offset.try_into().map_err(|_| BitmapError::TooLarge)?,
+            index_tables_size: tables.len() as u32,
+            number_of_index_subtables: subtables.len() as u32,
+            color_ref: 0,
+            hori,
+            vert,
+            start_glyph_index: strike.glyphs.keys().next().copied().unwrap_or_default(),
+            end_glyph_index: strike.glyphs.keys().last().copied().unwrap_or_default(),
+            ppem_x: strike.ppem_x,
+            ppem_y: strike.ppem_y,
+            bit_depth: strike.bit_depth,
+            flags: strike.flags,
+        });
+        offset += tables.len();
+        index_tables.extend(tables);
+    }
+
+    let mut location = TableWriter::default();
+    major_version.write_into(&mut location);
+    0u16.write_into(&mut location);
+    (sizes.len() as u32).write_into(&mut location);
+    sizes.write_into(&mut location);
+    location.write_slice(&index_tables);
+    Ok((location.into_data().bytes, data.into_data().bytes))
+}
+
+/// An index subtable, with the range of glyphs it covers.
+type Subtable = (GlyphId, GlyphId, IndexSubtable);
+
+/// Writes the images of a strike, returning the index subtables that locate
+/// them.
+///
+/// Glyphs are split into runs that share an image format. A run of glyphs
+/// with the same metrics and image size uses format 2 or 5, with the metrics
+/// stored once; other runs use whichever of formats 1, 3 and 4 is smallest.
+fn write_strike(
+    strike: &BitmapStrike,
+    data: &mut TableWriter,
+) -> Result<Vec<Subtable>, BitmapError> {
+    let glyphs = strike.glyphs.iter().collect::<Vec<_>>();
+    let formats = glyphs
+        .iter()
+        .map(|(_, glyph)| glyph.image_format())
+        .collect::<Vec<_>>();
+    let keys = glyphs
+        .iter()
+        .map(|(_, glyph)| glyph.constant_format(strike.bit_depth))
+        .collect::<Vec<_>>();
+    // the number of glyphs from each index that share constant metrics
+    let mut runs = vec![0; glyphs.len()];
+    for i in (0..glyphs.len()).rev() {
+        runs[i] = match &keys[i] {
+            None => 0,
+            Some(key) if keys.get(i + 1) == Some(&Some(*key)) => runs[i + 1] + 1,
+            Some(_) => 1,
+        };
+    }
+
+    let mut subtables = Vec::new();
+    let mut start = 0;
+    while start < glyphs.len() {
+        let image_data_offset = data_len(data)?;
+        if runs[start] >= MIN_CONSTANT_RUN {
+            let run = &glyphs[start..start + runs[start]];
+            let (image_format, metrics, image_size) = keys[start].unwrap();
+            for (_, glyph) in run {
+                glyph.write_into(data, false);
+            }
+            let first = *run[0].0;
+            let last = *run[run.len() - 1].0;
+            let subtable = if (last.to_u16() - first.to_u16()) as usize + 1 == run.len() {
+                IndexSubtable2::new(
+                    image_format,
+                    image_data_offset,
+                    image_size as u32,
+                    vec![metrics.clone()],
+                )
+                .into()
+            } else {
+                let gids = run.iter().map(|(gid, _)| **gid).collect();
+                IndexSubtable5::new(
+                    image_format,
+                    image_data_offset,
+                    image_size as u32,
+                    vec![metrics.clone()],
+                    gids,
+                )
+                .into()
+            };
+            subtables.push((first, last, subtable));
+            start += run.len();
+            continue;
+        }
+
+        let mut end = start + 1;
+        while end < glyphs.len() && formats[end] == formats[start] && runs[end] < MIN_CONSTANT_RUN {
+            end += 1;
+        }
+        let mut offsets = Vec::with_capacity(end - start + 1);
+        for (gid, glyph) in &glyphs[start..end] {
+            offsets.push((**gid, data_len(data)? - image_data_offset));
+            glyph.write_into(data, true);
+        }
+        let total = data_len(data)? - image_data_offset;
+        let first = offsets[0].0;
+        let last = offsets[offsets.len() - 1].0;
+        let subtable = variable_subtable(formats[start], image_data_offset, &offsets, total);
+        subtables.push((first, last, subtable));
+        start = end;
+    }
+    Ok(subtables)
+}
+
+fn data_len(data: &TableWriter) -> Result<u32, BitmapError> {
+    data.current_data()
+        .bytes
+        .len()
+        .try_into()
+        .map_err(|_| BitmapError::TooLarge)
+}
+
+/// Returns the smallest index subtable for glyphs with their own metrics.
+///
+/// `offsets` are the glyphs and the offsets of their images from
+/// `image_data_offset`, and `total` is the length of all the images.
+fn variable_subtable(
+    image_format: u16,
+    image_data_offset: u32,
+    offsets: &[(GlyphId, u32)],
+    total: u32,
+) -> IndexSubtable {
+    let first = offsets[0].0.to_u16() as usize;
+    let last = offsets[offsets.len() - 1].0.to_u16() as usize;
+    let num_glyphs = last - first + 1;
+    let fits_u16 = total <= u16::MAX as u32;
+    // formats 1 and 3 cover every glyph in the range, so they can only be
+    // smaller than format 4 if there are few gaps
+    let format_1_len = 4 * (num_glyphs + 1);
+    let format_3_len = (2 * (num_glyphs + 1)).next_multiple_of(4);
+    let format_4_len = 4 + 4 * (offsets.len() + 1);
+    let format = [
+        (fits_u16, format_3_len, 3),
+        (fits_u16, format_4_len, 4),
+        (true, format_1_len, 1),
+    ]
+    .into_iter()
+    .filter(|(valid, ..)| *valid)
+    .min_by_key(|(_, len, _)| *len)
+    .map(|(.., format)| format)
+    .unwrap();
+
+    // offsets for every glyph in the range; a missing glyph has an empty image
+    let mut range_offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut present = offsets.iter().peekable();
+    for gid in first..=last {
+        let offset = match present.next_if(|(glyph, _)| glyph.to_u16() as usize == gid) {
+            Some((_, offset)) => *offset,
+            None => present.peek().map(|(_, offset)| *offset).unwrap_or(total),
+        };
+        range_offsets.push(offset);
+    }
+    range_offsets.push(total);
+
+    match format {
+        3 => IndexSubtable3::new(
+            image_format,
+            image_data_offset,
+            range_offsets.into_iter().map(|o| o as u16).collect(),
+        )
+        .into(),
+        4 => {
+            let glyph_array = offsets
+                .iter()
+                .copied()
+                .chain([(GlyphId::NOTDEF, total)])
+                .map(|(gid, offset)| GlyphIdOffsetPair::new(gid, offset as u16))
+                .collect();
+            IndexSubtable4::new(image_format, image_data_offset, glyph_array).into()
+        }
+        _ => IndexSubtable1::new(image_format, image_data_offset, range_offsets).into(),
+    }
+}
+
+/// Writes the index subtable array of a strike, followed by the subtables.
+fn write_index_tables(subtables: &[Subtable]) -> Vec<u8> {
+    let mut writer = TableWriter::default();
+    let mut offset = subtables.len() * INDEX_SUBTABLE_RECORD_LEN;
+    let mut bodies = TableWriter::default();
+    for (first, last, subtable) in subtables {
+        IndexSubtableArray::new(*first, *last, offset as u32).write_into(&mut writer);
+        subtable.write_into(&mut bodies);
+        // subtables are aligned to four bytes
+        let len = bodies.current_data().bytes.len();
+        bodies.write_slice(&vec![0; len.next_multiple_of(4) - len]);
+        offset = subtables.len() * INDEX_SUBTABLE_RECORD_LEN + bodies.current_data().bytes.len();
+    }
+    let mut data = writer.into_data().bytes;
+    data.extend(bodies.into_data().bytes);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{
+        tables::{
+            bitmap::{BitmapContent, BitmapDataFormat, BitmapMetrics as ReadMetrics},
+            cbdt::Cbdt as ReadCbdt,
+            cblc::Cblc as ReadCblc,
+            ebdt::Ebdt as ReadEbdt,
+            eblc::Eblc as ReadEblc,
+        },
+        FontRead,
+    };
+
+    use super::*;
+
+    fn big_metrics(width: u8, height: u8) -> BigGlyphMetrics {
+        BigGlyphMetrics::new(
+            height,
+            width,
+            1,
+            height as i8 - 2,
+            width + 2,
+            -(width as i8) / 2,
+            1,
+            height + 2,
+        )
+    }
+
+    fn small_metrics(width: u8, height: u8, bearing_y: i8) -> SmallGlyphMetrics {
+        SmallGlyphMetrics::new(height, width, 0, bearing_y, width + 1)
+    }
+
+    fn index_formats(location: &[u8], strike: usize) -> Vec<u16> {
+        let eblc = ReadEblc::read(location.into()).unwrap();
+        let size = &eblc.bitmap_sizes()[strike];
+        let offset = size.index_subtable_array_offset() as usize;
+        (0..size.number_of_index_subtables() as usize)
+            .map(|i| {
+                let array = &location[offset + i * 8..];
+                let sub = u32::from_be_bytes(array[4..8].try_into().unwrap()) as usize;
+                u16::from_be_bytes(location[offset + sub..offset + sub + 2].try_into().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn color_round_trip() {
+        let png = |n: u8| vec![0x89, b'P', b'N', b'G', n];
+        let mut strike = BitmapStrike::new(109, 32);
+        strike.add_glyph(
+            GlyphId::new(4),
+            BitmapMetrics::Small(small_metrics(136, 128, 101)),
+            BitmapImage::Png(png(1)),
+        );
+        strike.add_glyph(
+            GlyphId::new(5),
+            BitmapMetrics::Big(big_metrics(20, 30)),
+            BitmapImage::Png(png(2)),
+        );
+        let (cblc, cbdt) = build_cbdt(&[strike]).unwrap();
+        let cblc_data = crate::dump_table(&cblc).unwrap();
+        let cbdt_data = crate::dump_table(&cbdt).unwrap();
+        let cblc = ReadCblc::read(cblc_data.as_slice().into()).unwrap();
+        let cbdt = ReadCbdt::read(cbdt_data.as_slice().into()).unwrap();
+        assert_eq!(cbdt.major_version(), 3);
+        let size = &cblc.bitmap_sizes()[0];
+        assert_eq!(size.bit_depth(), 32);
+        assert_eq!(size.start_glyph_index(), GlyphId::new(4));
+        assert_eq!(size.end_glyph_index(), GlyphId::new(5));
+        assert_eq!(size.hori.ascender(), 101);
+        assert_eq!(size.hori.descender(), -27);
+        assert_eq!(size.hori.width_max(), 136);
+
+        let location = size.location(cblc.offset_data(), GlyphId::new(4)).unwrap();
+        assert_eq!(location.format, 17);
+        let glyph = cbdt.data(&location).unwrap();
+        let ReadMetrics::Small(metrics) = glyph.metrics else {
+            panic!("expected small metrics");
+        };
+        assert_eq!(metrics.bearing_y, 101);
+        let BitmapContent::Data(BitmapDataFormat::Png, data) = glyph.content else {
+            panic!("expected PNG data");
+        };
+        assert_eq!(data, png(1));
+        let location = size.location(cblc.offset_data(), GlyphId::new(5)).unwrap();
+        assert_eq!(location.format, 18);
+        let glyph = cbdt.data(&location).unwrap();
+        assert!(matches!(glyph.content, BitmapContent::Data(_, data) if data == png(2)));
+    }
+
+    #[test]
+    fn mono_round_trip() {
+        // four glyphs with identical metrics, two with their own, and a composite
+        let mut strike = BitmapStrike::new(12, 1);
+        for gid in [1, 2, 4, 6] {
+            strike.add_glyph(
+                GlyphId::new(gid),
+                BitmapMetrics::Big(big_metrics(8, 2)),
+                BitmapImage::ByteAligned(vec![gid as u8, 0xFF]),
+            );
+        }
+        strike.add_glyph(
+            GlyphId::new(7),
+            BitmapMetrics::Small(small_metrics(3, 3, 3)),
+            BitmapImage::BitAligned(vec![0b1011_0101, 0b1000_0000]),
+        );
+        strike.add_glyph(
+            GlyphId::new(9),
+            BitmapMetrics::Small(small_metrics(9, 1, 1)),
+            BitmapImage::ByteAligned(vec![0xFF, 0x80]),
+        );
+        strike.add_glyph(
+            GlyphId::new(10),
+            BitmapMetrics::Big(big_metrics(8, 4)),
+            BitmapImage::Composite(vec![
+                BdtComponent::new(GlyphId::new(1), 0, 0),
+                BdtComponent::new(GlyphId::new(2), 0, 2),
+            ]),
+        );
+        let (eblc, ebdt) = build_ebdt(&[strike]).unwrap();
+        let eblc_data = crate::dump_table(&eblc).unwrap();
+        let ebdt_data = crate::dump_table(&ebdt).unwrap();
+        assert_eq!(index_formats(&eblc_data, 0), [5, 3, 3, 3]);
+        let eblc = ReadEblc::read(eblc_data.as_slice().into()).unwrap();
+        let ebdt = ReadEbdt::read(ebdt_data.as_slice().into()).unwrap();
+        let size = &eblc.bitmap_sizes()[0];
+        assert_eq!(size.number_of_index_subtables(), 4);
+
+        let location = size.location(eblc.offset_data(), GlyphId::new(4)).unwrap();
+        assert_eq!(location.format, 5);
+        let glyph = ebdt.data(&location).unwrap();
+        assert!(matches!(glyph.metrics, ReadMetrics::Big(m) if m.width == 8 && m.height == 2));
+        assert!(matches!(glyph.content, BitmapContent::Data(_, [4, 0xFF])));
+
+        let location = size.location(eblc.offset_data(), GlyphId::new(7)).unwrap();
+        assert_eq!(location.format, 2);
+        let glyph = ebdt.data(&location).unwrap();
+        assert!(matches!(
+            glyph.content,
+            BitmapContent::Data(BitmapDataFormat::BitAligned, [0b1011_0101, 0b1000_0000])
+        ));
+
+        let location = size.location(eblc.offset_data(), GlyphId::new(10)).unwrap();
+        assert_eq!(location.format, 9);
+        let glyph = ebdt.data(&location).unwrap();
+        let BitmapContent::Composite(components) = glyph.content else {
+            panic!("expected composite");
+        };
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[1].y_offset, 2);
+    }
+
+    #[test]
+    fn index_formats_for_gaps() {
+        let mut strike = BitmapStrike::new(12, 8);
+        // a sparse range is cheaper with format 4
+        for gid in [10, 100] {
+            strike.add_glyph(
+                GlyphId::new(gid),
+                BitmapMetrics::Small(small_metrics(1, 1, 1)),
+                BitmapImage::ByteAligned(vec![0xFF]),
+            );
+        }
+        // a few gaps are cheaper with format 3
+        for gid in [200, 201, 203, 204] {
+            strike.add_glyph(
+                GlyphId::new(gid),
+                BitmapMetrics::Big(big_metrics(1, 1)),
+                BitmapImage::Composite(vec![BdtComponent::new(GlyphId::new(10), 0, 0)]),
+            );
+        }
+        let (eblc, ebdt) = build_ebdt(&[strike]).unwrap();
+        let eblc_data = crate::dump_table(&eblc).unwrap();
+        assert_eq!(index_formats(&eblc_data, 0), [4, 3]);
+        let ebdt_data = crate::dump_table(&ebdt).unwrap();
+        let eblc = ReadEblc::read(eblc_data.as_slice().into()).unwrap();
+        let ebdt = ReadEbdt::read(ebdt_data.as_slice().into()).unwrap();
+        let size = &eblc.bitmap_sizes()[0];
+        for gid in [10, 100, 200, 204] {
+            let location = size
+                .location(eblc.offset_data(), GlyphId::new(gid))
+                .unwrap();
+            assert!(ebdt.data(&location).is_ok(), "glyph {gid}");
+        }
+        // the missing glyph has an empty image
+        let location = size
+            .location(eblc.offset_data(), GlyphId::new(203))
+            .unwrap();
+        let next = size
+            .location(eblc.offset_data(), GlyphId::new(202))
+            .unwrap();
+        assert_eq!(location.data_offset, next.data_offset);
+    }
+
+    #[test]
+    fn line_metrics() {
+        let mut strike = BitmapStrike::new(10, 1);
+        strike.add_glyph(
+            GlyphId::new(1),
+            BitmapMetrics::Small(SmallGlyphMetrics::new(10, 4, -1, 8, 4)),
+            BitmapImage::BitAligned(vec![0; 5]),
+        );
+        strike.add_glyph(
+            GlyphId::new(2),
+            BitmapMetrics::Small(SmallGlyphMetrics::new(6, 8, 1, 3, 10)),
+            BitmapImage::BitAligned(vec![0; 6]),
+        );
+        let (hori, vert) = strike.line_metrics();
+        assert_eq!(hori, vert);
+        assert_eq!(hori.ascender, 8);
+        assert_eq!(hori.descender, -3);
+        assert_eq!(hori.width_max, 8);
+        assert_eq!(hori.min_origin_sb, -1);
+        // the first glyph extends past its advance by one pixel
+        assert_eq!(hori.min_advance_sb, 1);
+        assert_eq!(hori.max_before_bl, 8);
+        assert_eq!(hori.min_after_bl, -3);
+    }
+
+    #[test]
+    fn errors() {
+        let mut strike = BitmapStrike::new(10, 1);
+        strike.add_glyph(
+            GlyphId::new(1),
+            BitmapMetrics::Small(small_metrics(9, 2, 0)),
+            BitmapImage::ByteAligned(vec![0; 3]),
+        );
+        assert_eq!(
+            build_ebdt(&[strike.clone()]),
+            Err(BitmapError::ImageSize {
+                glyph: GlyphId::new(1),
+                expected: 4,
+                found: 3
+            })
+        );
+        strike.add_glyph(
+            GlyphId::new(1),
+            BitmapMetrics::Small(small_metrics(9, 2, 0)),
+            BitmapImage::Png(vec![0]),
+        );
+        assert_eq!(
+            build_ebdt(&[strike.clone()]),
+            Err(BitmapError::UnexpectedPng(GlyphId::new(1)))
+        );
+        strike.bit_depth = 32;
+        assert_eq!(
+            build_ebdt(&[strike.clone()]),
+            Err(BitmapError::InvalidBitDepth(32))
+        );
+        assert!(build_cbdt(&[strike]).is_ok());
+    }
+}
@@ -0,0 +1,11 @@
+//! The [CBDT (Color Bitmap Data)](https://learn.microsoft.com/en-us/typography/opentype/spec/cbdt) table
+
+super::bitmap::bitmap_table!(
+    /// The [CBDT (Color Bitmap Data)](https://learn.microsoft.com/en-us/typography/opentype/spec/cbdt) table
+    ///
+    /// This table contains the color bitmaps of the font, which are located by the
+    /// ['CBLC' table][super::cblc]. It is built together with that table by
+    /// [`build_cbdt`][super::bitmap::build_cbdt].
+    Cbdt,
+    b"CBDT"
+);
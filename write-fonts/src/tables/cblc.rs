@@ -0,0 +1,11 @@
+//! The [CBLC (Color Bitmap Location)](https://learn.microsoft.com/en-us/typography/opentype/spec/cblc) table
+
+super::bitmap::bitmap_table!(
+    /// The [CBLC (Color Bitmap Location)](https://learn.microsoft.com/en-us/typography/opentype/spec/cblc) table
+    ///
+    /// This table contains the locations of the color bitmaps in the
+    /// ['CBDT' table][super::cbdt]. It is built together with that table by
+    /// [`build_cbdt`][super::bitmap::build_cbdt].
+    Cblc,
+    b"CBLC"
+);
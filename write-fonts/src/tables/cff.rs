@@ -0,0 +1,757 @@
+//! The [CFF](https://learn.microsoft.com/en-us/typography/opentype/spec/cff) table
+
+include!("../../generated/generated_cff.rs");
+
+use std::collections::HashMap;
+
+use kurbo::Rect;
+use read_fonts::tables::postscript::STANDARD_STRINGS;
+
+use super::postscript::{
+    build_fd_select, escaped, subroutinize, write_index, write_private_dict, CffError, Charstring,
+    DictWriter, PrivateDict, Subroutines,
+};
+
+// Top DICT and Font DICT operators
+const VERSION: u16 = 0;
+const NOTICE: u16 = 1;
+const FULL_NAME: u16 = 2;
+const FAMILY_NAME: u16 = 3;
+const WEIGHT: u16 = 4;
+const FONT_BBOX: u16 = 5;
+const CHARSET: u16 = 15;
+const CHARSTRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const COPYRIGHT: u16 = escaped(0);
+const IS_FIXED_PITCH: u16 = escaped(1);
+const ITALIC_ANGLE: u16 = escaped(2);
+const UNDERLINE_POSITION: u16 = escaped(3);
+const UNDERLINE_THICKNESS: u16 = escaped(4);
+const FONT_MATRIX: u16 = escaped(7);
+const ROS: u16 = escaped(30);
+const CID_COUNT: u16 = escaped(34);
+const FD_ARRAY: u16 = escaped(36);
+const FD_SELECT: u16 = escaped(37);
+const FONT_NAME: u16 = escaped(38);
+
+/// The [CFF](https://learn.microsoft.com/en-us/typography/opentype/spec/cff) table
+///
+/// This table contains a single font, with its PostScript outlines. It is
+/// constructed with a [`CffBuilder`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cff(Vec<u8>);
+
+impl TopLevelTable for Cff {
+    /// 'CFF '
+    const TAG: Tag = Tag::new(b"CFF ");
+}
+
+impl Validate for Cff {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FontWrite for Cff {
+    fn write_into(&self, writer: &mut TableWriter) {
+        writer.write_slice(&self.0)
+    }
+}
+
+/// The entries of a Top DICT that describe the font.
+///
+/// Strings are omitted if they are `None`, and other values if they have
+/// their default values.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopDict {
+    pub version: Option<String>,
+    pub notice: Option<String>,
+    pub copyright: Option<String>,
+    pub full_name: Option<String>,
+    pub family_name: Option<String>,
+    pub weight: Option<String>,
+    pub is_fixed_pitch: bool,
+    pub italic_angle: f64,
+    pub underline_position: f64,
+    pub underline_thickness: f64,
+    /// The transformation from glyph space to text space; the default is a
+    /// scale of 1/1000.
+    pub font_matrix: Option<[f64; 6]>,
+}
+
+impl Default for TopDict {
+    fn default() -> Self {
+        Self {
+            version: None,
+            notice: None,
+            copyright: None,
+            full_name: None,
+            family_name: None,
+            weight: None,
+            is_fixed_pitch: false,
+            italic_angle: 0.0,
+            underline_position: -100.0,
+            underline_thickness: 50.0,
+            font_matrix: None,
+        }
+    }
+}
+
+/// The character collection of a CID-keyed font.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CidFont {
+    pub registry: String,
+    pub ordering: String,
+    pub supplement: i32,
+}
+
+/// A Font DICT of a CID-keyed font, with its Private DICT.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontDict {
+    pub name: Option<String>,
+    pub private_dict: PrivateDict,
+}
+
+/// A builder for the 'CFF ' table.
+///
+/// Glyphs are added in glyph order; each charstring is compiled when it is
+/// added, so that errors can be associated with a particular glyph. The
+/// font bounding box is computed from the outlines.
+///
+/// # Example
+///
+/// ```
+/// use kurbo::{BezPath, Rect, Shape};
+/// use write_fonts::tables::{
+///     cff::{CffBuilder, TopDict},
+///     postscript::{Charstring, PrivateDict},
+/// };
+///
+/// let square = Rect::new(50.0, 0.0, 450.0, 400.0).into_path(0.1);
+/// let mut builder = CffBuilder::new("MyFont-Regular", TopDict::default(), PrivateDict::default());
+/// builder
+///     .add_glyph(".notdef", 500.0, &Charstring::default())
+///     .unwrap()
+///     .add_glyph("square", 500.0, &Charstring::from_path(&square))
+///     .unwrap();
+/// let _cff = builder.build().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CffBuilder {
+    font_name: String,
+    top_dict: TopDict,
+    cid_font: Option<CidFont>,
+    font_dicts: Vec<FontDict>,
+    strings: Strings,
+    /// The SID (or CID, in CID-keyed fonts) of each glyph.
+    charset: Vec<u16>,
+    fds: Vec<u16>,
+    charstrings: Vec<Vec<u8>>,
+    bounds: Option<Rect>,
+    subroutinize: bool,
+}
+
+impl CffBuilder {
+    /// Creates a builder for a name-keyed font.
+    pub fn new(font_name: impl Into<String>, top_dict: TopDict, private_dict: PrivateDict) -> Self {
+        let font_dict = FontDict {
+            name: None,
+            private_dict,
+        };
+        Self::new_impl(font_name.into(), top_dict, None, vec![font_dict])
+    }
+
+    /// Creates a builder for a CID-keyed font, whose glyphs are each
+    /// associated with one of the Font DICTs.
+    pub fn new_cid(
+        font_name: impl Into<String>,
+        top_dict: TopDict,
+        cid_font: CidFont,
+        font_dicts: Vec<FontDict>,
+    ) -> Self {
+        Self::new_impl(font_name.into(), top_dict, Some(cid_font), font_dicts)
+    }
+
+    fn new_impl(
+        font_name: String,
+        top_dict: TopDict,
+        cid_font: Option<CidFont>,
+        font_dicts: Vec<FontDict>,
+    ) -> Self {
+        Self {
+            font_name,
+            top_dict,
+            cid_font,
+            font_dicts,
+            strings: Strings::default(),
+            charset: Vec::new(),
+            fds: Vec::new(),
+            charstrings: Vec::new(),
+            bounds: None,
+            subroutinize: false,
+        }
+    }
+
+    /// Sets whether repeated parts of charstrings are moved into
+    /// subroutines, which makes the table smaller but takes longer to build.
+    pub fn set_subroutinize(&mut self, subroutinize: bool) -> &mut Self {
+        self.subroutinize = subroutinize;
+        self
+    }
+
+    /// Adds a glyph to a name-keyed font.
+    ///
+    /// The first glyph should be `.notdef`. The width is omitted from the
+    /// charstring if it is the `default_width_x` of the Private DICT.
+    pub fn add_glyph(
+        &mut self,
+        name: &str,
+        width: f64,
+        charstring: &Charstring,
+    ) -> Result<&mut Self, CffError> {
+        if self.cid_font.is_some() {
+            return Err(CffError::WrongKeying);
+        }
+        let sid = self.strings.sid(name)?;
+        self.add_glyph_impl(sid, 0, width, charstring)
+    }
+
+    /// Adds a glyph to a CID-keyed font, using the Font DICT at index `fd`.
+    ///
+    /// The first glyph should have CID 0. The width is omitted from the
+    /// charstring if it is the `default_width_x` of the Private DICT.
+    pub fn add_cid_glyph(
+        &mut self,
+        cid: u16,
+        fd: usize,
+        width: f64,
+        charstring: &Charstring,
+    ) -> Result<&mut Self, CffError> {
+        if self.cid_font.is_none() {
+            return Err(CffError::WrongKeying);
+        }
+        self.add_glyph_impl(cid, fd, width, charstring)
+    }
+
+    fn add_glyph_impl(
+        &mut self,
+        id: u16,
+        fd: usize,
+        width: f64,
+        charstring: &Charstring,
+    ) -> Result<&mut Self, CffError> {
+        if self.charstrings.len() >= u16::MAX as usize {
+            return Err(CffError::TooMany("glyphs"));
+        }
+        let private_dict = &self
+            .font_dicts
+            .get(fd)
+            .ok_or(CffError::InvalidFontDict(fd))?
+            .private_dict;
+        let width =
+            (width != private_dict.default_width_x).then_some(width - private_dict.nominal_width_x);
+        self.charstrings.push(charstring.compile_type2(width)?);
+        self.charset.push(id);
+        self.fds.push(fd as u16);
+        if let Some(bounds) = charstring.bounds() {
+            self.bounds = Some(self.bounds.map_or(bounds, |all| all.union(bounds)));
+        }
+        Ok(self)
+    }
+
+    /// Constructs the table.
+    pub fn build(mut self) -> Result<Cff, CffError> {
+        let is_cid = self.cid_font.is_some();
+        if self.font_dicts.len() > u8::MAX as usize + 1 {
+            return Err(CffError::TooMany("Font DICTs"));
+        }
+
+        // the entries of the Top DICT that do not depend on the layout
+        let mut top_dict = DictWriter::default();
+        if let Some(cid_font) = &self.cid_font {
+            // ROS must be the first entry
+            let registry = self.strings.sid(&cid_font.registry)?;
+            let ordering = self.strings.sid(&cid_font.ordering)?;
+            top_dict.numbers(
+                ROS,
+                &[registry as f64, ordering as f64, cid_font.supplement as f64],
+            );
+        }
+        let top = &self.top_dict;
+        for (op, string) in [
+            (VERSION, &top.version),
+            (NOTICE, &top.notice),
+            (COPYRIGHT, &top.copyright),
+            (FULL_NAME, &top.full_name),
+            (FAMILY_NAME, &top.family_name),
+            (WEIGHT, &top.weight),
+        ] {
+            if let Some(string) = string {
+                top_dict.numbers(op, &[self.strings.sid(string)? as f64]);
+            }
+        }
+        if top.is_fixed_pitch {
+            top_dict.numbers(IS_FIXED_PITCH, &[1.0]);
+        }
+        if top.italic_angle != 0.0 {
+            top_dict.numbers(ITALIC_ANGLE, &[top.italic_angle]);
+        }
+        if top.underline_position != -100.0 {
+            top_dict.numbers(UNDERLINE_POSITION, &[top.underline_position]);
+        }
+        if top.underline_thickness != 50.0 {
+            top_dict.numbers(UNDERLINE_THICKNESS, &[top.underline_thickness]);
+        }
+        if let Some(matrix) = &top.font_matrix {
+            top_dict.numbers(FONT_MATRIX, matrix);
+        }
+        let bounds = self.bounds.unwrap_or_default();
+        let bbox = [
+            bounds.x0.floor(),
+            bounds.y0.floor(),
+            bounds.x1.ceil(),
+            bounds.y1.ceil(),
+        ];
+        if bbox != [0.0; 4] {
+            top_dict.numbers(FONT_BBOX, &bbox);
+        }
+        if is_cid {
+            let cid_count = self.charset.iter().max().map_or(0, |cid| *cid as usize + 1);
+            top_dict.numbers(CID_COUNT, &[cid_count as f64]);
+        }
+        let font_dict_names = self
+            .font_dicts
+            .iter()
+            .map(|font_dict| {
+                font_dict
+                    .name
+                    .as_ref()
+                    .map(|name| self.strings.sid(name))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_fds = self.font_dicts.len();
+        let subrs = if self.subroutinize {
+            subroutinize(&self.charstrings, &self.fds, &vec![0; num_fds], false)
+        } else {
+            Subroutines::none(std::mem::take(&mut self.charstrings), num_fds)
+        };
+        // each Private DICT is followed by its local subroutines
+        let privates: Vec<_> = self
+            .font_dicts
+            .iter()
+            .zip(&subrs.local_subrs)
+            .map(|(font_dict, local_subrs)| {
+                write_private_dict(&font_dict.private_dict, local_subrs, false)
+            })
+            .collect();
+        let write_top_dict = |[charset, fd_select, charstrings, fd_array, private]: [usize; 5]| {
+            let mut dict = top_dict.clone();
+            dict.offsets(CHARSET, &[charset]);
+            if is_cid {
+                dict.offsets(FD_SELECT, &[fd_select]);
+            }
+            dict.offsets(CHARSTRINGS, &[charstrings]);
+            if is_cid {
+                dict.offsets(FD_ARRAY, &[fd_array]);
+            } else {
+                dict.offsets(PRIVATE, &[privates[0].0, private]);
+            }
+            dict.into_data()
+        };
+        let write_font_dicts = |private_offset: usize| {
+            let mut offset = private_offset;
+            let font_dicts: Vec<_> = font_dict_names
+                .iter()
+                .zip(&privates)
+                .map(|(name, (private_len, private))| {
+                    let mut dict = DictWriter::default();
+                    if let Some(name) = name {
+                        dict.numbers(FONT_NAME, &[*name as f64]);
+                    }
+                    dict.offsets(PRIVATE, &[*private_len, offset]);
+                    offset += private.len();
+                    dict.into_data()
+                })
+                .collect();
+            write_index(font_dicts.iter().map(Vec::as_slice), false)
+        };
+
+        let names = write_index([self.font_name.as_bytes()], false);
+        let top_dict_len = write_index([write_top_dict([0; 5]).as_slice()], false).len();
+        let strings = write_index(self.strings.custom.iter().map(String::as_bytes), false);
+        let global_subrs = write_index(subrs.global_subrs.iter().map(Vec::as_slice), false);
+        let charset = build_charset(&self.charset);
+        let fd_select = if is_cid {
+            crate::dump_table(&build_fd_select(&self.fds))?
+        } else {
+            Vec::new()
+        };
+        let charstrings = write_index(subrs.charstrings.iter().map(Vec::as_slice), false);
+
+        let charset_offset =
+            HEADER_SIZE + names.len() + top_dict_len + strings.len() + global_subrs.len();
+        let fd_select_offset = charset_offset + charset.len();
+        let charstrings_offset = fd_select_offset + fd_select.len();
+        let fd_array_offset = charstrings_offset + charstrings.len();
+        let fd_array_len = if is_cid { write_font_dicts(0).len() } else { 0 };
+        let private_offset = fd_array_offset + fd_array_len;
+        let top_dict = write_top_dict([
+            charset_offset,
+            fd_select_offset,
+            charstrings_offset,
+            fd_array_offset,
+            private_offset,
+        ]);
+
+        let mut data = names;
+        data.extend(write_index([top_dict.as_slice()], false));
+        data.extend(strings);
+        data.extend(global_subrs);
+        data.extend(charset);
+        data.extend(fd_select);
+        data.extend(charstrings);
+        if is_cid {
+            data.extend(write_font_dicts(private_offset));
+        }
+        for (_, private) in privates {
+            data.extend(private);
+        }
+        let off_size = match HEADER_SIZE + data.len() {
+            0..=0xFF => 1,
+            0x100..=0xFFFF => 2,
+            0x10000..=0xFFFFFF => 3,
+            _ => 4,
+        };
+        let header = CffHeader::new(HEADER_SIZE as u8, off_size, Vec::new(), data);
+        Ok(Cff(crate::dump_table(&header)?))
+    }
+}
+
+/// The size of the header written by [`CffBuilder`].
+const HEADER_SIZE: usize = 4;
+
+/// Allocates string identifiers, using the standard strings where possible.
+#[derive(Clone, Debug)]
+struct Strings {
+    ids: HashMap<String, u16>,
+    custom: Vec<String>,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        let ids = STANDARD_STRINGS
+            .iter()
+            .enumerate()
+            .map(|(sid, string)| (string.to_string(), sid as u16))
+            .collect();
+        Self {
+            ids,
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl Strings {
+    fn sid(&mut self, string: &str) -> Result<u16, CffError> {
+        if let Some(sid) = self.ids.get(string) {
+            return Ok(*sid);
+        }
+        let sid = STANDARD_STRINGS.len() + self.custom.len();
+        let sid: u16 = sid.try_into().map_err(|_| CffError::TooMany("strings"))?;
+        self.ids.insert(string.to_string(), sid);
+        self.custom.push(string.to_string());
+        Ok(sid)
+    }
+}
+
+/// Writes a charset for the SIDs or CIDs of each glyph, with format 0 or,
+/// if it is smaller, format 2.
+fn build_charset(ids: &[u16]) -> Vec<u8> {
+    // .notdef is not included
+    let ids = ids.get(1..).unwrap_or_default();
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for id in ids {
+        match ranges.last_mut() {
+            Some((first, n_left)) if *first as u32 + *n_left as u32 + 1 == *id as u32 => {
+                *n_left += 1
+            }
+            _ => ranges.push((*id, 0)),
+        }
+    }
+    if ranges.len() * 4 < ids.len() * 2 {
+        let mut data = vec![2];
+        for (first, n_left) in ranges {
+            data.extend_from_slice(&first.to_be_bytes());
+            data.extend_from_slice(&n_left.to_be_bytes());
+        }
+        return data;
+    }
+    let mut data = vec![0];
+    for id in ids {
+        data.extend_from_slice(&id.to_be_bytes());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::{BezPath, Circle, Shape};
+    use read_fonts::{
+        tables::{
+            cff::Cff as ReadCff,
+            postscript::{
+                charstring::{self, PenSink},
+                dict::{self, Entry},
+                Index,
+            },
+        },
+        FontData, FontRead,
+    };
+
+    use super::*;
+    use crate::{
+        pens::{write_to_pen, BezPathPen},
+        tables::postscript::font::CffFont,
+        tables::postscript::CharstringBuilder,
+    };
+
+    fn entries(data: &[u8]) -> Vec<Entry> {
+        dict::entries(data, None).collect::<Result<_, _>>().unwrap()
+    }
+
+    fn private_entries(cff: &ReadCff, dict_data: &[u8]) -> Vec<Entry> {
+        let range = entries(dict_data)
+            .into_iter()
+            .find_map(|entry| match entry {
+                Entry::PrivateDictRange(range) => Some(range),
+                _ => None,
+            })
+            .unwrap();
+        entries(&cff.offset_data().as_bytes()[range])
+    }
+
+    fn outline(font: &CffFont, gid: usize) -> BezPath {
+        let private = match &font.fd_select {
+            Some(fd_select) => {
+                let fd = fd_select.font_index(GlyphId::new(gid as u16)).unwrap();
+                font.font_dicts[fd as usize].private.as_ref()
+            }
+            None => font.private.as_ref(),
+        };
+        let local_subrs = private
+            .and_then(|private| private.subrs)
+            .map(|subrs| Index::new(subrs, false).unwrap());
+        let mut pen = BezPathPen::new();
+        let data = font.charstrings.get(gid).unwrap();
+        let global_subrs = font.global_subrs.clone();
+        charstring::evaluate(
+            data,
+            global_subrs,
+            local_subrs,
+            None,
+            &mut PenSink::new(&mut pen),
+        )
+        .unwrap();
+        pen.into_inner()
+    }
+
+    #[test]
+    fn name_keyed_round_trip() {
+        let top_dict = TopDict {
+            full_name: Some("Test Font Regular".into()),
+            weight: Some("Regular".into()),
+            italic_angle: -12.5,
+            ..Default::default()
+        };
+        let private_dict = PrivateDict {
+            blue_values: vec![-10.0, 0.0, 500.0, 510.0],
+            std_vw: Some(40.0),
+            default_width_x: 500.0,
+            nominal_width_x: 450.0,
+            ..Default::default()
+        };
+        let square = Rect::new(50.0, -20.0, 450.0, 400.0).into_path(0.1);
+        let mut hinted = CharstringBuilder::new();
+        hinted.vstem(50.0, 40.0);
+        write_to_pen(&square, &mut hinted);
+
+        let mut builder = CffBuilder::new("TestFont-Regular", top_dict, private_dict);
+        builder
+            .add_glyph(".notdef", 500.0, &Charstring::default())
+            .unwrap()
+            .add_glyph("A", 600.0, &Charstring::from_path(&square))
+            .unwrap()
+            .add_glyph("square.alt", 500.0, &hinted.build())
+            .unwrap();
+        let data = builder.build().unwrap().0;
+
+        let cff = ReadCff::read(FontData::new(&data)).unwrap();
+        assert_eq!(cff.name(0).unwrap().to_string(), "TestFont-Regular");
+        let top_dict = cff.top_dicts().get(0).unwrap();
+        let top_entries = entries(top_dict);
+        let string = |sid| cff.string(sid).unwrap().to_string();
+        assert!(top_entries.iter().any(|entry| matches!(
+            entry, Entry::FullName(sid) if string(*sid) == "Test Font Regular"
+        )));
+        assert!(top_entries.iter().any(|entry| matches!(
+            entry, Entry::Weight(sid) if string(*sid) == "Regular"
+        )));
+        assert!(top_entries.contains(&Entry::ItalicAngle(Fixed::from_f64(-12.5))));
+        assert!(top_entries.contains(&Entry::FontBbox([50, -20, 450, 400].map(Fixed::from_i32))));
+        let private_entries = private_entries(&cff, top_dict);
+        assert!(private_entries.contains(&Entry::DefaultWidthX(Fixed::from_i32(500))));
+        assert!(private_entries.contains(&Entry::NominalWidthX(Fixed::from_i32(450))));
+        assert!(private_entries.contains(&Entry::StdVw(Fixed::from_i32(40))));
+        let blues = private_entries
+            .iter()
+            .find_map(|entry| match entry {
+                Entry::BlueValues(blues) => Some(blues.values().to_vec()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            blues,
+            [(-10, 0), (500, 510)].map(|(a, b)| (Fixed::from_i32(a), Fixed::from_i32(b)))
+        );
+
+        let font = CffFont::new_cff(&data).unwrap();
+        let names: Vec<_> = font
+            .charset()
+            .unwrap()
+            .into_iter()
+            .map(|sid| string(read_fonts::tables::postscript::StringId::new(sid)))
+            .collect();
+        assert_eq!(names, [".notdef", "A", "square.alt"]);
+        assert!(outline(&font, 0).elements().is_empty());
+        assert_eq!(outline(&font, 1), square);
+        assert_eq!(outline(&font, 2), square);
+        // only the width of the second glyph differs from the default
+        assert_eq!(font.charstrings.get(1).unwrap()[..2], [247, 150 - 108]);
+        assert_eq!(font.charstrings.get(0).unwrap(), [14]);
+    }
+
+    #[test]
+    fn cid_keyed_round_trip() {
+        let font_dicts = vec![
+            FontDict {
+                name: Some("TestFont-Latin".into()),
+                private_dict: PrivateDict::default(),
+            },
+            FontDict {
+                name: Some("TestFont-Ideographs".into()),
+                private_dict: PrivateDict {
+                    language_group: 1,
+                    ..Default::default()
+                },
+            },
+        ];
+        let cid_font = CidFont {
+            registry: "Adobe".into(),
+            ordering: "Identity".into(),
+            supplement: 0,
+        };
+        let mut builder = CffBuilder::new_cid("TestFont", TopDict::default(), cid_font, font_dicts);
+        let square = Charstring::from_path(&Rect::new(0.0, 0.0, 100.0, 100.0).into_path(0.1));
+        for (cid, fd) in [(0, 0), (1, 0), (2, 0), (3, 0), (4, 1), (5, 1), (9, 1)] {
+            builder.add_cid_glyph(cid, fd, 1000.0, &square).unwrap();
+        }
+        assert!(matches!(
+            builder.add_glyph("a", 500.0, &square),
+            Err(CffError::WrongKeying)
+        ));
+        assert!(matches!(
+            builder.add_cid_glyph(10, 2, 500.0, &square),
+            Err(CffError::InvalidFontDict(2))
+        ));
+        let data = builder.build().unwrap().0;
+
+        let cff = ReadCff::read(FontData::new(&data)).unwrap();
+        let string = |sid| cff.string(sid).unwrap().to_string();
+        let top_entries = entries(cff.top_dicts().get(0).unwrap());
+        assert!(matches!(
+            top_entries[0],
+            Entry::Ros { registry, ordering, .. }
+                if string(registry) == "Adobe" && string(ordering) == "Identity"
+        ));
+        assert!(top_entries.contains(&Entry::CidCount(10)));
+
+        let font = CffFont::new_cff(&data).unwrap();
+        assert_eq!(font.charset().unwrap(), [0, 1, 2, 3, 4, 5, 9]);
+        let fd_select = font.fd_select.as_ref().unwrap();
+        let fds: Vec<_> = (0..7)
+            .map(|gid| fd_select.font_index(GlyphId::new(gid)).unwrap())
+            .collect();
+        assert_eq!(fds, [0, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(font.font_dicts.len(), 2);
+        let private = &font.font_dicts[1].private.as_ref().unwrap().entries;
+        assert_eq!(private.len(), 1);
+        for gid in 0..7 {
+            assert_eq!(outline(&font, gid).bounding_box(), square.bounds().unwrap());
+        }
+    }
+
+    #[test]
+    fn subroutinized_round_trip() {
+        let build = |subroutinize: bool, font_dicts: usize| {
+            let font_dicts = vec![FontDict::default(); font_dicts];
+            let cid_font = CidFont::default();
+            let mut builder =
+                CffBuilder::new_cid("TestFont", TopDict::default(), cid_font, font_dicts.clone());
+            builder.set_subroutinize(subroutinize);
+            for cid in 0..40u16 {
+                let x = cid as f64 * 10.0;
+                let mut path = Rect::new(x, 0.0, x + 100.0, 700.0).into_path(0.1);
+                path.extend(Circle::new((x + 300.0, 300.0), 200.0).path_elements(0.1));
+                let fd = cid as usize % font_dicts.len();
+                builder
+                    .add_cid_glyph(cid, fd, 600.0, &Charstring::from_path(&path))
+                    .unwrap();
+            }
+            builder.build().unwrap().0
+        };
+        for font_dicts in [1, 2] {
+            let data = build(false, font_dicts);
+            let subroutinized = build(true, font_dicts);
+            assert!(subroutinized.len() < data.len() * 2 / 3);
+            let font = CffFont::new_cff(&data).unwrap();
+            let subroutinized = CffFont::new_cff(&subroutinized).unwrap();
+            for gid in 0..40 {
+                assert_eq!(outline(&font, gid), outline(&subroutinized, gid));
+            }
+            // subroutines used by the glyphs of both Font DICTs are global
+            let private = subroutinized.font_dicts[0].private.as_ref().unwrap();
+            assert_eq!(private.subrs.is_some(), font_dicts == 1);
+            assert_eq!(subroutinized.global_subrs.count() > 0, font_dicts == 2);
+        }
+
+        // name-keyed fonts have a single Private DICT
+        let mut builder = CffBuilder::new("TestFont", TopDict::default(), PrivateDict::default());
+        builder.set_subroutinize(true);
+        for i in 0..20 {
+            let path = Circle::new((i as f64, 300.0), 200.0).to_path(0.1);
+            builder
+                .add_glyph(&format!("glyph{i}"), 600.0, &Charstring::from_path(&path))
+                .unwrap();
+        }
+        let data = builder.build().unwrap().0;
+        let font = CffFont::new_cff(&data).unwrap();
+        assert!(font.private.as_ref().unwrap().subrs.is_some());
+        for gid in 0..20 {
+            let circle = Circle::new((gid as f64, 300.0), 200.0).to_path(0.1);
+            let expected = Charstring::from_path(&circle).bounds().unwrap();
+            assert_eq!(outline(&font, gid).bounding_box(), expected);
+        }
+    }
+
+    #[test]
+    fn charset_formats() {
+        // ranges are smaller for consecutive identifiers
+        assert_eq!(build_charset(&[0, 1, 2, 3, 4]), [2, 0, 1, 0, 3]);
+        assert_eq!(build_charset(&[0, 9, 4]), [0, 0, 9, 0, 4]);
+        assert_eq!(build_charset(&[0]), [0]);
+    }
+}
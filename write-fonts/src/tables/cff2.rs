@@ -0,0 +1,497 @@
+//! The [CFF2](https://learn.microsoft.com/en-us/typography/opentype/spec/cff2) table
+
+include!("../../generated/generated_cff2.rs");
+
+use super::{
+    postscript::{
+        build_fd_select, escaped, subroutinize, write_index, write_private_dict, CffError,
+        Charstring, DictWriter, PrivateDict, Subroutines,
+    },
+    variations::ItemVariationStore,
+};
+
+// Top DICT and Font DICT operators
+const CHARSTRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const VARIATION_STORE: u16 = 24;
+const FONT_MATRIX: u16 = escaped(7);
+const FD_ARRAY: u16 = escaped(36);
+const FD_SELECT: u16 = escaped(37);
+
+/// The size of the header written by [`Cff2Builder`].
+const HEADER_SIZE: usize = 5;
+
+/// The [CFF2](https://learn.microsoft.com/en-us/typography/opentype/spec/cff2) table
+///
+/// This table contains PostScript outlines, which may be variable. It is
+/// constructed with a [`Cff2Builder`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cff2(Vec<u8>);
+
+impl TopLevelTable for Cff2 {
+    /// 'CFF2'
+    const TAG: Tag = Tag::new(b"CFF2");
+}
+
+impl Validate for Cff2 {
+    fn validate_impl(&self, _ctx: &mut ValidationCtx) {}
+}
+
+impl FontWrite for Cff2 {
+    fn write_into(&self, writer: &mut TableWriter) {
+        writer.write_slice(&self.0)
+    }
+}
+
+/// A builder for the 'CFF2' table.
+///
+/// Glyphs are added in glyph order; each charstring is compiled when it is
+/// added, so that errors can be associated with a particular glyph.
+///
+/// The deltas of variable charstrings are for the regions of the item
+/// variation data selected by the `vsindex` of their Private DICT.
+///
+/// # Example
+///
+/// ```
+/// use write_fonts::tables::{
+///     cff2::Cff2Builder,
+///     postscript::{CharstringBuilder, PrivateDict},
+/// };
+/// use write_fonts::types::Pen;
+///
+/// let mut glyph = CharstringBuilder::new();
+/// glyph.hstem(0.0, 20.0);
+/// glyph.move_to(0.0, 0.0);
+/// glyph.line_to(100.0, 0.0);
+/// glyph.line_to(100.0, 20.0);
+/// glyph.close();
+///
+/// let mut builder = Cff2Builder::new(vec![PrivateDict::default()], None);
+/// builder.add_glyph(0, &glyph.build()).unwrap();
+/// let _cff2 = builder.build().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cff2Builder {
+    private_dicts: Vec<PrivateDict>,
+    variation_store: Option<ItemVariationStore>,
+    font_matrix: Option<[f64; 6]>,
+    fds: Vec<u16>,
+    charstrings: Vec<Vec<u8>>,
+    subroutinize: bool,
+}
+
+impl Cff2Builder {
+    /// Creates a builder with a Font DICT for each Private DICT, and the
+    /// variation store for the deltas of variable charstrings.
+    pub fn new(
+        private_dicts: Vec<PrivateDict>,
+        variation_store: Option<ItemVariationStore>,
+    ) -> Self {
+        Self {
+            private_dicts,
+            variation_store,
+            font_matrix: None,
+            fds: Vec::new(),
+            charstrings: Vec::new(),
+            subroutinize: false,
+        }
+    }
+
+    /// Sets the transformation from glyph space to text space; the default
+    /// is a scale of 1/1000.
+    pub fn set_font_matrix(&mut self, font_matrix: [f64; 6]) -> &mut Self {
+        self.font_matrix = Some(font_matrix);
+        self
+    }
+
+    /// Sets whether repeated parts of charstrings are moved into
+    /// subroutines, which makes the table smaller but takes longer to build.
+    pub fn set_subroutinize(&mut self, subroutinize: bool) -> &mut Self {
+        self.subroutinize = subroutinize;
+        self
+    }
+
+    /// Adds a glyph, using the Font DICT at index `fd`.
+    pub fn add_glyph(&mut self, fd: usize, charstring: &Charstring) -> Result<&mut Self, CffError> {
+        if self.charstrings.len() >= u16::MAX as usize {
+            return Err(CffError::TooMany("glyphs"));
+        }
+        let private_dict = self
+            .private_dicts
+            .get(fd)
+            .ok_or(CffError::InvalidFontDict(fd))?;
+        if charstring.num_regions() != 0 {
+            if self.variation_store.is_none() {
+                return Err(CffError::UnexpectedVariations);
+            }
+            let expected = self.num_regions(private_dict);
+            if expected != charstring.num_regions() {
+                return Err(CffError::RegionCountMismatch {
+                    expected,
+                    found: charstring.num_regions(),
+                });
+            }
+        }
+        self.charstrings.push(charstring.compile_cff2()?);
+        self.fds.push(fd as u16);
+        Ok(self)
+    }
+
+    /// The number of regions of the item variation data of a Private DICT.
+    fn num_regions(&self, private_dict: &PrivateDict) -> usize {
+        self.variation_store
+            .as_ref()
+            .and_then(|store| store.item_variation_data.get(private_dict.vsindex as usize))
+            .and_then(|data| data.as_ref())
+            .map(|data| data.region_indexes.len())
+            .unwrap_or_default()
+    }
+
+    /// Constructs the table.
+    pub fn build(mut self) -> Result<Cff2, CffError> {
+        if self.private_dicts.len() > u16::MAX as usize {
+            return Err(CffError::TooMany("Font DICTs"));
+        }
+        let num_fds = self.private_dicts.len();
+        let subrs = if self.subroutinize {
+            let num_regions: Vec<_> = self
+                .private_dicts
+                .iter()
+                .map(|private_dict| self.num_regions(private_dict))
+                .collect();
+            subroutinize(&self.charstrings, &self.fds, &num_regions, true)
+        } else {
+            Subroutines::none(std::mem::take(&mut self.charstrings), num_fds)
+        };
+        // each Private DICT is followed by its local subroutines
+        let privates: Vec<_> = self
+            .private_dicts
+            .iter()
+            .zip(&subrs.local_subrs)
+            .map(|(private_dict, local_subrs)| write_private_dict(private_dict, local_subrs, true))
+            .collect();
+        let has_fd_select = self.private_dicts.len() > 1;
+        let write_top_dict = |[charstrings, variation_store, fd_select, fd_array]: [usize; 4]| {
+            let mut dict = DictWriter::default();
+            if let Some(matrix) = &self.font_matrix {
+                dict.numbers(FONT_MATRIX, matrix);
+            }
+            dict.offsets(CHARSTRINGS, &[charstrings]);
+            if self.variation_store.is_some() {
+                dict.offsets(VARIATION_STORE, &[variation_store]);
+            }
+            if has_fd_select {
+                dict.offsets(FD_SELECT, &[fd_select]);
+            }
+            dict.offsets(FD_ARRAY, &[fd_array]);
+            dict.into_data()
+        };
+        let write_font_dicts = |private_offset: usize| {
+            let mut offset = private_offset;
+            let font_dicts: Vec<_> = privates
+                .iter()
+                .map(|(private_len, private)| {
+                    let mut dict = DictWriter::default();
+                    dict.offsets(PRIVATE, &[*private_len, offset]);
+                    offset += private.len();
+                    dict.into_data()
+                })
+                .collect();
+            write_index(font_dicts.iter().map(Vec::as_slice), true)
+        };
+
+        let top_dict_len = write_top_dict([0; 4]).len();
+        let global_subrs = write_index(subrs.global_subrs.iter().map(Vec::as_slice), true);
+        // the variation store is prefixed by its length
+        let variation_store = match &self.variation_store {
+            Some(store) => {
+                let store = crate::dump_table(store)?;
+                let len: u16 = store
+                    .len()
+                    .try_into()
+                    .map_err(|_| CffError::TooMany("variation store bytes"))?;
+                let mut data = len.to_be_bytes().to_vec();
+                data.extend(store);
+                data
+            }
+            None => Vec::new(),
+        };
+        let fd_select = if has_fd_select {
+            crate::dump_table(&build_fd_select(&self.fds))?
+        } else {
+            Vec::new()
+        };
+        let charstrings = write_index(subrs.charstrings.iter().map(Vec::as_slice), true);
+
+        let variation_store_offset = HEADER_SIZE + top_dict_len + global_subrs.len();
+        let fd_select_offset = variation_store_offset + variation_store.len();
+        let charstrings_offset = fd_select_offset + fd_select.len();
+        let fd_array_offset = charstrings_offset + charstrings.len();
+        let private_offset = fd_array_offset + write_font_dicts(0).len();
+        let top_dict = write_top_dict([
+            charstrings_offset,
+            variation_store_offset,
+            fd_select_offset,
+            fd_array_offset,
+        ]);
+
+        let mut data = global_subrs;
+        data.extend(variation_store);
+        data.extend(fd_select);
+        data.extend(charstrings);
+        data.extend(write_font_dicts(private_offset));
+        for (_, private) in privates {
+            data.extend(private);
+        }
+        let header = Cff2Header::new(
+            HEADER_SIZE as u8,
+            top_dict.len() as u16,
+            Vec::new(),
+            top_dict,
+            data,
+        );
+        Ok(Cff2(crate::dump_table(&header)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use kurbo::{BezPath, Circle, Rect, Shape};
+    use read_fonts::{
+        tables::{
+            cff2::Cff2 as ReadCff2,
+            postscript::{
+                charstring::{self, PenSink},
+                dict::{self, Entry},
+                BlendState, Index,
+            },
+            variations::ItemVariationStore as ReadItemVariationStore,
+        },
+        FontData, FontRead,
+    };
+
+    use super::*;
+    use crate::{
+        pens::BezPathPen,
+        tables::postscript::font::CffFont,
+        tables::variations::{
+            ItemVariationData, RegionAxisCoordinates, VariationRegion, VariationRegionList,
+        },
+    };
+
+    /// A store with a single region, which peaks at the maximum of a single
+    /// axis, and item variation data for no regions and for that region.
+    fn variation_store() -> ItemVariationStore {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates::new(
+            F2Dot14::ZERO,
+            F2Dot14::ONE,
+            F2Dot14::ONE,
+        )]);
+        ItemVariationStore::new(
+            VariationRegionList::new(vec![region]),
+            vec![
+                Some(ItemVariationData::new(0, 0, Vec::new(), Vec::new())),
+                Some(ItemVariationData::new(0, 0, vec![0], Vec::new())),
+            ],
+        )
+    }
+
+    fn rect(x1: f64, y1: f64, x2: f64, y2: f64) -> BezPath {
+        Rect::new(x1, y1, x2, y2).into_path(0.1)
+    }
+
+    fn read_store(data: &[u8]) -> ReadItemVariationStore<'_> {
+        let cff2 = ReadCff2::read(FontData::new(data)).unwrap();
+        let offset = dict::entries(cff2.top_dict_data(), None)
+            .find_map(|entry| match entry {
+                Ok(Entry::VariationStoreOffset(offset)) => Some(offset),
+                _ => None,
+            })
+            .unwrap();
+        // skip the length of the store
+        ReadItemVariationStore::read(FontData::new(&data[offset + 2..])).unwrap()
+    }
+
+    #[test]
+    fn variable_round_trip() {
+        let private_dicts = vec![
+            PrivateDict {
+                blue_values: vec![0.0, 10.0],
+                ..Default::default()
+            },
+            PrivateDict {
+                vsindex: 1,
+                ..Default::default()
+            },
+        ];
+        let light = Charstring::from_path(&rect(10.0, 0.0, 110.0, 100.0));
+        let bold = Charstring::from_path(&rect(10.0, 0.0, 210.0, 120.0));
+        let variable = Charstring::variable(&light, &[bold.delta(&light).unwrap()]).unwrap();
+        let mut builder = Cff2Builder::new(private_dicts, Some(variation_store()));
+        builder
+            .add_glyph(0, &Charstring::default())
+            .unwrap()
+            .add_glyph(1, &variable)
+            .unwrap()
+            .add_glyph(0, &light)
+            .unwrap();
+        let data = builder.build().unwrap().0;
+
+        let cff2 = ReadCff2::read(FontData::new(&data)).unwrap();
+        assert_eq!(cff2.header().major_version(), 2);
+        let store = read_store(&data);
+
+        let font = CffFont::new_cff2(&data).unwrap();
+        let fd_select = font.fd_select.as_ref().unwrap();
+        let fds: Vec<_> = (0..3)
+            .map(|gid| fd_select.font_index(GlyphId::new(gid)).unwrap())
+            .collect();
+        assert_eq!(fds, [0, 1, 0]);
+        assert_eq!(font.font_dicts.len(), 2);
+
+        for (coord, expected) in [
+            (0.0, rect(10.0, 0.0, 110.0, 100.0)),
+            (0.5, rect(10.0, 0.0, 160.0, 110.0)),
+            (1.0, rect(10.0, 0.0, 210.0, 120.0)),
+        ] {
+            let coords = [F2Dot14::from_f32(coord)];
+            let blend_state = BlendState::new(store.clone(), &coords, 1).unwrap();
+            let mut pen = BezPathPen::new();
+            let global_subrs = font.global_subrs.clone();
+            let charstring = font.charstrings.get(1).unwrap();
+            charstring::evaluate(
+                charstring,
+                global_subrs,
+                None,
+                Some(blend_state),
+                &mut PenSink::new(&mut pen),
+            )
+            .unwrap();
+            assert_eq!(pen.into_inner().bounding_box(), expected.bounding_box());
+        }
+    }
+
+    fn outline(font: &CffFont, store: &ReadItemVariationStore, gid: u16, coord: f32) -> BezPath {
+        let fd = match &font.fd_select {
+            Some(fd_select) => fd_select.font_index(GlyphId::new(gid)).unwrap() as usize,
+            None => 0,
+        };
+        let local_subrs = font.font_dicts[fd]
+            .private
+            .as_ref()
+            .and_then(|private| private.subrs)
+            .map(|subrs| Index::new(subrs, true).unwrap());
+        let coords = [F2Dot14::from_f32(coord)];
+        let blend_state = BlendState::new(store.clone(), &coords, 1).unwrap();
+        let mut pen = BezPathPen::new();
+        charstring::evaluate(
+            font.charstrings.get(gid as usize).unwrap(),
+            font.global_subrs.clone(),
+            local_subrs,
+            Some(blend_state),
+            &mut PenSink::new(&mut pen),
+        )
+        .unwrap();
+        pen.into_inner()
+    }
+
+    #[test]
+    fn subroutinized_round_trip() {
+        let build = |subroutinize: bool| {
+            let private_dict = PrivateDict {
+                vsindex: 1,
+                ..Default::default()
+            };
+            let mut builder = Cff2Builder::new(vec![private_dict; 2], Some(variation_store()));
+            builder.set_subroutinize(subroutinize);
+            for i in 0..40 {
+                let x = i as f64 * 10.0;
+                let [light, bold] = [100.0, 200.0].map(|size| {
+                    let mut path = rect(x, 0.0, x + size, 700.0);
+                    // the shape of each Font DICT only varies in its width
+                    let circle =
+                        Circle::new((x + size + 300.0, 300.0), 200.0 + 20.0 * (i % 2) as f64);
+                    path.extend(circle.path_elements(0.1));
+                    Charstring::from_path(&path)
+                });
+                let variable =
+                    Charstring::variable(&light, &[bold.delta(&light).unwrap()]).unwrap();
+                builder.add_glyph(i % 2, &variable).unwrap();
+            }
+            builder.build().unwrap().0
+        };
+        let data = build(false);
+        let subroutinized = build(true);
+        assert!(subroutinized.len() < data.len() * 2 / 3);
+        let (store, subroutinized_store) = (read_store(&data), read_store(&subroutinized));
+        let font = CffFont::new_cff2(&data).unwrap();
+        let subroutinized = CffFont::new_cff2(&subroutinized).unwrap();
+        assert!(subroutinized.global_subrs.count() > 0);
+        for private in subroutinized
+            .font_dicts
+            .iter()
+            .map(|font_dict| &font_dict.private)
+        {
+            assert!(private.as_ref().unwrap().subrs.is_some());
+        }
+        for gid in 0..40 {
+            for coord in [0.0, 0.5, 1.0] {
+                assert_eq!(
+                    outline(&font, &store, gid, coord),
+                    outline(&subroutinized, &subroutinized_store, gid, coord)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn single_font_dict() {
+        let mut builder = Cff2Builder::new(vec![PrivateDict::default()], None);
+        builder.set_font_matrix([0.0005, 0.0, 0.0, 0.0005, 0.0, 0.0]);
+        builder
+            .add_glyph(0, &Charstring::from_path(&rect(0.0, 0.0, 50.0, 50.0)))
+            .unwrap();
+        let data = builder.build().unwrap().0;
+        let cff2 = ReadCff2::read(FontData::new(&data)).unwrap();
+        let top_entries: Vec<_> = dict::entries(cff2.top_dict_data(), None)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(top_entries
+            .iter()
+            .any(|entry| matches!(entry, Entry::FontMatrix(_))));
+        assert!(!top_entries
+            .iter()
+            .any(|entry| matches!(entry, Entry::FdSelectOffset(_))));
+        let font = CffFont::new_cff2(&data).unwrap();
+        assert!(font.fd_select.is_none());
+        assert_eq!(font.charstrings.count(), 1);
+    }
+
+    #[test]
+    fn invalid_glyphs() {
+        let light = Charstring::from_path(&rect(0.0, 0.0, 100.0, 100.0));
+        let bold = Charstring::from_path(&rect(0.0, 0.0, 200.0, 100.0));
+        let variable = Charstring::variable(&light, &[bold.delta(&light).unwrap()]).unwrap();
+
+        let mut builder = Cff2Builder::new(vec![PrivateDict::default()], None);
+        assert!(matches!(
+            builder.add_glyph(0, &variable),
+            Err(CffError::UnexpectedVariations)
+        ));
+        assert!(matches!(
+            builder.add_glyph(1, &light),
+            Err(CffError::InvalidFontDict(1))
+        ));
+        let mut builder = Cff2Builder::new(vec![PrivateDict::default()], Some(variation_store()));
+        assert!(matches!(
+            builder.add_glyph(0, &variable),
+            Err(CffError::RegionCountMismatch {
+                expected: 0,
+                found: 1
+            })
+        ));
+    }
+}
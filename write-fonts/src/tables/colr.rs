@@ -0,0 +1,418 @@
+//! The [COLR](https://learn.microsoft.com/en-us/typography/opentype/spec/colr) table
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use super::variations::{DeltaSetIndexMap, ItemVariationStore};
+
+include!("../../generated/generated_colr.rs");
+
+/// The most layers a single [`PaintColrLayers`] can reference.
+const MAX_PAINT_COLR_LAYERS: usize = u8::MAX as usize;
+
+/// The longest run of layers that is considered for reuse.
+const MAX_REUSE_LEN: usize = 32;
+
+impl Colr {
+    fn compute_version(&self) -> u16 {
+        let is_v1 = self.base_glyph_list.is_some()
+            || self.layer_list.is_some()
+            || self.clip_list.is_some()
+            || self.var_index_map.is_some()
+            || self.item_variation_store.is_some();
+        is_v1 as u16
+    }
+
+    fn check_sorted_and_in_bounds(&self, ctx: &mut ValidationCtx) {
+        let num_layers = self.layer_records.as_ref().map(Vec::len).unwrap_or(0);
+        if num_layers > u16::MAX as usize {
+            ctx.report("too many layer records");
+        }
+        if let Some(records) = self.base_glyph_records.as_ref() {
+            if records.len() > u16::MAX as usize {
+                ctx.report("too many base glyph records");
+            }
+            if records.windows(2).any(|w| w[0].glyph_id >= w[1].glyph_id) {
+                ctx.report("base glyph records must be sorted by glyph id without duplicates");
+            }
+            for record in records {
+                let end = record.first_layer_index as usize + record.num_layers as usize;
+                if end > num_layers {
+                    ctx.report(format!(
+                        "layers for base glyph {} are out of bounds",
+                        record.glyph_id
+                    ));
+                }
+            }
+        }
+        if let Some(list) = self.base_glyph_list.as_ref() {
+            let records = &list.base_glyph_paint_records;
+            if records.windows(2).any(|w| w[0].glyph_id >= w[1].glyph_id) {
+                ctx.report(
+                    "base glyph paint records must be sorted by glyph id without duplicates",
+                );
+            }
+        }
+        if let Some(list) = self.clip_list.as_ref() {
+            if list
+                .clips
+                .windows(2)
+                .any(|w| w[0].end_glyph_id >= w[1].start_glyph_id)
+            {
+                ctx.report("clips must be sorted by glyph id and must not overlap");
+            }
+        }
+    }
+}
+
+impl Clip {
+    fn validate_glyph_range(&self, ctx: &mut ValidationCtx) {
+        if self.start_glyph_id > self.end_glyph_id {
+            ctx.report(format!(
+                "start_glyph_id {} larger than end_glyph_id {}",
+                self.start_glyph_id, self.end_glyph_id
+            ));
+        }
+    }
+}
+
+/// A builder for a [`Colr`] table.
+///
+/// This handles sorting the glyph records, packing layers into the
+/// [`LayerList`] and grouping clip boxes into ranges. Layers added with
+/// [`add_layers`](Self::add_layers) are shared with any earlier layers that
+/// contain the same sequence of paints, so repeated groups of layers are only
+/// stored once.
+#[derive(Clone, Debug, Default)]
+pub struct ColrBuilder {
+    v0_glyphs: BTreeMap<GlyphId, Vec<Layer>>,
+    v1_glyphs: BTreeMap<GlyphId, Paint>,
+    clip_boxes: BTreeMap<GlyphId, ClipBox>,
+    layers: Vec<Paint>,
+    // the index in `layers` of each run of layers that can be reused
+    reuse_pool: HashMap<Vec<Paint>, u32>,
+    var_index_map: Option<DeltaSetIndexMap>,
+    item_variation_store: Option<ItemVariationStore>,
+}
+
+impl ColrBuilder {
+    /// Adds a version 0 color glyph, made of the given layers.
+    ///
+    /// Each layer is a glyph id and the index of its color in the `CPAL`
+    /// table, in drawing order. A glyph may have both a version 0 and a
+    /// version 1 definition, for the benefit of older implementations.
+    pub fn add_v0_glyph(
+        &mut self,
+        glyph: GlyphId,
+        layers: impl IntoIterator<Item = (GlyphId, u16)>,
+    ) {
+        let layers = layers
+            .into_iter()
+            .map(|(glyph_id, palette_index)| Layer::new(glyph_id, palette_index))
+            .collect();
+        self.v0_glyphs.insert(glyph, layers);
+    }
+
+    /// Adds a version 1 color glyph, drawn with the given paint.
+    pub fn add_v1_glyph(&mut self, glyph: GlyphId, paint: Paint) {
+        self.v1_glyphs.insert(glyph, paint);
+    }
+
+    /// Sets the clip box of a version 1 color glyph.
+    pub fn set_clip_box(&mut self, glyph: GlyphId, clip_box: ClipBox) {
+        self.clip_boxes.insert(glyph, clip_box);
+    }
+
+    /// Sets the variation data referenced by the variable paints.
+    ///
+    /// If `var_index_map` is `None`, each `varIndexBase` refers directly to
+    /// the delta set with that index in the store.
+    pub fn set_variations(
+        &mut self,
+        var_index_map: Option<DeltaSetIndexMap>,
+        item_variation_store: ItemVariationStore,
+    ) {
+        self.var_index_map = var_index_map;
+        self.item_variation_store = Some(item_variation_store);
+    }
+
+    /// Adds a list of layers to the layer list, returning a paint that draws
+    /// them in order.
+    ///
+    /// Runs of layers that were already added are referenced rather than
+    /// repeated, and lists with more layers than fit in one
+    /// [`PaintColrLayers`] are split into a tree. A single layer is returned
+    /// as is.
+    pub fn add_layers(&mut self, layers: Vec<Paint>) -> Paint {
+        let mut layers = self.reuse_layers(layers);
+        while layers.len() > MAX_PAINT_COLR_LAYERS {
+            layers = layers
+                .chunks(MAX_PAINT_COLR_LAYERS)
+                .map(|chunk| self.add_layers(chunk.to_vec()))
+                .collect();
+        }
+        if layers.len() == 1 {
+            return layers.pop().unwrap();
+        }
+        let first_layer_index = self.layers.len() as u32;
+        for range in reuse_ranges(layers.len()) {
+            let index = first_layer_index + range.start as u32;
+            self.reuse_pool
+                .entry(layers[range].to_vec())
+                .or_insert(index);
+        }
+        let paint = Paint::colr_layers(layers.len() as u8, first_layer_index);
+        self.layers.extend(layers);
+        paint
+    }
+
+    /// Replaces runs of layers that already exist in the layer list with a
+    /// [`PaintColrLayers`] that references them, preferring longer runs.
+    fn reuse_layers(&self, mut layers: Vec<Paint>) -> Vec<Paint> {
+        loop {
+            let mut ranges = reuse_ranges(layers.len()).collect::<Vec<_>>();
+            ranges.sort_by_key(|range| std::cmp::Reverse((range.len(), range.end)));
+            let Some((range, first_layer_index)) = ranges.into_iter().find_map(|range| {
+                let index = *self.reuse_pool.get(&layers[range.clone()])?;
+                Some((range, index))
+            }) else {
+                return layers;
+            };
+            let paint = Paint::colr_layers(range.len() as u8, first_layer_index);
+            layers.splice(range, [paint]);
+        }
+    }
+
+    /// Builds the table.
+    ///
+    /// The version is 1 if any version 1 data was added, and 0 otherwise.
+    pub fn build(self) -> Colr {
+        let mut base_glyph_records = Vec::with_capacity(self.v0_glyphs.len());
+        let mut layer_records = Vec::new();
+        for (glyph, layers) in self.v0_glyphs {
+            base_glyph_records.push(BaseGlyph::new(
+                glyph,
+                layer_records.len() as u16,
+                layers.len() as u16,
+            ));
+            layer_records.extend(layers);
+        }
+
+        // glyphs with consecutive ids and the same box share a clip record
+        let mut clips: Vec<Clip> = Vec::new();
+        for (glyph, clip_box) in self.clip_boxes {
+            match clips.last_mut() {
+                Some(clip)
+                    if clip.end_glyph_id.to_u16().checked_add(1) == Some(glyph.to_u16())
+                        && *clip.clip_box == clip_box =>
+                {
+                    clip.end_glyph_id = glyph;
+                }
+                _ => clips.push(Clip::new(glyph, glyph, clip_box)),
+            }
+        }
+
+        let base_glyph_list = (!self.v1_glyphs.is_empty()).then(|| {
+            BaseGlyphList::new(
+                self.v1_glyphs
+                    .into_iter()
+                    .map(|(glyph, paint)| BaseGlyphPaint::new(glyph, paint))
+                    .collect(),
+            )
+        });
+        Colr {
+            base_glyph_records: (!base_glyph_records.is_empty())
+                .then_some(base_glyph_records)
+                .into(),
+            layer_records: (!layer_records.is_empty()).then_some(layer_records).into(),
+            base_glyph_list: base_glyph_list.into(),
+            layer_list: (!self.layers.is_empty())
+                .then(|| LayerList::new(self.layers))
+                .into(),
+            clip_list: (!clips.is_empty()).then(|| ClipList::new(clips)).into(),
+            var_index_map: self.var_index_map.into(),
+            item_variation_store: self.item_variation_store.into(),
+        }
+    }
+}
+
+/// The ranges of a list of layers that are considered for reuse.
+fn reuse_ranges(num_layers: usize) -> impl Iterator<Item = Range<usize>> {
+    (0..num_layers).flat_map(move |start| {
+        let max_end = num_layers.min(start + MAX_REUSE_LEN);
+        (start + 2..=max_end).map(move |end| start..end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use read_fonts::{tables::colr as read_colr, FontRead};
+
+    use super::*;
+
+    fn solid(palette_index: u16) -> Paint {
+        Paint::solid(palette_index, F2Dot14::from_f32(1.0))
+    }
+
+    fn glyph(glyph_id: u16, palette_index: u16) -> Paint {
+        Paint::glyph(solid(palette_index), GlyphId::new(glyph_id))
+    }
+
+    fn clip_box(size: i16) -> ClipBox {
+        ClipBox::format_1(0.into(), 0.into(), size.into(), size.into())
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut builder = ColrBuilder::default();
+        builder.add_v0_glyph(
+            GlyphId::new(5),
+            [(GlyphId::new(1), 0), (GlyphId::new(2), 1)],
+        );
+        builder.add_v0_glyph(GlyphId::new(3), [(GlyphId::new(4), 2)]);
+        let layers = builder.add_layers(vec![glyph(1, 0), glyph(2, 1)]);
+        builder.add_v1_glyph(GlyphId::new(5), layers);
+        let gradient = Paint::linear_gradient(
+            ColorLine::new(
+                Extend::Reflect,
+                vec![
+                    ColorStop::new(F2Dot14::from_f32(0.0), 0, F2Dot14::from_f32(1.0)),
+                    ColorStop::new(F2Dot14::from_f32(1.0), 1, F2Dot14::from_f32(0.5)),
+                ],
+            ),
+            0.into(),
+            0.into(),
+            100.into(),
+            0.into(),
+            0.into(),
+            100.into(),
+        );
+        builder.add_v1_glyph(GlyphId::new(3), Paint::glyph(gradient, GlyphId::new(4)));
+        builder.set_clip_box(GlyphId::new(3), clip_box(100));
+        builder.set_clip_box(GlyphId::new(5), clip_box(100));
+        let colr = builder.build();
+        assert_eq!(colr.compute_version(), 1);
+
+        let data = crate::dump_table(&colr).unwrap();
+        let colr = read_colr::Colr::read(data.as_slice().into()).unwrap();
+        assert_eq!(colr.version(), 1);
+        assert_eq!(colr.v0_base_glyph(GlyphId::new(3)).unwrap(), Some(0..1));
+        assert_eq!(colr.v0_base_glyph(GlyphId::new(5)).unwrap(), Some(1..3));
+        assert_eq!(colr.v0_layer(2).unwrap(), (GlyphId::new(2), 1));
+
+        let (paint, _) = colr.v1_base_glyph(GlyphId::new(5)).unwrap().unwrap();
+        let read_colr::Paint::ColrLayers(layers) = paint else {
+            panic!("expected PaintColrLayers, found {paint:?}");
+        };
+        assert_eq!(layers.num_layers(), 2);
+        let (paint, _) = colr.v1_base_glyph(GlyphId::new(3)).unwrap().unwrap();
+        let read_colr::Paint::Glyph(paint) = paint else {
+            panic!("expected PaintGlyph, found {paint:?}");
+        };
+        let read_colr::Paint::LinearGradient(gradient) = paint.paint().unwrap() else {
+            panic!("expected PaintLinearGradient");
+        };
+        let color_line = gradient.color_line().unwrap();
+        assert_eq!(color_line.extend(), Extend::Reflect);
+        assert_eq!(color_line.color_stops().len(), 2);
+
+        // the boxes are the same, but the glyphs aren't consecutive
+        let clips = colr.clip_list().unwrap().unwrap();
+        assert_eq!(clips.clips().len(), 2);
+        let Some(read_colr::ClipBox::Format1(clip_box)) =
+            colr.v1_clip_box(GlyphId::new(5)).unwrap()
+        else {
+            panic!("expected ClipBoxFormat1");
+        };
+        assert_eq!(clip_box.x_max().to_i16(), 100);
+    }
+
+    #[test]
+    fn v0_only() {
+        let mut builder = ColrBuilder::default();
+        builder.add_v0_glyph(GlyphId::new(1), [(GlyphId::new(2), 0)]);
+        let colr = builder.build();
+        let data = crate::dump_table(&colr).unwrap();
+        // v0 header plus one base glyph record and one layer record
+        assert_eq!(data.len(), 14 + 6 + 4);
+        let colr = read_colr::Colr::read(data.as_slice().into()).unwrap();
+        assert_eq!(colr.version(), 0);
+        assert_eq!(colr.v0_layer(0).unwrap(), (GlyphId::new(2), 0));
+    }
+
+    #[test]
+    fn clip_ranges() {
+        let mut builder = ColrBuilder::default();
+        for gid in [1, 2, 3, 5] {
+            builder.set_clip_box(GlyphId::new(gid), clip_box(10));
+        }
+        builder.set_clip_box(GlyphId::new(4), clip_box(20));
+        let colr = builder.build();
+        let ranges = colr
+            .clip_list
+            .as_ref()
+            .unwrap()
+            .clips
+            .iter()
+            .map(|clip| (clip.start_glyph_id.to_u16(), clip.end_glyph_id.to_u16()))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(1, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn reuse_layers() {
+        let mut builder = ColrBuilder::default();
+        let first = builder.add_layers(vec![glyph(1, 0), glyph(2, 1), glyph(3, 2)]);
+        assert_eq!(first, Paint::colr_layers(3, 0));
+        // an identical list is shared entirely
+        let again = builder.add_layers(vec![glyph(1, 0), glyph(2, 1), glyph(3, 2)]);
+        assert_eq!(again, first);
+        assert_eq!(builder.layers.len(), 3);
+        // a list that contains a run of existing layers references that run
+        let overlap = builder.add_layers(vec![glyph(2, 1), glyph(3, 2), glyph(4, 3)]);
+        assert_eq!(overlap, Paint::colr_layers(2, 3));
+        assert_eq!(
+            &builder.layers[3..],
+            [Paint::colr_layers(2, 1), glyph(4, 3)]
+        );
+        // a single layer is returned as is
+        assert_eq!(builder.add_layers(vec![glyph(9, 0)]), glyph(9, 0));
+    }
+
+    #[test]
+    fn too_many_layers() {
+        let mut builder = ColrBuilder::default();
+        let layers = (0..600).map(|gid| glyph(gid, 0)).collect();
+        let paint = builder.add_layers(layers);
+        // 600 layers are split into groups of 255, 255 and 90
+        assert_eq!(paint, Paint::colr_layers(3, 600));
+        assert_eq!(
+            &builder.layers[600..],
+            [
+                Paint::colr_layers(255, 0),
+                Paint::colr_layers(255, 255),
+                Paint::colr_layers(90, 510),
+            ]
+        );
+        builder.add_v1_glyph(GlyphId::new(1), paint);
+        let data = crate::dump_table(&builder.build()).unwrap();
+        let colr = read_colr::Colr::read(data.as_slice().into()).unwrap();
+        assert_eq!(colr.layer_list().unwrap().unwrap().num_layers(), 603);
+    }
+
+    #[test]
+    fn validate_sorting() {
+        let colr = Colr::new(
+            Some(vec![
+                BaseGlyph::new(GlyphId::new(2), 0, 1),
+                BaseGlyph::new(GlyphId::new(1), 1, 1),
+            ]),
+            Some(vec![Layer::new(GlyphId::new(3), 0)]),
+        );
+        let err = crate::dump_table(&colr).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("must be sorted"), "{msg}");
+        assert!(msg.contains("out of bounds"), "{msg}");
+    }
+}
@@ -0,0 +1,3 @@
+//! The [CPAL](https://learn.microsoft.com/en-us/typography/opentype/spec/cpal) table
+
+include!("../../generated/generated_cpal.rs");
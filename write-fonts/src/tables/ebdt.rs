@@ -0,0 +1,11 @@
+//! The [EBDT (Embedded Bitmap Data)](https://learn.microsoft.com/en-us/typography/opentype/spec/ebdt) table
+
+super::bitmap::bitmap_table!(
+    /// The [EBDT (Embedded Bitmap Data)](https://learn.microsoft.com/en-us/typography/opentype/spec/ebdt) table
+    ///
+    /// This table contains the bitmaps of the font, which are located by the
+    /// ['EBLC' table][super::eblc]. It is built together with that table by
+    /// [`build_ebdt`][super::bitmap::build_ebdt].
+    Ebdt,
+    b"EBDT"
+);
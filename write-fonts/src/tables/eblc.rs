@@ -0,0 +1,11 @@
+//! The [EBLC (Embedded Bitmap Location)](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc) table
+
+super::bitmap::bitmap_table!(
+    /// The [EBLC (Embedded Bitmap Location)](https://learn.microsoft.com/en-us/typography/opentype/spec/eblc) table
+    ///
+    /// This table contains the locations of the bitmaps in the
+    /// ['EBDT' table][super::ebdt]. It is built together with that table by
+    /// [`build_ebdt`][super::bitmap::build_ebdt].
+    Eblc,
+    b"EBLC"
+);
@@ -11,6 +11,8 @@ use super::{
 
 include!("../../generated/generated_gdef.rs");
 
+pub mod builders;
+
 impl Gdef {
     fn compute_version(&self) -> MajorMinor {
         if self.item_var_store.is_some() {
@@ -0,0 +1,358 @@
+//! Building the GDEF table.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    gpos::{builders::Metric, ExtensionSubtable, PositionLookup},
+    layout::{
+        builders::{Builder, RemapVariationIndices},
+        ClassDefBuilder, CoverageTable, CoverageTableBuilder,
+    },
+    variations::ivs_builder::{VariationIndexRemapping, VariationStoreBuilder},
+};
+
+use super::{CaretValue, Gdef, GlyphClassDef, LigCaretList, LigGlyph, MarkGlyphSets};
+
+/// A caret position in a ligature glyph.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CaretValueBuilder {
+    /// A coordinate in design units, which may be variable.
+    Coordinate(Metric),
+    /// The index of a contour point in the glyph.
+    PointIndex(u16),
+}
+
+/// A builder for the [`Gdef`] table.
+///
+/// Glyph classes may be set explicitly, or inferred from the mark attachment
+/// lookups in GPOS; explicit classes are never replaced by inferred ones.
+///
+/// Variable ligature carets are added to the item variation store that is
+/// built with [`build_with_var_store`][Self::build_with_var_store]; this store
+/// is also used by GPOS, and should contain its variable values as well.
+#[derive(Clone, Debug, Default)]
+pub struct GdefBuilder {
+    glyph_classes: BTreeMap<GlyphId, GlyphClassDef>,
+    inferred_classes: BTreeMap<GlyphId, GlyphClassDef>,
+    ligature_carets: BTreeMap<GlyphId, Vec<CaretValueBuilder>>,
+    mark_glyph_sets: Vec<BTreeSet<GlyphId>>,
+}
+
+impl GdefBuilder {
+    /// Set the class of a glyph, replacing any existing class.
+    pub fn set_glyph_class(&mut self, glyph: GlyphId, class: GlyphClassDef) {
+        self.glyph_classes.insert(glyph, class);
+    }
+
+    /// Set the caret positions of a ligature glyph, in order.
+    pub fn set_ligature_carets(&mut self, glyph: GlyphId, carets: Vec<CaretValueBuilder>) {
+        self.ligature_carets.insert(glyph, carets);
+    }
+
+    /// Add a mark glyph set, returning its index.
+    ///
+    /// If an identical set has already been added, its index is returned.
+    pub fn add_mark_glyph_set(&mut self, glyphs: BTreeSet<GlyphId>) -> u16 {
+        let index = match self.mark_glyph_sets.iter().position(|set| *set == glyphs) {
+            Some(index) => index,
+            None => {
+                self.mark_glyph_sets.push(glyphs);
+                self.mark_glyph_sets.len() - 1
+            }
+        };
+        index as u16
+    }
+
+    /// Infer glyph classes from the mark attachment lookups in GPOS.
+    ///
+    /// Glyphs that are attached as marks are always marks; otherwise glyphs
+    /// are bases or ligatures, according to the last lookup they are used in.
+    pub fn infer_glyph_classes(&mut self, lookups: &[PositionLookup]) {
+        let mut marks = BTreeSet::new();
+        let mut set_class = |coverage: &CoverageTable, class: GlyphClassDef| {
+            for glyph in coverage.iter() {
+                self.inferred_classes.insert(glyph, class);
+            }
+        };
+        for lookup in lookups {
+            match lookup {
+                PositionLookup::MarkToBase(lookup) => {
+                    for subtable in &lookup.subtables {
+                        set_class(&subtable.base_coverage, GlyphClassDef::Base);
+                        marks.extend(subtable.mark_coverage.iter());
+                    }
+                }
+                PositionLookup::MarkToLig(lookup) => {
+                    for subtable in &lookup.subtables {
+                        set_class(&subtable.ligature_coverage, GlyphClassDef::Ligature);
+                        marks.extend(subtable.mark_coverage.iter());
+                    }
+                }
+                PositionLookup::MarkToMark(lookup) => {
+                    for subtable in &lookup.subtables {
+                        marks.extend(subtable.mark1_coverage.iter());
+                        marks.extend(subtable.mark2_coverage.iter());
+                    }
+                }
+                PositionLookup::Extension(lookup) => {
+                    for subtable in &lookup.subtables {
+                        match &**subtable {
+                            ExtensionSubtable::MarkToBase(ext) => {
+                                set_class(&ext.extension.base_coverage, GlyphClassDef::Base);
+                                marks.extend(ext.extension.mark_coverage.iter());
+                            }
+                            ExtensionSubtable::MarkToLig(ext) => {
+                                set_class(
+                                    &ext.extension.ligature_coverage,
+                                    GlyphClassDef::Ligature,
+                                );
+                                marks.extend(ext.extension.mark_coverage.iter());
+                            }
+                            ExtensionSubtable::MarkToMark(ext) => {
+                                marks.extend(ext.extension.mark1_coverage.iter());
+                                marks.extend(ext.extension.mark2_coverage.iter());
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        for glyph in marks {
+            self.inferred_classes.insert(glyph, GlyphClassDef::Mark);
+        }
+    }
+
+    /// Returns `true` if there is nothing to put in a GDEF table.
+    pub fn is_empty(&self) -> bool {
+        self.glyph_classes.is_empty()
+            && self.inferred_classes.is_empty()
+            && self.ligature_carets.is_empty()
+            && self.mark_glyph_sets.is_empty()
+    }
+
+    /// Build the table, and the item variation store.
+    ///
+    /// `var_store` may already contain the deltas of other tables, such as
+    /// GPOS; the returned remapping must be applied to those tables. The
+    /// variation store is only included if it contains any deltas.
+    pub fn build_with_var_store(
+        self,
+        mut var_store: VariationStoreBuilder,
+    ) -> (Gdef, VariationIndexRemapping) {
+        let mut gdef = self.build(&mut var_store);
+        let has_deltas = !var_store.is_empty();
+        let (store, key_map) = var_store.build();
+        gdef.remap_variation_indices(&key_map);
+        if has_deltas {
+            gdef.item_var_store = store.into();
+        }
+        (gdef, key_map)
+    }
+}
+
+impl Builder for GdefBuilder {
+    type Output = Gdef;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Gdef {
+        let mut classes = self.inferred_classes;
+        classes.extend(self.glyph_classes);
+        let glyph_class_def = (!classes.is_empty()).then(|| {
+            classes
+                .into_iter()
+                .map(|(glyph, class)| (glyph, class as u16))
+                .collect::<ClassDefBuilder>()
+                .build()
+        });
+
+        let lig_caret_list = (!self.ligature_carets.is_empty()).then(|| {
+            let coverage =
+                CoverageTableBuilder::from_glyphs(self.ligature_carets.keys().copied().collect());
+            let lig_glyphs = self
+                .ligature_carets
+                .into_values()
+                .map(|carets| {
+                    LigGlyph::new(
+                        carets
+                            .into_iter()
+                            .map(|caret| caret.build(var_store))
+                            .collect(),
+                    )
+                })
+                .collect();
+            LigCaretList::new(coverage.build(), lig_glyphs)
+        });
+
+        let mark_glyph_sets_def = (!self.mark_glyph_sets.is_empty()).then(|| {
+            MarkGlyphSets::new(
+                self.mark_glyph_sets
+                    .into_iter()
+                    .map(|set| CoverageTableBuilder::from_glyphs(set.into_iter().collect()).build())
+                    .collect(),
+            )
+        });
+
+        Gdef {
+            glyph_class_def: glyph_class_def.into(),
+            lig_caret_list: lig_caret_list.into(),
+            mark_glyph_sets_def: mark_glyph_sets_def.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Builder for CaretValueBuilder {
+    type Output = CaretValue;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> CaretValue {
+        match self {
+            CaretValueBuilder::Coordinate(metric) => match metric.build_device(var_store) {
+                Some(device) => CaretValue::format_3(metric.default, device),
+                None => CaretValue::format_1(metric.default),
+            },
+            CaretValueBuilder::PointIndex(index) => CaretValue::format_2(index),
+        }
+    }
+}
+
+impl RemapVariationIndices for Gdef {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.lig_caret_list.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for LigCaretList {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.lig_glyphs.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for LigGlyph {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.caret_values.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for CaretValue {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        if let CaretValue::Format3(table) = self {
+            table.device.remap_variation_indices(key_map)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::{F2Dot14, MajorMinor};
+    use read_fonts::{FontData, FontRead};
+
+    use crate::tables::{
+        gpos::builders::{AnchorBuilder, MarkBasePosBuilder, MarkMarkPosBuilder},
+        layout::{DeviceOrVariationIndex, Lookup, LookupFlag},
+        variations::{RegionAxisCoordinates, VariationRegion},
+    };
+
+    use super::*;
+
+    fn gid(id: u16) -> GlyphId {
+        GlyphId::new(id)
+    }
+
+    fn classes(gdef: &Gdef) -> Vec<(GlyphId, u16)> {
+        let class_def = gdef.glyph_class_def.as_ref().unwrap();
+        class_def.iter().filter(|(_, class)| *class != 0).collect()
+    }
+
+    #[test]
+    fn infer_classes() {
+        let mut var_store = VariationStoreBuilder::new();
+        let mut mark_base = MarkBasePosBuilder::default();
+        mark_base
+            .insert_mark(gid(10), "top", AnchorBuilder::new(0, 500))
+            .unwrap();
+        mark_base.insert_base(gid(1), "top", AnchorBuilder::new(200, 700));
+        mark_base.insert_base(gid(2), "top", AnchorBuilder::new(200, 700));
+        let mut mark_mark = MarkMarkPosBuilder::default();
+        mark_mark
+            .insert_mark1(gid(11), "top", AnchorBuilder::new(0, 500))
+            .unwrap();
+        // a mark used as a base is still a mark
+        mark_mark.insert_mark2(gid(2), "top", AnchorBuilder::new(0, 800));
+        let lookups = vec![
+            PositionLookup::MarkToBase(Lookup::new(
+                LookupFlag::empty(),
+                mark_base.build(&mut var_store),
+                0,
+            )),
+            PositionLookup::MarkToMark(Lookup::new(
+                LookupFlag::empty(),
+                mark_mark.build(&mut var_store),
+                0,
+            )),
+        ];
+
+        let mut builder = GdefBuilder::default();
+        builder.set_glyph_class(gid(1), GlyphClassDef::Component);
+        builder.infer_glyph_classes(&lookups);
+        let gdef = builder.build(&mut var_store);
+        assert_eq!(
+            classes(&gdef),
+            [(gid(1), 4), (gid(2), 3), (gid(10), 3), (gid(11), 3)]
+        );
+    }
+
+    #[test]
+    fn carets_and_mark_sets() {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates {
+            start_coord: F2Dot14::from_f32(0.0),
+            peak_coord: F2Dot14::from_f32(1.0),
+            end_coord: F2Dot14::from_f32(1.0),
+        }]);
+        let mut builder = GdefBuilder::default();
+        builder.set_ligature_carets(
+            gid(5),
+            vec![
+                CaretValueBuilder::Coordinate(300.into()),
+                CaretValueBuilder::Coordinate(Metric::new(600, vec![(region, 50)])),
+                CaretValueBuilder::PointIndex(12),
+            ],
+        );
+        let set = [gid(10), gid(11)].into_iter().collect::<BTreeSet<_>>();
+        assert_eq!(builder.add_mark_glyph_set(set.clone()), 0);
+        assert_eq!(builder.add_mark_glyph_set([gid(12)].into()), 1);
+        assert_eq!(builder.add_mark_glyph_set(set), 0);
+
+        let (gdef, _) = builder.build_with_var_store(VariationStoreBuilder::new());
+        assert!(gdef.glyph_class_def.is_none());
+        let carets = &gdef.lig_caret_list.as_ref().unwrap().lig_glyphs[0].caret_values;
+        assert_eq!(*carets[0], CaretValue::format_1(300));
+        let CaretValue::Format3(caret) = &*carets[1] else {
+            panic!("expected format 3, got {:?}", carets[1]);
+        };
+        assert!(matches!(
+            *caret.device,
+            DeviceOrVariationIndex::VariationIndex(_)
+        ));
+        assert_eq!(*carets[2], CaretValue::format_2(12));
+        assert_eq!(
+            gdef.mark_glyph_sets_def.as_ref().unwrap().coverages.len(),
+            2
+        );
+
+        let bytes = crate::dump_table(&gdef).unwrap();
+        let gdef = read_fonts::tables::gdef::Gdef::read(FontData::new(&bytes)).unwrap();
+        assert_eq!(gdef.version(), MajorMinor::VERSION_1_3);
+        assert!(gdef.item_var_store().is_some());
+    }
+
+    #[test]
+    fn no_var_store_without_deltas() {
+        let mut builder = GdefBuilder::default();
+        builder.set_ligature_carets(gid(5), vec![CaretValueBuilder::Coordinate(300.into())]);
+        let (gdef, _) = builder.build_with_var_store(VariationStoreBuilder::new());
+        assert!(gdef.item_var_store.is_none());
+    }
+}
@@ -190,6 +190,13 @@ impl CompositeGlyph {
     pub fn components(&self) -> &[Component] {
         &self.components
     }
+
+    /// The components of this glyph, for modifying them in place.
+    ///
+    /// The caller is responsible for updating the `bbox` afterwards.
+    pub fn components_mut(&mut self) -> &mut [Component] {
+        &mut self.components
+    }
 }
 
 impl FontWrite for CompositeGlyph {
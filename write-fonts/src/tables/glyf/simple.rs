@@ -138,6 +138,13 @@ impl SimpleGlyph {
     pub fn contours(&self) -> &[Contour] {
         &self.contours
     }
+
+    /// The contours of this glyph, for modifying its points in place.
+    ///
+    /// The caller is responsible for updating the `bbox` afterwards.
+    pub fn contours_mut(&mut self) -> &mut [Contour] {
+        &mut self.contours
+    }
 }
 
 impl Contour {
@@ -154,6 +161,10 @@ impl Contour {
     pub fn iter(&self) -> impl Iterator<Item = &CurvePoint> {
         self.0.iter()
     }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut CurvePoint> {
+        self.0.iter_mut()
+    }
 }
 
 impl MalformedPath {
@@ -12,6 +12,8 @@ use super::layout::{
     FeatureVariations, Lookup, LookupList, LookupSubtable, LookupType, ScriptList, SequenceContext,
 };
 
+pub mod builders;
+
 #[cfg(test)]
 #[path = "../tests/test_gpos.rs"]
 mod spec_tests;
@@ -0,0 +1,186 @@
+//! Builders for GPOS subtables.
+//!
+//! These accept glyph-level rules, and handle choosing subtable formats,
+//! building coverage and class tables, and collecting variable values into an
+//! [`ItemVariationStore`][crate::tables::variations::ItemVariationStore].
+
+mod anchor;
+mod cursive;
+mod mark;
+mod pair;
+mod single;
+mod value_record;
+
+pub use anchor::AnchorBuilder;
+pub use cursive::CursivePosBuilder;
+pub use mark::{
+    MarkBasePosBuilder, MarkLigPosBuilder, MarkMarkPosBuilder, PreviouslyAssignedClass,
+};
+pub use pair::PairPosBuilder;
+pub use single::SinglePosBuilder;
+pub use value_record::{Metric, ValueRecordBuilder};
+
+use crate::tables::{
+    layout::builders::RemapVariationIndices, variations::ivs_builder::VariationIndexRemapping,
+};
+
+use super::{
+    AnchorTable, BaseArray, BaseRecord, Class1Record, Class2Record, ComponentRecord,
+    CursivePosFormat1, EntryExitRecord, LigatureArray, LigatureAttach, Mark2Array, Mark2Record,
+    MarkArray, MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1, MarkRecord, PairPos,
+    PairSet, PairValueRecord, SinglePos, ValueRecord,
+};
+
+impl RemapVariationIndices for ValueRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        for device in [
+            &mut self.x_placement_device,
+            &mut self.y_placement_device,
+            &mut self.x_advance_device,
+            &mut self.y_advance_device,
+        ] {
+            device.remap_variation_indices(key_map);
+        }
+    }
+}
+
+impl RemapVariationIndices for PairPos {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        match self {
+            PairPos::Format1(table) => table.pair_sets.remap_variation_indices(key_map),
+            PairPos::Format2(table) => table.class1_records.remap_variation_indices(key_map),
+        }
+    }
+}
+
+impl RemapVariationIndices for PairSet {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.pair_value_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for PairValueRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.value_record1.remap_variation_indices(key_map);
+        self.value_record2.remap_variation_indices(key_map);
+    }
+}
+
+impl RemapVariationIndices for Class1Record {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.class2_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for Class2Record {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.value_record1.remap_variation_indices(key_map);
+        self.value_record2.remap_variation_indices(key_map);
+    }
+}
+
+impl RemapVariationIndices for AnchorTable {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        if let AnchorTable::Format3(table) = self {
+            table.x_device.remap_variation_indices(key_map);
+            table.y_device.remap_variation_indices(key_map);
+        }
+    }
+}
+
+impl RemapVariationIndices for MarkArray {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for MarkRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark_anchor.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for MarkBasePosFormat1 {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark_array.remap_variation_indices(key_map);
+        self.base_array.remap_variation_indices(key_map);
+    }
+}
+
+impl RemapVariationIndices for BaseArray {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.base_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for BaseRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.base_anchors.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for MarkLigPosFormat1 {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark_array.remap_variation_indices(key_map);
+        self.ligature_array.remap_variation_indices(key_map);
+    }
+}
+
+impl RemapVariationIndices for LigatureArray {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.ligature_attaches.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for LigatureAttach {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.component_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for ComponentRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.ligature_anchors.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for MarkMarkPosFormat1 {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark1_array.remap_variation_indices(key_map);
+        self.mark2_array.remap_variation_indices(key_map);
+    }
+}
+
+impl RemapVariationIndices for Mark2Array {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark2_records.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for Mark2Record {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.mark2_anchors.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for SinglePos {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        match self {
+            SinglePos::Format1(table) => table.value_record.remap_variation_indices(key_map),
+            SinglePos::Format2(table) => table.value_records.remap_variation_indices(key_map),
+        }
+    }
+}
+
+impl RemapVariationIndices for CursivePosFormat1 {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.entry_exit_record.remap_variation_indices(key_map)
+    }
+}
+
+impl RemapVariationIndices for EntryExitRecord {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.entry_anchor.remap_variation_indices(key_map);
+        self.exit_anchor.remap_variation_indices(key_map);
+    }
+}
@@ -0,0 +1,90 @@
+//! Building anchor tables with variable coordinates
+
+use std::collections::HashMap;
+
+use crate::tables::{
+    gpos::AnchorTable, layout::builders::Builder, variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::Metric;
+
+/// A builder for an [`AnchorTable`], where each coordinate may be variable.
+///
+/// Anchors with variable coordinates are written in format 3; otherwise
+/// format 2 is used if there is a contour point, and format 1 if not.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AnchorBuilder {
+    pub x: Metric,
+    pub y: Metric,
+    /// The index of a glyph contour point that the anchor should track.
+    pub contour_point: Option<u16>,
+}
+
+/// Anchors that have already been built.
+///
+/// Identical anchors are written as identical tables, which are shared when
+/// the table is serialized; sharing them here means their deltas are only
+/// added to the variation store once.
+#[derive(Debug, Default)]
+pub(crate) struct AnchorCache {
+    built: HashMap<AnchorBuilder, AnchorTable>,
+}
+
+impl AnchorBuilder {
+    pub fn new(x: impl Into<Metric>, y: impl Into<Metric>) -> Self {
+        Self {
+            x: x.into(),
+            y: y.into(),
+            contour_point: None,
+        }
+    }
+
+    pub fn with_contour_point(mut self, point: u16) -> Self {
+        self.contour_point = Some(point);
+        self
+    }
+
+    /// The size of the built anchor, including any device tables.
+    pub(crate) fn encoded_len(&self) -> usize {
+        const DEVICE_LEN: usize = 6;
+        match (self.x.is_variable(), self.y.is_variable()) {
+            (false, false) if self.contour_point.is_some() => 8,
+            (false, false) => 6,
+            (x, y) => 10 + (x as usize + y as usize) * DEVICE_LEN,
+        }
+    }
+}
+
+impl AnchorCache {
+    pub(crate) fn build(
+        &mut self,
+        anchor: &AnchorBuilder,
+        var_store: &mut VariationStoreBuilder,
+    ) -> AnchorTable {
+        if let Some(table) = self.built.get(anchor) {
+            return table.clone();
+        }
+        let table = anchor.clone().build(var_store);
+        self.built.insert(anchor.clone(), table.clone());
+        table
+    }
+}
+
+impl Builder for AnchorBuilder {
+    type Output = AnchorTable;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> AnchorTable {
+        if self.x.is_variable() || self.y.is_variable() {
+            return AnchorTable::format_3(
+                self.x.default,
+                self.y.default,
+                self.x.build_device(var_store),
+                self.y.build_device(var_store),
+            );
+        }
+        match self.contour_point {
+            Some(point) => AnchorTable::format_2(self.x.default, self.y.default, point),
+            None => AnchorTable::format_1(self.x.default, self.y.default),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Building cursive attachment subtables
+
+use std::collections::BTreeMap;
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    gpos::{CursivePosFormat1, EntryExitRecord},
+    layout::{
+        builders::{split_by_size, Builder},
+        CoverageTableBuilder,
+    },
+    variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::anchor::{AnchorBuilder, AnchorCache};
+
+/// The size of a subtable, excluding its coverage and records.
+const CURSIVE_POS_HEADER_LEN: usize = 6;
+
+type EntryExit = (Option<AnchorBuilder>, Option<AnchorBuilder>);
+
+/// A builder for [`CursivePosFormat1`] subtables.
+///
+/// Glyphs are split into several subtables if their anchors would not fit in
+/// one.
+#[derive(Clone, Debug, Default)]
+pub struct CursivePosBuilder {
+    items: BTreeMap<GlyphId, EntryExit>,
+}
+
+impl CursivePosBuilder {
+    /// Add the entry and exit anchors for a glyph, replacing any existing
+    /// anchors.
+    pub fn insert(
+        &mut self,
+        glyph: GlyphId,
+        entry: Option<AnchorBuilder>,
+        exit: Option<AnchorBuilder>,
+    ) {
+        self.items.insert(glyph, (entry, exit));
+    }
+
+    /// Returns `true` if anchors exist for this glyph.
+    pub fn contains(&self, glyph: GlyphId) -> bool {
+        self.items.contains_key(&glyph)
+    }
+
+    /// Returns `true` if no glyphs have entry or exit anchors.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Builder for CursivePosBuilder {
+    type Output = Vec<CursivePosFormat1>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        // anchors are only shared within a subtable, so this overestimates
+        let item_len = |(_, (entry, exit)): &(GlyphId, EntryExit)| {
+            6 + [entry, exit]
+                .into_iter()
+                .flatten()
+                .map(AnchorBuilder::encoded_len)
+                .sum::<usize>()
+        };
+        let mut cache = AnchorCache::default();
+        split_by_size(self.items, CURSIVE_POS_HEADER_LEN, item_len)
+            .into_iter()
+            .map(|items| {
+                let (glyphs, records): (Vec<_>, Vec<_>) = items
+                    .into_iter()
+                    .map(|(glyph, (entry, exit))| {
+                        let entry = entry.map(|anchor| cache.build(&anchor, var_store));
+                        let exit = exit.map(|anchor| cache.build(&anchor, var_store));
+                        (glyph, EntryExitRecord::new(entry, exit))
+                    })
+                    .unzip();
+                let coverage = CoverageTableBuilder::from_glyphs(glyphs).build();
+                CursivePosFormat1::new(coverage, records)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tables::gpos::AnchorTable;
+
+    use super::*;
+
+    #[test]
+    fn entry_and_exit_anchors() {
+        let mut builder = CursivePosBuilder::default();
+        builder.insert(
+            GlyphId::new(5),
+            Some(AnchorBuilder::new(0, 100)),
+            Some(AnchorBuilder::new(500, 100)),
+        );
+        builder.insert(GlyphId::new(3), None, Some(AnchorBuilder::new(400, 100)));
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert_eq!(subtables.len(), 1);
+        let table = &subtables[0];
+        assert_eq!(
+            table.coverage.iter().collect::<Vec<_>>(),
+            [GlyphId::new(3), GlyphId::new(5)]
+        );
+        assert!(table.entry_exit_record[0].entry_anchor.is_none());
+        assert_eq!(
+            table.entry_exit_record[1].exit_anchor.as_ref(),
+            Some(&AnchorTable::format_1(500, 100))
+        );
+    }
+
+    #[test]
+    fn large_subtables_are_split() {
+        let mut builder = CursivePosBuilder::default();
+        for gid in 0..5000u16 {
+            let anchor = |x: u16| Some(AnchorBuilder::new(x as i16, gid as i16));
+            builder.insert(GlyphId::new(gid), anchor(0), anchor(gid));
+        }
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert!(subtables.len() > 1);
+        for subtable in &subtables {
+            assert!(crate::dump_table(subtable).is_ok());
+        }
+    }
+}
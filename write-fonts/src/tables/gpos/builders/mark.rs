@@ -0,0 +1,555 @@
+//! Building mark attachment subtables
+
+use std::collections::BTreeMap;
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    gpos::{
+        AnchorTable, BaseArray, BaseRecord, ComponentRecord, LigatureArray, LigatureAttach,
+        Mark2Array, Mark2Record, MarkArray, MarkBasePosFormat1, MarkLigPosFormat1,
+        MarkMarkPosFormat1, MarkRecord,
+    },
+    layout::{builders::Builder, CoverageTable, CoverageTableBuilder},
+    variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::anchor::{AnchorBuilder, AnchorCache};
+
+/// An error indicating that a mark glyph was added to two different classes
+/// in the same lookup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviouslyAssignedClass {
+    pub glyph_id: GlyphId,
+    /// The class the glyph was previously added to.
+    pub class: String,
+}
+
+/// The anchors of the glyphs that marks attach to, for each mark class.
+type AttachAnchors = BTreeMap<String, AnchorBuilder>;
+
+/// Mark glyphs, grouped into classes.
+///
+/// Classes are numbered in the order they are first used.
+#[derive(Clone, Debug, Default)]
+struct MarkList {
+    classes: Vec<String>,
+    glyphs: BTreeMap<GlyphId, (u16, AnchorBuilder)>,
+}
+
+/// A builder for [`MarkBasePosFormat1`] subtables.
+///
+/// Marks are added to named classes, and each base glyph has an anchor for
+/// some or all of the classes. Identical anchors are shared.
+///
+/// All marks and bases are written to a single subtable; if this exceeds the
+/// limits of 16-bit offsets, it is split when the table is serialized.
+#[derive(Clone, Debug, Default)]
+pub struct MarkBasePosBuilder {
+    marks: MarkList,
+    bases: BTreeMap<GlyphId, AttachAnchors>,
+}
+
+/// A builder for [`MarkLigPosFormat1`] subtables.
+///
+/// Each ligature glyph may have an anchor for each mark class on each of its
+/// components.
+#[derive(Clone, Debug, Default)]
+pub struct MarkLigPosBuilder {
+    marks: MarkList,
+    ligatures: BTreeMap<GlyphId, Vec<AttachAnchors>>,
+}
+
+/// A builder for [`MarkMarkPosFormat1`] subtables.
+///
+/// The attaching marks (mark1) are grouped into classes, and the marks they
+/// attach to (mark2) have an anchor for some or all of the classes.
+#[derive(Clone, Debug, Default)]
+pub struct MarkMarkPosBuilder {
+    marks: MarkList,
+    mark2s: BTreeMap<GlyphId, AttachAnchors>,
+}
+
+impl MarkList {
+    fn insert(
+        &mut self,
+        glyph: GlyphId,
+        class: &str,
+        anchor: AnchorBuilder,
+    ) -> Result<u16, PreviouslyAssignedClass> {
+        if let Some((existing, _)) = self.glyphs.get(&glyph) {
+            let existing = &self.classes[*existing as usize];
+            if existing != class {
+                return Err(PreviouslyAssignedClass {
+                    glyph_id: glyph,
+                    class: existing.clone(),
+                });
+            }
+        }
+        let id = match self.class_id(class) {
+            Some(id) => id,
+            None => {
+                self.classes.push(class.to_owned());
+                (self.classes.len() - 1) as u16
+            }
+        };
+        self.glyphs.insert(glyph, (id, anchor));
+        Ok(id)
+    }
+
+    fn class_id(&self, class: &str) -> Option<u16> {
+        self.classes
+            .iter()
+            .position(|existing| existing == class)
+            .map(|id| id as u16)
+    }
+
+    fn class_count(&self) -> usize {
+        self.classes.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Order a glyph's anchors by class, ignoring any unknown classes.
+    ///
+    /// Returns `None` if the glyph has no anchors for any known class.
+    fn anchors_by_class(
+        &self,
+        anchors: &AttachAnchors,
+        cache: &mut AnchorCache,
+        var_store: &mut VariationStoreBuilder,
+    ) -> Option<Vec<Option<AnchorTable>>> {
+        let mut result = vec![None; self.class_count()];
+        for (class, anchor) in anchors {
+            if let Some(id) = self.class_id(class) {
+                result[id as usize] = Some(cache.build(anchor, var_store));
+            }
+        }
+        result.iter().any(Option::is_some).then_some(result)
+    }
+
+    /// Build the anchors for each attaching glyph, ignoring glyphs with no
+    /// anchors.
+    fn build_attach_anchors(
+        &self,
+        glyphs: &BTreeMap<GlyphId, AttachAnchors>,
+        cache: &mut AnchorCache,
+        var_store: &mut VariationStoreBuilder,
+    ) -> (CoverageTable, Vec<Vec<Option<AnchorTable>>>) {
+        let (coverage, anchors): (Vec<_>, Vec<_>) = glyphs
+            .iter()
+            .filter_map(|(gid, anchors)| {
+                self.anchors_by_class(anchors, cache, var_store)
+                    .map(|anchors| (*gid, anchors))
+            })
+            .unzip();
+        (CoverageTableBuilder::from_glyphs(coverage).build(), anchors)
+    }
+
+    fn build(
+        &self,
+        cache: &mut AnchorCache,
+        var_store: &mut VariationStoreBuilder,
+    ) -> (CoverageTable, MarkArray) {
+        let coverage = CoverageTableBuilder::from_glyphs(self.glyphs.keys().copied().collect());
+        let records = self
+            .glyphs
+            .values()
+            .map(|(class, anchor)| MarkRecord::new(*class, cache.build(anchor, var_store)))
+            .collect();
+        (coverage.build(), MarkArray::new(records))
+    }
+}
+
+impl MarkBasePosBuilder {
+    /// Add a mark glyph to a class, with the anchor used to attach it.
+    ///
+    /// Returns the id of the class, or an error if the glyph has already
+    /// been added to a different class.
+    pub fn insert_mark(
+        &mut self,
+        glyph: GlyphId,
+        class: &str,
+        anchor: AnchorBuilder,
+    ) -> Result<u16, PreviouslyAssignedClass> {
+        self.marks.insert(glyph, class, anchor)
+    }
+
+    /// Add the anchor where marks in `class` attach to a base glyph.
+    pub fn insert_base(&mut self, glyph: GlyphId, class: &str, anchor: AnchorBuilder) {
+        self.bases
+            .entry(glyph)
+            .or_default()
+            .insert(class.to_owned(), anchor);
+    }
+
+    /// Returns `true` if there are no marks or no bases.
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty() || self.bases.is_empty()
+    }
+}
+
+impl MarkLigPosBuilder {
+    /// Add a mark glyph to a class, with the anchor used to attach it.
+    ///
+    /// Returns the id of the class, or an error if the glyph has already
+    /// been added to a different class.
+    pub fn insert_mark(
+        &mut self,
+        glyph: GlyphId,
+        class: &str,
+        anchor: AnchorBuilder,
+    ) -> Result<u16, PreviouslyAssignedClass> {
+        self.marks.insert(glyph, class, anchor)
+    }
+
+    /// Add the anchors where marks in `class` attach to each component of a
+    /// ligature glyph.
+    ///
+    /// Components with no anchor for this class should be `None`.
+    pub fn insert_ligature(
+        &mut self,
+        glyph: GlyphId,
+        class: &str,
+        components: Vec<Option<AnchorBuilder>>,
+    ) {
+        let existing = self.ligatures.entry(glyph).or_default();
+        if existing.len() < components.len() {
+            existing.resize_with(components.len(), Default::default);
+        }
+        for (component, anchor) in existing.iter_mut().zip(components) {
+            if let Some(anchor) = anchor {
+                component.insert(class.to_owned(), anchor);
+            }
+        }
+    }
+
+    /// Returns `true` if there are no marks or no ligatures.
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty() || self.ligatures.is_empty()
+    }
+}
+
+impl MarkMarkPosBuilder {
+    /// Add an attaching mark glyph to a class, with the anchor used to attach
+    /// it.
+    ///
+    /// Returns the id of the class, or an error if the glyph has already
+    /// been added to a different class.
+    pub fn insert_mark1(
+        &mut self,
+        glyph: GlyphId,
+        class: &str,
+        anchor: AnchorBuilder,
+    ) -> Result<u16, PreviouslyAssignedClass> {
+        self.marks.insert(glyph, class, anchor)
+    }
+
+    /// Add the anchor where marks in `class` attach to a mark2 glyph.
+    pub fn insert_mark2(&mut self, glyph: GlyphId, class: &str, anchor: AnchorBuilder) {
+        self.mark2s
+            .entry(glyph)
+            .or_default()
+            .insert(class.to_owned(), anchor);
+    }
+
+    /// Returns `true` if there are no marks or no base marks.
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty() || self.mark2s.is_empty()
+    }
+}
+
+impl Builder for MarkBasePosBuilder {
+    type Output = Vec<MarkBasePosFormat1>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let mut cache = AnchorCache::default();
+        let (mark_coverage, mark_array) = self.marks.build(&mut cache, var_store);
+        let (base_coverage, anchors) =
+            self.marks
+                .build_attach_anchors(&self.bases, &mut cache, var_store);
+        let base_array = BaseArray::new(anchors.into_iter().map(BaseRecord::new).collect());
+        vec![MarkBasePosFormat1::new(
+            mark_coverage,
+            base_coverage,
+            mark_array,
+            base_array,
+        )]
+    }
+}
+
+impl Builder for MarkLigPosBuilder {
+    type Output = Vec<MarkLigPosFormat1>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let mut cache = AnchorCache::default();
+        let (mark_coverage, mark_array) = self.marks.build(&mut cache, var_store);
+        let (coverage, attaches): (Vec<_>, Vec<_>) = self
+            .ligatures
+            .iter()
+            .filter_map(|(gid, components)| {
+                let records = components
+                    .iter()
+                    .map(|anchors| {
+                        self.marks
+                            .anchors_by_class(anchors, &mut cache, var_store)
+                            .unwrap_or_else(|| vec![None; self.marks.class_count()])
+                    })
+                    .map(ComponentRecord::new)
+                    .collect::<Vec<_>>();
+                let has_anchors = records
+                    .iter()
+                    .any(|rec| rec.ligature_anchors.iter().any(|anchor| anchor.is_some()));
+                has_anchors.then(|| (*gid, LigatureAttach::new(records)))
+            })
+            .unzip();
+        vec![MarkLigPosFormat1::new(
+            mark_coverage,
+            CoverageTableBuilder::from_glyphs(coverage).build(),
+            mark_array,
+            LigatureArray::new(attaches),
+        )]
+    }
+}
+
+impl Builder for MarkMarkPosBuilder {
+    type Output = Vec<MarkMarkPosFormat1>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let mut cache = AnchorCache::default();
+        let (mark1_coverage, mark1_array) = self.marks.build(&mut cache, var_store);
+        let (mark2_coverage, anchors) =
+            self.marks
+                .build_attach_anchors(&self.mark2s, &mut cache, var_store);
+        let mark2_array = Mark2Array::new(anchors.into_iter().map(Mark2Record::new).collect());
+        vec![MarkMarkPosFormat1::new(
+            mark1_coverage,
+            mark2_coverage,
+            mark1_array,
+            mark2_array,
+        )]
+    }
+}
+
+impl std::fmt::Display for PreviouslyAssignedClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Glyph {} was previously assigned to class '{}'",
+            self.glyph_id, self.class
+        )
+    }
+}
+
+impl std::error::Error for PreviouslyAssignedClass {}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use read_fonts::{tables::gpos as rgpos, FontData, FontRead};
+
+    use crate::tables::{
+        gpos::builders::Metric,
+        layout::{
+            builders::RemapVariationIndices, DeviceOrVariationIndex, Lookup, LookupFlag, LookupList,
+        },
+        variations::{RegionAxisCoordinates, VariationRegion},
+    };
+
+    use super::*;
+
+    fn gid(id: u16) -> GlyphId {
+        GlyphId::new(id)
+    }
+
+    fn anchor(x: i16, y: i16) -> AnchorBuilder {
+        AnchorBuilder::new(x, y)
+    }
+
+    fn coords(anchor: &AnchorTable) -> (i16, i16) {
+        match anchor {
+            AnchorTable::Format1(table) => (table.x_coordinate, table.y_coordinate),
+            AnchorTable::Format2(table) => (table.x_coordinate, table.y_coordinate),
+            AnchorTable::Format3(table) => (table.x_coordinate, table.y_coordinate),
+        }
+    }
+
+    #[test]
+    fn mark_to_base() {
+        let mut builder = MarkBasePosBuilder::default();
+        assert_eq!(builder.insert_mark(gid(20), "top", anchor(0, 500)), Ok(0));
+        assert_eq!(
+            builder.insert_mark(gid(21), "bottom", anchor(0, -50)),
+            Ok(1)
+        );
+        assert_eq!(builder.insert_mark(gid(22), "top", anchor(10, 510)), Ok(0));
+        builder.insert_base(gid(1), "top", anchor(250, 700));
+        builder.insert_base(gid(1), "bottom", anchor(250, 0));
+        builder.insert_base(gid(2), "bottom", anchor(300, 0));
+        // no marks in this class
+        builder.insert_base(gid(3), "ogonek", anchor(300, 0));
+
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert_eq!(subtables.len(), 1);
+        let table = &subtables[0];
+        assert_eq!(table.mark_coverage.len(), 3);
+        let classes = table
+            .mark_array
+            .mark_records
+            .iter()
+            .map(|rec| rec.mark_class)
+            .collect::<Vec<_>>();
+        assert_eq!(classes, [0, 1, 0]);
+
+        assert_eq!(
+            table.base_coverage.iter().collect::<Vec<_>>(),
+            [gid(1), gid(2)]
+        );
+        let records = &table.base_array.base_records;
+        assert_eq!(records[0].base_anchors.len(), 2);
+        assert_eq!(
+            coords(records[0].base_anchors[1].as_ref().unwrap()),
+            (250, 0)
+        );
+        assert!(records[1].base_anchors[0].is_none());
+    }
+
+    #[test]
+    fn conflicting_mark_class() {
+        let mut builder = MarkBasePosBuilder::default();
+        builder.insert_mark(gid(20), "top", anchor(0, 500)).unwrap();
+        assert_eq!(
+            builder.insert_mark(gid(20), "bottom", anchor(0, -50)),
+            Err(PreviouslyAssignedClass {
+                glyph_id: gid(20),
+                class: "top".into()
+            })
+        );
+        // the failed insert doesn't create a class
+        assert_eq!(
+            builder.insert_mark(gid(21), "cedilla", anchor(0, -50)),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn mark_to_ligature() {
+        let mut builder = MarkLigPosBuilder::default();
+        builder.insert_mark(gid(20), "top", anchor(0, 500)).unwrap();
+        builder
+            .insert_mark(gid(21), "bottom", anchor(0, -50))
+            .unwrap();
+        builder.insert_ligature(
+            gid(5),
+            "top",
+            vec![Some(anchor(200, 700)), None, Some(anchor(800, 700))],
+        );
+        builder.insert_ligature(gid(5), "bottom", vec![Some(anchor(200, 0))]);
+
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        let table = &subtables[0];
+        let attach = &table.ligature_array.ligature_attaches[0];
+        assert_eq!(attach.component_records.len(), 3);
+        let anchors = &attach.component_records[0].ligature_anchors;
+        assert_eq!(coords(anchors[0].as_ref().unwrap()), (200, 700));
+        assert_eq!(coords(anchors[1].as_ref().unwrap()), (200, 0));
+        let anchors = &attach.component_records[1].ligature_anchors;
+        assert!(anchors.iter().all(|anchor| anchor.is_none()));
+        let anchors = &attach.component_records[2].ligature_anchors;
+        assert_eq!(coords(anchors[0].as_ref().unwrap()), (800, 700));
+        assert!(anchors[1].is_none());
+    }
+
+    #[test]
+    fn mark_to_mark() {
+        let mut builder = MarkMarkPosBuilder::default();
+        builder
+            .insert_mark1(gid(20), "top", anchor(0, 500).with_contour_point(3))
+            .unwrap();
+        builder.insert_mark2(gid(21), "top", anchor(0, 800));
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        let table = &subtables[0];
+        assert!(matches!(
+            table.mark1_array.mark_records[0].mark_anchor.as_ref(),
+            AnchorTable::Format2(_)
+        ));
+        assert_eq!(table.mark2_array.mark2_records[0].mark2_anchors.len(), 1);
+    }
+
+    #[test]
+    fn variable_anchors_are_deduplicated() {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates {
+            start_coord: F2Dot14::from_f32(0.0),
+            peak_coord: F2Dot14::from_f32(1.0),
+            end_coord: F2Dot14::from_f32(1.0),
+        }]);
+        let top = AnchorBuilder::new(Metric::new(250, vec![(region.clone(), 30)]), 700);
+        let mut builder = MarkBasePosBuilder::default();
+        builder.insert_mark(gid(20), "top", anchor(0, 500)).unwrap();
+        builder.insert_base(gid(1), "top", top.clone());
+        builder.insert_base(gid(2), "top", top);
+        builder.insert_base(
+            gid(3),
+            "top",
+            AnchorBuilder::new(Metric::new(300, vec![(region, 40)]), 700),
+        );
+
+        let mut var_store = VariationStoreBuilder::new();
+        let mut subtables = builder.build(&mut var_store);
+        let (store, key_map) = var_store.build();
+        subtables.remap_variation_indices(&key_map);
+        let n_deltas: u16 = store
+            .item_variation_data
+            .iter()
+            .map(|data| data.as_ref().unwrap().item_count)
+            .sum();
+        assert_eq!(n_deltas, 2);
+
+        let records = &subtables[0].base_array.base_records;
+        let AnchorTable::Format3(anchor) = records[0].base_anchors[0].as_ref().unwrap() else {
+            panic!("expected format 3");
+        };
+        assert!(matches!(
+            anchor.x_device.as_ref(),
+            Some(DeviceOrVariationIndex::VariationIndex(_))
+        ));
+        assert!(anchor.y_device.is_none());
+        assert_eq!(records[0].base_anchors, records[1].base_anchors);
+    }
+
+    #[test]
+    fn large_mark_to_base_is_split() {
+        let mut builder = MarkBasePosBuilder::default();
+        for class in 0..20u16 {
+            let name = format!("class{class}");
+            builder
+                .insert_mark(gid(1000 + class), &name, anchor(0, class as i16))
+                .unwrap();
+            for base in 0..600u16 {
+                builder.insert_base(gid(base), &name, anchor(base as i16, class as i16));
+            }
+        }
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert_eq!(subtables.len(), 1);
+        let lookup = Lookup::new(LookupFlag::empty(), subtables, 0);
+        let bytes = crate::dump_table(&LookupList::new(vec![lookup])).unwrap();
+        let lookups = rgpos::PositionLookupList::read(FontData::new(&bytes)).unwrap();
+        let n_subtables = match lookups.lookups().get(0).unwrap() {
+            rgpos::PositionLookup::MarkToBase(lookup) => lookup.sub_table_count(),
+            rgpos::PositionLookup::Extension(lookup) => lookup.sub_table_count(),
+            _ => panic!("unexpected lookup type"),
+        };
+        assert!(n_subtables > 1);
+    }
+}
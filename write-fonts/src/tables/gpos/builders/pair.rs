@@ -0,0 +1,834 @@
+//! Building pair adjustment (kerning) subtables
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    gpos::{
+        Class1Record, Class2Record, PairPos, PairPosFormat1, PairPosFormat2, PairSet,
+        PairValueRecord, ValueFormat, ValueRecord,
+    },
+    layout::{builders::Builder, ClassDefBuilder, CoverageTableBuilder},
+    variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::ValueRecordBuilder;
+
+/// The size of a format 2 subtable, excluding its coverage, class definitions
+/// and records.
+const PAIR_POS_2_HEADER_LEN: usize = 16;
+
+type RecordPair = (ValueRecordBuilder, ValueRecordBuilder);
+
+/// A builder for [`PairPos`] subtables.
+///
+/// Adjustments may be given for specific pairs of glyphs, or for all pairs
+/// of glyphs in two classes. Glyph pairs are written as format 1 subtables,
+/// ahead of any class pairs, so that they can be used as exceptions to
+/// class kerning.
+///
+/// Class pairs are written as format 2 subtables. If compaction is enabled
+/// with [`set_compaction`][Self::set_compaction], a class subtable that would
+/// be larger than 64K is instead split into several subtables, each covering
+/// the first classes that use a similar set of second classes; this is
+/// similar to the 'compact' mode in fontTools.
+///
+/// Otherwise, subtables may be larger than the 64K limit of a lookup; when
+/// the table is written, these are split as needed.
+#[derive(Clone, Debug, Default)]
+pub struct PairPosBuilder {
+    pairs: BTreeMap<GlyphId, BTreeMap<GlyphId, RecordPair>>,
+    class_subtables: Vec<ClassPairSubtable>,
+    force_subtable_break: bool,
+    compact: bool,
+}
+
+/// The class pairs of a single format 2 subtable, before compaction.
+#[derive(Clone, Debug, Default)]
+struct ClassPairSubtable {
+    // in both lists, classes are disjoint
+    classes1: Vec<BTreeSet<GlyphId>>,
+    classes2: Vec<BTreeSet<GlyphId>>,
+    values: BTreeMap<(usize, usize), RecordPair>,
+}
+
+impl PairPosBuilder {
+    /// Add an adjustment for a pair of glyphs.
+    ///
+    /// If this pair already has an adjustment, the existing one is kept.
+    pub fn insert_pair(
+        &mut self,
+        glyph1: GlyphId,
+        record1: ValueRecordBuilder,
+        glyph2: GlyphId,
+        record2: ValueRecordBuilder,
+    ) {
+        self.pairs
+            .entry(glyph1)
+            .or_default()
+            .entry(glyph2)
+            .or_insert((record1, record2));
+    }
+
+    /// Add an adjustment for each pair of glyphs in two classes.
+    ///
+    /// A new subtable is started if either class overlaps a class already in
+    /// the current subtable without being identical to it. If this pair of
+    /// classes already has an adjustment, the existing one is kept.
+    pub fn insert_classes(
+        &mut self,
+        class1: BTreeSet<GlyphId>,
+        record1: ValueRecordBuilder,
+        class2: BTreeSet<GlyphId>,
+        record2: ValueRecordBuilder,
+    ) {
+        if class1.is_empty() || class2.is_empty() {
+            return;
+        }
+        let start_new = std::mem::take(&mut self.force_subtable_break)
+            || self
+                .class_subtables
+                .last()
+                .map(|subtable| !subtable.can_add(&class1, &class2))
+                .unwrap_or(true);
+        if start_new {
+            self.class_subtables.push(Default::default());
+        }
+        self.class_subtables
+            .last_mut()
+            .unwrap()
+            .add(class1, class2, (record1, record2));
+    }
+
+    /// Start a new subtable for any class pairs added after this call.
+    ///
+    /// This is equivalent to the `subtable` statement in feature files.
+    pub fn add_subtable_break(&mut self) {
+        self.force_subtable_break = true;
+    }
+
+    /// Split class subtables that would be larger than 64K by grouping first
+    /// classes that use similar second classes.
+    ///
+    /// This is off by default. It usually produces a smaller table than the
+    /// splitting done when the table is written, but takes longer to build.
+    pub fn set_compaction(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    /// Returns `true` if no adjustments have been added.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty() && self.class_subtables.is_empty()
+    }
+
+    fn build_glyph_pairs(
+        pairs: BTreeMap<GlyphId, BTreeMap<GlyphId, RecordPair>>,
+        var_store: &mut VariationStoreBuilder,
+    ) -> Vec<PairPos> {
+        // one subtable for each combination of value formats, so that records
+        // are not padded with unused fields. Variable and non-variable values
+        // share a subtable, with null offsets for the non-variable ones.
+        let mut by_format: BTreeMap<_, BTreeMap<GlyphId, Vec<_>>> = BTreeMap::new();
+        for (glyph1, seconds) in pairs {
+            for (glyph2, (record1, record2)) in seconds {
+                let key = (
+                    record1.format() & !ValueFormat::ANY_DEVICE_OR_VARIDX,
+                    record2.format() & !ValueFormat::ANY_DEVICE_OR_VARIDX,
+                );
+                by_format
+                    .entry(key)
+                    .or_default()
+                    .entry(glyph1)
+                    .or_default()
+                    .push((glyph2, record1, record2));
+            }
+        }
+        by_format
+            .into_values()
+            .map(|pairs| {
+                let (format1, format2) = value_formats(
+                    pairs
+                        .values()
+                        .flatten()
+                        .map(|(_, record1, record2)| (record1, record2)),
+                );
+                let coverage = CoverageTableBuilder::from_glyphs(pairs.keys().copied().collect());
+                let pair_sets = pairs
+                    .into_values()
+                    .map(|records| {
+                        PairSet::new(
+                            records
+                                .into_iter()
+                                .map(|(glyph2, record1, record2)| {
+                                    PairValueRecord::new(
+                                        glyph2,
+                                        record1.build_with_format(format1, var_store),
+                                        record2.build_with_format(format2, var_store),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect();
+                PairPos::Format1(PairPosFormat1::new(coverage.build(), pair_sets))
+            })
+            .collect()
+    }
+}
+
+impl Builder for PairPosBuilder {
+    type Output = Vec<PairPos>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        let mut subtables = Self::build_glyph_pairs(self.pairs, var_store);
+        for subtable in self.class_subtables {
+            subtables.extend(subtable.build(self.compact, var_store));
+        }
+        subtables
+    }
+}
+
+impl ClassPairSubtable {
+    fn can_add(&self, class1: &BTreeSet<GlyphId>, class2: &BTreeSet<GlyphId>) -> bool {
+        class_index(&self.classes1, class1).is_some()
+            && class_index(&self.classes2, class2).is_some()
+    }
+
+    fn add(&mut self, class1: BTreeSet<GlyphId>, class2: BTreeSet<GlyphId>, records: RecordPair) {
+        let idx1 = add_class(&mut self.classes1, class1);
+        let idx2 = add_class(&mut self.classes2, class2);
+        self.values.entry((idx1, idx2)).or_insert(records);
+    }
+
+    /// Build one or more subtables, grouping the first classes to minimize
+    /// the total size if `compact` is set.
+    fn build(mut self, compact: bool, var_store: &mut VariationStoreBuilder) -> Vec<PairPos> {
+        let mut rows: Vec<BTreeMap<usize, RecordPair>> =
+            vec![Default::default(); self.classes1.len()];
+        for ((idx1, idx2), records) in std::mem::take(&mut self.values) {
+            rows[idx1].insert(idx2, records);
+        }
+        let clusters = match compact {
+            true => self.cluster_rows(&rows),
+            false => vec![(0..rows.len()).collect()],
+        };
+        let mut rows = rows.into_iter().map(Some).collect::<Vec<_>>();
+        clusters
+            .into_iter()
+            .map(|cluster| {
+                let rows = cluster
+                    .iter()
+                    .map(|idx1| (*idx1, rows[*idx1].take().unwrap()))
+                    .collect::<Vec<_>>();
+                self.build_subtable(rows, var_store)
+            })
+            .collect()
+    }
+
+    /// Greedily merge groups of first classes, for as long as merging does
+    /// not increase the total size.
+    ///
+    /// Each group of first classes will become its own subtable. This is the
+    /// same approach as fontTools' `compact` mode. If all the classes fit in
+    /// a single subtable, they are not split.
+    fn cluster_rows(&self, rows: &[BTreeMap<usize, RecordPair>]) -> Vec<Vec<usize>> {
+        let class2_sizes = self.classes2.iter().map(BTreeSet::len).collect::<Vec<_>>();
+        let mut estimates = rows
+            .iter()
+            .enumerate()
+            .map(|(idx, row)| Some(SizeEstimate::new(&self.classes1[idx], row, &class2_sizes)))
+            .collect::<Vec<_>>();
+        let whole = estimates
+            .iter()
+            .flatten()
+            .cloned()
+            .reduce(|a, b| a.merge(&b, &class2_sizes));
+        let fits = whole
+            .map(|whole| whole.size <= u16::MAX as usize)
+            .unwrap_or(true);
+        if fits {
+            return vec![(0..rows.len()).collect()];
+        }
+
+        let mut clusters = (0..rows.len())
+            .map(|idx| Some(vec![idx]))
+            .collect::<Vec<_>>();
+        // the size of merging each pair of clusters, where i < j
+        let mut merged_sizes = (0..rows.len())
+            .map(|i| {
+                (0..rows.len())
+                    .map(|j| match (&estimates[i], &estimates[j]) {
+                        (Some(a), Some(b)) if i < j => a.merge(b, &class2_sizes).size,
+                        _ => usize::MAX,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        loop {
+            let mut best: Option<(isize, usize, usize)> = None;
+            for (i, a) in estimates.iter().enumerate() {
+                let Some(a) = a else {
+                    continue;
+                };
+                for (j, b) in estimates.iter().enumerate().skip(i + 1) {
+                    let Some(b) = b else {
+                        continue;
+                    };
+                    let change = merged_sizes[i][j] as isize - (a.size + b.size) as isize;
+                    if best.map(|(best, ..)| change < best).unwrap_or(true) {
+                        best = Some((change, i, j));
+                    }
+                }
+            }
+            let Some((change, i, j)) = best.filter(|(change, ..)| *change <= 0) else {
+                break;
+            };
+            log::trace!("merging pairpos rows {i} and {j}, saving {} bytes", -change);
+            let other = clusters[j].take().unwrap();
+            let cluster = clusters[i].as_mut().unwrap();
+            cluster.extend(other);
+            cluster.sort_unstable();
+            let other = estimates[j].take().unwrap();
+            let merged = estimates[i].as_ref().unwrap().merge(&other, &class2_sizes);
+            for (k, estimate) in estimates.iter().enumerate() {
+                if let Some(estimate) = estimate.as_ref().filter(|_| k != i) {
+                    merged_sizes[i.min(k)][i.max(k)] = merged.merge(estimate, &class2_sizes).size;
+                }
+            }
+            estimates[i] = Some(merged);
+        }
+
+        let mut clusters = clusters.into_iter().flatten().collect::<Vec<_>>();
+        // order subtables by their first glyph
+        clusters.sort_by_key(|cluster| {
+            cluster
+                .iter()
+                .filter_map(|idx| self.classes1[*idx].first())
+                .min()
+                .copied()
+        });
+        clusters
+    }
+
+    /// The first class definition for a group of classes, and their coverage.
+    ///
+    /// The largest class is assigned class 0, so that it can be omitted from
+    /// the class definition.
+    fn class_def1(&self, cluster: &[usize]) -> (ClassDefBuilder, CoverageTableBuilder) {
+        let order = sorted_classes(&self.classes1, cluster.iter().copied());
+        let class_def = order
+            .iter()
+            .enumerate()
+            .flat_map(|(class, idx)| {
+                self.classes1[*idx]
+                    .iter()
+                    .map(move |gid| (*gid, class as u16))
+            })
+            .collect();
+        let coverage = cluster
+            .iter()
+            .flat_map(|idx| self.classes1[*idx].iter().copied())
+            .collect();
+        (class_def, coverage)
+    }
+
+    /// The second class definition for a group of classes.
+    ///
+    /// Class 0 is reserved for glyphs that are not in any class.
+    fn class_def2(&self, columns: &BTreeSet<usize>) -> ClassDefBuilder {
+        sorted_classes(&self.classes2, columns.iter().copied())
+            .iter()
+            .enumerate()
+            .flat_map(|(class, idx)| {
+                self.classes2[*idx]
+                    .iter()
+                    .map(move |gid| (*gid, class as u16 + 1))
+            })
+            .collect()
+    }
+
+    fn build_subtable(
+        &self,
+        rows: Vec<(usize, BTreeMap<usize, RecordPair>)>,
+        var_store: &mut VariationStoreBuilder,
+    ) -> PairPos {
+        let (format1, format2) = value_formats(
+            rows.iter()
+                .flat_map(|(_, row)| row.values().map(|(r1, r2)| (r1, r2))),
+        );
+        let cluster = rows.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
+        let (class_def1, coverage) = self.class_def1(&cluster);
+        let columns = used_columns(rows.iter().map(|(_, row)| row));
+        let column_order = sorted_classes(&self.classes2, columns.iter().copied());
+        let class_def2 = self.class_def2(&columns);
+
+        let empty = || {
+            Class2Record::new(
+                ValueRecord::new().with_explicit_value_format(format1),
+                ValueRecord::new().with_explicit_value_format(format2),
+            )
+        };
+        let mut rows = rows.into_iter().collect::<BTreeMap<_, _>>();
+        let class1_records = sorted_classes(&self.classes1, cluster.iter().copied())
+            .into_iter()
+            .map(|idx1| {
+                let mut row = rows.remove(&idx1).unwrap_or_default();
+                // class 0 has no adjustments
+                let class2_records = std::iter::once(empty())
+                    .chain(column_order.iter().map(|idx2| match row.remove(idx2) {
+                        Some((record1, record2)) => Class2Record::new(
+                            record1.build_with_format(format1, var_store),
+                            record2.build_with_format(format2, var_store),
+                        ),
+                        None => empty(),
+                    }))
+                    .collect();
+                Class1Record::new(class2_records)
+            })
+            .collect();
+        PairPos::Format2(PairPosFormat2::new(
+            coverage.build(),
+            class_def1.build(),
+            class_def2.build(),
+            class1_records,
+        ))
+    }
+}
+
+/// An estimate of the size of a format 2 subtable for a group of first
+/// classes, used when compacting.
+///
+/// This is computed from the numbers of classes and glyphs, and the value
+/// formats, without building the subtable.
+#[derive(Clone, Debug)]
+struct SizeEstimate {
+    n_classes1: usize,
+    n_glyphs1: usize,
+    largest_class1: usize,
+    /// The second classes used, as a bitmask.
+    classes2: Vec<u64>,
+    formats: (ValueFormat, ValueFormat),
+    size: usize,
+}
+
+impl SizeEstimate {
+    fn new(
+        class1: &BTreeSet<GlyphId>,
+        row: &BTreeMap<usize, RecordPair>,
+        class2_sizes: &[usize],
+    ) -> Self {
+        let mut classes2 = vec![0u64; class2_sizes.len().div_ceil(64)];
+        for idx2 in row.keys() {
+            classes2[idx2 / 64] |= 1 << (idx2 % 64);
+        }
+        let formats = value_formats(row.values().map(|(r1, r2)| (r1, r2)));
+        Self::with_size(
+            class1.len(),
+            class1.len(),
+            1,
+            classes2,
+            formats,
+            class2_sizes,
+        )
+    }
+
+    fn merge(&self, other: &Self, class2_sizes: &[usize]) -> Self {
+        Self::with_size(
+            self.n_glyphs1 + other.n_glyphs1,
+            self.largest_class1.max(other.largest_class1),
+            self.n_classes1 + other.n_classes1,
+            self.classes2
+                .iter()
+                .zip(&other.classes2)
+                .map(|(a, b)| a | b)
+                .collect(),
+            (
+                self.formats.0 | other.formats.0,
+                self.formats.1 | other.formats.1,
+            ),
+            class2_sizes,
+        )
+    }
+
+    /// Coverage and class definitions are assumed to use two bytes per glyph.
+    fn with_size(
+        n_glyphs1: usize,
+        largest_class1: usize,
+        n_classes1: usize,
+        classes2: Vec<u64>,
+        formats: (ValueFormat, ValueFormat),
+        class2_sizes: &[usize],
+    ) -> Self {
+        let mut n_classes2 = 0;
+        let mut n_glyphs2 = 0;
+        for (i, word) in classes2.iter().enumerate() {
+            let mut word = *word;
+            while word != 0 {
+                n_classes2 += 1;
+                n_glyphs2 += class2_sizes[i * 64 + word.trailing_zeros() as usize];
+                word &= word - 1;
+            }
+        }
+        let record_len =
+            (formats.0.bits().count_ones() + formats.1.bits().count_ones()) as usize * 2;
+        let size = PAIR_POS_2_HEADER_LEN
+            + 4
+            + 2 * n_glyphs1
+            // the largest first class is class 0, and omitted
+            + 6
+            + 2 * (n_glyphs1 - largest_class1)
+            + 6
+            + 2 * n_glyphs2
+            + n_classes1 * (n_classes2 + 1) * record_len;
+        Self {
+            n_classes1,
+            n_glyphs1,
+            largest_class1,
+            classes2,
+            formats,
+            size,
+        }
+    }
+}
+
+/// Returns the index of `class` in `classes`, `Some(None)` if it is disjoint
+/// from all of them, or `None` if it partially overlaps one of them.
+fn class_index(classes: &[BTreeSet<GlyphId>], class: &BTreeSet<GlyphId>) -> Option<Option<usize>> {
+    let mut result = None;
+    for (idx, existing) in classes.iter().enumerate() {
+        if existing == class {
+            result = Some(idx);
+        } else if !existing.is_disjoint(class) {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+fn add_class(classes: &mut Vec<BTreeSet<GlyphId>>, class: BTreeSet<GlyphId>) -> usize {
+    match class_index(classes, &class) {
+        Some(Some(idx)) => idx,
+        _ => {
+            classes.push(class);
+            classes.len() - 1
+        }
+    }
+}
+
+/// Sort classes with the largest first, and then by their first glyph.
+fn sorted_classes(
+    classes: &[BTreeSet<GlyphId>],
+    indices: impl Iterator<Item = usize>,
+) -> Vec<usize> {
+    let mut indices = indices.collect::<Vec<_>>();
+    indices.sort_by_key(|idx| {
+        (
+            std::cmp::Reverse(classes[*idx].len()),
+            classes[*idx].first(),
+        )
+    });
+    indices
+}
+
+fn used_columns<'a>(
+    rows: impl Iterator<Item = &'a BTreeMap<usize, RecordPair>>,
+) -> BTreeSet<usize> {
+    rows.flat_map(|row| row.keys().copied()).collect()
+}
+
+/// The union of the formats of all the records in a subtable.
+fn value_formats<'a>(
+    records: impl Iterator<Item = (&'a ValueRecordBuilder, &'a ValueRecordBuilder)>,
+) -> (ValueFormat, ValueFormat) {
+    records.fold(
+        (ValueFormat::empty(), ValueFormat::empty()),
+        |(format1, format2), (record1, record2)| {
+            (format1 | record1.format(), format2 | record2.format())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use read_fonts::{tables::gpos as rgpos, FontData, FontRead};
+
+    use crate::tables::{
+        gpos::builders::Metric,
+        layout::{
+            builders::RemapVariationIndices, DeviceOrVariationIndex, Lookup, LookupFlag, LookupList,
+        },
+        variations::{RegionAxisCoordinates, VariationRegion},
+    };
+
+    use super::*;
+
+    fn glyphs(ids: impl IntoIterator<Item = u16>) -> BTreeSet<GlyphId> {
+        ids.into_iter().map(GlyphId::new).collect()
+    }
+
+    fn kern(x_advance: i16) -> ValueRecordBuilder {
+        ValueRecordBuilder::new().with_x_advance(x_advance)
+    }
+
+    fn build(builder: PairPosBuilder) -> Vec<PairPos> {
+        let mut var_store = VariationStoreBuilder::new();
+        builder.build(&mut var_store)
+    }
+
+    // the adjustments of the first matching subtable, as a harfbuzz-like
+    // shaper would apply them
+    fn lookup_kern(subtables: &[PairPos], glyph1: u16, glyph2: u16) -> Option<i16> {
+        let (glyph1, glyph2) = (GlyphId::new(glyph1), GlyphId::new(glyph2));
+        for subtable in subtables {
+            match subtable {
+                PairPos::Format1(table) => {
+                    let Some(idx) = table.coverage.iter().position(|gid| gid == glyph1) else {
+                        continue;
+                    };
+                    if let Some(record) = table.pair_sets[idx]
+                        .pair_value_records
+                        .iter()
+                        .find(|rec| rec.second_glyph == glyph2)
+                    {
+                        return record.value_record1.x_advance;
+                    }
+                }
+                PairPos::Format2(table) => {
+                    if !table.coverage.iter().any(|gid| gid == glyph1) {
+                        continue;
+                    }
+                    let class1 = table.class_def1.iter().find(|(gid, _)| *gid == glyph1);
+                    let class2 = table.class_def2.iter().find(|(gid, _)| *gid == glyph2);
+                    let class1 = class1.map(|(_, cls)| cls).unwrap_or(0) as usize;
+                    let class2 = class2.map(|(_, cls)| cls).unwrap_or(0) as usize;
+                    let record = &table.class1_records[class1].class2_records[class2];
+                    return Some(record.value_record1.x_advance.unwrap_or(0));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn glyph_pairs() {
+        let mut builder = PairPosBuilder::default();
+        builder.insert_pair(
+            GlyphId::new(5),
+            kern(-10),
+            GlyphId::new(6),
+            Default::default(),
+        );
+        builder.insert_pair(
+            GlyphId::new(5),
+            kern(-20),
+            GlyphId::new(8),
+            Default::default(),
+        );
+        builder.insert_pair(
+            GlyphId::new(2),
+            kern(15),
+            GlyphId::new(8),
+            Default::default(),
+        );
+        // ignored; the first value is kept
+        builder.insert_pair(
+            GlyphId::new(2),
+            kern(0),
+            GlyphId::new(8),
+            Default::default(),
+        );
+        // a different format gets its own subtable
+        builder.insert_pair(
+            GlyphId::new(3),
+            kern(1).with_y_advance(2),
+            GlyphId::new(4),
+            Default::default(),
+        );
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 2);
+        assert!(subtables
+            .iter()
+            .all(|sub| matches!(sub, PairPos::Format1(_))));
+        assert_eq!(lookup_kern(&subtables, 5, 6), Some(-10));
+        assert_eq!(lookup_kern(&subtables, 5, 8), Some(-20));
+        assert_eq!(lookup_kern(&subtables, 2, 8), Some(15));
+        assert_eq!(lookup_kern(&subtables, 2, 6), None);
+        assert_eq!(lookup_kern(&subtables, 3, 4), Some(1));
+    }
+
+    #[test]
+    fn class_pairs_after_glyph_pairs() {
+        let mut builder = PairPosBuilder::default();
+        builder.insert_classes(glyphs([1, 2, 3]), kern(-5), glyphs([10, 11]), kern(0));
+        builder.insert_classes(glyphs([4]), kern(-7), glyphs([10, 11]), kern(0));
+        builder.insert_pair(GlyphId::new(1), kern(3), GlyphId::new(10), kern(0));
+        let subtables = build(builder);
+        assert!(matches!(subtables[0], PairPos::Format1(_)));
+        assert_eq!(lookup_kern(&subtables, 1, 10), Some(3));
+        assert_eq!(lookup_kern(&subtables, 2, 11), Some(-5));
+        assert_eq!(lookup_kern(&subtables, 4, 10), Some(-7));
+        assert_eq!(lookup_kern(&subtables, 4, 12), Some(0));
+    }
+
+    #[test]
+    fn overlapping_classes_start_new_subtable() {
+        let mut builder = PairPosBuilder::default();
+        builder.insert_classes(glyphs([1, 2]), kern(-5), glyphs([10]), kern(0));
+        builder.insert_classes(glyphs([2, 3]), kern(-7), glyphs([10]), kern(0));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 2);
+        // glyph 2 uses the first subtable
+        assert_eq!(lookup_kern(&subtables, 2, 10), Some(-5));
+        assert_eq!(lookup_kern(&subtables, 3, 10), Some(-7));
+
+        let mut builder = PairPosBuilder::default();
+        builder.insert_classes(glyphs([1, 2]), kern(-5), glyphs([10]), kern(0));
+        builder.add_subtable_break();
+        builder.insert_classes(glyphs([3, 4]), kern(-7), glyphs([10]), kern(0));
+        assert_eq!(build(builder).len(), 2);
+    }
+
+    // two groups of single glyph first classes, each adjusting against its
+    // own set of `n / 2` second classes, with 16 byte records
+    fn grouped_classes(n: u16, compact: bool) -> Vec<PairPos> {
+        let record = |value: i16| {
+            kern(value)
+                .with_x_placement(value)
+                .with_y_placement(value)
+                .with_y_advance(value)
+        };
+        let mut builder = PairPosBuilder::default();
+        builder.set_compaction(compact);
+        for first in 0..n {
+            for second in 0..n / 2 {
+                let value = -(second as i16) - 1;
+                builder.insert_classes(
+                    glyphs([first]),
+                    record(value),
+                    glyphs([1000 + (first % 2) * n + second]),
+                    record(value),
+                );
+            }
+        }
+        build(builder)
+    }
+
+    #[test]
+    fn compact_class_pairs() {
+        // too large for one subtable, and smaller as two
+        let subtables = grouped_classes(64, true);
+        assert_eq!(subtables.len(), 2);
+        for sub in &subtables {
+            let PairPos::Format2(table) = sub else {
+                panic!("expected format 2");
+            };
+            assert_eq!(table.class1_records.len(), 32);
+            assert_eq!(table.class1_records[0].class2_records.len(), 33);
+        }
+        assert_eq!(lookup_kern(&subtables, 0, 1003), Some(-4));
+        assert_eq!(lookup_kern(&subtables, 3, 1067), Some(-4));
+        assert_eq!(lookup_kern(&subtables, 3, 1003), Some(0));
+
+        // without compaction, the subtable is only split when written
+        assert_eq!(grouped_classes(64, false).len(), 1);
+        // and small subtables are never compacted
+        assert_eq!(grouped_classes(8, true).len(), 1);
+    }
+
+    #[test]
+    fn class_def_uses_class_zero() {
+        let mut builder = PairPosBuilder::default();
+        builder.insert_classes(glyphs([1]), kern(-5), glyphs([10]), kern(0));
+        builder.insert_classes(glyphs([2, 3, 4]), kern(-7), glyphs([10]), kern(0));
+        let subtables = build(builder);
+        let PairPos::Format2(table) = &subtables[0] else {
+            panic!("expected format 2");
+        };
+        // the largest first class is class 0, and not in the class def
+        assert_eq!(
+            table.class_def1.iter().collect::<Vec<_>>(),
+            [(GlyphId::new(1), 1)]
+        );
+        assert_eq!(table.coverage.len(), 4);
+        assert_eq!(lookup_kern(&subtables, 3, 10), Some(-7));
+    }
+
+    #[test]
+    fn variable_values() {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates {
+            start_coord: F2Dot14::from_f32(0.0),
+            peak_coord: F2Dot14::from_f32(1.0),
+            end_coord: F2Dot14::from_f32(1.0),
+        }]);
+        let mut builder = PairPosBuilder::default();
+        builder.insert_pair(
+            GlyphId::new(1),
+            kern(-10),
+            GlyphId::new(2),
+            Default::default(),
+        );
+        builder.insert_pair(
+            GlyphId::new(1),
+            ValueRecordBuilder::new().with_x_advance(Metric::new(-20, vec![(region, -5)])),
+            GlyphId::new(3),
+            Default::default(),
+        );
+        let mut var_store = VariationStoreBuilder::new();
+        let mut subtables = builder.build(&mut var_store);
+        assert_eq!(subtables.len(), 1);
+        let (_store, key_map) = var_store.build();
+        subtables.remap_variation_indices(&key_map);
+
+        let PairPos::Format1(table) = &subtables[0] else {
+            panic!("expected format 1");
+        };
+        let records = &table.pair_sets[0].pair_value_records;
+        let format = ValueFormat::X_ADVANCE | ValueFormat::X_ADVANCE_DEVICE;
+        assert_eq!(records[0].value_record1.format(), format);
+        assert!(records[0].value_record1.x_advance_device.is_none());
+        assert!(matches!(
+            records[1].value_record1.x_advance_device.as_ref(),
+            Some(DeviceOrVariationIndex::VariationIndex(_))
+        ));
+
+        let lookup = Lookup::new(LookupFlag::empty(), subtables, 0);
+        let bytes = crate::dump_table(&LookupList::new(vec![lookup])).unwrap();
+        let lookups = rgpos::PositionLookupList::read(FontData::new(&bytes)).unwrap();
+        let rgpos::PositionLookup::Pair(lookup) = lookups.lookups().get(0).unwrap() else {
+            panic!("expected pair lookup");
+        };
+        let Ok(rgpos::PairPos::Format1(table)) = lookup.subtables().get(0) else {
+            panic!("expected format 1");
+        };
+        assert_eq!(table.value_format1(), format);
+    }
+
+    #[test]
+    fn large_subtables_are_split() {
+        let mut builder = PairPosBuilder::default();
+        for glyph1 in 0..400u16 {
+            for glyph2 in 0..100u16 {
+                builder.insert_pair(
+                    GlyphId::new(glyph1),
+                    kern((glyph1 + glyph2) as i16),
+                    GlyphId::new(1000 + glyph2),
+                    Default::default(),
+                );
+            }
+        }
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        let lookup = Lookup::new(LookupFlag::empty(), subtables, 0);
+        let bytes = crate::dump_table(&LookupList::new(vec![lookup])).unwrap();
+        let lookups = rgpos::PositionLookupList::read(FontData::new(&bytes)).unwrap();
+        let n_subtables = match lookups.lookups().get(0).unwrap() {
+            rgpos::PositionLookup::Pair(lookup) => lookup.sub_table_count(),
+            rgpos::PositionLookup::Extension(lookup) => lookup.sub_table_count(),
+            _ => panic!("unexpected lookup type"),
+        };
+        assert!(n_subtables > 1);
+    }
+}
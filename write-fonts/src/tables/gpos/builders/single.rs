@@ -0,0 +1,214 @@
+//! Building single positioning subtables
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    gpos::{SinglePos, ValueFormat},
+    layout::{
+        builders::{split_by_size, Builder},
+        CoverageTableBuilder,
+    },
+    variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::ValueRecordBuilder;
+
+/// The size of a format 2 subtable, excluding its coverage and records.
+const SINGLE_POS_2_HEADER_LEN: usize = 8;
+/// The size of a device or variation index table referenced by a record.
+const DEVICE_LEN: usize = 6;
+
+/// A builder for [`SinglePos`] subtables.
+///
+/// Glyphs that share the same adjustment are written as a format 1 subtable;
+/// the remaining glyphs are grouped by the fields they adjust, and written as
+/// format 2 subtables, split as needed so that each fits in 64K.
+#[derive(Clone, Debug, Default)]
+pub struct SinglePosBuilder {
+    items: BTreeMap<GlyphId, ValueRecordBuilder>,
+}
+
+impl SinglePosBuilder {
+    /// Add an adjustment, replacing any existing adjustment for `glyph`.
+    pub fn insert(&mut self, glyph: GlyphId, record: ValueRecordBuilder) {
+        self.items.insert(glyph, record);
+    }
+
+    /// Returns `true` if an adjustment exists for this glyph.
+    pub fn contains(&self, glyph: GlyphId) -> bool {
+        self.items.contains_key(&glyph)
+    }
+
+    /// Returns `true` if no adjustments have been added.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Builder for SinglePosBuilder {
+    type Output = Vec<SinglePos>;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output {
+        let mut by_value: BTreeMap<ValueRecordBuilder, BTreeSet<GlyphId>> = BTreeMap::new();
+        for (glyph, record) in self.items {
+            by_value.entry(record).or_default().insert(glyph);
+        }
+
+        // glyphs that share a value, and glyphs with a unique value grouped by
+        // format, ignoring device flags
+        let mut shared = Vec::new();
+        let mut unique: BTreeMap<ValueFormat, Vec<(GlyphId, ValueRecordBuilder)>> = BTreeMap::new();
+        for (record, glyphs) in by_value {
+            if glyphs.len() > 1 {
+                shared.push((glyphs.into_iter().collect::<Vec<_>>(), record));
+            } else {
+                let glyph = glyphs.into_iter().next().unwrap();
+                let format = record.format() & !ValueFormat::ANY_DEVICE_OR_VARIDX;
+                unique.entry(format).or_default().push((glyph, record));
+            }
+        }
+
+        let mut format_2 = Vec::new();
+        for (_, mut items) in unique {
+            if items.len() == 1 {
+                let (glyph, record) = items.pop().unwrap();
+                shared.push((vec![glyph], record));
+                continue;
+            }
+            items.sort_unstable_by_key(|(glyph, _)| *glyph);
+            let format = items.iter().fold(ValueFormat::empty(), |format, (_, rec)| {
+                format | rec.format()
+            });
+            let n_devices = (format & ValueFormat::ANY_DEVICE_OR_VARIDX)
+                .bits()
+                .count_ones() as usize;
+            let item_len = 2 + format.record_byte_len() + n_devices * DEVICE_LEN;
+            format_2.extend(
+                split_by_size(items, SINGLE_POS_2_HEADER_LEN, |_| item_len)
+                    .into_iter()
+                    .map(|items| (format, items)),
+            );
+        }
+
+        // the subtables have disjoint coverage; order them by first glyph
+        shared.sort_unstable_by_key(|(glyphs, _)| glyphs[0]);
+        let mut result = Vec::with_capacity(shared.len() + format_2.len());
+        for (glyphs, record) in shared {
+            let coverage = CoverageTableBuilder::from_glyphs(glyphs).build();
+            result.push(SinglePos::format_1(coverage, record.build(var_store)));
+        }
+        for (format, items) in format_2 {
+            let (glyphs, records): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .map(|(glyph, rec)| (glyph, rec.build_with_format(format, var_store)))
+                .unzip();
+            let coverage = CoverageTableBuilder::from_glyphs(glyphs).build();
+            result.push(SinglePos::format_2(coverage, records));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+
+    use crate::tables::{
+        gpos::builders::Metric,
+        layout::{builders::RemapVariationIndices, DeviceOrVariationIndex},
+        variations::{RegionAxisCoordinates, VariationRegion},
+    };
+
+    use super::*;
+
+    fn x_advance(val: impl Into<Metric>) -> ValueRecordBuilder {
+        ValueRecordBuilder::new().with_x_advance(val)
+    }
+
+    #[test]
+    fn shared_values_use_format_1() {
+        let mut builder = SinglePosBuilder::default();
+        for gid in 1..=5 {
+            builder.insert(GlyphId::new(gid), x_advance(20));
+        }
+        builder.insert(GlyphId::new(10), x_advance(30));
+        builder.insert(GlyphId::new(11), x_advance(40));
+        builder.insert(GlyphId::new(12), x_advance(50).with_y_advance(5));
+
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert_eq!(subtables.len(), 3);
+        let SinglePos::Format1(table) = &subtables[0] else {
+            panic!("expected format 1, got {:?}", subtables[0]);
+        };
+        assert_eq!(table.coverage.len(), 5);
+        assert_eq!(table.value_record.x_advance, Some(20));
+        // the only record with this format
+        let SinglePos::Format1(table) = &subtables[1] else {
+            panic!("expected format 1, got {:?}", subtables[1]);
+        };
+        assert_eq!(table.value_record.y_advance, Some(5));
+        let SinglePos::Format2(table) = &subtables[2] else {
+            panic!("expected format 2, got {:?}", subtables[2]);
+        };
+        let advances = table
+            .value_records
+            .iter()
+            .map(|rec| rec.x_advance)
+            .collect::<Vec<_>>();
+        assert_eq!(advances, [Some(30), Some(40)]);
+    }
+
+    #[test]
+    fn variable_values() {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates {
+            start_coord: F2Dot14::from_f32(0.0),
+            peak_coord: F2Dot14::from_f32(1.0),
+            end_coord: F2Dot14::from_f32(1.0),
+        }]);
+        let mut builder = SinglePosBuilder::default();
+        builder.insert(GlyphId::new(1), x_advance(10));
+        builder.insert(
+            GlyphId::new(2),
+            x_advance(Metric::new(20, vec![(region, 8)])),
+        );
+        let mut var_store = VariationStoreBuilder::new();
+        let mut subtables = builder.build(&mut var_store);
+        let (_, key_map) = var_store.build();
+        subtables.remap_variation_indices(&key_map);
+
+        assert_eq!(subtables.len(), 1);
+        let SinglePos::Format2(table) = &subtables[0] else {
+            panic!("expected format 2, got {:?}", subtables[0]);
+        };
+        let format = ValueFormat::X_ADVANCE | ValueFormat::X_ADVANCE_DEVICE;
+        assert!(table.value_records.iter().all(|rec| rec.format() == format));
+        assert!(table.value_records[0].x_advance_device.is_none());
+        assert!(matches!(
+            table.value_records[1].x_advance_device.as_ref(),
+            Some(DeviceOrVariationIndex::VariationIndex(_))
+        ));
+    }
+
+    #[test]
+    fn large_subtables_are_split() {
+        let mut builder = SinglePosBuilder::default();
+        for gid in 0..20_000u16 {
+            builder.insert(GlyphId::new(gid), x_advance(gid as i16));
+        }
+        let subtables = builder.build(&mut VariationStoreBuilder::new());
+        assert_eq!(subtables.len(), 2);
+        let n_glyphs = subtables
+            .iter()
+            .map(|sub| match sub {
+                SinglePos::Format2(table) => {
+                    assert!(crate::dump_table(table).is_ok());
+                    table.value_records.len()
+                }
+                _ => panic!("expected format 2"),
+            })
+            .sum::<usize>();
+        assert_eq!(n_glyphs, 20_000);
+    }
+}
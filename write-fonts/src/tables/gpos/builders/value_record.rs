@@ -0,0 +1,152 @@
+//! Building value records with variable values
+
+use crate::tables::{
+    gpos::{ValueFormat, ValueRecord},
+    layout::{builders::Builder, DeviceOrVariationIndex, PendingVariationIndex},
+    variations::{ivs_builder::VariationStoreBuilder, VariationRegion},
+};
+
+/// A value that may vary across the designspace.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Metric {
+    /// The value at the default location.
+    pub default: i16,
+    /// The difference from the default value in each region where it varies.
+    pub deltas: Vec<(VariationRegion, i16)>,
+}
+
+/// A builder for a [`ValueRecord`], where each value may be variable.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValueRecordBuilder {
+    pub x_placement: Option<Metric>,
+    pub y_placement: Option<Metric>,
+    pub x_advance: Option<Metric>,
+    pub y_advance: Option<Metric>,
+}
+
+impl Metric {
+    pub fn new(default: i16, deltas: Vec<(VariationRegion, i16)>) -> Self {
+        Self { default, deltas }
+    }
+
+    /// Returns `true` if any of the deltas are non-zero.
+    pub fn is_variable(&self) -> bool {
+        self.deltas.iter().any(|(_, delta)| *delta != 0)
+    }
+
+    pub(crate) fn build_device(
+        &self,
+        var_store: &mut VariationStoreBuilder,
+    ) -> Option<DeviceOrVariationIndex> {
+        self.is_variable().then(|| {
+            let id = var_store.add_deltas(self.deltas.clone());
+            DeviceOrVariationIndex::PendingVariationIndex(PendingVariationIndex::new(id))
+        })
+    }
+}
+
+impl From<i16> for Metric {
+    fn from(default: i16) -> Self {
+        Self::new(default, Vec::new())
+    }
+}
+
+impl ValueRecordBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_x_placement(mut self, val: impl Into<Metric>) -> Self {
+        self.x_placement = Some(val.into());
+        self
+    }
+
+    pub fn with_y_placement(mut self, val: impl Into<Metric>) -> Self {
+        self.y_placement = Some(val.into());
+        self
+    }
+
+    pub fn with_x_advance(mut self, val: impl Into<Metric>) -> Self {
+        self.x_advance = Some(val.into());
+        self
+    }
+
+    pub fn with_y_advance(mut self, val: impl Into<Metric>) -> Self {
+        self.y_advance = Some(val.into());
+        self
+    }
+
+    /// The [`ValueFormat`] of the built record.
+    ///
+    /// This includes the device flags for any variable values.
+    pub fn format(&self) -> ValueFormat {
+        let mut format = ValueFormat::empty();
+        for (metric, flag, device_flag) in [
+            (
+                &self.x_placement,
+                ValueFormat::X_PLACEMENT,
+                ValueFormat::X_PLACEMENT_DEVICE,
+            ),
+            (
+                &self.y_placement,
+                ValueFormat::Y_PLACEMENT,
+                ValueFormat::Y_PLACEMENT_DEVICE,
+            ),
+            (
+                &self.x_advance,
+                ValueFormat::X_ADVANCE,
+                ValueFormat::X_ADVANCE_DEVICE,
+            ),
+            (
+                &self.y_advance,
+                ValueFormat::Y_ADVANCE,
+                ValueFormat::Y_ADVANCE_DEVICE,
+            ),
+        ] {
+            if let Some(metric) = metric {
+                format |= flag;
+                if metric.is_variable() {
+                    format |= device_flag;
+                }
+            }
+        }
+        format
+    }
+
+    /// Build the record, using the provided format.
+    ///
+    /// All records in a subtable must have the same format, which may include
+    /// fields that are not set in this record.
+    pub(crate) fn build_with_format(
+        self,
+        format: ValueFormat,
+        var_store: &mut VariationStoreBuilder,
+    ) -> ValueRecord {
+        self.build(var_store).with_explicit_value_format(format)
+    }
+}
+
+impl Builder for ValueRecordBuilder {
+    type Output = ValueRecord;
+
+    fn build(self, var_store: &mut VariationStoreBuilder) -> ValueRecord {
+        let mut record = ValueRecord::new();
+        if let Some(metric) = &self.x_placement {
+            record.x_placement = Some(metric.default);
+            record.x_placement_device = metric.build_device(var_store).into();
+        }
+        if let Some(metric) = &self.y_placement {
+            record.y_placement = Some(metric.default);
+            record.y_placement_device = metric.build_device(var_store).into();
+        }
+        if let Some(metric) = &self.x_advance {
+            record.x_advance = Some(metric.default);
+            record.x_advance_device = metric.build_device(var_store).into();
+        }
+        if let Some(metric) = &self.y_advance {
+            record.y_advance = Some(metric.default);
+            record.y_advance_device = metric.build_device(var_store).into();
+        }
+        record
+    }
+}
@@ -0,0 +1,373 @@
+//! Builders for GSUB subtables.
+//!
+//! These accept glyph-level rules, and handle choosing subtable formats,
+//! building coverage tables, and splitting rules into several subtables when
+//! they would not fit in one.
+
+use std::collections::BTreeMap;
+
+use font_types::GlyphId;
+
+use crate::tables::{
+    layout::{
+        builders::{split_by_size, Builder},
+        CoverageTableBuilder,
+    },
+    variations::ivs_builder::VariationStoreBuilder,
+};
+
+use super::{
+    AlternateSet, AlternateSubstFormat1, Ligature, LigatureSet, LigatureSubstFormat1,
+    MultipleSubstFormat1, Sequence, SingleSubst,
+};
+
+/// The size of a subtable header with a format, a coverage offset and a count.
+const SUBTABLE_HEADER_LEN: usize = 6;
+/// The size added to a subtable by each covered glyph: the coverage entry,
+/// and an offset to (or value for) the glyph's rule.
+const PER_GLYPH_LEN: usize = 4;
+
+/// A builder for [`SingleSubst`] subtables.
+///
+/// A format 1 subtable is used if all substitutions share the same glyph id
+/// delta; otherwise format 2 is used.
+#[derive(Clone, Debug, Default)]
+pub struct SingleSubBuilder {
+    items: BTreeMap<GlyphId, GlyphId>,
+}
+
+impl SingleSubBuilder {
+    /// Add a substitution, replacing any existing substitution for `target`.
+    pub fn insert(&mut self, target: GlyphId, replacement: GlyphId) {
+        self.items.insert(target, replacement);
+    }
+
+    /// Returns `true` if a substitution exists for this glyph.
+    pub fn contains(&self, target: GlyphId) -> bool {
+        self.items.contains_key(&target)
+    }
+
+    /// Returns `true` if there are no substitutions.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The shared delta of all substitutions, if they have one.
+    fn shared_delta(&self) -> Option<i16> {
+        let mut deltas = self
+            .items
+            .iter()
+            .map(|(target, replacement)| replacement.to_u16().wrapping_sub(target.to_u16()) as i16);
+        let first = deltas.next()?;
+        deltas.all(|delta| delta == first).then_some(first)
+    }
+}
+
+impl Builder for SingleSubBuilder {
+    type Output = Vec<SingleSubst>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        if let Some(delta) = self.shared_delta() {
+            let coverage = CoverageTableBuilder::from_glyphs(self.items.into_keys().collect());
+            return vec![SingleSubst::format_1(coverage.build(), delta)];
+        }
+        split_by_size(self.items, SUBTABLE_HEADER_LEN, |_| PER_GLYPH_LEN)
+            .into_iter()
+            .map(|items| {
+                let (targets, replacements): (Vec<_>, Vec<_>) = items.into_iter().unzip();
+                let coverage = CoverageTableBuilder::from_glyphs(targets);
+                SingleSubst::format_2(coverage.build(), replacements)
+            })
+            .collect()
+    }
+}
+
+/// A builder for [`MultipleSubstFormat1`] subtables.
+#[derive(Clone, Debug, Default)]
+pub struct MultipleSubBuilder {
+    items: BTreeMap<GlyphId, Vec<GlyphId>>,
+}
+
+impl MultipleSubBuilder {
+    /// Add a substitution, replacing any existing substitution for `target`.
+    pub fn insert(&mut self, target: GlyphId, replacement: Vec<GlyphId>) {
+        self.items.insert(target, replacement);
+    }
+
+    /// Returns `true` if a substitution exists for this glyph.
+    pub fn contains(&self, target: GlyphId) -> bool {
+        self.items.contains_key(&target)
+    }
+
+    /// Returns `true` if there are no substitutions.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Builder for MultipleSubBuilder {
+    type Output = Vec<MultipleSubstFormat1>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        split_by_size(self.items, SUBTABLE_HEADER_LEN, |(_, seq)| {
+            PER_GLYPH_LEN + glyph_array_len(seq)
+        })
+        .into_iter()
+        .map(|items| {
+            let (targets, sequences): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .map(|(target, seq)| (target, Sequence::new(seq)))
+                .unzip();
+            let coverage = CoverageTableBuilder::from_glyphs(targets);
+            MultipleSubstFormat1::new(coverage.build(), sequences)
+        })
+        .collect()
+    }
+}
+
+/// A builder for [`AlternateSubstFormat1`] subtables.
+#[derive(Clone, Debug, Default)]
+pub struct AlternateSubBuilder {
+    items: BTreeMap<GlyphId, Vec<GlyphId>>,
+}
+
+impl AlternateSubBuilder {
+    /// Add a set of alternates, replacing any existing set for `target`.
+    pub fn insert(&mut self, target: GlyphId, alternates: Vec<GlyphId>) {
+        self.items.insert(target, alternates);
+    }
+
+    /// Returns `true` if there are alternates for this glyph.
+    pub fn contains(&self, target: GlyphId) -> bool {
+        self.items.contains_key(&target)
+    }
+
+    /// Returns `true` if there are no substitutions.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Builder for AlternateSubBuilder {
+    type Output = Vec<AlternateSubstFormat1>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        split_by_size(self.items, SUBTABLE_HEADER_LEN, |(_, alts)| {
+            PER_GLYPH_LEN + glyph_array_len(alts)
+        })
+        .into_iter()
+        .map(|items| {
+            let (targets, alternate_sets): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .map(|(target, alts)| (target, AlternateSet::new(alts)))
+                .unzip();
+            let coverage = CoverageTableBuilder::from_glyphs(targets);
+            AlternateSubstFormat1::new(coverage.build(), alternate_sets)
+        })
+        .collect()
+    }
+}
+
+/// A builder for [`LigatureSubstFormat1`] subtables.
+///
+/// Within each ligature set, ligatures with more components are placed
+/// first, so that the longest matching sequence is used.
+#[derive(Clone, Debug, Default)]
+pub struct LigatureSubBuilder {
+    items: BTreeMap<GlyphId, Vec<(Vec<GlyphId>, GlyphId)>>,
+}
+
+impl LigatureSubBuilder {
+    /// Add a ligature, replacing the sequence of glyphs in `target`.
+    ///
+    /// If this sequence already has a ligature, the existing one is kept.
+    pub fn insert(&mut self, target: Vec<GlyphId>, replacement: GlyphId) {
+        let Some((first, rest)) = target.split_first() else {
+            return;
+        };
+        let ligatures = self.items.entry(*first).or_default();
+        if !ligatures.iter().any(|(components, _)| components == rest) {
+            ligatures.push((rest.to_owned(), replacement));
+        }
+    }
+
+    /// Returns `true` if there is a ligature for this sequence.
+    pub fn contains(&self, target: &[GlyphId]) -> bool {
+        let Some((first, rest)) = target.split_first() else {
+            return false;
+        };
+        self.items
+            .get(first)
+            .map(|ligatures| ligatures.iter().any(|(components, _)| components == rest))
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if there are no ligatures.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Builder for LigatureSubBuilder {
+    type Output = Vec<LigatureSubstFormat1>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        let ligature_set_len = |ligatures: &[(Vec<GlyphId>, GlyphId)]| {
+            // the ligature count, and for each ligature an offset and a table
+            // with the glyph, a count and the components
+            2 + ligatures
+                .iter()
+                .map(|(components, _)| 2 + 2 + glyph_array_len(components))
+                .sum::<usize>()
+        };
+        split_by_size(self.items, SUBTABLE_HEADER_LEN, |(_, ligatures)| {
+            PER_GLYPH_LEN + ligature_set_len(ligatures)
+        })
+        .into_iter()
+        .map(|items| {
+            let (targets, ligature_sets): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .map(|(first, mut ligatures)| {
+                    // stable, so that ligatures of the same length stay in the
+                    // order they were added
+                    ligatures.sort_by_key(|(components, _)| std::cmp::Reverse(components.len()));
+                    let ligatures = ligatures
+                        .into_iter()
+                        .map(|(components, glyph)| Ligature::new(glyph, components))
+                        .collect();
+                    (first, LigatureSet::new(ligatures))
+                })
+                .unzip();
+            let coverage = CoverageTableBuilder::from_glyphs(targets);
+            LigatureSubstFormat1::new(coverage.build(), ligature_sets)
+        })
+        .collect()
+    }
+}
+
+/// The size of a table containing a count and an array of glyph ids.
+fn glyph_array_len(glyphs: &[GlyphId]) -> usize {
+    2 + glyphs.len() * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{tables::gsub as rgsub, FontData, FontRead};
+
+    use crate::tables::layout::{Lookup, LookupFlag, LookupList};
+
+    use super::*;
+
+    fn gids(ids: impl IntoIterator<Item = u16>) -> Vec<GlyphId> {
+        ids.into_iter().map(GlyphId::new).collect()
+    }
+
+    fn build<B: Builder>(builder: B) -> B::Output {
+        builder.build(&mut VariationStoreBuilder::new())
+    }
+
+    #[test]
+    fn single_sub_format() {
+        let mut builder = SingleSubBuilder::default();
+        builder.insert(GlyphId::new(5), GlyphId::new(25));
+        builder.insert(GlyphId::new(9), GlyphId::new(29));
+        let subtables = build(builder.clone());
+        assert_eq!(subtables.len(), 1);
+        let SingleSubst::Format1(table) = &subtables[0] else {
+            panic!("expected format 1");
+        };
+        assert_eq!(table.delta_glyph_id, 20);
+
+        // a negative delta, wrapping around
+        let mut wrapping = SingleSubBuilder::default();
+        wrapping.insert(GlyphId::new(20), GlyphId::new(5));
+        wrapping.insert(GlyphId::new(30), GlyphId::new(15));
+        let SingleSubst::Format1(table) = &build(wrapping)[0] else {
+            panic!("expected format 1");
+        };
+        assert_eq!(table.delta_glyph_id, -15);
+
+        builder.insert(GlyphId::new(7), GlyphId::new(8));
+        let subtables = build(builder);
+        let SingleSubst::Format2(table) = &subtables[0] else {
+            panic!("expected format 2");
+        };
+        assert_eq!(table.substitute_glyph_ids, gids([25, 8, 29]));
+    }
+
+    #[test]
+    fn multiple_and_alternate() {
+        let mut builder = MultipleSubBuilder::default();
+        builder.insert(GlyphId::new(10), gids([1, 2]));
+        builder.insert(GlyphId::new(3), gids([4, 5, 6]));
+        assert!(builder.contains(GlyphId::new(3)));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        assert_eq!(
+            subtables[0].coverage.iter().collect::<Vec<_>>(),
+            gids([3, 10])
+        );
+        assert_eq!(
+            subtables[0].sequences[0].substitute_glyph_ids,
+            gids([4, 5, 6])
+        );
+
+        let mut builder = AlternateSubBuilder::default();
+        builder.insert(GlyphId::new(10), gids([11, 12, 13]));
+        let subtables = build(builder);
+        assert_eq!(
+            subtables[0].alternate_sets[0].alternate_glyph_ids,
+            gids([11, 12, 13])
+        );
+    }
+
+    #[test]
+    fn ligatures_sorted_by_length() {
+        let mut builder = LigatureSubBuilder::default();
+        // f f -> ff, f i -> fi, f f i -> ffi
+        builder.insert(gids([1, 1]), GlyphId::new(10));
+        builder.insert(gids([1, 2]), GlyphId::new(11));
+        builder.insert(gids([1, 1, 2]), GlyphId::new(12));
+        // duplicate, ignored
+        builder.insert(gids([1, 2]), GlyphId::new(13));
+        builder.insert(gids([3, 2]), GlyphId::new(14));
+        assert!(builder.contains(&gids([1, 1, 2])));
+        assert!(!builder.contains(&gids([1, 3])));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        let ligatures = &subtables[0].ligature_sets[0].ligatures;
+        let ligatures = ligatures
+            .iter()
+            .map(|lig| (lig.component_glyph_ids.clone(), lig.ligature_glyph.to_u16()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ligatures,
+            [(gids([1, 2]), 12), (gids([1]), 10), (gids([2]), 11)]
+        );
+        assert_eq!(subtables[0].ligature_sets.len(), 2);
+    }
+
+    #[test]
+    fn split_large_subtables() {
+        // long ligatures for many first glyphs; too large for one subtable
+        let mut builder = LigatureSubBuilder::default();
+        for first in 0..500u16 {
+            for i in 0..10u16 {
+                let components = std::iter::once(first).chain((0..10).map(|j| 1000 + i * 10 + j));
+                builder.insert(gids(components), GlyphId::new(2000 + first));
+            }
+        }
+        let subtables = build(builder);
+        assert!(subtables.len() > 1);
+        let n_sets = subtables
+            .iter()
+            .map(|sub| sub.ligature_sets.len())
+            .sum::<usize>();
+        assert_eq!(n_sets, 500);
+
+        let lookup = Lookup::new(LookupFlag::empty(), subtables, 0);
+        let bytes = crate::dump_table(&LookupList::new(vec![lookup])).unwrap();
+        let lookups = rgsub::SubstitutionLookupList::read(FontData::new(&bytes)).unwrap();
+        assert_eq!(lookups.lookup_count(), 1);
+    }
+}
@@ -79,6 +79,96 @@ pub fn iup_delta_optimize(
     Ok(result)
 }
 
+/// For the outline given in `coords`, with contour endpoints given `ends`,
+/// infer the deltas of the points that have no explicit delta.
+///
+/// This is the inverse of [`iup_delta_optimize`]: each contour without any
+/// explicit delta is unchanged, and other missing deltas are interpolated
+/// from their neighbours. Points after the last contour (such as phantom
+/// points) have no neighbours, and a missing delta for them is zero.
+///
+/// See:
+/// * <https://github.com/fonttools/fonttools/blob/6a13bdc2e668334b04466b288d31179df1cff7be/Lib/fontTools/varLib/iup.py#L95>
+pub fn iup_delta(
+    deltas: &[Option<Vec2>],
+    coords: &[Point],
+    contour_ends: &[usize],
+) -> Result<Vec<Vec2>, IupError> {
+    if deltas.len() != coords.len() {
+        return Err(IupError::DeltaCoordLengthMismatch {
+            num_deltas: deltas.len(),
+            num_coords: coords.len(),
+        });
+    }
+    let mut contour_ends = contour_ends.to_vec();
+    contour_ends.sort();
+    if let Some(last) = contour_ends.last().filter(|end| **end >= coords.len()) {
+        return Err(IupError::CoordEndsMismatch {
+            num_coords: coords.len(),
+            expected_num_coords: last + 1,
+        });
+    }
+
+    let mut result = Vec::with_capacity(coords.len());
+    let mut start = 0;
+    for end in contour_ends {
+        result.extend(iup_contour(&deltas[start..=end], &coords[start..=end]));
+        start = end + 1;
+    }
+    result.extend(
+        deltas[start..]
+            .iter()
+            .map(|delta| delta.unwrap_or_default()),
+    );
+    Ok(result)
+}
+
+/// Interpolates the missing deltas of a single contour.
+///
+/// <https://github.com/fonttools/fonttools/blob/6a13bdc2e668334b04466b288d31179df1cff7be/Lib/fontTools/varLib/iup.py#L66>
+fn iup_contour(deltas: &[Option<Vec2>], coords: &[Point]) -> Vec<Vec2> {
+    let n = deltas.len();
+    let explicit: Vec<_> = (0..n).filter(|i| deltas[*i].is_some()).collect();
+    let (Some(first), Some(last)) = (explicit.first().copied(), explicit.last().copied()) else {
+        return vec![Vec2::default(); n];
+    };
+    let delta = |i: usize| deltas[i].unwrap_or_default();
+    let mut result = Vec::with_capacity(n);
+    if first != 0 {
+        result.extend(iup_segment(
+            &coords[..first],
+            coords[first],
+            delta(first),
+            coords[last],
+            delta(last),
+        ));
+    }
+    result.push(delta(first));
+    for pair in explicit.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        if end - start > 1 {
+            result.extend(iup_segment(
+                &coords[start + 1..end],
+                coords[start],
+                delta(start),
+                coords[end],
+                delta(end),
+            ));
+        }
+        result.push(delta(end));
+    }
+    if last != n - 1 {
+        result.extend(iup_segment(
+            &coords[last + 1..],
+            coords[last],
+            delta(last),
+            coords[first],
+            delta(first),
+        ));
+    }
+    result
+}
+
 #[derive(Clone, Debug)]
 pub enum IupError {
     DeltaCoordLengthMismatch {
@@ -1313,4 +1403,58 @@ mod tests {
 
         iup_delta_optimize(deltas, coords, 0.5, &contour_ends).unwrap();
     }
+
+    #[test]
+    fn interpolate_optimized_deltas() {
+        // a square, with a point on each side, and the phantom points
+        let coords: Vec<_> = [
+            (0.0, 0.0),
+            (50.0, 0.0),
+            (100.0, 0.0),
+            (100.0, 100.0),
+            (0.0, 100.0),
+            (0.0, 0.0),
+            (100.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 0.0),
+        ]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y))
+        .collect();
+        let deltas: Vec<_> = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (20.0, 0.0),
+            (20.0, 30.0),
+            (0.0, 30.0),
+            (0.0, 0.0),
+            (20.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 0.0),
+        ]
+        .into_iter()
+        .map(|(x, y)| Vec2::new(x, y))
+        .collect();
+        let contour_ends = [4];
+
+        let optimized =
+            iup_delta_optimize(deltas.clone(), coords.clone(), 0.5, &contour_ends).unwrap();
+        // the middle point can be interpolated
+        assert!(!optimized[1].required);
+        let sparse: Vec<_> = optimized
+            .iter()
+            .map(|delta| {
+                delta
+                    .required
+                    .then(|| Vec2::new(delta.x as _, delta.y as _))
+            })
+            .collect();
+        let interpolated = iup_delta(&sparse, &coords, &contour_ends).unwrap();
+        assert_eq!(interpolated, deltas);
+
+        // a contour without deltas is unchanged
+        let none = vec![None; coords.len()];
+        let interpolated = iup_delta(&none, &coords, &contour_ends).unwrap();
+        assert!(interpolated.iter().all(|delta| *delta == Vec2::ZERO));
+    }
 }
@@ -2,6 +2,40 @@
 
 include!("../../generated/generated_hmtx.rs");
 
+/// Expands long metrics and trailing side bearings to one metric per glyph.
+pub(crate) fn expand_metrics(
+    long: impl Iterator<Item = (u16, i16)>,
+    bearings: impl Iterator<Item = i16>,
+    num_glyphs: usize,
+) -> Vec<LongMetric> {
+    let mut metrics: Vec<_> = long
+        .map(|(advance, bearing)| LongMetric::new(advance, bearing))
+        .collect();
+    let last_advance = metrics
+        .last()
+        .map(|metric| metric.advance)
+        .unwrap_or_default();
+    metrics.extend(bearings.map(|bearing| LongMetric::new(last_advance, bearing)));
+    metrics.resize(num_glyphs, LongMetric::new(last_advance, 0));
+    metrics
+}
+
+/// Splits metrics into long metrics and trailing side bearings.
+///
+/// Trailing glyphs with the same advance as the last long metric only store
+/// their side bearing.
+pub(crate) fn pack_metrics(mut metrics: Vec<LongMetric>) -> (Vec<LongMetric>, Vec<i16>) {
+    let mut num_long = metrics.len();
+    while num_long > 1 && metrics[num_long - 1].advance == metrics[num_long - 2].advance {
+        num_long -= 1;
+    }
+    let bearings = metrics
+        .drain(num_long..)
+        .map(|metric| metric.side_bearing)
+        .collect();
+    (metrics, bearings)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -8,6 +8,8 @@ use std::{
 pub use read_fonts::tables::layout::LookupFlag;
 use read_fonts::FontRead;
 
+pub mod builders;
+
 #[cfg(test)]
 #[path = "../tests/layout.rs"]
 mod spec_tests;
@@ -308,7 +310,7 @@ impl ClassDef {
                 .and_then(|idx| table.class_value_array.get(idx as usize))
                 .copied(),
             ClassDef::Format2(table) => table.class_range_records.iter().find_map(|rec| {
-                (rec.start_glyph_id <= glyph && rec.end_glyph_id <= glyph).then_some(rec.class)
+                (rec.start_glyph_id <= glyph && rec.end_glyph_id >= glyph).then_some(rec.class)
             }),
         }
     }
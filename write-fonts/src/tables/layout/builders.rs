@@ -0,0 +1,108 @@
+//! Common traits for building layout tables.
+
+use crate::{
+    offsets::{NullableOffsetMarker, OffsetMarker},
+    tables::variations::ivs_builder::{VariationIndexRemapping, VariationStoreBuilder},
+};
+
+use super::{DeviceOrVariationIndex, Lookup, VariationIndex};
+
+mod contextual;
+mod feature_variations;
+mod features;
+
+pub use contextual::{ChainedSequenceContextBuilder, SequenceContextBuilder};
+pub use feature_variations::{ConditionBox, FeatureVariationsBuilder};
+pub use features::{LayoutHeaderBuilder, DEFAULT_LANGUAGE, DEFAULT_SCRIPT};
+
+/// A type that builds one or more layout tables.
+///
+/// Variable values are added to the provided [`VariationStoreBuilder`], and
+/// are written as [`PendingVariationIndex`][super::PendingVariationIndex]
+/// tables. Once the variation store has been built, these must be replaced
+/// with their final indices, using [`RemapVariationIndices`].
+pub trait Builder {
+    type Output;
+
+    /// Build the output, adding any variable values to `var_store`.
+    fn build(self, var_store: &mut VariationStoreBuilder) -> Self::Output;
+}
+
+/// A table that may contain [`PendingVariationIndex`][super::PendingVariationIndex]
+/// tables.
+pub trait RemapVariationIndices {
+    /// Replace each pending index with its final index in the variation store.
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping);
+}
+
+impl RemapVariationIndices for DeviceOrVariationIndex {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        if let DeviceOrVariationIndex::PendingVariationIndex(pending) = self {
+            let index = key_map
+                .get(pending.delta_set_id)
+                .expect("all pending indices are in the variation store");
+            *self = DeviceOrVariationIndex::VariationIndex(VariationIndex::new(
+                index.delta_set_outer_index,
+                index.delta_set_inner_index,
+            ));
+        }
+    }
+}
+
+impl<T: RemapVariationIndices> RemapVariationIndices for Vec<T> {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.iter_mut()
+            .for_each(|item| item.remap_variation_indices(key_map))
+    }
+}
+
+impl<T: RemapVariationIndices, const N: usize> RemapVariationIndices for OffsetMarker<T, N> {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.as_mut().remap_variation_indices(key_map)
+    }
+}
+
+impl<T: RemapVariationIndices, const N: usize> RemapVariationIndices
+    for NullableOffsetMarker<T, N>
+{
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        if let Some(table) = self.as_mut() {
+            table.remap_variation_indices(key_map)
+        }
+    }
+}
+
+impl<T: RemapVariationIndices> RemapVariationIndices for Lookup<T> {
+    fn remap_variation_indices(&mut self, key_map: &VariationIndexRemapping) {
+        self.subtables.remap_variation_indices(key_map)
+    }
+}
+
+/// Split items into groups that can each be written as a single subtable,
+/// without any 16-bit offset overflowing.
+///
+/// `header_len` is the size of the subtable without any items. `item_len`
+/// returns the size added by an item, including its entry in the coverage
+/// table and any tables it references.
+pub(crate) fn split_by_size<T>(
+    items: impl IntoIterator<Item = T>,
+    header_len: usize,
+    item_len: impl Fn(&T) -> usize,
+) -> Vec<Vec<T>> {
+    let mut groups = Vec::new();
+    let mut current = Vec::new();
+    let mut current_len = header_len;
+    for item in items {
+        let len = item_len(&item);
+        if !current.is_empty() && current_len + len > u16::MAX as usize {
+            groups.push(std::mem::take(&mut current));
+            current_len = header_len;
+        }
+        current_len += len;
+        current.push(item);
+    }
+    if !current.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
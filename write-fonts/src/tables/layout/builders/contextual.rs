@@ -0,0 +1,565 @@
+//! Building contextual and chained contextual subtables
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use font_types::GlyphId;
+
+use crate::{
+    tables::{
+        layout::{
+            ChainedClassSequenceRule, ChainedClassSequenceRuleSet, ChainedSequenceContext,
+            ChainedSequenceRule, ChainedSequenceRuleSet, ClassDef, ClassDefBuilder,
+            ClassSequenceRule, ClassSequenceRuleSet, CoverageTable, CoverageTableBuilder,
+            SequenceContext, SequenceLookupRecord, SequenceRule, SequenceRuleSet,
+        },
+        variations::ivs_builder::VariationStoreBuilder,
+    },
+    validate::Validate,
+    FontWrite,
+};
+
+use super::Builder;
+
+/// A contextual rule: a sequence of glyph sets to match, and the lookups to
+/// apply at positions in the input sequence.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ContextRule {
+    // in logical order, with the glyph closest to the input last
+    backtrack: Vec<BTreeSet<GlyphId>>,
+    input: Vec<BTreeSet<GlyphId>>,
+    lookahead: Vec<BTreeSet<GlyphId>>,
+    lookups: Vec<SequenceLookupRecord>,
+}
+
+/// Rules, grouped into the subtables requested by the user.
+#[derive(Clone, Debug, Default)]
+struct ContextRules {
+    subtables: Vec<Vec<ContextRule>>,
+    force_subtable_break: bool,
+}
+
+/// A builder for [`SequenceContext`] subtables.
+///
+/// Rules are matched in the order they are added. Each group of rules
+/// (separated by [`add_subtable_break`][Self::add_subtable_break]) is written
+/// in whichever of formats 1, 2 or 3 is smallest; format 1 requires that each
+/// position match a single glyph, and format 2 that the glyph sets used in
+/// the rules do not partially overlap.
+///
+/// The output can be converted into the GSUB or GPOS contextual lookup types.
+#[derive(Clone, Debug, Default)]
+pub struct SequenceContextBuilder(ContextRules);
+
+/// A builder for [`ChainedSequenceContext`] subtables.
+///
+/// This chooses formats in the same way as [`SequenceContextBuilder`], with
+/// separate class definitions for the backtrack, input and lookahead
+/// sequences.
+#[derive(Clone, Debug, Default)]
+pub struct ChainedSequenceContextBuilder(ContextRules);
+
+impl SequenceContextBuilder {
+    /// Add a rule matching the `input` sequence.
+    ///
+    /// Each lookup record refers to a position in `input`. Rules with an
+    /// empty glyph set can never match, and are ignored.
+    pub fn add_rule(&mut self, input: Vec<BTreeSet<GlyphId>>, lookups: Vec<SequenceLookupRecord>) {
+        self.0.add(ContextRule {
+            input,
+            lookups,
+            ..Default::default()
+        })
+    }
+
+    /// Start a new subtable for any rules added after this call.
+    pub fn add_subtable_break(&mut self) {
+        self.0.force_subtable_break = true;
+    }
+
+    /// Returns `true` if no rules have been added.
+    pub fn is_empty(&self) -> bool {
+        self.0.subtables.is_empty()
+    }
+}
+
+impl ChainedSequenceContextBuilder {
+    /// Add a rule matching the `input` sequence, with the given context.
+    ///
+    /// All sequences are in logical order; the last item in `backtrack` is
+    /// the glyph immediately preceding the input. Each lookup record refers
+    /// to a position in `input`. Rules with an empty glyph set can never
+    /// match, and are ignored.
+    pub fn add_rule(
+        &mut self,
+        backtrack: Vec<BTreeSet<GlyphId>>,
+        input: Vec<BTreeSet<GlyphId>>,
+        lookahead: Vec<BTreeSet<GlyphId>>,
+        lookups: Vec<SequenceLookupRecord>,
+    ) {
+        self.0.add(ContextRule {
+            backtrack,
+            input,
+            lookahead,
+            lookups,
+        })
+    }
+
+    /// Start a new subtable for any rules added after this call.
+    pub fn add_subtable_break(&mut self) {
+        self.0.force_subtable_break = true;
+    }
+
+    /// Returns `true` if no rules have been added.
+    pub fn is_empty(&self) -> bool {
+        self.0.subtables.is_empty()
+    }
+}
+
+impl Builder for SequenceContextBuilder {
+    type Output = Vec<SequenceContext>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        self.0.build()
+    }
+}
+
+impl Builder for ChainedSequenceContextBuilder {
+    type Output = Vec<ChainedSequenceContext>;
+
+    fn build(self, _var_store: &mut VariationStoreBuilder) -> Self::Output {
+        self.0.build()
+    }
+}
+
+impl ContextRules {
+    fn add(&mut self, rule: ContextRule) {
+        // a rule with an empty glyph set can never match
+        let sets = [&rule.backtrack, &rule.input, &rule.lookahead];
+        if rule.input.is_empty() || sets.iter().any(|seq| seq.iter().any(BTreeSet::is_empty)) {
+            return;
+        }
+        if std::mem::take(&mut self.force_subtable_break) || self.subtables.is_empty() {
+            self.subtables.push(Vec::new());
+        }
+        self.subtables.last_mut().unwrap().push(rule);
+    }
+
+    fn build<T: ContextSubtable>(self) -> Vec<T> {
+        self.subtables
+            .iter()
+            .flat_map(|rules| build_smallest(rules))
+            .collect()
+    }
+}
+
+/// A rule with its sequences encoded as glyphs or classes.
+///
+/// The backtrack sequence is reversed, and the input excludes the first item.
+struct EncodedRule<T> {
+    backtrack: Vec<T>,
+    input: Vec<T>,
+    lookahead: Vec<T>,
+    lookups: Vec<SequenceLookupRecord>,
+}
+
+/// The three formats shared by contextual and chained contextual subtables.
+trait ContextSubtable: FontWrite + Validate + Sized {
+    /// Rule sets are in coverage order.
+    fn format_1(coverage: CoverageTable, rule_sets: Vec<Vec<EncodedRule<GlyphId>>>) -> Self;
+
+    /// Class definitions are for the backtrack, input and lookahead
+    /// sequences; rule sets are indexed by the class of the first glyph.
+    fn format_2(
+        coverage: CoverageTable,
+        class_defs: [ClassDef; 3],
+        rule_sets: Vec<Vec<EncodedRule<u16>>>,
+    ) -> Self;
+
+    fn format_3(coverages: EncodedRule<CoverageTable>) -> Self;
+}
+
+/// Build a group of rules in each possible format, returning the smallest.
+fn build_smallest<T: ContextSubtable>(rules: &[ContextRule]) -> Vec<T> {
+    let candidates = [
+        build_format_1(rules).map(|table| vec![table]),
+        build_format_2(rules).map(|table| vec![table]),
+        Some(rules.iter().map(build_format_3).collect()),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .min_by_key(|tables| {
+            tables
+                .iter()
+                .map(|table| {
+                    crate::dump_table(table)
+                        .map(|bytes| bytes.len())
+                        .unwrap_or(usize::MAX)
+                })
+                .fold(0usize, usize::saturating_add)
+        })
+        .unwrap()
+}
+
+fn build_format_1<T: ContextSubtable>(rules: &[ContextRule]) -> Option<T> {
+    fn single_glyphs(sets: &[BTreeSet<GlyphId>]) -> Option<Vec<GlyphId>> {
+        sets.iter()
+            .map(|set| (set.len() == 1).then(|| *set.first().unwrap()))
+            .collect()
+    }
+
+    let mut rule_sets: BTreeMap<GlyphId, Vec<_>> = BTreeMap::new();
+    for rule in rules {
+        let mut backtrack = single_glyphs(&rule.backtrack)?;
+        backtrack.reverse();
+        let input = single_glyphs(&rule.input)?;
+        rule_sets.entry(input[0]).or_default().push(EncodedRule {
+            backtrack,
+            input: input[1..].to_vec(),
+            lookahead: single_glyphs(&rule.lookahead)?,
+            lookups: rule.lookups.clone(),
+        });
+    }
+    let coverage = CoverageTableBuilder::from_glyphs(rule_sets.keys().copied().collect()).build();
+    Some(T::format_1(coverage, rule_sets.into_values().collect()))
+}
+
+fn build_format_2<T: ContextSubtable>(rules: &[ContextRule]) -> Option<T> {
+    let backtrack = ClassAssignment::new(rules.iter().flat_map(|rule| &rule.backtrack))?;
+    let input = ClassAssignment::new(rules.iter().flat_map(|rule| &rule.input))?;
+    let lookahead = ClassAssignment::new(rules.iter().flat_map(|rule| &rule.lookahead))?;
+
+    let mut rule_sets: Vec<Vec<_>> = Vec::new();
+    for rule in rules {
+        let first = input.class(&rule.input[0])? as usize;
+        if rule_sets.len() <= first {
+            rule_sets.resize_with(first + 1, Vec::new);
+        }
+        rule_sets[first].push(EncodedRule {
+            backtrack: rule
+                .backtrack
+                .iter()
+                .rev()
+                .map(|set| backtrack.class(set))
+                .collect::<Option<_>>()?,
+            input: rule.input[1..]
+                .iter()
+                .map(|set| input.class(set))
+                .collect::<Option<_>>()?,
+            lookahead: rule
+                .lookahead
+                .iter()
+                .map(|set| lookahead.class(set))
+                .collect::<Option<_>>()?,
+            lookups: rule.lookups.clone(),
+        });
+    }
+    let coverage = rules
+        .iter()
+        .flat_map(|rule| rule.input[0].iter().copied())
+        .collect::<CoverageTableBuilder>()
+        .build();
+    Some(T::format_2(
+        coverage,
+        [
+            backtrack.class_def(),
+            input.class_def(),
+            lookahead.class_def(),
+        ],
+        rule_sets,
+    ))
+}
+
+fn build_format_3<T: ContextSubtable>(rule: &ContextRule) -> T {
+    let coverage = |set: &BTreeSet<GlyphId>| {
+        CoverageTableBuilder::from_glyphs(set.iter().copied().collect()).build()
+    };
+    T::format_3(EncodedRule {
+        backtrack: rule.backtrack.iter().rev().map(coverage).collect(),
+        input: rule.input.iter().map(coverage).collect(),
+        lookahead: rule.lookahead.iter().map(coverage).collect(),
+        lookups: rule.lookups.clone(),
+    })
+}
+
+/// An assignment of glyph sets to classes, for one class definition.
+struct ClassAssignment<'a> {
+    classes: HashMap<&'a BTreeSet<GlyphId>, u16>,
+}
+
+impl<'a> ClassAssignment<'a> {
+    /// Returns `None` if any two sets overlap without being identical.
+    fn new(sets: impl Iterator<Item = &'a BTreeSet<GlyphId>>) -> Option<Self> {
+        let mut glyphs: HashMap<GlyphId, &BTreeSet<GlyphId>> = HashMap::new();
+        let mut distinct = Vec::new();
+        for set in sets {
+            let mut is_new = false;
+            for glyph in set {
+                match glyphs.get(glyph) {
+                    Some(existing) if *existing != set => return None,
+                    Some(_) => (),
+                    None => {
+                        glyphs.insert(*glyph, set);
+                        is_new = true;
+                    }
+                }
+            }
+            if is_new {
+                distinct.push(set);
+            }
+        }
+        // larger classes first; class 0 is reserved for unlisted glyphs
+        distinct.sort_by_key(|set| (std::cmp::Reverse(set.len()), set.first()));
+        let classes = distinct
+            .into_iter()
+            .enumerate()
+            .map(|(i, set)| (set, i as u16 + 1))
+            .collect();
+        Some(Self { classes })
+    }
+
+    /// Returns `None` for a set that was not assigned a class.
+    fn class(&self, set: &BTreeSet<GlyphId>) -> Option<u16> {
+        self.classes.get(set).copied()
+    }
+
+    fn class_def(&self) -> ClassDef {
+        self.classes
+            .iter()
+            .flat_map(|(set, class)| set.iter().map(|gid| (*gid, *class)))
+            .collect::<ClassDefBuilder>()
+            .build()
+    }
+}
+
+impl ContextSubtable for SequenceContext {
+    fn format_1(coverage: CoverageTable, rule_sets: Vec<Vec<EncodedRule<GlyphId>>>) -> Self {
+        let rule_sets = rule_sets
+            .into_iter()
+            .map(|rules| {
+                Some(SequenceRuleSet::new(
+                    rules
+                        .into_iter()
+                        .map(|rule| SequenceRule::new(rule.input, rule.lookups))
+                        .collect(),
+                ))
+            })
+            .collect();
+        SequenceContext::format_1(coverage, rule_sets)
+    }
+
+    fn format_2(
+        coverage: CoverageTable,
+        [_, class_def, _]: [ClassDef; 3],
+        rule_sets: Vec<Vec<EncodedRule<u16>>>,
+    ) -> Self {
+        let rule_sets = rule_sets
+            .into_iter()
+            .map(|rules| {
+                (!rules.is_empty()).then(|| {
+                    ClassSequenceRuleSet::new(
+                        rules
+                            .into_iter()
+                            .map(|rule| ClassSequenceRule::new(rule.input, rule.lookups))
+                            .collect(),
+                    )
+                })
+            })
+            .collect();
+        SequenceContext::format_2(coverage, class_def, rule_sets)
+    }
+
+    fn format_3(coverages: EncodedRule<CoverageTable>) -> Self {
+        SequenceContext::format_3(coverages.input, coverages.lookups)
+    }
+}
+
+impl ContextSubtable for ChainedSequenceContext {
+    fn format_1(coverage: CoverageTable, rule_sets: Vec<Vec<EncodedRule<GlyphId>>>) -> Self {
+        let rule_sets = rule_sets
+            .into_iter()
+            .map(|rules| {
+                Some(ChainedSequenceRuleSet::new(
+                    rules
+                        .into_iter()
+                        .map(|rule| {
+                            ChainedSequenceRule::new(
+                                rule.backtrack,
+                                rule.input,
+                                rule.lookahead,
+                                rule.lookups,
+                            )
+                        })
+                        .collect(),
+                ))
+            })
+            .collect();
+        ChainedSequenceContext::format_1(coverage, rule_sets)
+    }
+
+    fn format_2(
+        coverage: CoverageTable,
+        [backtrack, input, lookahead]: [ClassDef; 3],
+        rule_sets: Vec<Vec<EncodedRule<u16>>>,
+    ) -> Self {
+        let rule_sets = rule_sets
+            .into_iter()
+            .map(|rules| {
+                (!rules.is_empty()).then(|| {
+                    ChainedClassSequenceRuleSet::new(
+                        rules
+                            .into_iter()
+                            .map(|rule| {
+                                ChainedClassSequenceRule::new(
+                                    rule.backtrack,
+                                    rule.input,
+                                    rule.lookahead,
+                                    rule.lookups,
+                                )
+                            })
+                            .collect(),
+                    )
+                })
+            })
+            .collect();
+        ChainedSequenceContext::format_2(coverage, backtrack, input, lookahead, rule_sets)
+    }
+
+    fn format_3(coverages: EncodedRule<CoverageTable>) -> Self {
+        ChainedSequenceContext::format_3(
+            coverages.backtrack,
+            coverages.input,
+            coverages.lookahead,
+            coverages.lookups,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ids: impl IntoIterator<Item = u16>) -> BTreeSet<GlyphId> {
+        ids.into_iter().map(GlyphId::new).collect()
+    }
+
+    fn lookup(index: u16) -> Vec<SequenceLookupRecord> {
+        vec![SequenceLookupRecord::new(0, index)]
+    }
+
+    fn build<B: Builder>(builder: B) -> B::Output {
+        builder.build(&mut VariationStoreBuilder::new())
+    }
+
+    #[test]
+    fn glyph_rules_use_format_1() {
+        let mut builder = ChainedSequenceContextBuilder::default();
+        for i in 0..5 {
+            builder.add_rule(
+                vec![set([1]), set([2])],
+                vec![set([10 + i]), set([20])],
+                vec![set([30])],
+                lookup(i),
+            );
+        }
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        let ChainedSequenceContext::Format1(table) = &subtables[0] else {
+            panic!("expected format 1, got {:?}", subtables[0]);
+        };
+        let rule = &table.chained_seq_rule_sets[0]
+            .as_ref()
+            .unwrap()
+            .chained_seq_rules[0];
+        // the backtrack is stored in reverse order
+        assert_eq!(rule.backtrack_sequence, [GlyphId::new(2), GlyphId::new(1)]);
+        assert_eq!(rule.input_sequence, [GlyphId::new(20)]);
+        assert_eq!(rule.lookahead_sequence, [GlyphId::new(30)]);
+    }
+
+    #[test]
+    fn class_rules_use_format_2() {
+        let letters = set(10..40);
+        let marks = set(50..60);
+        let mut builder = ChainedSequenceContextBuilder::default();
+        builder.add_rule(
+            vec![letters.clone()],
+            vec![marks.clone(), marks.clone()],
+            vec![],
+            lookup(0),
+        );
+        builder.add_rule(
+            vec![],
+            vec![marks.clone()],
+            vec![letters.clone()],
+            lookup(1),
+        );
+        builder.add_rule(
+            vec![marks.clone()],
+            vec![letters.clone()],
+            vec![],
+            lookup(2),
+        );
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        let ChainedSequenceContext::Format2(table) = &subtables[0] else {
+            panic!("expected format 2, got {:?}", subtables[0]);
+        };
+        assert_eq!(table.coverage.len(), 40);
+        // letters are the larger class
+        assert_eq!(table.input_class_def.get(GlyphId::new(10)), 1);
+        let mark_rules = table.chained_class_seq_rule_sets[2].as_ref().unwrap();
+        assert_eq!(mark_rules.chained_class_seq_rules.len(), 2);
+        assert_eq!(mark_rules.chained_class_seq_rules[0].input_sequence, [2]);
+        assert!(table.chained_class_seq_rule_sets[0].is_none());
+    }
+
+    #[test]
+    fn overlapping_classes_use_format_3() {
+        let mut builder = ChainedSequenceContextBuilder::default();
+        builder.add_rule(vec![], vec![set(10..20)], vec![set([1])], lookup(0));
+        builder.add_rule(vec![], vec![set(15..25)], vec![set([2])], lookup(1));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 2);
+        assert!(subtables
+            .iter()
+            .all(|sub| matches!(sub, ChainedSequenceContext::Format3(_))));
+    }
+
+    #[test]
+    fn single_rule_prefers_format_3() {
+        let mut builder = SequenceContextBuilder::default();
+        builder.add_rule(vec![set(10..20), set([5])], lookup(0));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 1);
+        assert!(matches!(subtables[0], SequenceContext::Format3(_)));
+    }
+
+    #[test]
+    fn empty_sets_are_ignored() {
+        let mut builder = ChainedSequenceContextBuilder::default();
+        builder.add_rule(vec![set([])], vec![set([1])], vec![], lookup(0));
+        builder.add_rule(vec![], vec![set([1])], vec![set([])], lookup(0));
+        builder.add_rule(vec![], vec![set([1]), set([])], vec![], lookup(0));
+        assert!(builder.is_empty());
+        builder.add_rule(vec![set([2])], vec![set([1])], vec![], lookup(0));
+        assert_eq!(build(builder).len(), 1);
+    }
+
+    #[test]
+    fn subtable_breaks() {
+        let mut builder = SequenceContextBuilder::default();
+        builder.add_rule(vec![set([1]), set([2])], lookup(0));
+        builder.add_rule(vec![set([3]), set([2])], lookup(0));
+        builder.add_subtable_break();
+        builder.add_rule(vec![set([4]), set([2])], lookup(0));
+        builder.add_rule(vec![set([5]), set([2])], lookup(0));
+        builder.add_rule(vec![set([6]), set([2])], lookup(0));
+        let subtables = build(builder);
+        assert_eq!(subtables.len(), 2);
+        let SequenceContext::Format1(table) = &subtables[1] else {
+            panic!("expected format 1, got {:?}", subtables[1]);
+        };
+        assert_eq!(table.seq_rule_sets.len(), 3);
+    }
+}
@@ -0,0 +1,341 @@
+//! Building feature variations from possibly overlapping regions
+//!
+//! This is adapted from the `featureVars` module in fontTools:
+//! <https://github.com/fonttools/fonttools/blob/main/Lib/fontTools/varLib/featureVars.py>
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use font_types::{F2Dot14, Tag};
+
+use crate::tables::layout::{
+    ConditionFormat1, ConditionSet, Feature, FeatureList, FeatureTableSubstitution,
+    FeatureTableSubstitutionRecord, FeatureVariationRecord, FeatureVariations,
+};
+
+/// A box in the normalized design space: the range of each constrained axis,
+/// by axis index.
+///
+/// Axes that are not included are unconstrained.
+pub type ConditionBox = BTreeMap<u16, (F2Dot14, F2Dot14)>;
+
+/// A builder for [`FeatureVariations`].
+///
+/// Each rule adds lookups to a feature (usually `rvrn`) within a region of
+/// the design space, described as the union of one or more boxes. Where the
+/// regions of several rules overlap, the lookups of all of them are applied;
+/// to express this, overlapping regions are split into boxes that each have
+/// their own condition set, ordered so that the most specific box (the one
+/// with the most rules) matches first.
+#[derive(Clone, Debug)]
+pub struct FeatureVariationsBuilder {
+    feature: Tag,
+    rules: Vec<(Vec<ConditionBox>, BTreeSet<u16>)>,
+}
+
+impl FeatureVariationsBuilder {
+    /// Create a builder for variations of the feature with this tag.
+    pub fn new(feature: Tag) -> Self {
+        Self {
+            feature,
+            rules: Vec::new(),
+        }
+    }
+
+    /// The tag of the feature whose lookups vary.
+    pub fn feature(&self) -> Tag {
+        self.feature
+    }
+
+    /// Add a rule, applying `lookups` within the union of `region`.
+    pub fn add_rule(&mut self, region: Vec<ConditionBox>, lookups: impl IntoIterator<Item = u16>) {
+        self.rules.push((region, lookups.into_iter().collect()));
+    }
+
+    /// Returns `true` if no rules have been added.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Build the feature variations for the features in `feature_list`.
+    ///
+    /// Each feature with this builder's tag is replaced, within each box, by
+    /// a feature with the additional lookups for that box. Returns `None` if
+    /// there are no rules.
+    pub fn build(&self, feature_list: &FeatureList) -> Option<FeatureVariations> {
+        let boxes = self.overlay();
+        if boxes.is_empty() {
+            return None;
+        }
+        let features = feature_list
+            .feature_records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.feature_tag == self.feature)
+            .map(|(i, record)| (i as u16, &*record.feature))
+            .collect::<Vec<_>>();
+        let records = boxes
+            .into_iter()
+            .map(|(condition_box, lookups)| {
+                let conditions = condition_box
+                    .into_iter()
+                    .map(|(axis, (min, max))| ConditionFormat1::new(axis, min, max))
+                    .collect();
+                let substitutions = features
+                    .iter()
+                    .map(|(index, feature)| {
+                        let mut indices = feature.lookup_list_indices.clone();
+                        indices.extend(lookups.iter().copied());
+                        indices.sort_unstable();
+                        indices.dedup();
+                        let params = feature.feature_params.as_ref().cloned();
+                        FeatureTableSubstitutionRecord::new(*index, Feature::new(params, indices))
+                    })
+                    .collect();
+                FeatureVariationRecord::new(
+                    Some(ConditionSet::new(conditions)),
+                    Some(FeatureTableSubstitution::new(substitutions)),
+                )
+            })
+            .collect();
+        Some(FeatureVariations::new(records))
+    }
+
+    /// Split the regions of the rules into disjoint boxes, each with the
+    /// lookups of all the rules that apply there.
+    ///
+    /// Boxes are ordered by the number of rules that apply, most first.
+    fn overlay(&self) -> Vec<(ConditionBox, BTreeSet<u16>)> {
+        let rules = merge_rules(&self.rules);
+
+        // each box, with the indices of the rules that apply within it
+        let mut boxes = vec![(ConditionBox::new(), BTreeSet::new())];
+        for (i, (region, _)) in rules.iter().enumerate() {
+            let mut next = BoxMap::default();
+            next.insert(ConditionBox::new(), &BTreeSet::new());
+            for (bot, rank) in &boxes {
+                for top in region {
+                    let (intersection, remainder) = overlay_box(top, bot);
+                    if let Some(intersection) = intersection {
+                        let mut rank = rank.clone();
+                        rank.insert(i);
+                        next.insert(intersection, &rank);
+                    }
+                    if let Some(remainder) = remainder {
+                        next.insert(remainder, rank);
+                    }
+                }
+            }
+            boxes = next.items;
+        }
+
+        // this sort is stable, so boxes with the same rank keep their order
+        boxes.sort_by_key(|(_, rank)| std::cmp::Reverse(rank.len()));
+        boxes
+            .into_iter()
+            .filter(|(_, rank)| !rank.is_empty())
+            .map(|(condition_box, rank)| {
+                let lookups = rank
+                    .iter()
+                    .flat_map(|i| rules[*i].1.iter().copied())
+                    .collect();
+                (condition_box, lookups)
+            })
+            .collect()
+    }
+}
+
+/// Boxes in insertion order, with the union of the ranks of each box.
+#[derive(Default)]
+struct BoxMap {
+    items: Vec<(ConditionBox, BTreeSet<usize>)>,
+    indices: HashMap<ConditionBox, usize>,
+}
+
+impl BoxMap {
+    fn insert(&mut self, condition_box: ConditionBox, rank: &BTreeSet<usize>) {
+        match self.indices.get(&condition_box) {
+            Some(i) => self.items[*i].1.extend(rank.iter().copied()),
+            None => {
+                self.indices.insert(condition_box.clone(), self.items.len());
+                self.items.push((condition_box, rank.clone()));
+            }
+        }
+    }
+}
+
+/// Merge rules with the same lookups, and then rules with the same region.
+fn merge_rules(
+    rules: &[(Vec<ConditionBox>, BTreeSet<u16>)],
+) -> Vec<(Vec<ConditionBox>, BTreeSet<u16>)> {
+    let mut by_lookups: Vec<(Vec<ConditionBox>, &BTreeSet<u16>)> = Vec::new();
+    for (region, lookups) in rules {
+        match by_lookups
+            .iter_mut()
+            .find(|(_, existing)| *existing == lookups)
+        {
+            Some((existing, _)) => existing.extend(region.iter().cloned()),
+            None => by_lookups.push((region.clone(), lookups)),
+        }
+    }
+
+    // ordered by the last occurrence of each region
+    let mut by_region: Vec<(Vec<ConditionBox>, BTreeSet<u16>)> = Vec::new();
+    for (region, lookups) in by_lookups.into_iter().rev() {
+        let mut region = region.into_iter().map(cleanup_box).collect::<Vec<_>>();
+        region.sort();
+        match by_region
+            .iter_mut()
+            .find(|(existing, _)| *existing == region)
+        {
+            Some((_, existing)) => existing.extend(lookups.iter().copied()),
+            None => by_region.push((region, lookups.clone())),
+        }
+    }
+    by_region.reverse();
+    by_region
+}
+
+/// Remove axes whose range is the full normalized range.
+fn cleanup_box(condition_box: ConditionBox) -> ConditionBox {
+    let full = (F2Dot14::from_f32(-1.0), F2Dot14::from_f32(1.0));
+    condition_box
+        .into_iter()
+        .filter(|(_, range)| *range != full)
+        .collect()
+}
+
+/// Overlay the `top` box on the `bot` box.
+///
+/// Returns the intersection of the boxes, if they intersect, and the
+/// remainder of `bot`, if any. The remainder may not be exact, since the
+/// true remainder may not be a box, but it always includes it.
+fn overlay_box(
+    top: &ConditionBox,
+    bot: &ConditionBox,
+) -> (Option<ConditionBox>, Option<ConditionBox>) {
+    let mut intersection = top.clone();
+    intersection.extend(bot.iter().map(|(axis, range)| (*axis, *range)));
+    for (axis, (min1, max1)) in top {
+        if let Some((min2, max2)) = bot.get(axis) {
+            let min = *min1.max(min2);
+            let max = *max1.min(max2);
+            if min >= max {
+                return (None, Some(bot.clone()));
+            }
+            intersection.insert(*axis, (min, max));
+        }
+    }
+
+    // the remainder is empty if each of bot's ranges lies within the
+    // intersection, and can be shrunk if exactly one range extends past
+    // the intersection on one side; otherwise it is all of bot.
+    let mut remainder = bot.clone();
+    let mut extruding = top.keys().any(|axis| !bot.contains_key(axis));
+    let mut fully_inside = !extruding;
+    for (axis, (min2, max2)) in bot {
+        if !top.contains_key(axis) {
+            continue;
+        }
+        let (min1, max1) = intersection[axis];
+        if min1 <= *min2 && *max2 <= max1 {
+            continue;
+        }
+        if extruding {
+            return (Some(intersection), Some(bot.clone()));
+        }
+        extruding = true;
+        fully_inside = false;
+        let range = if min1 <= *min2 {
+            (max1.max(*min2), *max2)
+        } else if *max2 <= max1 {
+            (*min2, min1.min(*max2))
+        } else {
+            return (Some(intersection), Some(bot.clone()));
+        };
+        remainder.insert(*axis, range);
+    }
+
+    if fully_inside {
+        (Some(intersection), None)
+    } else {
+        (Some(intersection), Some(remainder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: f32, max: f32) -> (F2Dot14, F2Dot14) {
+        (F2Dot14::from_f32(min), F2Dot14::from_f32(max))
+    }
+
+    fn condition_box(ranges: &[(u16, f32, f32)]) -> ConditionBox {
+        ranges
+            .iter()
+            .map(|(axis, min, max)| (*axis, range(*min, *max)))
+            .collect()
+    }
+
+    #[test]
+    fn overlay_boxes() {
+        let top = condition_box(&[(0, 0.5, 1.0)]);
+        let bot = condition_box(&[(0, 0.0, 1.0)]);
+        let (intersection, remainder) = overlay_box(&top, &bot);
+        assert_eq!(intersection, Some(top.clone()));
+        assert_eq!(remainder, Some(condition_box(&[(0, 0.0, 0.5)])));
+
+        // bot is inside top
+        let (intersection, remainder) = overlay_box(&bot, &top);
+        assert_eq!(intersection, Some(top.clone()));
+        assert_eq!(remainder, None);
+
+        // disjoint
+        let other = condition_box(&[(0, -1.0, 0.25)]);
+        assert_eq!(overlay_box(&top, &other), (None, Some(other.clone())));
+
+        // the remainder of a box extending past top on two axes is not a box
+        let top = condition_box(&[(0, 0.5, 1.0), (1, 0.5, 1.0)]);
+        let bot = condition_box(&[(0, 0.0, 1.0), (1, 0.0, 1.0)]);
+        let (intersection, remainder) = overlay_box(&top, &bot);
+        assert_eq!(intersection, Some(top));
+        assert_eq!(remainder, Some(bot));
+    }
+
+    #[test]
+    fn overlapping_rules_are_split() {
+        let mut builder = FeatureVariationsBuilder::new(Tag::new(b"rvrn"));
+        // wght >= 0.5, and wdth >= 0.5
+        builder.add_rule(vec![condition_box(&[(0, 0.5, 1.0)])], [1]);
+        builder.add_rule(vec![condition_box(&[(1, 0.5, 1.0)])], [2]);
+        let boxes = builder.overlay();
+        assert_eq!(
+            boxes,
+            [
+                (
+                    condition_box(&[(0, 0.5, 1.0), (1, 0.5, 1.0)]),
+                    [1, 2].into()
+                ),
+                (condition_box(&[(1, 0.5, 1.0)]), [2].into()),
+                (condition_box(&[(0, 0.5, 1.0)]), [1].into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_are_merged() {
+        let mut builder = FeatureVariationsBuilder::new(Tag::new(b"rvrn"));
+        builder.add_rule(vec![condition_box(&[(0, 0.5, 1.0)])], [1]);
+        builder.add_rule(vec![condition_box(&[(0, -1.0, -0.5)])], [1]);
+        // the full range is the same as no condition
+        builder.add_rule(vec![condition_box(&[(0, -1.0, -0.5), (1, -1.0, 1.0)])], [3]);
+        let boxes = builder.overlay();
+        assert_eq!(
+            boxes,
+            [
+                (condition_box(&[(0, -1.0, -0.5)]), [1, 3].into()),
+                (condition_box(&[(0, 0.5, 1.0)]), [1].into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Building the script and feature lists of GSUB and GPOS
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use font_types::Tag;
+
+use crate::tables::{
+    gpos::{Gpos, PositionLookup},
+    gsub::{Gsub, SubstitutionLookup},
+    layout::{
+        Feature, FeatureList, FeatureParams, FeatureRecord, FeatureVariations, LangSys,
+        LangSysRecord, LookupList, Script, ScriptList, ScriptRecord,
+    },
+};
+
+use super::FeatureVariationsBuilder;
+
+/// The tag of the default language system of a script.
+pub const DEFAULT_LANGUAGE: Tag = Tag::new(b"dflt");
+/// The tag of the default script.
+pub const DEFAULT_SCRIPT: Tag = Tag::new(b"DFLT");
+
+/// A builder for the [`ScriptList`] and [`FeatureList`] of a GSUB or GPOS
+/// table.
+///
+/// Features are added for a language system (a script and a language), with
+/// the indices of their lookups in the lookup list. Unless excluded with
+/// [`exclude_default`][Self::exclude_default], each language also includes
+/// the features of its script's default language (`dflt`).
+///
+/// Features with the same tag, lookups and parameters are shared between
+/// language systems.
+///
+/// Feature variations may also be added; their feature is then included in
+/// every language system, even if it has no lookups by default.
+#[derive(Clone, Debug, Default)]
+pub struct LayoutHeaderBuilder {
+    systems: BTreeMap<(Tag, Tag), LangSysFeatures>,
+    feature_params: BTreeMap<Tag, FeatureParams>,
+    feature_variations: Option<FeatureVariationsBuilder>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct LangSysFeatures {
+    features: BTreeMap<Tag, BTreeSet<u16>>,
+    required: Option<Tag>,
+    exclude_default: bool,
+}
+
+/// A feature table, identified by its contents.
+type FeatureKey = (Tag, Vec<u16>, Option<FeatureParams>);
+
+impl LayoutHeaderBuilder {
+    /// Add a language system, which may have no features.
+    pub fn add_language_system(&mut self, script: Tag, language: Tag) {
+        self.systems.entry((script, language)).or_default();
+    }
+
+    /// Add lookups to a feature in a language system.
+    ///
+    /// Lookups are identified by their index in the lookup list, and are
+    /// always applied in lookup list order.
+    pub fn add_feature(
+        &mut self,
+        script: Tag,
+        language: Tag,
+        feature: Tag,
+        lookups: impl IntoIterator<Item = u16>,
+    ) {
+        self.systems
+            .entry((script, language))
+            .or_default()
+            .features
+            .entry(feature)
+            .or_default()
+            .extend(lookups);
+    }
+
+    /// Set the required feature of a language system.
+    pub fn set_required_feature(&mut self, script: Tag, language: Tag, feature: Tag) {
+        let system = self.systems.entry((script, language)).or_default();
+        system.features.entry(feature).or_default();
+        system.required = Some(feature);
+    }
+
+    /// Don't include the features of the script's default language in this
+    /// language system.
+    pub fn exclude_default(&mut self, script: Tag, language: Tag) {
+        self.systems
+            .entry((script, language))
+            .or_default()
+            .exclude_default = true;
+    }
+
+    /// Set the parameters used by all features with this tag.
+    pub fn set_feature_params(&mut self, feature: Tag, params: FeatureParams) {
+        self.feature_params.insert(feature, params);
+    }
+
+    /// Set the feature variations, replacing any existing variations.
+    pub fn set_feature_variations(&mut self, variations: FeatureVariationsBuilder) {
+        self.feature_variations = Some(variations);
+    }
+
+    /// Returns `true` if no features have been added.
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Build the script and feature lists.
+    ///
+    /// If there are feature variations, their feature is included in every
+    /// language system; use [`build_feature_variations`] to build the
+    /// variations themselves.
+    ///
+    /// [`build_feature_variations`]: Self::build_feature_variations
+    pub fn build(&self) -> (ScriptList, FeatureList) {
+        let variations = self
+            .feature_variations
+            .as_ref()
+            .filter(|variations| !variations.is_empty());
+        let mut keys = self.systems.keys().copied().collect::<Vec<_>>();
+        if keys.is_empty() && variations.is_some() {
+            keys.push((DEFAULT_SCRIPT, DEFAULT_LANGUAGE));
+        }
+        let systems = keys
+            .into_iter()
+            .map(|key| {
+                let mut system = self.resolve(key);
+                if let Some(variations) = variations {
+                    system.features.entry(variations.feature()).or_default();
+                }
+                (key, system)
+            })
+            .collect::<Vec<_>>();
+
+        let features = systems
+            .iter()
+            .flat_map(|(_, system)| {
+                system
+                    .features
+                    .iter()
+                    .map(|(tag, lookups)| self.feature_key(*tag, lookups))
+            })
+            .collect::<BTreeSet<_>>();
+        let feature_indices = features
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key, i as u16))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut scripts: BTreeMap<Tag, Script> = BTreeMap::new();
+        for ((script, language), system) in &systems {
+            let index_of =
+                |tag: &Tag| feature_indices[&self.feature_key(*tag, &system.features[tag])];
+            let mut lang_sys = LangSys::new(system.features.keys().map(index_of).collect());
+            lang_sys.feature_indices.sort_unstable();
+            if let Some(required) = &system.required {
+                lang_sys.required_feature_index = index_of(required);
+            }
+            let script = scripts.entry(*script).or_default();
+            if *language == DEFAULT_LANGUAGE {
+                script.default_lang_sys = lang_sys.into();
+            } else {
+                // languages are visited in tag order
+                script
+                    .lang_sys_records
+                    .push(LangSysRecord::new(*language, lang_sys));
+            }
+        }
+
+        let script_list = ScriptList::new(
+            scripts
+                .into_iter()
+                .map(|(tag, script)| ScriptRecord::new(tag, script))
+                .collect(),
+        );
+        let feature_list = FeatureList::new(
+            features
+                .into_iter()
+                .map(|(tag, lookups, params)| {
+                    FeatureRecord::new(tag, Feature::new(params, lookups))
+                })
+                .collect(),
+        );
+        (script_list, feature_list)
+    }
+
+    /// Build the feature variations for a `feature_list` returned by
+    /// [`build`](Self::build).
+    pub fn build_feature_variations(
+        &self,
+        feature_list: &FeatureList,
+    ) -> Option<FeatureVariations> {
+        self.feature_variations
+            .as_ref()
+            .filter(|variations| !variations.is_empty())
+            .and_then(|variations| variations.build(feature_list))
+    }
+
+    /// Build a complete GSUB table with these features.
+    pub fn build_gsub(&self, lookups: Vec<SubstitutionLookup>) -> Gsub {
+        let (script_list, feature_list) = self.build();
+        let feature_variations = self.build_feature_variations(&feature_list);
+        let mut gsub = Gsub::new(script_list, feature_list, LookupList::new(lookups));
+        gsub.feature_variations = feature_variations.into();
+        gsub
+    }
+
+    /// Build a complete GPOS table with these features.
+    pub fn build_gpos(&self, lookups: Vec<PositionLookup>) -> Gpos {
+        let (script_list, feature_list) = self.build();
+        let feature_variations = self.build_feature_variations(&feature_list);
+        let mut gpos = Gpos::new(script_list, feature_list, LookupList::new(lookups));
+        gpos.feature_variations = feature_variations.into();
+        gpos
+    }
+
+    /// The features of a language system, including any inherited from the
+    /// script's default language.
+    fn resolve(&self, (script, language): (Tag, Tag)) -> LangSysFeatures {
+        let mut system = self.systems[&(script, language)].clone();
+        if language == DEFAULT_LANGUAGE || system.exclude_default {
+            return system;
+        }
+        if let Some(default) = self.systems.get(&(script, DEFAULT_LANGUAGE)) {
+            for (tag, lookups) in &default.features {
+                system
+                    .features
+                    .entry(*tag)
+                    .or_default()
+                    .extend(lookups.iter().copied());
+            }
+            system.required = system.required.or(default.required);
+        }
+        system
+    }
+
+    fn feature_key(&self, tag: Tag, lookups: &BTreeSet<u16>) -> FeatureKey {
+        (
+            tag,
+            lookups.iter().copied().collect(),
+            self.feature_params.get(&tag).cloned(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use read_fonts::{FontData, FontRead};
+
+    use super::*;
+
+    const LATN: Tag = Tag::new(b"latn");
+    const TRK: Tag = Tag::new(b"TRK ");
+    const DEU: Tag = Tag::new(b"DEU ");
+    const KERN: Tag = Tag::new(b"kern");
+    const LIGA: Tag = Tag::new(b"liga");
+    const LOCL: Tag = Tag::new(b"locl");
+
+    fn feature_tags(list: &FeatureList, lang_sys: &LangSys) -> Vec<(Tag, Vec<u16>)> {
+        lang_sys
+            .feature_indices
+            .iter()
+            .map(|idx| {
+                let record = &list.feature_records[*idx as usize];
+                (
+                    record.feature_tag,
+                    record.feature.lookup_list_indices.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_language_is_inherited() {
+        let mut builder = LayoutHeaderBuilder::default();
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LIGA, [0]);
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LOCL, [1]);
+        builder.add_feature(LATN, TRK, LOCL, [2]);
+        builder.add_feature(LATN, DEU, LOCL, [3]);
+        builder.exclude_default(LATN, DEU);
+
+        let (scripts, features) = builder.build();
+        assert_eq!(scripts.script_records.len(), 1);
+        let script = &scripts.script_records[0].script;
+        let default = script.default_lang_sys.as_ref().unwrap();
+        assert_eq!(
+            feature_tags(&features, default),
+            [(LIGA, vec![0]), (LOCL, vec![1])]
+        );
+
+        let tags = script
+            .lang_sys_records
+            .iter()
+            .map(|rec| rec.lang_sys_tag)
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [DEU, TRK]);
+        let deu = &script.lang_sys_records[0].lang_sys;
+        assert_eq!(feature_tags(&features, deu), [(LOCL, vec![3])]);
+        let trk = &script.lang_sys_records[1].lang_sys;
+        assert_eq!(
+            feature_tags(&features, trk),
+            [(LIGA, vec![0]), (LOCL, vec![1, 2])]
+        );
+    }
+
+    #[test]
+    fn features_are_sorted_and_shared() {
+        let mut builder = LayoutHeaderBuilder::default();
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LIGA, [3, 1]);
+        builder.add_feature(DEFAULT_SCRIPT, DEFAULT_LANGUAGE, LIGA, [1, 3, 1]);
+        builder.add_feature(DEFAULT_SCRIPT, DEFAULT_LANGUAGE, KERN, [0]);
+        builder.add_feature(LATN, TRK, LIGA, [4]);
+
+        let (scripts, features) = builder.build();
+        let tags = features
+            .feature_records
+            .iter()
+            .map(|rec| rec.feature_tag)
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [KERN, LIGA, LIGA]);
+        assert_eq!(
+            features.feature_records[1].feature.lookup_list_indices,
+            [1, 3]
+        );
+
+        let tags = scripts
+            .script_records
+            .iter()
+            .map(|rec| rec.script_tag)
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [DEFAULT_SCRIPT, LATN]);
+        let dflt = scripts.script_records[0].script.default_lang_sys.as_ref();
+        let latn = scripts.script_records[1].script.default_lang_sys.as_ref();
+        assert_eq!(dflt.unwrap().feature_indices, [0, 1]);
+        assert_eq!(latn.unwrap().feature_indices, [1]);
+    }
+
+    #[test]
+    fn required_feature() {
+        let mut builder = LayoutHeaderBuilder::default();
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LIGA, [0]);
+        builder.set_required_feature(LATN, TRK, LOCL);
+        builder.add_language_system(DEFAULT_SCRIPT, DEFAULT_LANGUAGE);
+
+        let (scripts, features) = builder.build();
+        let dflt = scripts.script_records[0].script.default_lang_sys.as_ref();
+        assert!(dflt.unwrap().feature_indices.is_empty());
+        let trk = &scripts.script_records[1].script.lang_sys_records[0].lang_sys;
+        let required = &features.feature_records[trk.required_feature_index as usize];
+        assert_eq!(required.feature_tag, LOCL);
+        let latn = scripts.script_records[1].script.default_lang_sys.as_ref();
+        assert_eq!(latn.unwrap().required_feature_index, 0xFFFF);
+    }
+
+    #[test]
+    fn build_gsub() {
+        let mut builder = LayoutHeaderBuilder::default();
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LIGA, [0]);
+        let gsub = builder.build_gsub(vec![SubstitutionLookup::Single(Default::default())]);
+        let bytes = crate::dump_table(&gsub).unwrap();
+        let gsub = read_fonts::tables::gsub::Gsub::read(FontData::new(&bytes)).unwrap();
+        assert_eq!(gsub.feature_list().unwrap().feature_records().len(), 1);
+        assert_eq!(gsub.lookup_list().unwrap().lookup_count(), 1);
+    }
+
+    #[test]
+    fn feature_variations() {
+        const RVRN: Tag = Tag::new(b"rvrn");
+        let mut builder = LayoutHeaderBuilder::default();
+        builder.add_feature(LATN, DEFAULT_LANGUAGE, LIGA, [0]);
+        builder.add_feature(LATN, TRK, RVRN, [1]);
+        let mut variations = FeatureVariationsBuilder::new(RVRN);
+        let heavy = [(0, (F2Dot14::from_f32(0.6), F2Dot14::from_f32(1.0)))];
+        variations.add_rule(vec![heavy.into()], [2]);
+        builder.set_feature_variations(variations);
+
+        let gsub = builder.build_gsub(Vec::new());
+        // rvrn is added to the default language, without lookups
+        let tags = gsub
+            .feature_list
+            .feature_records
+            .iter()
+            .map(|rec| (rec.feature_tag, rec.feature.lookup_list_indices.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [(LIGA, vec![0]), (RVRN, vec![]), (RVRN, vec![1])]);
+        let variations = gsub.feature_variations.as_ref().unwrap();
+        assert_eq!(variations.feature_variation_records.len(), 1);
+        let record = &variations.feature_variation_records[0];
+        let conditions = &record.condition_set.as_ref().unwrap().conditions;
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].filter_range_min_value, F2Dot14::from_f32(0.6));
+        let substitutions = &record.feature_table_substitution.as_ref().unwrap();
+        let alternates = substitutions
+            .substitutions
+            .iter()
+            .map(|sub| {
+                (
+                    sub.feature_index,
+                    sub.alternate_feature.lookup_list_indices.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(alternates, [(1, vec![2]), (2, vec![1, 2])]);
+
+        let bytes = crate::dump_table(&gsub).unwrap();
+        let gsub = read_fonts::tables::gsub::Gsub::read(FontData::new(&bytes)).unwrap();
+        assert_eq!(gsub.version(), font_types::MajorMinor::VERSION_1_1);
+        assert!(gsub.feature_variations().is_some());
+    }
+}
@@ -0,0 +1,291 @@
+//! Structures and encodings shared by the [CFF] and [CFF2] tables.
+//!
+//! [CFF]: https://learn.microsoft.com/en-us/typography/opentype/spec/cff
+//! [CFF2]: https://learn.microsoft.com/en-us/typography/opentype/spec/cff2
+
+include!("../../generated/generated_postscript.rs");
+
+pub mod charstring;
+mod dict;
+pub(crate) mod font;
+mod subrs;
+
+pub use charstring::{Charstring, CharstringBuilder};
+pub use dict::PrivateDict;
+
+pub(crate) use dict::DictWriter;
+pub(crate) use subrs::{subroutinize, Subroutines};
+
+/// The escape byte for two-byte DICT and charstring operators.
+pub(crate) const ESCAPE: u8 = 12;
+
+/// Returns a two-byte operator, stored as `ESCAPE << 8 | op`.
+pub(crate) const fn escaped(op: u8) -> u16 {
+    (ESCAPE as u16) << 8 | op as u16
+}
+
+/// An error that occurred while compiling a CFF or CFF2 table.
+#[derive(Clone, Debug)]
+pub enum CffError {
+    /// Charstrings that were combined into a variable charstring did not
+    /// have the same structure.
+    IncompatibleCharstrings,
+    /// A variable charstring was used in a table without variations.
+    UnexpectedVariations,
+    /// A charstring has deltas for a different number of regions than the
+    /// item variation data of its Private DICT.
+    RegionCountMismatch { expected: usize, found: usize },
+    /// A charstring operator needs more operands than the argument stack
+    /// can hold.
+    StackOverflow,
+    /// A hint mask refers to a stem that does not exist.
+    InvalidStemIndex(usize),
+    /// A glyph refers to a Font DICT that does not exist.
+    InvalidFontDict(usize),
+    /// A glyph was added with the wrong method for the kind of font being
+    /// built (name-keyed or CID-keyed).
+    WrongKeying,
+    /// The table has more glyphs, strings or Font DICTs than can be stored.
+    TooMany(&'static str),
+    /// The table could not be written.
+    Write(crate::error::Error),
+}
+
+impl std::fmt::Display for CffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CffError::IncompatibleCharstrings => {
+                write!(f, "Charstrings do not have compatible structures")
+            }
+            CffError::UnexpectedVariations => {
+                write!(f, "Variable charstrings are only supported in CFF2")
+            }
+            CffError::RegionCountMismatch { expected, found } => write!(
+                f,
+                "Charstring has deltas for {found} regions, but its variation data has {expected}"
+            ),
+            CffError::StackOverflow => write!(f, "Charstring operands overflow the stack"),
+            CffError::InvalidStemIndex(index) => {
+                write!(f, "Hint mask refers to missing stem {index}")
+            }
+            CffError::InvalidFontDict(index) => {
+                write!(f, "Glyph refers to missing Font DICT {index}")
+            }
+            CffError::WrongKeying => write!(
+                f,
+                "Name-keyed and CID-keyed glyphs cannot be mixed in one font"
+            ),
+            CffError::TooMany(what) => write!(f, "Too many {what}"),
+            CffError::Write(error) => write!(f, "Failed to write table: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CffError {}
+
+impl From<crate::error::Error> for CffError {
+    fn from(error: crate::error::Error) -> Self {
+        CffError::Write(error)
+    }
+}
+
+/// The encoding of numbers in charstrings or DICTs.
+#[derive(Clone, Copy)]
+pub(crate) enum NumberEncoding {
+    Charstring,
+    Dict,
+}
+
+impl NumberEncoding {
+    pub(crate) fn write_number(self, out: &mut Vec<u8>, value: f64) {
+        if value.fract() == 0.0 && (i16::MIN as f64..=i16::MAX as f64).contains(&value) {
+            let value = value as i32;
+            match value {
+                -107..=107 => out.push((value + 139) as u8),
+                108..=1131 => {
+                    let value = value - 108;
+                    out.extend([(value >> 8) as u8 + 247, value as u8]);
+                }
+                -1131..=-108 => {
+                    let value = -value - 108;
+                    out.extend([(value >> 8) as u8 + 251, value as u8]);
+                }
+                _ => {
+                    out.push(28);
+                    out.extend_from_slice(&(value as i16).to_be_bytes());
+                }
+            }
+            return;
+        }
+        match self {
+            NumberEncoding::Charstring => {
+                out.push(255);
+                out.extend_from_slice(&Fixed::from_f64(value).to_bits().to_be_bytes());
+            }
+            NumberEncoding::Dict if value.fract() == 0.0 => {
+                out.push(29);
+                out.extend_from_slice(&(value as i32).to_be_bytes());
+            }
+            NumberEncoding::Dict => write_real(out, value),
+        }
+    }
+
+    /// The `blend` operator of a CFF2 charstring or Private DICT.
+    pub(crate) fn blend_op(self) -> u8 {
+        match self {
+            NumberEncoding::Charstring => 16,
+            NumberEncoding::Dict => 23,
+        }
+    }
+}
+
+/// Writes a DICT real number, as a string of nibbles.
+fn write_real(out: &mut Vec<u8>, value: f64) {
+    // six decimal places are more than enough for any DICT value
+    let text = format!("{}", (value * 1e6).round() / 1e6);
+    let mut nibbles: Vec<u8> = text
+        .bytes()
+        .map(|byte| match byte {
+            b'.' => 0xA,
+            b'-' => 0xE,
+            digit => digit - b'0',
+        })
+        .collect();
+    nibbles.push(0xF);
+    if nibbles.len() % 2 == 1 {
+        nibbles.push(0xF);
+    }
+    out.push(30);
+    out.extend(nibbles.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+}
+
+/// Writes a one or two-byte operator.
+pub(crate) fn write_op(out: &mut Vec<u8>, op: u16) {
+    if op > u8::MAX as u16 {
+        out.extend([ESCAPE, op as u8]);
+    } else {
+        out.push(op as u8);
+    }
+}
+
+/// Writes a CFF (`is_cff2 == false`) or CFF2 INDEX.
+pub(crate) fn write_index<'a>(items: impl IntoIterator<Item = &'a [u8]>, is_cff2: bool) -> Vec<u8> {
+    let items: Vec<_> = items.into_iter().collect();
+    let mut index = if is_cff2 {
+        (items.len() as u32).to_be_bytes().to_vec()
+    } else {
+        (items.len() as u16).to_be_bytes().to_vec()
+    };
+    if items.is_empty() {
+        return index;
+    }
+    let data_len: usize = items.iter().map(|item| item.len()).sum();
+    let off_size = match data_len + 1 {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x10000..=0xFFFFFF => 3,
+        _ => 4,
+    };
+    index.push(off_size as u8);
+    let mut offset = 1u32;
+    index.extend_from_slice(&offset.to_be_bytes()[4 - off_size..]);
+    for item in &items {
+        offset += item.len() as u32;
+        index.extend_from_slice(&offset.to_be_bytes()[4 - off_size..]);
+    }
+    for item in items {
+        index.extend_from_slice(item);
+    }
+    index
+}
+
+/// Writes a Private DICT followed by its local subroutines, if it has any.
+///
+/// Returns the size of the DICT, and the data.
+pub(crate) fn write_private_dict(
+    private_dict: &PrivateDict,
+    local_subrs: &[Vec<u8>],
+    is_cff2: bool,
+) -> (usize, Vec<u8>) {
+    let mut dict = private_dict.writer(is_cff2);
+    if !local_subrs.is_empty() {
+        // the offset is from the start of the DICT, and the entry is a five
+        // byte integer and the operator
+        let len = dict.len() + 6;
+        dict.offsets(dict::SUBRS, &[len]);
+    }
+    let mut data = dict.into_data();
+    let len = data.len();
+    if !local_subrs.is_empty() {
+        data.extend(write_index(local_subrs.iter().map(Vec::as_slice), is_cff2));
+    }
+    (len, data)
+}
+
+/// Returns the FDSelect for the Font DICT indices of each glyph, with the
+/// smaller of formats 0 and 3, or format 4 if there are more than 256
+/// Font DICTs.
+pub(crate) fn build_fd_select(fds: &[u16]) -> FdSelect {
+    let mut ranges: Vec<(u32, u16)> = Vec::new();
+    for (gid, fd) in fds.iter().enumerate() {
+        if ranges.last().map(|(_, last)| last) != Some(fd) {
+            ranges.push((gid as u32, *fd));
+        }
+    }
+    if fds.iter().any(|fd| *fd > u8::MAX as u16) {
+        let ranges = ranges
+            .into_iter()
+            .map(|(first, fd)| FdSelectRange4::new(first, fd))
+            .collect();
+        return FdSelect::format_4(ranges, fds.len() as u32);
+    }
+    // format 0 uses a byte per glyph, format 3 three bytes per range and a
+    // two byte sentinel
+    if fds.len() <= ranges.len() * 3 + 2 {
+        return FdSelect::format_0(fds.iter().map(|fd| *fd as u8).collect());
+    }
+    let ranges = ranges
+        .into_iter()
+        .map(|(first, fd)| FdSelectRange3::new(first as u16, fd as u8))
+        .collect();
+    FdSelect::format_3(ranges, fds.len() as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::{tables::postscript as read_ps, FontData};
+
+    use super::*;
+
+    #[test]
+    fn index_round_trip() {
+        let items: [&[u8]; 3] = [b"one", b"", &[7; 300]];
+        for is_cff2 in [false, true] {
+            let data = write_index(items, is_cff2);
+            let index = read_ps::Index::new(&data, is_cff2).unwrap();
+            assert_eq!(index.count(), 3);
+            assert_eq!(index.size_in_bytes().unwrap(), data.len());
+            for (i, item) in items.iter().enumerate() {
+                assert_eq!(index.get(i).unwrap(), *item);
+            }
+        }
+        assert_eq!(write_index([], false), [0, 0]);
+    }
+
+    #[test]
+    fn fd_select_formats() {
+        for (fds, format) in [
+            (vec![0, 1, 0, 1], 0),
+            (vec![0; 20].into_iter().chain([1; 20]).collect(), 3),
+            (vec![0, 0, 300, 300], 4),
+        ] {
+            let data = crate::dump_table(&build_fd_select(&fds)).unwrap();
+            assert_eq!(data[0], format);
+            let fd_select = read_ps::FdSelect::read(FontData::new(&data)).unwrap();
+            for (gid, fd) in fds.iter().enumerate() {
+                let gid = types::GlyphId::new(gid as u16);
+                assert_eq!(fd_select.font_index(gid), Some(*fd));
+            }
+        }
+    }
+}
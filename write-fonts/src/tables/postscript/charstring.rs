@@ -0,0 +1,803 @@
+//! Building Type 2 and CFF2 charstrings.
+//!
+//! A [`Charstring`] stores absolute coordinates, and is only converted to
+//! the relative operands of the charstring format when it is compiled. This
+//! makes it possible to combine the outlines of several masters into one
+//! variable charstring, with a `blend` for each operand that varies.
+
+use kurbo::{BezPath, Rect, Shape};
+use types::{Fixed, Pen};
+
+use super::{CffError, NumberEncoding};
+use crate::pens::write_to_pen;
+
+// charstring operators
+pub(super) const HSTEM: u8 = 1;
+pub(super) const VSTEM: u8 = 3;
+pub(super) const VMOVETO: u8 = 4;
+pub(super) const RLINETO: u8 = 5;
+pub(super) const RRCURVETO: u8 = 8;
+pub(super) const ENDCHAR: u8 = 14;
+pub(super) const HSTEMHM: u8 = 18;
+pub(super) const HINTMASK: u8 = 19;
+pub(super) const CNTRMASK: u8 = 20;
+pub(super) const RMOVETO: u8 = 21;
+pub(super) const HMOVETO: u8 = 22;
+pub(super) const VSTEMHM: u8 = 23;
+
+/// The maximum number of operands on the argument stack of a Type 2
+/// charstring.
+const TYPE2_STACK_LIMIT: usize = 48;
+/// The maximum number of operands on the argument stack of a CFF2
+/// charstring.
+const CFF2_STACK_LIMIT: usize = 513;
+
+/// A coordinate, with its deltas for each region of the variation data.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Value {
+    default: f64,
+    deltas: Vec<f64>,
+}
+
+impl Value {
+    fn new(default: f64) -> Self {
+        Self {
+            default,
+            deltas: Vec::new(),
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.deltas.iter().all(|delta| *delta == 0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.default == 0.0 && self.is_constant()
+    }
+
+    /// Rounds to the 16.16 values that can be stored in a charstring, so
+    /// that rounding errors do not accumulate in relative operands.
+    fn quantized(&self) -> Self {
+        let quantize = |value: f64| Fixed::from_f64(value).to_f64();
+        Self {
+            default: quantize(self.default),
+            deltas: self.deltas.iter().copied().map(quantize).collect(),
+        }
+    }
+
+    fn sub(&self, other: &Value) -> Self {
+        Self {
+            default: self.default - other.default,
+            deltas: self
+                .deltas
+                .iter()
+                .zip(&other.deltas)
+                .map(|(delta, other)| delta - other)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Command {
+    MoveTo([Value; 2]),
+    LineTo([Value; 2]),
+    CurveTo([Value; 6]),
+    HintMask(Vec<usize>),
+    CounterMask(Vec<usize>),
+}
+
+impl Command {
+    fn values(&self) -> &[Value] {
+        match self {
+            Command::MoveTo(values) | Command::LineTo(values) => values,
+            Command::CurveTo(values) => values,
+            Command::HintMask(_) | Command::CounterMask(_) => &[],
+        }
+    }
+
+    fn values_mut(&mut self) -> &mut [Value] {
+        match self {
+            Command::MoveTo(values) | Command::LineTo(values) => values,
+            Command::CurveTo(values) => values,
+            Command::HintMask(_) | Command::CounterMask(_) => &mut [],
+        }
+    }
+
+    fn same_structure(&self, other: &Command) -> bool {
+        match (self, other) {
+            (Command::MoveTo(_), Command::MoveTo(_))
+            | (Command::LineTo(_), Command::LineTo(_))
+            | (Command::CurveTo(_), Command::CurveTo(_)) => true,
+            (Command::HintMask(a), Command::HintMask(b))
+            | (Command::CounterMask(a), Command::CounterMask(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// The outline and hints of a glyph, in a form that can be compiled to a
+/// Type 2 (CFF) or CFF2 charstring.
+///
+/// A charstring is usually built with a [`CharstringBuilder`], or from a
+/// [`BezPath`] with [`Charstring::from_path`]. Charstrings of compatible
+/// masters can be combined into a variable charstring with
+/// [`Charstring::variable`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Charstring {
+    hstems: Vec<[Value; 2]>,
+    vstems: Vec<[Value; 2]>,
+    commands: Vec<Command>,
+    num_regions: usize,
+}
+
+impl Charstring {
+    /// Creates a charstring, without hints, from a path.
+    pub fn from_path(path: &BezPath) -> Self {
+        let mut builder = CharstringBuilder::new();
+        write_to_pen(path, &mut builder);
+        builder.build()
+    }
+
+    /// Creates a variable charstring from the charstring of the default
+    /// master and, for each region of the item variation data, a charstring
+    /// holding the deltas of each coordinate for that region.
+    ///
+    /// All charstrings must be static and have the same structure: the same
+    /// stems, hint masks and sequence of commands. For masters that are
+    /// each the only one with a region at their location, the deltas are
+    /// given by [`Charstring::delta`].
+    pub fn variable(default: &Charstring, deltas: &[Charstring]) -> Result<Self, CffError> {
+        if default.num_regions != 0
+            || deltas
+                .iter()
+                .any(|delta| delta.num_regions != 0 || !default.same_structure(delta))
+        {
+            return Err(CffError::IncompatibleCharstrings);
+        }
+        let region_values: Vec<Vec<f64>> = deltas
+            .iter()
+            .map(|delta| delta.values().map(|value| value.default).collect())
+            .collect();
+        let mut charstring = default.clone();
+        charstring.num_regions = deltas.len();
+        for (i, value) in charstring.values_mut().enumerate() {
+            value.deltas = region_values.iter().map(|values| values[i]).collect();
+        }
+        Ok(charstring)
+    }
+
+    /// Returns the difference between this charstring and the charstring
+    /// of the default master, which must be static and have the same
+    /// structure.
+    pub fn delta(&self, default: &Charstring) -> Result<Self, CffError> {
+        if self.num_regions != 0 || default.num_regions != 0 || !self.same_structure(default) {
+            return Err(CffError::IncompatibleCharstrings);
+        }
+        let mut delta = self.clone();
+        for (value, default) in delta.values_mut().zip(default.values()) {
+            value.default -= default.default;
+        }
+        Ok(delta)
+    }
+
+    /// The number of regions that this charstring has deltas for, which is
+    /// zero for a static charstring.
+    pub fn num_regions(&self) -> usize {
+        self.num_regions
+    }
+
+    /// The number of stem hints.
+    pub fn num_stems(&self) -> usize {
+        self.hstems.len() + self.vstems.len()
+    }
+
+    /// Returns the bounding box of the default outline, or `None` if it is
+    /// empty.
+    pub fn bounds(&self) -> Option<Rect> {
+        let mut path = BezPath::new();
+        for command in &self.commands {
+            match command.values() {
+                [x, y] if matches!(command, Command::MoveTo(_)) => {
+                    path.move_to((x.default, y.default))
+                }
+                [x, y] => path.line_to((x.default, y.default)),
+                [x0, y0, x1, y1, x, y] => path.curve_to(
+                    (x0.default, y0.default),
+                    (x1.default, y1.default),
+                    (x.default, y.default),
+                ),
+                _ => (),
+            }
+        }
+        (!path.elements().is_empty()).then(|| path.bounding_box())
+    }
+
+    /// Compiles a Type 2 charstring, for the `CFF` table.
+    ///
+    /// If `width` is provided, it must already be relative to the
+    /// `nominalWidthX` of the Private DICT.
+    pub fn compile_type2(&self, width: Option<f64>) -> Result<Vec<u8>, CffError> {
+        if self.num_regions != 0 {
+            return Err(CffError::UnexpectedVariations);
+        }
+        self.compile(false, width)
+    }
+
+    /// Compiles a CFF2 charstring, with a `blend` for each run of operands
+    /// that vary.
+    ///
+    /// The deltas are for the regions of the item variation data selected
+    /// by the `vsindex` of the Private DICT.
+    pub fn compile_cff2(&self) -> Result<Vec<u8>, CffError> {
+        self.compile(true, None)
+    }
+
+    fn compile(&self, is_cff2: bool, width: Option<f64>) -> Result<Vec<u8>, CffError> {
+        let num_stems = self.num_stems();
+        let mut has_masks = false;
+        for command in &self.commands {
+            if let Command::HintMask(stems) | Command::CounterMask(stems) = command {
+                has_masks = true;
+                if let Some(index) = stems.iter().find(|index| **index >= num_stems) {
+                    return Err(CffError::InvalidStemIndex(*index));
+                }
+            }
+        }
+
+        let mut encoder = Encoder {
+            out: Vec::new(),
+            stack_limit: if is_cff2 {
+                CFF2_STACK_LIMIT
+            } else {
+                TYPE2_STACK_LIMIT
+            },
+            num_regions: self.num_regions,
+            stack_len: 0,
+        };
+        if let Some(width) = width {
+            NumberEncoding::Charstring.write_number(&mut encoder.out, width);
+            encoder.stack_len = 1;
+        }
+        let zero = Value {
+            default: 0.0,
+            deltas: vec![0.0; self.num_regions],
+        };
+        let (hstem, vstem) = if has_masks {
+            (HSTEMHM, VSTEMHM)
+        } else {
+            (HSTEM, VSTEM)
+        };
+        encoder.stems(&self.hstems, hstem, &zero)?;
+        encoder.stems(&self.vstems, vstem, &zero)?;
+
+        let mut current = [zero.clone(), zero];
+        let mut relative = |points: &[Value]| {
+            let mut operands = Vec::new();
+            for point in points.chunks(2) {
+                let point = [point[0].quantized(), point[1].quantized()];
+                operands.push(point[0].sub(&current[0]));
+                operands.push(point[1].sub(&current[1]));
+                current = point;
+            }
+            operands
+        };
+        let mut commands = self.commands.iter().peekable();
+        while let Some(command) = commands.next() {
+            match command {
+                Command::MoveTo(point) => {
+                    let [dx, dy]: [Value; 2] = relative(point).try_into().unwrap();
+                    if dy.is_zero() {
+                        encoder.operands(&[dx])?;
+                        encoder.op(HMOVETO);
+                    } else if dx.is_zero() {
+                        encoder.operands(&[dy])?;
+                        encoder.op(VMOVETO);
+                    } else {
+                        encoder.operands(&[dx, dy])?;
+                        encoder.op(RMOVETO);
+                    }
+                }
+                Command::LineTo(_) | Command::CurveTo(_) => {
+                    let op = match command {
+                        Command::LineTo(_) => RLINETO,
+                        _ => RRCURVETO,
+                    };
+                    let mut next = Some(command);
+                    while let Some(command) = next {
+                        let operands = relative(command.values());
+                        if !encoder.fits(&operands) {
+                            encoder.op(op);
+                        }
+                        encoder.operands(&operands)?;
+                        next = commands.next_if(|next| next.same_structure(command));
+                    }
+                    encoder.op(op);
+                }
+                Command::HintMask(stems) | Command::CounterMask(stems) => {
+                    encoder.op(match command {
+                        Command::HintMask(_) => HINTMASK,
+                        _ => CNTRMASK,
+                    });
+                    let mut mask = vec![0u8; num_stems.div_ceil(8)];
+                    for index in stems {
+                        mask[index / 8] |= 0x80 >> (index % 8);
+                    }
+                    encoder.out.extend(mask);
+                }
+            }
+        }
+        if !is_cff2 {
+            encoder.op(ENDCHAR);
+        }
+        Ok(encoder.out)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &Value> {
+        self.hstems
+            .iter()
+            .chain(&self.vstems)
+            .flatten()
+            .chain(self.commands.iter().flat_map(Command::values))
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.hstems
+            .iter_mut()
+            .chain(&mut self.vstems)
+            .flatten()
+            .chain(self.commands.iter_mut().flat_map(Command::values_mut))
+    }
+
+    fn same_structure(&self, other: &Charstring) -> bool {
+        self.hstems.len() == other.hstems.len()
+            && self.vstems.len() == other.vstems.len()
+            && self.commands.len() == other.commands.len()
+            && self
+                .commands
+                .iter()
+                .zip(&other.commands)
+                .all(|(a, b)| a.same_structure(b))
+    }
+}
+
+/// Writes operands and operators, tracking the depth of the argument stack.
+struct Encoder {
+    out: Vec<u8>,
+    stack_limit: usize,
+    num_regions: usize,
+    stack_len: usize,
+}
+
+impl Encoder {
+    /// Calls `f` for each run of constant or varying operands.
+    fn runs<'a>(operands: &'a [Value], mut f: impl FnMut(&'a [Value], bool)) {
+        let mut rest = operands;
+        while let Some(first) = rest.first() {
+            let is_constant = first.is_constant();
+            let len = rest
+                .iter()
+                .position(|operand| operand.is_constant() != is_constant)
+                .unwrap_or(rest.len());
+            let (run, tail) = rest.split_at(len);
+            f(run, is_constant);
+            rest = tail;
+        }
+    }
+
+    /// Returns `true` if the operands can be added to the stack, including
+    /// the deltas and count of each `blend`.
+    fn fits(&self, operands: &[Value]) -> bool {
+        let mut len = self.stack_len;
+        let mut peak = len;
+        Self::runs(operands, |run, is_constant| {
+            if !is_constant {
+                peak = peak.max(len + run.len() * (self.num_regions + 1) + 1);
+            }
+            len += run.len();
+            peak = peak.max(len);
+        });
+        peak <= self.stack_limit
+    }
+
+    /// Writes operands, with a `blend` for each run of operands that vary.
+    fn operands(&mut self, operands: &[Value]) -> Result<(), CffError> {
+        if !self.fits(operands) {
+            return Err(CffError::StackOverflow);
+        }
+        let encoding = NumberEncoding::Charstring;
+        let out = &mut self.out;
+        Self::runs(operands, |run, is_constant| {
+            for operand in run {
+                encoding.write_number(out, operand.default);
+            }
+            if !is_constant {
+                for operand in run {
+                    for delta in &operand.deltas {
+                        encoding.write_number(out, *delta);
+                    }
+                }
+                encoding.write_number(out, run.len() as f64);
+                out.push(encoding.blend_op());
+            }
+        });
+        self.stack_len += operands.len();
+        Ok(())
+    }
+
+    fn op(&mut self, op: u8) {
+        self.out.push(op);
+        self.stack_len = 0;
+    }
+
+    /// Writes stem hints, whose edges are relative to the previous edge of
+    /// the same operator.
+    fn stems(&mut self, stems: &[[Value; 2]], op: u8, zero: &Value) -> Result<(), CffError> {
+        if stems.is_empty() {
+            return Ok(());
+        }
+        let mut edge = zero.clone();
+        for [position, width] in stems {
+            let (position, width) = (position.quantized(), width.quantized());
+            let mut operands = [position.sub(&edge), width.clone()];
+            if !self.fits(&operands) {
+                self.op(op);
+                operands[0] = position.clone();
+            }
+            self.operands(&operands)?;
+            edge = Value {
+                default: position.default + width.default,
+                deltas: position
+                    .deltas
+                    .iter()
+                    .zip(&width.deltas)
+                    .map(|(a, b)| a + b)
+                    .collect(),
+            };
+        }
+        self.op(op);
+        Ok(())
+    }
+}
+
+/// Records the outline and hints of a glyph as a [`Charstring`].
+///
+/// Quadratic curves are converted to cubic curves, and contours are closed
+/// implicitly, as they are in charstrings.
+#[derive(Clone, Debug, Default)]
+pub struct CharstringBuilder {
+    charstring: Charstring,
+    current: (f64, f64),
+}
+
+impl CharstringBuilder {
+    /// Creates a builder for an empty charstring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a horizontal stem hint, from `y` to `y + dy`.
+    ///
+    /// Stems should be added in increasing order of position.
+    pub fn hstem(&mut self, y: f64, dy: f64) -> &mut Self {
+        self.charstring.hstems.push([Value::new(y), Value::new(dy)]);
+        self
+    }
+
+    /// Adds a vertical stem hint, from `x` to `x + dx`.
+    ///
+    /// Stems should be added in increasing order of position.
+    pub fn vstem(&mut self, x: f64, dx: f64) -> &mut Self {
+        self.charstring.vstems.push([Value::new(x), Value::new(dx)]);
+        self
+    }
+
+    /// Sets the stem hints that are active for the commands that follow.
+    ///
+    /// Stems are identified by their index, counting all horizontal stems
+    /// before the vertical stems, each in the order they were added.
+    pub fn hint_mask(&mut self, stems: impl IntoIterator<Item = usize>) -> &mut Self {
+        let stems = stems.into_iter().collect();
+        self.charstring.commands.push(Command::HintMask(stems));
+        self
+    }
+
+    /// Sets the stem hints that are used as counters, with the same
+    /// identifiers as [`hint_mask`](Self::hint_mask).
+    pub fn counter_mask(&mut self, stems: impl IntoIterator<Item = usize>) -> &mut Self {
+        let stems = stems.into_iter().collect();
+        self.charstring.commands.push(Command::CounterMask(stems));
+        self
+    }
+
+    /// Returns the recorded charstring.
+    pub fn build(self) -> Charstring {
+        self.charstring
+    }
+
+    fn point(&mut self, x: f32, y: f32) -> [Value; 2] {
+        self.current = (x as f64, y as f64);
+        [Value::new(x as f64), Value::new(y as f64)]
+    }
+}
+
+impl Pen for CharstringBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.charstring.commands.push(Command::MoveTo(point));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.charstring.commands.push(Command::LineTo(point));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (cx0, cy0, x, y) = (cx0 as f64, cy0 as f64, x as f64, y as f64);
+        let c1 = (x0 + 2.0 / 3.0 * (cx0 - x0), y0 + 2.0 / 3.0 * (cy0 - y0));
+        let c2 = (x + 2.0 / 3.0 * (cx0 - x), y + 2.0 / 3.0 * (cy0 - y));
+        self.current = (x, y);
+        self.charstring.commands.push(Command::CurveTo(
+            [c1.0, c1.1, c2.0, c2.1, x, y].map(Value::new),
+        ));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.current = (x as f64, y as f64);
+        self.charstring.commands.push(Command::CurveTo(
+            [cx0, cy0, cx1, cy1, x, y].map(|value| Value::new(value as f64)),
+        ));
+    }
+
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use font_types::F2Dot14;
+    use read_fonts::{
+        tables::{
+            postscript::{charstring, BlendState, Index},
+            variations::ItemVariationStore as ReadItemVariationStore,
+        },
+        FontData, FontRead,
+    };
+
+    use super::*;
+    use crate::tables::variations::{
+        ItemVariationData, ItemVariationStore, RegionAxisCoordinates, VariationRegion,
+        VariationRegionList,
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Op {
+        MoveTo(f64, f64),
+        LineTo(f64, f64),
+        CurveTo(f64, f64, f64, f64, f64, f64),
+        Close,
+        HStem(f64, f64),
+        VStem(f64, f64),
+        HintMask(Vec<u8>),
+        CounterMask(Vec<u8>),
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Op>);
+
+    impl charstring::CommandSink for Recorder {
+        fn move_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push(Op::MoveTo(x.to_f64(), y.to_f64()));
+        }
+
+        fn line_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push(Op::LineTo(x.to_f64(), y.to_f64()));
+        }
+
+        fn curve_to(&mut self, cx0: Fixed, cy0: Fixed, cx1: Fixed, cy1: Fixed, x: Fixed, y: Fixed) {
+            let [cx0, cy0, cx1, cy1, x, y] = [cx0, cy0, cx1, cy1, x, y].map(Fixed::to_f64);
+            self.0.push(Op::CurveTo(cx0, cy0, cx1, cy1, x, y));
+        }
+
+        fn close(&mut self) {
+            self.0.push(Op::Close);
+        }
+
+        fn hstem(&mut self, y: Fixed, dy: Fixed) {
+            self.0.push(Op::HStem(y.to_f64(), dy.to_f64()));
+        }
+
+        fn vstem(&mut self, x: Fixed, dx: Fixed) {
+            self.0.push(Op::VStem(x.to_f64(), dx.to_f64()));
+        }
+
+        fn hint_mask(&mut self, mask: &[u8]) {
+            self.0.push(Op::HintMask(mask.to_vec()));
+        }
+
+        fn counter_mask(&mut self, mask: &[u8]) {
+            self.0.push(Op::CounterMask(mask.to_vec()));
+        }
+    }
+
+    fn evaluate(data: &[u8], blend_state: Option<BlendState>) -> Vec<Op> {
+        // an empty INDEX, and a byte that would hold the size of its offsets
+        let global_subrs = Index::new(&[0, 0, 0, 0, 0], true).unwrap();
+        let mut recorder = Recorder::default();
+        charstring::evaluate(data, global_subrs, None, blend_state, &mut recorder).unwrap();
+        recorder.0
+    }
+
+    /// A store with a single region, which peaks at the maximum of a single
+    /// axis.
+    fn variation_store(num_regions: usize) -> Vec<u8> {
+        let region = VariationRegion::new(vec![RegionAxisCoordinates::new(
+            F2Dot14::ZERO,
+            F2Dot14::ONE,
+            F2Dot14::ONE,
+        )]);
+        let data = ItemVariationData::new(0, 0, vec![0; num_regions], Vec::new());
+        let store =
+            ItemVariationStore::new(VariationRegionList::new(vec![region]), vec![Some(data)]);
+        crate::dump_table(&store).unwrap()
+    }
+
+    fn square(size: f32) -> Charstring {
+        let mut builder = CharstringBuilder::new();
+        builder.move_to(10.0, 0.0);
+        builder.line_to(10.0 + size, 0.0);
+        builder.line_to(10.0 + size, size);
+        builder.line_to(10.0, size);
+        builder.close();
+        builder.build()
+    }
+
+    #[test]
+    fn path_round_trip() {
+        let mut builder = CharstringBuilder::new();
+        builder
+            .hstem(0.0, 20.0)
+            .hstem(480.0, 20.0)
+            .vstem(10.0, 30.5)
+            .hint_mask([0, 2]);
+        builder.move_to(10.0, 0.0);
+        builder.line_to(200.0, 0.0);
+        builder.curve_to(250.0, 0.0, 300.0, 50.0, 300.0, 100.0);
+        builder.quad_to(300.0, 400.0, 0.0, 400.0);
+        builder.close();
+        builder.hint_mask([1]);
+        builder.move_to(10.0, 450.0);
+        builder.line_to(10.0, 500.0);
+        builder.close();
+        let charstring = builder.build();
+
+        let expected = [
+            Op::HStem(0.0, 20.0),
+            Op::HStem(480.0, 500.0),
+            Op::VStem(10.0, 40.5),
+            Op::HintMask(vec![0b1010_0000]),
+            Op::MoveTo(10.0, 0.0),
+            Op::LineTo(200.0, 0.0),
+            Op::CurveTo(250.0, 0.0, 300.0, 50.0, 300.0, 100.0),
+            Op::CurveTo(300.0, 300.0, 200.0, 400.0, 0.0, 400.0),
+            // contours are closed by the next move
+            Op::HintMask(vec![0b0100_0000]),
+            Op::Close,
+            Op::MoveTo(10.0, 450.0),
+            Op::LineTo(10.0, 500.0),
+            Op::Close,
+        ];
+        let type2 = charstring.compile_type2(Some(-20.0)).unwrap();
+        assert_eq!(type2.last(), Some(&ENDCHAR));
+        assert_eq!(evaluate(&type2, None), expected);
+        // without an `endchar`, the last contour is left open
+        let cff2 = charstring.compile_cff2().unwrap();
+        assert_eq!(evaluate(&cff2, None), &expected[..expected.len() - 1]);
+        assert_eq!(charstring.bounds(), Some(Rect::new(0.0, 0.0, 300.0, 500.0)));
+    }
+
+    #[test]
+    fn fractional_coordinates() {
+        let mut builder = CharstringBuilder::new();
+        builder.move_to(0.1, 0.2);
+        builder.line_to(100.3, 0.2);
+        builder.line_to(50.7, 80.9);
+        let data = builder.build().compile_cff2().unwrap();
+        let quantize = |value: f32| Fixed::from_f64(value as f64).to_f64();
+        assert_eq!(
+            evaluate(&data, None),
+            [
+                Op::MoveTo(quantize(0.1), quantize(0.2)),
+                Op::LineTo(quantize(100.3), quantize(0.2)),
+                Op::LineTo(quantize(50.7), quantize(80.9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn blend_round_trip() {
+        let default = square(100.0);
+        let bold = square(140.0);
+        let charstring = Charstring::variable(&default, &[bold.delta(&default).unwrap()]).unwrap();
+        assert_eq!(charstring.num_regions(), 1);
+        assert!(matches!(
+            charstring.compile_type2(None),
+            Err(CffError::UnexpectedVariations)
+        ));
+        let data = charstring.compile_cff2().unwrap();
+
+        let store = variation_store(1);
+        let store = ReadItemVariationStore::read(FontData::new(&store)).unwrap();
+        for (coord, size) in [(0.0, 100.0), (0.5, 120.0), (1.0, 140.0)] {
+            let coords = [F2Dot14::from_f32(coord)];
+            let blend_state = BlendState::new(store.clone(), &coords, 0).unwrap();
+            assert_eq!(
+                evaluate(&data, Some(blend_state)),
+                [
+                    Op::MoveTo(10.0, 0.0),
+                    Op::LineTo(10.0 + size, 0.0),
+                    Op::LineTo(10.0 + size, size),
+                    Op::LineTo(10.0, size),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn incompatible_charstrings() {
+        let mut builder = CharstringBuilder::new();
+        builder.move_to(0.0, 0.0);
+        builder.curve_to(1.0, 1.0, 2.0, 2.0, 3.0, 3.0);
+        let curve = builder.build();
+        let line = Charstring::from_path(&kurbo::Line::new((0.0, 0.0), (3.0, 3.0)).into_path(0.1));
+        assert!(matches!(
+            curve.delta(&line),
+            Err(CffError::IncompatibleCharstrings)
+        ));
+        assert!(matches!(
+            Charstring::variable(&curve, &[line]),
+            Err(CffError::IncompatibleCharstrings)
+        ));
+    }
+
+    #[test]
+    fn operands_are_split_to_fit_the_stack() {
+        let mut builder = CharstringBuilder::new();
+        for i in 0..40 {
+            builder.hstem(i as f64 * 30.0, 10.0);
+        }
+        builder.hint_mask(0..40);
+        builder.move_to(0.0, 0.0);
+        for i in 0..40 {
+            builder.line_to(i as f32, 1.0 + i as f32);
+        }
+        let charstring = builder.build();
+        let ops = evaluate(&charstring.compile_type2(Some(100.0)).unwrap(), None);
+        assert_eq!(ops.len(), 40 + 1 + 1 + 40 + 1);
+        assert_eq!(ops[39], Op::HStem(1170.0, 1180.0));
+        assert_eq!(ops[40], Op::HintMask(vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF]));
+        assert_eq!(ops[81], Op::LineTo(39.0, 40.0));
+
+        // each curve has six varying operands, which need 6 * 100 + 1 slots
+        let mut builder = CharstringBuilder::new();
+        builder.move_to(0.0, 0.0);
+        builder.curve_to(1.0, 1.0, 2.0, 2.0, 3.0, 3.0);
+        let curve = builder.build();
+        let deltas = vec![curve.clone(); 100];
+        let charstring = Charstring::variable(&curve, &deltas).unwrap();
+        assert!(matches!(
+            charstring.compile_cff2(),
+            Err(CffError::StackOverflow)
+        ));
+    }
+
+    #[test]
+    fn invalid_hint_mask() {
+        let mut builder = CharstringBuilder::new();
+        builder.hstem(0.0, 10.0).hint_mask([1]);
+        assert!(matches!(
+            builder.build().compile_cff2(),
+            Err(CffError::InvalidStemIndex(1))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+//! Writing DICTs.
+
+use super::{escaped, write_op, NumberEncoding};
+
+// Private DICT operators
+const BLUE_VALUES: u16 = 6;
+const OTHER_BLUES: u16 = 7;
+const FAMILY_BLUES: u16 = 8;
+const FAMILY_OTHER_BLUES: u16 = 9;
+const STD_HW: u16 = 10;
+const STD_VW: u16 = 11;
+pub(super) const SUBRS: u16 = 19;
+const BLUE_SCALE: u16 = escaped(9);
+const BLUE_SHIFT: u16 = escaped(10);
+const BLUE_FUZZ: u16 = escaped(11);
+const STEM_SNAP_H: u16 = escaped(12);
+const STEM_SNAP_V: u16 = escaped(13);
+const FORCE_BOLD: u16 = escaped(14);
+const LANGUAGE_GROUP: u16 = escaped(17);
+const EXPANSION_FACTOR: u16 = escaped(18);
+const DEFAULT_WIDTH_X: u16 = 20;
+const NOMINAL_WIDTH_X: u16 = 21;
+const VSINDEX: u16 = 22;
+
+/// Accumulates the entries of a DICT.
+#[derive(Clone, Default)]
+pub(crate) struct DictWriter {
+    data: Vec<u8>,
+}
+
+impl DictWriter {
+    /// Adds an entry with number operands.
+    pub(crate) fn numbers(&mut self, op: u16, values: &[f64]) -> &mut Self {
+        for value in values {
+            NumberEncoding::Dict.write_number(&mut self.data, *value);
+        }
+        write_op(&mut self.data, op);
+        self
+    }
+
+    /// Adds an entry with a delta-encoded array operand, where each number
+    /// is relative to the previous one.
+    pub(crate) fn deltas(&mut self, op: u16, values: &[f64]) -> &mut Self {
+        let mut previous = 0.0;
+        for value in values {
+            NumberEncoding::Dict.write_number(&mut self.data, value - previous);
+            previous = *value;
+        }
+        write_op(&mut self.data, op);
+        self
+    }
+
+    /// Adds an entry whose operands are already encoded.
+    pub(crate) fn encoded(&mut self, op: u16, operands: &[u8]) -> &mut Self {
+        self.data.extend_from_slice(operands);
+        write_op(&mut self.data, op);
+        self
+    }
+
+    /// Adds an entry whose operands are offsets or sizes.
+    ///
+    /// These use the five byte integer encoding, so that the size of a DICT
+    /// does not depend on the offsets it contains.
+    pub(crate) fn offsets(&mut self, op: u16, values: &[usize]) -> &mut Self {
+        for value in values {
+            self.data.push(29);
+            self.data.extend_from_slice(&(*value as u32).to_be_bytes());
+        }
+        write_op(&mut self.data, op);
+        self
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// The hinting parameters of a Private DICT.
+///
+/// Zones and stem widths are in font units. Array values are stored as
+/// absolute values, and delta-encoded when the DICT is written. Values
+/// that are `None` or empty are omitted, and take their default values.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateDict {
+    /// Pairs of bottom and top edges of the alignment zones, in increasing
+    /// order; the first pair is the baseline overshoot zone.
+    pub blue_values: Vec<f64>,
+    /// Pairs of edges of the descender zones, in increasing order.
+    pub other_blues: Vec<f64>,
+    /// The `blue_values` shared by a font family.
+    pub family_blues: Vec<f64>,
+    /// The `other_blues` shared by a font family.
+    pub family_other_blues: Vec<f64>,
+    /// The point size below which overshoots are suppressed, scaled so
+    /// that it applies to the size in pixels.
+    pub blue_scale: Option<f64>,
+    /// The minimum overshoot, in font units, that is enforced above the
+    /// point size given by `blue_scale`.
+    pub blue_shift: Option<f64>,
+    /// The distance by which alignment zones are extended on each side.
+    pub blue_fuzz: Option<f64>,
+    /// The dominant width of horizontal stems.
+    pub std_hw: Option<f64>,
+    /// The dominant width of vertical stems.
+    pub std_vw: Option<f64>,
+    /// Common widths of horizontal stems, in increasing order.
+    pub stem_snap_h: Vec<f64>,
+    /// Common widths of vertical stems, in increasing order.
+    pub stem_snap_v: Vec<f64>,
+    /// Whether glyphs should be emboldened at small sizes; only used in
+    /// `CFF` tables.
+    pub force_bold: bool,
+    /// The script of the glyphs: 0 for most scripts, 1 for ideographs.
+    pub language_group: i32,
+    /// The amount by which counters may be widened for ideographs.
+    pub expansion_factor: Option<f64>,
+    /// The width of glyphs whose charstring has no width; only used in
+    /// `CFF` tables.
+    pub default_width_x: f64,
+    /// The width that charstring widths are relative to; only used in
+    /// `CFF` tables.
+    pub nominal_width_x: f64,
+    /// The item variation data used by charstrings; only used in `CFF2`
+    /// tables.
+    pub vsindex: u16,
+}
+
+impl PrivateDict {
+    /// Returns the DICT entries for a `CFF` or `CFF2` table.
+    pub(crate) fn writer(&self, is_cff2: bool) -> DictWriter {
+        let mut dict = DictWriter::default();
+        if is_cff2 && self.vsindex != 0 {
+            dict.numbers(VSINDEX, &[self.vsindex as f64]);
+        }
+        for (op, values) in [
+            (BLUE_VALUES, &self.blue_values),
+            (OTHER_BLUES, &self.other_blues),
+            (FAMILY_BLUES, &self.family_blues),
+            (FAMILY_OTHER_BLUES, &self.family_other_blues),
+        ] {
+            if !values.is_empty() {
+                dict.deltas(op, values);
+            }
+        }
+        for (op, value) in [
+            (BLUE_SCALE, self.blue_scale),
+            (BLUE_SHIFT, self.blue_shift),
+            (BLUE_FUZZ, self.blue_fuzz),
+            (STD_HW, self.std_hw),
+            (STD_VW, self.std_vw),
+        ] {
+            if let Some(value) = value {
+                dict.numbers(op, &[value]);
+            }
+        }
+        for (op, values) in [
+            (STEM_SNAP_H, &self.stem_snap_h),
+            (STEM_SNAP_V, &self.stem_snap_v),
+        ] {
+            if !values.is_empty() {
+                dict.deltas(op, values);
+            }
+        }
+        if self.force_bold && !is_cff2 {
+            dict.numbers(FORCE_BOLD, &[1.0]);
+        }
+        if self.language_group != 0 {
+            dict.numbers(LANGUAGE_GROUP, &[self.language_group as f64]);
+        }
+        if let Some(value) = self.expansion_factor {
+            dict.numbers(EXPANSION_FACTOR, &[value]);
+        }
+        if !is_cff2 {
+            if self.default_width_x != 0.0 {
+                dict.numbers(DEFAULT_WIDTH_X, &[self.default_width_x]);
+            }
+            if self.nominal_width_x != 0.0 {
+                dict.numbers(NOMINAL_WIDTH_X, &[self.nominal_width_x]);
+            }
+        }
+        dict
+    }
+}
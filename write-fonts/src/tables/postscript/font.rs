@@ -0,0 +1,492 @@
+//! The binary structures of an existing CFF or CFF2 table.
+//!
+//! These are used by the subsetter and the instancer, which rewrite the
+//! tables at the level of their DICTs and INDEXes.
+
+use read_fonts::{
+    tables::postscript::{Error, FdSelect, Index},
+    tables::{cff::Cff, cff2::Cff2},
+    FontData, FontRead, ReadError,
+};
+use types::GlyphId;
+
+use super::{build_fd_select, escaped, write_index, DictWriter, ESCAPE};
+
+// DICT operators, with two-byte operators stored as `ESCAPE << 8 | op`
+pub(crate) const CHARSET: u16 = 15;
+pub(crate) const ENCODING: u16 = 16;
+pub(crate) const CHARSTRINGS: u16 = 17;
+pub(crate) const PRIVATE: u16 = 18;
+pub(crate) const SUBRS: u16 = 19;
+pub(crate) const VARIATION_STORE: u16 = 24;
+pub(crate) const ROS: u16 = escaped(30);
+pub(crate) const FD_ARRAY: u16 = escaped(36);
+pub(crate) const FD_SELECT: u16 = escaped(37);
+
+pub(crate) fn ps_error(error: Error) -> ReadError {
+    match error {
+        Error::Read(error) => error,
+        _ => ReadError::MalformedData("invalid CFF data"),
+    }
+}
+
+pub(crate) fn malformed() -> ReadError {
+    ReadError::MalformedData("invalid CFF data")
+}
+
+/// A DICT operator with its encoded operands.
+#[derive(Clone, Debug)]
+pub(crate) struct DictEntry<'a> {
+    pub(crate) operands: &'a [u8],
+    pub(crate) op: u16,
+}
+
+impl DictEntry<'_> {
+    /// Decodes the operands.
+    pub(crate) fn numbers(&self) -> Result<Vec<f64>, ReadError> {
+        let data = self.operands;
+        let mut values = Vec::new();
+        let mut pos = 0;
+        while let Some(&b0) = data.get(pos) {
+            let bytes = data.get(pos + 1..).unwrap_or_default();
+            let (value, len) = match b0 {
+                28 if bytes.len() >= 2 => (i16::from_be_bytes([bytes[0], bytes[1]]) as f64, 3),
+                29 if bytes.len() >= 4 => {
+                    (i32::from_be_bytes(bytes[..4].try_into().unwrap()) as f64, 5)
+                }
+                30 => {
+                    let mut text = String::new();
+                    let mut len = 1;
+                    'nibbles: for byte in bytes {
+                        len += 1;
+                        for nibble in [byte >> 4, byte & 0xF] {
+                            match nibble {
+                                0..=9 => text.push((b'0' + nibble) as char),
+                                0xA => text.push('.'),
+                                0xB => text.push('E'),
+                                0xC => text.push_str("E-"),
+                                0xE => text.push('-'),
+                                0xF => break 'nibbles,
+                                _ => return Err(malformed()),
+                            }
+                        }
+                    }
+                    (text.parse().map_err(|_| malformed())?, len)
+                }
+                32..=246 => (b0 as f64 - 139.0, 1),
+                247..=250 if !bytes.is_empty() => {
+                    ((b0 as f64 - 247.0) * 256.0 + bytes[0] as f64 + 108.0, 2)
+                }
+                251..=254 if !bytes.is_empty() => {
+                    (-(b0 as f64 - 251.0) * 256.0 - bytes[0] as f64 - 108.0, 2)
+                }
+                _ => return Err(malformed()),
+            };
+            values.push(value);
+            pos += len;
+        }
+        Ok(values)
+    }
+
+    /// Decodes the operands, which must all be integers.
+    pub(crate) fn int_operands(&self) -> Result<Vec<i32>, ReadError> {
+        self.numbers()?
+            .into_iter()
+            .map(|value| match value.fract() == 0.0 {
+                true => Ok(value as i32),
+                false => Err(malformed()),
+            })
+            .collect()
+    }
+
+    pub(crate) fn offset(&self) -> Result<usize, ReadError> {
+        let operands = self.int_operands()?;
+        match operands.last() {
+            Some(offset) if *offset >= 0 => Ok(*offset as usize),
+            _ => Err(malformed()),
+        }
+    }
+
+    /// The size and offset operands of a Private DICT entry.
+    fn private_range(&self) -> Result<(usize, usize), ReadError> {
+        match self.int_operands()?.as_slice() {
+            [size, offset] if *size >= 0 && *offset >= 0 => Ok((*size as usize, *offset as usize)),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+/// Splits DICT data into operators and their operands.
+pub(crate) fn parse_dict(data: &[u8]) -> Result<Vec<DictEntry<'_>>, ReadError> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        let b0 = data[pos];
+        match b0 {
+            ESCAPE => {
+                let op = *data.get(pos + 1).ok_or(malformed())?;
+                entries.push(DictEntry {
+                    operands: &data[start..pos],
+                    op: escaped(op),
+                });
+                pos += 2;
+                start = pos;
+                continue;
+            }
+            0..=27 => {
+                entries.push(DictEntry {
+                    operands: &data[start..pos],
+                    op: b0 as u16,
+                });
+                pos += 1;
+                start = pos;
+                continue;
+            }
+            28 => pos += 3,
+            29 => pos += 5,
+            // a real number, encoded as nibbles terminated by 0xf
+            30 => loop {
+                pos += 1;
+                let byte = *data.get(pos).ok_or(malformed())?;
+                if byte & 0xF == 0xF || byte >> 4 == 0xF {
+                    pos += 1;
+                    break;
+                }
+            },
+            32..=246 => pos += 1,
+            247..=254 => pos += 2,
+            _ => return Err(malformed()),
+        }
+    }
+    if pos > data.len() || start != data.len() {
+        return Err(malformed());
+    }
+    Ok(entries)
+}
+
+/// Writes DICT entries, omitting those with the operators in `replaced` and
+/// appending `extra` entries.
+///
+/// The operands of `extra` entries use the five byte integer encoding, so
+/// that the size of a DICT does not depend on the offsets it contains.
+pub(crate) fn write_dict(
+    entries: &[DictEntry],
+    replaced: &[u16],
+    extra: &[(u16, Vec<usize>)],
+) -> Vec<u8> {
+    let mut dict = DictWriter::default();
+    for entry in entries.iter().filter(|entry| !replaced.contains(&entry.op)) {
+        dict.encoded(entry.op, entry.operands);
+    }
+    for (op, operands) in extra {
+        dict.offsets(*op, operands);
+    }
+    dict.into_data()
+}
+
+/// A Private DICT and its local subroutines.
+pub(crate) struct PrivateDict<'a> {
+    pub(crate) entries: Vec<DictEntry<'a>>,
+    pub(crate) subrs: Option<&'a [u8]>,
+}
+
+impl<'a> PrivateDict<'a> {
+    fn new(
+        data: &'a [u8],
+        (size, offset): (usize, usize),
+        is_cff2: bool,
+    ) -> Result<Self, ReadError> {
+        let dict = data
+            .get(offset..offset + size)
+            .ok_or(ReadError::OutOfBounds)?;
+        let entries = parse_dict(dict)?;
+        let subrs = match entries.iter().find(|entry| entry.op == SUBRS) {
+            Some(entry) => {
+                let subrs = data
+                    .get(offset + entry.offset()?..)
+                    .ok_or(ReadError::OutOfBounds)?;
+                let len = Index::new(subrs, is_cff2)
+                    .map_err(ps_error)?
+                    .size_in_bytes()?;
+                Some(subrs.get(..len).ok_or(ReadError::OutOfBounds)?)
+            }
+            None => None,
+        };
+        Ok(Self { entries, subrs })
+    }
+
+    /// Writes the DICT, followed by the local subroutines.
+    ///
+    /// Returns the data and the size of the DICT.
+    fn write(&self) -> (Vec<u8>, usize) {
+        let Some(subrs) = self.subrs else {
+            let dict = write_dict(&self.entries, &[SUBRS], &[]);
+            let len = dict.len();
+            return (dict, len);
+        };
+        // the subroutines immediately follow the DICT
+        let len = write_dict(&self.entries, &[SUBRS], &[(SUBRS, vec![0])]).len();
+        let mut data = write_dict(&self.entries, &[SUBRS], &[(SUBRS, vec![len])]);
+        data.extend_from_slice(subrs);
+        (data, len)
+    }
+}
+
+/// A Font DICT of a CID-keyed or CFF2 font, with its Private DICT.
+pub(crate) struct FontDict<'a> {
+    pub(crate) entries: Vec<DictEntry<'a>>,
+    pub(crate) private: Option<PrivateDict<'a>>,
+}
+
+/// The parsed structures of a CFF or CFF2 table.
+pub(crate) struct CffFont<'a> {
+    data: &'a [u8],
+    is_cff2: bool,
+    pub(crate) top_dict: Vec<DictEntry<'a>>,
+    pub(crate) global_subrs: Index<'a>,
+    pub(crate) charstrings: Index<'a>,
+    pub(crate) fd_select: Option<FdSelect<'a>>,
+    pub(crate) font_dicts: Vec<FontDict<'a>>,
+    /// The Private DICT of a name-keyed CFF font.
+    pub(crate) private: Option<PrivateDict<'a>>,
+}
+
+impl<'a> CffFont<'a> {
+    pub(crate) fn new_cff(data: &'a [u8]) -> Result<Self, ReadError> {
+        let cff = Cff::read(FontData::new(data))?;
+        let top_dict = cff.top_dicts().get(0).map_err(ps_error)?;
+        let global_subrs = Index::Format1(cff.global_subrs());
+        Self::new(data, false, top_dict, global_subrs)
+    }
+
+    pub(crate) fn new_cff2(data: &'a [u8]) -> Result<Self, ReadError> {
+        let cff2 = Cff2::read(FontData::new(data))?;
+        let global_subrs = Index::Format2(cff2.global_subrs());
+        Self::new(data, true, cff2.top_dict_data(), global_subrs)
+    }
+
+    fn new(
+        data: &'a [u8],
+        is_cff2: bool,
+        top_dict: &'a [u8],
+        global_subrs: Index<'a>,
+    ) -> Result<Self, ReadError> {
+        let top_dict = parse_dict(top_dict)?;
+        let find = |op| top_dict.iter().find(|entry| entry.op == op);
+        let index_at = |offset: usize| {
+            let data = data.get(offset..).ok_or(ReadError::OutOfBounds)?;
+            Index::new(data, is_cff2).map_err(ps_error)
+        };
+        let charstrings = index_at(find(CHARSTRINGS).ok_or(malformed())?.offset()?)?;
+        let fd_select = find(FD_SELECT)
+            .map(|entry| {
+                let data = data.get(entry.offset()?..).ok_or(ReadError::OutOfBounds)?;
+                FdSelect::read(FontData::new(data))
+            })
+            .transpose()?;
+        let mut font_dicts = Vec::new();
+        if let Some(entry) = find(FD_ARRAY) {
+            let fd_array = index_at(entry.offset()?)?;
+            for i in 0..fd_array.count() as usize {
+                let entries = parse_dict(fd_array.get(i).map_err(ps_error)?)?;
+                let private = entries
+                    .iter()
+                    .find(|entry| entry.op == PRIVATE)
+                    .map(|entry| PrivateDict::new(data, entry.private_range()?, is_cff2))
+                    .transpose()?;
+                font_dicts.push(FontDict { entries, private });
+            }
+        }
+        let private = find(PRIVATE)
+            .map(|entry| PrivateDict::new(data, entry.private_range()?, is_cff2))
+            .transpose()?;
+        Ok(Self {
+            data,
+            is_cff2,
+            top_dict,
+            global_subrs,
+            charstrings,
+            fd_select,
+            font_dicts,
+            private,
+        })
+    }
+
+    pub(crate) fn is_cid(&self) -> bool {
+        self.top_dict.iter().any(|entry| entry.op == ROS)
+    }
+
+    pub(crate) fn num_glyphs(&self) -> usize {
+        self.charstrings.count() as usize
+    }
+
+    /// Returns the string identifier (or CID) of each glyph.
+    pub(crate) fn charset(&self) -> Result<Vec<u16>, ReadError> {
+        let num_glyphs = self.num_glyphs();
+        let offset = match self.top_dict.iter().find(|entry| entry.op == CHARSET) {
+            Some(entry) => entry.offset()?,
+            None => 0,
+        };
+        match offset {
+            // ISOAdobe, where glyph ids are string ids
+            0 => return Ok((0..num_glyphs as u16).collect()),
+            // the predefined expert charsets are not supported
+            1 | 2 => return Err(ReadError::MalformedData("unsupported CFF charset")),
+            _ => (),
+        }
+        let data = self.data.get(offset..).ok_or(ReadError::OutOfBounds)?;
+        let read_u16 = |pos: usize| {
+            data.get(pos..pos + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .ok_or(ReadError::OutOfBounds)
+        };
+        let mut sids = vec![0];
+        let mut pos = 1;
+        match data.first() {
+            Some(0) => {
+                while sids.len() < num_glyphs {
+                    sids.push(read_u16(pos)?);
+                    pos += 2;
+                }
+            }
+            Some(format @ (1 | 2)) => {
+                while sids.len() < num_glyphs {
+                    let first = read_u16(pos)?;
+                    let num_left = if *format == 1 {
+                        *data.get(pos + 2).ok_or(ReadError::OutOfBounds)? as u16
+                    } else {
+                        read_u16(pos + 2)?
+                    };
+                    pos += if *format == 1 { 3 } else { 4 };
+                    sids.extend((0..=num_left).map(|i| first.saturating_add(i)));
+                }
+                sids.truncate(num_glyphs);
+            }
+            _ => return Err(malformed()),
+        }
+        Ok(sids)
+    }
+
+    /// Writes an FDSelect for the given glyphs, in their new order.
+    pub(crate) fn write_fd_select(
+        &self,
+        old_glyphs: impl Iterator<Item = GlyphId>,
+    ) -> Option<Vec<u8>> {
+        let fd_select = self.fd_select.as_ref()?;
+        let fds: Vec<_> = old_glyphs
+            .map(|gid| fd_select.font_index(gid).unwrap_or_default())
+            .collect();
+        Some(crate::dump_table(&build_fd_select(&fds)).expect("FDSelect is always valid"))
+    }
+
+    /// Writes the Font DICT INDEX and all Private DICTs, which are placed
+    /// at `offset` in the new table.
+    ///
+    /// Returns the FDArray INDEX (if any) and the data of the Private DICTs,
+    /// and the Private DICT operands of a name-keyed font.
+    pub(crate) fn write_dicts(
+        &self,
+        offset: usize,
+    ) -> (Option<Vec<u8>>, Vec<u8>, Option<[usize; 2]>) {
+        let privates: Vec<_> = self
+            .font_dicts
+            .iter()
+            .map(|font_dict| font_dict.private.as_ref().map(PrivateDict::write))
+            .collect();
+        let write_font_dicts = |private_ranges: &[Option<[usize; 2]>]| {
+            let font_dicts: Vec<_> = self
+                .font_dicts
+                .iter()
+                .zip(private_ranges)
+                .map(|(font_dict, range)| match range {
+                    Some(range) => {
+                        write_dict(&font_dict.entries, &[PRIVATE], &[(PRIVATE, range.to_vec())])
+                    }
+                    None => write_dict(&font_dict.entries, &[], &[]),
+                })
+                .collect();
+            write_index(font_dicts.iter().map(Vec::as_slice), self.is_cff2)
+        };
+
+        let fd_array = (!self.font_dicts.is_empty()).then(|| {
+            let dummy_ranges: Vec<_> = privates
+                .iter()
+                .map(|p| p.as_ref().map(|_| [0, 0]))
+                .collect();
+            write_font_dicts(&dummy_ranges)
+        });
+        let mut private_data = Vec::new();
+        let mut private_offset = offset + fd_array.as_ref().map(Vec::len).unwrap_or_default();
+        let mut ranges = Vec::new();
+        for private in &privates {
+            ranges.push(private.as_ref().map(|(data, len)| {
+                let range = [*len, private_offset];
+                private_offset += data.len();
+                private_data.extend_from_slice(data);
+                range
+            }));
+        }
+        let fd_array = fd_array.map(|_| write_font_dicts(&ranges));
+        let top_private = self.private.as_ref().map(|private| {
+            let (data, len) = private.write();
+            let range = [len, private_offset];
+            private_data.extend_from_slice(&data);
+            range
+        });
+        (fd_array, private_data, top_private)
+    }
+}
+
+/// Returns the offset entries of a new Top DICT.
+///
+/// The offsets are those of the charset, charstrings, FDSelect, FDArray and
+/// variation store; entries are only added for structures that exist in the
+/// font.
+pub(crate) fn top_dict_offsets(
+    font: &CffFont,
+    [charset, charstrings, fd_select, fd_array, variation_store]: [usize; 5],
+    private: Option<[usize; 2]>,
+) -> Vec<(u16, Vec<usize>)> {
+    let has = |op| font.top_dict.iter().any(|entry| entry.op == op);
+    let mut offsets = Vec::new();
+    if !font.is_cff2 {
+        offsets.push((CHARSET, vec![charset]));
+    }
+    offsets.push((CHARSTRINGS, vec![charstrings]));
+    if font.fd_select.is_some() {
+        offsets.push((FD_SELECT, vec![fd_select]));
+    }
+    if !font.font_dicts.is_empty() {
+        offsets.push((FD_ARRAY, vec![fd_array]));
+    }
+    if has(VARIATION_STORE) {
+        offsets.push((VARIATION_STORE, vec![variation_store]));
+    }
+    if let (Some(range), true) = (private, font.private.is_some()) {
+        offsets.push((PRIVATE, range.to_vec()));
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dict_round_trip() {
+        // 0 1000 FontBBox-ish operands, a real, and an escaped operator
+        let data = [139, 250, 124, 5, 30, 0x2a, 0x5f, 12, 7, 29, 0, 0, 1, 0, 17];
+        let entries = parse_dict(&data).unwrap();
+        let ops: Vec<_> = entries.iter().map(|entry| entry.op).collect();
+        assert_eq!(ops, [5, escaped(7), CHARSTRINGS]);
+        assert_eq!(entries[0].int_operands().unwrap(), [0, 1000]);
+        assert!(entries[1].int_operands().is_err());
+        assert_eq!(entries[2].offset().unwrap(), 256);
+        assert_eq!(write_dict(&entries, &[], &[]), data);
+        let rewritten = write_dict(&entries, &[CHARSTRINGS], &[(CHARSTRINGS, vec![256])]);
+        assert_eq!(rewritten, data);
+        let rewritten = write_dict(&entries, &[CHARSTRINGS], &[(CHARSTRINGS, vec![0x10203])]);
+        assert_eq!(&rewritten[9..], [29, 0, 1, 2, 3, 17]);
+    }
+}
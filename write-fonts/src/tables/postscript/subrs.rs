@@ -0,0 +1,914 @@
+//! Subroutinizing charstrings.
+//!
+//! Charstrings are split into commands, after each of which the argument
+//! stack is empty, so that any sequence of commands can be moved into a
+//! subroutine. Sequences that are repeated in several glyphs are found with
+//! a suffix array of the commands of all glyphs, and each glyph is encoded
+//! with the subroutines that make it smallest. Subroutines that do not save
+//! space are then discarded, and the glyphs encoded again, until the set of
+//! subroutines no longer changes.
+//!
+//! Subroutines may call shorter subroutines, up to the nesting limit of the
+//! charstring interpreter. A subroutine that is only used, directly or
+//! through other subroutines, by the glyphs of one Font DICT is a local
+//! subroutine of that Font DICT; the others are global subroutines.
+
+use std::{collections::HashMap, ops::Range};
+
+use read_fonts::tables::postscript::charstring::NESTING_DEPTH_LIMIT;
+
+use super::{
+    charstring::{
+        CNTRMASK, ENDCHAR, HINTMASK, HMOVETO, HSTEM, HSTEMHM, RMOVETO, VMOVETO, VSTEM, VSTEMHM,
+    },
+    NumberEncoding, ESCAPE,
+};
+
+// charstring operators that are only used by subroutines
+const CALLSUBR: u8 = 10;
+const RETURN: u8 = 11;
+const VSINDEX: u8 = 15;
+const BLEND: u8 = 16;
+const CALLGSUBR: u8 = 29;
+
+/// The maximum number of subroutines, so that each INDEX can hold them.
+const MAX_SUBRS: usize = u16::MAX as usize;
+/// The number of times glyphs are encoded to find the prices of
+/// candidates, before candidates that do not save space are discarded.
+const PRICE_ITERATIONS: usize = 4;
+/// The number of times glyphs are encoded while discarding candidates.
+const MAX_ITERATIONS: usize = 12;
+/// The size of a call before subroutines are numbered: a two byte number
+/// and the operator.
+const CALL_SIZE: usize = 3;
+/// The size of a subroutine besides its commands: the `return` operator
+/// and its offset in the INDEX.
+const SUBR_OVERHEAD: usize = 3;
+
+/// Charstrings that call subroutines, with the subroutines.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Subroutines {
+    pub(crate) charstrings: Vec<Vec<u8>>,
+    pub(crate) global_subrs: Vec<Vec<u8>>,
+    /// The local subroutines of each Font DICT.
+    pub(crate) local_subrs: Vec<Vec<Vec<u8>>>,
+}
+
+impl Subroutines {
+    /// Returns charstrings that are not subroutinized.
+    pub(crate) fn none(charstrings: Vec<Vec<u8>>, num_fds: usize) -> Self {
+        Self {
+            charstrings,
+            global_subrs: Vec::new(),
+            local_subrs: vec![Vec::new(); num_fds],
+        }
+    }
+}
+
+/// Moves repeated sequences of commands into subroutines.
+///
+/// `fds` is the Font DICT of each charstring, and `num_regions` the number
+/// of regions of the variation data of each Font DICT. The charstrings must
+/// not call subroutines already, and CFF2 charstrings must not use
+/// `vsindex`; charstrings that do are left unchanged.
+pub(crate) fn subroutinize(
+    charstrings: &[Vec<u8>],
+    fds: &[u16],
+    num_regions: &[usize],
+    is_cff2: bool,
+) -> Subroutines {
+    let mut subroutinizer = Subroutinizer::new(charstrings, fds, num_regions, is_cff2);
+    subroutinizer.find_candidates();
+    let encoding = subroutinizer.select();
+    subroutinizer.write(&encoding, num_regions.len())
+}
+
+/// A part of an encoded glyph or subroutine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Part {
+    /// A command, by its token.
+    Token(u32),
+    /// A call to a candidate subroutine.
+    Call(usize),
+}
+
+/// A repeated sequence of commands, which may become a subroutine.
+#[derive(Clone, Debug)]
+struct Candidate {
+    /// The position of its first occurrence in the sequence of all tokens.
+    start: usize,
+    /// The number of tokens.
+    len: usize,
+    /// The size of the tokens.
+    size: usize,
+    /// The number of occurrences, some of which may overlap.
+    count: usize,
+}
+
+/// The glyphs and subroutines encoded with a set of candidates.
+struct Encoding {
+    glyphs: Vec<Vec<Part>>,
+    /// The parts of each candidate that is used.
+    bodies: Vec<Vec<Part>>,
+    /// The number of calls to each candidate, from glyphs and from the
+    /// bodies of the candidates that are used.
+    usage: Vec<usize>,
+    /// The deepest nesting of calls in each candidate, including itself.
+    heights: Vec<usize>,
+}
+
+struct Subroutinizer<'a> {
+    is_cff2: bool,
+    /// The bytes of each distinct command; separators are empty.
+    tokens: Vec<&'a [u8]>,
+    /// Whether each token ends with `endchar`.
+    ends_glyph: Vec<bool>,
+    /// The tokens of all glyphs, each glyph followed by a unique separator
+    /// so that no repeated sequence spans two glyphs.
+    seq: Vec<u32>,
+    /// The range of `seq` of each glyph, and its Font DICT.
+    glyphs: Vec<(Range<usize>, u16)>,
+    candidates: Vec<Candidate>,
+    /// The candidates in order of length, so that callees come before
+    /// their callers.
+    by_len: Vec<usize>,
+    /// The candidates that occur at each position of `seq`.
+    matches: Vec<Vec<usize>>,
+}
+
+impl<'a> Subroutinizer<'a> {
+    fn new(charstrings: &'a [Vec<u8>], fds: &[u16], num_regions: &[usize], is_cff2: bool) -> Self {
+        let mut subroutinizer = Subroutinizer {
+            is_cff2,
+            tokens: Vec::new(),
+            ends_glyph: Vec::new(),
+            seq: Vec::new(),
+            glyphs: Vec::new(),
+            candidates: Vec::new(),
+            by_len: Vec::new(),
+            matches: Vec::new(),
+        };
+        let mut ids: HashMap<&[u8], u32> = HashMap::new();
+        for (charstring, fd) in charstrings.iter().zip(fds) {
+            let regions = num_regions.get(*fd as usize).copied().unwrap_or_default();
+            let start = subroutinizer.seq.len();
+            match split_commands(charstring, is_cff2, regions) {
+                Some((ends, has_width)) => {
+                    let num_commands = ends.len();
+                    let mut token_start = 0;
+                    for (i, end) in ends.into_iter().enumerate() {
+                        let token = &charstring[token_start..end];
+                        token_start = end;
+                        // only the last command of a Type 2 charstring is
+                        // `endchar`; the last byte of others may be a mask
+                        let ends_glyph = !is_cff2 && i == num_commands - 1;
+                        // the width must stay in the glyph
+                        let id = if i == 0 && has_width {
+                            subroutinizer.push_token(token, false)
+                        } else {
+                            match ids.get(token) {
+                                Some(id) => *id,
+                                None => {
+                                    let id = subroutinizer.push_token(token, ends_glyph);
+                                    ids.insert(token, id);
+                                    id
+                                }
+                            }
+                        };
+                        subroutinizer.seq.push(id);
+                    }
+                }
+                None => {
+                    let id = subroutinizer.push_token(charstring, false);
+                    subroutinizer.seq.push(id);
+                }
+            }
+            subroutinizer
+                .glyphs
+                .push((start..subroutinizer.seq.len(), *fd));
+            let separator = subroutinizer.push_token(&[], false);
+            subroutinizer.seq.push(separator);
+        }
+        subroutinizer
+    }
+
+    fn push_token(&mut self, token: &'a [u8], ends_glyph: bool) -> u32 {
+        self.tokens.push(token);
+        self.ends_glyph
+            .push(ends_glyph && token.last() == Some(&ENDCHAR));
+        (self.tokens.len() - 1) as u32
+    }
+
+    fn token_size(&self, token: u32) -> usize {
+        self.tokens[token as usize].len()
+    }
+
+    /// Finds the sequences of tokens that are repeated, and keeps those
+    /// that would save the most space as subroutines.
+    fn find_candidates(&mut self) {
+        let suffixes = suffix_array(&self.seq);
+        let lcp = lcp_array(&self.seq, &suffixes);
+        let mut offsets = Vec::with_capacity(self.seq.len() + 1);
+        offsets.push(0);
+        for token in &self.seq {
+            offsets.push(offsets.last().unwrap() + self.token_size(*token));
+        }
+
+        // each interval of the suffix array whose suffixes share a longest
+        // common prefix is a repeated sequence, occurring at the start of
+        // each suffix
+        let mut scored = Vec::new();
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        for i in 1..=suffixes.len() {
+            let len = lcp.get(i).copied().unwrap_or_default();
+            let mut lower = i - 1;
+            while len < stack.last().unwrap().0 {
+                let (interval_len, interval_lower) = stack.pop().unwrap();
+                lower = interval_lower;
+                let start = suffixes[lower];
+                let size = offsets[start + interval_len] - offsets[start];
+                let count = i - lower;
+                let savings = (count * size.saturating_sub(CALL_SIZE)) as isize
+                    - (size + SUBR_OVERHEAD) as isize;
+                if savings > 0 {
+                    scored.push((savings, interval_len, size, lower..i));
+                }
+            }
+            if len > stack.last().unwrap().0 {
+                stack.push((len, lower));
+            }
+        }
+        scored.sort_by_key(|(savings, ..)| std::cmp::Reverse(*savings));
+        scored.truncate(MAX_SUBRS);
+
+        self.matches = vec![Vec::new(); self.seq.len()];
+        for (_, len, size, interval) in scored {
+            let index = self.candidates.len();
+            let occurrences = &suffixes[interval];
+            let start = occurrences.iter().min().copied().unwrap_or_default();
+            self.candidates.push(Candidate {
+                start,
+                len,
+                size,
+                count: occurrences.len(),
+            });
+            for position in occurrences {
+                self.matches[*position].push(index);
+            }
+        }
+        self.by_len = (0..self.candidates.len()).collect();
+        self.by_len.sort_by_key(|index| self.candidates[*index].len);
+    }
+
+    /// Chooses the candidates that become subroutines, and encodes the
+    /// glyphs with them.
+    ///
+    /// The price of calling a candidate is the size of the call, and its
+    /// share of the size of the subroutine. Prices are updated from the
+    /// number of calls each time the glyphs are encoded, so that
+    /// candidates that are used more often become cheaper.
+    fn select(&self) -> Encoding {
+        let mut selected = vec![true; self.candidates.len()];
+        let mut call_sizes = vec![CALL_SIZE; self.candidates.len()];
+        let mut prices: Vec<_> = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let share = (candidate.size + SUBR_OVERHEAD) as f64 / candidate.count as f64;
+                CALL_SIZE as f64 + share
+            })
+            .collect();
+        let mut iteration = 0;
+        loop {
+            iteration += 1;
+            let encoding = self.encode_all(&selected, &prices);
+            for (index, uses) in encoding.usage.iter().enumerate() {
+                if *uses == 0 {
+                    selected[index] = false;
+                }
+            }
+            if iteration == MAX_ITERATIONS {
+                return encoding;
+            }
+
+            // discard the subroutines that cost more than they save
+            let mut changed = false;
+            for index in 0..self.candidates.len() {
+                if !selected[index] {
+                    continue;
+                }
+                let size = self.parts_size(&encoding.bodies[index], &call_sizes);
+                let uses = encoding.usage[index];
+                prices[index] =
+                    call_sizes[index] as f64 + (size + SUBR_OVERHEAD) as f64 / uses.max(1) as f64;
+                let savings = (uses * size.saturating_sub(call_sizes[index])) as isize
+                    - (size + SUBR_OVERHEAD) as isize;
+                if iteration > PRICE_ITERATIONS && (uses < 2 || savings <= 0) {
+                    selected[index] = false;
+                    changed = true;
+                }
+            }
+            if iteration > PRICE_ITERATIONS && !changed {
+                return encoding;
+            }
+
+            // the most used subroutines get the smallest numbers
+            let mut by_usage: Vec<_> = (0..self.candidates.len())
+                .filter(|index| selected[*index])
+                .collect();
+            by_usage.sort_by_key(|index| std::cmp::Reverse(encoding.usage[*index]));
+            let bias = subr_bias(by_usage.len());
+            for (number, index) in by_usage.into_iter().enumerate() {
+                call_sizes[index] = number_size(number as i32 - bias) + 1;
+            }
+        }
+    }
+
+    /// Encodes the bodies of the selected candidates, and then the glyphs,
+    /// and counts the calls to each candidate.
+    fn encode_all(&self, selected: &[bool], prices: &[f64]) -> Encoding {
+        let mut bodies = vec![Vec::new(); self.candidates.len()];
+        let mut heights = vec![0; self.candidates.len()];
+        for index in self.by_len.iter().copied().filter(|index| selected[*index]) {
+            let candidate = &self.candidates[index];
+            let range = candidate.start..candidate.start + candidate.len;
+            // a subroutine is called at a nesting depth of at least one
+            let (parts, height) = self.encode(
+                range,
+                Some(index),
+                NESTING_DEPTH_LIMIT as usize - 1,
+                selected,
+                prices,
+                &heights,
+            );
+            bodies[index] = parts;
+            heights[index] = height + 1;
+        }
+        let glyphs: Vec<_> = self
+            .glyphs
+            .iter()
+            .map(|(range, _)| {
+                let max_height = NESTING_DEPTH_LIMIT as usize;
+                self.encode(range.clone(), None, max_height, selected, prices, &heights)
+                    .0
+            })
+            .collect();
+
+        let mut usage = vec![0; self.candidates.len()];
+        for parts in &glyphs {
+            count_calls(parts, &mut usage);
+        }
+        for index in self.by_len.iter().rev() {
+            if usage[*index] > 0 {
+                count_calls(&bodies[*index], &mut usage);
+            }
+        }
+        Encoding {
+            glyphs,
+            bodies,
+            usage,
+            heights,
+        }
+    }
+
+    /// Encodes the tokens in `range` at the lowest price, using the
+    /// selected candidates whose height is at most `max_height`.
+    ///
+    /// Returns the parts, and the height of the tallest candidate called.
+    fn encode(
+        &self,
+        range: Range<usize>,
+        exclude: Option<usize>,
+        max_height: usize,
+        selected: &[bool],
+        prices: &[f64],
+        heights: &[usize],
+    ) -> (Vec<Part>, usize) {
+        let len = range.len();
+        // the lowest price of the tokens after each position, and the
+        // candidate to call at that position to achieve it
+        let mut sizes = vec![0.0; len + 1];
+        let mut calls = vec![None; len];
+        for i in (0..len).rev() {
+            let position = range.start + i;
+            sizes[i] = self.token_size(self.seq[position]) as f64 + sizes[i + 1];
+            for index in self.matches[position].iter().copied() {
+                let candidate = &self.candidates[index];
+                if !selected[index]
+                    || Some(index) == exclude
+                    || i + candidate.len > len
+                    || heights[index] > max_height
+                {
+                    continue;
+                }
+                let size = prices[index] + sizes[i + candidate.len];
+                if size < sizes[i] {
+                    sizes[i] = size;
+                    calls[i] = Some(index);
+                }
+            }
+        }
+
+        let mut parts = Vec::new();
+        let mut height = 0;
+        let mut i = 0;
+        while i < len {
+            match calls[i] {
+                Some(index) => {
+                    parts.push(Part::Call(index));
+                    height = height.max(heights[index]);
+                    i += self.candidates[index].len;
+                }
+                None => {
+                    parts.push(Part::Token(self.seq[range.start + i]));
+                    i += 1;
+                }
+            }
+        }
+        (parts, height)
+    }
+
+    fn parts_size(&self, parts: &[Part], call_sizes: &[usize]) -> usize {
+        parts
+            .iter()
+            .map(|part| match part {
+                Part::Token(token) => self.token_size(*token),
+                Part::Call(index) => call_sizes[*index],
+            })
+            .sum()
+    }
+
+    /// Numbers the subroutines that are used, and writes the glyphs and
+    /// subroutines.
+    fn write(&self, encoding: &Encoding, num_fds: usize) -> Subroutines {
+        // callers come before their callees
+        let used: Vec<_> = self
+            .by_len
+            .iter()
+            .rev()
+            .copied()
+            .filter(|index| encoding.usage[*index] > 0)
+            .collect();
+        debug_assert!(used
+            .iter()
+            .all(|index| encoding.heights[*index] <= NESTING_DEPTH_LIMIT as usize));
+
+        // the Font DICTs of the glyphs that use each subroutine, directly or
+        // through other subroutines
+        let mut users = vec![Users::None; self.candidates.len()];
+        for (parts, (_, fd)) in encoding.glyphs.iter().zip(&self.glyphs) {
+            for part in parts {
+                if let Part::Call(index) = part {
+                    users[*index] = users[*index].add(Users::One(*fd));
+                }
+            }
+        }
+        for index in used.iter().copied() {
+            for part in &encoding.bodies[index] {
+                if let Part::Call(callee) = part {
+                    users[*callee] = users[*callee].add(users[index]);
+                }
+            }
+        }
+
+        let mut global = Vec::new();
+        let mut local = vec![Vec::new(); num_fds];
+        for index in used {
+            match users[index] {
+                Users::One(fd) => local[fd as usize].push(index),
+                _ => global.push(index),
+            }
+        }
+        let mut locations = vec![(CALLGSUBR, 0); self.candidates.len()];
+        for (subrs, op) in std::iter::once((&mut global, CALLGSUBR))
+            .chain(local.iter_mut().map(|subrs| (subrs, CALLSUBR)))
+        {
+            subrs.sort_by_key(|index| std::cmp::Reverse(encoding.usage[*index]));
+            let bias = subr_bias(subrs.len());
+            for (number, index) in subrs.iter().enumerate() {
+                locations[*index] = (op, number as i32 - bias);
+            }
+        }
+
+        let write_subr = |index: &usize| {
+            let parts = &encoding.bodies[*index];
+            let mut data = self.write_parts(parts, &locations);
+            let ends_glyph = matches!(
+                parts.last(), Some(Part::Token(token)) if self.ends_glyph[*token as usize]
+            );
+            if !self.is_cff2 && !ends_glyph {
+                data.push(RETURN);
+            }
+            data
+        };
+        Subroutines {
+            charstrings: encoding
+                .glyphs
+                .iter()
+                .map(|parts| self.write_parts(parts, &locations))
+                .collect(),
+            global_subrs: global.iter().map(write_subr).collect(),
+            local_subrs: local
+                .iter()
+                .map(|subrs| subrs.iter().map(write_subr).collect())
+                .collect(),
+        }
+    }
+
+    fn write_parts(&self, parts: &[Part], locations: &[(u8, i32)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for part in parts {
+            match part {
+                Part::Token(token) => data.extend_from_slice(self.tokens[*token as usize]),
+                Part::Call(index) => {
+                    let (op, number) = locations[*index];
+                    NumberEncoding::Charstring.write_number(&mut data, number as f64);
+                    data.push(op);
+                }
+            }
+        }
+        data
+    }
+}
+
+/// The Font DICTs of the glyphs that use a subroutine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Users {
+    None,
+    One(u16),
+    Many,
+}
+
+impl Users {
+    fn add(self, other: Users) -> Users {
+        match (self, other) {
+            (Users::None, users) | (users, Users::None) => users,
+            (Users::One(a), Users::One(b)) if a == b => self,
+            _ => Users::Many,
+        }
+    }
+}
+
+fn count_calls(parts: &[Part], usage: &mut [usize]) {
+    for part in parts {
+        if let Part::Call(index) = part {
+            usage[*index] += 1;
+        }
+    }
+}
+
+/// The bias that is added to subroutine numbers, for an INDEX with `count`
+/// subroutines.
+fn subr_bias(count: usize) -> i32 {
+    match count {
+        0..=1239 => 107,
+        1240..=33899 => 1131,
+        _ => 32768,
+    }
+}
+
+/// The size of an integer in a charstring.
+fn number_size(value: i32) -> usize {
+    match value {
+        -107..=107 => 1,
+        -1131..=1131 => 2,
+        _ => 3,
+    }
+}
+
+/// Splits a charstring into commands, after each of which the argument
+/// stack is empty.
+///
+/// Returns the end of each command, and whether the first command includes
+/// the width of a Type 2 charstring, or `None` if the charstring calls
+/// subroutines, changes its variation data or is malformed.
+fn split_commands(data: &[u8], is_cff2: bool, num_regions: usize) -> Option<(Vec<usize>, bool)> {
+    let mut ends = Vec::new();
+    let mut has_width = false;
+    let mut num_operands = 0;
+    let mut last_int = 0;
+    let mut num_stems = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let b0 = data[i];
+        let (value, len) = match b0 {
+            32..=246 => (b0 as i32 - 139, 1),
+            247..=250 => ((b0 as i32 - 247) * 256 + *data.get(i + 1)? as i32 + 108, 2),
+            251..=254 => (-(b0 as i32 - 251) * 256 - *data.get(i + 1)? as i32 - 108, 2),
+            28 => (
+                i16::from_be_bytes(data.get(i + 1..i + 3)?.try_into().ok()?) as i32,
+                3,
+            ),
+            255 => (0, 5),
+            _ => (0, 0),
+        };
+        if len > 0 {
+            num_operands += 1;
+            last_int = value;
+            i += len;
+            continue;
+        }
+
+        let is_first = ends.is_empty() && !is_cff2;
+        let mut len = if b0 == ESCAPE { 2 } else { 1 };
+        match b0 {
+            BLEND if is_cff2 => {
+                let count = usize::try_from(last_int).ok()?;
+                num_operands = usize::checked_sub(num_operands, count * num_regions + 1)? + count;
+                i += 1;
+                continue;
+            }
+            VSINDEX if is_cff2 => return None,
+            CALLSUBR | CALLGSUBR | RETURN => return None,
+            HSTEM | VSTEM | HSTEMHM | VSTEMHM | HINTMASK | CNTRMASK => {
+                // operands before a mask are an implicit vstemhm
+                let width = is_first && num_operands % 2 == 1;
+                has_width |= width;
+                num_stems += (num_operands - width as usize) / 2;
+                if matches!(b0, HINTMASK | CNTRMASK) {
+                    len += num_stems.div_ceil(8);
+                }
+            }
+            RMOVETO => has_width |= is_first && num_operands > 2,
+            HMOVETO | VMOVETO => has_width |= is_first && num_operands > 1,
+            ENDCHAR => has_width |= is_first && matches!(num_operands, 1 | 5),
+            _ => (),
+        }
+        i += len;
+        ends.push(i);
+        num_operands = 0;
+    }
+    // operands must be followed by an operator
+    (i == data.len() && num_operands == 0).then_some((ends, has_width))
+}
+
+/// Returns the starting positions of the suffixes of `seq`, in sorted
+/// order, by prefix doubling.
+fn suffix_array(seq: &[u32]) -> Vec<usize> {
+    let len = seq.len();
+    let mut suffixes: Vec<_> = (0..len).collect();
+    let mut ranks: Vec<_> = seq.iter().map(|token| *token as usize).collect();
+    let mut next_ranks = vec![0; len];
+    let mut k = 1;
+    while k < len {
+        // sort by the ranks of the first 2k tokens
+        let key = |ranks: &[usize], i: usize| (ranks[i], ranks.get(i + k).map_or(0, |r| r + 1));
+        suffixes.sort_unstable_by_key(|i| key(&ranks, *i));
+        next_ranks[suffixes[0]] = 0;
+        for j in 1..len {
+            let is_new = key(&ranks, suffixes[j - 1]) != key(&ranks, suffixes[j]);
+            next_ranks[suffixes[j]] = next_ranks[suffixes[j - 1]] + is_new as usize;
+        }
+        std::mem::swap(&mut ranks, &mut next_ranks);
+        if ranks[suffixes[len - 1]] == len - 1 {
+            break;
+        }
+        k *= 2;
+    }
+    suffixes
+}
+
+/// Returns the length of the longest common prefix of each suffix and the
+/// one before it in the suffix array, with Kasai's algorithm.
+fn lcp_array(seq: &[u32], suffixes: &[usize]) -> Vec<usize> {
+    let mut ranks = vec![0; seq.len()];
+    for (rank, suffix) in suffixes.iter().enumerate() {
+        ranks[*suffix] = rank;
+    }
+    let mut lcp = vec![0; seq.len()];
+    let mut len = 0;
+    for (i, rank) in ranks.iter().enumerate() {
+        if *rank == 0 {
+            len = 0;
+            continue;
+        }
+        let j = suffixes[rank - 1];
+        while i + len < seq.len() && j + len < seq.len() && seq[i + len] == seq[j + len] {
+            len += 1;
+        }
+        lcp[*rank] = len;
+        len = len.saturating_sub(1);
+    }
+    lcp
+}
+
+#[cfg(test)]
+mod tests {
+    use read_fonts::tables::postscript::{charstring, Index};
+    use types::{Fixed, Pen};
+
+    use super::*;
+    use crate::tables::postscript::{write_index, CharstringBuilder};
+
+    /// Records everything that a charstring does.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl charstring::CommandSink for Recorder {
+        fn move_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push(format!("M {x} {y}"));
+        }
+
+        fn line_to(&mut self, x: Fixed, y: Fixed) {
+            self.0.push(format!("L {x} {y}"));
+        }
+
+        fn curve_to(&mut self, cx0: Fixed, cy0: Fixed, cx1: Fixed, cy1: Fixed, x: Fixed, y: Fixed) {
+            self.0.push(format!("C {cx0} {cy0} {cx1} {cy1} {x} {y}"));
+        }
+
+        fn close(&mut self) {
+            self.0.push("Z".into());
+        }
+
+        fn hstem(&mut self, y: Fixed, dy: Fixed) {
+            self.0.push(format!("hstem {y} {dy}"));
+        }
+
+        fn vstem(&mut self, x: Fixed, dx: Fixed) {
+            self.0.push(format!("vstem {x} {dx}"));
+        }
+
+        fn hint_mask(&mut self, mask: &[u8]) {
+            self.0.push(format!("hintmask {mask:?}"));
+        }
+    }
+
+    fn evaluate(data: &[u8], global_subrs: &[u8], local_subrs: &[u8]) -> Vec<String> {
+        // an empty INDEX is followed by a byte that would hold the size of
+        // its offsets
+        let global_subrs = [global_subrs, &[0]].concat();
+        let local_subrs = [local_subrs, &[0]].concat();
+        let global_subrs = Index::new(&global_subrs, false).unwrap();
+        let local_subrs = Index::new(&local_subrs, false).unwrap();
+        let mut recorder = Recorder::default();
+        charstring::evaluate(data, global_subrs, Some(local_subrs), None, &mut recorder).unwrap();
+        recorder.0
+    }
+
+    /// Checks that each subroutinized glyph does the same as the original,
+    /// and returns the total size of the charstrings and subroutines.
+    fn check_round_trip(charstrings: &[Vec<u8>], fds: &[u16], subrs: &Subroutines) -> usize {
+        let global_subrs = write_index(subrs.global_subrs.iter().map(Vec::as_slice), false);
+        let local_subrs: Vec<_> = subrs
+            .local_subrs
+            .iter()
+            .map(|subrs| write_index(subrs.iter().map(Vec::as_slice), false))
+            .collect();
+        for ((original, subroutinized), fd) in charstrings.iter().zip(&subrs.charstrings).zip(fds) {
+            let local_subrs = &local_subrs[*fd as usize];
+            assert_eq!(
+                evaluate(subroutinized, &global_subrs, local_subrs),
+                evaluate(original, &[0, 0], &[0, 0])
+            );
+        }
+        global_subrs.len()
+            + local_subrs.iter().map(Vec::len).sum::<usize>()
+            + subrs.charstrings.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Draws the same shape, relative to the current point.
+    fn serif(builder: &mut CharstringBuilder, x: f32, y: f32) {
+        builder.line_to(x + 15.0, y);
+        builder.curve_to(x + 40.0, y, x + 60.0, y + 20.0, x + 60.0, y + 45.0);
+        builder.line_to(x + 60.0, y + 300.0);
+        builder.curve_to(x + 60.0, y + 330.0, x + 30.0, y + 350.0, x, y + 350.0);
+    }
+
+    fn glyph(x: f32, is_hinted: bool, fd_shape: f32) -> Vec<u8> {
+        let mut builder = CharstringBuilder::new();
+        if is_hinted {
+            builder
+                .hstem(0.0, 20.0)
+                .vstem(x as f64, 30.0)
+                .hint_mask([0, 1]);
+        }
+        builder.move_to(x, 0.0);
+        serif(&mut builder, x, 0.0);
+        builder.line_to(x + fd_shape, 600.0);
+        builder.curve_to(x - 100.0, 600.0, x - 200.0, 500.0, x - 200.0, 400.0);
+        builder.close();
+        builder.move_to(x + 500.0, 0.0);
+        serif(&mut builder, x + 500.0, 0.0);
+        builder.close();
+        builder.build().compile_type2(Some(x as f64)).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let charstrings: Vec<_> = (0..20)
+            .map(|i| glyph(i as f32 * 10.0, i % 2 == 0, 0.0))
+            .collect();
+        let fds = vec![0; charstrings.len()];
+        let subrs = subroutinize(&charstrings, &fds, &[0], false);
+        assert!(subrs.global_subrs.is_empty());
+        assert!(!subrs.local_subrs[0].is_empty());
+        let size = check_round_trip(&charstrings, &fds, &subrs);
+        let original_size: usize = charstrings.iter().map(Vec::len).sum();
+        assert!(size < original_size * 2 / 3, "{size} vs {original_size}");
+    }
+
+    #[test]
+    fn local_and_global_subrs() {
+        // the glyphs of each Font DICT share a shape, and all glyphs share
+        // the serifs
+        let mut charstrings = Vec::new();
+        let mut fds = Vec::new();
+        for i in 0..30 {
+            let fd = i % 3;
+            charstrings.push(glyph(i as f32, false, 1000.0 * fd as f32));
+            fds.push(fd as u16);
+        }
+        let subrs = subroutinize(&charstrings, &fds, &[0, 0, 0], false);
+        assert!(!subrs.global_subrs.is_empty());
+        assert!(subrs.local_subrs.iter().all(|subrs| !subrs.is_empty()));
+        check_round_trip(&charstrings, &fds, &subrs);
+    }
+
+    /// Returns the deepest nesting of subroutine calls in a charstring
+    /// without hints.
+    fn call_depth(data: &[u8], subrs: &[Vec<u8>]) -> usize {
+        let bias = subr_bias(subrs.len());
+        let mut depth = 0;
+        let mut last_int = 0;
+        let mut i = 0;
+        while i < data.len() {
+            match data[i] {
+                32..=246 => {
+                    last_int = data[i] as i32 - 139;
+                    i += 1;
+                }
+                247..=254 => {
+                    let sign = if data[i] < 251 { 1 } else { -1 };
+                    let b0 = data[i] as i32 - if sign > 0 { 247 } else { 251 };
+                    last_int = sign * (b0 * 256 + data[i + 1] as i32 + 108);
+                    i += 2;
+                }
+                28 => {
+                    last_int = i16::from_be_bytes([data[i + 1], data[i + 2]]) as i32;
+                    i += 3;
+                }
+                CALLSUBR => {
+                    let subr = &subrs[(last_int + bias) as usize];
+                    depth = depth.max(call_depth(subr, subrs) + 1);
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+        depth
+    }
+
+    #[test]
+    fn nesting_depth_limit() {
+        // each sequence is the one before it twice, around a new command,
+        // so that each subroutine would call the one before it
+        let mut data: Vec<u8> = Vec::new();
+        for i in 0..NESTING_DEPTH_LIMIT as i16 + 2 {
+            let mut command = vec![28];
+            command.extend((1000 + i).to_be_bytes());
+            command.extend([139 + 20, HSTEM]);
+            data = [data.as_slice(), &command, &data].concat();
+        }
+        data.push(ENDCHAR);
+        let charstrings = vec![data];
+
+        // with every candidate, subroutines are nested as deeply as allowed
+        let mut subroutinizer = Subroutinizer::new(&charstrings, &[0], &[0], false);
+        subroutinizer.find_candidates();
+        let num_candidates = subroutinizer.candidates.len();
+        let encoding = subroutinizer.encode_all(
+            &vec![true; num_candidates],
+            &vec![CALL_SIZE as f64; num_candidates],
+        );
+        let max_height = encoding.heights.iter().max().copied();
+        assert_eq!(max_height, Some(NESTING_DEPTH_LIMIT as usize));
+
+        let subrs = subroutinize(&charstrings, &[0], &[0], false);
+        // evaluation also fails if the nesting limit is exceeded
+        let size = check_round_trip(&charstrings, &[0], &subrs);
+        assert!(size < charstrings[0].len() / 10);
+        let depth = call_depth(&subrs.charstrings[0], &subrs.local_subrs[0]);
+        assert!((2..=NESTING_DEPTH_LIMIT as usize).contains(&depth));
+    }
+
+    #[test]
+    fn split() {
+        // a width, nine stems and a hint mask whose first byte is the same
+        // as `endchar`, then a move and `endchar`
+        let mut data = vec![139 + 50, 139, 139 + 20, HSTEMHM];
+        data.extend([139 + 10; 16]);
+        data.extend([VSTEMHM, HINTMASK, ENDCHAR, 0b1000_0000]);
+        data.extend([139, 139, RMOVETO, ENDCHAR]);
+        assert_eq!(
+            split_commands(&data, false, 0),
+            Some((vec![4, 21, 24, 27, 28], true))
+        );
+        // blends pop their deltas
+        let data = [139, 139 + 1, 139 + 2, 139 + 3, 139 + 2, BLEND, RMOVETO];
+        assert_eq!(split_commands(&data, true, 1), Some((vec![7], false)));
+        // calls and trailing operands
+        assert_eq!(split_commands(&[139, CALLSUBR], false, 0), None);
+        assert_eq!(split_commands(&[139, 139], false, 0), None);
+    }
+}
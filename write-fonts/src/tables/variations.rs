@@ -5,6 +5,7 @@ include!("../../generated/generated_variations.rs");
 pub use read_fonts::tables::variations::{TupleIndex, TupleVariationCount};
 
 pub mod ivs_builder;
+pub mod model;
 
 impl TupleVariationHeader {
     pub fn new(
@@ -92,6 +92,14 @@ impl VariationStoreBuilder {
         self.delta_sets.add(DeltaSet(delta_set))
     }
 
+    /// Returns `true` if no deltas have been added.
+    pub fn is_empty(&self) -> bool {
+        match &self.delta_sets {
+            DeltaSetStorage::Direct(deltas) => deltas.is_empty(),
+            DeltaSetStorage::Deduplicated(deltas) => deltas.is_empty(),
+        }
+    }
+
     fn canonical_index_for_region(&mut self, region: VariationRegion) -> usize {
         let next_idx = self.all_regions.len();
         *self.all_regions.entry(region).or_insert(next_idx)
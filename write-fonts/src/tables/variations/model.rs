@@ -0,0 +1,670 @@
+//! Computing deltas from master values
+//!
+//! This is a port of the `VariationModel` in fontTools:
+//! <https://github.com/fonttools/fonttools/blob/main/Lib/fontTools/varLib/models.py>
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use font_types::F2Dot14;
+use kurbo::Point;
+
+use crate::tables::gvar::{GlyphDelta, GlyphDeltas};
+
+use super::{RegionAxisCoordinates, Tuple, VariationRegion};
+
+/// A location in the normalized design space: the coordinate on each axis,
+/// by axis index.
+///
+/// Axes that are not included are at their default location.
+pub type NormalizedLocation = BTreeMap<u16, F2Dot14>;
+
+/// The region of influence of a master, as (start, peak, end) for each axis.
+type Support = BTreeMap<u16, (f64, f64, f64)>;
+
+/// An error when building or using a [`VariationModel`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VariationModelError {
+    /// There is no master at the default location.
+    MissingDefault,
+    /// More than one master has this location.
+    DuplicateLocation(NormalizedLocation),
+    /// A location includes an axis index greater than the axis count.
+    AxisOutOfRange(u16),
+    /// The number of values does not match the number of masters.
+    WrongValueCount { expected: usize, actual: usize },
+    /// The masters of a glyph have different numbers of points.
+    InconsistentPointCount,
+    /// A delta is too large for the type it is stored as.
+    Overflow,
+}
+
+/// A model of how values vary between masters.
+///
+/// This assigns each master a region of influence (its support), such that
+/// the value at any location is the sum of the deltas of each master,
+/// scaled by how far the location is within its support.
+///
+/// Some masters may not have a value; these are 'sparse' masters, and the
+/// deltas are computed using a model of the other masters.
+#[derive(Clone, Debug)]
+pub struct VariationModel {
+    axis_count: u16,
+    /// The locations in the order given by the user.
+    original_locations: Vec<NormalizedLocation>,
+    /// The locations sorted so that each master only depends on earlier ones.
+    locations: Vec<NormalizedLocation>,
+    /// For each sorted location, its index in the original locations.
+    reverse_mapping: Vec<usize>,
+    supports: Vec<Support>,
+    /// For each master, the scalar of each earlier master at its location.
+    delta_weights: Vec<Vec<(usize, f64)>>,
+}
+
+impl VariationModel {
+    /// Create a model for masters at these locations.
+    ///
+    /// One of the locations must be the default location.
+    pub fn new(
+        locations: Vec<NormalizedLocation>,
+        axis_count: u16,
+    ) -> Result<Self, VariationModelError> {
+        let original_locations = locations
+            .into_iter()
+            .map(|loc| {
+                loc.into_iter()
+                    .filter(|(_, value)| *value != F2Dot14::ZERO)
+                    .collect::<NormalizedLocation>()
+            })
+            .collect::<Vec<_>>();
+        if let Some(axis) = original_locations
+            .iter()
+            .flat_map(|loc| loc.keys())
+            .find(|axis| **axis >= axis_count)
+        {
+            return Err(VariationModelError::AxisOutOfRange(*axis));
+        }
+        let mut seen = BTreeSet::new();
+        for loc in &original_locations {
+            if !seen.insert(loc) {
+                return Err(VariationModelError::DuplicateLocation(loc.clone()));
+            }
+        }
+        if !seen.contains(&NormalizedLocation::new()) {
+            return Err(VariationModelError::MissingDefault);
+        }
+
+        let mut reverse_mapping = (0..original_locations.len()).collect::<Vec<_>>();
+        let sort_key = master_sort_key(&original_locations);
+        reverse_mapping.sort_by_cached_key(|i| sort_key(&original_locations[*i]));
+        let locations = reverse_mapping
+            .iter()
+            .map(|i| original_locations[*i].clone())
+            .collect::<Vec<_>>();
+
+        let supports = compute_supports(&locations);
+        let delta_weights = locations
+            .iter()
+            .enumerate()
+            .map(|(i, loc)| {
+                supports[..i]
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(j, support)| {
+                        let scalar = support_scalar(loc, support);
+                        (scalar != 0.0).then_some((j, scalar))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            axis_count,
+            original_locations,
+            locations,
+            reverse_mapping,
+            supports,
+            delta_weights,
+        })
+    }
+
+    /// The locations of the masters, in the order they are processed.
+    ///
+    /// The default location is always first.
+    pub fn locations(&self) -> &[NormalizedLocation] {
+        &self.locations
+    }
+
+    /// The regions of the non-default masters, in the order of
+    /// [`locations`][Self::locations].
+    pub fn regions(&self) -> Vec<VariationRegion> {
+        self.supports[1..]
+            .iter()
+            .map(|support| self.region(support))
+            .collect()
+    }
+
+    /// Compute the deltas of a scalar value, such as a metric.
+    ///
+    /// `values` has a value for each master, in the order the locations were
+    /// given; `None` indicates a sparse master. Deltas are rounded, and
+    /// returned with the region of each non-default master, suitable for
+    /// [`VariationStoreBuilder`][super::ivs_builder::VariationStoreBuilder].
+    pub fn scalar_deltas(
+        &self,
+        values: &[Option<f64>],
+    ) -> Result<Vec<(VariationRegion, i32)>, VariationModelError> {
+        let values = values
+            .iter()
+            .map(|value| value.map(|value| vec![value]))
+            .collect::<Vec<_>>();
+        let (model, deltas) = self.deltas(&values)?;
+        model.supports[1..]
+            .iter()
+            .zip(&deltas[1..])
+            .map(|(support, delta)| Ok((model.region(support), checked_delta(delta[0])?)))
+            .collect()
+    }
+
+    /// Compute the deltas of the points of a glyph, for gvar.
+    ///
+    /// `values` has the points of each master, in the order the locations
+    /// were given; `None` indicates a sparse master. Deltas are rounded, and
+    /// masters whose deltas are all zero are omitted.
+    pub fn glyph_deltas(
+        &self,
+        values: &[Option<Vec<Point>>],
+    ) -> Result<Vec<GlyphDeltas>, VariationModelError> {
+        let values = values
+            .iter()
+            .map(|points| {
+                points
+                    .as_ref()
+                    .map(|points| points.iter().flat_map(|pt| [pt.x, pt.y]).collect())
+            })
+            .collect::<Vec<_>>();
+        let (model, deltas) = self.deltas(&values)?;
+        model.supports[1..]
+            .iter()
+            .zip(&deltas[1..])
+            .filter(|(_, deltas)| deltas.iter().any(|delta| *delta != 0.0))
+            .map(|(support, deltas)| {
+                let (peak, intermediate) = model.tuples(support);
+                let deltas = deltas
+                    .chunks_exact(2)
+                    .map(|delta| {
+                        Ok(GlyphDelta::required(
+                            checked_delta(delta[0])?,
+                            checked_delta(delta[1])?,
+                        ))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(GlyphDeltas::new(peak, deltas, intermediate))
+            })
+            .collect()
+    }
+
+    /// Compute the rounded deltas for each master, in model order.
+    ///
+    /// If any masters are sparse, this uses a model of the other masters,
+    /// which is returned along with the deltas.
+    fn deltas(
+        &self,
+        values: &[Option<Vec<f64>>],
+    ) -> Result<(std::borrow::Cow<'_, Self>, Vec<Vec<f64>>), VariationModelError> {
+        if values.len() != self.original_locations.len() {
+            return Err(VariationModelError::WrongValueCount {
+                expected: self.original_locations.len(),
+                actual: values.len(),
+            });
+        }
+        let len = values.iter().flatten().map(Vec::len).next().unwrap_or(0);
+        if values.iter().flatten().any(|value| value.len() != len) {
+            return Err(VariationModelError::InconsistentPointCount);
+        }
+        if values.iter().any(Option::is_none) {
+            let (locations, values): (Vec<_>, Vec<_>) = self
+                .original_locations
+                .iter()
+                .zip(values)
+                .filter(|(_, value)| value.is_some())
+                .map(|(loc, value)| (loc.clone(), value.clone()))
+                .unzip();
+            let model = VariationModel::new(locations, self.axis_count)?;
+            let (_, deltas) = model.deltas(&values)?;
+            return Ok((std::borrow::Cow::Owned(model), deltas));
+        }
+
+        let mut out: Vec<Vec<f64>> = Vec::with_capacity(values.len());
+        for (i, weights) in self.delta_weights.iter().enumerate() {
+            let mut delta = values[self.reverse_mapping[i]].clone().unwrap();
+            for (j, weight) in weights {
+                for (value, prev) in delta.iter_mut().zip(&out[*j]) {
+                    *value -= prev * weight;
+                }
+            }
+            // round each delta before it is used by later masters, as
+            // fontTools does when building fonts
+            delta.iter_mut().for_each(|value| *value = ot_round(*value));
+            out.push(delta);
+        }
+        Ok((std::borrow::Cow::Borrowed(self), out))
+    }
+
+    fn region(&self, support: &Support) -> VariationRegion {
+        VariationRegion::new(
+            (0..self.axis_count)
+                .map(|axis| {
+                    let (start, peak, end) = support.get(&axis).copied().unwrap_or_default();
+                    RegionAxisCoordinates {
+                        start_coord: F2Dot14::from_f32(start as f32),
+                        peak_coord: F2Dot14::from_f32(peak as f32),
+                        end_coord: F2Dot14::from_f32(end as f32),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// The peak tuple of a support, and its intermediate region if it is
+    /// not the one implied by the peak.
+    fn tuples(&self, support: &Support) -> (Tuple, Option<(Tuple, Tuple)>) {
+        let region = self.region(support);
+        let peak = region
+            .region_axes
+            .iter()
+            .map(|axis| axis.peak_coord)
+            .collect();
+        let is_implied = region.region_axes.iter().all(|axis| {
+            axis.start_coord == axis.peak_coord.min(F2Dot14::ZERO)
+                && axis.end_coord == axis.peak_coord.max(F2Dot14::ZERO)
+        });
+        let intermediate = (!is_implied).then(|| {
+            let start = region.region_axes.iter().map(|axis| axis.start_coord);
+            let end = region.region_axes.iter().map(|axis| axis.end_coord);
+            (Tuple::new(start.collect()), Tuple::new(end.collect()))
+        });
+        (Tuple::new(peak), intermediate)
+    }
+}
+
+/// The key used to order masters; see [`master_sort_key`].
+type SortKey = (usize, std::cmp::Reverse<usize>, Vec<u16>, Vec<i8>, Vec<i32>);
+
+/// The key used to order masters.
+///
+/// Masters are ordered by the number of axes they are on, then by how many
+/// of those axes have a master on that axis alone at the same coordinate,
+/// and then by axis, sign and magnitude.
+fn master_sort_key(locations: &[NormalizedLocation]) -> impl Fn(&NormalizedLocation) -> SortKey {
+    let axis_points = locations
+        .iter()
+        .filter(|loc| loc.len() == 1)
+        .flat_map(|loc| loc.iter().map(|(axis, value)| (*axis, *value)))
+        .collect::<BTreeSet<_>>();
+    move |loc| {
+        let on_point = loc
+            .iter()
+            .filter(|(axis, value)| axis_points.contains(&(**axis, **value)))
+            .count();
+        (
+            loc.len(),
+            std::cmp::Reverse(on_point),
+            loc.keys().copied().collect(),
+            loc.values()
+                .map(|value| value.to_bits().signum() as i8)
+                .collect(),
+            loc.values()
+                .map(|value| (value.to_bits() as i32).abs())
+                .collect(),
+        )
+    }
+}
+
+/// Compute the support of each master, given the sorted locations.
+fn compute_supports(locations: &[NormalizedLocation]) -> Vec<Support> {
+    // the range of each axis across all masters
+    let mut ranges: BTreeMap<u16, (f64, f64)> = BTreeMap::new();
+    for (axis, value) in locations.iter().flat_map(|loc| loc.iter()) {
+        let value = value.to_f32() as f64;
+        let range = ranges.entry(*axis).or_insert((value, value));
+        range.0 = range.0.min(value);
+        range.1 = range.1.max(value);
+    }
+    let mut regions = locations
+        .iter()
+        .map(|loc| {
+            loc.iter()
+                .map(|(axis, value)| {
+                    let value = value.to_f32() as f64;
+                    let (min, max) = ranges[axis];
+                    let triple = if value > 0.0 {
+                        (0.0, value, max)
+                    } else {
+                        (min, value, 0.0)
+                    };
+                    (*axis, triple)
+                })
+                .collect::<Support>()
+        })
+        .collect::<Vec<_>>();
+
+    for i in 0..regions.len() {
+        let (prev_regions, rest) = regions.split_at_mut(i);
+        let region = &mut rest[0];
+        for prev in prev_regions.iter() {
+            // masters on other axes don't participate
+            if !prev.keys().eq(region.keys()) {
+                continue;
+            }
+            // nor do masters outside the current box
+            let relevant = region.iter().all(|(axis, (lower, peak, upper))| {
+                let prev_peak = prev[axis].1;
+                prev_peak == *peak || (*lower < prev_peak && prev_peak < *upper)
+            });
+            if !relevant {
+                continue;
+            }
+
+            // split the box in the direction with the largest range ratio;
+            // for symmetry, cut across all axes with an equal ratio.
+            let mut best_axes = BTreeMap::new();
+            let mut best_ratio = -1.0;
+            for (axis, (_, val, _)) in prev.iter() {
+                let (lower, loc_v, upper) = region[axis];
+                let (mut new_lower, mut new_upper) = (lower, upper);
+                let ratio = if *val < loc_v {
+                    new_lower = *val;
+                    (val - loc_v) / (lower - loc_v)
+                } else if loc_v < *val {
+                    new_upper = *val;
+                    (val - loc_v) / (upper - loc_v)
+                } else {
+                    continue;
+                };
+                if ratio > best_ratio {
+                    best_axes.clear();
+                    best_ratio = ratio;
+                }
+                if ratio == best_ratio {
+                    best_axes.insert(*axis, (new_lower, loc_v, new_upper));
+                }
+            }
+            region.extend(best_axes);
+        }
+    }
+    regions
+}
+
+/// The scalar of a support at a location, using OpenType semantics.
+fn support_scalar(location: &NormalizedLocation, support: &Support) -> f64 {
+    let mut scalar = 1.0;
+    for (axis, (lower, peak, upper)) in support {
+        let (lower, peak, upper) = (*lower, *peak, *upper);
+        if peak == 0.0 || lower > peak || peak > upper || (lower < 0.0 && upper > 0.0) {
+            continue;
+        }
+        let value = location
+            .get(axis)
+            .map(|value| value.to_f32() as f64)
+            .unwrap_or(0.0);
+        if value == peak {
+            continue;
+        }
+        if value <= lower || upper <= value {
+            return 0.0;
+        }
+        if value < peak {
+            scalar *= (value - lower) / (peak - lower);
+        } else {
+            scalar *= (value - upper) / (peak - upper);
+        }
+    }
+    scalar
+}
+
+/// Round half up, as fontTools' `otRound`.
+fn ot_round(value: f64) -> f64 {
+    (value + 0.5).floor()
+}
+
+/// Convert a rounded delta to an integer type, if it is in range.
+fn checked_delta<T: TryFrom<i64>>(delta: f64) -> Result<T, VariationModelError> {
+    if !delta.is_finite() {
+        return Err(VariationModelError::Overflow);
+    }
+    T::try_from(delta as i64).map_err(|_| VariationModelError::Overflow)
+}
+
+impl std::fmt::Display for VariationModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariationModelError::MissingDefault => {
+                write!(f, "No master at the default location")
+            }
+            VariationModelError::DuplicateLocation(loc) => {
+                write!(f, "More than one master at location {loc:?}")
+            }
+            VariationModelError::AxisOutOfRange(axis) => {
+                write!(f, "Axis index {axis} is out of range")
+            }
+            VariationModelError::WrongValueCount { expected, actual } => {
+                write!(f, "Expected {expected} master values, found {actual}")
+            }
+            VariationModelError::InconsistentPointCount => {
+                write!(f, "Masters have different numbers of points")
+            }
+            VariationModelError::Overflow => {
+                write!(f, "A delta is out of range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VariationModelError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WGHT: u16 = 0;
+    const WDTH: u16 = 1;
+
+    fn loc(values: &[(u16, f32)]) -> NormalizedLocation {
+        values
+            .iter()
+            .map(|(axis, value)| (*axis, F2Dot14::from_f32(*value)))
+            .collect()
+    }
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 0.002, "{left} != {right}");
+    }
+
+    // from the fontTools test suite
+    #[test]
+    fn supports_and_weights() {
+        let locations = vec![
+            loc(&[(WGHT, 0.55)]),
+            loc(&[(WGHT, -0.55)]),
+            loc(&[(WGHT, -1.0)]),
+            loc(&[(WDTH, 1.0)]),
+            loc(&[(WGHT, 0.66), (WDTH, 1.0)]),
+            loc(&[(WGHT, 0.66), (WDTH, 0.66)]),
+            loc(&[]),
+            loc(&[(WGHT, 1.0), (WDTH, 1.0)]),
+            loc(&[(WGHT, 1.0)]),
+        ];
+        let model = VariationModel::new(locations, 2).unwrap();
+        assert_eq!(
+            model.locations(),
+            [
+                loc(&[]),
+                loc(&[(WGHT, -0.55)]),
+                loc(&[(WGHT, -1.0)]),
+                loc(&[(WGHT, 0.55)]),
+                loc(&[(WGHT, 1.0)]),
+                loc(&[(WDTH, 1.0)]),
+                loc(&[(WDTH, 1.0), (WGHT, 1.0)]),
+                loc(&[(WDTH, 1.0), (WGHT, 0.66)]),
+                loc(&[(WDTH, 0.66), (WGHT, 0.66)]),
+            ]
+        );
+
+        let expected_supports: [&[(u16, _)]; 9] = [
+            &[],
+            &[(WGHT, (-1.0, -0.55, 0.0))],
+            &[(WGHT, (-1.0, -1.0, -0.55))],
+            &[(WGHT, (0.0, 0.55, 1.0))],
+            &[(WGHT, (0.55, 1.0, 1.0))],
+            &[(WDTH, (0.0, 1.0, 1.0))],
+            &[(WDTH, (0.0, 1.0, 1.0)), (WGHT, (0.0, 1.0, 1.0))],
+            &[(WDTH, (0.0, 1.0, 1.0)), (WGHT, (0.0, 0.66, 1.0))],
+            &[(WDTH, (0.0, 0.66, 1.0)), (WGHT, (0.0, 0.66, 1.0))],
+        ];
+        for (support, expected) in model.supports.iter().zip(expected_supports) {
+            assert_eq!(support.len(), expected.len());
+            for (axis, (lower, peak, upper)) in expected {
+                let actual = support[axis];
+                assert_close(actual.0, *lower);
+                assert_close(actual.1, *peak);
+                assert_close(actual.2, *upper);
+            }
+        }
+
+        let expected_weights: [&[(usize, f64)]; 9] = [
+            &[],
+            &[(0, 1.0)],
+            &[(0, 1.0)],
+            &[(0, 1.0)],
+            &[(0, 1.0)],
+            &[(0, 1.0)],
+            &[(0, 1.0), (4, 1.0), (5, 1.0)],
+            &[(0, 1.0), (3, 0.7555), (4, 0.2444), (5, 1.0), (6, 0.66)],
+            &[
+                (0, 1.0),
+                (3, 0.7555),
+                (4, 0.2444),
+                (5, 0.66),
+                (6, 0.4356),
+                (7, 0.66),
+            ],
+        ];
+        for (weights, expected) in model.delta_weights.iter().zip(expected_weights) {
+            assert_eq!(weights.len(), expected.len());
+            for ((i, weight), (expected_i, expected_weight)) in weights.iter().zip(expected) {
+                assert_eq!(i, expected_i);
+                assert_close(*weight, *expected_weight);
+            }
+        }
+    }
+
+    #[test]
+    fn scalar_deltas() {
+        let locations = vec![
+            loc(&[]),
+            loc(&[(WGHT, 1.0)]),
+            loc(&[(WDTH, 1.0)]),
+            loc(&[(WGHT, 1.0), (WDTH, 1.0)]),
+        ];
+        let model = VariationModel::new(locations, 2).unwrap();
+        let deltas = model
+            .scalar_deltas(&[Some(100.0), Some(200.0), Some(150.0), Some(300.0)])
+            .unwrap();
+        let deltas = deltas.iter().map(|(_, delta)| *delta).collect::<Vec<_>>();
+        assert_eq!(deltas, [100, 50, 50]);
+        assert_eq!(model.regions().len(), 3);
+
+        // without the corner master, the corner is interpolated
+        let deltas = model
+            .scalar_deltas(&[Some(100.0), Some(200.0), Some(150.0), None])
+            .unwrap();
+        assert_eq!(deltas.len(), 2);
+        let peaks = deltas
+            .iter()
+            .map(|(region, delta)| {
+                let peaks = region
+                    .region_axes
+                    .iter()
+                    .map(|axis| axis.peak_coord.to_f32())
+                    .collect::<Vec<_>>();
+                (peaks, *delta)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(peaks, [(vec![1.0, 0.0], 100), (vec![0.0, 1.0], 50)]);
+    }
+
+    #[test]
+    fn intermediate_masters() {
+        let locations = vec![loc(&[]), loc(&[(WGHT, 1.0)]), loc(&[(WGHT, 0.5)])];
+        let model = VariationModel::new(locations, 1).unwrap();
+        let deltas = model
+            .scalar_deltas(&[Some(100.0), Some(200.0), Some(160.0)])
+            .unwrap();
+        // the intermediate master is sorted first, so the other master's
+        // support starts at the intermediate location
+        let deltas = deltas.iter().map(|(_, delta)| *delta).collect::<Vec<_>>();
+        assert_eq!(deltas, [60, 100]);
+
+        let glyph_deltas = model
+            .glyph_deltas(&[
+                Some(vec![Point::new(0.0, 0.0)]),
+                Some(vec![Point::new(100.0, 0.0)]),
+                Some(vec![Point::new(0.0, 0.0)]),
+            ])
+            .unwrap();
+        // the intermediate master has no deltas, so it is omitted
+        assert_eq!(glyph_deltas.len(), 1);
+
+        let tuple = |value: f32| Tuple::new(vec![F2Dot14::from_f32(value)]);
+        let (peak, intermediate) = model.tuples(&model.supports[1]);
+        // gvar would otherwise end this region at the peak
+        assert_eq!(peak, tuple(0.5));
+        assert_eq!(intermediate, Some((tuple(0.0), tuple(1.0))));
+        let (peak, intermediate) = model.tuples(&model.supports[2]);
+        assert_eq!(peak, tuple(1.0));
+        assert_eq!(intermediate, Some((tuple(0.5), tuple(1.0))));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            VariationModel::new(vec![loc(&[(WGHT, 1.0)])], 1).unwrap_err(),
+            VariationModelError::MissingDefault
+        );
+        assert_eq!(
+            VariationModel::new(vec![loc(&[]), loc(&[(WDTH, 1.0)])], 1).unwrap_err(),
+            VariationModelError::AxisOutOfRange(WDTH)
+        );
+        assert!(matches!(
+            VariationModel::new(vec![loc(&[]), loc(&[(WGHT, 0.0)])], 1),
+            Err(VariationModelError::DuplicateLocation(_))
+        ));
+        let model = VariationModel::new(vec![loc(&[]), loc(&[(WGHT, 1.0)])], 1).unwrap();
+        assert_eq!(
+            model.scalar_deltas(&[Some(1.0)]).unwrap_err(),
+            VariationModelError::WrongValueCount {
+                expected: 2,
+                actual: 1
+            }
+        );
+        // the default master can't be sparse
+        assert_eq!(
+            model.scalar_deltas(&[None, Some(1.0)]).unwrap_err(),
+            VariationModelError::MissingDefault
+        );
+        assert_eq!(
+            model.scalar_deltas(&[Some(0.0), Some(3e9)]).unwrap_err(),
+            VariationModelError::Overflow
+        );
+        let points = |x| Some(vec![Point::new(x, 0.0)]);
+        assert_eq!(
+            model
+                .glyph_deltas(&[points(-20000.0), points(20000.0)])
+                .unwrap_err(),
+            VariationModelError::Overflow
+        );
+        assert!(model
+            .glyph_deltas(&[points(-10000.0), points(10000.0)])
+            .is_ok());
+    }
+}
@@ -0,0 +1,111 @@
+//! Encoding fonts as [WOFF] and [WOFF2].
+//!
+//! Both formats wrap a single sfnt font. For WOFF each table is compressed
+//! individually with zlib; for WOFF2 the tables are concatenated into one
+//! Brotli stream, after applying the `glyf`/`loca` and `hmtx` transforms
+//! where possible.
+//!
+//! [WOFF]: https://www.w3.org/TR/WOFF/
+//! [WOFF2]: https://www.w3.org/TR/WOFF2/
+
+mod woff2;
+
+use read_fonts::{FontRef, TableProvider};
+use types::Tag;
+
+pub use woff2::encode_woff2;
+
+const WOFF_SIGNATURE: Tag = Tag::new(b"wOFF");
+const WOFF_HEADER_LEN: usize = 44;
+const WOFF_TABLE_ENTRY_LEN: usize = 20;
+const SFNT_HEADER_LEN: usize = 12;
+const SFNT_TABLE_RECORD_LEN: usize = 16;
+
+/// Encodes a font as WOFF.
+///
+/// Each table is zlib compressed, unless that would not make it smaller,
+/// in which case it is stored uncompressed. The WOFF version is set to the
+/// font revision from the `head` table, if present.
+pub fn encode_woff(font: &FontRef) -> Vec<u8> {
+    let records = font.table_directory.table_records();
+    let num_tables = records.len();
+    let mut directory = Vec::with_capacity(num_tables * WOFF_TABLE_ENTRY_LEN);
+    let mut table_data = Vec::new();
+    let mut total_sfnt_size = SFNT_HEADER_LEN + num_tables * SFNT_TABLE_RECORD_LEN;
+    let data_start = WOFF_HEADER_LEN + num_tables * WOFF_TABLE_ENTRY_LEN;
+    for record in records {
+        let tag = record.tag();
+        let data = font
+            .table_data(tag)
+            .map(|data| data.as_bytes())
+            .unwrap_or_default();
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 9);
+        let stored = if compressed.len() < data.len() {
+            &compressed
+        } else {
+            data
+        };
+        directory.extend_from_slice(&tag.to_be_bytes());
+        directory.extend_from_slice(&((data_start + table_data.len()) as u32).to_be_bytes());
+        directory.extend_from_slice(&(stored.len() as u32).to_be_bytes());
+        directory.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        directory.extend_from_slice(&record.checksum().to_be_bytes());
+        table_data.extend_from_slice(stored);
+        table_data.resize(round4(table_data.len()), 0);
+        total_sfnt_size += round4(data.len());
+    }
+    let revision = font
+        .head()
+        .map(|head| head.font_revision().to_bits() as u32)
+        .unwrap_or_default();
+    let mut woff = Vec::with_capacity(data_start + table_data.len());
+    woff.extend_from_slice(&WOFF_SIGNATURE.to_be_bytes());
+    woff.extend_from_slice(&font.table_directory.sfnt_version().to_be_bytes());
+    woff.extend_from_slice(&((data_start + table_data.len()) as u32).to_be_bytes());
+    woff.extend_from_slice(&(num_tables as u16).to_be_bytes());
+    // reserved
+    woff.extend_from_slice(&0u16.to_be_bytes());
+    woff.extend_from_slice(&(total_sfnt_size as u32).to_be_bytes());
+    // major and minor version
+    woff.extend_from_slice(&revision.to_be_bytes());
+    // no metadata or private data
+    woff.extend_from_slice(&[0u8; 20]);
+    woff.extend_from_slice(&directory);
+    woff.extend_from_slice(&table_data);
+    woff
+}
+
+fn round4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn woff_round_trip() {
+        let sfnt = font_test_data::NOTO_SERIF_DISPLAY_TRIMMED;
+        let font = FontRef::new(sfnt).unwrap();
+        let woff = encode_woff(&font);
+        assert!(woff.len() < sfnt.len());
+        let decoded = read_fonts::woff::decode(&woff).unwrap();
+        let decoded = FontRef::new(&decoded).unwrap();
+        assert_eq!(
+            decoded.table_directory.num_tables(),
+            font.table_directory.num_tables()
+        );
+        for record in font.table_directory.table_records() {
+            let tag = record.tag();
+            let expected = font.table_data(tag).unwrap();
+            let actual = decoded.table_data(tag).unwrap();
+            if tag == Tag::new(b"head") {
+                // the decoder recomputes checkSumAdjustment
+                assert_eq!(actual.as_bytes()[..8], expected.as_bytes()[..8]);
+                assert_eq!(actual.as_bytes()[12..], expected.as_bytes()[12..]);
+            } else {
+                assert_eq!(actual.as_bytes(), expected.as_bytes(), "{tag}");
+            }
+        }
+    }
+}
@@ -0,0 +1,710 @@
+//! WOFF2 encoding.
+
+use read_fonts::{
+    tables::glyf::{CompositeGlyph, CompositeGlyphFlags, Glyph, SimpleGlyph},
+    FontRef, TableProvider,
+};
+use types::{GlyphId, Tag};
+
+use super::{round4, SFNT_HEADER_LEN, SFNT_TABLE_RECORD_LEN};
+
+const WOFF2_SIGNATURE: Tag = Tag::new(b"wOF2");
+const WOFF2_HEADER_LEN: usize = 48;
+const GLYF: Tag = Tag::new(b"glyf");
+const LOCA: Tag = Tag::new(b"loca");
+const HMTX: Tag = Tag::new(b"hmtx");
+
+/// Tags that can be encoded by index in the table directory.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Encodes a font as WOFF2.
+///
+/// The `glyf`/`loca` transform is applied to TrueType fonts, and the `hmtx`
+/// transform is applied when the left side bearings can be reconstructed
+/// from the glyph bounding boxes. If the glyph data cannot be transformed
+/// (for instance because it is malformed) it is stored as is.
+pub fn encode_woff2(font: &FontRef) -> Vec<u8> {
+    let mut tables = font
+        .table_directory
+        .table_records()
+        .iter()
+        .map(|record| {
+            let data = font
+                .table_data(record.tag())
+                .map(|data| data.as_bytes())
+                .unwrap_or_default();
+            (record.tag(), data)
+        })
+        .collect::<Vec<_>>();
+    // loca must immediately follow glyf
+    if let Some(loca_ix) = tables.iter().position(|(tag, _)| *tag == LOCA) {
+        let loca = tables.remove(loca_ix);
+        let glyf_ix = tables.iter().position(|(tag, _)| *tag == GLYF);
+        let ix = glyf_ix.map(|ix| ix + 1).unwrap_or(loca_ix);
+        tables.insert(ix, loca);
+    }
+    let transformed_glyf = match transform_glyf(font) {
+        Ok(glyf) => glyf,
+        Err(e) => {
+            log::warn!("not applying glyf transform: {e}");
+            None
+        }
+    };
+    let transformed_hmtx = transformed_glyf
+        .as_ref()
+        .and_then(|glyf| transform_hmtx(font, &glyf.x_mins));
+
+    let mut directory = Vec::new();
+    let mut stream = Vec::new();
+    let mut total_sfnt_size = SFNT_HEADER_LEN + tables.len() * SFNT_TABLE_RECORD_LEN;
+    for (tag, data) in &tables {
+        let transformed = match *tag {
+            GLYF => transformed_glyf.as_ref().map(|glyf| glyf.data.as_slice()),
+            LOCA => transformed_glyf.as_ref().map(|_| [].as_slice()),
+            HMTX => transformed_hmtx.as_deref(),
+            _ => None,
+        };
+        // the null transform is version 3 for glyf and loca, 0 otherwise
+        let version = match (*tag, transformed.is_some()) {
+            (GLYF | LOCA, true) => 0,
+            (GLYF | LOCA, false) => 3,
+            (_, true) => 1,
+            (_, false) => 0,
+        };
+        match KNOWN_TAGS.iter().position(|known| Tag::new(known) == *tag) {
+            Some(ix) => directory.push(ix as u8 | version << 6),
+            None => {
+                directory.push(63 | version << 6);
+                directory.extend_from_slice(&tag.to_be_bytes());
+            }
+        }
+        push_base128(&mut directory, data.len() as u32);
+        if let Some(transformed) = transformed {
+            push_base128(&mut directory, transformed.len() as u32);
+        }
+        stream.extend_from_slice(transformed.unwrap_or(data));
+        total_sfnt_size += round4(data.len());
+    }
+    let compressed = compress(&stream);
+    let length = round4(WOFF2_HEADER_LEN + directory.len() + compressed.len());
+    let revision = font
+        .head()
+        .map(|head| head.font_revision().to_bits() as u32)
+        .unwrap_or_default();
+    let mut woff2 = Vec::with_capacity(length);
+    woff2.extend_from_slice(&WOFF2_SIGNATURE.to_be_bytes());
+    woff2.extend_from_slice(&font.table_directory.sfnt_version().to_be_bytes());
+    woff2.extend_from_slice(&(length as u32).to_be_bytes());
+    woff2.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    // reserved
+    woff2.extend_from_slice(&0u16.to_be_bytes());
+    woff2.extend_from_slice(&(total_sfnt_size as u32).to_be_bytes());
+    woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    // major and minor version
+    woff2.extend_from_slice(&revision.to_be_bytes());
+    // no metadata or private data
+    woff2.extend_from_slice(&[0u8; 20]);
+    woff2.extend_from_slice(&directory);
+    woff2.extend_from_slice(&compressed);
+    woff2.resize(length, 0);
+    woff2
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let params = brotli::enc::BrotliEncoderParams {
+        mode: brotli::enc::backward_references::BrotliEncoderMode::BROTLI_MODE_FONT,
+        quality: 11,
+        lgwin: 22,
+        size_hint: data.len(),
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    // writing to a vec can't fail
+    brotli::BrotliCompress(&mut &data[..], &mut out, &params).unwrap();
+    out
+}
+
+fn push_base128(out: &mut Vec<u8>, value: u32) {
+    let mut len = 1;
+    while len < 5 && value >> (7 * len) != 0 {
+        len += 1;
+    }
+    for i in (0..len).rev() {
+        let byte = ((value >> (7 * i)) & 0x7F) as u8;
+        out.push(if i > 0 { byte | 0x80 } else { byte });
+    }
+}
+
+fn push_255_u16(out: &mut Vec<u8>, value: u16) {
+    const WORD_CODE: u8 = 253;
+    const ONE_MORE_BYTE_CODE2: u8 = 254;
+    const ONE_MORE_BYTE_CODE1: u8 = 255;
+    const LOWEST_U_CODE: u16 = 253;
+    match value {
+        0..=252 => out.push(value as u8),
+        253..=505 => out.extend([ONE_MORE_BYTE_CODE1, (value - LOWEST_U_CODE) as u8]),
+        506..=761 => out.extend([ONE_MORE_BYTE_CODE2, (value - LOWEST_U_CODE * 2) as u8]),
+        _ => {
+            out.push(WORD_CODE);
+            out.extend(value.to_be_bytes());
+        }
+    }
+}
+
+/// The transformed glyf table, along with the xMin of each glyph.
+struct TransformedGlyf {
+    data: Vec<u8>,
+    x_mins: Vec<i16>,
+}
+
+/// The streams of the transformed glyf table.
+#[derive(Default)]
+struct GlyfStreams {
+    n_contour: Vec<u8>,
+    n_points: Vec<u8>,
+    flags: Vec<u8>,
+    glyph: Vec<u8>,
+    composite: Vec<u8>,
+    bbox_bitmap: Vec<u8>,
+    bbox: Vec<u8>,
+    instructions: Vec<u8>,
+    overlap_bitmap: Vec<u8>,
+}
+
+/// Applies the glyf transform, returning `None` if the font has no
+/// glyf table.
+fn transform_glyf(font: &FontRef) -> Result<Option<TransformedGlyf>, read_fonts::ReadError> {
+    let (Ok(glyf), Ok(head), Ok(maxp)) = (font.glyf(), font.head(), font.maxp()) else {
+        return Ok(None);
+    };
+    let loca = font.loca(None)?;
+    let num_glyphs = maxp.num_glyphs();
+    let index_format = head.index_to_loc_format() as u16;
+    // the decoder rebuilds loca from scratch, so the table must not have
+    // any extra data
+    let loca_len = (num_glyphs as usize + 1) * if index_format == 0 { 2 } else { 4 };
+    let orig_loca_len = font.table_data(LOCA).map(|data| data.len());
+    if orig_loca_len != Some(loca_len) {
+        return Err(read_fonts::ReadError::MalformedData(
+            "loca length does not match glyph count",
+        ));
+    }
+    // reconstructed glyphs are padded to four bytes, which must still
+    // fit in a short loca
+    if index_format == 0 && glyf.offset_data().len() + 3 * num_glyphs as usize > 0x1FFFF {
+        return Ok(None);
+    }
+    let bitmap_len = 4 * (num_glyphs as usize).div_ceil(32);
+    let mut streams = GlyfStreams {
+        bbox_bitmap: vec![0; bitmap_len],
+        overlap_bitmap: vec![0; (num_glyphs as usize).div_ceil(8)],
+        ..Default::default()
+    };
+    let mut has_overlap = false;
+    let mut x_mins = Vec::with_capacity(num_glyphs as usize);
+    for gid in 0..num_glyphs {
+        let glyph = loca.get_glyf(GlyphId::new(gid), &glyf)?;
+        let ix = gid as usize;
+        match glyph {
+            None => {
+                streams.n_contour.extend(0i16.to_be_bytes());
+                x_mins.push(0);
+            }
+            Some(Glyph::Simple(glyph)) => {
+                let x_min = streams.push_simple(&glyph, ix)?;
+                if glyph.has_overlapping_contours() {
+                    streams.overlap_bitmap[ix / 8] |= 0x80 >> (ix % 8);
+                    has_overlap = true;
+                }
+                x_mins.push(x_min);
+            }
+            Some(Glyph::Composite(glyph)) => {
+                streams.push_composite(&glyph, ix)?;
+                x_mins.push(glyph.x_min());
+            }
+        }
+    }
+    let option_flags = has_overlap as u16;
+    let bbox_len = streams.bbox_bitmap.len() + streams.bbox.len();
+    let mut data = Vec::new();
+    for value in [0, option_flags, num_glyphs, index_format] {
+        data.extend(value.to_be_bytes());
+    }
+    for len in [
+        streams.n_contour.len(),
+        streams.n_points.len(),
+        streams.flags.len(),
+        streams.glyph.len(),
+        streams.composite.len(),
+        bbox_len,
+        streams.instructions.len(),
+    ] {
+        data.extend((len as u32).to_be_bytes());
+    }
+    for stream in [
+        &streams.n_contour,
+        &streams.n_points,
+        &streams.flags,
+        &streams.glyph,
+        &streams.composite,
+        &streams.bbox_bitmap,
+        &streams.bbox,
+        &streams.instructions,
+    ] {
+        data.extend_from_slice(stream);
+    }
+    if has_overlap {
+        data.extend_from_slice(&streams.overlap_bitmap);
+    }
+    Ok(Some(TransformedGlyf { data, x_mins }))
+}
+
+impl GlyfStreams {
+    /// Writes a simple glyph, returning its xMin.
+    fn push_simple(
+        &mut self,
+        glyph: &SimpleGlyph,
+        ix: usize,
+    ) -> Result<i16, read_fonts::ReadError> {
+        let end_pts = glyph.end_pts_of_contours();
+        self.n_contour.extend((end_pts.len() as i16).to_be_bytes());
+        let mut last_end = -1i32;
+        for end_pt in end_pts {
+            let end_pt = end_pt.get() as i32;
+            if end_pt <= last_end {
+                return Err(read_fonts::ReadError::MalformedData(
+                    "contour end points are not increasing",
+                ));
+            }
+            push_255_u16(&mut self.n_points, (end_pt - last_end) as u16);
+            last_end = end_pt;
+        }
+        let (mut last_x, mut last_y) = (0i32, 0i32);
+        let mut bbox = [i16::MAX, i16::MAX, i16::MIN, i16::MIN];
+        let mut num_points = 0;
+        for point in glyph.points() {
+            self.push_triplet(
+                point.on_curve,
+                point.x as i32 - last_x,
+                point.y as i32 - last_y,
+            );
+            (last_x, last_y) = (point.x as i32, point.y as i32);
+            bbox = [
+                bbox[0].min(point.x),
+                bbox[1].min(point.y),
+                bbox[2].max(point.x),
+                bbox[3].max(point.y),
+            ];
+            num_points += 1;
+        }
+        if num_points != glyph.num_points() {
+            return Err(read_fonts::ReadError::MalformedData(
+                "simple glyph has missing points",
+            ));
+        }
+        let instructions = glyph.instructions();
+        push_255_u16(&mut self.glyph, instructions.len() as u16);
+        self.instructions.extend_from_slice(instructions);
+        let stored = [glyph.x_min(), glyph.y_min(), glyph.x_max(), glyph.y_max()];
+        if stored != bbox {
+            self.push_bbox(ix, stored);
+        }
+        Ok(stored[0])
+    }
+
+    fn push_composite(
+        &mut self,
+        glyph: &CompositeGlyph,
+        ix: usize,
+    ) -> Result<(), read_fonts::ReadError> {
+        self.n_contour.extend((-1i16).to_be_bytes());
+        self.push_bbox(
+            ix,
+            [glyph.x_min(), glyph.y_min(), glyph.x_max(), glyph.y_max()],
+        );
+        let data = glyph.component_data();
+        let mut pos = 0;
+        let mut have_instructions = false;
+        loop {
+            let flags = data
+                .get(pos..pos + 2)
+                .map(|bytes| {
+                    CompositeGlyphFlags::from_bits_truncate(u16::from_be_bytes([
+                        bytes[0], bytes[1],
+                    ]))
+                })
+                .ok_or(read_fonts::ReadError::OutOfBounds)?;
+            // flags and glyph id
+            let mut len = 4;
+            len += if flags.contains(CompositeGlyphFlags::ARG_1_AND_2_ARE_WORDS) {
+                4
+            } else {
+                2
+            };
+            if flags.contains(CompositeGlyphFlags::WE_HAVE_A_SCALE) {
+                len += 2;
+            } else if flags.contains(CompositeGlyphFlags::WE_HAVE_AN_X_AND_Y_SCALE) {
+                len += 4;
+            } else if flags.contains(CompositeGlyphFlags::WE_HAVE_A_TWO_BY_TWO) {
+                len += 8;
+            }
+            pos += len;
+            have_instructions |= flags.contains(CompositeGlyphFlags::WE_HAVE_INSTRUCTIONS);
+            if !flags.contains(CompositeGlyphFlags::MORE_COMPONENTS) {
+                break;
+            }
+        }
+        let components = data.get(..pos).ok_or(read_fonts::ReadError::OutOfBounds)?;
+        self.composite.extend_from_slice(components);
+        if have_instructions {
+            let instructions = glyph.instructions().unwrap_or_default();
+            push_255_u16(&mut self.glyph, instructions.len() as u16);
+            self.instructions.extend_from_slice(instructions);
+        }
+        Ok(())
+    }
+
+    fn push_bbox(&mut self, ix: usize, bbox: [i16; 4]) {
+        self.bbox_bitmap[ix / 8] |= 0x80 >> (ix % 8);
+        for value in bbox {
+            self.bbox.extend(value.to_be_bytes());
+        }
+    }
+
+    /// Writes a point delta with the triplet encoding from section 5.2 of
+    /// the spec.
+    fn push_triplet(&mut self, on_curve: bool, x: i32, y: i32) {
+        let (abs_x, abs_y) = (x.unsigned_abs(), y.unsigned_abs());
+        let on_curve_bit = if on_curve { 0 } else { 128 };
+        let x_sign_bit = (x >= 0) as u32;
+        let y_sign_bit = (y >= 0) as u32;
+        let xy_sign_bits = x_sign_bit + 2 * y_sign_bit;
+        let flag = if x == 0 && abs_y < 1280 {
+            self.glyph.push(abs_y as u8);
+            on_curve_bit + ((abs_y & 0xF00) >> 7) + y_sign_bit
+        } else if y == 0 && abs_x < 1280 {
+            self.glyph.push(abs_x as u8);
+            on_curve_bit + 10 + ((abs_x & 0xF00) >> 7) + x_sign_bit
+        } else if abs_x < 65 && abs_y < 65 {
+            self.glyph
+                .push((((abs_x - 1) & 0xF) << 4 | ((abs_y - 1) & 0xF)) as u8);
+            on_curve_bit + 20 + ((abs_x - 1) & 0x30) + (((abs_y - 1) & 0x30) >> 2) + xy_sign_bits
+        } else if abs_x < 769 && abs_y < 769 {
+            self.glyph.push((abs_x - 1) as u8);
+            self.glyph.push((abs_y - 1) as u8);
+            on_curve_bit
+                + 84
+                + 12 * (((abs_x - 1) & 0x300) >> 8)
+                + (((abs_y - 1) & 0x300) >> 6)
+                + xy_sign_bits
+        } else if abs_x < 4096 && abs_y < 4096 {
+            self.glyph.push((abs_x >> 4) as u8);
+            self.glyph.push(((abs_x & 0xF) << 4 | abs_y >> 8) as u8);
+            self.glyph.push(abs_y as u8);
+            on_curve_bit + 120 + xy_sign_bits
+        } else {
+            self.glyph.extend((abs_x as u16).to_be_bytes());
+            self.glyph.extend((abs_y as u16).to_be_bytes());
+            on_curve_bit + 124 + xy_sign_bits
+        };
+        self.flags.push(flag as u8);
+    }
+}
+
+/// Applies the hmtx transform, if any of the side bearings match the
+/// glyph xMin values.
+fn transform_hmtx(font: &FontRef, x_mins: &[i16]) -> Option<Vec<u8>> {
+    let hmtx = font.hmtx().ok()?;
+    let num_h_metrics = font.hhea().ok()?.number_of_long_metrics() as usize;
+    let num_glyphs = x_mins.len();
+    let h_metrics = hmtx.h_metrics();
+    let lsbs = hmtx.left_side_bearings();
+    // the decoder reconstructs the table from scratch, so there can't be
+    // any extra data
+    let expected_len = num_h_metrics * 4 + num_glyphs.checked_sub(num_h_metrics)? * 2;
+    if num_h_metrics == 0
+        || h_metrics.len() != num_h_metrics
+        || font.table_data(HMTX)?.len() != expected_len
+    {
+        return None;
+    }
+    let proportional_match = h_metrics
+        .iter()
+        .zip(x_mins)
+        .all(|(metric, x_min)| metric.side_bearing() == *x_min);
+    let monospaced_match = lsbs
+        .iter()
+        .zip(&x_mins[num_h_metrics..])
+        .all(|(lsb, x_min)| lsb.get() == *x_min);
+    if !(proportional_match || monospaced_match) {
+        return None;
+    }
+    let flags = proportional_match as u8 | (monospaced_match as u8) << 1;
+    let mut data = vec![flags];
+    for metric in h_metrics {
+        data.extend(metric.advance().to_be_bytes());
+    }
+    if !proportional_match {
+        for metric in h_metrics {
+            data.extend(metric.side_bearing().to_be_bytes());
+        }
+    }
+    if !monospaced_match {
+        for lsb in lsbs {
+            data.extend(lsb.get().to_be_bytes());
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::BezPath;
+
+    use super::*;
+    use crate::{
+        from_obj::ToOwnedTable,
+        tables::{
+            glyf::{
+                Anchor, Bbox, Component, ComponentFlags, CompositeGlyph as OwnedComposite,
+                GlyfLocaBuilder, SimpleGlyph as OwnedSimple, Transform,
+            },
+            head::Head,
+        },
+        FontBuilder,
+    };
+
+    /// Checks that two fonts have equivalent glyphs.
+    ///
+    /// The decoder writes simple glyphs in a canonical encoding, so the glyf
+    /// table is not necessarily byte for byte identical.
+    fn assert_same_glyphs(actual: &FontRef, expected: &FontRef) {
+        fn glyphs<'a>(font: &FontRef<'a>) -> Vec<Option<Glyph<'a>>> {
+            let glyf = font.glyf().unwrap();
+            let loca = font.loca(None).unwrap();
+            (0..font.maxp().unwrap().num_glyphs())
+                .map(|gid| loca.get_glyf(GlyphId::new(gid), &glyf).unwrap())
+                .collect()
+        }
+        let (actual, expected) = (glyphs(actual), glyphs(expected));
+        assert_eq!(actual.len(), expected.len());
+        for (gid, (actual, expected)) in actual.iter().zip(&expected).enumerate() {
+            match (actual, expected) {
+                (None, None) => (),
+                (Some(Glyph::Simple(actual)), Some(Glyph::Simple(expected))) => {
+                    assert_eq!(
+                        actual.points().collect::<Vec<_>>(),
+                        expected.points().collect::<Vec<_>>(),
+                        "{gid}"
+                    );
+                    assert_eq!(actual.end_pts_of_contours(), expected.end_pts_of_contours());
+                    assert_eq!(actual.instructions(), expected.instructions());
+                    assert_eq!(
+                        actual.has_overlapping_contours(),
+                        expected.has_overlapping_contours()
+                    );
+                    assert_eq!(
+                        [
+                            actual.x_min(),
+                            actual.y_min(),
+                            actual.x_max(),
+                            actual.y_max()
+                        ],
+                        [
+                            expected.x_min(),
+                            expected.y_min(),
+                            expected.x_max(),
+                            expected.y_max()
+                        ],
+                    );
+                }
+                (Some(Glyph::Composite(actual)), Some(Glyph::Composite(expected))) => {
+                    assert_eq!(
+                        actual.components().collect::<Vec<_>>(),
+                        expected.components().collect::<Vec<_>>(),
+                        "{gid}"
+                    );
+                    assert_eq!(actual.instructions(), expected.instructions());
+                    assert_eq!(
+                        [
+                            actual.x_min(),
+                            actual.y_min(),
+                            actual.x_max(),
+                            actual.y_max()
+                        ],
+                        [
+                            expected.x_min(),
+                            expected.y_min(),
+                            expected.x_max(),
+                            expected.y_max()
+                        ],
+                    );
+                }
+                _ => panic!("glyph {gid} differs"),
+            }
+        }
+    }
+
+    /// Encodes and decodes the font, checking that all tables survive.
+    ///
+    /// Returns the encoded font.
+    fn assert_round_trip(sfnt: &[u8]) -> Vec<u8> {
+        let font = FontRef::new(sfnt).unwrap();
+        let woff2 = encode_woff2(&font);
+        let decoded = read_fonts::woff::decode(&woff2).unwrap();
+        let decoded_font = FontRef::new(&decoded).unwrap();
+        let tags = |font: &FontRef| {
+            font.table_directory
+                .table_records()
+                .iter()
+                .map(|record| record.tag())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tags(&decoded_font), tags(&font));
+        for tag in tags(&font) {
+            let expected = font.table_data(tag).unwrap().as_bytes();
+            let actual = decoded_font.table_data(tag).unwrap().as_bytes();
+            match tag {
+                GLYF | LOCA => (),
+                // the decoder recomputes checkSumAdjustment
+                _ if tag == Tag::new(b"head") => {
+                    assert_eq!(actual[..8], expected[..8]);
+                    assert_eq!(actual[12..], expected[12..]);
+                }
+                _ => assert_eq!(actual, expected, "{tag}"),
+            }
+        }
+        if font.glyf().is_ok() {
+            assert_same_glyphs(&decoded_font, &font);
+        }
+        woff2
+    }
+
+    #[test]
+    fn round_trip() {
+        for sfnt in [
+            font_test_data::VAZIRMATN_VAR,
+            font_test_data::SIMPLE_GLYF,
+            font_test_data::NOTO_SERIF_DISPLAY_TRIMMED,
+            font_test_data::COLRV0V1,
+            font_test_data::EMBEDDED_BITMAPS,
+            font_test_data::CANTARELL_VF_TRIMMED,
+        ] {
+            let woff2 = assert_round_trip(sfnt);
+            assert!(woff2.len() < sfnt.len());
+        }
+    }
+
+    #[test]
+    fn applies_transforms() {
+        let font = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let glyf = transform_glyf(&font).unwrap().unwrap();
+        assert!(transform_hmtx(&font, &glyf.x_mins).is_some());
+        let woff2 = encode_woff2(&font);
+        let decoded = read_fonts::woff::decode(&woff2).unwrap();
+        // glyf is rebuilt by the decoder
+        let decoded = FontRef::new(&decoded).unwrap();
+        assert_ne!(
+            decoded.table_data(GLYF).unwrap().as_bytes(),
+            font.table_data(GLYF).unwrap().as_bytes()
+        );
+    }
+
+    #[test]
+    fn round_trip_large_coordinates() {
+        // replace the glyphs of a font with ones that exercise every
+        // point encoding
+        let base = FontRef::new(font_test_data::VAZIRMATN_VAR).unwrap();
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        for (x, y) in [
+            (0.0, -1279.0),
+            (1279.0, -1279.0),
+            (1215.0, -1215.0),
+            (1983.0, -1983.0),
+            (-2112.0, 2112.0),
+            (20000.0, -12000.0),
+        ] {
+            path.line_to((x, y));
+        }
+        path.quad_to((-12000.0, 12000.0), (-12.0, 7.0));
+        path.close_path();
+        let simple = OwnedSimple::from_bezpath(&path).unwrap();
+        let composite = OwnedComposite::new(
+            Component::new(
+                GlyphId::new(1),
+                Anchor::Offset { x: 300, y: -20 },
+                Transform::default(),
+                ComponentFlags::default(),
+            ),
+            Bbox {
+                x_min: 1,
+                y_min: 2,
+                x_max: 3,
+                y_max: 4,
+            },
+        );
+        let mut builder = GlyfLocaBuilder::new();
+        builder
+            .add_glyph(&simple)
+            .unwrap()
+            .add_glyph(&simple)
+            .unwrap()
+            .add_glyph(&composite)
+            .unwrap()
+            .add_glyph(&simple)
+            .unwrap();
+        let (glyf, loca, loca_format) = builder.build();
+        let mut head: Head = base.head().unwrap().to_owned_table();
+        head.index_to_loc_format = loca_format as i16;
+        let mut builder = FontBuilder::new();
+        builder
+            .add_table(&glyf)
+            .unwrap()
+            .add_table(&loca)
+            .unwrap()
+            .add_table(&head)
+            .unwrap()
+            .copy_missing_tables(base);
+        let woff2 = assert_round_trip(&builder.build());
+        assert_eq!(woff2, builder.build_woff2());
+    }
+
+    #[test]
+    fn base128() {
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (63, &[0x3F]),
+            (128, &[0x81, 0x00]),
+            (u32::MAX, &[0x8F, 0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            let mut out = Vec::new();
+            push_base128(&mut out, value);
+            assert_eq!(out, expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn uint_255() {
+        for (value, expected) in [
+            (252, &[252][..]),
+            (253, &[255, 0]),
+            (505, &[255, 252]),
+            (506, &[254, 0]),
+            (761, &[254, 255]),
+            (762, &[253, 0x02, 0xFA]),
+        ] {
+            let mut out = Vec::new();
+            push_255_u16(&mut out, value);
+            assert_eq!(out, expected, "{value}");
+        }
+    }
+}